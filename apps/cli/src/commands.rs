@@ -1,13 +1,20 @@
+use crate::starters::Starter;
 use axum::Router;
 use axum::body::Body;
 use axum::http::{Request, Response, StatusCode};
 use axum::middleware::{self, Next};
+use bamboo_ssg::build_hooks::{HookContext, HookStage};
+use bamboo_ssg::images::ImageProgress;
+use bamboo_ssg::remote::RemoteClient;
 use bamboo_ssg::{
-    BuildState, SiteBuilder, ThemeEngine, classify_changes, clean_output_dir,
-    compute_content_hashes, expand_targets, load_cache, save_cache, validate_internal_links,
+    BuildState, ExcludedPaths, GenerateConfig, RenderReport, Site, SiteBuilder, ThemeEngine,
+    changed_paths, check_links, classify_changes, clean_output_dir, compute_content_hashes,
+    expand_targets, export, lint, load_cache, migrate, save_cache, template_test,
+    validate_internal_links, with_jobs,
 };
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use std::fs;
+use std::io::{IsTerminal, Write};
 use std::net::SocketAddr;
 use std::path::Path;
 use std::sync::mpsc::{RecvTimeoutError, channel};
@@ -18,6 +25,79 @@ use tower_http::services::ServeDir;
 
 const DEBOUNCE_DURATION: Duration = Duration::from_millis(300);
 
+/// Errors from a build run on the worker pool, which must be `Send`.
+type BuildError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Config environment, unpublished content, and concurrency chosen on the
+/// command line.
+#[derive(Clone)]
+pub struct BuildOptions {
+    /// `[environments.<name>]` table merged over `bamboo.toml`.
+    pub environment: String,
+    /// `--drafts`: build drafts even if the config doesn't enable them.
+    pub drafts: bool,
+    /// `--future`: build future-dated posts and notes even if the config
+    /// doesn't enable them.
+    pub future: bool,
+    /// `--jobs`: worker threads for the build, overriding `jobs` in the
+    /// config.
+    pub jobs: Option<usize>,
+    /// `--skip`: site-wide artifacts to leave out of this build, on top of
+    /// any the `[generate]` config already turns off.
+    pub skip: Vec<Artifact>,
+}
+
+/// A site-wide artifact `--skip` can leave out of a build.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Artifact {
+    /// Every `rss.xml` feed.
+    Rss,
+    /// Every `atom.xml` feed.
+    Atom,
+    /// `sitemap.xml`.
+    Sitemap,
+    /// `search-index.json` and the search page.
+    Search,
+    /// Redirect pages for `redirect_from`.
+    Redirects,
+}
+
+impl BuildOptions {
+    fn site_builder(&self, input_dir: &Path) -> SiteBuilder {
+        SiteBuilder::new(input_dir)
+            .environment(&self.environment)
+            .include_drafts(self.drafts)
+            .include_future(self.future)
+    }
+
+    /// Turns off the `--skip` artifacts in `generate`.
+    fn apply_skips(&self, generate: &mut GenerateConfig) {
+        for artifact in &self.skip {
+            match artifact {
+                Artifact::Rss => generate.rss = false,
+                Artifact::Atom => generate.atom = false,
+                Artifact::Sitemap => generate.sitemap = false,
+                Artifact::Search => generate.search = false,
+                Artifact::Redirects => generate.redirects = false,
+            }
+        }
+    }
+
+    /// Runs `build` with `--jobs` worker threads, falling back to `jobs` in
+    /// the config and then to one per core.
+    fn run<T: Send>(
+        &self,
+        input_dir: &Path,
+        build: impl FnOnce() -> Result<T, BuildError> + Send,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let jobs = match self.jobs {
+            Some(jobs) => Some(jobs),
+            None => self.site_builder(input_dir).config()?.jobs,
+        };
+        with_jobs(jobs, build)?.map_err(|error| error as Box<dyn std::error::Error>)
+    }
+}
+
 fn escape_toml_string(input: &str) -> String {
     let mut output = String::with_capacity(input.len());
     for character in input.chars() {
@@ -38,76 +118,66 @@ fn escape_toml_string(input: &str) -> String {
     output
 }
 
-pub fn new_site(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub fn new_site(name: &str, starter: Starter) -> Result<(), Box<dyn std::error::Error>> {
     let site_dir = Path::new(name);
 
     if site_dir.exists() {
         return Err(format!("Directory '{}' already exists", name).into());
     }
 
-    fs::create_dir_all(site_dir.join("content").join("posts"))?;
-    fs::create_dir_all(site_dir.join("data"))?;
-    fs::create_dir_all(site_dir.join("static").join("images"))?;
+    scaffold_site(site_dir, name, starter)?;
 
-    let escaped_name = escape_toml_string(name);
-    let config = format!(
-        r#"title = "{escaped_name}"
-base_url = "http://localhost:3000"
-description = "A new Bamboo site"
-language = "en"
-"#
-    );
-    fs::write(site_dir.join("bamboo.toml"), config)?;
-
-    let index_content = r#"+++
-title = "Home"
-+++
-
-Welcome to your new Bamboo site!
-"#;
-    fs::write(site_dir.join("content").join("_index.md"), index_content)?;
-
-    let about_content = r#"+++
-title = "About"
-weight = 10
-+++
+    println!("Created new site: {name}");
+    println!("  cd {name}");
+    println!("  bamboo serve");
 
-This is the about page.
-"#;
-    fs::write(site_dir.join("content").join("about.md"), about_content)?;
+    Ok(())
+}
 
-    let post_content = r#"+++
-title = "Hello World"
-tags = ["welcome", "first-post"]
-+++
+pub fn new_content(
+    input: Option<&Path>,
+    kind: &str,
+    title: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = input.unwrap_or(Path::new("."));
+    if !input_dir.join("bamboo.toml").exists() {
+        return Err(format!("no bamboo.toml in {}", input_dir.display()).into());
+    }
 
-This is your first blog post. Start writing!
+    let today = chrono::Local::now().date_naive();
+    let path = bamboo_ssg::archetypes::new_content(input_dir, kind, title, today)?;
+    println!("Created {}", path.display());
 
-You can use **markdown** formatting, including:
+    Ok(())
+}
 
-- Lists
-- Code blocks
-- And more!
+fn scaffold_site(
+    site_dir: &Path,
+    name: &str,
+    starter: Starter,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for directory in starter.directories() {
+        let path = site_dir.join(directory);
+        fs::create_dir_all(&path)?;
+        if directory.starts_with("templates/") {
+            fs::write(path.join(".gitkeep"), "")?;
+        }
+    }
 
-```rust
-fn main() {
-    println!("Hello, world!");
-}
-```
-"#;
     fs::write(
-        site_dir
-            .join("content")
-            .join("posts")
-            .join("2024-01-01-hello-world.md"),
-        post_content,
+        site_dir.join("bamboo.toml"),
+        starter.config(&escape_toml_string(name)),
     )?;
 
-    fs::write(site_dir.join(".gitignore"), "dist/\n.bamboo-cache/\n")?;
+    for (relative_path, contents) in starter.files() {
+        let path = site_dir.join(relative_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)?;
+    }
 
-    println!("Created new site: {name}");
-    println!("  cd {name}");
-    println!("  bamboo serve");
+    fs::write(site_dir.join(".gitignore"), "dist/\n.bamboo-cache/\n")?;
 
     Ok(())
 }
@@ -151,12 +221,31 @@ pub fn build_site(
     theme: &str,
     input: Option<&Path>,
     output: &Path,
-    drafts: bool,
+    options: &BuildOptions,
     base_url: Option<&str>,
     clean: bool,
+    offline: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let input_dir = input.unwrap_or(Path::new("."));
+    options.run(input_dir, || {
+        run_build(theme, input_dir, output, options, base_url, clean, offline)
+    })
+}
 
+/// A full `bamboo build`. On top of the library's render this fetches
+/// remote data (reply counts, link previews, remote images), annotates
+/// archived links, precompresses, writes `manifest.json`, and checks
+/// external resources; `bamboo serve` skips all of these but the cached
+/// remote data.
+fn run_build(
+    theme: &str,
+    input_dir: &Path,
+    output: &Path,
+    options: &BuildOptions,
+    base_url: Option<&str>,
+    clean: bool,
+    offline: bool,
+) -> Result<(), BuildError> {
     if clean {
         clean_output_dir(output)?;
     }
@@ -164,35 +253,57 @@ pub fn build_site(
     println!("Building site...");
     let start = Instant::now();
 
-    let mut builder = SiteBuilder::new(input_dir).include_drafts(drafts);
+    let hooks = options.site_builder(input_dir).config()?.build.hooks;
+    let hook_context = HookContext {
+        input_dir,
+        output_dir: output,
+        changed_files: &[],
+        serving: false,
+    };
+    hooks.run(HookStage::Before, &hook_context)?;
 
-    if let Some(url) = base_url {
-        builder = builder.base_url(url);
-    }
+    let mut site = load_site(theme, input_dir, options, base_url)?;
 
-    let mut shortcode_dirs = Vec::new();
-    let site_shortcodes = input_dir.join("templates").join("shortcodes");
-    if site_shortcodes.is_dir() {
-        shortcode_dirs.push(site_shortcodes);
+    let mut remote = RemoteClient::new(site.config.remote.clone());
+    if offline {
+        remote = remote.offline(true);
     }
-    let theme_path = std::path::Path::new(theme);
-    let theme_shortcodes = theme_path.join("templates").join("shortcodes");
-    if theme_shortcodes.is_dir() {
-        shortcode_dirs.push(theme_shortcodes);
-    }
-    if !shortcode_dirs.is_empty() {
-        builder = builder.shortcode_dirs(&shortcode_dirs)?;
+
+    if site.config.discussions.fetch_counts {
+        let counted = bamboo_ssg::discussions::fetch_reply_counts(
+            &remote,
+            &mut site,
+            &bamboo_ssg::cache_dir(input_dir),
+        )?;
+        println!("Loaded reply counts for {counted} discussion(s)");
     }
-    let theme_templates = theme_path.join("templates");
-    if theme_templates.is_dir() {
-        builder = builder.theme_templates_dir(&theme_templates);
+
+    if site.config.link_previews.fetch {
+        let previewed = bamboo_ssg::link_previews::fetch_link_previews(
+            &remote,
+            &mut site,
+            &bamboo_ssg::cache_dir(input_dir),
+        )?;
+        println!("Loaded link previews for {previewed} bookmark(s)");
     }
 
-    let site = builder.build()?;
+    if site
+        .config
+        .images
+        .as_ref()
+        .is_some_and(|images| images.remote)
+    {
+        let downloaded = bamboo_ssg::remote_images::fetch_remote_images(
+            &remote,
+            &mut site,
+            &bamboo_ssg::cache_dir(input_dir),
+        )?;
+        println!("Serving {downloaded} remote image(s) locally");
+    }
 
-    let override_dir = input_dir.to_path_buf();
-    let theme_engine = ThemeEngine::new_with_overrides(theme, &override_dir)?;
-    theme_engine.render_site(&site, output)?;
+    let theme_engine = theme_engine(theme, input_dir)?;
+    let report = theme_engine.render_site(&site, output)?;
+    print_render_report(&site, &report);
 
     let elapsed = start.elapsed();
     println!(
@@ -203,6 +314,36 @@ pub fn build_site(
         elapsed
     );
 
+    if let Some(ref archive_config) = site.config.link_archive {
+        let archived = bamboo_ssg::archive::annotate_output(
+            &remote,
+            output,
+            &bamboo_ssg::cache_dir(input_dir),
+            &site.config.base_url,
+            archive_config,
+        )?;
+        println!(
+            "Annotated {} external link(s) with archived snapshots",
+            archived.annotated
+        );
+        for warning in &archived.warnings {
+            eprintln!("warning: {warning}");
+        }
+    }
+
+    hooks.run(HookStage::After, &hook_context)?;
+
+    if let Some(ref precompress_config) = site.config.precompress {
+        let stats = bamboo_ssg::precompress::precompress_output(output, precompress_config)?;
+        if stats.brotli_unavailable {
+            eprintln!("warning: could not run `brotli`; skipped .br files");
+        }
+        println!("Precompressed {} file(s)", stats.files);
+    }
+
+    // Last, so the hashes cover everything above that rewrote the output.
+    bamboo_ssg::build_manifest::generate_build_manifest(&site, output, &report.fingerprinted)?;
+
     let warnings = validate_internal_links(
         output,
         &site.config.base_url,
@@ -215,28 +356,189 @@ pub fn build_site(
         eprintln!("{} broken link(s) found", warnings.len());
     }
 
+    if site.config.generate.redirects {
+        for chain in bamboo_ssg::redirects::redirect_chains(&site) {
+            eprintln!("warning: {chain}");
+        }
+    }
+
+    for warning in bamboo_ssg::wellknown::security_txt_warnings(&site, chrono::Utc::now()) {
+        eprintln!("warning: {warning}");
+    }
+
+    if let Some(ref resource_config) = site.config.external_resources {
+        let disallowed = bamboo_ssg::resources::check_external_resources(
+            output,
+            &site.config.base_url,
+            resource_config,
+        );
+        for warning in &disallowed {
+            eprintln!("warning: {}", warning);
+        }
+        if !disallowed.is_empty() {
+            eprintln!(
+                "{} external resource(s) not on the allowlist",
+                disallowed.len()
+            );
+            if resource_config.strict {
+                return Err(
+                    format!("{} disallowed external resource(s) found", disallowed.len()).into(),
+                );
+            }
+        }
+    }
+
     Ok(())
 }
 
+/// Loads the site in `input_dir` with `base_url` overridden, the site's and
+/// the theme's shortcodes, and the theme's templates for shortcode lookups.
+fn load_site(
+    theme: &str,
+    input_dir: &Path,
+    options: &BuildOptions,
+    base_url: Option<&str>,
+) -> Result<Site, BuildError> {
+    let mut builder = options.site_builder(input_dir);
+    if let Some(url) = base_url {
+        builder = builder.base_url(url);
+    }
+
+    let mut shortcode_dirs = Vec::new();
+    let site_shortcodes = input_dir.join("templates").join("shortcodes");
+    if site_shortcodes.is_dir() {
+        shortcode_dirs.push(site_shortcodes);
+    }
+    let theme_path = std::path::Path::new(theme);
+    let theme_shortcodes = theme_path.join("templates").join("shortcodes");
+    if theme_shortcodes.is_dir() {
+        shortcode_dirs.push(theme_shortcodes);
+    }
+    if !shortcode_dirs.is_empty() {
+        builder = builder.shortcode_dirs(&shortcode_dirs)?;
+    }
+    let theme_templates = theme_path.join("templates");
+    if theme_templates.is_dir() {
+        builder = builder.theme_templates_dir(&theme_templates);
+    }
+
+    let mut site = builder.build()?;
+    options.apply_skips(&mut site.config.generate);
+    Ok(site)
+}
+
+/// The theme engine for `theme` with `input_dir`'s template overrides, its
+/// cache directory, and image progress on stderr.
+fn theme_engine(theme: &str, input_dir: &Path) -> Result<ThemeEngine, BuildError> {
+    let mut theme_engine = ThemeEngine::new_with_overrides(theme, input_dir)?;
+    theme_engine.set_cache_dir(&bamboo_ssg::cache_dir(input_dir));
+    show_image_progress(&mut theme_engine);
+    Ok(theme_engine)
+}
+
+/// Redraws a progress line on stderr as images start processing, when
+/// stderr is a terminal: count, current file, and the estimated time left.
+fn show_image_progress(theme_engine: &mut ThemeEngine) {
+    if !std::io::stderr().is_terminal() {
+        return;
+    }
+    theme_engine.set_image_progress(|progress: &ImageProgress<'_>| {
+        let eta = match progress.remaining() {
+            Some(remaining) => {
+                let seconds = remaining.as_secs_f64().round() as u64;
+                format!("{}:{:02}", seconds / 60, seconds % 60)
+            }
+            None => "--:--".to_string(),
+        };
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r\x1b[2KProcessing images [{}/{}] {} (ETA {eta})",
+            progress.done + 1,
+            progress.total,
+            progress.path
+        );
+        let _ = stderr.flush();
+    });
+}
+
+/// Clears the image progress line and prints what image processing did,
+/// unless `[images]` sets `progress = false`, then the render's warnings.
+fn print_render_report(site: &Site, report: &RenderReport) {
+    let progress = site
+        .config
+        .images
+        .as_ref()
+        .is_some_and(|images| images.progress);
+    if let Some(stats) = report.images.as_ref().filter(|stats| stats.images > 0)
+        && progress
+    {
+        if std::io::stderr().is_terminal() {
+            eprint!("\r\x1b[2K");
+        }
+        eprintln!("{stats}");
+    }
+    for warning in &report.warnings {
+        eprintln!("warning: {warning}");
+    }
+}
+
 fn build_site_incremental(
     theme: &str,
     input: &Path,
     output: &Path,
-    drafts: bool,
+    options: &BuildOptions,
+    base_url: Option<&str>,
+    cached_state: Option<&BuildState>,
+) -> Result<BuildState, Box<dyn std::error::Error>> {
+    options.run(input, || {
+        run_incremental_build(theme, input, output, options, base_url, cached_state)
+    })
+}
+
+fn run_incremental_build(
+    theme: &str,
+    input: &Path,
+    output: &Path,
+    options: &BuildOptions,
     base_url: Option<&str>,
     cached_state: Option<&BuildState>,
-) -> std::result::Result<BuildState, Box<dyn std::error::Error>> {
+) -> Result<BuildState, BuildError> {
     let start = Instant::now();
 
-    let new_hashes = compute_content_hashes(input)?;
+    let excluded = ExcludedPaths::new(input, output);
+    let mut new_hashes = compute_content_hashes(input, &excluded)?;
+    let changed_files = cached_state
+        .map(|state| changed_paths(&state.content_hashes, &new_hashes))
+        .unwrap_or_default();
+
+    let hooks = options.site_builder(input).config()?.build.hooks;
+    let hook_context = HookContext {
+        input_dir: input,
+        output_dir: output,
+        changed_files: &changed_files,
+        serving: true,
+    };
+    if !hooks.before.is_empty() && (cached_state.is_none() || !changed_files.is_empty()) {
+        hooks.run(HookStage::Before, &hook_context)?;
+        // Pick up whatever the hooks wrote. Their own writes trigger
+        // another rebuild, which stops here when the output is unchanged.
+        new_hashes = compute_content_hashes(input, &excluded)?;
+    }
 
     let targets = if let Some(previous_state) = cached_state {
-        let classification = classify_changes(&previous_state.content_hashes, &new_hashes);
+        let classification = classify_changes(
+            &previous_state.content_hashes,
+            &new_hashes,
+            &previous_state.template_dependencies,
+        );
         let target_set = expand_targets(&classification);
         if target_set.is_empty() {
             println!("No changes detected, skipping rebuild.");
             return Ok(BuildState {
                 content_hashes: new_hashes,
+                template_dependencies: previous_state.template_dependencies.clone(),
+                asset_paths: previous_state.asset_paths.clone(),
             });
         }
         if target_set.contains(&bamboo_ssg::RenderTarget::All) {
@@ -258,35 +560,51 @@ fn build_site_incremental(
         println!("Building site...");
     }
 
-    let mut builder = SiteBuilder::new(input).include_drafts(drafts);
-
-    if let Some(url) = base_url {
-        builder = builder.base_url(url);
+    let mut site = load_site(theme, input, options, base_url)?;
+
+    if site.config.discussions.fetch_counts {
+        // Rebuilds while serving reuse counts from the last full build
+        // rather than hitting the network on every save.
+        let remote = RemoteClient::default().offline(true);
+        bamboo_ssg::discussions::fetch_reply_counts(
+            &remote,
+            &mut site,
+            &bamboo_ssg::cache_dir(input),
+        )?;
     }
 
-    let mut shortcode_dirs = Vec::new();
-    let site_shortcodes = input.join("templates").join("shortcodes");
-    if site_shortcodes.is_dir() {
-        shortcode_dirs.push(site_shortcodes);
-    }
-    let theme_path = std::path::Path::new(theme);
-    let theme_shortcodes = theme_path.join("templates").join("shortcodes");
-    if theme_shortcodes.is_dir() {
-        shortcode_dirs.push(theme_shortcodes);
-    }
-    if !shortcode_dirs.is_empty() {
-        builder = builder.shortcode_dirs(&shortcode_dirs)?;
-    }
-    let theme_templates = theme_path.join("templates");
-    if theme_templates.is_dir() {
-        builder = builder.theme_templates_dir(&theme_templates);
+    if site.config.link_previews.fetch {
+        // Same as reply counts: serve only reads previews already cached.
+        let remote = RemoteClient::default().offline(true);
+        bamboo_ssg::link_previews::fetch_link_previews(
+            &remote,
+            &mut site,
+            &bamboo_ssg::cache_dir(input),
+        )?;
     }
 
-    let site = builder.build()?;
+    if site
+        .config
+        .images
+        .as_ref()
+        .is_some_and(|images| images.remote)
+    {
+        // Serve only rewrites images already downloaded by `bamboo build`.
+        let remote = RemoteClient::default().offline(true);
+        bamboo_ssg::remote_images::fetch_remote_images(
+            &remote,
+            &mut site,
+            &bamboo_ssg::cache_dir(input),
+        )?;
+    }
 
-    let override_dir = input.to_path_buf();
-    let theme_engine = ThemeEngine::new_with_overrides(theme, &override_dir)?;
-    theme_engine.render_site_with_targets(&site, output, targets.as_ref())?;
+    let mut theme_engine = theme_engine(theme, input)?;
+    if let Some(previous_state) = cached_state.filter(|_| is_incremental) {
+        theme_engine.set_asset_paths(previous_state.asset_paths.clone());
+    }
+    let report = theme_engine.render_site_with_targets(&site, output, targets.as_ref())?;
+    print_render_report(&site, &report);
+    hooks.run(HookStage::After, &hook_context)?;
 
     let elapsed = start.elapsed();
     println!(
@@ -299,6 +617,8 @@ fn build_site_incremental(
 
     Ok(BuildState {
         content_hashes: new_hashes,
+        template_dependencies: theme_engine.template_dependencies(&site),
+        asset_paths: report.fingerprinted,
     })
 }
 
@@ -306,7 +626,7 @@ pub async fn serve_site(
     theme: &str,
     input: Option<&Path>,
     output: &Path,
-    drafts: bool,
+    options: &BuildOptions,
     port: u16,
     clean: bool,
     open_browser: bool,
@@ -327,7 +647,7 @@ pub async fn serve_site(
         theme,
         &input_dir,
         output,
-        drafts,
+        options,
         Some(&serve_base_url),
         initial_cache.as_ref(),
     ) {
@@ -354,8 +674,10 @@ pub async fn serve_site(
     let error_state_clone = error_state.clone();
     let cached_state_clone = cached_state.clone();
     let input_dir_clone = input_dir.clone();
+    let options = options.clone();
+    let excluded = ExcludedPaths::new(&input_dir, output);
 
-    let (notify_tx, notify_rx) = channel();
+    let (notify_tx, notify_rx) = channel::<notify::Result<notify::Event>>();
 
     let mut watcher = RecommendedWatcher::new(
         notify_tx,
@@ -396,6 +718,9 @@ pub async fn serve_site(
     std::thread::spawn(move || {
         loop {
             match notify_rx.recv() {
+                Ok(Ok(event))
+                    if !event.paths.is_empty()
+                        && event.paths.iter().all(|path| excluded.contains(path)) => {}
                 Ok(_event) => {
                     loop {
                         match notify_rx.recv_timeout(DEBOUNCE_DURATION) {
@@ -416,7 +741,7 @@ pub async fn serve_site(
                         &theme_str,
                         &input_dir_clone,
                         &output_dir,
-                        drafts,
+                        &options,
                         Some(&serve_url),
                         previous_state.as_ref(),
                     ) {
@@ -484,6 +809,669 @@ pub async fn serve_site(
     Ok(())
 }
 
+pub fn import_feed(
+    source: &str,
+    input: Option<&Path>,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = input.unwrap_or(Path::new("."));
+    let posts_dir = input_dir.join("content").join("posts");
+
+    let xml = if source.starts_with("http://") || source.starts_with("https://") {
+        let mut remote = RemoteClient::default();
+        if offline {
+            remote = remote.offline(true);
+        }
+        remote.fetch_text(source)?
+    } else {
+        fs::read_to_string(source)?
+    };
+
+    let import = bamboo_ssg::import::import_feed(&xml, &posts_dir)?;
+    for path in &import.written {
+        println!("  created {}", path.display());
+    }
+    for path in &import.skipped {
+        eprintln!("warning: {} already exists; skipped", path.display());
+    }
+    println!(
+        "Imported {} post(s) into {}",
+        import.written.len(),
+        posts_dir.display()
+    );
+
+    Ok(())
+}
+
+/// A site generator `bamboo migrate` converts from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum MigrateSource {
+    /// A Hugo site.
+    Hugo,
+    /// A Zola site.
+    Zola,
+}
+
+pub fn migrate_site(
+    source: MigrateSource,
+    path: &Path,
+    output: &Path,
+    posts_section: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let generator = match source {
+        MigrateSource::Hugo => migrate::Generator::Hugo,
+        MigrateSource::Zola => migrate::Generator::Zola,
+    };
+    let report = migrate::migrate(generator, path, output, posts_section)?;
+
+    for warning in &report.warnings {
+        println!("  warning: {warning}");
+    }
+    println!(
+        "Migrated {} post(s), {} page(s), and {} file(s) into {}",
+        report.posts,
+        report.pages,
+        report.files,
+        output.display()
+    );
+    println!(
+        "Run `bamboo serve --input {}` to preview the site",
+        output.display()
+    );
+
+    Ok(())
+}
+
+pub fn test_templates(
+    theme: &str,
+    input: Option<&Path>,
+    templates: &[String],
+    context_file: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = input.unwrap_or(Path::new("."));
+    let theme_engine = ThemeEngine::new_with_overrides(theme, input_dir)?;
+
+    let site = template_test::sample_site()?;
+    let mut context = template_test::sample_context(&site);
+    if let Some(path) = context_file {
+        template_test::merge_context_file(&mut context, path)?;
+    }
+
+    let names: Vec<String> = if templates.is_empty() {
+        theme_engine
+            .template_names()
+            .into_iter()
+            .filter(|name| {
+                !["partials/", "macros/", "shortcodes/", "_hooks/"]
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+            })
+            .collect()
+    } else {
+        templates.to_vec()
+    };
+
+    let mut failures = 0;
+    for name in &names {
+        let report = template_test::test_template(&theme_engine, name, &context);
+        if report.is_ok() {
+            println!("  ok    {name}");
+            continue;
+        }
+        failures += 1;
+        println!("  FAIL  {name}");
+        for variable in &report.undefined_variables {
+            println!("        undefined variable: {variable}");
+        }
+        if let Some(ref error) = report.error {
+            println!("        error: {error}");
+        }
+    }
+
+    println!("{} template(s) tested, {failures} failed", names.len());
+    if failures > 0 {
+        return Err(format!("{failures} template(s) failed").into());
+    }
+    Ok(())
+}
+
+pub fn lint_templates(
+    theme: &str,
+    input: Option<&Path>,
+    context_file: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = input.unwrap_or(Path::new("."));
+    let theme_engine = ThemeEngine::new_with_overrides(theme, input_dir)?;
+
+    let site = template_test::sample_site()?;
+    let mut context = template_test::sample_context(&site);
+    if let Some(path) = context_file {
+        template_test::merge_context_file(&mut context, path)?;
+    }
+
+    let issues = lint::lint_templates(&theme_engine, &context);
+    let mut current_template = None;
+    for issue in &issues {
+        if current_template != Some(&issue.template) {
+            println!("{}", issue.template);
+            current_template = Some(&issue.template);
+        }
+        println!("  {}", issue.problem);
+    }
+
+    let template_count = theme_engine.template_names().len();
+    if issues.is_empty() {
+        println!("{template_count} template(s) linted, no problems found");
+        return Ok(());
+    }
+    println!(
+        "{template_count} template(s) linted, {} problem(s) found",
+        issues.len()
+    );
+    Err(format!("{} lint problem(s) found", issues.len()).into())
+}
+
+pub fn review_due(
+    input: Option<&Path>,
+    date: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = input.unwrap_or(Path::new("."));
+    let today = match date {
+        Some(date) => chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| format!("invalid --date '{date}', expected YYYY-MM-DD"))?,
+        None => chrono::Local::now().date_naive(),
+    };
+
+    let site = SiteBuilder::new(input_dir).include_drafts(true).build()?;
+    let report = bamboo_ssg::review::review_due(&site, today);
+
+    for (url, raw) in &report.invalid {
+        eprintln!("warning: {url}: unrecognized review_by date '{raw}'");
+    }
+
+    if report.due.is_empty() {
+        println!("No content is due for review as of {today}");
+        return Ok(());
+    }
+
+    for (owner, items) in &report.due {
+        println!("{owner} ({})", items.len());
+        for item in items {
+            println!(
+                "  {}  {:>4} day(s) overdue  {}  {}",
+                item.review_by, item.days_overdue, item.title, item.url
+            );
+        }
+    }
+    println!(
+        "{} item(s) due for review as of {today}",
+        report.due_count()
+    );
+    Ok(())
+}
+
+pub fn check_site(
+    input: Option<&Path>,
+    output: &Path,
+    environment: &str,
+    base_url: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !output.is_dir() {
+        return Err(format!(
+            "{} does not exist; run `bamboo build` first",
+            output.display()
+        )
+        .into());
+    }
+
+    let input_dir = input.unwrap_or(Path::new("."));
+    let config = SiteBuilder::new(input_dir)
+        .environment(environment)
+        .config()?;
+    let base_url = base_url.unwrap_or(&config.base_url);
+    let broken = check_links(output, base_url, &config.link_check_ignore);
+    for reference in &broken {
+        println!("  {reference}");
+    }
+
+    if !broken.is_empty() {
+        return Err(format!(
+            "{} broken reference(s) in {}",
+            broken.len(),
+            output.display()
+        )
+        .into());
+    }
+    println!("No broken links in {}", output.display());
+    Ok(())
+}
+
+pub fn site_stats(
+    input: Option<&Path>,
+    output: &Path,
+    environment: &str,
+    top: usize,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = input.unwrap_or(Path::new("."));
+    let site = SiteBuilder::new(input_dir)
+        .environment(environment)
+        .build()?;
+    let mut stats = bamboo_ssg::stats::site_stats(&site, top);
+    if output.is_dir() {
+        stats.output = Some(bamboo_ssg::stats::output_stats(output, top));
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} post(s), {} page(s), {} note(s), {} collection item(s)",
+        stats.posts, stats.pages, stats.notes, stats.collection_items
+    );
+    println!(
+        "{} word(s), {} in posts; {:.1} minute(s) average reading time per post",
+        stats.words, stats.post_words, stats.average_reading_time
+    );
+
+    let counts = |title: &str, counts: &[(String, usize)]| {
+        if counts.is_empty() {
+            return;
+        }
+        println!("\n{title}");
+        for (name, count) in counts.iter().take(top) {
+            println!("  {count:>5}  {name}");
+        }
+    };
+    let years: Vec<(String, usize)> = stats
+        .posts_per_year
+        .iter()
+        .rev()
+        .map(|(year, count)| (year.to_string(), *count))
+        .collect();
+    counts("Posts per year", &years);
+    counts("Posts per tag", &stats.posts_per_tag);
+    counts("Posts per category", &stats.posts_per_category);
+
+    if !stats.largest_pages.is_empty() {
+        println!("\nLongest pages");
+        for page in &stats.largest_pages {
+            println!("  {:>7} words  {}  {}", page.words, page.title, page.url);
+        }
+    }
+
+    match &stats.output {
+        Some(output_stats) => {
+            println!(
+                "\n{} file(s), {} in {}",
+                output_stats.files,
+                format_bytes(output_stats.bytes),
+                output.display()
+            );
+            for (extension, bytes) in output_stats.bytes_per_extension.iter().take(top) {
+                let extension = if extension.is_empty() {
+                    "(none)"
+                } else {
+                    extension
+                };
+                println!("  {:>10}  .{extension}", format_bytes(*bytes));
+            }
+            println!("\nLargest files");
+            for (path, bytes) in &output_stats.largest_files {
+                println!("  {:>10}  {path}", format_bytes(*bytes));
+            }
+        }
+        None => println!(
+            "\n{} does not exist; run `bamboo build` for output sizes",
+            output.display()
+        ),
+    }
+    Ok(())
+}
+
+/// A format `bamboo export` writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// JSON, as one document or one file per page.
+    Json,
+}
+
+pub fn export_site(
+    format: ExportFormat,
+    input: Option<&Path>,
+    output: Option<&Path>,
+    per_page: bool,
+    environment: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = input.unwrap_or(Path::new("."));
+    let site = SiteBuilder::new(input_dir)
+        .environment(environment)
+        .build()?;
+    match format {
+        ExportFormat::Json if per_page => {
+            let output = output.unwrap_or(Path::new("api"));
+            let files = export::write_export_dir(&site, output)?;
+            println!("Exported {files} file(s) to {}", output.display());
+        }
+        ExportFormat::Json => {
+            let output = output.unwrap_or(Path::new(export::SITE_EXPORT_FILE));
+            export::write_export(&site, output)?;
+            println!("Exported the site to {}", output.display());
+        }
+    }
+    Ok(())
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+pub fn deploy_gh_pages(
+    theme: &str,
+    input: Option<&Path>,
+    output: &Path,
+    environment: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = input.unwrap_or(Path::new("."));
+    let config = SiteBuilder::new(input_dir)
+        .environment(environment)
+        .config()?;
+    let gh_pages = config.deploy.gh_pages;
+    let remote_url = bamboo_ssg::gh_pages::remote_url(input_dir, &gh_pages)?;
+    let base_url =
+        bamboo_ssg::gh_pages::site_url(&gh_pages, &remote_url).unwrap_or(config.base_url);
+    let output = match &gh_pages.folder {
+        Some(folder) => input_dir.join(folder),
+        None => output.to_path_buf(),
+    };
+
+    let options = BuildOptions {
+        environment: environment.to_string(),
+        drafts: false,
+        future: false,
+        jobs: None,
+        skip: Vec::new(),
+    };
+    build_site(
+        theme,
+        Some(input_dir),
+        &output,
+        &options,
+        Some(&base_url),
+        true,
+        false,
+    )?;
+
+    match bamboo_ssg::gh_pages::publish(input_dir, &output, &gh_pages)? {
+        Some(commit) => println!(
+            "Deployed {base_url} to {} ({})",
+            gh_pages.destination(),
+            &commit[..commit.len().min(7)]
+        ),
+        None => println!("{} is already up to date", gh_pages.destination()),
+    }
+    Ok(())
+}
+
+/// `bamboo deploy s3` flags, each overriding `[deploy.s3]`.
+pub struct S3Options {
+    /// `--bucket`: bucket to upload to.
+    pub bucket: Option<String>,
+    /// `--distribution`: CloudFront distribution to invalidate.
+    pub distribution: Option<String>,
+    /// `--prefix`: key prefix the site lives under.
+    pub prefix: Option<String>,
+    /// `--region`: AWS region.
+    pub region: Option<String>,
+    /// `--delete`: remove objects for files no longer in the output.
+    pub delete: bool,
+    /// `--dry-run`: print the plan without uploading anything.
+    pub dry_run: bool,
+}
+
+pub fn deploy_s3(
+    theme: &str,
+    input: Option<&Path>,
+    output: &Path,
+    environment: &str,
+    options: &S3Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = input.unwrap_or(Path::new("."));
+    let mut s3 = SiteBuilder::new(input_dir)
+        .environment(environment)
+        .config()?
+        .deploy
+        .s3;
+    s3.bucket = options.bucket.clone().or(s3.bucket);
+    s3.distribution = options.distribution.clone().or(s3.distribution);
+    s3.prefix = options.prefix.clone().unwrap_or(s3.prefix);
+    s3.region = options.region.clone().or(s3.region);
+    s3.delete |= options.delete;
+    if s3.bucket.is_none() {
+        return Err("no bucket; pass --bucket or set `bucket` under [deploy.s3]".into());
+    }
+
+    let build_options = BuildOptions {
+        environment: environment.to_string(),
+        drafts: false,
+        future: false,
+        jobs: None,
+        skip: Vec::new(),
+    };
+    build_site(
+        theme,
+        Some(input_dir),
+        output,
+        &build_options,
+        None,
+        true,
+        false,
+    )?;
+
+    let plan = bamboo_ssg::s3::plan_deploy(output, &s3)?;
+    for upload in &plan.uploads {
+        println!(
+            "  upload {} ({})",
+            s3.key(&upload.path),
+            upload.cache_control
+        );
+    }
+    for path in &plan.deletes {
+        println!("  delete {}", s3.key(path));
+    }
+    for path in &plan.invalidations {
+        println!("  invalidate {path}");
+    }
+    if options.dry_run {
+        println!(
+            "Dry run: {} upload(s), {} deletion(s), {} invalidation path(s)",
+            plan.uploads.len(),
+            plan.deletes.len(),
+            plan.invalidations.len()
+        );
+        return Ok(());
+    }
+
+    let invalidation = bamboo_ssg::s3::apply(output, &s3, &plan)?;
+    println!(
+        "Uploaded {} of {} file(s) and deleted {} from s3://{}/{}",
+        plan.uploads.len(),
+        plan.manifest.files.len(),
+        plan.deletes.len(),
+        s3.bucket.as_deref().unwrap_or_default(),
+        s3.key("")
+    );
+    if let Some(id) = invalidation {
+        println!("Created CloudFront invalidation {id}");
+    }
+    Ok(())
+}
+
+pub fn deploy_rsync(
+    theme: &str,
+    input: Option<&Path>,
+    output: &Path,
+    environment: &str,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = input.unwrap_or(Path::new("."));
+    let rsync = SiteBuilder::new(input_dir)
+        .environment(environment)
+        .config()?
+        .deploy
+        .rsync;
+
+    let options = BuildOptions {
+        environment: environment.to_string(),
+        drafts: false,
+        future: false,
+        jobs: None,
+        skip: Vec::new(),
+    };
+    build_site(theme, Some(input_dir), output, &options, None, true, false)?;
+
+    let report = bamboo_ssg::rsync::deploy(output, &rsync, dry_run)?;
+    for path in &report.uploaded {
+        println!("  upload {path}");
+    }
+    for path in &report.deleted {
+        println!("  delete {path}");
+    }
+    let (uploaded, deleted) = (report.uploaded.len(), report.deleted.len());
+    let destination = rsync.destination();
+    if dry_run {
+        println!("Dry run: would upload {uploaded} and delete {deleted} file(s) in {destination}");
+    } else {
+        println!("Uploaded {uploaded} and deleted {deleted} file(s) in {destination}");
+    }
+    Ok(())
+}
+
+pub fn deploy_netlify(
+    theme: &str,
+    input: Option<&Path>,
+    output: &Path,
+    environment: &str,
+    site: Option<&str>,
+    production: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = input.unwrap_or(Path::new("."));
+    let config = SiteBuilder::new(input_dir)
+        .environment(environment)
+        .config()?;
+    let mut netlify = config.deploy.netlify;
+    if let Some(site) = site {
+        netlify.site_id = Some(site.to_string());
+    }
+    netlify.credentials()?;
+
+    let options = BuildOptions {
+        environment: environment.to_string(),
+        drafts: false,
+        future: false,
+        jobs: None,
+        skip: Vec::new(),
+    };
+    build_site(theme, Some(input_dir), output, &options, None, true, false)?;
+
+    println!("Uploading {} to Netlify...", output.display());
+    let client = RemoteClient::new(config.remote);
+    let deploy = bamboo_ssg::netlify::deploy(&client, output, &netlify, production)?;
+    if production {
+        println!("Deployed to production: {}", deploy.ssl_url);
+    } else {
+        println!("Draft deploy: {}", deploy.deploy_ssl_url);
+        println!("Run with --prod to publish it to {}", deploy.ssl_url);
+    }
+    Ok(())
+}
+
+pub fn deploy_vercel(
+    theme: &str,
+    input: Option<&Path>,
+    output: &Path,
+    environment: &str,
+    project: Option<&str>,
+    production: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_dir = input.unwrap_or(Path::new("."));
+    let config = SiteBuilder::new(input_dir)
+        .environment(environment)
+        .config()?;
+    let mut vercel = config.deploy.vercel;
+    if let Some(project) = project {
+        vercel.project = Some(project.to_string());
+    }
+    vercel.credentials()?;
+
+    let options = BuildOptions {
+        environment: environment.to_string(),
+        drafts: false,
+        future: false,
+        jobs: None,
+        skip: Vec::new(),
+    };
+    build_site(theme, Some(input_dir), output, &options, None, true, false)?;
+
+    println!("Uploading {} to Vercel...", output.display());
+    let client = RemoteClient::new(config.remote);
+    let deployment = bamboo_ssg::vercel_deploy::deploy(&client, output, &vercel, production)?;
+    if production {
+        match deployment.alias.first() {
+            Some(alias) => println!("Deployed to production: https://{alias}"),
+            None => println!("Deployed to production: {}", deployment.preview_url()),
+        }
+    } else {
+        println!("Preview deployment: {}", deployment.preview_url());
+        println!("Run with --prod to publish it to production");
+    }
+    Ok(())
+}
+
+pub fn verify_deploy(
+    url: &str,
+    output: &Path,
+    sample: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !output.is_dir() {
+        return Err(format!(
+            "{} does not exist; run `bamboo build` first",
+            output.display()
+        )
+        .into());
+    }
+
+    let client = RemoteClient::default();
+    let report = bamboo_ssg::verify::verify_deploy(&client, url, output, sample)?;
+    for issue in &report.issues {
+        println!("  {issue}");
+    }
+
+    println!(
+        "{} of {} file(s) checked against {url}, {} problem(s) found",
+        report.checked,
+        report.total,
+        report.issues.len()
+    );
+    if !report.issues.is_empty() {
+        return Err(format!("deployed site differs from {}", output.display()).into());
+    }
+    Ok(())
+}
+
 fn build_error_overlay(error_message: &str) -> String {
     let escaped_message = error_message
         .replace('&', "&amp;")
@@ -598,6 +1586,88 @@ async fn error_overlay_middleware(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_every_starter_builds() {
+        for &starter in <Starter as clap::ValueEnum>::value_variants() {
+            let dir = tempfile::TempDir::new().unwrap();
+            let site_dir = dir.path().join("site");
+            scaffold_site(&site_dir, "My \"Site\"", starter).unwrap();
+            assert!(site_dir.join("templates").is_dir());
+
+            let site = SiteBuilder::new(&site_dir).build().unwrap();
+            assert_eq!(site.config.title, "My \"Site\"");
+            let output = dir.path().join("dist");
+            ThemeEngine::new("default")
+                .unwrap()
+                .render_site(&site, &output)
+                .unwrap();
+            assert!(output.join("index.html").exists(), "{starter:?}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_manifest_covers_post_render_changes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let site_dir = dir.path().join("site");
+        fs::create_dir_all(site_dir.join("content")).unwrap();
+        fs::write(
+            site_dir.join("bamboo.toml"),
+            "title = \"T\"\nbase_url = \"https://example.com\"\nbuild_manifest = true\n\n\
+             [precompress]\nformats = [\"gzip\"]\nmin_size = 0\n\n\
+             [build.hooks]\nafter = [\"echo '<!-- hook -->' >> \\\"$BAMBOO_OUTPUT_DIR/about/index.html\\\"\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            site_dir.join("content/about.md"),
+            "+++\ntitle = \"About\"\n+++\n\nAbout.",
+        )
+        .unwrap();
+        let output = dir.path().join("dist");
+        let options = BuildOptions {
+            environment: "production".to_string(),
+            drafts: false,
+            future: false,
+            jobs: None,
+            skip: Vec::new(),
+        };
+
+        build_site(
+            "default",
+            Some(&site_dir),
+            &output,
+            &options,
+            None,
+            true,
+            true,
+        )
+        .unwrap();
+
+        let manifest: bamboo_ssg::build_manifest::BuildManifest =
+            serde_json::from_str(&fs::read_to_string(output.join("manifest.json")).unwrap())
+                .unwrap();
+        let about = manifest
+            .files
+            .iter()
+            .find(|file| file.path == "about/index.html")
+            .unwrap();
+        let contents = fs::read(output.join("about/index.html")).unwrap();
+        assert!(String::from_utf8_lossy(&contents).ends_with("<!-- hook -->\n"));
+        let rehashed = bamboo_ssg::build_manifest::hash_output(&output, &Default::default())
+            .unwrap()
+            .files
+            .into_iter()
+            .find(|file| file.path == "about/index.html")
+            .unwrap();
+        assert_eq!(about.sha256, rehashed.sha256);
+        assert!(
+            manifest
+                .files
+                .iter()
+                .any(|file| file.path == "about/index.html.gz")
+        );
+    }
+
     #[test]
     fn test_escape_toml_string_plain() {
         assert_eq!(escape_toml_string("hello world"), "hello world");
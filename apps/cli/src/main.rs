@@ -1,4 +1,5 @@
 mod commands;
+mod starters;
 
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
@@ -15,6 +16,14 @@ struct Cli {
 enum Commands {
     New {
         name: String,
+
+        title: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = starters::Starter::Blog, conflicts_with = "title")]
+        template: starters::Starter,
+
+        #[arg(long, short, requires = "title")]
+        input: Option<PathBuf>,
     },
     Init,
     Build {
@@ -27,14 +36,32 @@ enum Commands {
         #[arg(long, short, default_value = "dist")]
         output: PathBuf,
 
+        #[arg(long, default_value = "production")]
+        env: String,
+
         #[arg(long)]
         drafts: bool,
 
+        #[arg(long)]
+        future: bool,
+
+        #[arg(long, short)]
+        jobs: Option<usize>,
+
+        #[arg(long, value_enum)]
+        skip: Vec<commands::Artifact>,
+
         #[arg(long)]
         base_url: Option<String>,
 
         #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
         clean: bool,
+
+        #[arg(long)]
+        offline: bool,
+
+        #[arg(long)]
+        check_links: bool,
     },
     Serve {
         #[arg(long, default_value = "default")]
@@ -46,9 +73,21 @@ enum Commands {
         #[arg(long, short, default_value = "dist")]
         output: PathBuf,
 
+        #[arg(long, default_value = "dev")]
+        env: String,
+
         #[arg(long)]
         drafts: bool,
 
+        #[arg(long)]
+        future: bool,
+
+        #[arg(long, short)]
+        jobs: Option<usize>,
+
+        #[arg(long, value_enum)]
+        skip: Vec<commands::Artifact>,
+
         #[arg(long, default_value = "3000")]
         port: u16,
 
@@ -58,6 +97,232 @@ enum Commands {
         #[arg(long)]
         open: bool,
     },
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+    },
+    Migrate {
+        #[arg(value_enum)]
+        from: commands::MigrateSource,
+
+        path: PathBuf,
+
+        #[arg(long, short, default_value = "bamboo-site")]
+        output: PathBuf,
+
+        #[arg(long)]
+        posts: Option<String>,
+    },
+    Template {
+        #[command(subcommand)]
+        action: TemplateAction,
+    },
+    ReviewDue {
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        #[arg(long)]
+        date: Option<String>,
+    },
+    Lint {
+        #[arg(long, default_value = "default")]
+        theme: String,
+
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        #[arg(long)]
+        context: Option<PathBuf>,
+    },
+    Check {
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        #[arg(long, short, default_value = "dist")]
+        output: PathBuf,
+
+        #[arg(long, default_value = "production")]
+        env: String,
+
+        #[arg(long)]
+        base_url: Option<String>,
+    },
+    Stats {
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        #[arg(long, short, default_value = "dist")]
+        output: PathBuf,
+
+        #[arg(long, default_value = "production")]
+        env: String,
+
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+
+        #[arg(long)]
+        json: bool,
+    },
+    Export {
+        #[arg(long, value_enum, default_value_t = commands::ExportFormat::Json)]
+        format: commands::ExportFormat,
+
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        #[arg(long)]
+        per_page: bool,
+
+        #[arg(long, default_value = "production")]
+        env: String,
+    },
+    Deploy {
+        #[command(subcommand)]
+        target: DeployTarget,
+    },
+    Verify {
+        url: String,
+
+        #[arg(long, short, default_value = "dist")]
+        output: PathBuf,
+
+        #[arg(long, default_value_t = bamboo_ssg::verify::DEFAULT_SAMPLE)]
+        sample: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum ImportSource {
+    Feed {
+        url: String,
+
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        #[arg(long)]
+        offline: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DeployTarget {
+    GhPages {
+        #[arg(long, default_value = "default")]
+        theme: String,
+
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        #[arg(long, short, default_value = "dist")]
+        output: PathBuf,
+
+        #[arg(long, default_value = "production")]
+        env: String,
+    },
+    S3 {
+        #[arg(long)]
+        bucket: Option<String>,
+
+        #[arg(long)]
+        distribution: Option<String>,
+
+        #[arg(long)]
+        prefix: Option<String>,
+
+        #[arg(long)]
+        region: Option<String>,
+
+        #[arg(long)]
+        delete: bool,
+
+        #[arg(long)]
+        dry_run: bool,
+
+        #[arg(long, default_value = "default")]
+        theme: String,
+
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        #[arg(long, short, default_value = "dist")]
+        output: PathBuf,
+
+        #[arg(long, default_value = "production")]
+        env: String,
+    },
+    Rsync {
+        #[arg(long)]
+        dry_run: bool,
+
+        #[arg(long, default_value = "default")]
+        theme: String,
+
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        #[arg(long, short, default_value = "dist")]
+        output: PathBuf,
+
+        #[arg(long, default_value = "production")]
+        env: String,
+    },
+    Netlify {
+        #[arg(long)]
+        prod: bool,
+
+        #[arg(long)]
+        site: Option<String>,
+
+        #[arg(long, default_value = "default")]
+        theme: String,
+
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        #[arg(long, short, default_value = "dist")]
+        output: PathBuf,
+
+        #[arg(long, default_value = "production")]
+        env: String,
+    },
+    Vercel {
+        #[arg(long)]
+        prod: bool,
+
+        #[arg(long)]
+        project: Option<String>,
+
+        #[arg(long, default_value = "default")]
+        theme: String,
+
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        #[arg(long, short, default_value = "dist")]
+        output: PathBuf,
+
+        #[arg(long, default_value = "production")]
+        env: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TemplateAction {
+    Test {
+        templates: Vec<String>,
+
+        #[arg(long, default_value = "default")]
+        theme: String,
+
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        #[arg(long)]
+        context: Option<PathBuf>,
+    },
 }
 
 #[tokio::main]
@@ -65,34 +330,219 @@ async fn main() {
     let cli = Cli::parse();
 
     let result = match cli.command {
-        Commands::New { name } => commands::new_site(&name),
+        Commands::New {
+            name,
+            title: Some(title),
+            input,
+            ..
+        } => commands::new_content(input.as_deref(), &name, &title),
+        Commands::New { name, template, .. } => commands::new_site(&name, template),
         Commands::Init => commands::init_site(),
         Commands::Build {
             theme,
             input,
             output,
+            env,
             drafts,
+            future,
+            jobs,
+            skip,
             base_url,
             clean,
+            offline,
+            check_links,
         } => commands::build_site(
             &theme,
             input.as_deref(),
             &output,
-            drafts,
+            &commands::BuildOptions {
+                environment: env.clone(),
+                drafts,
+                future,
+                jobs,
+                skip,
+            },
             base_url.as_deref(),
             clean,
-        ),
+            offline,
+        )
+        .and_then(|()| {
+            if check_links {
+                commands::check_site(input.as_deref(), &output, &env, base_url.as_deref())
+            } else {
+                Ok(())
+            }
+        }),
         Commands::Serve {
             theme,
             input,
             output,
+            env,
             drafts,
+            future,
+            jobs,
+            skip,
             port,
             clean,
             open,
         } => {
-            commands::serve_site(&theme, input.as_deref(), &output, drafts, port, clean, open).await
+            let options = commands::BuildOptions {
+                environment: env,
+                drafts,
+                future,
+                jobs,
+                skip,
+            };
+            commands::serve_site(
+                &theme,
+                input.as_deref(),
+                &output,
+                &options,
+                port,
+                clean,
+                open,
+            )
+            .await
         }
+        Commands::Import {
+            source:
+                ImportSource::Feed {
+                    url,
+                    input,
+                    offline,
+                },
+        } => commands::import_feed(&url, input.as_deref(), offline),
+        Commands::Migrate {
+            from,
+            path,
+            output,
+            posts,
+        } => commands::migrate_site(from, &path, &output, posts.as_deref()),
+        Commands::Template {
+            action:
+                TemplateAction::Test {
+                    templates,
+                    theme,
+                    input,
+                    context,
+                },
+        } => commands::test_templates(&theme, input.as_deref(), &templates, context.as_deref()),
+        Commands::ReviewDue { input, date } => {
+            commands::review_due(input.as_deref(), date.as_deref())
+        }
+        Commands::Lint {
+            theme,
+            input,
+            context,
+        } => commands::lint_templates(&theme, input.as_deref(), context.as_deref()),
+        Commands::Check {
+            input,
+            output,
+            env,
+            base_url,
+        } => commands::check_site(input.as_deref(), &output, &env, base_url.as_deref()),
+        Commands::Stats {
+            input,
+            output,
+            env,
+            top,
+            json,
+        } => commands::site_stats(input.as_deref(), &output, &env, top, json),
+        Commands::Export {
+            format,
+            input,
+            output,
+            per_page,
+            env,
+        } => commands::export_site(format, input.as_deref(), output.as_deref(), per_page, &env),
+        Commands::Deploy {
+            target:
+                DeployTarget::GhPages {
+                    theme,
+                    input,
+                    output,
+                    env,
+                },
+        } => commands::deploy_gh_pages(&theme, input.as_deref(), &output, &env),
+        Commands::Deploy {
+            target:
+                DeployTarget::S3 {
+                    bucket,
+                    distribution,
+                    prefix,
+                    region,
+                    delete,
+                    dry_run,
+                    theme,
+                    input,
+                    output,
+                    env,
+                },
+        } => commands::deploy_s3(
+            &theme,
+            input.as_deref(),
+            &output,
+            &env,
+            &commands::S3Options {
+                bucket,
+                distribution,
+                prefix,
+                region,
+                delete,
+                dry_run,
+            },
+        ),
+        Commands::Deploy {
+            target:
+                DeployTarget::Rsync {
+                    dry_run,
+                    theme,
+                    input,
+                    output,
+                    env,
+                },
+        } => commands::deploy_rsync(&theme, input.as_deref(), &output, &env, dry_run),
+        Commands::Deploy {
+            target:
+                DeployTarget::Netlify {
+                    prod,
+                    site,
+                    theme,
+                    input,
+                    output,
+                    env,
+                },
+        } => commands::deploy_netlify(
+            &theme,
+            input.as_deref(),
+            &output,
+            &env,
+            site.as_deref(),
+            prod,
+        ),
+        Commands::Deploy {
+            target:
+                DeployTarget::Vercel {
+                    prod,
+                    project,
+                    theme,
+                    input,
+                    output,
+                    env,
+                },
+        } => commands::deploy_vercel(
+            &theme,
+            input.as_deref(),
+            &output,
+            &env,
+            project.as_deref(),
+            prod,
+        ),
+        Commands::Verify {
+            url,
+            output,
+            sample,
+        } => commands::verify_deploy(&url, &output, sample),
     };
 
     if let Err(error) = result {
@@ -105,3 +555,18 @@ async fn main() {
         std::process::exit(1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_rejects_site_options_with_a_title() {
+        assert!(Cli::try_parse_from(["bamboo", "new", "post", "Hello", "-i", "site"]).is_ok());
+        assert!(Cli::try_parse_from(["bamboo", "new", "site", "--template", "docs"]).is_ok());
+        assert!(
+            Cli::try_parse_from(["bamboo", "new", "post", "Hello", "--template", "docs"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["bamboo", "new", "site", "--input", "site"]).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! Starter layouts scaffolded by `bamboo new --template`. Each starter is a
+//! `bamboo.toml`, content and data files that exercise the matching
+//! built-in template, and empty `templates/` directories for site-level
+//! overrides.
+
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Starter {
+    /// Home page, about page, and a first post.
+    Blog,
+    /// Documentation collection with a sidebar.
+    Docs,
+    /// Single-page portfolio driven by data files.
+    Portfolio,
+    /// Product landing page with features and pricing.
+    Landing,
+}
+
+impl Starter {
+    /// `bamboo.toml` for a site titled `escaped_name` (already escaped for a
+    /// TOML basic string).
+    pub fn config(self, escaped_name: &str) -> String {
+        let mut config = format!(
+            r#"title = "{escaped_name}"
+base_url = "http://localhost:3000"
+description = "A new Bamboo site"
+language = "en"
+"#
+        );
+        let extra = match self {
+            Self::Blog => "",
+            Self::Docs => {
+                r#"
+[extra]
+github = "https://github.com/your-name/your-project"
+"#
+            }
+            Self::Portfolio => {
+                r#"
+[extra]
+tagline = "Software engineer"
+github = "https://github.com/your-name"
+linkedin = "https://linkedin.com/in/your-name"
+"#
+            }
+            Self::Landing => {
+                r##"
+[extra]
+tagline = "The one-line pitch for your product."
+cta_text = "Get Started"
+cta_url = "#pricing"
+"##
+            }
+        };
+        config.push_str(extra);
+        config
+    }
+
+    /// Files to write, as paths relative to the site root and contents.
+    pub fn files(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Blog => BLOG_FILES,
+            Self::Docs => DOCS_FILES,
+            Self::Portfolio => PORTFOLIO_FILES,
+            Self::Landing => LANDING_FILES,
+        }
+    }
+
+    /// Empty directories to create, relative to the site root. Directories
+    /// under `templates/` get a `.gitkeep` so they survive a commit.
+    pub fn directories(self) -> &'static [&'static str] {
+        match self {
+            Self::Blog => &[
+                "content/posts",
+                "data",
+                "static/images",
+                "templates/partials",
+                "templates/shortcodes",
+            ],
+            Self::Docs => &[
+                "content/docs",
+                "data",
+                "static",
+                "templates/shortcodes",
+                "templates/_hooks",
+            ],
+            Self::Portfolio | Self::Landing => &["data", "static", "templates/partials"],
+        }
+    }
+}
+
+const BLOG_FILES: &[(&str, &str)] = &[
+    (
+        "content/_index.md",
+        r#"+++
+title = "Home"
++++
+
+Welcome to your new Bamboo site!
+"#,
+    ),
+    (
+        "content/about.md",
+        r#"+++
+title = "About"
+weight = 10
++++
+
+This is the about page.
+"#,
+    ),
+    (
+        "content/posts/2024-01-01-hello-world.md",
+        r#"+++
+title = "Hello World"
+tags = ["welcome", "first-post"]
++++
+
+This is your first blog post. Start writing!
+
+You can use **markdown** formatting, including:
+
+- Lists
+- Code blocks
+- And more!
+
+```rust
+fn main() {
+    println!("Hello, world!");
+}
+```
+"#,
+    ),
+];
+
+const DOCS_FILES: &[(&str, &str)] = &[
+    (
+        "content/_index.md",
+        r#"+++
+title = "Documentation"
+template = "docs.html"
++++
+
+# Welcome
+
+Start with [Getting Started](./docs/getting-started/), then read about
+[Configuration](./docs/configuration/).
+"#,
+    ),
+    ("content/docs/_collection.toml", "name = \"docs\"\n"),
+    (
+        "content/docs/getting-started.md",
+        r#"+++
+title = "Getting Started"
+weight = 1
+template = "docs.html"
++++
+
+# Getting Started
+
+## Installation
+
+Describe how to install your project.
+
+## First Steps
+
+Walk through a minimal example.
+"#,
+    ),
+    (
+        "content/docs/configuration.md",
+        r#"+++
+title = "Configuration"
+weight = 2
+template = "docs.html"
++++
+
+# Configuration
+
+Document each option your project supports.
+"#,
+    ),
+    (
+        "data/sidebar.toml",
+        r#"[[sections]]
+title = "Guide"
+items = [
+    { title = "Getting Started", url = "/docs/getting-started/" },
+    { title = "Configuration", url = "/docs/configuration/" },
+]
+"#,
+    ),
+];
+
+const PORTFOLIO_FILES: &[(&str, &str)] = &[
+    (
+        "content/_index.md",
+        r#"+++
+title = "Home"
+template = "portfolio.html"
++++
+
+A short introduction: who you are and what you work on.
+"#,
+    ),
+    (
+        "data/experience.toml",
+        r#"[[jobs]]
+title = "Software Engineer"
+company = "Your Company"
+period = "2022 - Present"
+achievements = [
+    "Something you shipped and why it mattered",
+]
+"#,
+    ),
+    (
+        "data/projects.toml",
+        r#"[[items]]
+title = "your-project"
+description = "What it does and who uses it."
+link = "https://github.com/your-name/your-project"
+technologies = ["Rust"]
+"#,
+    ),
+    (
+        "data/skills.toml",
+        r#"title = "Skills"
+top_items = ["Rust"]
+items = ["Rust", "SQL", "Distributed systems"]
+"#,
+    ),
+];
+
+const LANDING_FILES: &[(&str, &str)] = &[
+    (
+        "content/_index.md",
+        r#"+++
+title = "Home"
+template = "landing.html"
++++
+
+Explain the problem your product solves in a sentence or two.
+"#,
+    ),
+    (
+        "data/features.toml",
+        r#"[[items]]
+title = "Fast"
+description = "Why speed matters to your users."
+icon = "zap"
+
+[[items]]
+title = "Secure"
+description = "How you keep their data safe."
+icon = "shield"
+
+[[items]]
+title = "Simple"
+description = "What makes it easy to adopt."
+icon = "code"
+"#,
+    ),
+    (
+        "data/pricing.toml",
+        r#"[[plans]]
+name = "Free"
+price = "$0"
+description = "For trying it out"
+features = ["One project", "Community support"]
+cta = "Start Free"
+
+[[plans]]
+name = "Pro"
+price = "$19"
+period = "/month"
+description = "For teams"
+featured = true
+features = ["Unlimited projects", "Priority support"]
+cta = "Start Trial"
+"#,
+    ),
+];
@@ -0,0 +1,312 @@
+//! Queries the Tantivy index bamboo writes with `search_format = "tantivy"`,
+//! compiled to WebAssembly for the browser. The default theme's search page
+//! loads it from `/search-wasm/`; build it into a site's `static/` with
+//!
+//! ```sh
+//! wasm-pack build crates/bamboo-search-wasm --release --target web --out-dir "$PWD/<site>/static/search-wasm"
+//! ```
+//!
+//! The page fetches `search-index/files.json`, hands each listed file to
+//! [`SearchIndex::add_file`], calls [`SearchIndex::open`] with the
+//! manifest's `language`, and then calls [`SearchIndex::search`] per query.
+//! Everything stays in memory; nothing is fetched after the index opens.
+//!
+//! Queries are analyzed the way bamboo analyzed the text, every term must
+//! match, title matches count triple and tag matches double, and each term
+//! also matches words starting with it or one edit away from it.
+
+#![warn(missing_docs)]
+
+use std::fmt;
+use std::path::Path;
+
+use serde::Serialize;
+use tantivy::collector::TopDocs;
+use tantivy::directory::{Directory, RamDirectory};
+use tantivy::query::QueryParser;
+use tantivy::schema::{Field, Value};
+use tantivy::tokenizer::{
+    Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer, TextAnalyzer,
+};
+use tantivy::{Index, IndexReader, ReloadPolicy, TantivyDocument};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+/// Name bamboo registers its analyzer under, which the schema refers to.
+const TOKENIZER: &str = "bamboo";
+
+/// Why the index couldn't be opened or searched. Thrown as a JavaScript
+/// error from the WASM bindings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchError(String);
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+#[cfg(target_arch = "wasm32")]
+impl From<SearchError> for JsValue {
+    fn from(error: SearchError) -> JsValue {
+        JsError::new(&error.0).into()
+    }
+}
+
+fn search_error(error: impl fmt::Display) -> SearchError {
+    SearchError(error.to_string())
+}
+
+/// One search result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Hit {
+    /// Page title.
+    pub title: String,
+    /// Page URL.
+    pub url: String,
+    /// Tags, for posts.
+    pub tags: Vec<String>,
+    /// `YYYY-MM-DD`, or empty for undated pages.
+    pub date: String,
+    /// Short plain-text excerpt, possibly empty.
+    pub excerpt: String,
+}
+
+/// The fields of bamboo's index schema.
+struct Fields {
+    title: Field,
+    tags: Field,
+    body: Field,
+    url: Field,
+    date: Field,
+    excerpt: Field,
+}
+
+/// An open index.
+struct Opened {
+    index: Index,
+    reader: IndexReader,
+    fields: Fields,
+}
+
+/// The index, filled with [`SearchIndex::add_file`] and then opened with
+/// [`SearchIndex::open`].
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Default)]
+pub struct SearchIndex {
+    directory: RamDirectory,
+    opened: Option<Opened>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl SearchIndex {
+    /// An empty index.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new() -> SearchIndex {
+        SearchIndex::default()
+    }
+
+    /// Adds one of the files listed in `files.json`.
+    pub fn add_file(&mut self, name: &str, contents: &[u8]) -> Result<(), SearchError> {
+        self.directory
+            .atomic_write(Path::new(name), contents)
+            .map_err(search_error)
+    }
+
+    /// Opens the added files as an index whose text was analyzed for
+    /// `language`, the manifest's primary language subtag.
+    pub fn open(&mut self, language: &str) -> Result<(), SearchError> {
+        let index = Index::open(self.directory.clone()).map_err(search_error)?;
+        index.tokenizers().register(TOKENIZER, analyzer(language));
+        let schema = index.schema();
+        let field = |name: &str| schema.get_field(name).map_err(search_error);
+        let fields = Fields {
+            title: field("title")?,
+            tags: field("tags")?,
+            body: field("body")?,
+            url: field("url")?,
+            date: field("date")?,
+            excerpt: field("excerpt")?,
+        };
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()
+            .map_err(search_error)?;
+        self.opened = Some(Opened {
+            index,
+            reader,
+            fields,
+        });
+        Ok(())
+    }
+
+    /// The best `limit` matches for `query` as a JSON array of [`Hit`]s.
+    pub fn search(&self, query: &str, limit: usize) -> Result<String, SearchError> {
+        let hits = self.hits(query, limit)?;
+        serde_json::to_string(&hits).map_err(search_error)
+    }
+}
+
+impl SearchIndex {
+    /// The best `limit` matches for `query`, best first.
+    pub fn hits(&self, query: &str, limit: usize) -> Result<Vec<Hit>, SearchError> {
+        let Some(Opened {
+            index,
+            reader,
+            fields,
+        }) = &self.opened
+        else {
+            return Err(SearchError("the index hasn't been opened".to_string()));
+        };
+        let mut parser =
+            QueryParser::for_index(index, vec![fields.title, fields.tags, fields.body]);
+        parser.set_conjunction_by_default();
+        parser.set_field_boost(fields.title, 3.0);
+        parser.set_field_boost(fields.tags, 2.0);
+        for field in [fields.title, fields.tags, fields.body] {
+            parser.set_field_fuzzy(field, true, 1, true);
+        }
+        let (query, _) = parser.parse_query_lenient(query);
+
+        let searcher = reader.searcher();
+        let top = searcher
+            .search(&query, &TopDocs::with_limit(limit))
+            .map_err(search_error)?;
+        top.into_iter()
+            .map(|(_, address)| {
+                let document: TantivyDocument = searcher.doc(address).map_err(search_error)?;
+                let text = |field: Field| {
+                    document
+                        .get_first(field)
+                        .and_then(|value| value.as_str())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                Ok(Hit {
+                    title: text(fields.title),
+                    url: text(fields.url),
+                    tags: document
+                        .get_all(fields.tags)
+                        .filter_map(|value| value.as_str().map(str::to_string))
+                        .collect(),
+                    date: text(fields.date),
+                    excerpt: text(fields.excerpt),
+                })
+            })
+            .collect()
+    }
+}
+
+/// The analyzer bamboo indexed with, for a primary language subtag. Must
+/// match `bamboo_ssg::tantivy_index::analyzer`.
+fn analyzer(language: &str) -> TextAnalyzer {
+    let stemmer = match language {
+        "en" => Some(Language::English),
+        "fr" => Some(Language::French),
+        "de" => Some(Language::German),
+        "es" => Some(Language::Spanish),
+        "it" => Some(Language::Italian),
+        "pt" => Some(Language::Portuguese),
+        "nl" => Some(Language::Dutch),
+        _ => None,
+    };
+    let builder = TextAnalyzer::builder(SimpleTokenizer::default())
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser);
+    match stemmer {
+        Some(stemmer) => builder.filter(Stemmer::new(stemmer)).build(),
+        None => builder.build(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bamboo_ssg::search::SearchEntry;
+    use bamboo_ssg::search_language::SearchLanguage;
+    use bamboo_ssg::tantivy_index::tantivy_index;
+
+    fn entry(title: &str, url: &str, tags: &[&str], content: &str) -> SearchEntry {
+        SearchEntry {
+            title: title.to_string(),
+            url: url.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            date: "2024-03-01".to_string(),
+            excerpt: format!("About {title}"),
+            content: content.to_string(),
+        }
+    }
+
+    fn open_index() -> SearchIndex {
+        let entries = [
+            entry(
+                "Deploying to S3",
+                "/posts/s3/",
+                &["deploy"],
+                "Sync the output to a bucket and invalidate CloudFront.",
+            ),
+            entry(
+                "Writing themes",
+                "/posts/themes/",
+                &["templates"],
+                "Templates render every page. Deploying is covered elsewhere.",
+            ),
+        ];
+        let files = tantivy_index(&entries, &SearchLanguage::for_language(Some("en"))).unwrap();
+        let mut index = SearchIndex::new();
+        for (name, contents) in &files {
+            index.add_file(name, contents).unwrap();
+        }
+        index.open("en").unwrap();
+        index
+    }
+
+    #[test]
+    fn test_search_ranks_title_matches_first() {
+        let index = open_index();
+        let hits = index.hits("deploys", 10).unwrap();
+        let urls: Vec<&str> = hits.iter().map(|hit| hit.url.as_str()).collect();
+        assert_eq!(urls, ["/posts/s3/", "/posts/themes/"]);
+        assert_eq!(
+            hits[0],
+            Hit {
+                title: "Deploying to S3".to_string(),
+                url: "/posts/s3/".to_string(),
+                tags: vec!["deploy".to_string()],
+                date: "2024-03-01".to_string(),
+                excerpt: "About Deploying to S3".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_search_requires_every_term_and_tolerates_typos() {
+        let index = open_index();
+        let urls = |query: &str| -> Vec<String> {
+            index
+                .hits(query, 10)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.url)
+                .collect()
+        };
+        assert_eq!(urls("bucket deploy"), ["/posts/s3/"]);
+        assert_eq!(urls("cloudfrnt"), ["/posts/s3/"]);
+        assert_eq!(urls("templ"), ["/posts/themes/"]);
+        assert!(urls("kubernetes").is_empty());
+        assert_eq!(
+            index.search("kubernetes", 10).unwrap(),
+            "[]",
+            "search returns JSON"
+        );
+    }
+
+    #[test]
+    fn test_search_before_open() {
+        let error = SearchIndex::new().search("deploy", 10).unwrap_err();
+        assert_eq!(error.to_string(), "the index hasn't been opened");
+    }
+}
@@ -0,0 +1,173 @@
+//! Content scaffolding for `bamboo new <kind> "<title>"`: writes a new,
+//! correctly named markdown file from an archetype template.
+//!
+//! The kind picks the location: `post` goes to
+//! `content/posts/YYYY-MM-DD-<slug>.md`, `page` to `content/<slug>.md`, and
+//! the name of a collection (a `content/<name>/` directory with a
+//! `_collection.toml`) to `content/<name>/<slug>.md`.
+//!
+//! The file's contents come from `archetypes/<kind>.md` in the site, then
+//! `archetypes/default.md`, then a built-in frontmatter block. Archetypes
+//! are Tera templates rendered with:
+//!
+//! - `title`: the title as given.
+//! - `slug`: the title slugified, as used in the file name.
+//! - `date`: today's date, `YYYY-MM-DD`.
+//! - `kind`: `post`, `page`, or the collection name.
+//!
+//! Use `{{ title | json_encode() }}` to write the title as a quoted string
+//! that survives quotes and backslashes in TOML and YAML.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use tera::{Context, Tera};
+
+use crate::error::{BambooError, IoContext, Result};
+use crate::parsing::slugify;
+
+/// Directory under the site root holding archetype templates.
+pub const ARCHETYPES_DIR: &str = "archetypes";
+
+const DEFAULT_POST_ARCHETYPE: &str = "+++
+title = {{ title | json_encode() }}
+date = \"{{ date }}\"
+draft = true
+tags = []
++++
+
+";
+
+const DEFAULT_ARCHETYPE: &str = "+++
+title = {{ title | json_encode() }}
++++
+
+";
+
+/// Creates a `kind` file titled `title` under `input_dir`, dated `date`,
+/// and returns its path. Fails if the file already exists or `kind` is
+/// neither `post`, `page`, nor a collection.
+pub fn new_content(input_dir: &Path, kind: &str, title: &str, date: NaiveDate) -> Result<PathBuf> {
+    let slug = match slugify(title) {
+        slug if slug.is_empty() => "untitled".to_string(),
+        slug => slug,
+    };
+    let date = date.format("%Y-%m-%d").to_string();
+    let content_dir = input_dir.join("content");
+
+    let path = match kind {
+        "post" => content_dir.join("posts").join(format!("{date}-{slug}.md")),
+        "page" => content_dir.join(format!("{slug}.md")),
+        collection => {
+            let collection_dir = content_dir.join(collection);
+            if !collection_dir.join("_collection.toml").is_file() {
+                return Err(BambooError::NewContent {
+                    path: collection_dir,
+                    message: format!(
+                        "'{collection}' is not `post`, `page`, or a collection with a _collection.toml"
+                    ),
+                });
+            }
+            collection_dir.join(format!("{slug}.md"))
+        }
+    };
+    if path.exists() {
+        return Err(BambooError::NewContent {
+            path,
+            message: "file already exists".to_string(),
+        });
+    }
+
+    let mut context = Context::new();
+    context.insert("title", title);
+    context.insert("slug", &slug);
+    context.insert("date", &date);
+    context.insert("kind", kind);
+    let archetype = archetype(input_dir, kind)?;
+    let rendered = Tera::one_off(&archetype, &context, false)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).io_context("create_dir_all", parent)?;
+    }
+    fs::write(&path, rendered).io_context("write", &path)?;
+    Ok(path)
+}
+
+/// The archetype template for `kind`: the site's own, its
+/// `default.md`, or the built-in one.
+fn archetype(input_dir: &Path, kind: &str) -> Result<String> {
+    let archetypes_dir = input_dir.join(ARCHETYPES_DIR);
+    for name in [format!("{kind}.md"), "default.md".to_string()] {
+        let path = archetypes_dir.join(name);
+        if path.is_file() {
+            return fs::read_to_string(&path).io_context("read", &path);
+        }
+    }
+    Ok(if kind == "post" {
+        DEFAULT_POST_ARCHETYPE
+    } else {
+        DEFAULT_ARCHETYPE
+    }
+    .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, 9).unwrap()
+    }
+
+    #[test]
+    fn test_new_post_uses_builtin_archetype() {
+        let dir = TempDir::new().unwrap();
+
+        let path = new_content(dir.path(), "post", "Say \"Hello\" World", date()).unwrap();
+
+        assert_eq!(
+            path,
+            dir.path()
+                .join("content/posts/2026-03-09-say-hello-world.md")
+        );
+        let written = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            written,
+            "+++\ntitle = \"Say \\\"Hello\\\" World\"\ndate = \"2026-03-09\"\ndraft = true\ntags = []\n+++\n\n"
+        );
+        let (frontmatter, _) = crate::parsing::extract_frontmatter(&written, &path).unwrap();
+        assert_eq!(
+            frontmatter.get_string("title").as_deref(),
+            Some("Say \"Hello\" World")
+        );
+
+        let error = new_content(dir.path(), "post", "Say Hello World", date()).unwrap_err();
+        assert!(error.to_string().contains("file already exists"), "{error}");
+    }
+
+    #[test]
+    fn test_collection_and_site_archetypes() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("content/projects")).unwrap();
+        fs::write(dir.path().join("content/projects/_collection.toml"), "").unwrap();
+        fs::create_dir_all(dir.path().join(ARCHETYPES_DIR)).unwrap();
+        fs::write(
+            dir.path().join("archetypes/default.md"),
+            "---\ntitle: {{ title | json_encode() }}\nslug: {{ slug }}\nkind: {{ kind }}\n---\n",
+        )
+        .unwrap();
+
+        let path = new_content(dir.path(), "projects", "Rust CLI", date()).unwrap();
+
+        assert_eq!(path, dir.path().join("content/projects/rust-cli.md"));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "---\ntitle: \"Rust CLI\"\nslug: rust-cli\nkind: projects\n---\n"
+        );
+
+        let error = new_content(dir.path(), "recipes", "Soup", date()).unwrap_err();
+        assert!(error.to_string().contains("'recipes' is not"), "{error}");
+    }
+}
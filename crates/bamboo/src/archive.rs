@@ -0,0 +1,399 @@
+//! Link-rot mitigation: looks up Wayback Machine snapshots for external
+//! links in the generated HTML and annotates each link with its archived
+//! copy, so readers of old posts can still reach dead destinations.
+//!
+//! Lookups are cached in `.bamboo-cache/archive-links.json`; only URLs
+//! missing from the cache touch the network.
+
+use crate::error::{BambooError, IoContext, Result};
+use crate::remote::RemoteClient;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+const CACHE_FILE_NAME: &str = "archive-links.json";
+const AVAILABILITY_ENDPOINT: &str = "https://archive.org/wayback/available?url=";
+
+/// `[link_archive]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LinkArchiveConfig {
+    /// How archived URLs are exposed in the HTML.
+    #[serde(default)]
+    pub mode: ArchiveMode,
+    /// Hosts (e.g. `"github.com"`) whose links are never annotated.
+    #[serde(default)]
+    pub ignore_hosts: Vec<String>,
+}
+
+/// Presentation of an archived snapshot next to its original link.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveMode {
+    /// Adds `data-archive-url="..."` to the `<a>` tag.
+    #[default]
+    Attribute,
+    /// Appends `<sup class="archive-link"><a href="...">archived</a></sup>`
+    /// after the link.
+    Footnote,
+}
+
+/// Snapshot lookups keyed by original URL. `None` records that the archive
+/// has no copy, so the URL isn't queried again.
+pub type ArchiveCache = HashMap<String, Option<String>>;
+
+/// A snapshot lookup that failed during [`annotate_output`]. The link is
+/// left unannotated and looked up again on the next build.
+#[derive(Debug)]
+pub struct ArchiveWarning {
+    /// The external URL whose snapshot couldn't be looked up.
+    pub url: String,
+    /// Why the lookup failed.
+    pub error: BambooError,
+}
+
+impl std::fmt::Display for ArchiveWarning {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "archive lookup for '{}' failed: {}",
+            self.url, self.error
+        )
+    }
+}
+
+/// What [`annotate_output`] did.
+#[derive(Debug, Default)]
+pub struct ArchiveReport {
+    /// Number of links annotated.
+    pub annotated: usize,
+    /// Lookups that failed, in URL order.
+    pub warnings: Vec<ArchiveWarning>,
+}
+
+#[derive(Deserialize)]
+struct AvailabilityResponse {
+    #[serde(default)]
+    archived_snapshots: HashMap<String, Snapshot>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    #[serde(default)]
+    available: bool,
+    url: String,
+}
+
+/// Loads the snapshot cache from `cache_dir`, returning an empty cache if
+/// the file is missing or malformed.
+pub fn load_archive_cache(cache_dir: &Path) -> ArchiveCache {
+    fs::read_to_string(cache_dir.join(CACHE_FILE_NAME))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Writes the snapshot cache to `cache_dir/archive-links.json`.
+pub fn save_archive_cache(cache_dir: &Path, cache: &ArchiveCache) -> Result<()> {
+    fs::create_dir_all(cache_dir).io_context("create_dir_all", cache_dir)?;
+    let path = cache_dir.join(CACHE_FILE_NAME);
+    let content = serde_json::to_string_pretty(cache)
+        .map_err(|error| std::io::Error::other(error.to_string()))?;
+    fs::write(&path, content).io_context("write", &path)?;
+    Ok(())
+}
+
+/// Asks the Wayback Machine availability API for the closest snapshot of
+/// `url`.
+pub fn lookup_snapshot(client: &RemoteClient, url: &str) -> Result<Option<String>> {
+    let endpoint = format!(
+        "{AVAILABILITY_ENDPOINT}{}",
+        crate::remote::encode_query_value(url)
+    );
+    let body = client.fetch_text(&endpoint)?;
+    let response: AvailabilityResponse =
+        serde_json::from_str(&body).map_err(|error| BambooError::Remote {
+            url: endpoint.clone(),
+            message: error.to_string(),
+        })?;
+    Ok(response
+        .archived_snapshots
+        .get("closest")
+        .filter(|snapshot| snapshot.available)
+        .map(|snapshot| snapshot.url.replacen("http://", "https://", 1)))
+}
+
+/// Annotates every external link under `output_dir` with its archived
+/// snapshot. Unknown URLs are looked up through `client` and added to the
+/// cache in `cache_dir`; failed lookups are reported as warnings and
+/// retried on the next build. In offline mode only cached snapshots are
+/// used.
+pub fn annotate_output(
+    client: &RemoteClient,
+    output_dir: &Path,
+    cache_dir: &Path,
+    base_url: &str,
+    config: &LinkArchiveConfig,
+) -> Result<ArchiveReport> {
+    let lookup = |url: &str| (!client.is_offline()).then(|| lookup_snapshot(client, url));
+    annotate_output_with(lookup, output_dir, cache_dir, base_url, config)
+}
+
+/// [`annotate_output`] with the lookup supplied by the caller. `lookup`
+/// returns `None` to use cached snapshots only.
+fn annotate_output_with(
+    lookup: impl Fn(&str) -> Option<Result<Option<String>>>,
+    output_dir: &Path,
+    cache_dir: &Path,
+    base_url: &str,
+    config: &LinkArchiveConfig,
+) -> Result<ArchiveReport> {
+    let mut cache = load_archive_cache(cache_dir);
+    let html_files: Vec<_> = WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.path().is_file()
+                && entry.path().extension().and_then(|ext| ext.to_str()) == Some("html")
+        })
+        .map(|entry| entry.path().to_path_buf())
+        .collect();
+
+    let mut external = BTreeSet::new();
+    for path in &html_files {
+        let html = fs::read_to_string(path).io_context("read", path)?;
+        for href in crate::links::extract_hrefs(&html) {
+            let href = crate::xml::unescape(&href);
+            if is_archivable(&href, base_url, config) {
+                external.insert(href);
+            }
+        }
+    }
+
+    let mut cache_changed = false;
+    let mut warnings = Vec::new();
+    for url in external {
+        if cache.contains_key(&url) {
+            continue;
+        }
+        match lookup(&url) {
+            None => {}
+            Some(Ok(snapshot)) => {
+                cache.insert(url, snapshot);
+                cache_changed = true;
+            }
+            Some(Err(error)) => warnings.push(ArchiveWarning { url, error }),
+        }
+    }
+    if cache_changed {
+        save_archive_cache(cache_dir, &cache)?;
+    }
+
+    let snapshots: HashMap<String, String> = cache
+        .into_iter()
+        .filter_map(|(url, snapshot)| snapshot.map(|snapshot| (url, snapshot)))
+        .collect();
+
+    let mut annotated = 0;
+    for path in &html_files {
+        let html = fs::read_to_string(path).io_context("read", path)?;
+        let (rewritten, count) = annotate_html(&html, &snapshots, config.mode);
+        if count > 0 {
+            fs::write(path, rewritten).io_context("write", path)?;
+            annotated += count;
+        }
+    }
+    Ok(ArchiveReport {
+        annotated,
+        warnings,
+    })
+}
+
+fn is_archivable(href: &str, base_url: &str, config: &LinkArchiveConfig) -> bool {
+    if !(href.starts_with("http://") || href.starts_with("https://")) {
+        return false;
+    }
+    let base_url = base_url.trim_end_matches('/');
+    if !base_url.is_empty() && href.starts_with(base_url) {
+        return false;
+    }
+    let host = href
+        .split("://")
+        .nth(1)
+        .and_then(|rest| rest.split(['/', '?', '#']).next())
+        .unwrap_or("");
+    !config
+        .ignore_hosts
+        .iter()
+        .any(|ignored| host == ignored || host.ends_with(&format!(".{ignored}")))
+}
+
+/// Rewrites `<a href="...">` tags whose target appears in `snapshots`.
+/// Returns the new HTML and the number of links annotated.
+pub fn annotate_html(
+    html: &str,
+    snapshots: &HashMap<String, String>,
+    mode: ArchiveMode,
+) -> (String, usize) {
+    let mut output = String::with_capacity(html.len());
+    let mut remaining = html;
+    let mut count = 0;
+
+    while let Some(start) = remaining.find("<a ") {
+        output.push_str(&remaining[..start]);
+        let tag_and_rest = &remaining[start..];
+        let Some(tag_end) = tag_and_rest.find('>') else {
+            output.push_str(tag_and_rest);
+            remaining = "";
+            break;
+        };
+        let tag = &tag_and_rest[..tag_end];
+        let snapshot = href_value(tag)
+            .filter(|_| !tag.contains("data-archive-url"))
+            .and_then(|href| snapshots.get(&crate::xml::unescape(href)));
+
+        let Some(snapshot) = snapshot else {
+            output.push_str(&tag_and_rest[..=tag_end]);
+            remaining = &tag_and_rest[tag_end + 1..];
+            continue;
+        };
+
+        let escaped = crate::xml::escape(snapshot);
+        match mode {
+            ArchiveMode::Attribute => {
+                output.push_str(tag);
+                output.push_str(&format!(" data-archive-url=\"{escaped}\">"));
+                remaining = &tag_and_rest[tag_end + 1..];
+            }
+            ArchiveMode::Footnote => {
+                let Some(close) = tag_and_rest.find("</a>") else {
+                    output.push_str(&tag_and_rest[..=tag_end]);
+                    remaining = &tag_and_rest[tag_end + 1..];
+                    continue;
+                };
+                output.push_str(tag);
+                output.push_str(&format!(" data-archive-url=\"{escaped}\">"));
+                output.push_str(&tag_and_rest[tag_end + 1..close + 4]);
+                output.push_str(&format!(
+                    "<sup class=\"archive-link\"><a href=\"{escaped}\" data-archive-url=\"{escaped}\">archived</a></sup>"
+                ));
+                remaining = &tag_and_rest[close + 4..];
+            }
+        }
+        count += 1;
+    }
+
+    output.push_str(remaining);
+    (output, count)
+}
+
+fn href_value(tag: &str) -> Option<&str> {
+    let start = tag.find("href=\"")? + 6;
+    let end = tag[start..].find('"')?;
+    Some(&tag[start..start + end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshots() -> HashMap<String, String> {
+        HashMap::from([(
+            "https://dead.example.org/post".to_string(),
+            "https://web.archive.org/web/2020/https://dead.example.org/post".to_string(),
+        )])
+    }
+
+    #[test]
+    fn test_annotate_attribute_mode() {
+        let html =
+            r#"<p><a href="https://dead.example.org/post">Old</a> <a href="/local/">Local</a></p>"#;
+        let (rewritten, count) = annotate_html(html, &snapshots(), ArchiveMode::Attribute);
+        assert_eq!(count, 1);
+        assert!(rewritten.contains(
+            r#"<a href="https://dead.example.org/post" data-archive-url="https://web.archive.org/web/2020/https://dead.example.org/post">Old</a>"#
+        ));
+        assert!(rewritten.contains(r#"<a href="/local/">Local</a>"#));
+    }
+
+    #[test]
+    fn test_annotate_footnote_mode() {
+        let html = r#"<a href="https://dead.example.org/post">Old</a> text"#;
+        let (rewritten, count) = annotate_html(html, &snapshots(), ArchiveMode::Footnote);
+        assert_eq!(count, 1);
+        assert!(rewritten.contains(r#"Old</a><sup class="archive-link">"#));
+        assert!(rewritten.ends_with("</sup> text"));
+
+        let (again, second_count) = annotate_html(&rewritten, &snapshots(), ArchiveMode::Footnote);
+        assert_eq!(second_count, 0);
+        assert_eq!(again, rewritten);
+    }
+
+    #[test]
+    fn test_annotate_output_reports_failed_lookups() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let output_dir = dir.path().join("public");
+        let cache_dir = dir.path().join("cache");
+        fs::create_dir_all(&output_dir).unwrap();
+        fs::write(
+            output_dir.join("index.html"),
+            r#"<a href="https://dead.example.org/post">Old</a> <a href="https://down.example.org/">Down</a>"#,
+        )
+        .unwrap();
+        let lookup = |url: &str| {
+            Some(match url {
+                "https://dead.example.org/post" => Ok(snapshots().remove(url)),
+                _ => Err(BambooError::Remote {
+                    url: url.to_string(),
+                    message: "timed out".to_string(),
+                }),
+            })
+        };
+
+        let report = annotate_output_with(
+            lookup,
+            &output_dir,
+            &cache_dir,
+            "https://me.dev",
+            &LinkArchiveConfig::default(),
+        )
+        .unwrap();
+
+        assert_eq!(report.annotated, 1);
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.warnings[0].url, "https://down.example.org/");
+        assert!(
+            report.warnings[0]
+                .to_string()
+                .starts_with("archive lookup for 'https://down.example.org/' failed:")
+        );
+        let cache = load_archive_cache(&cache_dir);
+        assert!(cache.contains_key("https://dead.example.org/post"));
+        assert!(!cache.contains_key("https://down.example.org/"));
+    }
+
+    #[test]
+    fn test_is_archivable() {
+        let config = LinkArchiveConfig {
+            mode: ArchiveMode::Attribute,
+            ignore_hosts: vec!["github.com".to_string()],
+        };
+        assert!(is_archivable(
+            "https://blog.example.org/x",
+            "https://me.dev",
+            &config
+        ));
+        assert!(!is_archivable(
+            "https://me.dev/posts/",
+            "https://me.dev",
+            &config
+        ));
+        assert!(!is_archivable(
+            "https://gist.github.com/a",
+            "https://me.dev",
+            &config
+        ));
+        assert!(!is_archivable("/posts/", "https://me.dev", &config));
+    }
+}
@@ -1,11 +1,14 @@
-//! Post-build asset processing: Sass/SCSS compilation, CSS/JS/HTML
-//! minification, and content-hash fingerprinting of static files.
+//! Post-build asset processing: Sass/SCSS compilation, CSS bundling,
+//! CSS/JS/HTML minification, content-hash fingerprinting, and
+//! deduplication of byte-identical static files.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use walkdir::WalkDir;
 
@@ -15,41 +18,328 @@ use crate::error::Result;
 pub struct AssetConfig {
     /// If `true`, CSS/JS/HTML output is minified in place.
     pub minify: bool,
-    /// If `true`, CSS and JS files receive a content-hash suffix and every
-    /// reference to them is rewritten.
+    /// If `true`, CSS, JS, images, fonts, and media files receive a
+    /// content-hash suffix and every reference to them is rewritten.
     pub fingerprint: bool,
+    /// Globs of output paths that keep their names when fingerprinting.
+    pub fingerprint_exclude: Vec<String>,
+    /// Globs of output paths [`prepare_assets`] leaves for
+    /// [`fingerprint_deferred`], because a later pipeline reads them by
+    /// name (raster images, before [`crate::images`] runs).
+    pub fingerprint_deferred: Vec<String>,
+    /// Globs of output paths left untouched by minification and CSS
+    /// compilation.
+    pub minify_exclude: Vec<String>,
     /// Site base URL, needed to rewrite references during fingerprinting.
     pub base_url: String,
     /// Additional directories Sass/SCSS imports can resolve against.
     pub sass_load_paths: Vec<std::path::PathBuf>,
+    /// How byte-identical static files are deduplicated, if at all.
+    pub dedupe: Option<DedupeMode>,
+    /// Optional `[assets.css]` settings. When present, every CSS file is
+    /// compiled for its browser targets even if `minify` is off.
+    pub css: Option<CssConfig>,
+    /// Optional `[assets.js]` settings. When present, entry scripts are
+    /// bundled with their imports before minification.
+    pub js: Option<crate::bundler::JsConfig>,
+    /// If `true`, every minified CSS/JS file gets a `.map` next to it.
+    pub source_maps: bool,
+    /// Stylesheets concatenated into one file each. See
+    /// [`AssetsConfig::css_bundles`].
+    pub css_bundles: BTreeMap<String, Vec<String>>,
+    /// How HTML is minified when `minify` is on.
+    pub html: HtmlConfig,
+}
+
+/// The `[assets]` table in `bamboo.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssetsConfig {
+    /// Optional `[assets.css]` table. See [`CssConfig`].
+    #[serde(default)]
+    pub css: Option<CssConfig>,
+    /// Optional `[assets.js]` table. See [`crate::bundler::JsConfig`].
+    #[serde(default)]
+    pub js: Option<crate::bundler::JsConfig>,
+    /// Write a source map for every minified CSS and JS file. See
+    /// [`crate::source_maps`].
+    #[serde(default)]
+    pub source_maps: bool,
+    /// Write [`ASSET_MANIFEST_FILE`], mapping each fingerprinted asset's
+    /// original path to its hashed one.
+    #[serde(default)]
+    pub manifest: bool,
+    /// Globs of output paths (`images/og/*`, `**/*.svg`) that keep their
+    /// names when `fingerprint = true`.
+    #[serde(default)]
+    pub fingerprint_exclude: Vec<String>,
+    /// Globs of output paths (`vendor/**`, `**/*.min.js`) that minification
+    /// and CSS compilation leave byte-for-byte as copied.
+    #[serde(default)]
+    pub minify_exclude: Vec<String>,
+    /// The `[assets.css_bundles]` table: each key is a bundle's output path
+    /// (`"css/bundle.css"`), each value the stylesheets concatenated into
+    /// it, in order (`["css/theme.css", "css/site.css"]`). Paths are
+    /// relative to the output root, so theme and site files both resolve.
+    #[serde(default)]
+    pub css_bundles: BTreeMap<String, Vec<String>>,
+    /// The `[assets.html]` table. See [`HtmlConfig`].
+    #[serde(default)]
+    pub html: HtmlConfig,
+}
+
+/// Output path of the asset manifest.
+pub const ASSET_MANIFEST_FILE: &str = "asset-manifest.json";
+
+/// The `[assets.css]` table: how CSS output is compiled by lightningcss.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CssConfig {
+    /// Browsers to compile for, as `"<browser> <version>"` entries such as
+    /// `"safari 13"` or `"chrome 80"`. Syntax those browsers lack is
+    /// lowered and vendor prefixes they need are added. Empty targets the
+    /// latest browsers, leaving modern syntax alone.
+    #[serde(default)]
+    pub targets: Vec<String>,
+    /// Parse CSS nesting, flattening it when the targets need it. Defaults
+    /// to `true`.
+    #[serde(default = "default_true")]
+    pub nesting: bool,
+    /// Resolve `@custom-media` definitions into the media queries that use
+    /// them. Defaults to `true`.
+    #[serde(default = "default_true")]
+    pub custom_media: bool,
+}
+
+impl Default for CssConfig {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            nesting: true,
+            custom_media: true,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The `[assets.html]` table: how `minify = true` treats HTML. `<pre>` and
+/// `<textarea>` whitespace is always kept.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HtmlConfig {
+    /// Minify CSS in `<style>` elements and `style` attributes. Defaults
+    /// to `true`.
+    #[serde(default = "default_true")]
+    pub minify_css: bool,
+    /// Minify JavaScript in `<script>` elements. Defaults to `true`.
+    #[serde(default = "default_true")]
+    pub minify_js: bool,
+    /// Keep closing tags HTML allows leaving out. Defaults to `true`.
+    #[serde(default = "default_true")]
+    pub keep_closing_tags: bool,
+    /// Keep every comment.
+    #[serde(default)]
+    pub keep_comments: bool,
+    /// Globs matched against each comment's trimmed text
+    /// (`"google_ad_section_*"`); matching comments are kept.
+    #[serde(default)]
+    pub preserve_comments: Vec<String>,
+    /// Elements (`"code"`, `"x-poem"`) whose content is copied through
+    /// exactly as rendered.
+    #[serde(default)]
+    pub preserve_whitespace: Vec<String>,
+}
+
+impl Default for HtmlConfig {
+    fn default() -> Self {
+        Self {
+            minify_css: true,
+            minify_js: true,
+            keep_closing_tags: true,
+            keep_comments: false,
+            preserve_comments: Vec::new(),
+            preserve_whitespace: Vec::new(),
+        }
+    }
+}
+
+/// How [`dedupe_assets`] collapses byte-identical files, set with
+/// `dedupe_assets` in `bamboo.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupeMode {
+    /// Replace each duplicate with a hard link to the canonical copy. Every
+    /// URL keeps working; the deploy is smaller wherever the upload or
+    /// archive step preserves hard links.
+    Hardlink,
+    /// Delete each duplicate and point HTML/XML attributes and CSS `url()`s
+    /// at the canonical copy.
+    Rewrite,
 }
 
 /// Compiles Sass, optionally minifies, and optionally fingerprints the files
 /// under `output_dir`. Returns a mapping of original → fingerprinted paths
 /// (empty if fingerprinting is disabled).
 pub fn process_assets(output_dir: &Path, config: &AssetConfig) -> Result<HashMap<String, String>> {
-    compile_sass_files(output_dir, &config.sass_load_paths)?;
+    let path_mapping = prepare_assets(output_dir, config)?;
+    finalize_assets(output_dir, config, &path_mapping)?;
+    Ok(path_mapping)
+}
 
+/// First half of [`process_assets`]: compiles Sass, bundles CSS,
+/// minifies CSS/JS, and fingerprints them, without touching HTML. Run it
+/// before rendering so templates can resolve hashed names through the
+/// returned mapping.
+pub fn prepare_assets(output_dir: &Path, config: &AssetConfig) -> Result<HashMap<String, String>> {
+    compile_sass_files(output_dir, &config.sass_load_paths)?;
+    bundle_css(output_dir, &config.css_bundles, &config.base_url)?;
+    let bundles = match config.js {
+        Some(ref js) => crate::bundler::bundle_js(output_dir, js)?,
+        None => Vec::new(),
+    };
+
+    let minify_exclude = exclude_globs(&config.minify_exclude)?;
+    if config.minify || config.css.is_some() {
+        let css = config.css.clone().unwrap_or_default();
+        process_css_files(
+            output_dir,
+            &css,
+            config.minify,
+            config.source_maps,
+            &minify_exclude,
+        )?;
+    }
     if config.minify {
-        minify_css_files(output_dir)?;
-        minify_js_files(output_dir)?;
+        minify_js_files(output_dir, config.source_maps, &minify_exclude)?;
+    } else if config.js.as_ref().is_some_and(|js| js.minify) {
+        bundles
+            .par_iter()
+            .filter(|path| !is_excluded(&minify_exclude, output_dir, path))
+            .try_for_each(|path| minify_js_file(path, config.source_maps))?;
     }
 
-    let mut path_mapping = HashMap::new();
+    if config.fingerprint {
+        let exclude = [
+            config.fingerprint_exclude.as_slice(),
+            &config.fingerprint_deferred,
+        ]
+        .concat();
+        fingerprint_assets(output_dir, &config.base_url, &exclude)
+    } else {
+        Ok(HashMap::new())
+    }
+}
 
+/// Second half of [`process_assets`]: rewrites any remaining references
+/// to fingerprinted files in rendered HTML/XML, deduplicates identical
+/// files, then minifies HTML.
+pub fn finalize_assets(
+    output_dir: &Path,
+    config: &AssetConfig,
+    path_mapping: &HashMap<String, String>,
+) -> Result<()> {
     if config.fingerprint {
-        path_mapping = fingerprint_assets(output_dir)?;
-        update_html_references(output_dir, &path_mapping, &config.base_url)?;
+        update_html_references(output_dir, path_mapping, &config.base_url)?;
+    }
+
+    if let Some(mode) = config.dedupe {
+        dedupe_assets(output_dir, mode, &config.base_url)?;
     }
 
     if config.minify {
-        minify_html_files(output_dir)?;
+        minify_html_files(
+            output_dir,
+            &config.html,
+            &exclude_globs(&config.minify_exclude)?,
+        )?;
     }
 
-    Ok(path_mapping)
+    Ok(())
+}
+
+/// Fingerprints the `fingerprint_deferred` files [`prepare_assets`] left
+/// alone, once the pipelines that read them by name have run, and adds
+/// them to `path_mapping`. Stylesheets that reference them are rewritten
+/// and, if already fingerprinted, renamed to their new hash. Returns each
+/// stale stylesheet name mapped to its new one, so rendered HTML that
+/// already points at the old name can be updated along with the rest.
+pub fn fingerprint_deferred(
+    output_dir: &Path,
+    config: &AssetConfig,
+    path_mapping: &mut HashMap<String, String>,
+) -> Result<HashMap<String, String>> {
+    if !config.fingerprint || config.fingerprint_deferred.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let exclude = exclude_globs(&config.fingerprint_exclude)?;
+    let deferred = exclude_globs(&config.fingerprint_deferred)?;
+    let mut deferred_mapping = HashMap::new();
+    for extension in FINGERPRINT_EXTENSIONS {
+        for file_path in collect_files_with_extension(output_dir, extension)? {
+            if is_excluded(&deferred, output_dir, &file_path)
+                && !is_excluded(&exclude, output_dir, &file_path)
+            {
+                let (original, fingerprinted) = fingerprint_file(output_dir, &file_path)?;
+                deferred_mapping.insert(original, fingerprinted);
+            }
+        }
+    }
+    if deferred_mapping.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let originals: HashMap<String, String> = path_mapping
+        .iter()
+        .map(|(original, fingerprinted)| (fingerprinted.clone(), original.clone()))
+        .collect();
+    let mut renamed = HashMap::new();
+    for file_path in collect_files_with_extension(output_dir, "css")? {
+        let content = fs::read_to_string(&file_path)?;
+        let relative = relative_path(output_dir, &file_path);
+        let stylesheet_dir = Path::new(&relative).parent().unwrap_or(Path::new(""));
+        let updated =
+            rewrite_css_urls(&content, stylesheet_dir, &config.base_url, |_, resolved| {
+                deferred_mapping.get(resolved).cloned()
+            });
+        if updated == content {
+            continue;
+        }
+        fs::write(&file_path, &updated)?;
+        if let Some(original) = originals.get(&relative) {
+            let original_path = output_dir.join(original);
+            let refingerprinted = fingerprinted_path(&original_path, updated.as_bytes());
+            fs::rename(&file_path, &refingerprinted)?;
+            let refingerprinted = relative_path(output_dir, &refingerprinted);
+            path_mapping.insert(original.clone(), refingerprinted.clone());
+            renamed.insert(relative, refingerprinted);
+        }
+    }
+
+    path_mapping.extend(deferred_mapping);
+    Ok(renamed)
+}
+
+/// Writes [`ASSET_MANIFEST_FILE`] into `output` when `[assets]` sets
+/// `manifest = true`: a JSON object from each original asset path to its
+/// fingerprinted path, both relative to the output directory
+/// (`"css/site.css": "css/site.0123abcd.css"`), sorted by key. Scripts and
+/// deploy tooling can look hashed names up in it instead of guessing them.
+/// The object is empty unless `fingerprint = true`.
+pub fn generate_asset_manifest(
+    site: &crate::types::Site,
+    path_mapping: &HashMap<String, String>,
+    output: &(impl crate::sink::RenderSink + ?Sized),
+) -> Result<()> {
+    if !site.config.assets.manifest {
+        return Ok(());
+    }
+    let sorted: std::collections::BTreeMap<&String, &String> = path_mapping.iter().collect();
+    let json = serde_json::to_string_pretty(&sorted).unwrap_or_default() + "\n";
+    output.write_file(Path::new(ASSET_MANIFEST_FILE), json.as_bytes())
 }
 
-fn collect_files_with_extension(
+pub(crate) fn collect_files_with_extension(
     directory: &Path,
     extension: &str,
 ) -> Result<Vec<std::path::PathBuf>> {
@@ -69,60 +359,121 @@ fn collect_files_with_extension(
     Ok(files)
 }
 
-fn fingerprint_assets(output_dir: &Path) -> Result<HashMap<String, String>> {
+/// Static files [`fingerprint_assets`] hashes besides CSS and JS: images,
+/// fonts, and audio/video. Favicons, documents, and data files keep their
+/// names, since they're often linked from outside the site.
+pub const FINGERPRINT_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "avif", "svg", "woff", "woff2", "ttf", "otf", "eot",
+    "mp4", "webm", "mp3", "ogg", "wav",
+];
+
+/// Hashes static files first and points stylesheet `url()`s at their new
+/// names, then hashes CSS and JS, so a stylesheet's hash changes whenever
+/// an image or font it uses does. Paths matching an `exclude` glob
+/// (relative to `output_dir`) keep their names.
+fn fingerprint_assets(
+    output_dir: &Path,
+    base_url: &str,
+    exclude: &[String],
+) -> Result<HashMap<String, String>> {
+    let exclude = exclude_globs(exclude)?;
+    let included = |file_path: &PathBuf| !is_excluded(&exclude, output_dir, file_path);
+
     let mut path_mapping = HashMap::new();
+    for extension in FINGERPRINT_EXTENSIONS {
+        for file_path in collect_files_with_extension(output_dir, extension)? {
+            if included(&file_path) {
+                let (original, fingerprinted) = fingerprint_file(output_dir, &file_path)?;
+                path_mapping.insert(original, fingerprinted);
+            }
+        }
+    }
+    update_css_references(output_dir, &path_mapping, base_url)?;
 
     let css_files = collect_files_with_extension(output_dir, "css")?;
     let js_files = collect_files_with_extension(output_dir, "js")?;
+    for file_path in css_files.into_iter().chain(js_files) {
+        if included(&file_path) {
+            let (original, fingerprinted) = fingerprint_file(output_dir, &file_path)?;
+            path_mapping.insert(original, fingerprinted);
+        }
+    }
 
-    let all_files = css_files.into_iter().chain(js_files);
-
-    for file_path in all_files {
-        let content = fs::read(&file_path)?;
-
-        let mut hasher = Sha256::new();
-        hasher.update(&content);
-        let hash_result = hasher.finalize();
-        let hash_hex = format!("{:x}", hash_result);
-        let short_hash = &hash_hex[..8];
-
-        let stem = file_path
-            .file_stem()
-            .and_then(|stem| stem.to_str())
-            .unwrap_or("unknown");
-        let extension = file_path
-            .extension()
-            .and_then(|extension| extension.to_str())
-            .unwrap_or("");
-
-        let fingerprinted_name = format!("{}.{}.{}", stem, short_hash, extension);
-        let fingerprinted_path = file_path.with_file_name(&fingerprinted_name);
+    Ok(path_mapping)
+}
 
-        let original_relative = file_path
-            .strip_prefix(output_dir)
-            .unwrap_or(&file_path)
-            .to_string_lossy()
-            .replace('\\', "/");
+/// Compiles `minify_exclude`/`fingerprint_exclude`/`static_ignore` globs.
+/// `*` stays within one directory; `**` crosses directories.
+pub(crate) fn exclude_globs(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern.trim_start_matches('/'))
+            .literal_separator(true)
+            .build()
+            .map_err(|_| crate::error::BambooError::InvalidPath {
+                path: PathBuf::from(pattern),
+            })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|_| crate::error::BambooError::InvalidPath {
+            path: PathBuf::from(patterns.join(", ")),
+        })
+}
 
-        let fingerprinted_relative = fingerprinted_path
-            .strip_prefix(output_dir)
-            .unwrap_or(&fingerprinted_path)
-            .to_string_lossy()
-            .replace('\\', "/");
+pub(crate) fn is_excluded(exclude: &GlobSet, output_dir: &Path, file_path: &Path) -> bool {
+    exclude.is_match(file_path.strip_prefix(output_dir).unwrap_or(file_path))
+}
 
-        fs::rename(&file_path, &fingerprinted_path)?;
+/// Renames `file_path` to `<stem>.<hash>.<extension>`, returning its old
+/// and new paths relative to `output_dir`.
+fn fingerprint_file(output_dir: &Path, file_path: &Path) -> Result<(String, String)> {
+    let content = fs::read(file_path)?;
+    let fingerprinted_path = fingerprinted_path(file_path, &content);
+    fs::rename(file_path, &fingerprinted_path)?;
+
+    Ok((
+        relative_path(output_dir, file_path),
+        relative_path(output_dir, &fingerprinted_path),
+    ))
+}
 
-        path_mapping.insert(original_relative, fingerprinted_relative);
-    }
+/// `file_path` renamed to `<stem>.<hash>.<extension>` for `content`.
+fn fingerprinted_path(file_path: &Path, content: &[u8]) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(content);
+    let hash_result = hasher.finalize();
+    let hash_hex = format!("{:x}", hash_result);
+    let short_hash = &hash_hex[..8];
+
+    let stem = file_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("unknown");
+    let extension = file_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("");
+
+    file_path.with_file_name(format!("{}.{}.{}", stem, short_hash, extension))
+}
 
-    Ok(path_mapping)
+/// `path` relative to `output_dir`, with `/` separators.
+fn relative_path(output_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(output_dir)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
 }
 
 fn html_escape_url(url: &str) -> String {
     url.replace('/', "&#x2F;")
 }
 
-fn update_html_references(
+/// Points HTML/XML attribute values and `srcset` candidates naming a key
+/// of `path_mapping` at the mapped path.
+pub(crate) fn update_html_references(
     output_dir: &Path,
     path_mapping: &HashMap<String, String>,
     base_url: &str,
@@ -167,6 +518,7 @@ fn update_html_references(
                 updated = updated.replace(&search_relative, &replacement_relative);
             }
         }
+        updated = rewrite_srcsets(&updated, path_mapping, base_url);
 
         if updated != content {
             fs::write(&file_path, updated)?;
@@ -176,45 +528,423 @@ fn update_html_references(
     Ok(())
 }
 
-fn minify_css_files(output_dir: &Path) -> Result<()> {
-    use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+/// Points each `srcset` candidate in `html` whose URL (root-relative or
+/// under `base_url`) is a key of `path_mapping` at the mapped path.
+fn rewrite_srcsets(html: &str, path_mapping: &HashMap<String, String>, base_url: &str) -> String {
+    let mut updated = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("srcset=") {
+        let value_start = start + "srcset=".len();
+        let Some(delimiter) = rest[value_start..]
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+        else {
+            updated.push_str(&rest[..value_start]);
+            rest = &rest[value_start..];
+            continue;
+        };
+        let value_start = value_start + 1;
+        let Some(length) = rest[value_start..].find(delimiter) else {
+            break;
+        };
+        updated.push_str(&rest[..value_start]);
+        let candidates: Vec<String> = rest[value_start..value_start + length]
+            .split(',')
+            .map(|candidate| {
+                let trimmed = candidate.trim_start();
+                let url_end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+                let (url, descriptor) = trimmed.split_at(url_end);
+                let (prefix, path) =
+                    match url.strip_prefix(base_url).filter(|_| !base_url.is_empty()) {
+                        Some(path) => (base_url, path),
+                        None => ("", url),
+                    };
+                match path
+                    .strip_prefix('/')
+                    .and_then(|path| path_mapping.get(path))
+                {
+                    Some(fingerprinted) => {
+                        let leading = &candidate[..candidate.len() - trimmed.len()];
+                        format!("{leading}{prefix}/{fingerprinted}{descriptor}")
+                    }
+                    None => candidate.to_string(),
+                }
+            })
+            .collect();
+        updated.push_str(&candidates.join(","));
+        rest = &rest[value_start + length..];
+    }
+    updated.push_str(rest);
+    updated
+}
+
+/// Collapses byte-identical files under `output_dir` (everything but HTML,
+/// XML, and empty files) onto one canonical copy each: the shallowest path,
+/// then the first alphabetically. Returns the number of duplicates removed
+/// or linked.
+pub fn dedupe_assets(output_dir: &Path, mode: DedupeMode, base_url: &str) -> Result<usize> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(output_dir) {
+        let entry = entry.map_err(|error| crate::error::BambooError::WalkDir {
+            path: output_dir.to_path_buf(),
+            message: error.to_string(),
+        })?;
+        let is_page = entry
+            .path()
+            .extension()
+            .is_some_and(|extension| extension == "html" || extension == "xml");
+        if entry.file_type().is_file()
+            && !is_page
+            && entry.metadata().is_ok_and(|metadata| metadata.len() > 0)
+        {
+            files.push(entry.into_path());
+        }
+    }
+
+    let hashed = files
+        .par_iter()
+        .map(|path| {
+            let content = fs::read(path)?;
+            let hash = format!("{:x}", Sha256::digest(&content));
+            Ok((hash, path.clone()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut groups: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for (hash, path) in hashed {
+        groups.entry(hash).or_default().push(path);
+    }
+
+    let relative = |path: &Path| {
+        path.strip_prefix(output_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    };
+    let mut duplicates = HashMap::new();
+    for mut paths in groups.into_values().filter(|paths| paths.len() > 1) {
+        paths.sort_by_key(|path| (path.components().count(), path.clone()));
+        let canonical = &paths[0];
+        for duplicate in &paths[1..] {
+            fs::remove_file(duplicate)?;
+            if mode == DedupeMode::Hardlink && fs::hard_link(canonical, duplicate).is_err() {
+                fs::copy(canonical, duplicate)?;
+            }
+            duplicates.insert(relative(duplicate), relative(canonical));
+        }
+    }
+
+    if mode == DedupeMode::Rewrite {
+        update_html_references(output_dir, &duplicates, base_url)?;
+        update_css_references(output_dir, &duplicates, base_url)?;
+    }
+
+    Ok(duplicates.len())
+}
+
+/// Points every CSS `url()` that resolves to a key of `path_mapping`
+/// (relative to the stylesheet, root-relative, or under `base_url`) at the
+/// mapped path, written as an absolute URL.
+fn update_css_references(
+    output_dir: &Path,
+    path_mapping: &HashMap<String, String>,
+    base_url: &str,
+) -> Result<()> {
+    if path_mapping.is_empty() {
+        return Ok(());
+    }
+
+    for file_path in collect_files_with_extension(output_dir, "css")? {
+        let content = fs::read_to_string(&file_path)?;
+        let stylesheet_dir = file_path
+            .parent()
+            .and_then(|parent| parent.strip_prefix(output_dir).ok())
+            .unwrap_or(Path::new(""));
+
+        let updated = rewrite_css_urls(&content, stylesheet_dir, base_url, |_, resolved| {
+            path_mapping.get(resolved).cloned()
+        });
+        if updated != content {
+            fs::write(&file_path, updated)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewrites the `url()`s in `content`, a stylesheet in `stylesheet_dir`
+/// (relative to the output root). `rewrite` gets each URL's path as
+/// written and resolved against the output root, and returns the output
+/// path to point it at instead, written as an absolute URL under
+/// `base_url`. External URLs are left alone.
+pub(crate) fn rewrite_css_urls(
+    content: &str,
+    stylesheet_dir: &Path,
+    base_url: &str,
+    mut rewrite: impl FnMut(&str, &str) -> Option<String>,
+) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let mut updated = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("url(") {
+        let argument_start = start + "url(".len();
+        let Some(length) = rest[argument_start..].find(')') else {
+            break;
+        };
+        let argument = &rest[argument_start..argument_start + length];
+        let url = argument.trim().trim_matches(|c| c == '"' || c == '\'');
+        let split = url.find(['?', '#']).unwrap_or(url.len());
+        let (path, suffix) = url.split_at(split);
+
+        let resolved =
+            if let Some(path) = path.strip_prefix(base_url).filter(|_| !base_url.is_empty()) {
+                Some(normalize_path(Path::new(""), path))
+            } else if path.contains(':') || path.starts_with("//") {
+                None
+            } else if path.starts_with('/') {
+                Some(normalize_path(Path::new(""), path))
+            } else {
+                Some(normalize_path(stylesheet_dir, path))
+            };
+
+        updated.push_str(&rest[..argument_start]);
+        match resolved.and_then(|resolved| rewrite(path, &resolved)) {
+            Some(target) => updated.push_str(&format!("\"{base_url}/{target}{suffix}\"")),
+            None => updated.push_str(argument),
+        }
+        rest = &rest[argument_start + length..];
+    }
+    updated.push_str(rest);
+    updated
+}
+
+/// Writes each `[assets.css_bundles]` bundle: its stylesheets concatenated
+/// in order, with relative `url()`s made absolute so they still resolve
+/// from the bundle's directory. The sources stay in place for pages that
+/// link them directly; the bundle is minified and fingerprinted like any
+/// other stylesheet.
+fn bundle_css(
+    output_dir: &Path,
+    bundles: &BTreeMap<String, Vec<String>>,
+    base_url: &str,
+) -> Result<()> {
+    for (bundle, sources) in bundles {
+        let bundle_path = output_dir.join(bundle.trim_start_matches('/'));
+        let mut combined = String::new();
+        for source in sources {
+            let source = source.trim_start_matches('/');
+            let source_path = output_dir.join(source);
+            let content = fs::read_to_string(&source_path).map_err(|error| {
+                crate::error::BambooError::CssCompile {
+                    path: bundle_path.clone(),
+                    message: format!("can't read bundle source '{source}': {error}"),
+                }
+            })?;
+            let source_dir = Path::new(source).parent().unwrap_or(Path::new(""));
+            combined.push_str(&format!("/* {source} */\n"));
+            combined.push_str(&rewrite_css_urls(
+                &content,
+                source_dir,
+                base_url,
+                |path, resolved| {
+                    let relative = !path.is_empty() && !path.starts_with('/');
+                    relative.then(|| resolved.to_string())
+                },
+            ));
+            if !combined.ends_with('\n') {
+                combined.push('\n');
+            }
+        }
+        if let Some(parent) = bundle_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&bundle_path, combined)?;
+    }
+    Ok(())
+}
+
+/// Joins `path` onto `directory` (both relative to the output root),
+/// resolving `.` and `..`, as a `/`-separated string.
+pub(crate) fn normalize_path(directory: &Path, path: &str) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in directory.join(path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+/// Parses `[assets.css]` targets (`"safari 13.1"`) into lightningcss
+/// browser versions, or `None` when the list is empty.
+pub fn css_browsers(targets: &[String]) -> Result<Option<lightningcss::targets::Browsers>> {
+    if targets.is_empty() {
+        return Ok(None);
+    }
+    let invalid = |target: &str, message: &str| crate::error::BambooError::CssCompile {
+        path: PathBuf::from("bamboo.toml"),
+        message: format!("invalid [assets.css] target '{}': {}", target, message),
+    };
+    let mut browsers = lightningcss::targets::Browsers::default();
+    for target in targets {
+        let (name, version) = target
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| invalid(target, "expected \"<browser> <version>\""))?;
+        let mut parts = version.trim().split('.');
+        let mut component = || -> Result<u32> {
+            match parts.next() {
+                Some(part) => part
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|value| *value < 256)
+                    .ok_or_else(|| invalid(target, "version must be numeric")),
+                None => Ok(0),
+            }
+        };
+        let encoded = (component()? << 16) | (component()? << 8) | component()?;
+        let slot = match name.to_ascii_lowercase().as_str() {
+            "android" => &mut browsers.android,
+            "chrome" => &mut browsers.chrome,
+            "edge" => &mut browsers.edge,
+            "firefox" => &mut browsers.firefox,
+            "ie" => &mut browsers.ie,
+            "ios_saf" | "ios" => &mut browsers.ios_saf,
+            "opera" => &mut browsers.opera,
+            "safari" => &mut browsers.safari,
+            "samsung" => &mut browsers.samsung,
+            _ => return Err(invalid(target, "unknown browser")),
+        };
+        // Several entries for one browser keep the oldest version.
+        *slot = Some(slot.map_or(encoded, |existing| existing.min(encoded)));
+    }
+    Ok(Some(browsers))
+}
+
+/// Compiles every CSS file under `output_dir` with lightningcss: nesting
+/// and `@custom-media` are resolved, syntax is lowered and prefixed for
+/// `css.targets`, and whitespace is stripped when `minify` is set, with a
+/// source map alongside if `source_maps` is too.
+fn process_css_files(
+    output_dir: &Path,
+    css: &CssConfig,
+    minify: bool,
+    source_maps: bool,
+    exclude: &GlobSet,
+) -> Result<()> {
+    use lightningcss::stylesheet::{
+        MinifyOptions, ParserFlags, ParserOptions, PrinterOptions, StyleSheet,
+    };
+    use lightningcss::targets::{Features, Targets};
+
+    let mut targets = Targets {
+        browsers: css_browsers(&css.targets)?,
+        ..Targets::default()
+    };
+    let mut flags = ParserFlags::empty();
+    if css.nesting {
+        flags |= ParserFlags::NESTING;
+    }
+    if css.custom_media {
+        flags |= ParserFlags::CUSTOM_MEDIA;
+        // No browser ships `@custom-media`, so always inline it.
+        targets.include |= Features::CustomMediaQueries;
+    }
+
     let css_files = collect_files_with_extension(output_dir, "css")?;
     css_files
         .par_iter()
+        .filter(|file_path| !is_excluded(exclude, output_dir, file_path))
         .try_for_each(|file_path| -> Result<()> {
+            let compile_error = |message: String| crate::error::BambooError::CssCompile {
+                path: file_path.clone(),
+                message,
+            };
             let source = fs::read_to_string(file_path)?;
-            let mut stylesheet = StyleSheet::parse(&source, ParserOptions::default())
-                .map_err(|error| std::io::Error::other(error.to_string()))?;
+            let file_name = file_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let options = ParserOptions {
+                filename: file_name.clone(),
+                flags: flags.clone(),
+                ..ParserOptions::default()
+            };
+            let mut stylesheet = StyleSheet::parse(&source, options)
+                .map_err(|error| compile_error(error.to_string()))?;
             stylesheet
-                .minify(MinifyOptions::default())
-                .map_err(|error| std::io::Error::other(error.to_string()))?;
+                .minify(MinifyOptions {
+                    targets,
+                    ..MinifyOptions::default()
+                })
+                .map_err(|error| compile_error(error.to_string()))?;
+            let mut source_map = (minify && source_maps).then(|| {
+                let mut map = parcel_sourcemap::SourceMap::new("/");
+                map.add_source(&file_name);
+                map
+            });
             let result = stylesheet
                 .to_css(PrinterOptions {
-                    minify: true,
-                    ..Default::default()
+                    minify,
+                    targets,
+                    source_map: source_map.as_mut(),
+                    ..PrinterOptions::default()
                 })
-                .map_err(|error| std::io::Error::other(error.to_string()))?;
-            fs::write(file_path, result.code)?;
+                .map_err(|error| compile_error(error.to_string()))?;
+            let mut code = result.code;
+            if let Some(mut map) = source_map {
+                let json = crate::source_maps::to_json(&mut map, file_path, &source)?;
+                fs::write(
+                    file_path.with_file_name(crate::source_maps::map_file_name(file_path)),
+                    json,
+                )?;
+                code.push_str(&crate::source_maps::map_comment(file_path));
+            }
+            fs::write(file_path, code)?;
             Ok(())
         })
 }
 
-fn minify_js_files(output_dir: &Path) -> Result<()> {
+fn minify_js_files(output_dir: &Path, source_maps: bool, exclude: &GlobSet) -> Result<()> {
     let js_files = collect_files_with_extension(output_dir, "js")?;
-    js_files.par_iter().try_for_each(|file_path| -> Result<()> {
-        let session = minify_js::Session::new();
-        let source = fs::read(file_path)?;
-        let mut output = Vec::new();
-        minify_js::minify(
-            &session,
-            minify_js::TopLevelMode::Global,
-            &source,
-            &mut output,
-        )
-        .map_err(|error| std::io::Error::other(format!("{}: {}", file_path.display(), error)))?;
-        fs::write(file_path, output)?;
-        Ok(())
-    })
+    js_files
+        .par_iter()
+        .filter(|file_path| !is_excluded(exclude, output_dir, file_path))
+        .try_for_each(|file_path| minify_js_file(file_path, source_maps))
+}
+
+fn minify_js_file(file_path: &Path, source_maps: bool) -> Result<()> {
+    let session = minify_js::Session::new();
+    let source = fs::read(file_path)?;
+    let mut output = Vec::new();
+    minify_js::minify(
+        &session,
+        minify_js::TopLevelMode::Global,
+        &source,
+        &mut output,
+    )
+    .map_err(|error| std::io::Error::other(format!("{}: {}", file_path.display(), error)))?;
+    if source_maps {
+        let original = String::from_utf8_lossy(&source);
+        let minified = String::from_utf8_lossy(&output).into_owned();
+        let name = file_path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let mut map = crate::source_maps::minified_js_map(&name, &original, &minified);
+        let json = crate::source_maps::to_json(&mut map, file_path, &original)?;
+        fs::write(
+            file_path.with_file_name(crate::source_maps::map_file_name(file_path)),
+            json,
+        )?;
+        output.extend_from_slice(crate::source_maps::map_comment(file_path).as_bytes());
+    }
+    fs::write(file_path, output)?;
+    Ok(())
 }
 
 fn compile_sass_files(output_dir: &Path, load_paths: &[std::path::PathBuf]) -> Result<()> {
@@ -289,24 +1019,164 @@ fn compile_sass_files(output_dir: &Path, load_paths: &[std::path::PathBuf]) -> R
     Ok(())
 }
 
-fn minify_html_files(output_dir: &Path) -> Result<()> {
+fn minify_html_files(output_dir: &Path, html: &HtmlConfig, exclude: &GlobSet) -> Result<()> {
     let html_files = collect_files_with_extension(output_dir, "html")?;
 
     let mut cfg = minify_html::Cfg::new();
-    cfg.minify_css = true;
-    cfg.minify_js = true;
-    cfg.keep_closing_tags = true;
+    cfg.minify_css = html.minify_css;
+    cfg.minify_js = html.minify_js;
+    cfg.keep_closing_tags = html.keep_closing_tags;
+    cfg.keep_comments = html.keep_comments;
+
+    let mut comments = GlobSetBuilder::new();
+    for pattern in &html.preserve_comments {
+        let glob = GlobBuilder::new(pattern).build().map_err(|error| {
+            crate::error::BambooError::TomlParse {
+                path: PathBuf::from("bamboo.toml"),
+                message: format!(
+                    "invalid [assets.html] preserve_comments pattern '{pattern}': {error}"
+                ),
+            }
+        })?;
+        comments.add(glob);
+    }
+    let comments = comments
+        .build()
+        .map_err(|error| crate::error::BambooError::TomlParse {
+            path: PathBuf::from("bamboo.toml"),
+            message: error.to_string(),
+        })?;
+    let protect =
+        !html.keep_comments && !comments.is_empty() || !html.preserve_whitespace.is_empty();
 
     html_files
         .par_iter()
+        .filter(|file_path| !is_excluded(exclude, output_dir, file_path))
         .try_for_each(|file_path| -> Result<()> {
-            let content = fs::read(file_path)?;
-            let minified = minify_html::minify(&content, &cfg);
+            let content = fs::read_to_string(file_path)?;
+            let minified = if protect {
+                let (protected, saved) =
+                    protect_html(&content, &comments, &html.preserve_whitespace);
+                let minified =
+                    String::from_utf8_lossy(&minify_html::minify(protected.as_bytes(), &cfg))
+                        .into_owned();
+                restore_html(&minified, &saved)
+            } else {
+                String::from_utf8_lossy(&minify_html::minify(content.as_bytes(), &cfg)).into_owned()
+            };
             fs::write(file_path, minified)?;
             Ok(())
         })
 }
 
+const PLACEHOLDER_START: char = '\u{E000}';
+const PLACEHOLDER_END: char = '\u{E001}';
+
+/// Swaps the comments matching `comments` and the content of the
+/// `elements` for placeholders the minifier passes through untouched,
+/// returning the swapped-out text for [`restore_html`]. `<script>` and
+/// `<style>` content is never scanned.
+fn protect_html(html: &str, comments: &GlobSet, elements: &[String]) -> (String, Vec<String>) {
+    let lower = html.to_ascii_lowercase();
+    let elements: Vec<String> = elements
+        .iter()
+        .map(|name| name.to_ascii_lowercase())
+        .collect();
+    let mut protected = String::with_capacity(html.len());
+    let mut saved = Vec::new();
+    let mut placeholder = |protected: &mut String, text: &str| {
+        protected.push_str(&format!(
+            "{PLACEHOLDER_START}{}{PLACEHOLDER_END}",
+            saved.len()
+        ));
+        saved.push(text.to_string());
+    };
+
+    let tag_at = |position: usize, name: &str| {
+        lower[position..].starts_with(name)
+            && lower[position + name.len()..]
+                .starts_with(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/')
+    };
+
+    let mut position = 0;
+    while let Some(offset) = html[position..].find('<') {
+        let start = position + offset;
+        protected.push_str(&html[position..start]);
+
+        if html[start..].starts_with("<!--") {
+            let end = html[start..]
+                .find("-->")
+                .map_or(html.len(), |end| start + end + "-->".len());
+            let comment = &html[start..end];
+            let text = comment
+                .trim_start_matches("<!--")
+                .trim_end_matches("-->")
+                .trim();
+            if comments.is_match(text) {
+                placeholder(&mut protected, comment);
+            } else {
+                protected.push_str(comment);
+            }
+            position = end;
+            continue;
+        }
+
+        let raw = ["script", "style"]
+            .into_iter()
+            .find(|name| tag_at(start + 1, name));
+        let preserved = elements.iter().find(|name| tag_at(start + 1, name));
+        let Some(name) = raw.or(preserved.map(String::as_str)) else {
+            protected.push('<');
+            position = start + 1;
+            continue;
+        };
+        let Some(open_end) = html[start..].find('>').map(|end| start + end + 1) else {
+            protected.push_str(&html[start..]);
+            position = html.len();
+            break;
+        };
+        let close = lower[open_end..]
+            .find(&format!("</{name}"))
+            .map_or(html.len(), |close| open_end + close);
+        protected.push_str(&html[start..open_end]);
+        if preserved.is_some() && raw.is_none() && close > open_end {
+            placeholder(&mut protected, &html[open_end..close]);
+        } else {
+            protected.push_str(&html[open_end..close]);
+        }
+        position = close;
+    }
+    protected.push_str(&html[position..]);
+
+    (protected, saved)
+}
+
+/// Puts the text [`protect_html`] swapped out back in place.
+fn restore_html(html: &str, saved: &[String]) -> String {
+    let mut restored = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(PLACEHOLDER_START) {
+        restored.push_str(&rest[..start]);
+        let after = &rest[start + PLACEHOLDER_START.len_utf8()..];
+        let text = after.find(PLACEHOLDER_END).and_then(|end| {
+            let index: usize = after[..end].parse().ok()?;
+            Some((saved.get(index)?, end))
+        });
+        match text {
+            Some((text, end)) => {
+                restored.push_str(text);
+                rest = &after[end + PLACEHOLDER_END.len_utf8()..];
+            }
+            None => {
+                restored.push(PLACEHOLDER_START);
+                rest = after;
+            }
+        }
+    }
+    restored.push_str(rest);
+    restored
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -317,7 +1187,7 @@ mod tests {
         let dir = tempfile::TempDir::new().unwrap();
         fs::write(dir.path().join("style.css"), "body { color: red; }").unwrap();
 
-        let mapping = fingerprint_assets(dir.path()).unwrap();
+        let mapping = fingerprint_assets(dir.path(), "https://example.com", &[]).unwrap();
         assert_eq!(mapping.len(), 1);
 
         let (original, fingerprinted) = mapping.iter().next().unwrap();
@@ -327,6 +1197,58 @@ mod tests {
         assert!(fingerprinted.len() > "style..css".len());
     }
 
+    #[test]
+    fn test_fingerprint_static_files_before_stylesheets() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("css")).unwrap();
+        fs::create_dir_all(dir.path().join("images/og")).unwrap();
+        fs::create_dir_all(dir.path().join("fonts")).unwrap();
+        fs::write(dir.path().join("images/bg.png"), b"png bytes").unwrap();
+        fs::write(dir.path().join("images/og/card.png"), b"card").unwrap();
+        fs::write(dir.path().join("fonts/body.woff2"), b"woff2 bytes").unwrap();
+        fs::write(dir.path().join("favicon.ico"), b"icon").unwrap();
+        fs::write(
+            dir.path().join("css/site.css"),
+            "body{background:url(../images/bg.png)}@font-face{src:url('/fonts/body.woff2')}",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("index.html"),
+            r#"<img src="/images/bg.png"><meta content="https://example.com/images/og/card.png">"#,
+        )
+        .unwrap();
+
+        let mapping = fingerprint_assets(
+            dir.path(),
+            "https://example.com",
+            &["images/og/*".to_string()],
+        )
+        .unwrap();
+        update_html_references(dir.path(), &mapping, "https://example.com").unwrap();
+
+        let image = &mapping["images/bg.png"];
+        let font = &mapping["fonts/body.woff2"];
+        assert!(image.starts_with("images/bg.") && image.ends_with(".png"));
+        assert!(dir.path().join(image).exists());
+        assert!(!mapping.contains_key("images/og/card.png"));
+        assert!(!mapping.contains_key("favicon.ico"));
+        assert!(dir.path().join("images/og/card.png").exists());
+
+        let stylesheet = fs::read_to_string(dir.path().join(&mapping["css/site.css"])).unwrap();
+        assert_eq!(
+            stylesheet,
+            format!(
+                "body{{background:url(\"https://example.com/{image}\")}}@font-face{{src:url(\"https://example.com/{font}\")}}"
+            )
+        );
+        let hash = Sha256::digest(stylesheet.as_bytes());
+        assert!(mapping["css/site.css"].contains(&format!("{:x}", hash)[..8]));
+
+        let html = fs::read_to_string(dir.path().join("index.html")).unwrap();
+        assert!(html.contains(&format!("src=\"/{image}\"")));
+        assert!(html.contains("images/og/card.png"));
+    }
+
     #[test]
     fn test_html_reference_rewriting() {
         let dir = tempfile::TempDir::new().unwrap();
@@ -337,7 +1259,7 @@ mod tests {
         )
         .unwrap();
 
-        let mapping = fingerprint_assets(dir.path()).unwrap();
+        let mapping = fingerprint_assets(dir.path(), "https://example.com", &[]).unwrap();
         update_html_references(dir.path(), &mapping, "https://example.com").unwrap();
 
         let html = fs::read_to_string(dir.path().join("index.html")).unwrap();
@@ -346,6 +1268,151 @@ mod tests {
         assert!(html.contains(fingerprinted.as_str()));
     }
 
+    #[test]
+    fn test_fingerprint_deferred_images_and_their_stylesheets() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("css")).unwrap();
+        fs::create_dir_all(dir.path().join("images")).unwrap();
+        fs::write(dir.path().join("images/photo.png"), b"png bytes").unwrap();
+        fs::write(
+            dir.path().join("css/site.css"),
+            "a{background:url(../images/photo.png)}",
+        )
+        .unwrap();
+        let config = AssetConfig {
+            minify: false,
+            fingerprint: true,
+            fingerprint_exclude: Vec::new(),
+            fingerprint_deferred: vec!["**/*.{png,webp}".to_string()],
+            minify_exclude: Vec::new(),
+            base_url: "https://example.com".to_string(),
+            sass_load_paths: Vec::new(),
+            dedupe: None,
+            css: None,
+            js: None,
+            source_maps: false,
+            css_bundles: BTreeMap::new(),
+            html: HtmlConfig::default(),
+        };
+
+        let mut mapping = prepare_assets(dir.path(), &config).unwrap();
+        assert!(dir.path().join("images/photo.png").exists());
+        let stale_stylesheet = mapping["css/site.css"].clone();
+
+        // What the image pipeline and templates leave behind.
+        fs::write(dir.path().join("images/photo-640w.webp"), b"webp bytes").unwrap();
+        fs::write(
+            dir.path().join("index.html"),
+            format!(
+                r#"<link href="/{stale_stylesheet}"><img srcset="/images/photo-640w.webp 640w, /images/photo.png 1280w" src="/images/photo.png">"#
+            ),
+        )
+        .unwrap();
+
+        let renamed = fingerprint_deferred(dir.path(), &config, &mut mapping).unwrap();
+        let photo = &mapping["images/photo.png"];
+        let variant = &mapping["images/photo-640w.webp"];
+        let stylesheet = &mapping["css/site.css"];
+        assert!(photo.starts_with("images/photo.") && dir.path().join(photo).exists());
+        assert!(dir.path().join(variant).exists());
+        assert_ne!(stylesheet, &stale_stylesheet);
+        assert_eq!(
+            renamed,
+            HashMap::from([(stale_stylesheet, stylesheet.clone())])
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join(stylesheet)).unwrap(),
+            format!("a{{background:url(\"https://example.com/{photo}\")}}")
+        );
+
+        let mut references = mapping.clone();
+        references.extend(renamed);
+        update_html_references(dir.path(), &references, "https://example.com").unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("index.html")).unwrap(),
+            format!(
+                r#"<link href="/{stylesheet}"><img srcset="/{variant} 640w, /{photo} 1280w" src="/{photo}">"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_generate_asset_manifest() {
+        use crate::sink::{MemorySink, RenderSink};
+        let mut site = crate::template_test::sample_site().unwrap();
+        let path_mapping = HashMap::from([
+            ("js/app.js".to_string(), "js/app.89abcdef.js".to_string()),
+            (
+                "css/site.css".to_string(),
+                "css/site.01234567.css".to_string(),
+            ),
+        ]);
+
+        let sink = MemorySink::new();
+        generate_asset_manifest(&site, &path_mapping, &sink).unwrap();
+        assert!(sink.read_file(Path::new(ASSET_MANIFEST_FILE)).is_none());
+
+        site.config.assets.manifest = true;
+        generate_asset_manifest(&site, &path_mapping, &sink).unwrap();
+        let json =
+            String::from_utf8(sink.read_file(Path::new(ASSET_MANIFEST_FILE)).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            "{\n  \"css/site.css\": \"css/site.01234567.css\",\n  \"js/app.js\": \"js/app.89abcdef.js\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_dedupe_assets_hardlink() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("theme/fonts")).unwrap();
+        fs::write(dir.path().join("icon.svg"), "<svg/>").unwrap();
+        fs::write(dir.path().join("theme/fonts/icon.svg"), "<svg/>").unwrap();
+        fs::write(dir.path().join("other.svg"), "<svg></svg>").unwrap();
+
+        let removed =
+            dedupe_assets(dir.path(), DedupeMode::Hardlink, "https://example.com").unwrap();
+
+        assert_eq!(removed, 1);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("theme/fonts/icon.svg")).unwrap(),
+            "<svg/>"
+        );
+        assert!(dir.path().join("other.svg").exists());
+    }
+
+    #[test]
+    fn test_dedupe_assets_rewrite() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("fonts")).unwrap();
+        fs::create_dir_all(dir.path().join("theme/css")).unwrap();
+        fs::create_dir_all(dir.path().join("theme/fonts")).unwrap();
+        fs::write(dir.path().join("fonts/a.woff2"), "font").unwrap();
+        fs::write(dir.path().join("theme/fonts/a.woff2"), "font").unwrap();
+        fs::write(
+            dir.path().join("theme/css/site.css"),
+            "@font-face{src:url(../fonts/a.woff2?v=1) format(\"woff2\")}",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("index.html"),
+            r#"<link rel="preload" href="/theme/fonts/a.woff2">"#,
+        )
+        .unwrap();
+
+        dedupe_assets(dir.path(), DedupeMode::Rewrite, "https://example.com").unwrap();
+
+        assert!(!dir.path().join("theme/fonts/a.woff2").exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("theme/css/site.css")).unwrap(),
+            "@font-face{src:url(\"https://example.com/fonts/a.woff2?v=1\") format(\"woff2\")}"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("index.html")).unwrap(),
+            r#"<link rel="preload" href="/fonts/a.woff2">"#
+        );
+    }
+
     #[test]
     fn test_css_minification() {
         let dir = tempfile::TempDir::new().unwrap();
@@ -355,13 +1422,101 @@ mod tests {
         )
         .unwrap();
 
-        minify_css_files(dir.path()).unwrap();
+        process_css_files(
+            dir.path(),
+            &CssConfig::default(),
+            true,
+            false,
+            &GlobSet::empty(),
+        )
+        .unwrap();
 
         let minified = fs::read_to_string(dir.path().join("test.css")).unwrap();
         assert!(!minified.contains('\n'));
         assert!(minified.contains("color"));
     }
 
+    #[test]
+    fn test_css_nesting_flattened_for_targets() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("nested.css"),
+            ".card {\n  color: red;\n  & .title {\n    color: blue;\n  }\n}\n",
+        )
+        .unwrap();
+        let css = CssConfig {
+            targets: vec!["safari 13".to_string()],
+            ..CssConfig::default()
+        };
+
+        process_css_files(dir.path(), &css, true, false, &GlobSet::empty()).unwrap();
+
+        let output = fs::read_to_string(dir.path().join("nested.css")).unwrap();
+        assert!(output.contains(".card .title{color:#00f}"), "{}", output);
+        assert!(!output.contains('&'));
+    }
+
+    #[test]
+    fn test_css_vendor_prefixes_for_old_targets() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("style.css"),
+            ".box { user-select: none; }\n",
+        )
+        .unwrap();
+        let css = CssConfig {
+            targets: vec!["safari 12".to_string(), "firefox 60".to_string()],
+            ..CssConfig::default()
+        };
+
+        process_css_files(dir.path(), &css, false, false, &GlobSet::empty()).unwrap();
+
+        let output = fs::read_to_string(dir.path().join("style.css")).unwrap();
+        assert!(output.contains("-webkit-user-select: none"), "{}", output);
+        assert!(output.contains("-moz-user-select: none"), "{}", output);
+        assert!(output.contains('\n'), "minify is off: {}", output);
+    }
+
+    #[test]
+    fn test_css_custom_media_resolved() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("media.css"),
+            "@custom-media --narrow (max-width: 640px);\n@media (--narrow) { body { margin: 0; } }\n",
+        )
+        .unwrap();
+
+        process_css_files(
+            dir.path(),
+            &CssConfig::default(),
+            true,
+            false,
+            &GlobSet::empty(),
+        )
+        .unwrap();
+
+        let output = fs::read_to_string(dir.path().join("media.css")).unwrap();
+        assert!(!output.contains("--narrow"), "{}", output);
+        assert!(output.contains("640px){body{margin:0}}"), "{}", output);
+    }
+
+    #[test]
+    fn test_css_browsers_parsing() {
+        assert!(css_browsers(&[]).unwrap().is_none());
+        let browsers = css_browsers(&[
+            "safari 13.1".to_string(),
+            "Chrome 80".to_string(),
+            "chrome 70".to_string(),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(browsers.safari, Some((13 << 16) | (1 << 8)));
+        assert_eq!(browsers.chrome, Some(70 << 16));
+        assert!(css_browsers(&["netscape 4".to_string()]).is_err());
+        assert!(css_browsers(&["safari".to_string()]).is_err());
+        assert!(css_browsers(&["safari x".to_string()]).is_err());
+    }
+
     #[test]
     fn test_js_minification() {
         let dir = tempfile::TempDir::new().unwrap();
@@ -371,12 +1526,223 @@ mod tests {
         )
         .unwrap();
 
-        minify_js_files(dir.path()).unwrap();
+        minify_js_files(dir.path(), false, &GlobSet::empty()).unwrap();
 
         let minified = fs::read_to_string(dir.path().join("test.js")).unwrap();
         assert!(minified.len() < "function hello() {\n  var x = 1;\n  return x;\n}\n".len());
     }
 
+    #[test]
+    fn test_minify_exclude_leaves_matching_files_alone() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("vendor")).unwrap();
+        let script = "function hello() {\n  var x = 1;\n  return x;\n}\n";
+        let stylesheet = "body {\n  color: red;\n}\n";
+        let page = "<p>\n  Hello\n</p>\n";
+        for name in ["app.js", "vendor/lib.js", "app.css", "vendor/lib.css"] {
+            let content = if name.ends_with(".js") {
+                script
+            } else {
+                stylesheet
+            };
+            fs::write(dir.path().join(name), content).unwrap();
+        }
+        fs::write(dir.path().join("index.html"), page).unwrap();
+        fs::write(dir.path().join("vendor/embed.html"), page).unwrap();
+        let config = AssetConfig {
+            minify: true,
+            fingerprint: true,
+            fingerprint_exclude: vec!["vendor/lib.*".to_string()],
+            fingerprint_deferred: Vec::new(),
+            minify_exclude: vec!["vendor/**".to_string()],
+            base_url: "https://example.com".to_string(),
+            sass_load_paths: Vec::new(),
+            dedupe: None,
+            css: None,
+            js: None,
+            source_maps: false,
+            css_bundles: BTreeMap::new(),
+            html: HtmlConfig::default(),
+        };
+
+        let mapping = process_assets(dir.path(), &config).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("vendor/lib.js")).unwrap(),
+            script
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("vendor/lib.css")).unwrap(),
+            stylesheet
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("vendor/embed.html")).unwrap(),
+            page
+        );
+        assert!(
+            fs::read_to_string(dir.path().join(&mapping["app.js"]))
+                .unwrap()
+                .len()
+                < script.len()
+        );
+        assert!(
+            fs::read_to_string(dir.path().join(&mapping["app.css"]))
+                .unwrap()
+                .len()
+                < stylesheet.len()
+        );
+        assert!(
+            fs::read_to_string(dir.path().join("index.html"))
+                .unwrap()
+                .len()
+                < page.len()
+        );
+    }
+
+    #[test]
+    fn test_css_bundles_concatenate_in_order() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("theme/css")).unwrap();
+        fs::create_dir_all(dir.path().join("css")).unwrap();
+        fs::write(
+            dir.path().join("theme/css/base.css"),
+            "body { background: url(../img/bg.png); }\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("css/site.css"),
+            "h1 { background: url('/img/h1.png'); color: red; }",
+        )
+        .unwrap();
+        let bundles = BTreeMap::from([(
+            "css/bundle.css".to_string(),
+            vec![
+                "theme/css/base.css".to_string(),
+                "/css/site.css".to_string(),
+            ],
+        )]);
+
+        bundle_css(dir.path(), &bundles, "https://example.com/").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("css/bundle.css")).unwrap(),
+            "/* theme/css/base.css */\n\
+             body { background: url(\"https://example.com/theme/img/bg.png\"); }\n\
+             /* css/site.css */\n\
+             h1 { background: url('/img/h1.png'); color: red; }\n"
+        );
+        assert!(dir.path().join("css/site.css").exists());
+
+        let missing = BTreeMap::from([(
+            "css/bundle.css".to_string(),
+            vec!["css/missing.css".to_string()],
+        )]);
+        let error = bundle_css(dir.path(), &missing, "").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("can't read bundle source 'css/missing.css'"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_html_config_preserves_comments_and_elements() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let page = "<html>\n<body>\n<!-- google_ad_section_start -->\n<p>\n  Hello\n</p>\n\
+                    <!-- drop me -->\n<code>a   b</code>\n<pre>  x\n  y</pre>\n\
+                    <script>var  s = '<!-- not a comment -->';</script>\n</body>\n</html>\n";
+        fs::write(dir.path().join("index.html"), page).unwrap();
+        let html = HtmlConfig {
+            minify_js: false,
+            preserve_comments: vec!["google_ad_section_*".to_string()],
+            preserve_whitespace: vec!["CODE".to_string()],
+            ..HtmlConfig::default()
+        };
+
+        minify_html_files(dir.path(), &html, &GlobSet::empty()).unwrap();
+
+        let minified = fs::read_to_string(dir.path().join("index.html")).unwrap();
+        assert!(
+            minified.contains("<!-- google_ad_section_start -->"),
+            "{minified}"
+        );
+        assert!(!minified.contains("drop me"), "{minified}");
+        assert!(minified.contains("<code>a   b</code>"), "{minified}");
+        assert!(minified.contains("<pre>  x\n  y</pre>"), "{minified}");
+        assert!(
+            minified.contains("var  s = '<!-- not a comment -->';"),
+            "{minified}"
+        );
+        assert!(minified.contains("<p>Hello</p>"), "{minified}");
+    }
+
+    #[test]
+    fn test_invalid_exclude_pattern() {
+        let error = exclude_globs(&["vendor/[".to_string()]).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid path: vendor/[");
+    }
+
+    #[test]
+    fn test_source_maps_for_minified_assets() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let css = "body {\n  color: red;\n}\n\n.card {\n  margin: 0;\n}\n";
+        let js =
+            "function greet(name) {\n  console.log(\"Hello, \" + name);\n}\ngreet(\"world\");\n";
+        fs::write(dir.path().join("site.css"), css).unwrap();
+        fs::write(dir.path().join("app.js"), js).unwrap();
+
+        process_css_files(
+            dir.path(),
+            &CssConfig::default(),
+            true,
+            true,
+            &GlobSet::empty(),
+        )
+        .unwrap();
+        minify_js_files(dir.path(), true, &GlobSet::empty()).unwrap();
+
+        let minified = fs::read_to_string(dir.path().join("site.css")).unwrap();
+        assert!(minified.ends_with("\n/*# sourceMappingURL=site.css.map */"));
+        let map: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join("site.css.map")).unwrap())
+                .unwrap();
+        assert_eq!(map["sources"][0], "site.css");
+        assert_eq!(map["sourcesContent"][0], css);
+        assert!(!map["mappings"].as_str().unwrap().is_empty());
+
+        let minified = fs::read_to_string(dir.path().join("app.js")).unwrap();
+        assert!(minified.ends_with("\n//# sourceMappingURL=app.js.map"));
+        let map: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join("app.js.map")).unwrap())
+                .unwrap();
+        assert_eq!(map["sources"][0], "app.js");
+        assert_eq!(map["sourcesContent"][0], js);
+        assert!(!map["mappings"].as_str().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_no_source_maps_without_minify() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("site.css"), "body { color: red; }\n").unwrap();
+
+        process_css_files(
+            dir.path(),
+            &CssConfig::default(),
+            false,
+            true,
+            &GlobSet::empty(),
+        )
+        .unwrap();
+
+        assert!(!dir.path().join("site.css.map").exists());
+        assert!(
+            !fs::read_to_string(dir.path().join("site.css"))
+                .unwrap()
+                .contains("sourceMappingURL")
+        );
+    }
+
     #[test]
     fn test_html_minification() {
         let dir = tempfile::TempDir::new().unwrap();
@@ -386,7 +1752,7 @@ mod tests {
         )
         .unwrap();
 
-        minify_html_files(dir.path()).unwrap();
+        minify_html_files(dir.path(), &HtmlConfig::default(), &GlobSet::empty()).unwrap();
 
         let minified = fs::read_to_string(dir.path().join("test.html")).unwrap();
         assert!(minified.len() < "<html>\n  <body>\n    <p>Hello</p>\n  </body>\n</html>".len());
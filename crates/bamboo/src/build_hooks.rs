@@ -0,0 +1,216 @@
+//! `[build.hooks]`: shell commands run before and after rendering, so
+//! external asset tools (Tailwind CLI, esbuild, a favicon generator) can
+//! take part in a build without bamboo knowing about them:
+//!
+//! ```toml
+//! [build.hooks]
+//! before = ["npx tailwindcss -i styles/site.css -o static/css/site.css --minify"]
+//! after = ["npx pagefind --site \"$BAMBOO_OUTPUT_DIR\""]
+//! ```
+//!
+//! `before` commands run before the site is loaded, so files they write
+//! into `static/` or `content/` are picked up by the same build. `after`
+//! commands run once the output is rendered. Each command runs through the
+//! platform shell (`sh -c`, or `cmd /C` on Windows) from the site
+//! directory, with its output passed through and these variables set:
+//!
+//! - `BAMBOO_HOOK`: `before` or `after`.
+//! - `BAMBOO_MODE`: `build`, or `serve` for rebuilds while serving.
+//! - `BAMBOO_INPUT_DIR`, `BAMBOO_OUTPUT_DIR`: absolute paths.
+//! - `BAMBOO_CHANGED_FILES`: the site files (relative to the site
+//!   directory, one per line) that changed since the last build while
+//!   serving; empty for full builds.
+//!
+//! A command that exits unsuccessfully stops the build with
+//! [`BambooError::BuildHook`].
+
+use std::path::Path;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{BambooError, Result};
+
+/// The `[build]` table in `bamboo.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildConfig {
+    /// The `[build.hooks]` table. See [`BuildHooks`].
+    #[serde(default)]
+    pub hooks: BuildHooks,
+}
+
+/// The `[build.hooks]` table: commands run in order at each stage.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildHooks {
+    /// Run before the site is loaded and rendered.
+    #[serde(default)]
+    pub before: Vec<String>,
+    /// Run after the output directory is written.
+    #[serde(default)]
+    pub after: Vec<String>,
+}
+
+/// When a hook runs, exposed to it as `BAMBOO_HOOK`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    /// Before rendering.
+    Before,
+    /// After rendering.
+    After,
+}
+
+impl HookStage {
+    /// The `BAMBOO_HOOK` value.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HookStage::Before => "before",
+            HookStage::After => "after",
+        }
+    }
+}
+
+/// What the hooks of one build are told about it.
+#[derive(Debug, Clone, Copy)]
+pub struct HookContext<'a> {
+    /// The site directory, also the commands' working directory.
+    pub input_dir: &'a Path,
+    /// Where the site is rendered.
+    pub output_dir: &'a Path,
+    /// Site files changed since the previous build; empty for a full
+    /// build.
+    pub changed_files: &'a [String],
+    /// `true` for rebuilds triggered by `bamboo serve`.
+    pub serving: bool,
+}
+
+impl BuildHooks {
+    /// The commands for `stage`.
+    pub fn commands(&self, stage: HookStage) -> &[String] {
+        match stage {
+            HookStage::Before => &self.before,
+            HookStage::After => &self.after,
+        }
+    }
+
+    /// Runs the commands for `stage` in order, stopping at the first that
+    /// fails.
+    pub fn run(&self, stage: HookStage, context: &HookContext) -> Result<()> {
+        for command in self.commands(stage) {
+            run_command(command, stage, context)?;
+        }
+        Ok(())
+    }
+}
+
+fn run_command(command: &str, stage: HookStage, context: &HookContext) -> Result<()> {
+    let hook_error = |message: String| BambooError::BuildHook {
+        command: command.to_string(),
+        message,
+    };
+    let absolute = |path: &Path| {
+        std::path::absolute(path)
+            .unwrap_or_else(|_| path.to_path_buf())
+            .into_os_string()
+    };
+
+    let mut process = if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.arg("/C").arg(command);
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.arg("-c").arg(command);
+        process
+    };
+    let status = process
+        .current_dir(context.input_dir)
+        .env("BAMBOO_HOOK", stage.as_str())
+        .env(
+            "BAMBOO_MODE",
+            if context.serving { "serve" } else { "build" },
+        )
+        .env("BAMBOO_INPUT_DIR", absolute(context.input_dir))
+        .env("BAMBOO_OUTPUT_DIR", absolute(context.output_dir))
+        .env("BAMBOO_CHANGED_FILES", context.changed_files.join("\n"))
+        .status()
+        .map_err(|error| hook_error(error.to_string()))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(hook_error(match status.code() {
+            Some(code) => format!("exited with status {code}"),
+            None => "terminated by a signal".to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_config_parsing() {
+        let config: BuildConfig =
+            toml::from_str("[hooks]\nbefore = [\"tailwindcss -o static/site.css\"]\n").unwrap();
+        assert_eq!(config.hooks.before, vec!["tailwindcss -o static/site.css"]);
+        assert!(config.hooks.after.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hooks_receive_build_environment() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let hooks = BuildHooks {
+            before: vec![
+                "printf '%s|%s|%s' \"$BAMBOO_HOOK\" \"$BAMBOO_MODE\" \"$BAMBOO_CHANGED_FILES\" > hook.txt"
+                    .to_string(),
+                "printf '%s' \"$BAMBOO_OUTPUT_DIR\" >> hook.txt".to_string(),
+            ],
+            after: Vec::new(),
+        };
+        let changed = vec!["content/a.md".to_string(), "static/b.css".to_string()];
+        let context = HookContext {
+            input_dir: dir.path(),
+            output_dir: &dir.path().join("public"),
+            changed_files: &changed,
+            serving: true,
+        };
+
+        hooks.run(HookStage::Before, &context).unwrap();
+        hooks.run(HookStage::After, &context).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("hook.txt")).unwrap(),
+            format!(
+                "before|serve|content/a.md\nstatic/b.css{}",
+                dir.path().join("public").display()
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failing_hook_stops_the_build() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let hooks = BuildHooks {
+            before: Vec::new(),
+            after: vec!["exit 3".to_string(), "touch ran.txt".to_string()],
+        };
+        let context = HookContext {
+            input_dir: dir.path(),
+            output_dir: dir.path(),
+            changed_files: &[],
+            serving: false,
+        };
+
+        let error = hooks.run(HookStage::After, &context).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Build hook `exit 3` failed: exited with status 3"
+        );
+        assert!(!dir.path().join("ran.txt").exists());
+    }
+}
@@ -0,0 +1,209 @@
+//! `manifest.json`, a list of every file in the output directory with its
+//! SHA-256 hash, size, and where it came from, written as the last step of
+//! `bamboo build` when `bamboo.toml` sets `build_manifest = true`: after
+//! link archiving, `after` hooks, and precompression, so it hashes the
+//! files as deployed and lists the `.gz`/`.br` copies too. Deploy tooling
+//! can diff it against the previous deploy's manifest and upload only the
+//! files whose hashes changed.
+//!
+//! A file's `source` is the markdown file it was rendered from
+//! (`content/posts/hello.md`) or the static file it was copied from
+//! (`static/images/logo.png`), and is `null` for everything generated from
+//! the site as a whole: listings, feeds, the sitemap, theme files, and so
+//! on.
+//!
+//! The manifest needs the finished files on disk, so it is only written
+//! for directory builds. Rendering doesn't write it: embedders call
+//! [`generate_build_manifest`] themselves once their own post-processing
+//! is done, passing the [`RenderReport`](crate::RenderReport)'s
+//! `fingerprinted` map.
+
+use crate::error::{IoContext, Result};
+use crate::types::{Content, Site};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Output path of the manifest.
+pub const BUILD_MANIFEST_FILE: &str = "manifest.json";
+
+/// Contents of `manifest.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildManifest {
+    /// Every output file except the manifest itself, sorted by path.
+    pub files: Vec<ManifestFile>,
+}
+
+/// One file of the build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Path relative to the output directory, `/`-separated.
+    pub path: String,
+    /// Hex SHA-256 of the file's contents.
+    pub sha256: String,
+    /// Size in bytes.
+    pub size: u64,
+    /// Source file relative to the site directory, or `None` for
+    /// generated files.
+    pub source: Option<String>,
+}
+
+impl BuildManifest {
+    /// Paths whose hash differs from `previous`, or that `previous`
+    /// doesn't list: the files a deploy has to upload.
+    pub fn changed_since(&self, previous: &BuildManifest) -> Vec<&str> {
+        let previous: HashMap<&str, &str> = previous
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.sha256.as_str()))
+            .collect();
+        self.files
+            .iter()
+            .filter(|file| previous.get(file.path.as_str()) != Some(&file.sha256.as_str()))
+            .map(|file| file.path.as_str())
+            .collect()
+    }
+}
+
+/// Source of each output file that comes from a single file of the site,
+/// keyed by output path. `fingerprinted` maps static files to their
+/// fingerprinted names, as returned by [`crate::assets::prepare_assets`].
+pub fn source_files(
+    site: &Site,
+    fingerprinted: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let contents = site
+        .home
+        .iter()
+        .map(|home| &home.content)
+        .chain(site.pages.iter().map(|page| &page.content))
+        .chain(site.posts.iter().map(|post| &post.content))
+        .chain(site.notes.iter().map(|note| &note.content))
+        .chain(
+            site.collections
+                .values()
+                .flat_map(|collection| collection.items.iter().map(|item| &item.content)),
+        )
+        .chain(
+            site.collections
+                .values()
+                .filter_map(|collection| collection.index.as_ref()),
+        );
+    let slash = |path: &Path| path.to_string_lossy().replace('\\', "/");
+    let mut sources: HashMap<String, String> = contents
+        .map(|content: &Content| {
+            (
+                slash(&content.path),
+                format!("content/{}", slash(&content.source_path)),
+            )
+        })
+        .collect();
+    for asset in &site.assets {
+        let dest = slash(&asset.dest);
+        let output = fingerprinted.get(&dest).cloned().unwrap_or(dest.clone());
+        sources.insert(output, format!("static/{dest}"));
+    }
+    sources
+}
+
+/// Hashes every file under `output_dir` into a [`BuildManifest`].
+pub fn build_manifest(
+    site: &Site,
+    output_dir: &Path,
+    fingerprinted: &HashMap<String, String>,
+) -> Result<BuildManifest> {
+    let mut manifest = hash_output(output_dir, &source_files(site, fingerprinted))?;
+    manifest
+        .files
+        .retain(|file| file.path != BUILD_MANIFEST_FILE);
+    Ok(manifest)
+}
+
+/// Hashes every file under `output_dir`, taking each file's `source` from
+/// `sources`, keyed by output path.
+pub fn hash_output(output_dir: &Path, sources: &HashMap<String, String>) -> Result<BuildManifest> {
+    let files = crate::verify::output_files(output_dir)
+        .into_iter()
+        .map(|path| {
+            let full_path = output_dir.join(&path);
+            let bytes = std::fs::read(&full_path).io_context("read", &full_path)?;
+            Ok(ManifestFile {
+                sha256: format!("{:x}", Sha256::digest(&bytes)),
+                size: bytes.len() as u64,
+                source: sources.get(&path).cloned(),
+                path,
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(BuildManifest { files })
+}
+
+/// Writes `manifest.json` into `output_dir` when `build_manifest = true`.
+pub fn generate_build_manifest(
+    site: &Site,
+    output_dir: &Path,
+    fingerprinted: &HashMap<String, String>,
+) -> Result<()> {
+    if !site.config.build_manifest {
+        return Ok(());
+    }
+    let manifest = build_manifest(site, output_dir, fingerprinted)?;
+    let json = serde_json::to_string_pretty(&manifest).map_err(std::io::Error::other)?;
+    let path = output_dir.join(BUILD_MANIFEST_FILE);
+    std::fs::write(&path, json + "\n").io_context("write", &path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Asset;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_generate_build_manifest() {
+        let mut site = crate::template_test::sample_site().unwrap();
+        site.config.build_manifest = true;
+        site.assets.push(Asset {
+            source: PathBuf::from("site/static/style.css"),
+            dest: PathBuf::from("style.css"),
+        });
+        let fingerprinted =
+            HashMap::from([("style.css".to_string(), "style.0123abcd.css".to_string())]);
+        let output_dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(output_dir.path().join("about")).unwrap();
+        fs::write(output_dir.path().join("about/index.html"), "about").unwrap();
+        fs::write(output_dir.path().join("style.0123abcd.css"), "body{}").unwrap();
+        fs::write(output_dir.path().join("rss.xml"), "<rss/>").unwrap();
+
+        generate_build_manifest(&site, output_dir.path(), &fingerprinted).unwrap();
+        let manifest: BuildManifest = serde_json::from_str(
+            &fs::read_to_string(output_dir.path().join(BUILD_MANIFEST_FILE)).unwrap(),
+        )
+        .unwrap();
+
+        let paths: Vec<&str> = manifest
+            .files
+            .iter()
+            .map(|file| file.path.as_str())
+            .collect();
+        assert_eq!(paths, ["about/index.html", "rss.xml", "style.0123abcd.css"]);
+        assert_eq!(
+            manifest.files[0].source.as_deref(),
+            Some("content/about.md")
+        );
+        assert_eq!(manifest.files[0].size, 5);
+        assert_eq!(manifest.files[1].source, None);
+        assert_eq!(
+            manifest.files[2].source.as_deref(),
+            Some("static/style.css")
+        );
+
+        let rebuilt = build_manifest(&site, output_dir.path(), &fingerprinted).unwrap();
+        assert_eq!(rebuilt, manifest);
+        fs::write(output_dir.path().join("rss.xml"), "<rss></rss>").unwrap();
+        let rebuilt = build_manifest(&site, output_dir.path(), &fingerprinted).unwrap();
+        assert_eq!(rebuilt.changed_since(&manifest), ["rss.xml"]);
+    }
+}
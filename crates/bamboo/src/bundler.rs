@@ -0,0 +1,777 @@
+//! A small ES module bundler for the `[assets.js]` table.
+//!
+//! Each entry script's relative `import`s are resolved among the JS files
+//! already in the output (the site's and the theme's `static/` trees) and
+//! inlined into one classic script: every imported module runs once, in
+//! dependency order, inside its own function scope, and the entry replaces
+//! its original file so existing `<script>` tags keep working.
+//!
+//! Bindings are copied, not live: a module that reassigns an exported
+//! `let` after it finishes loading won't be seen by its importers.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{BambooError, Result};
+
+/// The `[assets.js]` table in `bamboo.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsConfig {
+    /// Entry scripts to bundle, relative to the output root
+    /// (`"js/main.js"`). Empty bundles every file under `js/` that
+    /// imports another module and isn't itself imported.
+    #[serde(default)]
+    pub entries: Vec<String>,
+    /// Minify the bundles even when the site-wide `minify` is off.
+    /// Defaults to `true`.
+    #[serde(default = "default_true")]
+    pub minify: bool,
+}
+
+impl Default for JsConfig {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            minify: true,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+const MODULES: &str = "__bamboo_modules";
+const EXPORTS: &str = "__bamboo_exports";
+
+/// Bundles the configured entries under `output_dir` in place and returns
+/// the paths of the bundles written.
+pub fn bundle_js(output_dir: &Path, config: &JsConfig) -> Result<Vec<PathBuf>> {
+    let mut modules = BTreeMap::new();
+    for path in crate::assets::collect_files_with_extension(output_dir, "js")? {
+        let key = module_key(output_dir, &path);
+        let source = fs::read_to_string(&path).map_err(|source| BambooError::IoPath {
+            operation: "read",
+            path: path.clone(),
+            source,
+        })?;
+        let module = parse_module(&key, &source).map_err(|message| BambooError::JsBundle {
+            path: path.clone(),
+            message,
+        })?;
+        modules.insert(key, module);
+    }
+    let keys: HashSet<String> = modules.keys().cloned().collect();
+    for (key, module) in &mut modules {
+        for import in &mut module.imports {
+            *import = resolve(key, import, &keys).map_err(|message| BambooError::JsBundle {
+                path: output_dir.join(key),
+                message,
+            })?;
+        }
+    }
+
+    let entries: Vec<String> = if config.entries.is_empty() {
+        let imported: HashSet<&String> = modules
+            .values()
+            .flat_map(|module| &module.imports)
+            .collect();
+        modules
+            .iter()
+            .filter(|(key, module)| {
+                key.starts_with("js/") && !module.imports.is_empty() && !imported.contains(key)
+            })
+            .map(|(key, _)| key.clone())
+            .collect()
+    } else {
+        config
+            .entries
+            .iter()
+            .map(|entry| entry.trim_start_matches('/').to_string())
+            .collect()
+    };
+
+    let mut written = Vec::new();
+    for entry in entries {
+        let path = output_dir.join(&entry);
+        if !modules.contains_key(&entry) {
+            return Err(BambooError::JsBundle {
+                path,
+                message: "entry script not found in the output".to_string(),
+            });
+        }
+        let mut order = Vec::new();
+        visit(&entry, &modules, &mut Vec::new(), &mut order).map_err(|message| {
+            BambooError::JsBundle {
+                path: path.clone(),
+                message,
+            }
+        })?;
+        let bundle = render_bundle(&entry, &order, &modules);
+        fs::write(&path, bundle).map_err(|source| BambooError::IoPath {
+            operation: "write",
+            path: path.clone(),
+            source,
+        })?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// A module with its `import`/`export` statements rewritten into reads
+/// from the module table and assignments to its exports object.
+struct Module {
+    body: String,
+    /// Imported modules: specifiers while parsing, resolved keys after.
+    imports: Vec<String>,
+    /// `(exported, local)` pairs assigned once the body has run.
+    exports: Vec<(String, String)>,
+}
+
+impl Module {
+    /// The body with import placeholders replaced by resolved keys.
+    fn body_with_keys(&self) -> String {
+        let mut body = self.body.trim().to_string();
+        for (index, key) in self.imports.iter().enumerate() {
+            body = body.replace(&format!("\u{0}{}\u{0}", index), key);
+        }
+        body
+    }
+}
+
+fn module_key(output_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(output_dir)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Resolves `specifier` as imported from `importer` to a module key.
+fn resolve(
+    importer: &str,
+    specifier: &str,
+    keys: &HashSet<String>,
+) -> std::result::Result<String, String> {
+    let mut parts: Vec<&str> = if specifier.starts_with("./") || specifier.starts_with("../") {
+        let mut directory: Vec<&str> = importer.split('/').collect();
+        directory.pop();
+        directory
+    } else if specifier.starts_with('/') {
+        Vec::new()
+    } else {
+        return Err(format!(
+            "can't bundle '{}': only relative or root-relative imports are supported",
+            specifier
+        ));
+    };
+    for part in specifier.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    let key = parts.join("/");
+    if keys.contains(&key) {
+        Ok(key)
+    } else if keys.contains(&format!("{}.js", key)) {
+        Ok(format!("{}.js", key))
+    } else {
+        Err(format!("import '{}' not found in the output", specifier))
+    }
+}
+
+/// Depth-first post-order walk from `key`, so dependencies come first.
+fn visit(
+    key: &str,
+    modules: &BTreeMap<String, Module>,
+    stack: &mut Vec<String>,
+    order: &mut Vec<String>,
+) -> std::result::Result<(), String> {
+    if order.iter().any(|done| done == key) {
+        return Ok(());
+    }
+    if let Some(position) = stack.iter().position(|open| open == key) {
+        let mut cycle = stack[position..].to_vec();
+        cycle.push(key.to_string());
+        return Err(format!("import cycle: {}", cycle.join(" -> ")));
+    }
+    stack.push(key.to_string());
+    for import in &modules[key].imports {
+        visit(import, modules, stack, order)?;
+    }
+    stack.pop();
+    order.push(key.to_string());
+    Ok(())
+}
+
+fn render_bundle(entry: &str, order: &[String], modules: &BTreeMap<String, Module>) -> String {
+    let mut bundle = format!("(() => {{\nconst {} = {{}};\n", MODULES);
+    for key in order {
+        let module = &modules[key];
+        if key == entry {
+            bundle.push_str(&format!(
+                "// {}\n(() => {{\n{}\n}})();\n",
+                key,
+                module.body_with_keys()
+            ));
+            continue;
+        }
+        bundle.push_str(&format!(
+            "// {}\n{}[\"{}\"] = (() => {{\nconst {} = {{}};\n{}\n",
+            key,
+            MODULES,
+            key,
+            EXPORTS,
+            module.body_with_keys()
+        ));
+        for (exported, local) in &module.exports {
+            bundle.push_str(&format!("{}.{} = {};\n", EXPORTS, exported, local));
+        }
+        bundle.push_str(&format!("return {};\n}})();\n", EXPORTS));
+    }
+    bundle.push_str("})();\n");
+    bundle
+}
+
+/// The module table lookup for `specifier`. Keys aren't known until every
+/// file is parsed, so the body holds the import's index as a placeholder
+/// that [`Module::body_with_keys`] fills in.
+fn module_reference(specifier: &str, imports: &[String]) -> String {
+    let index = imports
+        .iter()
+        .position(|import| import == specifier)
+        .unwrap_or(imports.len());
+    format!("{}[\"\u{0}{}\u{0}\"]", MODULES, index)
+}
+
+/// Rewrites the top-level `import`/`export` statements of `source`.
+fn parse_module(key: &str, source: &str) -> std::result::Result<Module, String> {
+    let mut body = String::with_capacity(source.len());
+    let mut imports: Vec<String> = Vec::new();
+    let mut exports = Vec::new();
+    let mut cursor = 0;
+    for offset in module_statements(source) {
+        if offset < cursor {
+            continue;
+        }
+        body.push_str(&source[cursor..offset]);
+        let statement = &source[offset..];
+        let line = source[..offset].matches('\n').count() + 1;
+        let fail = |message: &str| format!("{}:{}: {}", key, line, message);
+
+        if let Some(rest) = statement.strip_prefix("import") {
+            let (clause, specifier, length) =
+                split_from(rest).ok_or_else(|| fail("malformed import statement"))?;
+            if !imports.contains(&specifier) {
+                imports.push(specifier.clone());
+            }
+            let module = module_reference(&specifier, &imports);
+            body.push_str(&import_bindings(clause, &module).map_err(|error| fail(&error))?);
+            cursor = offset + "import".len() + length;
+            continue;
+        }
+
+        let rest = &statement["export".len()..];
+        let trimmed = rest.trim_start();
+        let skipped = "export".len() + rest.len() - trimmed.len();
+        if trimmed.starts_with('{') || trimmed.starts_with('*') {
+            let close = trimmed.find('}').unwrap_or(0);
+            let reexport = trimmed.starts_with('*')
+                || word(trimmed[close + 1..].trim_start(), "from").is_some();
+            if reexport {
+                let (clause, specifier, length) =
+                    split_from(rest).ok_or_else(|| fail("malformed export statement"))?;
+                if !imports.contains(&specifier) {
+                    imports.push(specifier.clone());
+                }
+                let module = module_reference(&specifier, &imports);
+                body.push_str(&reexports(clause, &module).map_err(|error| fail(&error))?);
+                cursor = offset + "export".len() + length;
+            } else {
+                if close == 0 {
+                    return Err(fail("unterminated export list"));
+                }
+                for (local, exported) in named_list(&trimmed[1..close]) {
+                    exports.push((exported, local));
+                }
+                cursor = offset + skipped + close + 1;
+                cursor += statement_end(&source[cursor..]);
+            }
+        } else if let Some(after) = word(trimmed, "default") {
+            let declaration = after.trim_start();
+            match declaration_name(declaration) {
+                Some(Ok(name)) => {
+                    exports.push(("default".to_string(), name));
+                    cursor = offset + source[offset..].len() - declaration.len();
+                }
+                _ => {
+                    body.push_str(&format!("{}.default = ", EXPORTS));
+                    cursor = offset + source[offset..].len() - declaration.len();
+                }
+            }
+        } else {
+            let name = declaration_name(trimmed)
+                .ok_or_else(|| fail("unsupported export statement"))?
+                .map_err(|error| fail(&error))?;
+            exports.push((name.clone(), name));
+            cursor = offset + skipped;
+        }
+    }
+    body.push_str(&source[cursor..]);
+    Ok(Module {
+        body,
+        imports,
+        exports,
+    })
+}
+
+/// `default`-style word match: `text` starts with `keyword` followed by a
+/// non-identifier character. Returns the rest.
+fn word<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+    let rest = text.strip_prefix(keyword)?;
+    match rest.chars().next() {
+        Some(next) if is_identifier_char(next) => None,
+        _ => Some(rest),
+    }
+}
+
+fn is_identifier_char(character: char) -> bool {
+    character.is_alphanumeric() || character == '_' || character == '$'
+}
+
+/// The binding a `function`/`class`/`const`/`let`/`var` declaration
+/// introduces: `None` if `text` isn't a declaration, an error for
+/// destructuring, which the bundler can't export.
+fn declaration_name(text: &str) -> Option<std::result::Result<String, String>> {
+    let text = word(text, "async").map(str::trim_start).unwrap_or(text);
+    let rest = ["function", "class", "const", "let", "var"]
+        .iter()
+        .find_map(|keyword| {
+            if *keyword == "function" {
+                text.strip_prefix(keyword)
+            } else {
+                word(text, keyword)
+            }
+        })?;
+    let rest = rest.trim_start().trim_start_matches('*').trim_start();
+    let name: String = rest
+        .chars()
+        .take_while(|c| is_identifier_char(*c))
+        .collect();
+    if name.is_empty() {
+        if rest.starts_with('{') || rest.starts_with('[') {
+            return Some(Err("destructuring exports aren't supported".to_string()));
+        }
+        return None;
+    }
+    Some(Ok(name))
+}
+
+/// Splits `<clause> from "<specifier>";` (or a bare `"<specifier>";`)
+/// into the clause, the specifier, and the length consumed.
+fn split_from(text: &str) -> Option<(&str, String, usize)> {
+    let open = text.find(['"', '\''])?;
+    let quote = text[open..].chars().next()?;
+    let close = open + 1 + text[open + 1..].find(quote)?;
+    let clause = text[..open].trim();
+    let clause = clause
+        .strip_suffix("from")
+        .map(str::trim_end)
+        .unwrap_or(clause);
+    let specifier = text[open + 1..close].to_string();
+    let end = close + 1;
+    Some((clause, specifier, end + statement_end(&text[end..])))
+}
+
+/// Length of the spaces and optional `;` closing a statement.
+fn statement_end(text: &str) -> usize {
+    let spaces = text.len() - text.trim_start_matches([' ', '\t']).len();
+    if text[spaces..].starts_with(';') {
+        spaces + 1
+    } else {
+        spaces
+    }
+}
+
+/// `a, b as c` → `[("a", "a"), ("b", "c")]`.
+fn named_list(list: &str) -> Vec<(String, String)> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| match name.split_once(" as ") {
+            Some((local, alias)) => (local.trim().to_string(), alias.trim().to_string()),
+            None => (name.to_string(), name.to_string()),
+        })
+        .collect()
+}
+
+fn import_bindings(clause: &str, module: &str) -> std::result::Result<String, String> {
+    let mut statements = Vec::new();
+    let mut rest = clause.trim();
+    if rest.is_empty() {
+        return Ok(String::new());
+    }
+    if !rest.starts_with('{') && !rest.starts_with('*') {
+        let end = rest.find(',').unwrap_or(rest.len());
+        statements.push(format!(
+            "const {} = {}.default;",
+            rest[..end].trim(),
+            module
+        ));
+        rest = rest[end..].trim_start_matches(',').trim();
+    }
+    if let Some(namespace) = rest.strip_prefix('*') {
+        let name = namespace
+            .trim()
+            .strip_prefix("as ")
+            .ok_or("expected `* as <name>`")?;
+        statements.push(format!("const {} = {};", name.trim(), module));
+    } else if let Some(list) = rest.strip_prefix('{') {
+        let list = list.strip_suffix('}').ok_or("unterminated import list")?;
+        let bindings: Vec<String> = named_list(list)
+            .into_iter()
+            .map(|(imported, local)| {
+                if imported == local {
+                    local
+                } else {
+                    format!("{}: {}", imported, local)
+                }
+            })
+            .collect();
+        statements.push(format!("const {{ {} }} = {};", bindings.join(", "), module));
+    } else if !rest.is_empty() {
+        return Err(format!("unsupported import clause '{}'", clause));
+    }
+    Ok(statements.join(" "))
+}
+
+fn reexports(clause: &str, module: &str) -> std::result::Result<String, String> {
+    let clause = clause.trim();
+    if clause == "*" {
+        return Ok(format!(
+            "Object.keys({module}).filter((name) => name !== \"default\").forEach((name) => {{ {EXPORTS}[name] = {module}[name]; }});"
+        ));
+    }
+    if let Some(namespace) = clause.strip_prefix('*') {
+        let name = namespace
+            .trim()
+            .strip_prefix("as ")
+            .ok_or("expected `* as <name>`")?;
+        return Ok(format!("{}.{} = {};", EXPORTS, name.trim(), module));
+    }
+    let list = clause
+        .strip_prefix('{')
+        .and_then(|list| list.strip_suffix('}'))
+        .ok_or_else(|| format!("unsupported export clause '{}'", clause))?;
+    Ok(named_list(list)
+        .into_iter()
+        .map(|(imported, exported)| format!("{}.{} = {}.{};", EXPORTS, exported, module, imported))
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// Byte offsets of the `import`/`export` keywords that begin top-level
+/// statements, skipping strings, template literals, comments, and regex
+/// literals. Dynamic `import()` and `import.meta` are left alone.
+fn module_statements(source: &str) -> Vec<usize> {
+    let bytes = source.as_bytes();
+    let mut offsets = Vec::new();
+    let mut depth = 0usize;
+    let mut previous: Option<u8> = None;
+    let mut line_start = true;
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        match byte {
+            b'\n' => {
+                line_start = true;
+                index += 1;
+                continue;
+            }
+            b' ' | b'\t' | b'\r' => {
+                index += 1;
+                continue;
+            }
+            b'/' if bytes.get(index + 1) == Some(&b'/') => {
+                index = source[index..]
+                    .find('\n')
+                    .map_or(bytes.len(), |end| index + end);
+                continue;
+            }
+            b'/' if bytes.get(index + 1) == Some(&b'*') => {
+                index = source[index + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| index + 2 + end + 2);
+                continue;
+            }
+            b'\'' | b'"' => index = skip_string(bytes, index),
+            b'`' => index = skip_template(bytes, index),
+            b'/' if previous.is_none_or(|before| b"(,=:[!&|?{};+-*%<>~^".contains(&before)) => {
+                index = skip_regex(bytes, index)
+            }
+            b'{' | b'(' | b'[' => {
+                depth += 1;
+                index += 1;
+            }
+            b'}' | b')' | b']' => {
+                depth = depth.saturating_sub(1);
+                index += 1;
+            }
+            _ if is_identifier_char(byte as char) => {
+                let start = index;
+                while index < bytes.len() && is_identifier_char(bytes[index] as char) {
+                    index += 1;
+                }
+                let statement_start = line_start || matches!(previous, Some(b';') | Some(b'}'));
+                if depth == 0 && statement_start {
+                    let next = bytes.get(index).copied();
+                    let module_keyword = match &source[start..index] {
+                        "import" => !matches!(next, Some(b'(') | Some(b'.')),
+                        "export" => true,
+                        _ => false,
+                    };
+                    if module_keyword {
+                        offsets.push(start);
+                    }
+                }
+            }
+            _ => index += 1,
+        }
+        previous = Some(bytes[index.saturating_sub(1)]);
+        line_start = false;
+    }
+    offsets
+}
+
+pub(crate) fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut index = start + 1;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'\n' => return index,
+            byte if byte == quote => return index + 1,
+            _ => index += 1,
+        }
+    }
+    bytes.len()
+}
+
+pub(crate) fn skip_template(bytes: &[u8], start: usize) -> usize {
+    let mut index = start + 1;
+    let mut braces = 0usize;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'`' if braces == 0 => return index + 1,
+            b'$' if bytes.get(index + 1) == Some(&b'{') => {
+                braces += 1;
+                index += 2;
+            }
+            b'{' if braces > 0 => {
+                braces += 1;
+                index += 1;
+            }
+            b'}' if braces > 0 => {
+                braces -= 1;
+                index += 1;
+            }
+            _ => index += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Skips a regex literal, or just the `/` if the line ends first (it was
+/// a division after all).
+pub(crate) fn skip_regex(bytes: &[u8], start: usize) -> usize {
+    let mut index = start + 1;
+    let mut class = false;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'\n' => return start + 1,
+            b'[' => {
+                class = true;
+                index += 1;
+            }
+            b']' => {
+                class = false;
+                index += 1;
+            }
+            b'/' if !class => {
+                index += 1;
+                while index < bytes.len() && bytes[index].is_ascii_alphabetic() {
+                    index += 1;
+                }
+                return index;
+            }
+            _ => index += 1,
+        }
+    }
+    start + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, path: &str, contents: &str) {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn site() -> tempfile::TempDir {
+        let dir = tempfile::TempDir::new().unwrap();
+        write(
+            dir.path(),
+            "js/lib/math.js",
+            "export const PI = 3.14;\nexport function double(value) {\n  return value * 2;\n}\nexport default function square(value) {\n  return value * value;\n}\n",
+        );
+        write(
+            dir.path(),
+            "js/lib/format.js",
+            "import { double as twice } from './math.js';\nconst label = (value) => `${value}`;\nexport { label, twice as doubled };\nexport * from \"./math\";\n",
+        );
+        write(
+            dir.path(),
+            "js/main.js",
+            "import square, { PI } from \"./lib/math.js\";\nimport * as format from './lib/format.js';\nconsole.log(format.label(square(2) + format.doubled(PI)));\n",
+        );
+        write(
+            dir.path(),
+            "js/plain.js",
+            "console.log('no modules here');\n",
+        );
+        dir
+    }
+
+    #[test]
+    fn test_bundle_inlines_imports_in_dependency_order() {
+        let dir = site();
+        let written = bundle_js(dir.path(), &JsConfig::default()).unwrap();
+        assert_eq!(written, vec![dir.path().join("js/main.js")]);
+
+        let bundle = fs::read_to_string(dir.path().join("js/main.js")).unwrap();
+        let math = bundle.find("// js/lib/math.js").unwrap();
+        let format = bundle.find("// js/lib/format.js").unwrap();
+        let main = bundle.find("// js/main.js").unwrap();
+        assert!(math < format && format < main, "{}", bundle);
+        assert!(!bundle.contains("import "), "{}", bundle);
+        assert!(!bundle.contains("export "), "{}", bundle);
+        assert!(bundle.contains("const { double: twice } = __bamboo_modules[\"js/lib/math.js\"];"));
+        assert!(bundle.contains("const square = __bamboo_modules[\"js/lib/math.js\"].default;"));
+        assert!(bundle.contains("const format = __bamboo_modules[\"js/lib/format.js\"];"));
+        assert!(bundle.contains("__bamboo_exports.default = square;"));
+        assert!(bundle.contains("__bamboo_exports.doubled = twice;"));
+
+        // Imported modules stay in place; plain scripts are untouched.
+        assert!(
+            fs::read_to_string(dir.path().join("js/lib/math.js"))
+                .unwrap()
+                .starts_with("export const PI")
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("js/plain.js")).unwrap(),
+            "console.log('no modules here');\n"
+        );
+    }
+
+    #[test]
+    fn test_bundle_minifies_through_asset_pipeline() {
+        let dir = site();
+        let config = crate::assets::AssetConfig {
+            minify: false,
+            fingerprint: false,
+            fingerprint_exclude: Vec::new(),
+            fingerprint_deferred: Vec::new(),
+            minify_exclude: Vec::new(),
+            base_url: "https://example.com".to_string(),
+            sass_load_paths: Vec::new(),
+            dedupe: None,
+            css: None,
+            source_maps: false,
+            css_bundles: Default::default(),
+            html: Default::default(),
+            js: Some(JsConfig {
+                entries: vec!["/js/main.js".to_string()],
+                minify: true,
+            }),
+        };
+        crate::assets::prepare_assets(dir.path(), &config).unwrap();
+
+        let bundle = fs::read_to_string(dir.path().join("js/main.js")).unwrap();
+        assert!(!bundle.contains("// js/lib/math.js"), "{}", bundle);
+        assert!(!bundle.contains("import "));
+        // Only the bundle is minified when `minify` is off.
+        assert!(
+            fs::read_to_string(dir.path().join("js/plain.js"))
+                .unwrap()
+                .ends_with('\n')
+        );
+    }
+
+    #[test]
+    fn test_bundle_errors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write(dir.path(), "js/a.js", "import './b.js';\n");
+        write(dir.path(), "js/b.js", "import { a } from './a.js';\n");
+        let config = JsConfig {
+            entries: vec!["js/a.js".to_string()],
+            ..JsConfig::default()
+        };
+        let error = bundle_js(dir.path(), &config).unwrap_err().to_string();
+        assert!(
+            error.contains("import cycle: js/a.js -> js/b.js -> js/a.js"),
+            "{}",
+            error
+        );
+
+        write(dir.path(), "js/b.js", "import confetti from 'confetti';\n");
+        let error = bundle_js(dir.path(), &config).unwrap_err().to_string();
+        assert!(
+            error.contains("only relative or root-relative"),
+            "{}",
+            error
+        );
+
+        write(
+            dir.path(),
+            "js/b.js",
+            "import { missing } from './missing.js';\n",
+        );
+        let error = bundle_js(dir.path(), &config).unwrap_err().to_string();
+        assert!(error.contains("'./missing.js' not found"), "{}", error);
+
+        write(dir.path(), "js/b.js", "export const { x, y } = point;\n");
+        let error = bundle_js(dir.path(), &config).unwrap_err().to_string();
+        assert!(error.contains("js/b.js:1: destructuring"), "{}", error);
+
+        let config = JsConfig {
+            entries: vec!["js/nope.js".to_string()],
+            ..JsConfig::default()
+        };
+        assert!(bundle_js(dir.path(), &config).is_err());
+    }
+
+    #[test]
+    fn test_module_statements_skip_non_statements() {
+        let source = "// import a from './a.js';\nconst s = 'import x from \"y\"';\nconst t = `export ${1}`;\n/* export */ const r = /import/g;\nimport('./lazy.js');\nconsole.log(import.meta.url);\nif (x) { export_thing(); }\nimport b from './b.js'; export const c = 1;\n";
+        let offsets = module_statements(source);
+        let keywords: Vec<&str> = offsets
+            .iter()
+            .map(|offset| &source[*offset..*offset + 6])
+            .collect();
+        assert_eq!(keywords, vec!["import", "export"]);
+        assert_eq!(offsets[0], source.rfind("import b").unwrap());
+    }
+}
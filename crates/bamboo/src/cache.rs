@@ -2,6 +2,7 @@
 //! `bamboo serve` can rebuild only what actually changed between edits.
 
 use crate::error::Result;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
@@ -19,25 +20,72 @@ pub struct BuildState {
     /// SHA-256 hash of every tracked content/data/static/template file,
     /// keyed by its project-relative path.
     pub content_hashes: HashMap<String, String>,
+    /// Which render targets each template fed into during this build, so
+    /// a template edit can re-render only the affected output. Missing in
+    /// caches written by older versions, which forces a full rebuild on
+    /// the next template change.
+    #[serde(default)]
+    pub template_dependencies: TemplateDependencies,
+    /// Fingerprinted asset names from the last full render (see
+    /// [`RenderReport::fingerprinted`](crate::RenderReport::fingerprinted)),
+    /// so incremental renders keep linking the hashed files.
+    #[serde(default)]
+    pub asset_paths: HashMap<String, String>,
+}
+
+/// Reverse dependency map from template names (relative to `templates/`,
+/// e.g. `partials/header.html`) to the [`RenderTarget`]s whose output
+/// used them, directly or through `extends`, `include`, `import`, or a
+/// shortcode call. Produced by
+/// [`ThemeEngine::template_dependencies`](crate::ThemeEngine::template_dependencies).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TemplateDependencies {
+    targets: HashMap<String, Vec<RenderTarget>>,
+}
+
+impl TemplateDependencies {
+    /// Records that `target` depends on `template`.
+    pub fn record(&mut self, template: &str, target: RenderTarget) {
+        let targets = self.targets.entry(template.to_string()).or_default();
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+
+    /// Targets that depend on `template`, or `None` if no tracked output
+    /// used it.
+    pub fn targets_for(&self, template: &str) -> Option<&[RenderTarget]> {
+        self.targets.get(template).map(Vec::as_slice)
+    }
+
+    /// Returns `true` if nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
 }
 
 /// Result of comparing two [`BuildState`]s: either a full rebuild is
 /// needed, or only a targeted subset.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ChangeClassification {
-    /// Something non-trivial (config, template, data) changed. Rebuild
-    /// everything.
+    /// Something non-trivial (config, untracked template, data) changed.
+    /// Rebuild everything.
     Full,
-    /// Only individual content files changed; render exactly these.
+    /// Only individual content files or tracked templates changed; render
+    /// exactly these.
     Targeted {
         /// Project-relative paths of the files that changed.
         changed_files: Vec<PathBuf>,
+        /// Targets affected by changed templates, looked up in the
+        /// previous build's [`TemplateDependencies`].
+        template_targets: Vec<RenderTarget>,
     },
 }
 
 /// One unit of render work that the theme engine can perform. Produced by
 /// [`expand_targets`] from a [`ChangeClassification`].
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum RenderTarget {
     /// Re-render the page with the given slug.
     Page(String),
@@ -45,6 +93,8 @@ pub enum RenderTarget {
     Post(String),
     /// Re-render every item in the named collection.
     Collection(String),
+    /// Re-render every note and the notes list.
+    Notes,
     /// Re-render paginated index pages.
     Pagination,
     /// Re-render every taxonomy index and term page.
@@ -59,6 +109,58 @@ pub enum RenderTarget {
     All,
 }
 
+/// Returns the project's cache directory (`project_dir/.bamboo-cache`),
+/// shared by the build state and other persisted lookups.
+pub fn cache_dir(project_dir: &Path) -> PathBuf {
+    project_dir.join(CACHE_DIR_NAME)
+}
+
+/// Directories a build writes into, which must never be treated as source
+/// changes: the output directory and the project's `.bamboo-cache`. Each is
+/// kept both as given and canonicalized, so a path reached through a
+/// symlink still matches.
+#[derive(Debug, Clone, Default)]
+pub struct ExcludedPaths {
+    paths: Vec<PathBuf>,
+}
+
+impl ExcludedPaths {
+    /// Excludes `output_dir` and `project_dir/.bamboo-cache`.
+    pub fn new(project_dir: &Path, output_dir: &Path) -> Self {
+        let mut excluded = Self::default();
+        excluded.add(output_dir);
+        excluded.add(&cache_dir(project_dir));
+        excluded
+    }
+
+    fn add(&mut self, path: &Path) {
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        if let Ok(canonical) = absolute.canonicalize()
+            && canonical != absolute
+        {
+            self.paths.push(canonical);
+        }
+        self.paths.push(absolute);
+    }
+
+    /// Whether `path` is one of the excluded directories or inside one.
+    pub fn contains(&self, path: &Path) -> bool {
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        if self
+            .paths
+            .iter()
+            .any(|excluded| absolute.starts_with(excluded))
+        {
+            return true;
+        }
+        absolute.canonicalize().is_ok_and(|canonical| {
+            self.paths
+                .iter()
+                .any(|excluded| canonical.starts_with(excluded))
+        })
+    }
+}
+
 /// Loads the persisted [`BuildState`] from `project_dir/.bamboo-cache/`.
 /// Returns `None` if the file is missing or malformed.
 pub fn load_cache(project_dir: &Path) -> Option<BuildState> {
@@ -81,15 +183,20 @@ pub fn save_cache(project_dir: &Path, state: &BuildState) -> Result<()> {
 
 /// Walks `input_dir`'s `content/`, `data/`, `static/`, and `templates/`
 /// subdirectories and returns a SHA-256 hash of every file found, keyed by
-/// project-relative path.
-pub fn compute_content_hashes(input_dir: &Path) -> Result<HashMap<String, String>> {
+/// project-relative path. Anything under `excluded` is skipped, so an output
+/// directory nested in (or symlinked into) one of them doesn't make every
+/// build look like a change.
+pub fn compute_content_hashes(
+    input_dir: &Path,
+    excluded: &ExcludedPaths,
+) -> Result<HashMap<String, String>> {
     let mut hashes = HashMap::new();
 
     let dirs_to_hash = ["content", "data", "static", "templates"];
     for dir_name in &dirs_to_hash {
         let dir = input_dir.join(dir_name);
         if dir.exists() {
-            hash_directory(&dir, input_dir, &mut hashes)?;
+            hash_directory(&dir, input_dir, excluded, &mut hashes)?;
         }
     }
 
@@ -118,38 +225,50 @@ fn hash_file(path: &Path) -> Result<String> {
 fn hash_directory(
     directory: &Path,
     base_dir: &Path,
+    excluded: &ExcludedPaths,
     hashes: &mut HashMap<String, String>,
 ) -> Result<()> {
-    for entry in WalkDir::new(directory) {
+    let mut files = Vec::new();
+    let entries = WalkDir::new(directory)
+        .into_iter()
+        .filter_entry(|entry| !excluded.contains(entry.path()));
+    for entry in entries {
         let entry = entry.map_err(|error| crate::error::BambooError::WalkDir {
             path: directory.to_path_buf(),
             message: error.to_string(),
         })?;
-
-        if !entry.file_type().is_file() {
-            continue;
+        if entry.file_type().is_file() {
+            files.push(entry.into_path());
         }
-
-        let path = entry.path();
-        let hash = hash_file(path)?;
-        let relative = path
-            .strip_prefix(base_dir)
-            .unwrap_or(path)
-            .to_string_lossy()
-            .replace('\\', "/");
-        hashes.insert(relative, hash);
     }
 
+    let hashed = files
+        .par_iter()
+        .map(|path| {
+            let relative = path
+                .strip_prefix(base_dir)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            Ok((relative, hash_file(path)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    hashes.extend(hashed);
+
     Ok(())
 }
 
 /// Compares fresh content hashes against a previously-persisted
 /// [`BuildState`] and decides whether the upcoming build needs to be
 /// [`Full`](ChangeClassification::Full) or
-/// [`Targeted`](ChangeClassification::Targeted).
+/// [`Targeted`](ChangeClassification::Targeted). A changed template is
+/// mapped to its dependents through `dependencies`; templates with no
+/// recorded dependents (new files, render hooks, or a cache from an older
+/// version) still force a full rebuild.
 pub fn classify_changes(
     old_hashes: &HashMap<String, String>,
     new_hashes: &HashMap<String, String>,
+    dependencies: &TemplateDependencies,
 ) -> ChangeClassification {
     let mut changed_files = Vec::new();
 
@@ -167,6 +286,7 @@ pub fn classify_changes(
     if changed_files.is_empty() && !has_deletions {
         return ChangeClassification::Targeted {
             changed_files: vec![],
+            template_targets: vec![],
         };
     }
 
@@ -174,15 +294,51 @@ pub fn classify_changes(
         .iter()
         .any(|path| path.to_string_lossy() == "bamboo.toml");
 
-    let has_template_change = changed_files
-        .iter()
-        .any(|path| path.to_string_lossy().starts_with("templates/"));
-
-    if has_config_change || has_template_change || has_deletions {
+    if has_config_change || has_deletions {
         return ChangeClassification::Full;
     }
 
-    ChangeClassification::Targeted { changed_files }
+    let mut template_targets = Vec::new();
+    for path in &changed_files {
+        let path_str = path.to_string_lossy().replace('\\', "/");
+        let Some(template) = path_str.strip_prefix("templates/") else {
+            continue;
+        };
+        let Some(targets) = dependencies.targets_for(template) else {
+            return ChangeClassification::Full;
+        };
+        for target in targets {
+            if !template_targets.contains(target) {
+                template_targets.push(target.clone());
+            }
+        }
+    }
+
+    ChangeClassification::Targeted {
+        changed_files,
+        template_targets,
+    }
+}
+
+/// Project-relative paths added, modified, or deleted between two
+/// [`compute_content_hashes`] snapshots, sorted.
+pub fn changed_paths(
+    old_hashes: &HashMap<String, String>,
+    new_hashes: &HashMap<String, String>,
+) -> Vec<String> {
+    let mut changed: Vec<String> = new_hashes
+        .iter()
+        .filter(|(path, hash)| old_hashes.get(*path) != Some(*hash))
+        .map(|(path, _)| path.clone())
+        .chain(
+            old_hashes
+                .keys()
+                .filter(|path| !new_hashes.contains_key(*path))
+                .cloned(),
+        )
+        .collect();
+    changed.sort();
+    changed
 }
 
 /// Translates a [`ChangeClassification`] into the set of
@@ -194,8 +350,11 @@ pub fn expand_targets(classification: &ChangeClassification) -> HashSet<RenderTa
             targets.insert(RenderTarget::All);
             targets
         }
-        ChangeClassification::Targeted { changed_files } => {
-            let mut targets = HashSet::new();
+        ChangeClassification::Targeted {
+            changed_files,
+            template_targets,
+        } => {
+            let mut targets: HashSet<RenderTarget> = template_targets.iter().cloned().collect();
 
             if changed_files.is_empty() {
                 return targets;
@@ -217,6 +376,10 @@ pub fn expand_targets(classification: &ChangeClassification) -> HashSet<RenderTa
                     targets.insert(RenderTarget::SearchIndex);
                     targets.insert(RenderTarget::AllTaxonomies);
                     targets.insert(RenderTarget::Page("index".to_string()));
+                } else if path_str.starts_with("content/notes/") {
+                    targets.insert(RenderTarget::Notes);
+                    targets.insert(RenderTarget::Feeds);
+                    targets.insert(RenderTarget::Sitemap);
                 } else if let Some(relative) = path_str.strip_prefix("content/") {
                     let components: Vec<&str> = relative.split('/').collect();
 
@@ -306,6 +469,8 @@ mod tests {
                 ("content/about.md".to_string(), "abc123".to_string()),
                 ("bamboo.toml".to_string(), "def456".to_string()),
             ]),
+            template_dependencies: TemplateDependencies::default(),
+            asset_paths: HashMap::new(),
         };
 
         save_cache(dir.path(), &state).unwrap();
@@ -331,33 +496,86 @@ mod tests {
         fs::write(dir.path().join("bamboo.toml"), "title = \"Test\"").unwrap();
         fs::write(dir.path().join("content/about.md"), "about page").unwrap();
 
-        let hashes = compute_content_hashes(dir.path()).unwrap();
+        let hashes = compute_content_hashes(dir.path(), &ExcludedPaths::default()).unwrap();
 
         assert!(hashes.contains_key("bamboo.toml"));
         assert!(hashes.contains_key("content/about.md"));
         assert_eq!(hashes.len(), 2);
     }
 
+    #[test]
+    fn test_compute_content_hashes_skips_excluded_paths() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("static/dist")).unwrap();
+        fs::write(dir.path().join("static/style.css"), "body {}").unwrap();
+        fs::write(dir.path().join("static/dist/index.html"), "built").unwrap();
+        let excluded = ExcludedPaths::new(dir.path(), &dir.path().join("static/dist"));
+
+        let hashes = compute_content_hashes(dir.path(), &excluded).unwrap();
+
+        assert_eq!(hashes.keys().collect::<Vec<_>>(), vec!["static/style.css"]);
+        assert!(excluded.contains(&dir.path().join(".bamboo-cache/build-state.json")));
+        assert!(!excluded.contains(&dir.path().join("static/style.css")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_excluded_paths_match_through_symlinks() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("dist")).unwrap();
+        fs::create_dir_all(dir.path().join("content")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("dist"), dir.path().join("content/dist"))
+            .unwrap();
+        fs::write(dir.path().join("dist/index.html"), "built").unwrap();
+        fs::write(dir.path().join("content/about.md"), "about page").unwrap();
+        let excluded = ExcludedPaths::new(dir.path(), &dir.path().join("dist"));
+
+        assert!(excluded.contains(&dir.path().join("content/dist/index.html")));
+        let hashes = compute_content_hashes(dir.path(), &excluded).unwrap();
+        assert_eq!(hashes.keys().collect::<Vec<_>>(), vec!["content/about.md"]);
+    }
+
     #[test]
     fn test_compute_content_hashes_deterministic() {
         let dir = TempDir::new().unwrap();
         fs::write(dir.path().join("bamboo.toml"), "title = \"Test\"").unwrap();
 
-        let hashes1 = compute_content_hashes(dir.path()).unwrap();
-        let hashes2 = compute_content_hashes(dir.path()).unwrap();
+        let hashes1 = compute_content_hashes(dir.path(), &ExcludedPaths::default()).unwrap();
+        let hashes2 = compute_content_hashes(dir.path(), &ExcludedPaths::default()).unwrap();
 
         assert_eq!(hashes1.get("bamboo.toml"), hashes2.get("bamboo.toml"));
     }
 
+    #[test]
+    fn test_changed_paths() {
+        let old = HashMap::from([
+            ("content/a.md".to_string(), "1".to_string()),
+            ("content/b.md".to_string(), "2".to_string()),
+            ("static/old.css".to_string(), "3".to_string()),
+        ]);
+        let new = HashMap::from([
+            ("content/a.md".to_string(), "1".to_string()),
+            ("content/b.md".to_string(), "changed".to_string()),
+            ("content/c.md".to_string(), "4".to_string()),
+        ]);
+
+        assert_eq!(
+            changed_paths(&old, &new),
+            vec!["content/b.md", "content/c.md", "static/old.css"]
+        );
+        assert!(changed_paths(&new, &new).is_empty());
+    }
+
     #[test]
     fn test_classify_changes_no_changes() {
         let hashes = HashMap::from([("file.md".to_string(), "abc".to_string())]);
-        let classification = classify_changes(&hashes, &hashes);
+        let classification = classify_changes(&hashes, &hashes, &TemplateDependencies::default());
 
         assert_eq!(
             classification,
             ChangeClassification::Targeted {
-                changed_files: vec![]
+                changed_files: vec![],
+                template_targets: vec![],
             }
         );
     }
@@ -367,10 +585,10 @@ mod tests {
         let old = HashMap::from([("content/about.md".to_string(), "abc".to_string())]);
         let new = HashMap::from([("content/about.md".to_string(), "def".to_string())]);
 
-        let classification = classify_changes(&old, &new);
+        let classification = classify_changes(&old, &new, &TemplateDependencies::default());
 
         match classification {
-            ChangeClassification::Targeted { changed_files } => {
+            ChangeClassification::Targeted { changed_files, .. } => {
                 assert_eq!(changed_files.len(), 1);
                 assert_eq!(changed_files[0], PathBuf::from("content/about.md"));
             }
@@ -383,7 +601,7 @@ mod tests {
         let old = HashMap::from([("bamboo.toml".to_string(), "abc".to_string())]);
         let new = HashMap::from([("bamboo.toml".to_string(), "def".to_string())]);
 
-        let classification = classify_changes(&old, &new);
+        let classification = classify_changes(&old, &new, &TemplateDependencies::default());
         assert_eq!(classification, ChangeClassification::Full);
     }
 
@@ -392,7 +610,42 @@ mod tests {
         let old = HashMap::from([("templates/base.html".to_string(), "abc".to_string())]);
         let new = HashMap::from([("templates/base.html".to_string(), "def".to_string())]);
 
-        let classification = classify_changes(&old, &new);
+        let classification = classify_changes(&old, &new, &TemplateDependencies::default());
+        assert_eq!(classification, ChangeClassification::Full);
+    }
+
+    #[test]
+    fn test_classify_changes_tracked_template_change() {
+        let old = HashMap::from([
+            ("templates/post.html".to_string(), "abc".to_string()),
+            (
+                "templates/partials/footer.html".to_string(),
+                "abc".to_string(),
+            ),
+        ]);
+        let mut new = old.clone();
+        new.insert("templates/post.html".to_string(), "def".to_string());
+        let mut dependencies = TemplateDependencies::default();
+        dependencies.record("post.html", RenderTarget::Post("hello".to_string()));
+        dependencies.record("partials/footer.html", RenderTarget::Pagination);
+
+        let classification = classify_changes(&old, &new, &dependencies);
+        let targets = expand_targets(&classification);
+
+        assert_eq!(
+            targets,
+            HashSet::from([RenderTarget::Post("hello".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_classify_changes_untracked_template_change() {
+        let old = HashMap::from([("templates/_hooks/link.html".to_string(), "abc".to_string())]);
+        let new = HashMap::from([("templates/_hooks/link.html".to_string(), "def".to_string())]);
+        let mut dependencies = TemplateDependencies::default();
+        dependencies.record("post.html", RenderTarget::Post("hello".to_string()));
+
+        let classification = classify_changes(&old, &new, &dependencies);
         assert_eq!(classification, ChangeClassification::Full);
     }
 
@@ -401,9 +654,9 @@ mod tests {
         let old = HashMap::new();
         let new = HashMap::from([("content/new.md".to_string(), "abc".to_string())]);
 
-        let classification = classify_changes(&old, &new);
+        let classification = classify_changes(&old, &new, &TemplateDependencies::default());
         match classification {
-            ChangeClassification::Targeted { changed_files } => {
+            ChangeClassification::Targeted { changed_files, .. } => {
                 assert_eq!(changed_files.len(), 1);
             }
             ChangeClassification::Full => panic!("expected Targeted"),
@@ -415,7 +668,7 @@ mod tests {
         let old = HashMap::from([("content/old.md".to_string(), "abc".to_string())]);
         let new = HashMap::new();
 
-        let classification = classify_changes(&old, &new);
+        let classification = classify_changes(&old, &new, &TemplateDependencies::default());
         assert_eq!(classification, ChangeClassification::Full);
     }
 
@@ -429,6 +682,7 @@ mod tests {
     fn test_expand_targets_empty() {
         let targets = expand_targets(&ChangeClassification::Targeted {
             changed_files: vec![],
+            template_targets: vec![],
         });
         assert!(targets.is_empty());
     }
@@ -437,6 +691,7 @@ mod tests {
     fn test_expand_targets_post_change() {
         let targets = expand_targets(&ChangeClassification::Targeted {
             changed_files: vec![PathBuf::from("content/posts/2024-01-15-hello.md")],
+            template_targets: vec![],
         });
 
         assert!(targets.contains(&RenderTarget::Post("hello".to_string())));
@@ -452,6 +707,7 @@ mod tests {
     fn test_expand_targets_page_change() {
         let targets = expand_targets(&ChangeClassification::Targeted {
             changed_files: vec![PathBuf::from("content/about.md")],
+            template_targets: vec![],
         });
 
         assert!(targets.contains(&RenderTarget::Page("about".to_string())));
@@ -464,6 +720,7 @@ mod tests {
     fn test_expand_targets_collection_change() {
         let targets = expand_targets(&ChangeClassification::Targeted {
             changed_files: vec![PathBuf::from("content/docs/intro.md")],
+            template_targets: vec![],
         });
 
         assert!(targets.contains(&RenderTarget::Collection("docs".to_string())));
@@ -472,10 +729,23 @@ mod tests {
         assert!(!targets.contains(&RenderTarget::Pagination));
     }
 
+    #[test]
+    fn test_expand_targets_note_change() {
+        let targets = expand_targets(&ChangeClassification::Targeted {
+            changed_files: vec![PathBuf::from("content/notes/2024-06-01-1430.md")],
+            template_targets: vec![],
+        });
+
+        assert!(targets.contains(&RenderTarget::Notes));
+        assert!(targets.contains(&RenderTarget::Feeds));
+        assert!(!targets.contains(&RenderTarget::Collection("notes".to_string())));
+    }
+
     #[test]
     fn test_expand_targets_static_change() {
         let targets = expand_targets(&ChangeClassification::Targeted {
             changed_files: vec![PathBuf::from("static/style.css")],
+            template_targets: vec![],
         });
 
         assert!(targets.contains(&RenderTarget::All));
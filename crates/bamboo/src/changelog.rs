@@ -0,0 +1,289 @@
+//! Machine-readable release data for changelog sites: a `releases.json`
+//! listing every release newest version first, so tools can ask the
+//! static site for the latest version, plus a `releases.xml` RSS feed
+//! titled by version number (see [`crate::feeds::generate_releases_rss`]).
+//!
+//! Releases are the items of the collection named by `[changelog]` in
+//! `bamboo.toml`. Each item's version comes from its `version`
+//! frontmatter, then `extra.version`, then its title with any leading `v`
+//! removed.
+
+use crate::error::Result;
+use crate::feeds::item_date;
+use crate::parsing::extract_excerpt;
+use crate::sink::RenderSink;
+use crate::types::{CollectionItem, Site};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::Path;
+
+/// Output filename of the release list.
+pub const RELEASES_JSON: &str = "releases.json";
+
+/// `[changelog]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ChangelogConfig {
+    /// Collection holding one item per release. Defaults to `"releases"`.
+    pub collection: String,
+}
+
+impl Default for ChangelogConfig {
+    fn default() -> Self {
+        Self {
+            collection: "releases".to_string(),
+        }
+    }
+}
+
+/// One release in `releases.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Release<'a> {
+    /// Version number without a leading `v`, e.g. `0.6.66`.
+    pub version: String,
+    /// Title of the release page.
+    pub title: &'a str,
+    /// Absolute URL of the release page.
+    pub url: String,
+    /// Release date from `date` frontmatter (`YYYY-MM-DD`).
+    pub date: Option<DateTime<Utc>>,
+    /// `breaking` frontmatter (or `extra.breaking`); `false` when unset.
+    pub breaking: bool,
+    /// Plain-text excerpt of the release notes.
+    pub summary: String,
+    /// The collection item the release was read from.
+    #[serde(skip)]
+    pub item: &'a CollectionItem,
+}
+
+#[derive(Serialize)]
+struct ReleasesFile<'a> {
+    latest: Option<&'a Release<'a>>,
+    releases: &'a [Release<'a>],
+}
+
+/// Every release in the configured changelog collection, newest version
+/// first (ties broken by date). Empty when `[changelog]` isn't set or the
+/// collection doesn't exist.
+pub fn releases(site: &Site) -> Vec<Release<'_>> {
+    let Some(config) = site.config.changelog.as_ref() else {
+        return Vec::new();
+    };
+    let Some(collection) = site.collections.get(&config.collection) else {
+        return Vec::new();
+    };
+    let base_url = site.config.base_url.trim_end_matches('/');
+
+    let mut releases: Vec<Release> = collection
+        .items
+        .iter()
+        .map(|item| {
+            let frontmatter = &item.content.frontmatter;
+            let extra = frontmatter.raw.get("extra");
+            let version = frontmatter
+                .get_string("version")
+                .or_else(|| {
+                    extra
+                        .and_then(|extra| extra.get("version"))
+                        .and_then(|version| version.as_str())
+                        .map(String::from)
+                })
+                .unwrap_or_else(|| item.content.title.clone());
+            let breaking = frontmatter
+                .get_bool("breaking")
+                .or_else(|| {
+                    extra
+                        .and_then(|extra| extra.get("breaking"))
+                        .and_then(|breaking| breaking.as_bool())
+                })
+                .unwrap_or(false);
+            Release {
+                version: version.trim_start_matches(['v', 'V']).to_string(),
+                title: &item.content.title,
+                url: format!("{}{}", base_url, item.content.url),
+                date: item_date(item),
+                breaking,
+                summary: extract_excerpt(&item.content.raw_content, 200).unwrap_or_default(),
+                item,
+            }
+        })
+        .collect();
+
+    releases.sort_by(|left, right| {
+        compare_versions(&right.version, &left.version).then(right.date.cmp(&left.date))
+    });
+    releases
+}
+
+/// Compares dotted version numbers numerically, so `0.6.10` sorts above
+/// `0.6.9`. A pre-release suffix (`1.0.0-rc.1`) sorts below the release.
+/// Components that aren't numbers compare as text.
+pub fn compare_versions(left: &str, right: &str) -> Ordering {
+    let (left_core, left_pre) = split_prerelease(left);
+    let (right_core, right_pre) = split_prerelease(right);
+
+    let mut left_parts = left_core.split('.');
+    let mut right_parts = right_core.split('.');
+    loop {
+        let ordering = match (left_parts.next(), right_parts.next()) {
+            (None, None) => break,
+            (left_part, right_part) => {
+                let left_part = left_part.unwrap_or("0");
+                let right_part = right_part.unwrap_or("0");
+                match (left_part.parse::<u64>(), right_part.parse::<u64>()) {
+                    (Ok(left_number), Ok(right_number)) => left_number.cmp(&right_number),
+                    _ => left_part.cmp(right_part),
+                }
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    match (left_pre, right_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(left_pre), Some(right_pre)) => left_pre.cmp(right_pre),
+    }
+}
+
+fn split_prerelease(version: &str) -> (&str, Option<&str>) {
+    match version.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (version, None),
+    }
+}
+
+/// Writes `releases.json` to `output` with `latest` (the newest
+/// release, or `null`) and the full `releases` list. Does nothing when
+/// the site has no changelog.
+pub fn generate_releases_json(site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
+    let releases = releases(site);
+    if releases.is_empty() {
+        return Ok(());
+    }
+    let file = ReleasesFile {
+        latest: releases.first(),
+        releases: &releases,
+    };
+    let json = serde_json::to_string_pretty(&file).map_err(std::io::Error::other)?;
+    output.write_file(Path::new(RELEASES_JSON), json.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template_test::sample_site;
+    use crate::types::{Collection, CollectionConfig, Content, Frontmatter, PageStats};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn release_item(slug: &str, frontmatter: serde_json::Value) -> CollectionItem {
+        let raw = frontmatter
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        CollectionItem {
+            content: Content {
+                slug: slug.to_string(),
+                title: slug.to_string(),
+                html: "<p>Notes</p>".to_string(),
+                raw_content: "Fixed a bug.".to_string(),
+                frontmatter: Frontmatter::new(raw),
+                path: PathBuf::from(format!("releases/{slug}.md")),
+                source_path: PathBuf::new(),
+                modified: None,
+                template: None,
+                weight: 0,
+                word_count: 3,
+                reading_time: 1,
+                toc: Vec::new(),
+                stats: PageStats::default(),
+                lang: None,
+                translations: Vec::new(),
+                url: format!("/releases/{slug}/"),
+            },
+        }
+    }
+
+    fn changelog_site() -> Site {
+        let mut site = sample_site().unwrap();
+        site.config.changelog = Some(ChangelogConfig::default());
+        site.collections.insert(
+            "releases".to_string(),
+            Collection {
+                name: "releases".to_string(),
+                items: vec![
+                    release_item("v0.6.9", serde_json::json!({"date": "2024-01-10"})),
+                    release_item(
+                        "v0.6.10",
+                        serde_json::json!({
+                            "date": "2024-02-01",
+                            "extra": {"version": "0.6.10", "breaking": true}
+                        }),
+                    ),
+                    release_item(
+                        "next",
+                        serde_json::json!({"version": "0.6.10-rc.1", "date": "2024-01-20"}),
+                    ),
+                ],
+                config: CollectionConfig::default(),
+                index: None,
+            },
+        );
+        site
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("0.6.10", "0.6.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0.0-rc.1", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("2.0.0", "10.0.0"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_releases_sorted_by_version() {
+        let site = changelog_site();
+        let releases = releases(&site);
+        let versions: Vec<&str> = releases
+            .iter()
+            .map(|release| release.version.as_str())
+            .collect();
+        assert_eq!(versions, vec!["0.6.10", "0.6.10-rc.1", "0.6.9"]);
+        assert!(releases[0].breaking);
+        assert!(!releases[2].breaking);
+        assert_eq!(releases[0].url, "https://example.com/releases/v0.6.10/");
+    }
+
+    #[test]
+    fn test_releases_json() {
+        let site = changelog_site();
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_releases_json(&site, output_dir.path()).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(output_dir.path().join(RELEASES_JSON)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["latest"]["version"], "0.6.10");
+        assert_eq!(json["latest"]["date"], "2024-02-01T00:00:00Z");
+        assert_eq!(json["releases"].as_array().unwrap().len(), 3);
+        assert_eq!(json["releases"][2]["summary"], "Fixed a bug.");
+    }
+
+    #[test]
+    fn test_no_changelog_config() {
+        let mut site = changelog_site();
+        site.config.changelog = None;
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_releases_json(&site, output_dir.path()).unwrap();
+        assert!(!output_dir.path().join(RELEASES_JSON).exists());
+    }
+}
@@ -0,0 +1,71 @@
+//! `bamboo deploy <target>`: publishing a built site. Each target has its
+//! own table under `[deploy]` in `bamboo.toml` and its own module:
+//!
+//! - `gh-pages`: [`crate::gh_pages`], `[deploy.gh_pages]`.
+//! - `s3`: [`crate::s3`], `[deploy.s3]`.
+//! - `rsync`: [`crate::rsync`], `[deploy.rsync]`.
+//! - `netlify`: [`crate::netlify`], `[deploy.netlify]`.
+//! - `vercel`: [`crate::vercel_deploy`], `[deploy.vercel]`.
+//!
+//! Targets drive the tools their hosts already provide (`git`, `aws`,
+//! `rsync`, and `curl` for APIs),
+//! the way [`crate::remote`] delegates HTTP to `curl`, and report failures
+//! as [`BambooError::Deploy`].
+
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{BambooError, Result};
+
+/// The `[deploy]` table in `bamboo.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeployConfig {
+    /// `[deploy.gh_pages]`. See [`crate::gh_pages::GhPagesConfig`].
+    #[serde(default)]
+    pub gh_pages: crate::gh_pages::GhPagesConfig,
+    /// `[deploy.s3]`. See [`crate::s3::S3Config`].
+    #[serde(default)]
+    pub s3: crate::s3::S3Config,
+    /// `[deploy.rsync]`. See [`crate::rsync::RsyncConfig`].
+    #[serde(default)]
+    pub rsync: crate::rsync::RsyncConfig,
+    /// `[deploy.netlify]`. See [`crate::netlify::NetlifyConfig`].
+    #[serde(default)]
+    pub netlify: crate::netlify::NetlifyConfig,
+    /// `[deploy.vercel]`. See [`crate::vercel_deploy::VercelDeployConfig`].
+    #[serde(default)]
+    pub vercel: crate::vercel_deploy::VercelDeployConfig,
+}
+
+/// Runs `command` to completion and returns its trimmed stdout, failing
+/// with its stderr if it can't be started or exits unsuccessfully.
+pub(crate) fn run_tool(target: &'static str, command: &mut Command) -> Result<String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command.output().map_err(|error| BambooError::Deploy {
+        target,
+        message: format!("failed to run {program}: {error}"),
+    })?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let status = match output.status.code() {
+        Some(code) => format!("exited with status {code}"),
+        None => "terminated by a signal".to_string(),
+    };
+    let arguments = command
+        .get_args()
+        .map(|argument| argument.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Err(BambooError::Deploy {
+        target,
+        message: if stderr.is_empty() {
+            format!("`{program} {arguments}` {status}")
+        } else {
+            format!("`{program} {arguments}` {status}: {stderr}")
+        },
+    })
+}
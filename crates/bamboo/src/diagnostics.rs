@@ -0,0 +1,374 @@
+//! Turns Tera render failures into [`BambooError::TemplateRender`] with
+//! the failing template, the line and column of the offending expression,
+//! and an excerpt of the surrounding source.
+//!
+//! Tera reports names (`Variable `page.subtitle` not found`, `Filter
+//! 'shout' not found`) but not positions, so the position is found by
+//! searching the template source for the first tag that uses that name.
+
+use crate::error::{BambooError, Result};
+use std::error::Error as _;
+use std::fs;
+use tera::{Context, Tera};
+
+/// Lines of source shown on each side of the offending line.
+const EXCERPT_CONTEXT_LINES: usize = 2;
+
+/// Renders `name` like [`Tera::render`], mapping failures to
+/// [`BambooError::TemplateRender`].
+pub(crate) fn render_template(tera: &Tera, name: &str, context: &Context) -> Result<String> {
+    tera.render(name, context)
+        .map_err(|error| template_render_error(tera, name, &error))
+}
+
+/// Builds a [`BambooError::TemplateRender`] for `error`, raised while
+/// rendering `rendered`.
+pub(crate) fn template_render_error(
+    tera: &Tera,
+    rendered: &str,
+    error: &tera::Error,
+) -> BambooError {
+    let mut messages = vec![error.to_string()];
+    let mut source = error.source();
+    while let Some(cause) = source {
+        messages.push(cause.to_string());
+        source = cause.source();
+    }
+
+    // Only undefined variables and errors inside blocks name the template
+    // they happened in; otherwise search the rendered template, its
+    // parents, and everything they include for the first one that uses
+    // the offending name.
+    let candidates = match failing_template(&messages) {
+        Some(template) => vec![template.to_string()],
+        None => related_templates(tera, rendered),
+    };
+    let located = candidates.iter().find_map(|template| {
+        let source = template_source(tera, template)?;
+        let (line, column) = messages
+            .iter()
+            .find_map(|message| locate(&source, message))?;
+        Some((template, line, column, excerpt(&source, line, column)))
+    });
+
+    let (template, line, column, excerpt) = match located {
+        Some((template, line, column, excerpt)) => {
+            (template.clone(), Some(line), Some(column), Some(excerpt))
+        }
+        None => (candidates[0].clone(), None, None, None),
+    };
+    BambooError::TemplateRender {
+        template,
+        line,
+        column,
+        message: messages.join(": "),
+        excerpt,
+    }
+}
+
+/// The template Tera was inside when it failed: the active template named
+/// by an undefined-variable error, otherwise the parent or block template
+/// named in the "Failed to render" location.
+fn failing_template(messages: &[String]) -> Option<&str> {
+    ["while rendering '", "(error happened in '"]
+        .iter()
+        .find_map(|marker| {
+            messages.iter().find_map(|message| {
+                let start = message.find(marker)? + marker.len();
+                let end = message[start..].find('\'')?;
+                Some(&message[start..start + end])
+            })
+        })
+}
+
+/// `name`, then its parents from nearest to furthest, then every template
+/// those include, transitively.
+fn related_templates(tera: &Tera, name: &str) -> Vec<String> {
+    let mut related = vec![name.to_string()];
+    if let Some(template) = tera.templates.get(name) {
+        related.extend(template.parents.iter().cloned());
+    }
+    let mut index = 0;
+    while index < related.len() {
+        if let Some(template) = tera.templates.get(&related[index]) {
+            let refs = crate::lint::TemplateRefs::from_nodes(&template.ast);
+            for (names, _) in refs.includes {
+                for include in names {
+                    if !related.contains(&include) {
+                        related.push(include);
+                    }
+                }
+            }
+        }
+        index += 1;
+    }
+    related
+}
+
+/// Source text of a loaded template: read from its file when it has one,
+/// otherwise taken from the built-in theme.
+fn template_source(tera: &Tera, name: &str) -> Option<String> {
+    let template = tera.templates.get(name)?;
+    match &template.path {
+        Some(path) => fs::read_to_string(path).ok(),
+        None => crate::theme::builtin_template_source(name).map(str::to_string),
+    }
+}
+
+/// What to look for in the template source for a given Tera message.
+enum Needle<'a> {
+    /// A variable path such as `page.title`.
+    Variable(&'a str),
+    /// A filter name, preceded by `|`.
+    Filter(&'a str),
+    /// A function name, followed by `(`.
+    Function(&'a str),
+    /// A test name, preceded by `is` or `is not`.
+    Test(&'a str),
+    /// A template name inside quotes, as in `{% include "x.html" %}`.
+    Template(&'a str),
+}
+
+fn needle(message: &str) -> Option<Needle<'_>> {
+    let between = |prefix: &str, close: char| -> Option<&str> {
+        let start = message.find(prefix)? + prefix.len();
+        let end = message[start..].find(close)?;
+        Some(&message[start..start + end])
+    };
+    if let Some(name) = between("Variable `", '`') {
+        return Some(Needle::Variable(name));
+    }
+    if let Some(name) = between("Filter '", '\'').or_else(|| between("Filter call '", '\'')) {
+        return Some(Needle::Filter(name));
+    }
+    if let Some(name) = between("Function '", '\'').or_else(|| between("Function call '", '\'')) {
+        return Some(Needle::Function(name));
+    }
+    if let Some(name) = between("Test '", '\'').or_else(|| between("Test call '", '\'')) {
+        return Some(Needle::Test(name));
+    }
+    if message.ends_with("' not found")
+        && let Some(name) = between("Template '", '\'')
+    {
+        return Some(Needle::Template(name));
+    }
+    None
+}
+
+/// 1-based line and column of the first use of the name in `message`
+/// inside a `{{ }}` or `{% %}` tag of `source`.
+fn locate(source: &str, message: &str) -> Option<(usize, usize)> {
+    let needle = needle(message)?;
+    let token = match needle {
+        Needle::Variable(name)
+        | Needle::Filter(name)
+        | Needle::Function(name)
+        | Needle::Test(name)
+        | Needle::Template(name) => name,
+    };
+
+    let offset = source
+        .match_indices(token)
+        .map(|(offset, _)| offset)
+        .find(|&offset| {
+            let before = &source[..offset];
+            let after = &source[offset + token.len()..];
+            let bounded = match needle {
+                Needle::Template(_) => before.ends_with(['"', '\'']),
+                _ => {
+                    !before.ends_with(is_identifier_char)
+                        && !after.starts_with(is_identifier_char)
+                        && !before.ends_with('.')
+                }
+            };
+            let preceded_by = before.trim_end();
+            let fits = match needle {
+                Needle::Filter(_) => preceded_by.ends_with('|'),
+                Needle::Function(_) => {
+                    after.trim_start().starts_with('(') && !preceded_by.ends_with('|')
+                }
+                Needle::Test(_) => {
+                    let preceded_by = preceded_by.strip_suffix("not").unwrap_or(preceded_by);
+                    preceded_by.trim_end().ends_with(" is")
+                }
+                Needle::Variable(_) | Needle::Template(_) => true,
+            };
+            bounded && fits && inside_tag(before)
+        })?;
+
+    let line_start = source[..offset]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let line = source[..offset].matches('\n').count() + 1;
+    let column = source[line_start..offset].chars().count() + 1;
+    Some((line, column))
+}
+
+fn is_identifier_char(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+/// Whether the end of `before` is inside an unclosed `{{` or `{%` tag.
+fn inside_tag(before: &str) -> bool {
+    let opened = before.rfind("{{").max(before.rfind("{%"));
+    let closed = before.rfind("}}").max(before.rfind("%}"));
+    match (opened, closed) {
+        (Some(opened), Some(closed)) => opened > closed,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+/// Numbered lines around `line`, marking it with `>` and putting a caret
+/// under `column`.
+fn excerpt(source: &str, line: usize, column: usize) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let first = line.saturating_sub(EXCERPT_CONTEXT_LINES).max(1);
+    let last = (line + EXCERPT_CONTEXT_LINES).min(lines.len());
+    let width = last.to_string().len();
+
+    let mut output = String::new();
+    for number in first..=last {
+        let marker = if number == line { '>' } else { ' ' };
+        let text = lines.get(number - 1).copied().unwrap_or("");
+        output.push_str(&format!("{marker} {number:>width$} | {text}\n"));
+        if number == line {
+            output.push_str(&format!("  {:>width$} | {}^\n", "", " ".repeat(column - 1)));
+        }
+    }
+    output.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_error(templates: &[(&str, &str)], name: &str) -> BambooError {
+        let dir = tempfile::TempDir::new().unwrap();
+        let files: Vec<(std::path::PathBuf, Option<&str>)> = templates
+            .iter()
+            .map(|(template, source)| {
+                let path = dir.path().join(template);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, source).unwrap();
+                (path, Some(*template))
+            })
+            .collect();
+        let mut tera = Tera::default();
+        tera.add_template_files(files).unwrap();
+        render_template(&tera, name, &Context::new()).unwrap_err()
+    }
+
+    #[test]
+    fn test_undefined_variable_in_partial() {
+        let error = render_error(
+            &[
+                (
+                    "page.html",
+                    "<main>\n{% include \"partials/header.html\" %}\n</main>",
+                ),
+                (
+                    "partials/header.html",
+                    "<header>\n  <h1>{{ title }}</h1>\n  <p>{{ page.subtitle }}</p>\n</header>",
+                ),
+            ],
+            "page.html",
+        );
+        let BambooError::TemplateRender {
+            template,
+            line,
+            column,
+            excerpt,
+            ..
+        } = &error
+        else {
+            panic!("unexpected error: {error:?}");
+        };
+        assert_eq!(template, "partials/header.html");
+        assert_eq!((*line, *column), (Some(2), Some(10)));
+        assert_eq!(
+            excerpt.as_deref().unwrap(),
+            "  1 | <header>\n> 2 |   <h1>{{ title }}</h1>\n    |          ^\n  3 |   <p>{{ page.subtitle }}</p>\n  4 | </header>"
+        );
+        assert!(error.to_string().starts_with(
+            "Template error in 'partials/header.html' at line 2, column 10: Failed to render 'page.html'"
+        ));
+    }
+
+    #[test]
+    fn test_unknown_filter_in_parent_template() {
+        let error = render_error(
+            &[
+                (
+                    "base.html",
+                    "<title>{{ \"shout\" }}</title>\n{% block content %}{% endblock %}\n{{ \"x\" | shout }}",
+                ),
+                (
+                    "child.html",
+                    "{% extends \"base.html\" %}{% block content %}hi{% endblock %}",
+                ),
+            ],
+            "child.html",
+        );
+        let BambooError::TemplateRender {
+            template,
+            line,
+            column,
+            ..
+        } = error
+        else {
+            panic!("unexpected error");
+        };
+        assert_eq!(template, "base.html");
+        assert_eq!((line, column), (Some(3), Some(10)));
+    }
+
+    #[test]
+    fn test_unknown_function_and_test() {
+        let source = "{% if shout %}{{ shout() }}{% endif %}\n{% if x is shout %}{% endif %}";
+        assert_eq!(locate(source, "Function 'shout' not found"), Some((1, 18)));
+        assert_eq!(locate(source, "Test 'shout' not found"), Some((2, 12)));
+        assert_eq!(locate(source, "Variable `shout` not found"), Some((1, 7)));
+        assert_eq!(locate("shout {{ 1 }}", "Variable `shout` not found"), None);
+    }
+
+    #[test]
+    fn test_unlocated_error_has_no_excerpt() {
+        let error = render_error(
+            &[("loop.html", "{% for x in 5 %}{% endfor %}")],
+            "loop.html",
+        );
+        let BambooError::TemplateRender {
+            template,
+            line,
+            excerpt,
+            ..
+        } = &error
+        else {
+            panic!("unexpected error");
+        };
+        assert_eq!(template, "loop.html");
+        assert_eq!(*line, None);
+        assert!(excerpt.is_none());
+        assert!(
+            error
+                .to_string()
+                .starts_with("Template error in 'loop.html': ")
+        );
+    }
+
+    #[test]
+    fn test_builtin_template_source() {
+        let engine = crate::theme::ThemeEngine::new("default").unwrap();
+        assert!(
+            template_source(engine.tera(), "base.html")
+                .unwrap()
+                .contains("<html")
+        );
+        assert!(
+            template_source(engine.tera(), "rss.xml")
+                .unwrap()
+                .contains("<rss")
+        );
+    }
+}
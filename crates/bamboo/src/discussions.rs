@@ -0,0 +1,321 @@
+//! Per-post discussion links (GitHub Discussions or issues, Mastodon
+//! posts) and their reply counts.
+//!
+//! A post's discussion URL comes from its `discussion` frontmatter field,
+//! falling back to the `[discussions] url` pattern in `bamboo.toml`. With
+//! `fetch_counts = true`, reply counts are fetched at build time and
+//! cached in `.bamboo-cache/discussion-counts.json`, so offline builds and
+//! failed lookups reuse the last known count.
+
+use crate::error::{IoContext, Result};
+use crate::remote::{RemoteClient, encode_query_value};
+use crate::types::{Post, Site};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const CACHE_FILE_NAME: &str = "discussion-counts.json";
+
+/// `[discussions]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DiscussionConfig {
+    /// Discussion URL for posts without `discussion` frontmatter.
+    /// `{slug}` and `{url}` (the post's absolute URL, query-encoded) are
+    /// substituted, e.g.
+    /// `"https://github.com/me/blog/discussions?discussions_q={slug}"`.
+    pub url: Option<String>,
+    /// Fetch reply counts for GitHub and Mastodon discussion URLs during
+    /// `bamboo build`. Defaults to `false`.
+    pub fetch_counts: bool,
+}
+
+/// Discussion thread for a post, exposed to templates as
+/// `post.discussion`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Discussion {
+    /// Where readers go to comment.
+    pub url: String,
+    /// Number of replies, when counts are fetched and the URL points at a
+    /// supported platform.
+    #[serde(default)]
+    pub reply_count: Option<u64>,
+}
+
+/// Reply counts keyed by discussion URL.
+pub type DiscussionCache = HashMap<String, u64>;
+
+/// Resolves the discussion URL for `post`: its `discussion` frontmatter
+/// value, otherwise the configured pattern. Returns `None` when neither is
+/// set.
+pub fn discussion_for(
+    config: &DiscussionConfig,
+    base_url: &str,
+    post: &Post,
+) -> Option<Discussion> {
+    let url = post
+        .content
+        .frontmatter
+        .get_string("discussion")
+        .or_else(|| {
+            let pattern = config.url.as_ref()?;
+            let absolute_url = format!("{}{}", base_url.trim_end_matches('/'), post.content.url);
+            Some(
+                pattern
+                    .replace("{slug}", &post.content.slug)
+                    .replace("{url}", &encode_query_value(&absolute_url)),
+            )
+        })?;
+    Some(Discussion {
+        url,
+        reply_count: None,
+    })
+}
+
+/// API endpoint and JSON field holding the reply count for a discussion
+/// URL. Supports GitHub discussions and issues
+/// (`github.com/<owner>/<repo>/discussions/<n>`) and Mastodon statuses
+/// (`<instance>/@<user>/<id>`).
+pub fn reply_count_endpoint(url: &str) -> Option<(String, &'static str)> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let rest = rest.split(['?', '#']).next()?;
+    let mut parts = rest.split('/').filter(|part| !part.is_empty());
+    let host = parts.next()?;
+    let segments: Vec<&str> = parts.collect();
+
+    if host == "github.com" {
+        if let [owner, repo, kind @ ("discussions" | "issues"), number] = segments.as_slice()
+            && number.chars().all(|character| character.is_ascii_digit())
+        {
+            return Some((
+                format!("https://api.github.com/repos/{owner}/{repo}/{kind}/{number}"),
+                "comments",
+            ));
+        }
+        return None;
+    }
+
+    let id = match segments.as_slice() {
+        [user, id] if user.starts_with('@') => id,
+        [_, _, "statuses", id] => id,
+        _ => return None,
+    };
+    if !id.chars().all(|character| character.is_ascii_digit()) {
+        return None;
+    }
+    Some((
+        format!("https://{host}/api/v1/statuses/{id}"),
+        "replies_count",
+    ))
+}
+
+/// Loads the reply count cache from `cache_dir`, returning an empty cache
+/// if the file is missing or malformed.
+pub fn load_discussion_cache(cache_dir: &Path) -> DiscussionCache {
+    fs::read_to_string(cache_dir.join(CACHE_FILE_NAME))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Writes the reply count cache to `cache_dir/discussion-counts.json`.
+pub fn save_discussion_cache(cache_dir: &Path, cache: &DiscussionCache) -> Result<()> {
+    fs::create_dir_all(cache_dir).io_context("create_dir_all", cache_dir)?;
+    let path = cache_dir.join(CACHE_FILE_NAME);
+    let content = serde_json::to_string_pretty(cache)
+        .map_err(|error| std::io::Error::other(error.to_string()))?;
+    fs::write(&path, content).io_context("write", &path)?;
+    Ok(())
+}
+
+/// Fills in `reply_count` for every post discussion on a supported
+/// platform. Counts are fetched through `client` and stored in the cache in
+/// `cache_dir`; offline or on a failed lookup, the cached count is used.
+/// Returns the number of posts that ended up with a count.
+pub fn fetch_reply_counts(
+    client: &RemoteClient,
+    site: &mut Site,
+    cache_dir: &Path,
+) -> Result<usize> {
+    let mut cache = load_discussion_cache(cache_dir);
+    let mut cache_changed = false;
+    let mut counted = 0;
+
+    for discussion in site
+        .posts
+        .iter_mut()
+        .filter_map(|post| post.discussion.as_mut())
+    {
+        let Some((endpoint, field)) = reply_count_endpoint(&discussion.url) else {
+            continue;
+        };
+        if !client.is_offline()
+            && let Ok(count) = fetch_count(client, &endpoint, field)
+            && cache.insert(discussion.url.clone(), count) != Some(count)
+        {
+            cache_changed = true;
+        }
+        discussion.reply_count = cache.get(&discussion.url).copied();
+        if discussion.reply_count.is_some() {
+            counted += 1;
+        }
+    }
+
+    if cache_changed {
+        save_discussion_cache(cache_dir, &cache)?;
+    }
+    Ok(counted)
+}
+
+fn fetch_count(client: &RemoteClient, endpoint: &str, field: &str) -> Result<u64> {
+    let body = client.fetch_text(endpoint)?;
+    let value: serde_json::Value =
+        serde_json::from_str(&body).map_err(|error| crate::error::BambooError::Remote {
+            url: endpoint.to_string(),
+            message: error.to_string(),
+        })?;
+    value
+        .get(field)
+        .and_then(serde_json::Value::as_u64)
+        .ok_or_else(|| crate::error::BambooError::Remote {
+            url: endpoint.to_string(),
+            message: format!("response has no numeric `{field}`"),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Content, Frontmatter, PageStats};
+    use chrono::{TimeZone, Utc};
+    use std::path::PathBuf;
+
+    fn post(slug: &str, discussion: Option<&str>) -> Post {
+        let mut frontmatter = Frontmatter::default();
+        if let Some(url) = discussion {
+            frontmatter
+                .raw
+                .insert("discussion".to_string(), serde_json::json!(url));
+        }
+        Post {
+            content: Content {
+                slug: slug.to_string(),
+                title: slug.to_string(),
+                html: String::new(),
+                raw_content: String::new(),
+                frontmatter,
+                path: PathBuf::from(format!("posts/{slug}/index.html")),
+                source_path: PathBuf::new(),
+                modified: None,
+                template: None,
+                weight: 0,
+                word_count: 0,
+                reading_time: 0,
+                stats: PageStats::default(),
+                lang: None,
+                translations: Vec::new(),
+                toc: vec![],
+                url: format!("/posts/{slug}/"),
+            },
+            date: Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(),
+            excerpt: None,
+            draft: false,
+            tags: vec![],
+            categories: vec![],
+            taxonomies_map: HashMap::new(),
+            redirect_from: vec![],
+            discussion: None,
+            link_preview: None,
+        }
+    }
+
+    #[test]
+    fn test_discussion_for_frontmatter_and_pattern() {
+        let config = DiscussionConfig {
+            url: Some("https://example.com/search?q={url}&s={slug}".to_string()),
+            fetch_counts: false,
+        };
+        let explicit = post("hello", Some("https://github.com/me/blog/discussions/7"));
+        assert_eq!(
+            discussion_for(&config, "https://blog.dev/", &explicit)
+                .unwrap()
+                .url,
+            "https://github.com/me/blog/discussions/7"
+        );
+
+        let fallback = post("hello", None);
+        assert_eq!(
+            discussion_for(&config, "https://blog.dev/", &fallback)
+                .unwrap()
+                .url,
+            "https://example.com/search?q=https%3A%2F%2Fblog.dev%2Fposts%2Fhello%2F&s=hello"
+        );
+        assert!(discussion_for(&DiscussionConfig::default(), "", &fallback).is_none());
+    }
+
+    #[test]
+    fn test_reply_count_endpoint() {
+        assert_eq!(
+            reply_count_endpoint("https://github.com/me/blog/discussions/12#top"),
+            Some((
+                "https://api.github.com/repos/me/blog/discussions/12".to_string(),
+                "comments"
+            ))
+        );
+        assert_eq!(
+            reply_count_endpoint("https://github.com/me/blog/issues/3")
+                .unwrap()
+                .0,
+            "https://api.github.com/repos/me/blog/issues/3"
+        );
+        assert_eq!(
+            reply_count_endpoint("https://mastodon.social/@me/112233"),
+            Some((
+                "https://mastodon.social/api/v1/statuses/112233".to_string(),
+                "replies_count"
+            ))
+        );
+        assert_eq!(
+            reply_count_endpoint("https://hachyderm.io/users/me/statuses/445566")
+                .unwrap()
+                .0,
+            "https://hachyderm.io/api/v1/statuses/445566"
+        );
+        assert!(reply_count_endpoint("https://github.com/me/blog/discussions").is_none());
+        assert!(reply_count_endpoint("https://example.com/comments").is_none());
+    }
+
+    #[test]
+    fn test_fetch_reply_counts_offline_uses_cache() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let cached_url = "https://mastodon.social/@me/1";
+        let mut cache = DiscussionCache::new();
+        cache.insert(cached_url.to_string(), 4);
+        save_discussion_cache(cache_dir.path(), &cache).unwrap();
+
+        let mut first = post("first", None);
+        first.discussion = Some(Discussion {
+            url: cached_url.to_string(),
+            reply_count: None,
+        });
+        let mut second = post("second", None);
+        second.discussion = Some(Discussion {
+            url: "https://mastodon.social/@me/2".to_string(),
+            reply_count: None,
+        });
+        let mut site = crate::template_test::sample_site().unwrap();
+        site.posts = vec![first, second];
+
+        let client = RemoteClient::default().offline(true);
+        let counted = fetch_reply_counts(&client, &mut site, cache_dir.path()).unwrap();
+        assert_eq!(counted, 1);
+        assert_eq!(
+            site.posts[0].discussion.as_ref().unwrap().reply_count,
+            Some(4)
+        );
+        assert_eq!(site.posts[1].discussion.as_ref().unwrap().reply_count, None);
+    }
+}
@@ -55,6 +55,24 @@ pub enum BambooError {
     #[error("Template error: {0}")]
     Template(#[from] tera::Error),
 
+    /// A template failed to render. Carries where the problem is in the
+    /// template source, when it can be located, and a few lines around it.
+    #[error("{}", describe_template_render(template, *line, *column, message, excerpt.as_deref()))]
+    TemplateRender {
+        /// Name of the template the error happened in, which may be a
+        /// partial, parent, or macro file of the one being rendered.
+        template: String,
+        /// 1-based line of the offending expression.
+        line: Option<usize>,
+        /// 1-based column of the offending expression.
+        column: Option<usize>,
+        /// Tera's message with its causes joined by `: `.
+        message: String,
+        /// Numbered source lines around `line`, with the offending line
+        /// marked and a caret under `column`.
+        excerpt: Option<String>,
+    },
+
     /// A content file's frontmatter block was malformed (unclosed delimiter,
     /// unrecognized format, etc.).
     #[error("Invalid frontmatter in file: {path}")]
@@ -136,6 +154,25 @@ pub enum BambooError {
         message: String,
     },
 
+    /// A `[build.hooks]` command couldn't be started or exited
+    /// unsuccessfully.
+    #[error("Build hook `{command}` failed: {message}")]
+    BuildHook {
+        /// The command as written in `bamboo.toml`.
+        command: String,
+        /// Exit status or spawn error.
+        message: String,
+    },
+
+    /// A `[[fonts]]` file couldn't be read, parsed, or subset.
+    #[error("Font processing error in {path}: {message}")]
+    FontProcessing {
+        /// Path of the offending font.
+        path: PathBuf,
+        /// Underlying problem.
+        message: String,
+    },
+
     /// Sass/SCSS compilation failed.
     #[error("Sass compilation error in {path}: {message}")]
     SassCompile {
@@ -145,6 +182,26 @@ pub enum BambooError {
         message: String,
     },
 
+    /// lightningcss could not parse or compile a stylesheet, or an
+    /// `[assets.css]` target is malformed.
+    #[error("CSS compilation error in {path}: {message}")]
+    CssCompile {
+        /// Path of the offending stylesheet.
+        path: PathBuf,
+        /// Compiler message.
+        message: String,
+    },
+
+    /// An `[assets.js]` entry couldn't be bundled: a malformed or
+    /// unsupported `import`/`export`, a missing module, or an import cycle.
+    #[error("JavaScript bundling error in {path}: {message}")]
+    JsBundle {
+        /// Path of the offending script.
+        path: PathBuf,
+        /// Bundler message.
+        message: String,
+    },
+
     /// A `{{< ref "..." >}}` shortcode references a page that doesn't exist.
     #[error("Broken reference '{{{{< ref \"{reference}\" >}}}}': no page found matching that path")]
     BrokenReference {
@@ -152,6 +209,75 @@ pub enum BambooError {
         reference: String,
     },
 
+    /// A network request made during the build failed.
+    #[error("Remote request to '{url}' failed: {message}")]
+    Remote {
+        /// URL that was requested.
+        url: String,
+        /// Transport or HTTP error message.
+        message: String,
+    },
+
+    /// A network request was skipped because offline mode is enabled.
+    #[error("Offline mode is enabled; skipped request to '{url}'")]
+    Offline {
+        /// URL that would have been requested.
+        url: String,
+    },
+
+    /// A `[[generated_pages]]` entry's `data` path matched nothing in
+    /// `data/`.
+    #[error("Generated page '{page}' references missing data '{data}'")]
+    MissingData {
+        /// URL of the generated page.
+        page: String,
+        /// The `data` path as written in `bamboo.toml`.
+        data: String,
+    },
+
+    /// `[wellknown.security]` has no contact, or its `expires` date is
+    /// missing, malformed, or past.
+    #[error("Invalid security.txt: {message}")]
+    SecurityTxt {
+        /// What is wrong with the configuration.
+        message: String,
+    },
+
+    /// The search index couldn't be built.
+    #[error("Search index error: {message}")]
+    SearchIndex {
+        /// What went wrong.
+        message: String,
+    },
+
+    /// A `bamboo deploy` step failed: an external tool couldn't be run or
+    /// exited unsuccessfully, or the target is misconfigured.
+    #[error("Deploy to {target} failed: {message}")]
+    Deploy {
+        /// The deploy target, e.g. `gh-pages`.
+        target: &'static str,
+        /// What went wrong.
+        message: String,
+    },
+
+    /// `bamboo new <kind>` couldn't create the content file.
+    #[error("Cannot create {path}: {message}")]
+    NewContent {
+        /// The file or collection directory involved.
+        path: PathBuf,
+        /// Why it couldn't be created.
+        message: String,
+    },
+
+    /// `bamboo migrate` couldn't convert a site.
+    #[error("Cannot migrate {path}: {message}")]
+    Migrate {
+        /// The site or output directory involved.
+        path: PathBuf,
+        /// Why the migration couldn't proceed.
+        message: String,
+    },
+
     /// Two content files resolved to the same output URL.
     #[error("Duplicate page slug '{slug}' in {path} conflicts with {existing_path}")]
     DuplicatePage {
@@ -164,6 +290,24 @@ pub enum BambooError {
     },
 }
 
+fn describe_template_render(
+    template: &str,
+    line: Option<usize>,
+    column: Option<usize>,
+    message: &str,
+    excerpt: Option<&str>,
+) -> String {
+    let location = match (line, column) {
+        (Some(line), Some(column)) => format!(" at line {line}, column {column}"),
+        (Some(line), None) => format!(" at line {line}"),
+        _ => String::new(),
+    };
+    match excerpt {
+        Some(excerpt) => format!("Template error in '{template}'{location}: {message}\n{excerpt}"),
+        None => format!("Template error in '{template}'{location}: {message}"),
+    }
+}
+
 /// Convenience alias for `Result<T, BambooError>` used throughout the crate.
 pub type Result<T> = std::result::Result<T, BambooError>;
 
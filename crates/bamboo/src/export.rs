@@ -0,0 +1,342 @@
+//! `bamboo export`: the built site model as JSON, for tools that want the
+//! content without the theme, such as a headless frontend, a search
+//! service, or static API endpoints served next to the site.
+//!
+//! [`export_site`] gathers the site settings, the home page, pages, posts,
+//! notes, collections, taxonomy terms with their posts, and `data/` into
+//! one document. Entries serialize the way templates see them: rendered
+//! HTML under `content`, frontmatter under `frontmatter`, and `url`,
+//! `toc`, and `stats` alongside. [`write_export_dir`] splits the same
+//! model into one file per entry, at the entry's output path with a
+//! `.json` extension (`posts/hello/index.json`), plus a [`SITE_EXPORT_FILE`]
+//! index linking to them.
+//!
+//! Objects are written with their keys sorted, so exporting an unchanged
+//! site gives identical files.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{IoContext, Result};
+use crate::parsing::slugify;
+use crate::types::{Collection, Content, Note, Page, Post, Site};
+
+/// Index file [`write_export_dir`] writes at the top of the directory.
+pub const SITE_EXPORT_FILE: &str = "site.json";
+
+/// The whole site, from [`export_site`].
+#[derive(Debug, Clone, Serialize)]
+pub struct SiteExport<'a> {
+    /// Site-wide settings.
+    pub site: SiteInfo<'a>,
+    /// The home page, if `content/_index.md` exists.
+    pub home: Option<&'a Page>,
+    /// Pages other than the home page.
+    pub pages: &'a [Page],
+    /// Posts, newest first.
+    pub posts: &'a [Post],
+    /// Notes, newest first.
+    pub notes: &'a [Note],
+    /// Collections by name.
+    pub collections: &'a HashMap<String, Collection>,
+    /// Terms of each taxonomy, by taxonomy name.
+    pub taxonomies: HashMap<&'a str, Vec<TaxonomyTerm>>,
+    /// Files from `data/`, by name.
+    pub data: &'a HashMap<String, Value>,
+}
+
+/// The settings from `bamboo.toml` an export carries.
+#[derive(Debug, Clone, Serialize)]
+pub struct SiteInfo<'a> {
+    /// Site title.
+    pub title: &'a str,
+    /// Base URL.
+    pub base_url: &'a str,
+    /// Site description.
+    pub description: Option<&'a str>,
+    /// Site author.
+    pub author: Option<&'a str>,
+    /// Site language.
+    pub language: Option<&'a str>,
+    /// The `[extra]` table.
+    pub extra: &'a HashMap<String, Value>,
+}
+
+/// One term of a taxonomy and the posts filed under it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaxonomyTerm {
+    /// Term as written in frontmatter.
+    pub name: String,
+    /// URL slug of the term.
+    pub slug: String,
+    /// Site-relative URL of the term's page.
+    pub url: String,
+    /// URLs of the posts with this term, newest first.
+    pub posts: Vec<String>,
+}
+
+/// An entry in the [`SITE_EXPORT_FILE`] index.
+#[derive(Debug, Clone, Serialize)]
+struct EntrySummary<'a> {
+    title: &'a str,
+    url: &'a str,
+    /// Publication date of posts and notes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<DateTime<Utc>>,
+    /// The entry's own file, relative to the export directory.
+    json: String,
+}
+
+/// Gathers `site` into one exportable document.
+pub fn export_site(site: &Site) -> SiteExport<'_> {
+    let config = &site.config;
+    let taxonomies = config
+        .taxonomies
+        .keys()
+        .map(|taxonomy| (taxonomy.as_str(), taxonomy_terms(site, taxonomy)))
+        .collect();
+    SiteExport {
+        site: SiteInfo {
+            title: &config.title,
+            base_url: &config.base_url,
+            description: config.description.as_deref(),
+            author: config.author.as_deref(),
+            language: config.language.as_deref(),
+            extra: &config.extra,
+        },
+        home: site.home.as_ref(),
+        pages: &site.pages,
+        posts: &site.posts,
+        notes: &site.notes,
+        collections: &site.collections,
+        taxonomies,
+        data: &site.data,
+    }
+}
+
+/// Every term of `taxonomy` used by a post, sorted by name.
+fn taxonomy_terms(site: &Site, taxonomy: &str) -> Vec<TaxonomyTerm> {
+    crate::taxonomy::taxonomy_terms(site, taxonomy)
+        .into_iter()
+        .map(|term| TaxonomyTerm {
+            url: format!("/{taxonomy}/{}/", term.slug),
+            posts: site
+                .posts
+                .iter()
+                .filter(|post| {
+                    post.taxonomies_map
+                        .get(taxonomy)
+                        .is_some_and(|terms| terms.iter().any(|name| slugify(name) == term.slug))
+                })
+                .map(|post| post.content.url.clone())
+                .collect(),
+            name: term.name,
+            slug: term.slug,
+        })
+        .collect()
+}
+
+/// Writes the whole site to `path` as one JSON document.
+pub fn write_export(site: &Site, path: &Path) -> Result<()> {
+    write_json(path, &export_site(site))
+}
+
+/// Writes one JSON file per page, post, note, collection item, and
+/// collection index under `dir`, each at its output path with a `.json`
+/// extension, and a [`SITE_EXPORT_FILE`] with the site settings,
+/// taxonomies, data, and a summary of every entry. Returns the number of
+/// files written.
+pub fn write_export_dir(site: &Site, dir: &Path) -> Result<usize> {
+    let mut written = 0;
+    let home = match &site.home {
+        Some(home) => write_entry(dir, &home.content, None, home, &mut written)?,
+        None => Value::Null,
+    };
+    let pages = site
+        .pages
+        .iter()
+        .map(|page| write_entry(dir, &page.content, None, page, &mut written))
+        .collect::<Result<Vec<_>>>()?;
+    let posts = site
+        .posts
+        .iter()
+        .map(|post| write_entry(dir, &post.content, Some(post.date), post, &mut written))
+        .collect::<Result<Vec<_>>>()?;
+    let notes = site
+        .notes
+        .iter()
+        .map(|note| write_entry(dir, &note.content, Some(note.date), note, &mut written))
+        .collect::<Result<Vec<_>>>()?;
+    let mut collections = serde_json::Map::new();
+    for (name, collection) in &site.collections {
+        let index = match &collection.index {
+            Some(index) => write_entry(dir, index, None, index, &mut written)?,
+            None => Value::Null,
+        };
+        let items = collection
+            .items
+            .iter()
+            .map(|item| write_entry(dir, &item.content, None, item, &mut written))
+            .collect::<Result<Vec<_>>>()?;
+        collections.insert(
+            name.clone(),
+            serde_json::json!({ "index": index, "items": items }),
+        );
+    }
+
+    let export = export_site(site);
+    let index = serde_json::json!({
+        "site": export.site,
+        "home": home,
+        "pages": pages,
+        "posts": posts,
+        "notes": notes,
+        "collections": collections,
+        "taxonomies": export.taxonomies,
+        "data": export.data,
+    });
+    write_json(&dir.join(SITE_EXPORT_FILE), &index)?;
+    Ok(written + 1)
+}
+
+/// Writes `entry` next to where its HTML would go under `dir` and returns
+/// its summary for the index.
+fn write_entry(
+    dir: &Path,
+    content: &Content,
+    date: Option<DateTime<Utc>>,
+    entry: &impl Serialize,
+    written: &mut usize,
+) -> Result<Value> {
+    let file = content.path.with_extension("json");
+    write_json(&dir.join(&file), entry)?;
+    *written += 1;
+    let summary = EntrySummary {
+        title: &content.title,
+        url: &content.url,
+        date,
+        json: file.to_string_lossy().replace('\\', "/"),
+    };
+    Ok(serde_json::to_value(summary).map_err(std::io::Error::other)?)
+}
+
+/// Writes `value` as pretty-printed JSON with sorted keys.
+fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    let value = serde_json::to_value(value).map_err(std::io::Error::other)?;
+    let json = serde_json::to_string_pretty(&value).map_err(std::io::Error::other)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).io_context("create_dir_all", parent)?;
+    }
+    fs::write(path, json + "\n").io_context("write", path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SiteBuilder;
+
+    fn build_site(dir: &Path) -> Site {
+        fs::write(
+            dir.join("bamboo.toml"),
+            "title = \"T\"\nbase_url = \"https://example.com\"\n\n[extra]\nowner = \"me\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.join("content/posts")).unwrap();
+        fs::create_dir_all(dir.join("content/docs")).unwrap();
+        fs::create_dir_all(dir.join("data")).unwrap();
+        fs::write(
+            dir.join("content/about.md"),
+            "+++\ntitle = \"About\"\n+++\n\nAbout *me*.",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("content/posts/2024-01-01-first.md"),
+            "+++\ntitle = \"First\"\ntags = [\"Rust Lang\"]\n+++\n\nHello.",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("content/posts/2024-02-01-second.md"),
+            "+++\ntitle = \"Second\"\ntags = [\"Rust Lang\", \"web\"]\n+++\n\nAgain.",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("content/docs/intro.md"),
+            "+++\ntitle = \"Intro\"\n+++\n\nStart here.",
+        )
+        .unwrap();
+        fs::write(dir.join("content/docs/_collection.toml"), "").unwrap();
+        fs::write(dir.join("data/authors.toml"), "name = \"Ann\"\n").unwrap();
+        SiteBuilder::new(dir).build().unwrap()
+    }
+
+    #[test]
+    fn test_write_export() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let site = build_site(dir.path());
+        let path = dir.path().join("out/site.json");
+
+        write_export(&site, &path).unwrap();
+
+        let json = fs::read_to_string(&path).unwrap();
+        let export: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(export["site"]["title"], "T");
+        assert_eq!(export["site"]["extra"]["owner"], "me");
+        assert_eq!(export["pages"][0]["title"], "About");
+        assert!(
+            export["pages"][0]["content"]
+                .as_str()
+                .unwrap()
+                .contains("<em>me</em>")
+        );
+        assert_eq!(export["posts"][0]["title"], "Second");
+        assert_eq!(export["collections"]["docs"]["items"][0]["title"], "Intro");
+        assert_eq!(export["data"]["authors"]["name"], "Ann");
+        assert_eq!(
+            export["taxonomies"]["tags"][0],
+            serde_json::json!({
+                "name": "Rust Lang",
+                "slug": "rust-lang",
+                "url": "/tags/rust-lang/",
+                "posts": ["/posts/second/", "/posts/first/"],
+            })
+        );
+        assert_eq!(export["taxonomies"]["categories"], serde_json::json!([]));
+
+        write_export(&site, &path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), json);
+    }
+
+    #[test]
+    fn test_write_export_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let site = build_site(dir.path());
+        let out = dir.path().join("api");
+
+        let written = write_export_dir(&site, &out).unwrap();
+
+        assert_eq!(written, 5);
+        let post: Value =
+            serde_json::from_str(&fs::read_to_string(out.join("posts/first/index.json")).unwrap())
+                .unwrap();
+        assert_eq!(post["title"], "First");
+        assert_eq!(post["tags"], serde_json::json!(["Rust Lang"]));
+        let index: Value =
+            serde_json::from_str(&fs::read_to_string(out.join(SITE_EXPORT_FILE)).unwrap()).unwrap();
+        assert_eq!(index["home"], Value::Null);
+        assert_eq!(index["pages"][0]["json"], "about/index.json");
+        assert_eq!(index["posts"][1]["url"], "/posts/first/");
+        assert_eq!(index["posts"][1]["json"], "posts/first/index.json");
+        assert!(index["posts"][1]["date"].is_string());
+        assert_eq!(
+            index["collections"]["docs"]["items"][0]["json"],
+            "docs/intro/index.json"
+        );
+        assert!(index["pages"][0].get("content").is_none());
+        assert_eq!(index["taxonomies"]["tags"][1]["slug"], "web");
+    }
+}
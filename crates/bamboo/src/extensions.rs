@@ -0,0 +1,155 @@
+//! Consumer-supplied Tera filters, functions, and testers.
+//!
+//! Tools embedding bamboo collect their template extensions in a
+//! [`TemplateExtensions`] and hand it to [`crate::ThemeEngine`] and
+//! [`crate::SiteBuilder`], which register them on every Tera instance they
+//! own (page templates, shortcodes, and render hooks).
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tera::{Filter, Function, Tera, Test, Value};
+
+/// A set of named filters, functions, and testers to install on bamboo's
+/// template engines. Entries are reference-counted, so the same set can be
+/// applied to several engines.
+///
+/// ```
+/// use bamboo_ssg::TemplateExtensions;
+///
+/// let mut extensions = TemplateExtensions::new();
+/// extensions.filter("shout", |value: &tera::Value, _: &std::collections::HashMap<String, tera::Value>| {
+///     Ok(tera::Value::String(value.as_str().unwrap_or_default().to_uppercase()))
+/// });
+/// ```
+#[derive(Clone, Default)]
+pub struct TemplateExtensions {
+    filters: Vec<(String, Arc<dyn Filter>)>,
+    functions: Vec<(String, Arc<dyn Function>)>,
+    testers: Vec<(String, Arc<dyn Test>)>,
+}
+
+impl TemplateExtensions {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a filter usable as `{{ value | name }}`. Registering a name
+    /// that bamboo already provides replaces the built-in.
+    pub fn filter(&mut self, name: impl Into<String>, filter: impl Filter + 'static) -> &mut Self {
+        self.filters.push((name.into(), Arc::new(filter)));
+        self
+    }
+
+    /// Adds a global function usable as `{{ name(arg=...) }}`.
+    pub fn function(
+        &mut self,
+        name: impl Into<String>,
+        function: impl Function + 'static,
+    ) -> &mut Self {
+        self.functions.push((name.into(), Arc::new(function)));
+        self
+    }
+
+    /// Adds a tester usable as `{% if value is name %}`.
+    pub fn tester(&mut self, name: impl Into<String>, tester: impl Test + 'static) -> &mut Self {
+        self.testers.push((name.into(), Arc::new(tester)));
+        self
+    }
+
+    /// Returns `true` if nothing has been registered.
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty() && self.functions.is_empty() && self.testers.is_empty()
+    }
+
+    /// Registers every extension on `tera`, in insertion order.
+    pub fn apply(&self, tera: &mut Tera) {
+        for (name, filter) in &self.filters {
+            tera.register_filter(name, SharedFilter(filter.clone()));
+        }
+        for (name, function) in &self.functions {
+            tera.register_function(name, SharedFunction(function.clone()));
+        }
+        for (name, tester) in &self.testers {
+            tera.register_tester(name, SharedTest(tester.clone()));
+        }
+    }
+}
+
+impl std::fmt::Debug for TemplateExtensions {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn names<T: ?Sized>(entries: &[(String, Arc<T>)]) -> Vec<&str> {
+            entries.iter().map(|(name, _)| name.as_str()).collect()
+        }
+        formatter
+            .debug_struct("TemplateExtensions")
+            .field("filters", &names(&self.filters))
+            .field("functions", &names(&self.functions))
+            .field("testers", &names(&self.testers))
+            .finish()
+    }
+}
+
+struct SharedFilter(Arc<dyn Filter>);
+
+impl Filter for SharedFilter {
+    fn filter(&self, value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+        self.0.filter(value, args)
+    }
+
+    fn is_safe(&self) -> bool {
+        self.0.is_safe()
+    }
+}
+
+struct SharedFunction(Arc<dyn Function>);
+
+impl Function for SharedFunction {
+    fn call(&self, args: &HashMap<String, Value>) -> tera::Result<Value> {
+        self.0.call(args)
+    }
+
+    fn is_safe(&self) -> bool {
+        self.0.is_safe()
+    }
+}
+
+struct SharedTest(Arc<dyn Test>);
+
+impl Test for SharedTest {
+    fn test(&self, value: Option<&Value>, args: &[Value]) -> tera::Result<bool> {
+        self.0.test(value, args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tera::Context;
+
+    #[test]
+    fn test_apply_registers_all_kinds() {
+        let mut extensions = TemplateExtensions::new();
+        extensions
+            .filter("shout", |value: &Value, _: &HashMap<String, Value>| {
+                Ok(Value::String(
+                    value.as_str().unwrap_or_default().to_uppercase(),
+                ))
+            })
+            .function("answer", |_: &HashMap<String, Value>| Ok(Value::from(42)))
+            .tester("short", |value: Option<&Value>, _: &[Value]| {
+                Ok(value.and_then(Value::as_str).is_some_and(|s| s.len() < 4))
+            });
+
+        let mut first = Tera::default();
+        let mut second = Tera::default();
+        extensions.apply(&mut first);
+        extensions.apply(&mut second);
+
+        let template = "{{ 'hi' | shout }} {{ answer() }} {% set word = 'abc' %}{% if word is short %}yes{% endif %}";
+        first.add_raw_template("t", template).unwrap();
+        second.add_raw_template("t", template).unwrap();
+        assert_eq!(first.render("t", &Context::new()).unwrap(), "HI 42 yes");
+        assert_eq!(second.render("t", &Context::new()).unwrap(), "HI 42 yes");
+    }
+}
@@ -1,282 +1,502 @@
-//! RSS 2.0 and Atom feed generation for a built [`Site`].
+//! RSS 2.0 and Atom feed generation for a built [`Site`], rendered from the
+//! theme's `rss.xml` and `atom.xml` templates.
 
+use crate::diagnostics::render_template;
 use crate::error::Result;
 use crate::parsing::extract_excerpt;
-use crate::types::{Collection, Site};
-use crate::xml::escape;
-use std::fs;
+use crate::sink::RenderSink;
+use crate::theme::site_metadata;
+use crate::types::{Collection, CollectionFeedOrder, CollectionItem, Content, Site};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
+use tera::{Context, Tera};
 
-/// Writes an RSS 2.0 feed (`rss.xml`) covering every post to `output_dir`.
-pub fn generate_rss(site: &Site, output_dir: &Path) -> Result<()> {
-    let base_url = site.config.base_url.trim_end_matches('/');
-    let language = site.config.language.as_deref().unwrap_or("en");
-
-    let mut items = String::new();
-    for post in &site.posts {
-        let post_url = format!("{}/posts/{}/", base_url, post.content.slug);
-        let pub_date = post.date.format("%a, %d %b %Y %H:%M:%S +0000").to_string();
-        let description = escape(post.excerpt.as_deref().unwrap_or(""));
-
-        items.push_str(&format!(
-            r#"    <item>
-      <title>{}</title>
-      <link>{}</link>
-      <guid>{}</guid>
-      <pubDate>{}</pubDate>
-      <description>{}</description>
-    </item>
-"#,
-            escape(&post.content.title),
-            escape(&post_url),
-            escape(&post_url),
-            pub_date,
-            description
-        ));
+/// Section name that refers to blog posts in [`FeedConfig`]; any other
+/// name besides [`NOTES_SECTION`] refers to a collection.
+pub const POSTS_SECTION: &str = "posts";
+
+/// Section name that refers to notes (`content/notes/`) in
+/// [`FeedConfig`].
+pub const NOTES_SECTION: &str = "notes";
+
+/// `[feed]` table from `bamboo.toml`, selecting what the main `rss.xml`
+/// and `atom.xml` cover. Per-collection feeds are unaffected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedConfig {
+    /// Sections merged into the main feed, newest first: `"posts"` for
+    /// blog posts, `"notes"` for notes, or a collection name. Defaults to
+    /// `["posts"]`; notes always get their own `notes/rss.xml` too.
+    pub sections: Vec<String>,
+    /// Sections left out of the main feed even if listed in `sections`.
+    pub exclude: Vec<String>,
+    /// Put each entry's full rendered HTML in RSS feeds as
+    /// `<content:encoded>`, next to the excerpt in `<description>`. Atom
+    /// feeds always carry it. Defaults to `false`.
+    pub full_content: bool,
+    /// Most entries in each feed, newest first. Every entry by default.
+    #[serde(alias = "feed_limit")]
+    pub limit: Option<usize>,
+    /// Keep draft posts and notes in feeds when the build includes drafts
+    /// (`drafts = true` or `--drafts`). Defaults to `false`, so preview
+    /// builds publish the same feeds as production.
+    pub drafts: bool,
+    /// Output filename of every RSS feed. Defaults to `rss.xml`.
+    pub rss_filename: String,
+    /// Output filename of every Atom feed. Defaults to `atom.xml`.
+    pub atom_filename: String,
+    /// Base URL entry GUIDs (and Atom `<id>`s) are built from instead of
+    /// `base_url`. Set it to the old address after a domain move so feed
+    /// readers keep recognizing entries they've already shown.
+    pub guid_base: Option<String>,
+    /// Whether RSS `<guid>`s are marked as permalinks. Defaults to `true`,
+    /// or `false` when `guid_base` is set, as those GUIDs may no longer
+    /// resolve.
+    pub guid_is_permalink: Option<bool>,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self {
+            sections: vec![POSTS_SECTION.to_string()],
+            exclude: Vec::new(),
+            full_content: false,
+            limit: None,
+            drafts: false,
+            rss_filename: RSS_TEMPLATE.to_string(),
+            atom_filename: ATOM_TEMPLATE.to_string(),
+            guid_base: None,
+            guid_is_permalink: None,
+        }
     }
+}
 
-    let rss = format!(
-        r#"<?xml version="1.0" encoding="UTF-8"?>
-<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
-  <channel>
-    <title>{}</title>
-    <link>{}</link>
-    <description>{}</description>
-    <language>{}</language>
-    <atom:link href="{}/rss.xml" rel="self" type="application/rss+xml"/>
-{}  </channel>
-</rss>
-"#,
-        escape(&site.config.title),
-        escape(base_url),
-        escape(site.config.description.as_deref().unwrap_or("")),
-        escape(language),
-        escape(base_url),
-        items
-    );
+impl FeedConfig {
+    /// Returns `true` if `section` belongs in the main feed.
+    pub fn includes(&self, section: &str) -> bool {
+        self.sections.iter().any(|name| name == section)
+            && !self.exclude.iter().any(|name| name == section)
+    }
 
-    fs::write(output_dir.join("rss.xml"), rss)?;
+    /// Whether RSS `<guid>`s are marked `isPermaLink="true"`.
+    pub fn guid_is_permalink(&self) -> bool {
+        self.guid_is_permalink.unwrap_or(self.guid_base.is_none())
+    }
 
-    Ok(())
+    /// Output filename of feeds rendered with `template` (`rss.xml` or
+    /// `atom.xml`).
+    pub fn filename<'a>(&'a self, template: &'a str) -> &'a str {
+        match template {
+            RSS_TEMPLATE => &self.rss_filename,
+            ATOM_TEMPLATE => &self.atom_filename,
+            _ => template,
+        }
+    }
+}
+
+/// Name of the RSS 2.0 feed template, which is also its output filename.
+pub const RSS_TEMPLATE: &str = "rss.xml";
+
+/// Name of the Atom feed template, which is also its output filename.
+pub const ATOM_TEMPLATE: &str = "atom.xml";
+
+/// Channel-level values inserted into feed templates as `feed`.
+#[derive(Serialize)]
+struct FeedMeta<'a> {
+    title: String,
+    url: String,
+    rss_url: String,
+    atom_url: String,
+    section: Option<&'a str>,
+    description: &'a str,
+    language: &'a str,
+    author: &'a str,
+    updated: DateTime<Utc>,
+    full_content: bool,
+    guid_is_permalink: bool,
 }
 
-/// Writes an Atom feed (`atom.xml`) covering every post to `output_dir`.
-pub fn generate_atom(site: &Site, output_dir: &Path) -> Result<()> {
+/// One feed item from a post, note, or collection item, inserted into
+/// feed templates through `entries`.
+#[derive(Serialize)]
+struct FeedEntry<'a> {
+    title: &'a str,
+    url: String,
+    guid: String,
+    date: Option<DateTime<Utc>>,
+    categories: Vec<String>,
+    summary: String,
+    #[serde(rename = "content")]
+    html: &'a str,
+    page: &'a Content,
+}
+
+/// Entries for the main feed from every section selected by
+/// [`FeedConfig`], newest first; undated collection items sort last.
+fn main_feed_entries(site: &Site) -> Vec<FeedEntry<'_>> {
     let base_url = site.config.base_url.trim_end_matches('/');
+    let mut entries: Vec<FeedEntry> = Vec::new();
 
-    let updated = site
-        .posts
-        .first()
-        .map(|post| post.date.to_rfc3339())
-        .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
-
-    let mut entries = String::new();
-    for post in &site.posts {
-        let post_url = format!("{}/posts/{}/", base_url, post.content.slug);
-        let summary = post.excerpt.as_deref().unwrap_or("");
-
-        entries.push_str(&format!(
-            r#"  <entry>
-    <title>{title}</title>
-    <link href="{url}" rel="alternate"/>
-    <id>{url}</id>
-    <updated>{updated}</updated>
-    <summary type="text">{summary}</summary>
-    <content type="html">{content}</content>
-  </entry>
-"#,
-            title = escape(&post.content.title),
-            url = escape(&post_url),
-            updated = post.date.to_rfc3339(),
-            summary = escape(summary),
-            content = escape(&post.content.html),
-        ));
+    if site.config.feed.includes(POSTS_SECTION) {
+        let posts = site
+            .posts
+            .iter()
+            .filter(|post| site.config.feed.drafts || !post.draft)
+            .filter(|post| post.content.in_feed());
+        entries.extend(posts.map(|post| FeedEntry {
+            title: &post.content.title,
+            url: format!("{}/posts/{}/", base_url, post.content.slug),
+            guid: guid(site, &format!("/posts/{}/", post.content.slug)),
+            date: Some(post.date),
+            categories: unique(post.tags.iter().chain(&post.categories).cloned()),
+            summary: post.excerpt.clone().unwrap_or_default(),
+            html: &post.content.html,
+            page: &post.content,
+        }));
     }
 
-    let author_name = site.config.author.as_deref().unwrap_or(&site.config.title);
-
-    let atom = format!(
-        r#"<?xml version="1.0" encoding="UTF-8"?>
-<feed xmlns="http://www.w3.org/2005/Atom">
-  <title>{title}</title>
-  <link href="{base_url}/" rel="alternate"/>
-  <link href="{base_url}/atom.xml" rel="self"/>
-  <id>{base_url}/</id>
-  <updated>{updated}</updated>
-  <author>
-    <name>{author}</name>
-  </author>
-  <subtitle>{description}</subtitle>
-{entries}</feed>
-"#,
-        title = escape(&site.config.title),
-        base_url = escape(base_url),
-        updated = updated,
-        author = escape(author_name),
-        description = escape(site.config.description.as_deref().unwrap_or("")),
-        entries = entries,
-    );
+    if site.config.feed.includes(NOTES_SECTION) {
+        entries.extend(note_entries(site));
+    }
 
-    fs::write(output_dir.join("atom.xml"), atom)?;
+    let mut collection_names: Vec<&String> = site
+        .collections
+        .keys()
+        .filter(|name| site.config.feed.includes(name))
+        .collect();
+    collection_names.sort();
+    for name in collection_names {
+        entries.extend(collection_entries(site, &site.collections[name]));
+    }
 
-    Ok(())
+    // Stable sort keeps the existing newest-first order of posts on ties.
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.date));
+    entries
 }
 
-/// Writes a per-collection RSS feed to `output_dir/<collection>/rss.xml`.
-pub fn generate_collection_rss(
-    site: &Site,
-    collection_name: &str,
-    collection: &Collection,
-    output_dir: &Path,
-) -> Result<()> {
+/// Feed entries for every note, newest first.
+fn note_entries(site: &Site) -> Vec<FeedEntry<'_>> {
     let base_url = site.config.base_url.trim_end_matches('/');
-    let language = site.config.language.as_deref().unwrap_or("en");
+    site.notes
+        .iter()
+        .filter(|note| site.config.feed.drafts || !note.draft)
+        .filter(|note| note.content.in_feed())
+        .map(|note| FeedEntry {
+            title: &note.content.title,
+            url: format!("{}{}", base_url, note.content.url),
+            guid: guid(site, &note.content.url),
+            date: Some(note.date),
+            categories: note.tags.clone(),
+            summary: crate::search::strip_html_tags(&note.content.html)
+                .trim()
+                .to_string(),
+            html: &note.content.html,
+            page: &note.content,
+        })
+        .collect()
+}
 
-    let mut items = String::new();
-    for item in &collection.items {
-        let item_url = format!("{}{}", base_url, item.content.url);
+/// Feed entries for every item of `collection`, in collection order.
+/// Items with `in_feed = false` are left out.
+fn collection_entries<'a>(site: &Site, collection: &'a Collection) -> Vec<FeedEntry<'a>> {
+    let base_url = site.config.base_url.trim_end_matches('/');
+    collection
+        .items
+        .iter()
+        .filter(|item| item.content.in_feed())
+        .map(|item| FeedEntry {
+            title: &item.content.title,
+            url: format!("{}{}", base_url, item.content.url),
+            guid: guid(site, &item.content.url),
+            date: item_date(item),
+            categories: frontmatter_categories(&item.content),
+            summary: extract_excerpt(&item.content.raw_content, 200).unwrap_or_default(),
+            html: &item.content.html,
+            page: &item.content,
+        })
+        .collect()
+}
 
-        let description = extract_excerpt(&item.content.raw_content, 200).unwrap_or_default();
+/// GUID of the entry at the site-relative `url`: the URL under
+/// [`FeedConfig::guid_base`], or under `base_url` without one.
+fn guid(site: &Site, url: &str) -> String {
+    let base = site
+        .config
+        .feed
+        .guid_base
+        .as_deref()
+        .unwrap_or(&site.config.base_url);
+    format!("{}{url}", base.trim_end_matches('/'))
+}
 
-        let mut item_xml = format!(
-            "    <item>\n      <title>{}</title>\n      <link>{}</link>\n      <guid>{}</guid>\n      <description>{}</description>\n",
-            escape(&item.content.title),
-            escape(&item_url),
-            escape(&item_url),
-            escape(&description),
-        );
+/// `tags` then `categories` frontmatter of `content`, without duplicates.
+fn frontmatter_categories(content: &Content) -> Vec<String> {
+    unique(
+        ["tags", "categories"]
+            .into_iter()
+            .flat_map(|key| content.frontmatter.get_array(key).unwrap_or_default()),
+    )
+}
 
-        if let Some(date_str) = item.content.frontmatter.get_string("date")
-            && let Ok(naive) = chrono::NaiveDate::parse_from_str(&date_str, "%Y-%m-%d")
-        {
-            let datetime = chrono::TimeZone::from_utc_datetime(
-                &chrono::Utc,
-                &naive.and_time(chrono::NaiveTime::MIN),
-            );
-            let pub_date = datetime.format("%a, %d %b %Y %H:%M:%S +0000").to_string();
-            item_xml.push_str(&format!("      <pubDate>{}</pubDate>\n", pub_date));
+/// `categories` in order, keeping the first of each duplicate.
+fn unique(categories: impl Iterator<Item = String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for category in categories {
+        if !unique.contains(&category) {
+            unique.push(category);
         }
-
-        item_xml.push_str("    </item>\n");
-        items.push_str(&item_xml);
     }
+    unique
+}
 
-    let rss = format!(
-        r#"<?xml version="1.0" encoding="UTF-8"?>
-<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
-  <channel>
-    <title>{} - {}</title>
-    <link>{}/{}/</link>
-    <description>{}</description>
-    <language>{}</language>
-    <atom:link href="{}/{}/rss.xml" rel="self" type="application/rss+xml"/>
-{}  </channel>
-</rss>
-"#,
-        escape(&site.config.title),
-        escape(collection_name),
-        escape(base_url),
-        escape(collection_name),
-        escape(site.config.description.as_deref().unwrap_or("")),
-        escape(language),
-        escape(base_url),
-        escape(collection_name),
-        items
-    );
+/// Date of a collection item from its `date` frontmatter.
+pub(crate) fn item_date(item: &CollectionItem) -> Option<DateTime<Utc>> {
+    item.content.frontmatter.get_date("date")
+}
 
-    let collection_dir = output_dir.join(collection_name);
-    fs::create_dir_all(&collection_dir)?;
-    fs::write(collection_dir.join("rss.xml"), rss)?;
+/// Renders `rss.xml` to `output` from the theme's feed template,
+/// covering the sections selected by [`FeedConfig`] (every post by
+/// default).
+pub fn generate_rss(tera: &Tera, site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
+    write_feed(
+        tera,
+        RSS_TEMPLATE,
+        site,
+        &main_feed_entries(site),
+        None,
+        output,
+    )
+}
 
-    Ok(())
+/// Renders `atom.xml` to `output` from the theme's feed template,
+/// covering the sections selected by [`FeedConfig`] (every post by
+/// default).
+pub fn generate_atom(tera: &Tera, site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
+    write_feed(
+        tera,
+        ATOM_TEMPLATE,
+        site,
+        &main_feed_entries(site),
+        None,
+        output,
+    )
 }
 
-/// Writes a per-collection Atom feed to `output_dir/<collection>/atom.xml`.
+/// Renders an RSS feed of every note to `output/notes/rss.xml`.
+pub fn generate_notes_rss(
+    tera: &Tera,
+    site: &Site,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    write_feed(
+        tera,
+        RSS_TEMPLATE,
+        site,
+        &note_entries(site),
+        Some(NOTES_SECTION),
+        output,
+    )
+}
+
+/// Renders an Atom feed of every note to `output/notes/atom.xml`.
+pub fn generate_notes_atom(
+    tera: &Tera,
+    site: &Site,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    write_feed(
+        tera,
+        ATOM_TEMPLATE,
+        site,
+        &note_entries(site),
+        Some(NOTES_SECTION),
+        output,
+    )
+}
+
+/// Entries of a collection's own feed, ordered by its `feed_order`.
+fn collection_feed_entries<'a>(site: &Site, collection: &'a Collection) -> Vec<FeedEntry<'a>> {
+    let mut entries = collection_entries(site, collection);
+    if collection.config.feed_order == CollectionFeedOrder::Date {
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.date));
+    }
+    entries
+}
+
+/// Renders a per-collection RSS feed to `output/<collection>/rss.xml`.
+/// The theme only calls this for collections with `feed = true` in
+/// `_collection.toml`.
+pub fn generate_collection_rss(
+    tera: &Tera,
+    site: &Site,
+    collection_name: &str,
+    collection: &Collection,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    write_feed(
+        tera,
+        RSS_TEMPLATE,
+        site,
+        &collection_feed_entries(site, collection),
+        Some(collection_name),
+        output,
+    )
+}
+
+/// Renders a per-collection Atom feed to `output/<collection>/atom.xml`.
+/// The theme only calls this for collections with `feed = true` in
+/// `_collection.toml`.
 pub fn generate_collection_atom(
+    tera: &Tera,
     site: &Site,
     collection_name: &str,
     collection: &Collection,
-    output_dir: &Path,
+    output: &(impl RenderSink + ?Sized),
 ) -> Result<()> {
-    let base_url = site.config.base_url.trim_end_matches('/');
+    write_feed(
+        tera,
+        ATOM_TEMPLATE,
+        site,
+        &collection_feed_entries(site, collection),
+        Some(collection_name),
+        output,
+    )
+}
 
-    let updated = collection
-        .items
+/// Output filename of the changelog's release feed.
+pub const RELEASES_FEED: &str = "releases.xml";
+
+/// Renders an RSS feed of the `[changelog]` collection to
+/// `output/releases.xml`, newest version first, with each entry
+/// titled by its version number. Does nothing when the site has no
+/// changelog.
+pub fn generate_releases_rss(
+    tera: &Tera,
+    site: &Site,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    let Some(config) = site.config.changelog.as_ref() else {
+        return Ok(());
+    };
+    let releases = crate::changelog::releases(site);
+    if releases.is_empty() {
+        return Ok(());
+    }
+
+    let entries: Vec<FeedEntry> = releases
         .iter()
-        .filter_map(|item| item.content.frontmatter.get_string("date"))
-        .filter_map(|date_str| chrono::NaiveDate::parse_from_str(&date_str, "%Y-%m-%d").ok())
-        .max()
-        .map(|date| {
-            chrono::TimeZone::from_utc_datetime(
-                &chrono::Utc,
-                &date.and_time(chrono::NaiveTime::MIN),
-            )
-            .to_rfc3339()
+        .filter(|release| release.item.content.in_feed())
+        .map(|release| FeedEntry {
+            title: &release.version,
+            url: release.url.clone(),
+            guid: guid(site, &release.item.content.url),
+            date: release.date,
+            categories: frontmatter_categories(&release.item.content),
+            summary: release.summary.clone(),
+            html: &release.item.content.html,
+            page: &release.item.content,
         })
-        .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+        .collect();
+    let entries = limit_entries(site, &entries);
 
-    let mut entries = String::new();
-    for item in &collection.items {
-        let item_url = format!("{}{}", base_url, item.content.url);
+    let base_url = site.config.base_url.trim_end_matches('/');
+    let mut context = feed_context(site, entries, Some(&config.collection));
+    context.insert(
+        "feed",
+        &FeedMeta {
+            title: format!("{} releases", site.config.title),
+            url: format!("{base_url}/{}/", config.collection),
+            rss_url: format!("{base_url}/{RELEASES_FEED}"),
+            atom_url: format!(
+                "{base_url}/{}/{}",
+                config.collection, site.config.feed.atom_filename
+            ),
+            section: Some(&config.collection),
+            description: site.config.description.as_deref().unwrap_or(""),
+            language: site.config.language.as_deref().unwrap_or("en"),
+            author: site.config.author.as_deref().unwrap_or(&site.config.title),
+            updated: entries
+                .iter()
+                .filter_map(|entry| entry.date)
+                .max()
+                .unwrap_or_else(Utc::now),
+            full_content: site.config.feed.full_content,
+            guid_is_permalink: site.config.feed.guid_is_permalink(),
+        },
+    );
+    let rendered = render_template(tera, RSS_TEMPLATE, &context)?;
 
-        let entry_updated = item
-            .content
-            .frontmatter
-            .get_string("date")
-            .and_then(|date_str| chrono::NaiveDate::parse_from_str(&date_str, "%Y-%m-%d").ok())
-            .map(|date| {
-                chrono::TimeZone::from_utc_datetime(
-                    &chrono::Utc,
-                    &date.and_time(chrono::NaiveTime::MIN),
-                )
-                .to_rfc3339()
-            })
-            .unwrap_or_else(|| updated.clone());
-
-        entries.push_str(&format!(
-            r#"  <entry>
-    <title>{title}</title>
-    <link href="{url}" rel="alternate"/>
-    <id>{url}</id>
-    <updated>{updated}</updated>
-    <content type="html">{content}</content>
-  </entry>
-"#,
-            title = escape(&item.content.title),
-            url = escape(&item_url),
-            updated = entry_updated,
-            content = escape(&item.content.html),
-        ));
+    output.write_file(Path::new(RELEASES_FEED), rendered.as_bytes())
+}
+
+/// Context for the site-wide feed templates, as rendered by
+/// [`generate_rss`] and [`generate_atom`].
+pub(crate) fn main_feed_context(site: &Site) -> Context {
+    feed_context(site, limit_entries(site, &main_feed_entries(site)), None)
+}
+
+/// The first `[feed] limit` of `entries`.
+fn limit_entries<'e, 'a>(site: &Site, entries: &'e [FeedEntry<'a>]) -> &'e [FeedEntry<'a>] {
+    match site.config.feed.limit {
+        Some(limit) => &entries[..limit.min(entries.len())],
+        None => entries,
     }
+}
 
-    let author_name = site.config.author.as_deref().unwrap_or(&site.config.title);
-
-    let atom = format!(
-        r#"<?xml version="1.0" encoding="UTF-8"?>
-<feed xmlns="http://www.w3.org/2005/Atom">
-  <title>{title} - {collection}</title>
-  <link href="{base_url}/{collection}/" rel="alternate"/>
-  <link href="{base_url}/{collection}/atom.xml" rel="self"/>
-  <id>{base_url}/{collection}/</id>
-  <updated>{updated}</updated>
-  <author>
-    <name>{author}</name>
-  </author>
-{entries}</feed>
-"#,
-        title = escape(&site.config.title),
-        collection = escape(collection_name),
-        base_url = escape(base_url),
-        updated = updated,
-        author = escape(author_name),
-        entries = entries,
-    );
+/// Template context with `site`, `feed` (channel title, URLs, and
+/// `updated`), and `entries` for a feed covering `section`, or the whole
+/// site when `section` is `None`.
+fn feed_context(site: &Site, entries: &[FeedEntry], section: Option<&str>) -> Context {
+    let base_url = site.config.base_url.trim_end_matches('/');
+    let (title, feed_dir) = match section {
+        Some(section) => (
+            format!("{} - {}", site.config.title, section),
+            format!("{base_url}/{section}"),
+        ),
+        None => (site.config.title.clone(), base_url.to_string()),
+    };
+    let feed = FeedMeta {
+        title,
+        url: format!("{feed_dir}/"),
+        rss_url: format!("{feed_dir}/{}", site.config.feed.rss_filename),
+        atom_url: format!("{feed_dir}/{}", site.config.feed.atom_filename),
+        section,
+        description: site.config.description.as_deref().unwrap_or(""),
+        language: site.config.language.as_deref().unwrap_or("en"),
+        author: site.config.author.as_deref().unwrap_or(&site.config.title),
+        updated: entries
+            .iter()
+            .filter_map(|entry| entry.date)
+            .max()
+            .unwrap_or_else(Utc::now),
+        full_content: site.config.feed.full_content,
+        guid_is_permalink: site.config.feed.guid_is_permalink(),
+    };
 
-    let collection_dir = output_dir.join(collection_name);
-    fs::create_dir_all(&collection_dir)?;
-    fs::write(collection_dir.join("atom.xml"), atom)?;
+    let mut context = Context::new();
+    context.insert("site", &site_metadata(site));
+    context.insert("feed", &feed);
+    context.insert("entries", entries);
+    context
+}
 
-    Ok(())
+/// Renders `template` with [`feed_context`] and writes it under its own
+/// name: the site-wide feed when `section` is `None`, otherwise a feed
+/// under `output/<section>/`.
+fn write_feed(
+    tera: &Tera,
+    template: &str,
+    site: &Site,
+    entries: &[FeedEntry],
+    section: Option<&str>,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    let entries = limit_entries(site, entries);
+    let rendered = render_template(tera, template, &feed_context(site, entries, section))?;
+
+    let path = Path::new(section.unwrap_or("")).join(site.config.feed.filename(template));
+    output.write_file(&path, rendered.as_bytes())
 }
 
 #[cfg(test)]
@@ -287,6 +507,13 @@ mod tests {
     use std::collections::HashMap;
     use std::path::PathBuf;
 
+    fn tera() -> &'static Tera {
+        static ENGINE: std::sync::OnceLock<crate::theme::ThemeEngine> = std::sync::OnceLock::new();
+        ENGINE
+            .get_or_init(|| crate::theme::ThemeEngine::new("default").unwrap())
+            .tera()
+    }
+
     fn test_site_with_post() -> Site {
         let date = Utc.from_utc_datetime(
             &NaiveDate::from_ymd_opt(2024, 6, 15)
@@ -303,12 +530,44 @@ mod tests {
                 posts_per_page: 10,
                 minify: false,
                 fingerprint: false,
+                dedupe_assets: None,
+                static_ignore: Vec::new(),
+                assets: crate::assets::AssetsConfig::default(),
+                build_manifest: false,
+                search_format: crate::search::SearchFormat::Json,
+                search_exclude: Vec::new(),
                 images: None,
+                social_images: None,
                 syntax_theme: crate::types::default_syntax_theme(),
                 taxonomies: crate::types::default_taxonomies(),
                 math: false,
                 favicon: None,
                 link_check_ignore: Vec::new(),
+                link_archive: None,
+                precompress: None,
+                fonts: Vec::new(),
+                build: crate::build_hooks::BuildConfig::default(),
+                deploy: crate::deploy::DeployConfig::default(),
+                remote: crate::remote::RemoteConfig::default(),
+                feed: crate::feeds::FeedConfig::default(),
+                sitemap: crate::sitemap::SitemapConfig::default(),
+                wellknown: crate::wellknown::WellKnownConfig::default(),
+                manifest: None,
+                llms: None,
+                vercel: None,
+                htaccess: None,
+                headers: None,
+                newsletter: None,
+                discussions: crate::discussions::DiscussionConfig::default(),
+                link_previews: crate::link_previews::LinkPreviewConfig::default(),
+                changelog: None,
+                external_resources: None,
+                generate: crate::types::GenerateConfig::default(),
+                drafts: false,
+                future: false,
+                generated_pages: Vec::new(),
+                outputs: Vec::new(),
+                jobs: None,
                 extra: HashMap::new(),
             },
             home: None,
@@ -321,10 +580,15 @@ mod tests {
                     raw_content: "Hello".to_string(),
                     frontmatter: Frontmatter::default(),
                     path: PathBuf::from("posts/hello-world/index.html"),
+                    source_path: PathBuf::new(),
+                    modified: None,
                     template: None,
                     weight: 0,
                     word_count: 1,
                     reading_time: 1,
+                    stats: crate::types::PageStats::default(),
+                    lang: None,
+                    translations: Vec::new(),
                     toc: vec![],
                     url: "/posts/hello-world/".to_string(),
                 },
@@ -335,10 +599,14 @@ mod tests {
                 categories: vec![],
                 taxonomies_map: HashMap::from([("tags".to_string(), vec!["test".to_string()])]),
                 redirect_from: vec![],
+                discussion: None,
+                link_preview: None,
             }],
+            notes: vec![],
             collections: HashMap::new(),
             data: HashMap::new(),
             assets: vec![],
+            image_requests: Vec::new(),
         }
     }
 
@@ -346,7 +614,7 @@ mod tests {
     fn test_rss_basic_structure() {
         let site = test_site_with_post();
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_rss(&site, output_dir.path()).unwrap();
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
 
         let rss_content = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
         assert!(rss_content.contains("<?xml version=\"1.0\""));
@@ -354,13 +622,140 @@ mod tests {
         assert!(rss_content.contains("<title>Test Blog</title>"));
         assert!(rss_content.contains("<title>Hello World</title>"));
         assert!(rss_content.contains("Hello excerpt"));
+        assert!(!rss_content.contains("<content:encoded>"));
+    }
+
+    #[test]
+    fn test_feed_categories_and_guids() {
+        let mut site = test_site_with_post();
+        site.posts[0].categories = vec!["rust".to_string(), "test".to_string()];
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+        let rss = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        assert!(
+            rss.contains(
+                "<guid isPermaLink=\"true\">https://example.com/posts/hello-world/</guid>"
+            )
+        );
+        assert!(rss.contains(
+            "<category>test</category>\n      <category>rust</category>\n      <description>"
+        ));
+
+        site.config.base_url = "https://new.example.com".to_string();
+        site.config.feed.guid_base = Some("https://example.com/".to_string());
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+        generate_atom(tera(), &site, output_dir.path()).unwrap();
+        let rss = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        assert!(rss.contains("<link>https://new.example.com/posts/hello-world/</link>"));
+        assert!(
+            rss.contains(
+                "<guid isPermaLink=\"false\">https://example.com/posts/hello-world/</guid>"
+            )
+        );
+        let atom = std::fs::read_to_string(output_dir.path().join("atom.xml")).unwrap();
+        assert!(atom.contains("<id>https://example.com/posts/hello-world/</id>"));
+        assert!(atom.contains("<category term=\"rust\"/>"));
+
+        site.config.feed.guid_is_permalink = Some(true);
+        assert!(site.config.feed.guid_is_permalink());
+    }
+
+    #[test]
+    fn test_rss_full_content() {
+        let mut site = test_site_with_post();
+        site.config.feed.full_content = true;
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+
+        let rss_content = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        assert!(rss_content.contains("xmlns:content=\"http://purl.org/rss/1.0/modules/content/\""));
+        assert!(rss_content.contains("<description>Hello excerpt</description>"));
+        assert!(rss_content.contains("<content:encoded>&lt;p&gt;"));
+    }
+
+    #[test]
+    fn test_feed_limit_and_drafts() {
+        let mut site = test_site_with_post();
+        let mut older = site.posts[0].clone();
+        older.content.title = "Older Post".to_string();
+        older.date -= chrono::Duration::days(1);
+        let mut draft = site.posts[0].clone();
+        draft.content.title = "Draft Post".to_string();
+        draft.draft = true;
+        site.posts.extend([older, draft]);
+        let output_dir = tempfile::TempDir::new().unwrap();
+
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+        let rss = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        assert!(rss.contains("Older Post"));
+        assert!(!rss.contains("Draft Post"));
+
+        site.config.feed.limit = Some(1);
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+        let rss = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        assert!(rss.contains("Hello World"));
+        assert!(!rss.contains("Older Post"));
+
+        site.config.feed.limit = None;
+        site.config.feed.drafts = true;
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+        let rss = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        assert!(rss.contains("Draft Post"));
+    }
+
+    #[test]
+    fn test_in_feed_false() {
+        let mut site = test_site_with_post();
+        let mut micro = site.posts[0].clone();
+        micro.content.title = "Micro Post".to_string();
+        micro
+            .content
+            .frontmatter
+            .raw
+            .insert("in_feed".to_string(), serde_json::json!(false));
+        site.posts.push(micro);
+        let mut docs = test_collection();
+        docs.items[0]
+            .content
+            .frontmatter
+            .raw
+            .insert("in_feed".to_string(), serde_json::json!(false));
+        let output_dir = tempfile::TempDir::new().unwrap();
+
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+        let rss = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        assert!(rss.contains("Hello World"));
+        assert!(!rss.contains("Micro Post"));
+
+        generate_collection_rss(tera(), &site, "docs", &docs, output_dir.path()).unwrap();
+        let rss = std::fs::read_to_string(output_dir.path().join("docs/rss.xml")).unwrap();
+        assert!(!rss.contains("Introduction"));
+    }
+
+    #[test]
+    fn test_feed_filenames() {
+        let mut site = test_site_with_post();
+        site.config.feed = toml::from_str(
+            "feed_limit = 5\nrss_filename = \"feed.xml\"\natom_filename = \"index.atom\"",
+        )
+        .unwrap();
+        assert_eq!(site.config.feed.limit, Some(5));
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+        generate_atom(tera(), &site, output_dir.path()).unwrap();
+
+        assert!(!output_dir.path().join("rss.xml").exists());
+        let atom = std::fs::read_to_string(output_dir.path().join("index.atom")).unwrap();
+        assert!(atom.contains("https://example.com/index.atom"));
+        let rss = std::fs::read_to_string(output_dir.path().join("feed.xml")).unwrap();
+        assert!(rss.contains("<title>Hello World</title>"));
     }
 
     #[test]
     fn test_atom_basic_structure() {
         let site = test_site_with_post();
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_atom(&site, output_dir.path()).unwrap();
+        generate_atom(tera(), &site, output_dir.path()).unwrap();
 
         let atom_content = std::fs::read_to_string(output_dir.path().join("atom.xml")).unwrap();
         assert!(atom_content.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\""));
@@ -374,7 +769,7 @@ mod tests {
         let mut site = test_site_with_post();
         site.config.title = "Blog & <Friends>".to_string();
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_rss(&site, output_dir.path()).unwrap();
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
 
         let rss_content = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
         assert!(rss_content.contains("Blog &amp; &lt;Friends&gt;"));
@@ -384,7 +779,7 @@ mod tests {
     fn test_atom_updated_uses_latest_post_date() {
         let site = test_site_with_post();
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_atom(&site, output_dir.path()).unwrap();
+        generate_atom(tera(), &site, output_dir.path()).unwrap();
 
         let atom_content = std::fs::read_to_string(output_dir.path().join("atom.xml")).unwrap();
         assert!(atom_content.contains("2024-06-15"));
@@ -406,14 +801,21 @@ mod tests {
                     raw_content: "Welcome".to_string(),
                     frontmatter,
                     path: PathBuf::from("docs/intro/index.html"),
+                    source_path: PathBuf::new(),
+                    modified: None,
                     template: None,
                     weight: 0,
                     word_count: 1,
                     reading_time: 1,
+                    stats: crate::types::PageStats::default(),
+                    lang: None,
+                    translations: Vec::new(),
                     toc: vec![],
                     url: "/docs/intro/".to_string(),
                 },
             }],
+            config: CollectionConfig::default(),
+            index: None,
         }
     }
 
@@ -422,7 +824,7 @@ mod tests {
         let site = test_site_with_post();
         let collection = test_collection();
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_collection_rss(&site, "docs", &collection, output_dir.path()).unwrap();
+        generate_collection_rss(tera(), &site, "docs", &collection, output_dir.path()).unwrap();
 
         let content = std::fs::read_to_string(output_dir.path().join("docs/rss.xml")).unwrap();
         assert!(content.contains("<title>Test Blog - docs</title>"));
@@ -437,7 +839,7 @@ mod tests {
         let site = test_site_with_post();
         let collection = test_collection();
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_collection_atom(&site, "docs", &collection, output_dir.path()).unwrap();
+        generate_collection_atom(tera(), &site, "docs", &collection, output_dir.path()).unwrap();
 
         let content = std::fs::read_to_string(output_dir.path().join("docs/atom.xml")).unwrap();
         assert!(content.contains("<title>Test Blog - docs</title>"));
@@ -459,21 +861,181 @@ mod tests {
                     raw_content: "A note".to_string(),
                     frontmatter: Frontmatter::default(),
                     path: PathBuf::from("notes/note-1/index.html"),
+                    source_path: PathBuf::new(),
+                    modified: None,
                     template: None,
                     weight: 0,
                     word_count: 2,
                     reading_time: 1,
+                    stats: crate::types::PageStats::default(),
+                    lang: None,
+                    translations: Vec::new(),
                     toc: vec![],
                     url: "/notes/note-1/".to_string(),
                 },
             }],
+            config: CollectionConfig::default(),
+            index: None,
         };
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_collection_rss(&site, "notes", &collection, output_dir.path()).unwrap();
+        generate_collection_rss(tera(), &site, "notes", &collection, output_dir.path()).unwrap();
 
         let content = std::fs::read_to_string(output_dir.path().join("notes/rss.xml")).unwrap();
         assert!(content.contains("<title>Note One</title>"));
         assert!(!content.contains("<pubDate>"));
         assert!(content.contains("<description>A note</description>"));
     }
+
+    #[test]
+    fn test_collection_feed_order() {
+        let site = test_site_with_post();
+        let mut collection = test_collection();
+        let mut older = collection.items[0].clone();
+        older.content.title = "Older".to_string();
+        older
+            .content
+            .frontmatter
+            .raw
+            .insert("date".to_string(), serde_json::json!("2024-01-01"));
+        let mut undated = collection.items[0].clone();
+        undated.content.title = "Undated".to_string();
+        undated.content.frontmatter = Frontmatter::default();
+        collection.items = vec![undated, older, collection.items[0].clone()];
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let titles = |collection: &Collection| {
+            generate_collection_rss(tera(), &site, "docs", collection, output_dir.path()).unwrap();
+            let rss = std::fs::read_to_string(output_dir.path().join("docs/rss.xml")).unwrap();
+            ["Introduction", "Older", "Undated"]
+                .into_iter()
+                .map(|title| rss.find(&format!("<title>{title}</title>")).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let by_date = titles(&collection);
+        assert!(by_date[0] < by_date[1] && by_date[1] < by_date[2]);
+
+        collection.config.feed_order = CollectionFeedOrder::Weight;
+        let by_weight = titles(&collection);
+        assert!(by_weight[2] < by_weight[1] && by_weight[1] < by_weight[0]);
+    }
+
+    #[test]
+    fn test_main_feed_section_selection() {
+        let mut site = test_site_with_post();
+        let mut docs = test_collection();
+        docs.items[0]
+            .content
+            .frontmatter
+            .raw
+            .insert("date".to_string(), serde_json::json!("2024-07-01"));
+        site.collections.insert("docs".to_string(), docs);
+        let output_dir = tempfile::TempDir::new().unwrap();
+
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+        let rss = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        assert!(!rss.contains("Introduction"));
+
+        site.config.feed.sections = vec!["posts".to_string(), "docs".to_string()];
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+        generate_atom(tera(), &site, output_dir.path()).unwrap();
+        let rss = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        let intro = rss.find("<title>Introduction</title>").unwrap();
+        let hello = rss.find("<title>Hello World</title>").unwrap();
+        assert!(intro < hello, "newer collection item should come first");
+        let atom = std::fs::read_to_string(output_dir.path().join("atom.xml")).unwrap();
+        assert!(atom.contains("<updated>2024-07-01T00:00:00+00:00</updated>"));
+        assert!(atom.contains("https://example.com/docs/intro/"));
+
+        site.config.feed.exclude = vec!["posts".to_string()];
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+        let rss = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        assert!(rss.contains("Introduction"));
+        assert!(!rss.contains("Hello World"));
+    }
+
+    #[test]
+    fn test_notes_feeds() {
+        let mut site = test_site_with_post();
+        let mut content = site.posts[0].content.clone();
+        content.title = "Quick thought".to_string();
+        content.html = "<p>Quick thought</p>".to_string();
+        content.url = "/notes/2024-07-01-093000/".to_string();
+        site.notes.push(Note {
+            content,
+            date: Utc.with_ymd_and_hms(2024, 7, 1, 9, 30, 0).unwrap(),
+            draft: false,
+            tags: vec![],
+        });
+        let output_dir = tempfile::TempDir::new().unwrap();
+
+        generate_notes_rss(tera(), &site, output_dir.path()).unwrap();
+        generate_notes_atom(tera(), &site, output_dir.path()).unwrap();
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+
+        let rss = std::fs::read_to_string(output_dir.path().join("notes/rss.xml")).unwrap();
+        assert!(rss.contains("<title>Test Blog - notes</title>"));
+        assert!(rss.contains("<link>https://example.com/notes/2024-07-01-093000/</link>"));
+        assert!(rss.contains("<pubDate>Mon, 01 Jul 2024 09:30:00 +0000</pubDate>"));
+        assert!(rss.contains("<description>Quick thought</description>"));
+        assert!(!rss.contains("Hello World"));
+        let atom = std::fs::read_to_string(output_dir.path().join("notes/atom.xml")).unwrap();
+        assert!(atom.contains("<link href=\"https://example.com/notes/atom.xml\" rel=\"self\"/>"));
+
+        let main = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        assert!(!main.contains("Quick thought"));
+        site.config.feed.sections.push("notes".to_string());
+        generate_rss(tera(), &site, output_dir.path()).unwrap();
+        let main = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        assert!(main.contains("Quick thought"));
+    }
+
+    #[test]
+    fn test_feed_template_override() {
+        let mut site = test_site_with_post();
+        site.posts[0]
+            .content
+            .frontmatter
+            .raw
+            .insert("cover".to_string(), serde_json::json!("/img/hello.jpg"));
+        let override_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(override_dir.path().join("templates")).unwrap();
+        std::fs::write(
+            override_dir.path().join("templates/rss.xml"),
+            r#"<rss xmlns:media="http://search.yahoo.com/mrss/"><title>{{ site.config.title }}</title>{% for entry in entries %}<media:content url="{{ entry.page.frontmatter.cover | safe }}"/>{% endfor %}</rss>"#,
+        )
+        .unwrap();
+        let engine =
+            crate::theme::ThemeEngine::new_with_overrides("default", override_dir.path()).unwrap();
+        let output_dir = tempfile::TempDir::new().unwrap();
+
+        generate_rss(engine.tera(), &site, output_dir.path()).unwrap();
+        generate_atom(engine.tera(), &site, output_dir.path()).unwrap();
+
+        let rss = std::fs::read_to_string(output_dir.path().join("rss.xml")).unwrap();
+        assert!(rss.contains("xmlns:media=\"http://search.yahoo.com/mrss/\""));
+        assert!(rss.contains("<title>Test Blog</title>"));
+        assert!(rss.contains("<media:content url=\"/img/hello.jpg\"/>"));
+        let atom = std::fs::read_to_string(output_dir.path().join("atom.xml")).unwrap();
+        assert!(atom.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\""));
+    }
+
+    #[test]
+    fn test_releases_feed() {
+        let mut site = test_site_with_post();
+        site.config.changelog = Some(crate::changelog::ChangelogConfig::default());
+        let mut releases = test_collection();
+        releases.items[0].content.title = "v1.2.0".to_string();
+        releases.items[0].content.url = "/releases/v1-2-0/".to_string();
+        site.collections.insert("releases".to_string(), releases);
+        let output_dir = tempfile::TempDir::new().unwrap();
+
+        generate_releases_rss(tera(), &site, output_dir.path()).unwrap();
+
+        let rss = std::fs::read_to_string(output_dir.path().join("releases.xml")).unwrap();
+        assert!(rss.contains("<title>Test Blog releases</title>"));
+        assert!(rss.contains("<title>1.2.0</title>"));
+        assert!(rss.contains("<link>https://example.com/releases/v1-2-0/</link>"));
+        assert!(rss.contains("href=\"https://example.com/releases.xml\" rel=\"self\""));
+        assert!(!rss.contains("Hello World"));
+    }
 }
@@ -0,0 +1,946 @@
+//! Self-hosted web fonts declared with `[[fonts]]` in `bamboo.toml`.
+//!
+//! After a directory build, each font under `static/` is cut down to the
+//! characters that appear in the rendered HTML (plus printable ASCII and
+//! common typographic punctuation), wrapped as WOFF, and written under
+//! `fonts/` with a content hash in its name, so it can be cached forever.
+//! Every page then gets the matching `@font-face` rules inlined into its
+//! `<head>`, along with `<link rel="preload">` tags for fonts that ask for
+//! them. The original file is removed from the output.
+//!
+//! TrueType (`glyf`) fonts are subset: glyphs keep their IDs, unused ones
+//! are emptied, and the `cmap` only lists kept characters so browsers fall
+//! back for anything else. `kern` is dropped when `GPOS` covers kerning.
+//! Fonts with a `GSUB` table are wrapped as WOFF without subsetting, since
+//! their ligatures and alternates could point at emptied glyphs. So are
+//! CFF-based OpenType fonts, and WOFF2 files are copied as they are, since
+//! decoding them needs Brotli. Each of these is reported in
+//! [`HostedFont::warning`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::error::{BambooError, Result};
+use crate::types::Site;
+
+/// Characters every subset keeps, whether or not the site uses them:
+/// printable ASCII plus punctuation that typographic filters produce.
+const ALWAYS_KEPT: &str = "\u{a0}\u{2018}\u{2019}\u{201c}\u{201d}\u{2013}\u{2014}\u{2026}\u{2022}";
+
+/// Tables left out of subset fonts.
+const DROPPED_TABLES: [&[u8; 4]; 3] = [b"DSIG", b"hdmx", b"LTSH"];
+
+/// One `[[fonts]]` entry from `bamboo.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FontConfig {
+    /// CSS `font-family` name the site's stylesheets use.
+    pub family: String,
+    /// Font file, relative to `static/` (`"fonts/Inter-Regular.ttf"`).
+    pub src: String,
+    /// CSS `font-weight`: a single weight or a variable font's range
+    /// (`"100 900"`). Defaults to `"400"`.
+    #[serde(default = "default_weight")]
+    pub weight: String,
+    /// CSS `font-style`. Defaults to `"normal"`.
+    #[serde(default = "default_style")]
+    pub style: String,
+    /// CSS `font-display`. Defaults to `"swap"`.
+    #[serde(default = "default_display")]
+    pub display: String,
+    /// If `false`, the font is fingerprinted and declared but keeps every
+    /// glyph. Defaults to `true`.
+    #[serde(default = "default_true")]
+    pub subset: bool,
+    /// If `true`, pages get a `<link rel="preload">` for the font.
+    #[serde(default)]
+    pub preload: bool,
+    /// Extra characters to keep, for text the build can't see (strings
+    /// inserted by scripts, say).
+    #[serde(default)]
+    pub text: String,
+}
+
+fn default_weight() -> String {
+    "400".to_string()
+}
+
+fn default_style() -> String {
+    "normal".to_string()
+}
+
+fn default_display() -> String {
+    "swap".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A font written by [`self_host_fonts`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostedFont {
+    /// Output path relative to the output root.
+    pub path: String,
+    /// CSS `format()` hint: `"woff"` or `"woff2"`.
+    pub format: &'static str,
+    /// Glyphs kept, or `None` if the font wasn't subset.
+    pub glyphs: Option<usize>,
+    /// Why a font with `subset = true` kept every glyph, for the build to
+    /// warn about.
+    pub warning: Option<String>,
+}
+
+/// Subsets, fingerprints, and declares every `[[fonts]]` entry, then
+/// inlines the `@font-face` rules into every HTML page under `output_dir`.
+pub fn self_host_fonts(site: &Site, output_dir: &Path) -> Result<Vec<HostedFont>> {
+    if site.config.fonts.is_empty() {
+        return Ok(Vec::new());
+    }
+    let html_files: Vec<PathBuf> = WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && entry
+                    .path()
+                    .extension()
+                    .is_some_and(|extension| extension == "html")
+        })
+        .map(|entry| entry.into_path())
+        .collect();
+
+    let mut used: BTreeSet<char> = (' '..='~').chain(ALWAYS_KEPT.chars()).collect();
+    for path in &html_files {
+        let html = fs::read_to_string(path)?;
+        used.extend(
+            crate::search::strip_html_tags(&html)
+                .chars()
+                .filter(|character| !character.is_control()),
+        );
+    }
+
+    let base_path = crate::redirects::base_path(site);
+    let mut hosted = Vec::new();
+    let mut rules = String::new();
+    let mut preloads = String::new();
+    for font in &site.config.fonts {
+        let mut characters = used.clone();
+        characters.extend(font.text.chars());
+        let entry = host_font(output_dir, font, &characters)?;
+        let url = format!("{}/{}", base_path, entry.path);
+        rules.push_str(&format!(
+            "@font-face{{font-family:\"{}\";font-style:{};font-weight:{};font-display:{};src:url(\"{}\") format(\"{}\")}}",
+            font.family.replace('"', "\\\""),
+            font.style,
+            font.weight,
+            font.display,
+            url,
+            entry.format
+        ));
+        if font.preload {
+            preloads.push_str(&format!(
+                "<link rel=\"preload\" href=\"{}\" as=\"font\" type=\"font/{}\" crossorigin>",
+                url, entry.format
+            ));
+        }
+        hosted.push(entry);
+    }
+
+    let head = format!("{}<style>{}</style>\n</head>", preloads, rules);
+    for path in &html_files {
+        let html = fs::read_to_string(path)?;
+        if let Some(index) = html.find("</head>") {
+            let mut updated = String::with_capacity(html.len() + head.len());
+            updated.push_str(&html[..index]);
+            updated.push_str(&head);
+            updated.push_str(&html[index + "</head>".len()..]);
+            fs::write(path, updated)?;
+        }
+    }
+    Ok(hosted)
+}
+
+fn host_font(
+    output_dir: &Path,
+    font: &FontConfig,
+    characters: &BTreeSet<char>,
+) -> Result<HostedFont> {
+    let source = output_dir.join(font.src.trim_start_matches('/'));
+    let font_error = |message: String| BambooError::FontProcessing {
+        path: source.clone(),
+        message,
+    };
+    let data = fs::read(&source).map_err(|error| font_error(error.to_string()))?;
+
+    let whole = |reason: &str| {
+        font.subset
+            .then(|| format!("{} {reason}, so it ships every glyph", font.src))
+    };
+    let (bytes, format, glyphs, warning) = if data.starts_with(b"wOF2") {
+        let warning = whole("is WOFF2, which bamboo can't decode to subset");
+        (data.clone(), "woff2", None, warning)
+    } else {
+        let sfnt = if data.starts_with(b"wOFF") {
+            decode_woff(&data).map_err(font_error)?
+        } else {
+            parse_sfnt(&data).map_err(font_error)?
+        };
+        let (sfnt, glyphs, warning) = if !sfnt.tables.contains_key(b"glyf") {
+            (
+                sfnt,
+                None,
+                whole("has CFF outlines, which bamboo can't subset"),
+            )
+        } else if sfnt.tables.contains_key(b"GSUB") {
+            let warning =
+                whole("has a GSUB table (ligatures, alternates) that subsetting would break");
+            (sfnt, None, warning)
+        } else if font.subset {
+            let (subset, glyphs) = subset_truetype(&sfnt, characters).map_err(font_error)?;
+            (subset, Some(glyphs), None)
+        } else {
+            (sfnt, None, None)
+        };
+        (encode_woff(&sfnt), "woff", glyphs, warning)
+    };
+
+    let hash = format!("{:x}", Sha256::digest(&bytes));
+    let path = format!(
+        "fonts/{}-{}-{}.{}.{}",
+        crate::parsing::slugify(&font.family),
+        crate::parsing::slugify(&font.weight),
+        crate::parsing::slugify(&font.style),
+        &hash[..8],
+        format
+    );
+    let destination = output_dir.join(&path);
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&destination, bytes)?;
+    if destination != source {
+        fs::remove_file(&source)?;
+    }
+    Ok(HostedFont {
+        path,
+        format,
+        glyphs,
+        warning,
+    })
+}
+
+/// An sfnt font (TrueType or CFF OpenType) as its raw tables.
+#[derive(Debug, Clone, PartialEq)]
+struct Sfnt {
+    flavor: u32,
+    tables: BTreeMap<[u8; 4], Vec<u8>>,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> std::result::Result<u16, String> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| "truncated font data".to_string())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> std::result::Result<u32, String> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| "truncated font data".to_string())
+}
+
+fn read_tag(data: &[u8], offset: usize) -> std::result::Result<[u8; 4], String> {
+    data.get(offset..offset + 4)
+        .map(|bytes| [bytes[0], bytes[1], bytes[2], bytes[3]])
+        .ok_or_else(|| "truncated font data".to_string())
+}
+
+fn parse_sfnt(data: &[u8]) -> std::result::Result<Sfnt, String> {
+    let flavor = read_u32(data, 0)?;
+    if !matches!(flavor, 0x0001_0000 | 0x7472_7565 | 0x4F54_544F) {
+        return Err("not a TrueType, OpenType, WOFF, or WOFF2 font".to_string());
+    }
+    let count = read_u16(data, 4)? as usize;
+    let mut tables = BTreeMap::new();
+    for index in 0..count {
+        let record = 12 + index * 16;
+        let tag = read_tag(data, record)?;
+        let offset = read_u32(data, record + 8)? as usize;
+        let length = read_u32(data, record + 12)? as usize;
+        let table = data
+            .get(offset..offset + length)
+            .ok_or_else(|| format!("table {} out of bounds", String::from_utf8_lossy(&tag)))?;
+        tables.insert(tag, table.to_vec());
+    }
+    Ok(Sfnt { flavor, tables })
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn pad4(data: &mut Vec<u8>) {
+    while !data.len().is_multiple_of(4) {
+        data.push(0);
+    }
+}
+
+/// Serializes `sfnt`, filling in table checksums and `head`'s
+/// `checkSumAdjustment`. Returns the font and the tables as written.
+fn write_sfnt(sfnt: &Sfnt) -> (Vec<u8>, BTreeMap<[u8; 4], Vec<u8>>) {
+    let mut tables = sfnt.tables.clone();
+    if let Some(head) = tables.get_mut(b"head")
+        && head.len() >= 12
+    {
+        head[8..12].copy_from_slice(&[0; 4]);
+    }
+    let count = tables.len() as u16;
+    let power = if count == 0 {
+        0
+    } else {
+        15 - count.leading_zeros() as u16
+    };
+    let search_range = (1u16 << power) * 16;
+
+    let mut font = Vec::new();
+    font.extend_from_slice(&sfnt.flavor.to_be_bytes());
+    font.extend_from_slice(&count.to_be_bytes());
+    font.extend_from_slice(&search_range.to_be_bytes());
+    font.extend_from_slice(&power.to_be_bytes());
+    font.extend_from_slice(&(count * 16 - search_range).to_be_bytes());
+    let mut offset = 12 + tables.len() * 16;
+    let mut head_offset = None;
+    for (tag, table) in &tables {
+        if tag == b"head" {
+            head_offset = Some(offset);
+        }
+        font.extend_from_slice(tag);
+        font.extend_from_slice(&checksum(table).to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        offset += table.len().next_multiple_of(4);
+    }
+    for table in tables.values() {
+        font.extend_from_slice(table);
+        pad4(&mut font);
+    }
+    if let Some(head_offset) = head_offset {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&font));
+        font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+        if let Some(head) = tables.get_mut(b"head") {
+            head[8..12].copy_from_slice(&adjustment.to_be_bytes());
+        }
+    }
+    (font, tables)
+}
+
+/// Wraps `sfnt` as WOFF 1.0, zlib-compressing each table that shrinks.
+fn encode_woff(sfnt: &Sfnt) -> Vec<u8> {
+    let (font, tables) = write_sfnt(sfnt);
+    let mut directory = Vec::new();
+    let mut data = Vec::new();
+    let header_length = 44 + tables.len() * 20;
+    for (tag, table) in &tables {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        let compressed = encoder
+            .write_all(table)
+            .and_then(|_| encoder.finish())
+            .ok()
+            .filter(|compressed| compressed.len() < table.len());
+        let stored = compressed.as_deref().unwrap_or(table);
+        directory.extend_from_slice(tag);
+        directory.extend_from_slice(&((header_length + data.len()) as u32).to_be_bytes());
+        directory.extend_from_slice(&(stored.len() as u32).to_be_bytes());
+        directory.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        directory.extend_from_slice(&checksum(table).to_be_bytes());
+        data.extend_from_slice(stored);
+        pad4(&mut data);
+    }
+
+    let mut woff = Vec::with_capacity(header_length + data.len());
+    woff.extend_from_slice(b"wOFF");
+    woff.extend_from_slice(&sfnt.flavor.to_be_bytes());
+    woff.extend_from_slice(&((header_length + data.len()) as u32).to_be_bytes());
+    woff.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+    woff.extend_from_slice(&0u16.to_be_bytes());
+    woff.extend_from_slice(&(font.len() as u32).to_be_bytes());
+    woff.extend_from_slice(&1u16.to_be_bytes());
+    woff.extend_from_slice(&0u16.to_be_bytes());
+    woff.extend_from_slice(&[0; 20]);
+    woff.extend_from_slice(&directory);
+    woff.extend_from_slice(&data);
+    woff
+}
+
+fn decode_woff(data: &[u8]) -> std::result::Result<Sfnt, String> {
+    let flavor = read_u32(data, 4)?;
+    let count = read_u16(data, 12)? as usize;
+    let mut tables = BTreeMap::new();
+    for index in 0..count {
+        let entry = 44 + index * 20;
+        let tag = read_tag(data, entry)?;
+        let offset = read_u32(data, entry + 4)? as usize;
+        let compressed_length = read_u32(data, entry + 8)? as usize;
+        let length = read_u32(data, entry + 12)? as usize;
+        let stored = data
+            .get(offset..offset + compressed_length)
+            .ok_or_else(|| format!("table {} out of bounds", String::from_utf8_lossy(&tag)))?;
+        let table = if compressed_length < length {
+            let mut table = Vec::with_capacity(length);
+            ZlibDecoder::new(stored)
+                .read_to_end(&mut table)
+                .map_err(|error| error.to_string())?;
+            table
+        } else {
+            stored.to_vec()
+        };
+        tables.insert(tag, table);
+    }
+    Ok(Sfnt { flavor, tables })
+}
+
+/// Subsets a TrueType font to `characters`. Returns the font and the
+/// number of glyphs kept.
+fn subset_truetype(
+    sfnt: &Sfnt,
+    characters: &BTreeSet<char>,
+) -> std::result::Result<(Sfnt, usize), String> {
+    let table = |tag: &[u8; 4]| {
+        sfnt.tables
+            .get(tag)
+            .ok_or_else(|| format!("missing {} table", String::from_utf8_lossy(tag)))
+    };
+    let head = table(b"head")?;
+    let glyph_count = read_u16(table(b"maxp")?, 4)? as usize;
+    let long_offsets = read_u16(head, 50)? != 0;
+    let loca = table(b"loca")?;
+    let glyf = table(b"glyf")?;
+
+    let mut offsets = Vec::with_capacity(glyph_count + 1);
+    for index in 0..=glyph_count {
+        offsets.push(if long_offsets {
+            read_u32(loca, index * 4)? as usize
+        } else {
+            read_u16(loca, index * 2)? as usize * 2
+        });
+    }
+    let glyph = |id: usize| -> &[u8] {
+        match (offsets.get(id), offsets.get(id + 1)) {
+            (Some(&start), Some(&end)) if start < end && end <= glyf.len() => &glyf[start..end],
+            _ => &[],
+        }
+    };
+
+    let mapping: BTreeMap<u32, u16> = parse_cmap(table(b"cmap")?)?
+        .into_iter()
+        .filter(|(code, id)| {
+            (*id as usize) < glyph_count
+                && char::from_u32(*code).is_some_and(|character| characters.contains(&character))
+        })
+        .collect();
+
+    let mut kept: BTreeSet<u16> = BTreeSet::new();
+    let mut pending: Vec<u16> = std::iter::once(0)
+        .chain(mapping.values().copied())
+        .collect();
+    while let Some(id) = pending.pop() {
+        if !kept.insert(id) {
+            continue;
+        }
+        pending.extend(
+            composite_components(glyph(id as usize))?
+                .into_iter()
+                .filter(|component| {
+                    (*component as usize) < glyph_count && !kept.contains(component)
+                }),
+        );
+    }
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity((glyph_count + 1) * 4);
+    for id in 0..glyph_count {
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        if kept.contains(&(id as u16)) {
+            new_glyf.extend_from_slice(glyph(id));
+            pad4(&mut new_glyf);
+        }
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+    let mut tables = sfnt.tables.clone();
+    for tag in DROPPED_TABLES {
+        tables.remove(tag);
+    }
+    if tables.contains_key(b"GPOS") {
+        // Browsers kern with GPOS; the legacy table only adds weight.
+        tables.remove(b"kern");
+    }
+    if let (Some(hhea), Some(hmtx)) = (sfnt.tables.get(b"hhea"), tables.get_mut(b"hmtx")) {
+        // Metrics of emptied glyphs are never used; zeros compress away.
+        let long_metrics = read_u16(hhea, 34)? as usize;
+        for id in (0..glyph_count).filter(|id| !kept.contains(&(*id as u16))) {
+            let (start, length) = if id < long_metrics {
+                (id * 4, 4)
+            } else {
+                (long_metrics * 4 + (id - long_metrics) * 2, 2)
+            };
+            if let Some(entry) = hmtx.get_mut(start..start + length) {
+                entry.fill(0);
+            }
+        }
+    }
+    let mut new_head = head.clone();
+    new_head[50..52].copy_from_slice(&1u16.to_be_bytes());
+    tables.insert(*b"head", new_head);
+    tables.insert(*b"loca", new_loca);
+    tables.insert(*b"glyf", new_glyf);
+    tables.insert(*b"cmap", build_cmap(&mapping));
+    if let Some(post) = tables.get_mut(b"post")
+        && post.len() >= 32
+    {
+        // Version 3 drops glyph names, which can be most of the table.
+        post.truncate(32);
+        post[..4].copy_from_slice(&0x0003_0000u32.to_be_bytes());
+    }
+    Ok((
+        Sfnt {
+            flavor: sfnt.flavor,
+            tables,
+        },
+        kept.len(),
+    ))
+}
+
+/// Glyph IDs a composite glyph is built from; empty for simple glyphs.
+fn composite_components(glyph: &[u8]) -> std::result::Result<Vec<u16>, String> {
+    const ARGS_ARE_WORDS: u16 = 0x0001;
+    const HAVE_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const HAVE_X_AND_Y_SCALE: u16 = 0x0040;
+    const HAVE_TWO_BY_TWO: u16 = 0x0080;
+
+    if glyph.len() < 10 || (read_u16(glyph, 0)? as i16) >= 0 {
+        return Ok(Vec::new());
+    }
+    let mut components = Vec::new();
+    let mut offset = 10;
+    loop {
+        let flags = read_u16(glyph, offset)?;
+        components.push(read_u16(glyph, offset + 2)?);
+        offset += 4 + if flags & ARGS_ARE_WORDS != 0 { 4 } else { 2 };
+        offset += if flags & HAVE_SCALE != 0 {
+            2
+        } else if flags & HAVE_X_AND_Y_SCALE != 0 {
+            4
+        } else if flags & HAVE_TWO_BY_TWO != 0 {
+            8
+        } else {
+            0
+        };
+        if flags & MORE_COMPONENTS == 0 {
+            return Ok(components);
+        }
+    }
+}
+
+/// Character → glyph mapping from the best Unicode subtable: format 12
+/// if present, otherwise format 4.
+fn parse_cmap(cmap: &[u8]) -> std::result::Result<BTreeMap<u32, u16>, String> {
+    let count = read_u16(cmap, 2)? as usize;
+    let mut best: Option<(u16, usize)> = None;
+    for index in 0..count {
+        let record = 4 + index * 8;
+        let platform = read_u16(cmap, record)?;
+        let encoding = read_u16(cmap, record + 2)?;
+        let offset = read_u32(cmap, record + 4)? as usize;
+        let unicode = platform == 0 || (platform == 3 && matches!(encoding, 1 | 10));
+        if !unicode {
+            continue;
+        }
+        let format = read_u16(cmap, offset)?;
+        if (format == 12 || format == 4) && best.is_none_or(|(best_format, _)| format > best_format)
+        {
+            best = Some((format, offset));
+        }
+    }
+    let (format, offset) = best.ok_or("no Unicode cmap subtable")?;
+    let mut mapping = BTreeMap::new();
+    if format == 12 {
+        let groups = read_u32(cmap, offset + 12)? as usize;
+        for group in 0..groups {
+            let record = offset + 16 + group * 12;
+            let start = read_u32(cmap, record)?;
+            let end = read_u32(cmap, record + 4)?;
+            let first_glyph = read_u32(cmap, record + 8)?;
+            for code in start..=end.min(0x10FFFF) {
+                mapping.insert(code, (first_glyph + code - start) as u16);
+            }
+        }
+    } else {
+        let segments = read_u16(cmap, offset + 6)? as usize / 2;
+        let ends = offset + 14;
+        let starts = ends + segments * 2 + 2;
+        let deltas = starts + segments * 2;
+        let range_offsets = deltas + segments * 2;
+        for segment in 0..segments {
+            let end = read_u16(cmap, ends + segment * 2)?;
+            let start = read_u16(cmap, starts + segment * 2)?;
+            let delta = read_u16(cmap, deltas + segment * 2)?;
+            let range_offset = read_u16(cmap, range_offsets + segment * 2)? as usize;
+            for code in start..=end {
+                if code == 0xFFFF {
+                    break;
+                }
+                let id = if range_offset == 0 {
+                    code.wrapping_add(delta)
+                } else {
+                    let address =
+                        range_offsets + segment * 2 + range_offset + (code - start) as usize * 2;
+                    match read_u16(cmap, address)? {
+                        0 => 0,
+                        id => id.wrapping_add(delta),
+                    }
+                };
+                if id != 0 {
+                    mapping.insert(code as u32, id);
+                }
+            }
+        }
+    }
+    Ok(mapping)
+}
+
+/// Builds a `cmap` with a format 4 subtable for the Basic Multilingual
+/// Plane and, when needed, a format 12 subtable for everything.
+fn build_cmap(mapping: &BTreeMap<u32, u16>) -> Vec<u8> {
+    // Runs of consecutive characters mapped to consecutive glyphs.
+    let mut runs: Vec<(u32, u32, u16)> = Vec::new();
+    for (&code, &id) in mapping {
+        match runs.last_mut() {
+            Some((start, end, first))
+                if *end + 1 == code && *first as u32 + (code - *start) == id as u32 =>
+            {
+                *end = code;
+            }
+            _ => runs.push((code, code, id)),
+        }
+    }
+
+    let mut format4_runs: Vec<(u16, u16, u16)> = runs
+        .iter()
+        .filter(|(start, _, _)| *start <= 0xFFFF)
+        .map(|&(start, end, first)| (start as u16, end.min(0xFFFE) as u16, first))
+        .collect();
+    // The required final segment maps U+FFFF to glyph 0.
+    format4_runs.push((0xFFFF, 0xFFFF, 0));
+    let segments = format4_runs.len() as u16;
+    let power = 15 - segments.leading_zeros() as u16;
+    let search_range = (1u16 << power) * 2;
+    let mut format4 = Vec::new();
+    format4.extend_from_slice(&4u16.to_be_bytes());
+    format4.extend_from_slice(&(16 + segments * 8).to_be_bytes());
+    format4.extend_from_slice(&0u16.to_be_bytes());
+    format4.extend_from_slice(&(segments * 2).to_be_bytes());
+    format4.extend_from_slice(&search_range.to_be_bytes());
+    format4.extend_from_slice(&power.to_be_bytes());
+    format4.extend_from_slice(&(segments * 2 - search_range).to_be_bytes());
+    for (_, end, _) in &format4_runs {
+        format4.extend_from_slice(&end.to_be_bytes());
+    }
+    format4.extend_from_slice(&0u16.to_be_bytes());
+    for (start, _, _) in &format4_runs {
+        format4.extend_from_slice(&start.to_be_bytes());
+    }
+    for (start, _, first) in &format4_runs {
+        format4.extend_from_slice(&first.wrapping_sub(*start).to_be_bytes());
+    }
+    for _ in &format4_runs {
+        format4.extend_from_slice(&0u16.to_be_bytes());
+    }
+
+    let astral = runs.last().is_some_and(|(_, end, _)| *end > 0xFFFF);
+    let mut subtables = vec![((3u16, 1u16), format4)];
+    if astral {
+        let mut format12 = Vec::new();
+        format12.extend_from_slice(&12u16.to_be_bytes());
+        format12.extend_from_slice(&0u16.to_be_bytes());
+        format12.extend_from_slice(&(16 + runs.len() as u32 * 12).to_be_bytes());
+        format12.extend_from_slice(&0u32.to_be_bytes());
+        format12.extend_from_slice(&(runs.len() as u32).to_be_bytes());
+        for (start, end, first) in &runs {
+            format12.extend_from_slice(&start.to_be_bytes());
+            format12.extend_from_slice(&end.to_be_bytes());
+            format12.extend_from_slice(&(*first as u32).to_be_bytes());
+        }
+        subtables.push(((3, 10), format12));
+    }
+
+    let mut cmap = Vec::new();
+    cmap.extend_from_slice(&0u16.to_be_bytes());
+    cmap.extend_from_slice(&(subtables.len() as u16).to_be_bytes());
+    let mut offset = 4 + subtables.len() * 8;
+    for ((platform, encoding), subtable) in &subtables {
+        cmap.extend_from_slice(&platform.to_be_bytes());
+        cmap.extend_from_slice(&encoding.to_be_bytes());
+        cmap.extend_from_slice(&(offset as u32).to_be_bytes());
+        offset += subtable.len();
+    }
+    for (_, subtable) in subtables {
+        cmap.extend_from_slice(&subtable);
+    }
+    cmap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A simple glyph: one contour, a bounding box, and filler outline
+    /// bytes, tagged with `marker` so it can be recognized after subsetting.
+    fn simple_glyph(marker: u8) -> Vec<u8> {
+        let mut glyph = vec![0, 1, 0, 0, 0, 0, 0, 100, 0, 100];
+        glyph.extend_from_slice(&[0, 0, 0, 0, marker, marker]);
+        glyph
+    }
+
+    /// A composite glyph built from `component`, with byte offsets.
+    fn composite_glyph(component: u16) -> Vec<u8> {
+        let mut glyph = vec![0xFF, 0xFF, 0, 0, 0, 0, 0, 100, 0, 100];
+        glyph.extend_from_slice(&0u16.to_be_bytes());
+        glyph.extend_from_slice(&component.to_be_bytes());
+        glyph.extend_from_slice(&[0, 0]);
+        glyph
+    }
+
+    /// A five-glyph TrueType font: `A`→1, `B`→2, `C`→3 (a composite of
+    /// glyph 4, which no character maps to), and `é`→5.
+    fn test_font() -> Vec<u8> {
+        let glyphs = [
+            simple_glyph(0xA0),
+            simple_glyph(0xA1),
+            simple_glyph(0xA2),
+            composite_glyph(4),
+            simple_glyph(0xA4),
+            simple_glyph(0xA5),
+        ];
+        let mut glyf = Vec::new();
+        let mut loca = Vec::new();
+        for glyph in &glyphs {
+            loca.extend_from_slice(&((glyf.len() / 2) as u16).to_be_bytes());
+            glyf.extend_from_slice(glyph);
+        }
+        loca.extend_from_slice(&((glyf.len() / 2) as u16).to_be_bytes());
+
+        let mut head = vec![0u8; 54];
+        head[..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        head[12..16].copy_from_slice(&0x5F0F_3CF5u32.to_be_bytes());
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        let mut maxp = 0x0000_5000u32.to_be_bytes().to_vec();
+        maxp.extend_from_slice(&(glyphs.len() as u16).to_be_bytes());
+        let mut post = 0x0002_0000u32.to_be_bytes().to_vec();
+        post.resize(40, 7);
+
+        let mapping = BTreeMap::from([
+            ('A' as u32, 1),
+            ('B' as u32, 2),
+            ('C' as u32, 3),
+            ('é' as u32, 5),
+        ]);
+        let sfnt = Sfnt {
+            flavor: 0x0001_0000,
+            tables: BTreeMap::from([
+                (*b"head", head),
+                (*b"maxp", maxp),
+                (*b"loca", loca),
+                (*b"glyf", glyf),
+                (*b"cmap", build_cmap(&mapping)),
+                (*b"post", post),
+            ]),
+        };
+        write_sfnt(&sfnt).0
+    }
+
+    fn site_with_fonts(base_url: &str, fonts: &str) -> Site {
+        let config = toml::from_str(&format!(
+            "title = \"Test\"\nbase_url = \"{}\"\n{}",
+            base_url, fonts
+        ))
+        .unwrap();
+        Site {
+            config,
+            home: None,
+            pages: Vec::new(),
+            posts: Vec::new(),
+            notes: Vec::new(),
+            collections: std::collections::HashMap::new(),
+            data: std::collections::HashMap::new(),
+            assets: Vec::new(),
+            image_requests: Vec::new(),
+        }
+    }
+
+    fn glyph_bytes(sfnt: &Sfnt, id: usize) -> Vec<u8> {
+        let loca = &sfnt.tables[b"loca"];
+        let start = read_u32(loca, id * 4).unwrap() as usize;
+        let end = read_u32(loca, id * 4 + 4).unwrap() as usize;
+        sfnt.tables[b"glyf"][start..end].to_vec()
+    }
+
+    #[test]
+    fn test_subset_keeps_used_glyphs_and_components() {
+        let sfnt = parse_sfnt(&test_font()).unwrap();
+        let characters = BTreeSet::from(['A', 'C', 'z']);
+
+        let (subset, glyphs) = subset_truetype(&sfnt, &characters).unwrap();
+
+        assert_eq!(glyphs, 4, "notdef, A, C, and C's component");
+        assert_eq!(
+            parse_cmap(&subset.tables[b"cmap"]).unwrap(),
+            BTreeMap::from([('A' as u32, 1), ('C' as u32, 3)])
+        );
+        assert_eq!(&glyph_bytes(&subset, 1)[14..16], &[0xA1, 0xA1]);
+        assert!(glyph_bytes(&subset, 2).is_empty());
+        assert_eq!(
+            composite_components(&glyph_bytes(&subset, 3)).unwrap(),
+            vec![4]
+        );
+        assert_eq!(&glyph_bytes(&subset, 4)[14..16], &[0xA4, 0xA4]);
+        assert!(glyph_bytes(&subset, 5).is_empty());
+        assert_eq!(read_u16(&subset.tables[b"head"], 50).unwrap(), 1);
+        assert_eq!(subset.tables[b"post"].len(), 32);
+    }
+
+    #[test]
+    fn test_woff_round_trip_and_checksums() {
+        let sfnt = parse_sfnt(&test_font()).unwrap();
+        let woff = encode_woff(&sfnt);
+        assert!(woff.starts_with(b"wOFF"));
+        assert_eq!(read_u32(&woff, 8).unwrap() as usize, woff.len());
+
+        let decoded = decode_woff(&woff).unwrap();
+        assert_eq!(decoded.flavor, sfnt.flavor);
+        assert_eq!(
+            decoded.tables.keys().collect::<Vec<_>>(),
+            sfnt.tables.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(decoded.tables[b"glyf"], sfnt.tables[b"glyf"]);
+
+        // A whole font sums to the magic constant once head is adjusted.
+        let (font, _) = write_sfnt(&decoded);
+        assert_eq!(checksum(&font), 0xB1B0_AFBA);
+    }
+
+    #[test]
+    fn test_cmap_round_trip_with_astral_characters() {
+        let mapping = BTreeMap::from([
+            (0x41, 10),
+            (0x42, 11),
+            (0x43, 12),
+            (0x61, 40),
+            (0x2014, 7),
+            (0x1F600, 90),
+            (0x1F601, 91),
+        ]);
+        let cmap = build_cmap(&mapping);
+        assert_eq!(read_u16(&cmap, 2).unwrap(), 2);
+        assert_eq!(parse_cmap(&cmap).unwrap(), mapping);
+    }
+
+    #[test]
+    fn test_self_host_fonts_end_to_end() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("fonts")).unwrap();
+        fs::write(dir.path().join("fonts/Test.ttf"), test_font()).unwrap();
+        fs::write(
+            dir.path().join("index.html"),
+            "<html><head><title>A</title></head><body>Café</body></html>",
+        )
+        .unwrap();
+        let site = site_with_fonts(
+            "https://example.com/blog",
+            "[[fonts]]\nfamily = \"Test Sans\"\nsrc = \"fonts/Test.ttf\"\npreload = true",
+        );
+
+        let hosted = self_host_fonts(&site, dir.path()).unwrap();
+
+        assert_eq!(hosted.len(), 1);
+        assert_eq!(hosted[0].format, "woff");
+        // notdef, A, B, C, its component, and é from "Café".
+        assert_eq!(hosted[0].glyphs, Some(6));
+        assert!(hosted[0].path.starts_with("fonts/test-sans-400-normal."));
+        assert!(hosted[0].path.ends_with(".woff"));
+        assert!(dir.path().join(&hosted[0].path).exists());
+        assert!(!dir.path().join("fonts/Test.ttf").exists());
+
+        let html = fs::read_to_string(dir.path().join("index.html")).unwrap();
+        let url = format!("/blog/{}", hosted[0].path);
+        assert!(html.contains(&format!(
+            "<link rel=\"preload\" href=\"{}\" as=\"font\" type=\"font/woff\" crossorigin>",
+            url
+        )));
+        assert!(html.contains(&format!(
+            "@font-face{{font-family:\"Test Sans\";font-style:normal;font-weight:400;font-display:swap;src:url(\"{}\") format(\"woff\")}}",
+            url
+        )));
+        assert!(html.contains("</style>\n</head><body>"));
+        assert_eq!(hosted[0].warning, None);
+    }
+
+    #[test]
+    fn test_fonts_kept_whole_warn() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("fonts")).unwrap();
+        let mut sfnt = parse_sfnt(&test_font()).unwrap();
+        sfnt.tables.insert(*b"GSUB", vec![1, 2, 3, 4]);
+        fs::write(dir.path().join("fonts/Liga.ttf"), write_sfnt(&sfnt).0).unwrap();
+        fs::write(dir.path().join("fonts/Packed.woff2"), b"wOF2 data").unwrap();
+        fs::write(dir.path().join("fonts/Whole.woff2"), b"wOF2 whole").unwrap();
+        let site = site_with_fonts(
+            "https://example.com",
+            "[[fonts]]\nfamily = \"Liga\"\nsrc = \"fonts/Liga.ttf\"\n\
+             [[fonts]]\nfamily = \"Packed\"\nsrc = \"fonts/Packed.woff2\"\n\
+             [[fonts]]\nfamily = \"Whole\"\nsrc = \"fonts/Whole.woff2\"\nsubset = false",
+        );
+
+        let hosted = self_host_fonts(&site, dir.path()).unwrap();
+
+        assert_eq!(hosted[0].glyphs, None);
+        let woff = decode_woff(&fs::read(dir.path().join(&hosted[0].path)).unwrap()).unwrap();
+        assert_eq!(woff.tables[b"GSUB"], sfnt.tables[b"GSUB"]);
+        assert_eq!(woff.tables[b"glyf"], sfnt.tables[b"glyf"]);
+        assert!(hosted[0].warning.as_ref().unwrap().contains("GSUB"));
+        assert_eq!(hosted[1].format, "woff2");
+        assert!(
+            hosted[1]
+                .warning
+                .as_ref()
+                .unwrap()
+                .starts_with("fonts/Packed.woff2 is WOFF2")
+        );
+        assert_eq!(hosted[2].warning, None);
+    }
+
+    #[test]
+    fn test_unsupported_font_data() {
+        assert!(parse_sfnt(b"not a font at all").is_err());
+        let dir = tempfile::TempDir::new().unwrap();
+        let site = site_with_fonts(
+            "https://example.com",
+            "[[fonts]]\nfamily = \"Missing\"\nsrc = \"/fonts/missing.ttf\"",
+        );
+        let error = self_host_fonts(&site, dir.path()).unwrap_err().to_string();
+        assert!(error.contains("fonts/missing.ttf"), "{}", error);
+    }
+}
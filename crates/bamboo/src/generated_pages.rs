@@ -0,0 +1,296 @@
+//! Pages rendered straight from a template, with no markdown stub. Each
+//! `[[generated_pages]]` entry in `bamboo.toml` names a URL, a template,
+//! and optionally a data file to render it with, so `/now/` can come from
+//! `data/now.toml` and `/uses/` from `data/uses.yaml`.
+//!
+//! Templates see `page` (`title`, `url`, `description`), `data` (the value
+//! the entry's `data` path points at, or `null`), `site`, `seo`, and `head`.
+//! Generated pages are listed in the sitemap and the search index.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Component, Path, PathBuf};
+use tera::{Context, Tera};
+
+use crate::diagnostics::render_template;
+use crate::error::{BambooError, Result};
+use crate::seo;
+use crate::sink::RenderSink;
+use crate::theme::site_metadata;
+use crate::types::Site;
+
+/// One `[[generated_pages]]` entry.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GeneratedPageConfig {
+    /// URL of the page, such as `/now/`.
+    pub path: String,
+    /// Template to render it with, such as `now.html`.
+    pub template: String,
+    /// Page title. Defaults to the data's `title` field, then the last
+    /// segment of `path` capitalized.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Page description, used for `<meta name="description">` and the
+    /// search index.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Dotted path into `site.data`: `now` for `data/now.toml`, or
+    /// `projects.featured` for one table inside `data/projects.toml`.
+    #[serde(default)]
+    pub data: Option<String>,
+}
+
+/// The `page` variable of a generated page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GeneratedPage {
+    /// Resolved title.
+    pub title: String,
+    /// URL with leading and trailing slashes.
+    pub url: String,
+    /// Description from the entry, if any.
+    pub description: Option<String>,
+}
+
+impl GeneratedPageConfig {
+    /// [`GeneratedPageConfig::path`] with leading and trailing slashes.
+    pub fn url(&self) -> String {
+        let trimmed = self.path.trim_matches('/');
+        if trimmed.is_empty() {
+            "/".to_string()
+        } else {
+            format!("/{trimmed}/")
+        }
+    }
+
+    /// Output file relative to the output directory: `<path>/index.html`.
+    /// Fails if the path would leave the output directory.
+    pub fn output_path(&self) -> Result<PathBuf> {
+        let relative = Path::new(self.path.trim_matches('/'));
+        if relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(BambooError::InvalidPath {
+                path: PathBuf::from(&self.path),
+            });
+        }
+        Ok(relative.join("index.html"))
+    }
+
+    /// The value `data` points at in `site.data`, or `null` without one.
+    pub fn resolve_data<'a>(&self, site: &'a Site) -> Result<&'a Value> {
+        static NULL: Value = Value::Null;
+        let Some(expression) = self.data.as_deref() else {
+            return Ok(&NULL);
+        };
+        let missing = || BambooError::MissingData {
+            page: self.url(),
+            data: expression.to_string(),
+        };
+        let mut segments = expression.split('.');
+        let first = segments.next().ok_or_else(missing)?;
+        let mut value = site.data.get(first).ok_or_else(missing)?;
+        for segment in segments {
+            value = match value {
+                Value::Array(items) => segment
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| items.get(index)),
+                _ => value.get(segment),
+            }
+            .ok_or_else(missing)?;
+        }
+        Ok(value)
+    }
+
+    /// The `page` variable for this entry, given its resolved `data`.
+    pub fn page(&self, data: &Value) -> GeneratedPage {
+        let title = self
+            .title
+            .clone()
+            .or_else(|| {
+                data.get("title")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| {
+                let url = self.url();
+                let segment = url.trim_matches('/').rsplit('/').next().unwrap_or("");
+                let mut characters = segment.chars();
+                characters
+                    .next()
+                    .map(|first| first.to_uppercase().chain(characters).collect())
+                    .unwrap_or_default()
+            });
+        GeneratedPage {
+            title,
+            url: self.url(),
+            description: self.description.clone(),
+        }
+    }
+}
+
+/// Inserts `page`, `data`, and `seo` for `config` into `context`.
+pub fn insert_context(
+    context: &mut Context,
+    site: &Site,
+    config: &GeneratedPageConfig,
+) -> Result<()> {
+    let data = config.resolve_data(site)?;
+    let page = config.page(data);
+    let mut meta = seo::site_seo(site);
+    meta.title = format!("{} | {}", page.title, site.config.title);
+    meta.url = format!("{}{}", site.config.base_url, page.url);
+    if let Some(description) = &page.description {
+        meta.description = description.clone();
+    }
+    context.insert("page", &page);
+    context.insert("data", data);
+    context.insert("head", &crate::head::Head::new(site, &meta));
+    context.insert("seo", &meta);
+    Ok(())
+}
+
+/// Renders every `[[generated_pages]]` entry into `output`.
+pub fn render_generated_pages(
+    tera: &Tera,
+    site: &Site,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    let metadata = site_metadata(site);
+    for config in &site.config.generated_pages {
+        let mut context = Context::new();
+        context.insert("site", &metadata);
+        insert_context(&mut context, site, config)?;
+        let rendered = render_template(tera, &config.template, &context)?;
+
+        output.write_file(&config.output_path()?, rendered.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Every string inside `value`, joined by spaces. The searchable text of a
+/// generated page.
+pub(crate) fn data_text(value: &Value) -> String {
+    fn collect<'a>(value: &'a Value, parts: &mut Vec<&'a str>) {
+        match value {
+            Value::String(text) => parts.push(text),
+            Value::Array(items) => items.iter().for_each(|item| collect(item, parts)),
+            Value::Object(map) => map.values().for_each(|item| collect(item, parts)),
+            _ => {}
+        }
+    }
+    let mut parts = Vec::new();
+    collect(value, &mut parts);
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn now_page(data: Option<&str>) -> GeneratedPageConfig {
+        GeneratedPageConfig {
+            path: "now".to_string(),
+            template: "now.html".to_string(),
+            title: None,
+            description: None,
+            data: data.map(str::to_string),
+        }
+    }
+
+    fn site_with_data() -> Site {
+        let config = toml::from_str(
+            "title = \"Test\"\nbase_url = \"https://example.com\"\n\
+             [[generated_pages]]\npath = \"/now/\"\ntemplate = \"now.html\"\ndata = \"now\"",
+        )
+        .unwrap();
+        Site {
+            config,
+            home: None,
+            pages: Vec::new(),
+            posts: Vec::new(),
+            notes: Vec::new(),
+            collections: HashMap::new(),
+            data: HashMap::from([(
+                "now".to_string(),
+                serde_json::json!({
+                    "title": "What I'm doing now",
+                    "items": [{"name": "Reading"}, {"name": "Writing"}]
+                }),
+            )]),
+            assets: Vec::new(),
+            image_requests: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_url_and_output_path() {
+        let page = now_page(None);
+        assert_eq!(page.url(), "/now/");
+        assert_eq!(page.output_path().unwrap(), PathBuf::from("now/index.html"));
+
+        let escaping = GeneratedPageConfig {
+            path: "/../outside/".to_string(),
+            ..now_page(None)
+        };
+        assert!(escaping.output_path().is_err());
+    }
+
+    #[test]
+    fn test_resolve_data() {
+        let site = site_with_data();
+        assert_eq!(now_page(None).resolve_data(&site).unwrap(), &Value::Null);
+        assert_eq!(
+            now_page(Some("now.items.1.name"))
+                .resolve_data(&site)
+                .unwrap(),
+            "Writing"
+        );
+        assert!(matches!(
+            now_page(Some("now.missing")).resolve_data(&site),
+            Err(BambooError::MissingData { .. })
+        ));
+    }
+
+    #[test]
+    fn test_page_title_fallbacks() {
+        let site = site_with_data();
+        let page = now_page(Some("now"));
+        assert_eq!(
+            page.page(page.resolve_data(&site).unwrap()).title,
+            "What I'm doing now"
+        );
+        assert_eq!(now_page(None).page(&Value::Null).title, "Now");
+    }
+
+    #[test]
+    fn test_render_generated_pages() {
+        let site = site_with_data();
+        let mut tera = Tera::default();
+        tera.add_raw_template(
+            "now.html",
+            "{{ page.title }}:{% for item in data.items %} {{ item.name }}{% endfor %}",
+        )
+        .unwrap();
+
+        let output_dir = tempfile::TempDir::new().unwrap();
+        render_generated_pages(&tera, &site, output_dir.path()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(output_dir.path().join("now/index.html")).unwrap(),
+            "What I&#x27;m doing now: Reading Writing"
+        );
+    }
+
+    #[test]
+    fn test_data_text() {
+        let site = site_with_data();
+        assert_eq!(
+            data_text(&site.data["now"]),
+            "Reading Writing What I'm doing now"
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! Read-only queries over a built [`Site`] for tools that embed bamboo:
+//! iterate content by section, look items up by URL or source path, list
+//! taxonomy terms, and fetch a page's table of contents without walking
+//! the raw structs.
+//!
+//! ```no_run
+//! use bamboo_ssg::{SiteBuilder, SiteGraph};
+//!
+//! let site = SiteBuilder::new("./my-site").build()?;
+//! let graph = SiteGraph::new(&site);
+//! for node in graph.section("posts") {
+//!     println!("{} {}", node.url(), node.title());
+//! }
+//! if let Some(node) = graph.by_url("/about/") {
+//!     for entry in node.toc() {
+//!         println!("{} {}", "#".repeat(entry.level as usize), entry.title);
+//!     }
+//! }
+//! # Ok::<_, bamboo_ssg::BambooError>(())
+//! ```
+
+use crate::feeds::{NOTES_SECTION, POSTS_SECTION};
+use crate::parsing::slugify;
+use crate::types::{CollectionItem, Content, Note, Page, Post, Site, TocEntry};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path};
+
+/// One renderable item of a [`Site`].
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    /// The home page (`content/_index.md`).
+    Home(&'a Page),
+    /// Any other page, including nested pages.
+    Page(&'a Page),
+    /// A blog post.
+    Post(&'a Post),
+    /// A note.
+    Note(&'a Note),
+    /// An item of a collection.
+    CollectionItem {
+        /// Name of the collection.
+        collection: &'a str,
+        /// The item itself.
+        item: &'a CollectionItem,
+    },
+}
+
+impl<'a> Node<'a> {
+    /// Fields shared by every kind of content.
+    pub fn content(&self) -> &'a Content {
+        match self {
+            Self::Home(page) | Self::Page(page) => &page.content,
+            Self::Post(post) => &post.content,
+            Self::Note(note) => &note.content,
+            Self::CollectionItem { item, .. } => &item.content,
+        }
+    }
+
+    /// Section the item belongs to: `"posts"`, `"notes"`, the collection
+    /// name, the top-level directory of a nested page (`"docs"` for
+    /// `content/docs/intro.md`), or `""` for the home page and top-level
+    /// pages.
+    pub fn section(&self) -> &'a str {
+        match self {
+            Self::Home(_) => "",
+            Self::Page(page) => {
+                let source = &page.content.source_path;
+                let mut components = source.components();
+                match (components.next(), components.next()) {
+                    (Some(Component::Normal(directory)), Some(_)) => {
+                        directory.to_str().unwrap_or("")
+                    }
+                    _ => "",
+                }
+            }
+            Self::Post(_) => POSTS_SECTION,
+            Self::Note(_) => NOTES_SECTION,
+            Self::CollectionItem { collection, .. } => collection,
+        }
+    }
+
+    /// Site-relative URL, e.g. `/posts/hello/`.
+    pub fn url(&self) -> &'a str {
+        &self.content().url
+    }
+
+    /// Display title.
+    pub fn title(&self) -> &'a str {
+        &self.content().title
+    }
+
+    /// Heading-based table of contents, in source order.
+    pub fn toc(&self) -> &'a [TocEntry] {
+        &self.content().toc
+    }
+
+    /// The post, if this node is one.
+    pub fn as_post(&self) -> Option<&'a Post> {
+        match self {
+            Self::Post(post) => Some(post),
+            _ => None,
+        }
+    }
+}
+
+/// A taxonomy term and the posts that use it.
+#[derive(Debug, Clone)]
+pub struct Term<'a> {
+    /// Term as first written in frontmatter, e.g. `"Rust"`.
+    pub name: &'a str,
+    /// URL slug shared by every spelling of the term, e.g. `"rust"`.
+    pub slug: String,
+    /// Posts using the term, newest first.
+    pub posts: Vec<&'a Post>,
+}
+
+/// Indexed, read-only view of a [`Site`]. Building one is cheap (a single
+/// pass over the content); lookups by URL and source path are hash-map
+/// lookups.
+pub struct SiteGraph<'a> {
+    site: &'a Site,
+    nodes: Vec<Node<'a>>,
+    by_url: HashMap<&'a str, usize>,
+    by_source_path: HashMap<String, usize>,
+}
+
+impl<'a> SiteGraph<'a> {
+    /// Indexes every item of `site`.
+    pub fn new(site: &'a Site) -> Self {
+        let mut collection_names: Vec<&String> = site.collections.keys().collect();
+        collection_names.sort();
+
+        let nodes: Vec<Node<'a>> = site
+            .home
+            .iter()
+            .map(Node::Home)
+            .chain(site.pages.iter().map(Node::Page))
+            .chain(site.posts.iter().map(Node::Post))
+            .chain(site.notes.iter().map(Node::Note))
+            .chain(collection_names.into_iter().flat_map(|name| {
+                site.collections[name]
+                    .items
+                    .iter()
+                    .map(move |item| Node::CollectionItem {
+                        collection: name,
+                        item,
+                    })
+            }))
+            .collect();
+
+        let mut by_url = HashMap::new();
+        let mut by_source_path = HashMap::new();
+        for (index, node) in nodes.iter().enumerate() {
+            by_url.entry(node.url()).or_insert(index);
+            by_source_path
+                .entry(normalize_source_path(&node.content().source_path))
+                .or_insert(index);
+        }
+
+        Self {
+            site,
+            nodes,
+            by_url,
+            by_source_path,
+        }
+    }
+
+    /// The underlying site.
+    pub fn site(&self) -> &'a Site {
+        self.site
+    }
+
+    /// Every item: the home page, pages, posts (newest first), notes
+    /// (newest first), then collection items by collection name.
+    pub fn nodes(&self) -> impl Iterator<Item = Node<'a>> + '_ {
+        self.nodes.iter().copied()
+    }
+
+    /// Distinct section names in use, sorted. See [`Node::section`].
+    pub fn sections(&self) -> Vec<&'a str> {
+        let mut sections: Vec<&'a str> = self.nodes.iter().map(Node::section).collect();
+        sections.sort_unstable();
+        sections.dedup();
+        sections
+    }
+
+    /// Items in `section`, in [`SiteGraph::nodes`] order.
+    pub fn section<'b>(&'b self, section: &'b str) -> impl Iterator<Item = Node<'a>> + 'b {
+        self.nodes().filter(move |node| node.section() == section)
+    }
+
+    /// Looks an item up by URL. Accepts site-relative (`/about/`) or
+    /// absolute URLs under `base_url`, with or without the trailing slash
+    /// or `index.html`; query strings and fragments are ignored.
+    pub fn by_url(&self, url: &str) -> Option<Node<'a>> {
+        let base_url = self.site.config.base_url.trim_end_matches('/');
+        let url = url.split(['?', '#']).next().unwrap_or("");
+        let url = match url.strip_prefix(base_url) {
+            Some(rest) if !base_url.is_empty() => rest,
+            _ => url,
+        };
+        let url = url.strip_suffix("index.html").unwrap_or(url);
+        let trimmed = url.trim_matches('/');
+        let normalized = if trimmed.is_empty() {
+            "/".to_string()
+        } else {
+            format!("/{trimmed}/")
+        };
+        self.by_url
+            .get(normalized.as_str())
+            .map(|&index| self.nodes[index])
+    }
+
+    /// Looks an item up by its source file, relative to `content/`
+    /// (`posts/2024-01-15-hello.md`) or to the site root
+    /// (`content/posts/2024-01-15-hello.md`).
+    pub fn by_source_path(&self, path: impl AsRef<Path>) -> Option<Node<'a>> {
+        let normalized = normalize_source_path(path.as_ref());
+        let relative = normalized.strip_prefix("content/").unwrap_or(&normalized);
+        self.by_source_path
+            .get(relative)
+            .or_else(|| self.by_source_path.get(&normalized))
+            .map(|&index| self.nodes[index])
+    }
+
+    /// Table of contents of the item at `url` (see [`SiteGraph::by_url`]).
+    pub fn toc(&self, url: &str) -> Option<&'a [TocEntry]> {
+        self.by_url(url).map(|node| node.toc())
+    }
+
+    /// Every term of `taxonomy` (e.g. `"tags"`) with the posts using it,
+    /// sorted by name. Terms that slugify the same (`Rust`, `rust`) are
+    /// merged.
+    pub fn terms(&self, taxonomy: &str) -> Vec<Term<'a>> {
+        let mut terms: BTreeMap<String, Term<'a>> = BTreeMap::new();
+        for post in &self.site.posts {
+            for name in post.taxonomies_map.get(taxonomy).into_iter().flatten() {
+                let slug = slugify(name);
+                terms
+                    .entry(slug.clone())
+                    .or_insert_with(|| Term {
+                        name,
+                        slug,
+                        posts: Vec::new(),
+                    })
+                    .posts
+                    .push(post);
+            }
+        }
+        let mut terms: Vec<Term<'a>> = terms.into_values().collect();
+        terms.sort_by(|a, b| a.name.cmp(b.name));
+        terms
+    }
+
+    /// Posts tagged with `term` in `taxonomy`, newest first. `term` is
+    /// compared by slug, so `"Rust"` and `"rust"` match the same posts.
+    pub fn posts_with_term(&self, taxonomy: &str, term: &str) -> Vec<&'a Post> {
+        let slug = slugify(term);
+        self.site
+            .posts
+            .iter()
+            .filter(|post| {
+                post.taxonomies_map
+                    .get(taxonomy)
+                    .is_some_and(|terms| terms.iter().any(|name| slugify(name) == slug))
+            })
+            .collect()
+    }
+}
+
+fn normalize_source_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('\\', "/")
+        .trim_start_matches("./")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template_test::sample_site;
+
+    fn site() -> Site {
+        let mut site = sample_site().unwrap();
+        let mut docs_page = site.pages[0].clone();
+        docs_page.content.title = "Docs Intro".to_string();
+        docs_page.content.url = "/docs/intro/".to_string();
+        docs_page.content.source_path = std::path::PathBuf::from("docs/intro.md");
+        site.pages.push(docs_page);
+        site.posts[0]
+            .taxonomies_map
+            .insert("tags".to_string(), vec!["Rust".to_string()]);
+        site.posts[1].taxonomies_map.insert(
+            "tags".to_string(),
+            vec!["rust".to_string(), "web".to_string()],
+        );
+        site
+    }
+
+    #[test]
+    fn test_sections() {
+        let site = site();
+        let graph = SiteGraph::new(&site);
+        assert_eq!(
+            graph.sections(),
+            vec!["", "docs", "notes", "posts", "projects"]
+        );
+        let posts: Vec<&str> = graph.section("posts").map(|node| node.title()).collect();
+        assert_eq!(posts, vec!["Second Post", "First Post"]);
+        let docs: Vec<&str> = graph.section("docs").map(|node| node.url()).collect();
+        assert_eq!(docs, vec!["/docs/intro/"]);
+        let top_level: Vec<&str> = graph.section("").map(|node| node.url()).collect();
+        assert_eq!(top_level, vec!["/", "/about/"]);
+    }
+
+    #[test]
+    fn test_by_url() {
+        let site = site();
+        let graph = SiteGraph::new(&site);
+        assert_eq!(graph.by_url("/about/").unwrap().title(), "About");
+        assert_eq!(graph.by_url("/about").unwrap().title(), "About");
+        assert_eq!(
+            graph
+                .by_url("https://example.com/about/index.html#top")
+                .unwrap()
+                .title(),
+            "About"
+        );
+        assert!(matches!(
+            graph.by_url("https://example.com/"),
+            Some(Node::Home(_))
+        ));
+        assert!(matches!(
+            graph.by_url("/projects/sample-project/"),
+            Some(Node::CollectionItem {
+                collection: "projects",
+                ..
+            })
+        ));
+        assert!(graph.by_url("/missing/").is_none());
+    }
+
+    #[test]
+    fn test_by_source_path() {
+        let site = site();
+        let graph = SiteGraph::new(&site);
+        assert_eq!(
+            graph.by_source_path("docs/intro.md").unwrap().url(),
+            "/docs/intro/"
+        );
+        assert_eq!(
+            graph.by_source_path("content/docs/intro.md").unwrap().url(),
+            "/docs/intro/"
+        );
+        assert!(graph.by_source_path("docs/missing.md").is_none());
+    }
+
+    #[test]
+    fn test_toc() {
+        let site = site();
+        let graph = SiteGraph::new(&site);
+        let toc = graph.toc("/about/").unwrap();
+        assert_eq!(toc.len(), 1);
+        assert_eq!(toc[0].title, "Background");
+    }
+
+    #[test]
+    fn test_terms() {
+        let site = site();
+        let graph = SiteGraph::new(&site);
+        let terms = graph.terms("tags");
+        let summary: Vec<(&str, &str, usize)> = terms
+            .iter()
+            .map(|term| (term.name, term.slug.as_str(), term.posts.len()))
+            .collect();
+        assert_eq!(summary, vec![("Rust", "rust", 2), ("web", "web", 1)]);
+        let titles: Vec<&str> = graph
+            .posts_with_term("tags", "RUST")
+            .iter()
+            .map(|post| post.content.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["Second Post", "First Post"]);
+        assert!(graph.terms("series").is_empty());
+    }
+}
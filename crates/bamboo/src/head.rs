@@ -0,0 +1,313 @@
+//! The `<head>` of a rendered page, assembled in one place. [`Head`] holds
+//! the page's `<meta>` tags (description, Open Graph, Twitter card), `<link>`
+//! tags (canonical URL, favicon, feed discovery), and JSON-LD blocks, built
+//! from `bamboo.toml`, frontmatter, and whichever features are turned on.
+//! The built-in `partials/head.html` (available to every theme) renders it,
+//! so a theme's base layout needs one include and picks up new head tags
+//! without changes.
+//!
+//! Templates see the page's head as `head` and the site-wide head used by
+//! listing pages as `site.head`.
+
+use crate::seo::SeoMeta;
+use crate::types::{Post, Site};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+/// Name of the built-in partial that renders [`Head`].
+pub const HEAD_PARTIAL: &str = "partials/head.html";
+
+/// Everything the engine puts in one page's `<head>`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Head {
+    /// `<meta>` tags, in document order.
+    pub meta: Vec<MetaTag>,
+    /// `<link>` tags, in document order.
+    pub links: Vec<LinkTag>,
+    /// Serialized JSON-LD objects, each ready to embed in a
+    /// `<script type="application/ld+json">` element.
+    pub json_ld: Vec<String>,
+}
+
+/// A `<meta>` tag keyed by `name` or `property`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetaTag {
+    /// `name` or `property`.
+    pub attribute: &'static str,
+    /// Value of `attribute`, such as `description` or `og:title`.
+    pub key: String,
+    /// The tag's `content`.
+    pub content: String,
+}
+
+/// A `<link>` tag.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LinkTag {
+    /// `rel`, such as `canonical` or `alternate`.
+    pub rel: &'static str,
+    /// Absolute or site-relative `href`.
+    pub href: String,
+    /// MIME `type`, if any.
+    #[serde(rename = "type")]
+    pub kind: Option<&'static str>,
+    /// `title`, if any.
+    pub title: Option<String>,
+}
+
+impl Head {
+    /// The head of a page described by `seo`: description, Open Graph and
+    /// Twitter tags, the canonical URL, the favicon, feed links for each
+    /// enabled feed, and a `WebSite` JSON-LD block on the home page.
+    pub fn new(site: &Site, seo: &SeoMeta) -> Self {
+        let mut head = Self::default();
+        head.meta("name", "description", &seo.description);
+        head.meta("property", "og:title", &seo.title);
+        head.meta("property", "og:description", &seo.description);
+        head.meta("property", "og:type", seo.kind);
+        head.meta("property", "og:url", &seo.url);
+        head.meta("property", "og:site_name", &seo.site_name);
+        if let Some(image) = &seo.image {
+            head.meta("property", "og:image", image);
+        }
+        head.meta("name", "twitter:card", seo.twitter_card);
+        head.meta("name", "twitter:title", &seo.title);
+        head.meta("name", "twitter:description", &seo.description);
+        if let Some(image) = &seo.image {
+            head.meta("name", "twitter:image", image);
+        }
+        if let Some(handle) = &seo.twitter_site {
+            head.meta("name", "twitter:site", handle);
+        }
+
+        head.link("canonical", &seo.url, None, None);
+        if let Some(favicon) = site.config.favicon.as_deref() {
+            head.favicon(site, favicon);
+        }
+        let base_url = site.config.base_url.trim_end_matches('/');
+        if let Some(manifest) = &site.config.manifest {
+            head.link(
+                "manifest",
+                &format!("{base_url}/{}", crate::webmanifest::MANIFEST_FILE),
+                None,
+                None,
+            );
+            if let Some(theme_color) = &manifest.theme_color {
+                head.meta("name", "theme-color", theme_color);
+            }
+        }
+        if site.config.generate.rss {
+            head.link(
+                "alternate",
+                &format!("{base_url}/{}", site.config.feed.rss_filename),
+                Some("application/rss+xml"),
+                Some(format!("{} RSS", site.config.title)),
+            );
+        }
+        if site.config.generate.atom {
+            head.link(
+                "alternate",
+                &format!("{base_url}/{}", site.config.feed.atom_filename),
+                Some("application/atom+xml"),
+                Some(format!("{} Atom", site.config.title)),
+            );
+        }
+
+        if seo.url == format!("{base_url}/") {
+            let mut website = json!({
+                "@context": "https://schema.org",
+                "@type": "WebSite",
+                "name": site.config.title,
+                "url": seo.url,
+            });
+            if !seo.description.is_empty() {
+                website["description"] = json!(seo.description);
+            }
+            head.json_ld(&website);
+        }
+        head
+    }
+
+    /// Appends `<meta {attribute}="{key}" content="{content}">`.
+    pub fn meta(&mut self, attribute: &'static str, key: &str, content: &str) {
+        self.meta.push(MetaTag {
+            attribute,
+            key: key.to_string(),
+            content: content.to_string(),
+        });
+    }
+
+    /// Appends a `<link>` tag.
+    pub fn link(
+        &mut self,
+        rel: &'static str,
+        href: &str,
+        kind: Option<&'static str>,
+        title: Option<String>,
+    ) {
+        self.links.push(LinkTag {
+            rel,
+            href: href.to_string(),
+            kind,
+            title,
+        });
+    }
+
+    /// Appends a JSON-LD block. `</` is escaped so the block can't close
+    /// its `<script>` element early.
+    pub fn json_ld(&mut self, value: &Value) {
+        self.json_ld.push(value.to_string().replace("</", "<\\/"));
+    }
+
+    fn favicon(&mut self, site: &Site, favicon: &str) {
+        let href = if favicon.starts_with('/') && !favicon.starts_with("//") {
+            format!("{}{favicon}", site.config.base_url.trim_end_matches('/'))
+        } else {
+            favicon.to_string()
+        };
+        let extension = favicon.rsplit('.').next().unwrap_or("");
+        let kind = match extension.to_ascii_lowercase().as_str() {
+            "svg" => Some("image/svg+xml"),
+            "png" => Some("image/png"),
+            "ico" => Some("image/x-icon"),
+            _ => None,
+        };
+        self.link("icon", &href, kind, None);
+    }
+}
+
+/// The head of listing pages and of a home page without `_index.md`.
+pub fn site_head(site: &Site) -> Head {
+    Head::new(site, &crate::seo::site_seo(site))
+}
+
+/// [`Head::new`] for a post, plus a `BlogPosting` JSON-LD block with its
+/// headline, dates, author, image, and tags.
+pub fn post_head(site: &Site, post: &Post, seo: &SeoMeta) -> Head {
+    let mut head = Head::new(site, seo);
+    let mut posting = json!({
+        "@context": "https://schema.org",
+        "@type": "BlogPosting",
+        "headline": post.content.title,
+        "url": seo.url,
+        "datePublished": post.date.to_rfc3339(),
+        "description": seo.description,
+    });
+    if let Some(updated) = post.content.frontmatter.get_string("updated") {
+        posting["dateModified"] = json!(updated);
+    }
+    if let Some(author) = &site.config.author {
+        posting["author"] = json!({ "@type": "Person", "name": author });
+    }
+    if let Some(image) = &seo.image {
+        posting["image"] = json!(image);
+    }
+    if !post.tags.is_empty() {
+        posting["keywords"] = json!(post.tags);
+    }
+    head.json_ld(&posting);
+    head
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SiteConfig;
+    use std::collections::HashMap;
+
+    fn site(config: &str) -> Site {
+        let config: SiteConfig = toml::from_str(&format!(
+            "title = \"Blog\"\nbase_url = \"https://example.com\"\ndescription = \"A blog\"\n{config}"
+        ))
+        .unwrap();
+        Site {
+            config,
+            home: None,
+            pages: Vec::new(),
+            posts: Vec::new(),
+            notes: Vec::new(),
+            collections: HashMap::new(),
+            data: HashMap::new(),
+            assets: Vec::new(),
+            image_requests: Vec::new(),
+        }
+    }
+
+    fn link<'a>(head: &'a Head, rel: &str) -> Option<&'a LinkTag> {
+        head.links.iter().find(|link| link.rel == rel)
+    }
+
+    #[test]
+    fn test_site_head() {
+        let site = site("favicon = \"/favicon.svg\"\n[feed]\nrss_filename = \"feed.xml\"");
+        let head = site_head(&site);
+
+        assert!(head.meta.contains(&MetaTag {
+            attribute: "property",
+            key: "og:url".to_string(),
+            content: "https://example.com/".to_string(),
+        }));
+        assert_eq!(
+            link(&head, "canonical").unwrap().href,
+            "https://example.com/"
+        );
+        let icon = link(&head, "icon").unwrap();
+        assert_eq!(icon.href, "https://example.com/favicon.svg");
+        assert_eq!(icon.kind, Some("image/svg+xml"));
+        let feeds: Vec<&str> = head
+            .links
+            .iter()
+            .filter(|link| link.rel == "alternate")
+            .map(|link| link.href.as_str())
+            .collect();
+        assert_eq!(
+            feeds,
+            [
+                "https://example.com/feed.xml",
+                "https://example.com/atom.xml"
+            ]
+        );
+        assert_eq!(head.json_ld.len(), 1);
+        assert!(head.json_ld[0].contains("\"@type\":\"WebSite\""));
+    }
+
+    #[test]
+    fn test_head_respects_generate_flags() {
+        let site = site("[generate]\nrss = false\natom = false");
+        let head = site_head(&site);
+        assert!(link(&head, "alternate").is_none());
+        assert!(link(&head, "icon").is_none());
+    }
+
+    #[test]
+    fn test_head_links_web_manifest() {
+        let head = site_head(&site("[manifest]\ntheme_color = \"#1e293b\""));
+        assert_eq!(
+            link(&head, "manifest").unwrap().href,
+            "https://example.com/site.webmanifest"
+        );
+        assert!(head.meta.iter().any(|tag| tag.key == "theme-color"));
+        assert!(link(&site_head(&site("")), "manifest").is_none());
+    }
+
+    #[test]
+    fn test_post_head_json_ld() {
+        let mut site = site("author = \"Ada\"");
+        site.posts = crate::template_test::sample_site().unwrap().posts;
+        let post = &site.posts[0];
+        let seo = crate::seo::post_seo(&site, post);
+        let head = post_head(&site, post, &seo);
+
+        assert_eq!(head.json_ld.len(), 1);
+        let posting: Value = serde_json::from_str(&head.json_ld[0]).unwrap();
+        assert_eq!(posting["@type"], "BlogPosting");
+        assert_eq!(posting["headline"], post.content.title.as_str());
+        assert_eq!(posting["author"]["name"], "Ada");
+    }
+
+    #[test]
+    fn test_json_ld_escapes_script_close() {
+        let mut head = Head::default();
+        head.json_ld(&json!({ "name": "</script><script>" }));
+        assert!(!head.json_ld[0].contains("</script>"));
+    }
+}
@@ -0,0 +1,129 @@
+//! `_headers`, the response header file read by Netlify and Cloudflare
+//! Pages, written when `bamboo.toml` has a `[headers]` table. Each key is a
+//! path pattern (`*` matches anything) mapped to the headers to send for
+//! it:
+//!
+//! ```toml
+//! [headers."/*"]
+//! Strict-Transport-Security = "max-age=63072000; includeSubDomains"
+//! Content-Security-Policy = "default-src 'self'"
+//!
+//! [headers."/fonts/*"]
+//! Cache-Control = "public, max-age=31536000"
+//! ```
+//!
+//! With `fingerprint = true`, every fingerprinted CSS and JS file also gets
+//! a year-long immutable `Cache-Control`, since its name changes whenever
+//! its contents do.
+
+use crate::error::Result;
+use crate::redirects::base_path;
+use crate::sink::RenderSink;
+use crate::types::Site;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Output path of the header rules.
+pub const HEADERS_FILE: &str = "_headers";
+
+/// `Cache-Control` sent for fingerprinted assets.
+pub const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// `[headers]` table from `bamboo.toml`: path patterns mapped to header
+/// names and values.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HeadersConfig {
+    /// Headers for each path pattern, such as `/*` or `/fonts/*`.
+    #[serde(flatten)]
+    pub paths: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Renders `_headers` for `site`. `fingerprinted` maps original asset
+/// paths to their fingerprinted names, as returned by
+/// [`crate::assets::prepare_assets`].
+pub fn render_headers(
+    site: &Site,
+    config: &HeadersConfig,
+    fingerprinted: &HashMap<String, String>,
+) -> String {
+    let base_path = base_path(site);
+    let mut rules: Vec<String> = config
+        .paths
+        .iter()
+        .filter(|(_, headers)| !headers.is_empty())
+        .map(|(path, headers)| {
+            let mut rule = format!("{base_path}/{}\n", path.trim_start_matches('/'));
+            for (name, value) in headers {
+                rule.push_str(&format!("  {name}: {value}\n"));
+            }
+            rule
+        })
+        .collect();
+
+    let mut assets: Vec<&String> = fingerprinted.values().collect();
+    assets.sort();
+    rules.extend(assets.into_iter().map(|asset| {
+        format!(
+            "{base_path}/{}\n  Cache-Control: {IMMUTABLE_CACHE_CONTROL}\n",
+            asset.trim_start_matches('/')
+        )
+    }));
+    rules.join("\n")
+}
+
+/// Writes `_headers` into `output` when `[headers]` is configured.
+pub fn generate_headers(
+    site: &Site,
+    fingerprinted: &HashMap<String, String>,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    let Some(config) = site.config.headers.as_ref() else {
+        return Ok(());
+    };
+    output.write_file(
+        Path::new(HEADERS_FILE),
+        render_headers(site, config, fingerprinted).as_bytes(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::MemorySink;
+    use crate::test_site::site;
+
+    #[test]
+    fn test_render_headers() {
+        let mut site = site(
+            "[headers.\"/*\"]\nX-Frame-Options = \"DENY\"\nReferrer-Policy = \"no-referrer\"\n\
+             [headers.\"/fonts/*\"]\nCache-Control = \"public, max-age=86400\"",
+        );
+        site.config.base_url = "https://example.com/blog/".to_string();
+        let fingerprinted =
+            HashMap::from([("style.css".to_string(), "style.0123abcd.css".to_string())]);
+
+        assert_eq!(
+            render_headers(&site, site.config.headers.as_ref().unwrap(), &fingerprinted),
+            "/blog/*\n  Referrer-Policy: no-referrer\n  X-Frame-Options: DENY\n\n\
+             /blog/fonts/*\n  Cache-Control: public, max-age=86400\n\n\
+             /blog/style.0123abcd.css\n  Cache-Control: public, max-age=31536000, immutable\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_headers() {
+        let mut site = site("[headers.\"/*\"]\nX-Frame-Options = \"DENY\"");
+        let sink = MemorySink::new();
+        generate_headers(&site, &HashMap::new(), &sink).unwrap();
+        assert_eq!(
+            sink.read_file(Path::new(HEADERS_FILE)).unwrap(),
+            b"/*\n  X-Frame-Options: DENY\n"
+        );
+
+        site.config.headers = None;
+        let sink = MemorySink::new();
+        generate_headers(&site, &HashMap::new(), &sink).unwrap();
+        assert!(sink.paths().is_empty());
+    }
+}
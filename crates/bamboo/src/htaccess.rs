@@ -0,0 +1,141 @@
+//! `.htaccess` for sites served by Apache, such as classic shared hosting,
+//! written when `bamboo.toml` has an `[htaccess]` table. Every redirect the
+//! site declares (`redirect_from` and `redirect_to`, chains flattened)
+//! becomes a `Redirect 301` rule, so Apache answers old URLs itself instead
+//! of serving the meta-refresh stub.
+//!
+//! `Redirect` matches by prefix, so a rule for `/old/` also sends
+//! `/old/page/` to the target with `page/` appended.
+//!
+//! With `cache_assets = true` and `fingerprint = true`, fingerprinted CSS,
+//! JS, images, fonts, and media are also served with a year-long immutable
+//! `Cache-Control`, since their names change whenever their contents do.
+
+use crate::error::Result;
+use crate::redirects::{base_path, flattened_redirects};
+use crate::sink::RenderSink;
+use crate::types::Site;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Output path of the Apache configuration.
+pub const HTACCESS_FILE: &str = ".htaccess";
+
+/// `[htaccess]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HtaccessConfig {
+    /// Send a long-lived `Cache-Control` for fingerprinted assets. Only
+    /// takes effect with `fingerprint = true`.
+    #[serde(default)]
+    pub cache_assets: bool,
+}
+
+/// Quotes `value` for an Apache directive when it contains whitespace or
+/// quotes.
+fn quote(value: &str) -> String {
+    if value.contains(|character: char| character.is_whitespace() || character == '"') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Renders `.htaccess` for `site`. Redirects are left out when
+/// `[generate] redirects = false`.
+pub fn render_htaccess(site: &Site, config: &HtaccessConfig) -> String {
+    let base_url = site.config.base_url.trim_end_matches('/');
+    let base_path = base_path(site);
+    let mut htaccess = String::from("# Generated by bamboo\n");
+
+    if site.config.generate.redirects {
+        let rules: Vec<String> = flattened_redirects(site)
+            .into_iter()
+            .map(|(source, target)| {
+                let target = if target.starts_with('/') && !target.starts_with("//") {
+                    format!("{base_url}{target}")
+                } else {
+                    target
+                };
+                format!(
+                    "Redirect 301 {} {}\n",
+                    quote(&format!("{base_path}{source}")),
+                    quote(&target)
+                )
+            })
+            .collect();
+        if !rules.is_empty() {
+            htaccess.push('\n');
+            htaccess.extend(rules);
+        }
+    }
+
+    if config.cache_assets && site.config.fingerprint {
+        let extensions = ["css", "js"]
+            .iter()
+            .chain(crate::assets::FINGERPRINT_EXTENSIONS)
+            .copied()
+            .collect::<Vec<_>>()
+            .join("|");
+        htaccess.push_str(&format!(
+            "\n<IfModule mod_headers.c>\n\
+             \x20 <FilesMatch \"\\.[0-9a-f]{{8}}\\.({extensions})$\">\n\
+             \x20   Header set Cache-Control \"public, max-age=31536000, immutable\"\n\
+             \x20 </FilesMatch>\n\
+             </IfModule>\n",
+        ));
+    }
+    htaccess
+}
+
+/// Writes `.htaccess` into `output` when `[htaccess]` is configured.
+pub fn generate_htaccess(site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
+    let Some(config) = site.config.htaccess.as_ref() else {
+        return Ok(());
+    };
+    output.write_file(
+        Path::new(HTACCESS_FILE),
+        render_htaccess(site, config).as_bytes(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::MemorySink;
+    use crate::test_site::site;
+
+    #[test]
+    fn test_render_htaccess() {
+        let mut site = site("[htaccess]");
+        site.config.base_url = "https://example.com/blog".to_string();
+        site.pages[0].redirect_from = vec!["/old-about".to_string(), "/old about/".to_string()];
+        assert_eq!(
+            render_htaccess(&site, &HtaccessConfig::default()),
+            "# Generated by bamboo\n\n\
+             Redirect 301 \"/blog/old about/\" https://example.com/blog/about/\n\
+             Redirect 301 /blog/old-about/ https://example.com/blog/about/\n"
+        );
+
+        site.config.generate.redirects = false;
+        let cached = HtaccessConfig { cache_assets: true };
+        assert_eq!(render_htaccess(&site, &cached), "# Generated by bamboo\n");
+        site.config.fingerprint = true;
+        assert!(
+            render_htaccess(&site, &cached)
+                .contains("  <FilesMatch \"\\.[0-9a-f]{8}\\.(css|js|png|jpg|jpeg|gif|webp|avif|svg|woff|woff2|ttf|otf|eot|mp4|webm|mp3|ogg|wav)$\">\n")
+        );
+    }
+
+    #[test]
+    fn test_generate_htaccess() {
+        let mut site = site("[htaccess]");
+        let sink = MemorySink::new();
+        generate_htaccess(&site, &sink).unwrap();
+        assert!(sink.file_exists(Path::new(HTACCESS_FILE)));
+
+        site.config.htaccess = None;
+        let sink = MemorySink::new();
+        generate_htaccess(&site, &sink).unwrap();
+        assert!(sink.paths().is_empty());
+    }
+}
@@ -0,0 +1,307 @@
+//! Lossless removal of EXIF, XMP, and IPTC metadata from JPEG, PNG, and
+//! WebP files, so photos don't publish the camera's GPS position, serial
+//! number, or capture time along with the picture.
+//!
+//! Only the metadata segments are dropped; the compressed image data is
+//! copied through untouched. An image whose EXIF rotates it keeps a
+//! minimal EXIF block holding just the orientation, so it still displays
+//! the right way up.
+
+use image::metadata::Orientation;
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// PNG chunks dropped by [`strip_metadata`]: EXIF, text (where XMP and
+/// raw EXIF profiles are stored), and modification time.
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 5] = [b"eXIf", b"tEXt", b"iTXt", b"zTXt", b"tIME"];
+/// VP8X flag bits announcing EXIF and XMP chunks.
+const WEBP_EXIF_FLAG: u8 = 0x08;
+const WEBP_XMP_FLAG: u8 = 0x04;
+
+/// Returns `bytes` with its metadata removed, or `None` when it is not a
+/// JPEG, PNG, or WebP file, is malformed, or carries no metadata.
+pub fn strip_metadata(bytes: &[u8]) -> Option<Vec<u8>> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        strip_jpeg(bytes)
+    } else if bytes.starts_with(PNG_SIGNATURE) {
+        strip_png(bytes)
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        strip_webp(bytes)
+    } else {
+        None
+    }
+}
+
+/// A big-endian TIFF structure with a single Orientation entry: the EXIF
+/// payload a stripped image keeps when it needs rotating.
+fn orientation_exif(orientation: Orientation) -> Vec<u8> {
+    let mut tiff = b"MM\0\x2a\0\0\0\x08".to_vec();
+    tiff.extend([0, 1]);
+    tiff.extend([0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, orientation.to_exif(), 0, 0]);
+    tiff.extend([0, 0, 0, 0]);
+    tiff
+}
+
+/// Orientation recorded in a TIFF-structured EXIF payload, if it rotates
+/// or flips the image.
+fn rotation(tiff: &[u8]) -> Option<Orientation> {
+    Orientation::from_exif_chunk(tiff)
+        .filter(|orientation| *orientation != Orientation::NoTransforms)
+}
+
+fn strip_jpeg(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut output = bytes[..2].to_vec();
+    let mut position = 2;
+    let mut exif_position = None;
+    let mut orientation = None;
+    let mut stripped = false;
+
+    loop {
+        if *bytes.get(position)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(position + 1)?;
+        match marker {
+            0xFF => {
+                position += 1;
+                continue;
+            }
+            // Start of scan or end of image: everything after is image data.
+            0xDA | 0xD9 => {
+                output.extend_from_slice(&bytes[position..]);
+                break;
+            }
+            0x01 | 0xD0..=0xD7 => {
+                output.extend_from_slice(&bytes[position..position + 2]);
+                position += 2;
+                continue;
+            }
+            _ => {}
+        }
+        let length = u16::from_be_bytes([*bytes.get(position + 2)?, *bytes.get(position + 3)?]);
+        let end = position + 2 + usize::from(length);
+        let payload = bytes.get(position + 4..end)?;
+        let metadata = match marker {
+            0xE1 => payload.starts_with(EXIF_HEADER) || payload.starts_with(XMP_HEADER),
+            0xED => true,
+            _ => false,
+        };
+        if metadata {
+            if let Some(tiff) = payload.strip_prefix(EXIF_HEADER) {
+                exif_position.get_or_insert(output.len());
+                orientation = orientation.or(rotation(tiff));
+            }
+            stripped = true;
+        } else {
+            output.extend_from_slice(&bytes[position..end]);
+        }
+        position = end;
+    }
+
+    if !stripped {
+        return None;
+    }
+    if let (Some(orientation), Some(exif_position)) = (orientation, exif_position) {
+        let payload = [EXIF_HEADER, &orientation_exif(orientation)].concat();
+        let length = u16::try_from(payload.len() + 2).ok()?;
+        let segment = [&[0xFF, 0xE1][..], &length.to_be_bytes(), &payload].concat();
+        output.splice(exif_position..exif_position, segment);
+    }
+    Some(output)
+}
+
+fn png_chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut crc = flate2::Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    let length = u32::try_from(data.len()).unwrap_or(u32::MAX);
+    [
+        &length.to_be_bytes()[..],
+        kind,
+        data,
+        &crc.sum().to_be_bytes(),
+    ]
+    .concat()
+}
+
+fn strip_png(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut output = PNG_SIGNATURE.to_vec();
+    let mut position = PNG_SIGNATURE.len();
+    let mut stripped = false;
+
+    while position < bytes.len() {
+        let length = u32::from_be_bytes(bytes.get(position..position + 4)?.try_into().ok()?);
+        let kind: &[u8; 4] = bytes.get(position + 4..position + 8)?.try_into().ok()?;
+        let end = position + 12 + usize::try_from(length).ok()?;
+        let chunk = bytes.get(position..end)?;
+        if PNG_METADATA_CHUNKS.contains(&kind) {
+            if kind == b"eXIf"
+                && let Some(orientation) = rotation(&chunk[8..chunk.len() - 4])
+            {
+                output.extend(png_chunk(b"eXIf", &orientation_exif(orientation)));
+            }
+            stripped = true;
+        } else {
+            output.extend_from_slice(chunk);
+        }
+        position = end;
+    }
+
+    stripped.then_some(output)
+}
+
+fn strip_webp(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut chunks = Vec::new();
+    let mut position = 12;
+    let mut stripped = false;
+    let mut keep_exif = false;
+
+    while position < bytes.len() {
+        let kind: &[u8; 4] = bytes.get(position..position + 4)?.try_into().ok()?;
+        let length = u32::from_le_bytes(bytes.get(position + 4..position + 8)?.try_into().ok()?);
+        let data_end = position + 8 + usize::try_from(length).ok()?;
+        let end = (data_end + (data_end & 1)).min(bytes.len());
+        let data = bytes.get(position + 8..data_end)?;
+        match kind {
+            b"EXIF" => {
+                let tiff = data.strip_prefix(EXIF_HEADER).unwrap_or(data);
+                if let Some(orientation) = rotation(tiff) {
+                    let exif = orientation_exif(orientation);
+                    let length = u32::try_from(exif.len()).ok()?;
+                    chunks.push([&b"EXIF"[..], &length.to_le_bytes(), &exif].concat());
+                    keep_exif = true;
+                }
+                stripped = true;
+            }
+            b"XMP " => stripped = true,
+            _ => chunks.push(bytes[position..end].to_vec()),
+        }
+        position = end;
+    }
+
+    if !stripped {
+        return None;
+    }
+    let mut body = b"WEBP".to_vec();
+    for mut chunk in chunks {
+        if chunk.starts_with(b"VP8X") && chunk.len() > 8 {
+            chunk[8] &= !WEBP_XMP_FLAG;
+            if !keep_exif {
+                chunk[8] &= !WEBP_EXIF_FLAG;
+            }
+        }
+        body.extend(chunk);
+    }
+    let length = u32::try_from(body.len()).ok()?;
+    Some([&b"RIFF"[..], &length.to_le_bytes(), &body].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Little-endian EXIF with an Orientation entry and a GPS IFD pointer.
+    fn camera_exif(orientation: u8) -> Vec<u8> {
+        let mut tiff = b"II\x2a\0\x08\0\0\0".to_vec();
+        tiff.extend([2, 0]);
+        tiff.extend([0x12, 0x01, 3, 0, 1, 0, 0, 0, orientation, 0, 0, 0]);
+        tiff.extend([0x25, 0x88, 4, 0, 1, 0, 0, 0, 0x26, 0, 0, 0]);
+        tiff.extend([0, 0, 0, 0]);
+        tiff.extend(b"GPS 51.5072N 0.1276W");
+        tiff
+    }
+
+    fn jpeg_segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let length = (payload.len() as u16 + 2).to_be_bytes();
+        [&[0xFF, marker][..], &length, payload].concat()
+    }
+
+    fn jpeg(orientation: u8) -> Vec<u8> {
+        [
+            &[0xFF, 0xD8][..],
+            &jpeg_segment(0xE1, &[EXIF_HEADER, &camera_exif(orientation)].concat()),
+            &jpeg_segment(0xE1, &[XMP_HEADER, b"<x:xmpmeta/>"].concat()),
+            &jpeg_segment(0xDB, &[0; 65]),
+            &[0xFF, 0xDA, 0, 2, 1, 2, 3, 0xFF, 0xD9],
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_strip_jpeg() {
+        let stripped = strip_metadata(&jpeg(1)).unwrap();
+        let expected = [
+            &[0xFF, 0xD8][..],
+            &jpeg_segment(0xDB, &[0; 65]),
+            &[0xFF, 0xDA, 0, 2, 1, 2, 3, 0xFF, 0xD9],
+        ]
+        .concat();
+        assert_eq!(stripped, expected);
+        assert_eq!(strip_metadata(&stripped), None);
+    }
+
+    #[test]
+    fn test_strip_jpeg_keeps_orientation() {
+        let stripped = strip_metadata(&jpeg(6)).unwrap();
+        assert!(!stripped.windows(3).any(|window| window == b"GPS"));
+        let tiff = &stripped[6 + EXIF_HEADER.len()..];
+        assert_eq!(
+            Orientation::from_exif_chunk(tiff),
+            Some(Orientation::Rotate90)
+        );
+        assert_eq!(strip_metadata(&stripped).unwrap(), stripped);
+    }
+
+    #[test]
+    fn test_strip_png() {
+        let mut source = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(2, 2)
+            .write_to(&mut source, image::ImageFormat::Png)
+            .unwrap();
+        let clean = source.into_inner();
+        let iend = clean.len() - 12;
+        let tagged = [
+            &clean[..iend],
+            &png_chunk(b"eXIf", &camera_exif(3)),
+            &png_chunk(b"tEXt", b"Comment\0Taken at home"),
+            &clean[iend..],
+        ]
+        .concat();
+
+        let stripped = strip_metadata(&tagged).unwrap();
+        assert!(!stripped.windows(4).any(|window| window == b"tEXt"));
+        assert!(!stripped.windows(3).any(|window| window == b"GPS"));
+        let decoded = image::load_from_memory(&stripped).unwrap();
+        assert_eq!(decoded.width(), 2);
+        assert_eq!(strip_metadata(&clean), None);
+    }
+
+    #[test]
+    fn test_strip_webp() {
+        let vp8x = [
+            &b"VP8X"[..],
+            &10u32.to_le_bytes(),
+            &[0x0C, 0, 0, 0, 1, 0, 0, 1, 0, 0],
+        ]
+        .concat();
+        let image = [&b"VP8L"[..], &3u32.to_le_bytes(), &[1, 2, 3, 0]].concat();
+        let exif = camera_exif(1);
+        let exif = [&b"EXIF"[..], &(exif.len() as u32).to_le_bytes(), &exif].concat();
+        let xmp = [&b"XMP "[..], &4u32.to_le_bytes(), b"<x/>"].concat();
+        let body = [&b"WEBP"[..], &vp8x, &image, &exif, &xmp].concat();
+        let webp = [&b"RIFF"[..], &(body.len() as u32).to_le_bytes(), &body].concat();
+
+        let stripped = strip_metadata(&webp).unwrap();
+        let body = [&b"WEBP"[..], &vp8x, &image].concat();
+        let mut expected = [&b"RIFF"[..], &(body.len() as u32).to_le_bytes(), &body].concat();
+        expected[20] = 0;
+        assert_eq!(stripped, expected);
+    }
+
+    #[test]
+    fn test_strip_metadata_ignores_other_files() {
+        assert_eq!(strip_metadata(b"GIF89a"), None);
+        assert_eq!(strip_metadata(&[0xFF, 0xD8, 0xFF]), None);
+    }
+}
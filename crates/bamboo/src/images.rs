@@ -1,19 +1,30 @@
 //! Responsive image generation: resizes source images to configured widths
 //! and emits `<picture>`/srcset-ready output alongside the originals.
+//! Variants are re-encoded without the source's metadata, with its EXIF
+//! orientation applied to the pixels. With `placeholders = true`, each
+//! image also gets a tiny blurred preview shown behind the `<img>` while
+//! the real image loads; with `dominant_colors = true`, a solid
+//! background in the image's dominant color.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use walkdir::WalkDir;
 
 use image::codecs::jpeg::JpegEncoder;
 use image::imageops::FilterType;
-use image::{ImageEncoder, ImageReader};
+use image::{DynamicImage, ImageDecoder, ImageEncoder, ImageReader};
 use rayon::prelude::*;
 
-use crate::error::Result;
+use crate::error::{IoContext, Result};
+use crate::graph::SiteGraph;
+use crate::types::Site;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use sha2::{Digest, Sha256};
 
 /// `[images]` table from `bamboo.toml`: drives the responsive-image
 /// generation pipeline.
@@ -29,8 +40,68 @@ pub struct ImageConfig {
     /// Output formats to emit per source image (e.g. `["webp", "jpg"]`).
     #[serde(default = "default_formats")]
     pub formats: Vec<String>,
+    /// Output formats by source extension, overriding `formats` for
+    /// those images (e.g. `png = ["webp", "png"]`). `"passthrough"` (or
+    /// an empty list) leaves matching images as they are. Keys are
+    /// lowercase, with `jpeg` folded into `jpg`.
+    #[serde(default, deserialize_with = "deserialize_format_policy")]
+    pub format_policy: BTreeMap<String, Vec<String>>,
+    /// Also remove EXIF, XMP, and IPTC metadata (GPS position, camera
+    /// serial, capture time) from the original images copied to the
+    /// output. Variants never carry it. Defaults to `false`.
+    #[serde(default)]
+    pub strip_metadata: bool,
+    /// Generate a blurred placeholder per image, recorded in
+    /// [`ImageManifest::placeholders`] and set as the `background-image`
+    /// of its `<img>` tags. Defaults to `false`.
+    #[serde(default)]
+    pub placeholders: bool,
+    /// Compute each image's dominant color, recorded in
+    /// [`ImageManifest::colors`] and set as the `background-color` of its
+    /// `<img>` tags. Defaults to `false`.
+    #[serde(default)]
+    pub dominant_colors: bool,
+    /// Download `http(s)` images referenced by content and serve them
+    /// from the site; see [`crate::remote_images`]. Defaults to `false`.
+    #[serde(default)]
+    pub remote: bool,
+    /// Crops served instead of the scaled image where their media query
+    /// matches, e.g. a square crop on narrow screens. Each rule becomes a
+    /// `<source media="...">` ahead of the width-based sources.
+    #[serde(default)]
+    pub art_direction: Vec<ArtDirection>,
+    /// `sizes` attribute given with each rewritten image's `srcset`: how
+    /// wide the image is displayed. Defaults to `100vw`.
+    #[serde(default = "default_sizes")]
+    pub sizes: String,
+    /// `sizes` for the images of a section's pages, keyed like
+    /// `[sitemap.sections]`: `"posts"`, `"notes"`, a collection name, the
+    /// top-level directory of nested pages, or `"pages"` for top-level
+    /// pages.
+    #[serde(default)]
+    pub section_sizes: HashMap<String, String>,
+    /// Glob patterns of images to leave alone, relative to the output
+    /// root (e.g. `images/logos/**`, `**/qr-*.png`). A directory holding
+    /// a [`NO_IMAGES_MARKER`] file is excluded along with everything
+    /// under it.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Worker threads for image processing, below the build's own
+    /// `jobs`. Defaults to one per core.
+    #[serde(default)]
+    pub threads: Option<usize>,
+    /// Report progress while images are processed, through the callback
+    /// set with [`ThemeEngine::set_image_progress`](crate::ThemeEngine::set_image_progress),
+    /// and a summary afterwards. `bamboo build` shows them on stderr.
+    /// Defaults to `true`.
+    #[serde(default = "default_progress")]
+    pub progress: bool,
 }
 
+/// File that excludes the images of its directory, and of every
+/// directory below it, from processing.
+pub const NO_IMAGES_MARKER: &str = ".bamboo-no-images";
+
 fn default_widths() -> Vec<u32> {
     vec![320, 640, 1024, 1920]
 }
@@ -39,6 +110,14 @@ fn default_quality() -> u8 {
     80
 }
 
+fn default_progress() -> bool {
+    true
+}
+
+fn default_sizes() -> String {
+    "100vw".to_string()
+}
+
 fn default_formats() -> Vec<String> {
     vec!["webp".to_string(), "jpg".to_string()]
 }
@@ -49,8 +128,142 @@ impl Default for ImageConfig {
             widths: default_widths(),
             quality: default_quality(),
             formats: default_formats(),
+            format_policy: BTreeMap::new(),
+            strip_metadata: false,
+            placeholders: false,
+            dominant_colors: false,
+            remote: false,
+            art_direction: Vec::new(),
+            sizes: default_sizes(),
+            section_sizes: HashMap::new(),
+            exclude: Vec::new(),
+            threads: None,
+            progress: default_progress(),
+        }
+    }
+}
+
+impl ImageConfig {
+    /// Output formats for the source image at `path`: its extension's
+    /// entry in `format_policy`, or `formats`. Empty for passthrough
+    /// images.
+    pub fn formats_for(&self, path: &str) -> &[String] {
+        Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| self.format_policy.get(&policy_key(extension)))
+            .unwrap_or(&self.formats)
+    }
+}
+
+/// `format_policy` key for a source extension.
+fn policy_key(extension: &str) -> String {
+    match extension.to_ascii_lowercase().as_str() {
+        "jpeg" => "jpg".to_string(),
+        extension => extension.to_string(),
+    }
+}
+
+/// Reads `[images.format_policy]`, where each extension maps to a list of
+/// formats or to `"passthrough"`.
+fn deserialize_format_policy<'de, D>(
+    deserializer: D,
+) -> std::result::Result<BTreeMap<String, Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Policy {
+        Formats(Vec<String>),
+        Keyword(String),
+    }
+
+    let policies = BTreeMap::<String, Policy>::deserialize(deserializer)?;
+    policies
+        .into_iter()
+        .map(|(extension, policy)| {
+            let formats = match policy {
+                Policy::Formats(formats) => formats,
+                Policy::Keyword(keyword) if keyword == "passthrough" => Vec::new(),
+                Policy::Keyword(keyword) => {
+                    return Err(serde::de::Error::custom(format!(
+                        "invalid format policy '{keyword}' for '{extension}', expected a list of formats or \"passthrough\""
+                    )));
+                }
+            };
+            Ok((policy_key(&extension), formats))
+        })
+        .collect()
+}
+
+/// The `sizes` attribute for the images of each rendered page.
+#[derive(Debug, Clone, Default)]
+pub struct ImageSizes {
+    default: String,
+    pages: HashMap<PathBuf, String>,
+}
+
+impl ImageSizes {
+    /// Resolves `config`'s `section_sizes` for every page, post, note, and
+    /// collection item of `site`. Other pages get `config.sizes`.
+    pub fn new(site: &Site, config: &ImageConfig) -> Self {
+        let pages = SiteGraph::new(site)
+            .nodes()
+            .filter_map(|node| {
+                let section = match node.section() {
+                    "" => crate::sitemap::PAGES_SECTION,
+                    section => section,
+                };
+                let sizes = config.section_sizes.get(section)?;
+                Some((node.content().path.clone(), sizes.clone()))
+            })
+            .collect();
+        Self {
+            default: config.sizes.clone(),
+            pages,
         }
     }
+
+    /// `sizes` for the page written to `path`, relative to the output
+    /// directory.
+    pub fn for_page(&self, path: &Path) -> &str {
+        self.pages.get(path).unwrap_or(&self.default)
+    }
+}
+
+/// An `[[images.art_direction]]` rule: where `media` matches, serve the
+/// image scaled to cover `width`×`height` and cropped to it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtDirection {
+    /// Media query the crop is used for, e.g. `(max-width: 640px)`.
+    pub media: String,
+    /// Crop width in pixels.
+    pub width: u32,
+    /// Crop height in pixels.
+    pub height: u32,
+}
+
+/// Parses inline art-direction rules, as given to the `image` shortcode's
+/// `art` argument: `;`-separated entries of a media query followed by a
+/// `WIDTHxHEIGHT` size, e.g. `(max-width: 640px) 640x640`.
+pub fn parse_art_direction(spec: &str) -> std::result::Result<Vec<ArtDirection>, String> {
+    spec.split(';')
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty())
+        .map(|rule| {
+            let invalid =
+                || format!("invalid art direction rule '{rule}', expected 'MEDIA WIDTHxHEIGHT'");
+            let (media, size) = rule.rsplit_once(char::is_whitespace).ok_or_else(invalid)?;
+            let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+            let parse = |value: &str| value.parse::<u32>().ok().filter(|value| *value > 0);
+            Ok(ArtDirection {
+                media: media.trim().to_string(),
+                width: parse(width).ok_or_else(invalid)?,
+                height: parse(height).ok_or_else(invalid)?,
+            })
+        })
+        .collect()
 }
 
 /// One resized output produced from a single source image.
@@ -64,12 +277,264 @@ pub struct ImageVariant {
     pub format: String,
 }
 
+/// A crop generated for an [`ArtDirection`] rule.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtDirectedVariant {
+    /// The rule's media query.
+    pub media: String,
+    /// Output path relative to the project root.
+    pub path: String,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Output format (`"webp"`, `"jpg"`, ...).
+    pub format: String,
+}
+
+/// A size the `image` shortcode asked the pipeline for: `src` scaled to
+/// `width`, or scaled to cover `width`×`height` and cropped to it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ImageRequest {
+    /// Source image path from the site root, as written in the shortcode
+    /// (e.g. `/images/photo.jpg`).
+    pub src: String,
+    /// Width in pixels. Never upscaled: a width beyond the original's
+    /// keeps the original size.
+    pub width: u32,
+    /// Crop height in pixels.
+    pub height: Option<u32>,
+}
+
+impl ImageRequest {
+    /// Source path relative to the output root.
+    fn source(&self) -> &str {
+        self.src.trim_start_matches('/')
+    }
+
+    /// File name suffix of the variant: `800w`, or `800x600` for a crop.
+    fn suffix(&self) -> String {
+        match self.height {
+            Some(height) => format!("{}x{}", self.width, height),
+            None => format!("{}w", self.width),
+        }
+    }
+
+    /// Path of the variant in `format`, relative to the output root:
+    /// `images/photo-800w.webp` for `/images/photo.jpg`.
+    pub fn variant_path(&self, format: &str) -> String {
+        let source = self.source();
+        let (directory, file_name) = match source.rfind('/') {
+            Some(index) => (&source[..=index], &source[index + 1..]),
+            None => ("", source),
+        };
+        let stem = file_name
+            .rfind('.')
+            .map_or(file_name, |index| &file_name[..index]);
+        format!("{directory}{stem}-{}.{format}", self.suffix())
+    }
+}
+
 /// All variants produced during a build, keyed by the original source image
 /// path. Consumed by templates that want to emit `<picture>` markup.
 #[derive(Debug, Clone, Serialize)]
 pub struct ImageManifest {
     /// Map from source image path to the list of variants generated for it.
     pub variants: HashMap<String, Vec<ImageVariant>>,
+    /// Map from source image path to its placeholder, a base64 `data:` URI
+    /// of a tiny blurred JPEG. Empty unless `placeholders = true`.
+    pub placeholders: HashMap<String, String>,
+    /// Map from source image path to its dominant color as `#rrggbb`.
+    /// Empty unless `dominant_colors = true`.
+    pub colors: HashMap<String, String>,
+    /// Map from source image path to its [`ArtDirection`] crops, rule by
+    /// rule and format by format.
+    pub art_direction: HashMap<String, Vec<ArtDirectedVariant>>,
+    /// Totals for the build summary.
+    pub stats: ImageStats,
+}
+
+/// What a [`process_images`] run did.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ImageStats {
+    /// Source images processed, excluded ones aside.
+    pub images: usize,
+    /// Of those, images whose variants were copied from the cache.
+    pub cached: usize,
+    /// Variant files written.
+    pub variants: usize,
+    /// Bytes saved: for each variant, how much smaller it is than its
+    /// source image, which a browser would otherwise have downloaded in
+    /// its place, plus any metadata stripped from the originals.
+    pub bytes_saved: u64,
+}
+
+impl std::fmt::Display for ImageStats {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "Processed {} image(s) ({} cached) into {} variant(s), saving {}",
+            self.images,
+            self.cached,
+            self.variants,
+            format_bytes(self.bytes_saved)
+        )
+    }
+}
+
+/// `bytes` in B, KiB, or MiB.
+fn format_bytes(bytes: u64) -> String {
+    const KIB: f64 = 1024.0;
+    let bytes_float = bytes as f64;
+    if bytes_float >= KIB * KIB {
+        format!("{:.1} MiB", bytes_float / (KIB * KIB))
+    } else if bytes_float >= KIB {
+        format!("{:.1} KiB", bytes_float / KIB)
+    } else {
+        format!("{bytes} B")
+    }
+}
+
+/// An image starting to process, reported to the progress callback of
+/// [`process_images_with_progress`].
+#[derive(Debug, Clone, Copy)]
+pub struct ImageProgress<'a> {
+    /// Images finished so far.
+    pub done: usize,
+    /// Images in the run.
+    pub total: usize,
+    /// Output-relative path of the image starting.
+    pub path: &'a str,
+    /// Time since the run started.
+    pub elapsed: Duration,
+}
+
+impl ImageProgress<'_> {
+    /// Estimated time left, from the average time per finished image.
+    /// `None` until one has finished.
+    pub fn remaining(&self) -> Option<Duration> {
+        (self.done > 0).then(|| {
+            self.elapsed
+                .div_f64(self.done as f64)
+                .mul_f64(self.total.saturating_sub(self.done) as f64)
+        })
+    }
+}
+
+/// Callback receiving [`ImageProgress`] as images start. Called from the
+/// worker threads.
+pub type ImageProgressFn<'a> = dyn Fn(&ImageProgress<'_>) + Send + Sync + 'a;
+
+/// Counts finished images of a [`process_images`] run for its progress
+/// callback.
+struct Progress<'a> {
+    total: usize,
+    done: AtomicUsize,
+    started: Instant,
+    callback: Option<&'a ImageProgressFn<'a>>,
+}
+
+impl<'a> Progress<'a> {
+    fn new(total: usize, callback: Option<&'a ImageProgressFn<'a>>) -> Self {
+        Self {
+            total,
+            done: AtomicUsize::new(0),
+            started: Instant::now(),
+            callback,
+        }
+    }
+
+    fn start(&self, path: &str) {
+        if let Some(callback) = self.callback {
+            callback(&ImageProgress {
+                done: self.done.load(Ordering::Relaxed),
+                total: self.total,
+                path,
+                elapsed: self.started.elapsed(),
+            });
+        }
+    }
+
+    fn finish_one(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// What [`process_images`] produced for one source image.
+struct ProcessedImage {
+    key: String,
+    source_hash: String,
+    variants: Vec<ImageVariant>,
+    placeholder: Option<String>,
+    color: Option<String>,
+    art_variants: Vec<ArtDirectedVariant>,
+    cached: bool,
+    /// Files written for the image, width variants, crops, and requested
+    /// sizes alike.
+    files: usize,
+    bytes_saved: u64,
+}
+
+/// Longest side of a placeholder image, in pixels.
+const PLACEHOLDER_SIZE: u32 = 16;
+const PLACEHOLDER_QUALITY: u8 = 60;
+
+/// Shrinks `image` to [`PLACEHOLDER_SIZE`], blurs it, and encodes it as a
+/// `data:image/jpeg;base64,...` URI of a few hundred bytes. Returns `None`
+/// for images with transparent pixels, which would let the placeholder
+/// show through once loaded.
+pub fn placeholder(image: &DynamicImage) -> Option<String> {
+    use base64::Engine;
+
+    let small = image
+        .resize(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, FilterType::Triangle)
+        .blur(1.0);
+    if small.to_rgba8().pixels().any(|pixel| pixel[3] < u8::MAX) {
+        return None;
+    }
+    let rgb = small.to_rgb8();
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, PLACEHOLDER_QUALITY)
+        .write_image(
+            rgb.as_raw(),
+            rgb.width(),
+            rgb.height(),
+            image::ExtendedColorType::Rgb8,
+        )
+        .ok()?;
+    Some(format!(
+        "data:image/jpeg;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(jpeg)
+    ))
+}
+
+/// Side of the thumbnail the dominant color is sampled from, in pixels.
+const COLOR_SAMPLE_SIZE: u32 = 32;
+
+/// The most common color of `image` as `#rrggbb`: pixels of a small
+/// thumbnail are bucketed by their top four bits per channel, and the
+/// fullest bucket's pixels are averaged. Mostly transparent pixels don't
+/// count; returns `None` if no pixel is opaque enough.
+pub fn dominant_color(image: &DynamicImage) -> Option<String> {
+    let thumbnail = image
+        .resize(COLOR_SAMPLE_SIZE, COLOR_SAMPLE_SIZE, FilterType::Triangle)
+        .to_rgba8();
+    let mut buckets: HashMap<(u8, u8, u8), (u32, [u32; 3])> = HashMap::new();
+    for pixel in thumbnail.pixels().filter(|pixel| pixel[3] >= 128) {
+        let [red, green, blue, _] = pixel.0;
+        let (count, sums) = buckets
+            .entry((red >> 4, green >> 4, blue >> 4))
+            .or_default();
+        *count += 1;
+        for (sum, channel) in sums.iter_mut().zip([red, green, blue]) {
+            *sum += u32::from(channel);
+        }
+    }
+    let (_, (count, sums)) = buckets
+        .into_iter()
+        .max_by_key(|(bucket, (count, _))| (*count, *bucket))?;
+    let [red, green, blue] = sums.map(|sum| (sum + count / 2) / count);
+    Some(format!("#{red:02x}{green:02x}{blue:02x}"))
 }
 
 const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp"];
@@ -97,197 +562,687 @@ fn is_generated_variant(path: &Path, configured_widths: &[u32]) -> bool {
     false
 }
 
+/// Whether `path` is a crop generated for one of the `rules`
+/// (`photo-640x640.webp` for a 640×640 rule).
+fn is_art_directed_variant(path: &Path, rules: &[ArtDirection]) -> bool {
+    let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+        return false;
+    };
+    rules
+        .iter()
+        .any(|rule| stem.ends_with(&format!("-{}x{}", rule.width, rule.height)))
+}
+
+/// Directory under the project cache holding encoded variants.
+const IMAGE_CACHE_DIR: &str = "images";
+const IMAGE_CACHE_FILE: &str = "images.json";
+
+/// `.bamboo-cache/images.json`: what was generated for each source image,
+/// so unchanged images are copied from `.bamboo-cache/images/` instead of
+/// being decoded and re-encoded.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ImageCache {
+    /// The `[images]` settings the cached variants were encoded with. A
+    /// change invalidates every entry.
+    settings: String,
+    /// Keyed by the SHA-256 of the source image as copied to the output.
+    images: HashMap<String, CachedImage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedImage {
+    variants: Vec<CachedVariant>,
+    placeholder: Option<String>,
+    #[serde(default)]
+    color: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedVariant {
+    width: u32,
+    format: String,
+}
+
+/// Name of a variant's file in its image's cache directory: its size
+/// suffix (`640w`, `800x600`) and format.
+fn cached_file_name(suffix: &str, format: &str) -> String {
+    format!("{suffix}.{format}")
+}
+
+fn cache_settings(config: &ImageConfig) -> String {
+    serde_json::json!([
+        config.widths,
+        config.quality,
+        config.formats,
+        config.format_policy,
+        config.placeholders,
+        config.dominant_colors
+    ])
+    .to_string()
+}
+
+fn load_image_cache(cache_dir: &Path, config: &ImageConfig) -> ImageCache {
+    fs::read_to_string(cache_dir.join(IMAGE_CACHE_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str::<ImageCache>(&content).ok())
+        .filter(|cache| cache.settings == cache_settings(config))
+        .unwrap_or_default()
+}
+
+/// Writes `cache` and deletes cached variants of images no longer in it.
+fn save_image_cache(cache_dir: &Path, cache: &ImageCache) -> Result<()> {
+    let variants_dir = cache_dir.join(IMAGE_CACHE_DIR);
+    if let Ok(entries) = fs::read_dir(&variants_dir) {
+        for entry in entries.flatten() {
+            if !cache
+                .images
+                .contains_key(entry.file_name().to_string_lossy().as_ref())
+            {
+                fs::remove_dir_all(entry.path()).io_context("remove_dir_all", &entry.path())?;
+            }
+        }
+    }
+    fs::create_dir_all(cache_dir).io_context("create_dir_all", cache_dir)?;
+    let path = cache_dir.join(IMAGE_CACHE_FILE);
+    let content = serde_json::to_string(cache).map_err(std::io::Error::other)?;
+    fs::write(&path, content).io_context("write", &path)
+}
+
 /// Walks `output_dir`, finds source images, and emits resized variants at
-/// each configured width/format combination. Returns the [`ImageManifest`]
-/// describing every variant produced.
-pub fn process_images(output_dir: &Path, config: &ImageConfig) -> Result<ImageManifest> {
-    let image_paths: Vec<_> = WalkDir::new(output_dir)
+/// each configured width/format combination, plus every size in
+/// `requests`. Returns the [`ImageManifest`] describing the variants
+/// produced at configured widths.
+///
+/// With a `cache_dir` (normally [`crate::cache_dir`] of the project), the
+/// encoded variants and placeholders are kept there, keyed by a hash of the
+/// source image, and images unchanged since the last build are copied from
+/// it instead of being re-encoded.
+///
+/// Images matching `exclude` or under a [`NO_IMAGES_MARKER`] are left as
+/// they are, apart from the sizes in `requests`.
+pub fn process_images(
+    output_dir: &Path,
+    config: &ImageConfig,
+    requests: &[ImageRequest],
+    cache_dir: Option<&Path>,
+) -> Result<ImageManifest> {
+    process_images_with_progress(output_dir, config, requests, cache_dir, None)
+}
+
+/// [`process_images`], calling `progress` as each image starts.
+pub fn process_images_with_progress(
+    output_dir: &Path,
+    config: &ImageConfig,
+    requests: &[ImageRequest],
+    cache_dir: Option<&Path>,
+    progress: Option<&ImageProgressFn<'_>>,
+) -> Result<ImageManifest> {
+    let cache = cache_dir
+        .map(|cache_dir| load_image_cache(cache_dir, config))
+        .unwrap_or_default();
+    let requested_paths: HashSet<String> = requests
+        .iter()
+        .flat_map(|request| {
+            config
+                .formats_for(request.source())
+                .iter()
+                .map(|format| request.variant_path(format))
+        })
+        .collect();
+    let excluded = exclude_patterns(&config.exclude)?;
+    let marked_dirs: Vec<PathBuf> = WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name() == NO_IMAGES_MARKER && entry.path().is_file())
+        .filter_map(|entry| entry.path().parent().map(Path::to_path_buf))
+        .collect();
+
+    let (excluded_paths, image_paths): (Vec<_>, Vec<_>) = WalkDir::new(output_dir)
         .into_iter()
         .filter_map(|entry| entry.ok())
         .filter(|entry| {
             let path = entry.path();
-            path.is_file() && is_image_file(path) && !is_generated_variant(path, &config.widths)
+            path.is_file()
+                && is_image_file(path)
+                && !is_generated_variant(path, &config.widths)
+                && !requested_paths.contains(&relative_path(output_dir, path))
+                && !is_art_directed_variant(path, &config.art_direction)
         })
         .map(|entry| entry.path().to_path_buf())
-        .collect();
+        .partition(|path| {
+            excluded.is_match(relative_path(output_dir, path))
+                || marked_dirs
+                    .iter()
+                    .any(|directory| path.starts_with(directory))
+        });
+
+    // Excluded images only get the sizes `image` shortcodes asked for,
+    // so the markup those shortcodes emitted still resolves.
+    excluded_paths
+        .par_iter()
+        .map(|path| -> Result<()> {
+            let relative_original = relative_path(output_dir, path);
+            let image_requests: Vec<&ImageRequest> = requests
+                .iter()
+                .filter(|request| request.source() == relative_original)
+                .collect();
+            let formats = config.formats_for(&relative_original);
+            if image_requests.is_empty() || formats.is_empty() {
+                return Ok(());
+            }
+            let source_image = decode_image(path)?;
+            for request in image_requests {
+                let resized = resize_for_request(&source_image, request);
+                for format in formats {
+                    let target = output_dir.join(request.variant_path(format));
+                    write_variant(&resized, format, config.quality, &target)?;
+                }
+            }
+            Ok(())
+        })
+        .collect::<Result<()>>()?;
 
-    type ImageResult = Result<Option<(String, Vec<ImageVariant>)>>;
-    let results: Vec<ImageResult> = image_paths
+    let progress = Progress::new(image_paths.len(), progress);
+    let results: Vec<Result<ProcessedImage>> = image_paths
         .par_iter()
-        .map(|path| -> Result<Option<(String, Vec<ImageVariant>)>> {
-            let reader = ImageReader::open(path).map_err(|error| {
-                crate::error::BambooError::ImageProcessing {
-                    message: format!("failed to open {}: {}", path.display(), error),
+        .map(|path| -> Result<ProcessedImage> {
+            let relative_original = relative_path(output_dir, path);
+            progress.start(&relative_original);
+            let original = fs::read(path).io_context("read", path)?;
+            let source_hash = format!("{:x}", Sha256::digest(&original));
+            let mut source_bytes = original.len() as u64;
+            let mut bytes_saved = 0;
+            if config.strip_metadata
+                && let Some(stripped) = crate::image_metadata::strip_metadata(&original)
+                && stripped != original
+            {
+                bytes_saved = source_bytes.saturating_sub(stripped.len() as u64);
+                source_bytes = stripped.len() as u64;
+                fs::write(path, stripped).io_context("write", path)?;
+            }
+            // Bytes saved by the variants written for `outputs`.
+            let variant_savings = |outputs: &[(String, &str)]| -> u64 {
+                outputs
+                    .iter()
+                    .filter_map(|(suffix, format)| {
+                        let stem = path.file_stem()?.to_str()?;
+                        fs::metadata(path.with_file_name(format!("{stem}-{suffix}.{format}"))).ok()
+                    })
+                    .map(|metadata| source_bytes.saturating_sub(metadata.len()))
+                    .sum()
+            };
+
+            let formats = config.formats_for(&relative_original);
+            let variant_path = |suffix: &str, format: &str| {
+                let stem = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or("image");
+                path.with_file_name(format!("{stem}-{suffix}.{format}"))
+            };
+            let art_requests: Vec<ImageRequest> = config
+                .art_direction
+                .iter()
+                .map(|rule| ImageRequest {
+                    src: format!("/{relative_original}"),
+                    width: rule.width,
+                    height: Some(rule.height),
+                })
+                .collect();
+            let image_requests: BTreeSet<&ImageRequest> = requests
+                .iter()
+                .filter(|request| request.source() == relative_original)
+                .chain(&art_requests)
+                .collect();
+            let art_variants: Vec<ArtDirectedVariant> = config
+                .art_direction
+                .iter()
+                .zip(&art_requests)
+                .flat_map(|(rule, request)| {
+                    formats.iter().map(move |format| ArtDirectedVariant {
+                        media: rule.media.clone(),
+                        path: relative_path(output_dir, &variant_path(&request.suffix(), format)),
+                        width: rule.width,
+                        height: rule.height,
+                        format: format.clone(),
+                    })
+                })
+                .collect();
+            let mut outputs: Vec<(String, &str)> = image_requests
+                .iter()
+                .flat_map(|request| {
+                    formats
+                        .iter()
+                        .map(|format| (request.suffix(), format.as_str()))
+                })
+                .collect();
+            let image_cache_dir =
+                cache_dir.map(|cache_dir| cache_dir.join(IMAGE_CACHE_DIR).join(&source_hash));
+
+            if let (Some(cached), Some(image_cache_dir)) =
+                (cache.images.get(&source_hash), &image_cache_dir)
+            {
+                let cached_outputs: Vec<(String, &str)> = cached
+                    .variants
+                    .iter()
+                    .map(|variant| (format!("{}w", variant.width), variant.format.as_str()))
+                    .chain(outputs.iter().cloned())
+                    .collect();
+                if cached_outputs.iter().all(|(suffix, format)| {
+                    image_cache_dir
+                        .join(cached_file_name(suffix, format))
+                        .is_file()
+                }) {
+                    for (suffix, format) in &cached_outputs {
+                        let target = variant_path(suffix, format);
+                        fs::copy(
+                            image_cache_dir.join(cached_file_name(suffix, format)),
+                            &target,
+                        )
+                        .io_context("copy", &target)?;
+                    }
+                    let image_variants = cached
+                        .variants
+                        .iter()
+                        .map(|variant| ImageVariant {
+                            path: relative_path(
+                                output_dir,
+                                &variant_path(&format!("{}w", variant.width), &variant.format),
+                            ),
+                            width: variant.width,
+                            format: variant.format.clone(),
+                        })
+                        .collect();
+                    progress.finish_one();
+                    return Ok(ProcessedImage {
+                        key: relative_original,
+                        source_hash,
+                        variants: image_variants,
+                        placeholder: cached.placeholder.clone(),
+                        color: cached.color.clone(),
+                        art_variants,
+                        cached: true,
+                        files: cached_outputs.len(),
+                        bytes_saved: bytes_saved + variant_savings(&cached_outputs),
+                    });
                 }
-            })?;
-            let source_image =
-                reader
-                    .decode()
-                    .map_err(|error| crate::error::BambooError::ImageProcessing {
-                        message: format!("failed to decode {}: {}", path.display(), error),
-                    })?;
+            }
 
+            let source_image = decode_image(path)?;
             let original_width = source_image.width();
-            let original_height = source_image.height();
-            let stem = path
-                .file_stem()
-                .and_then(|stem| stem.to_str())
-                .unwrap_or("image");
-            let parent_directory = path.parent().unwrap_or(output_dir);
-
-            let relative_original = path
-                .strip_prefix(output_dir)
-                .unwrap_or(path)
-                .to_string_lossy()
-                .replace('\\', "/");
 
             let mut image_variants = Vec::new();
 
             for &target_width in &config.widths {
-                if target_width >= original_width {
+                if target_width >= original_width || formats.is_empty() {
                     continue;
                 }
 
-                let scale_factor = target_width as f64 / original_width as f64;
-                let target_height = (original_height as f64 * scale_factor).round() as u32;
-                let resized =
-                    source_image.resize_exact(target_width, target_height, FilterType::Lanczos3);
-
-                for format in &config.formats {
-                    let variant_filename = format!("{}-{}w.{}", stem, target_width, format);
-                    let variant_path = parent_directory.join(&variant_filename);
-
-                    let write_result: std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> = match format.as_str() {
-                        "webp" => {
-                            let rgba_image = resized.to_rgba8();
-                            let encoder = webp::Encoder::from_rgba(
-                                rgba_image.as_raw(),
-                                resized.width(),
-                                resized.height(),
-                            );
-                            let encoded = encoder.encode(config.quality as f32);
-                            fs::write(&variant_path, &*encoded).map_err(|error| error.into())
-                        }
-                        "jpg" | "jpeg" => {
-                            (|| -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
-                                let file = File::create(&variant_path)?;
-                                let encoder = JpegEncoder::new_with_quality(&file, config.quality);
-                                let rgb_image = resized.to_rgb8();
-                                encoder.write_image(
-                                    rgb_image.as_raw(),
-                                    resized.width(),
-                                    resized.height(),
-                                    image::ExtendedColorType::Rgb8,
-                                )?;
-                                Ok(())
-                            })()
-                        }
-                        _ => {
-                            resized
-                                .save(&variant_path)
-                                .map_err(|error| error.into())
-                        }
-                    };
-
-                    write_result.map_err(|error| crate::error::BambooError::ImageProcessing {
-                        message: format!(
-                            "failed to write variant {}: {}",
-                            variant_path.display(),
-                            error
-                        ),
-                    })?;
-
-                    let relative_variant = variant_path
-                        .strip_prefix(output_dir)
-                        .unwrap_or(&variant_path)
-                        .to_string_lossy()
-                        .replace('\\', "/");
+                let resized = resize_to_width(&source_image, target_width);
 
+                for format in formats {
+                    let target = variant_path(&format!("{target_width}w"), format);
+                    write_variant(&resized, format, config.quality, &target)?;
                     image_variants.push(ImageVariant {
-                        path: relative_variant,
+                        path: relative_path(output_dir, &target),
                         width: target_width,
                         format: format.clone(),
                     });
                 }
             }
 
-            if !image_variants.is_empty() {
-                Ok(Some((relative_original, image_variants)))
+            for request in image_requests.iter().filter(|_| !formats.is_empty()) {
+                let resized = resize_for_request(&source_image, request);
+                for format in formats {
+                    let target = variant_path(&request.suffix(), format);
+                    write_variant(&resized, format, config.quality, &target)?;
+                }
+            }
+
+            let placeholder = if config.placeholders {
+                placeholder(&source_image)
+            } else {
+                None
+            };
+            let color = if config.dominant_colors {
+                dominant_color(&source_image)
             } else {
-                Ok(None)
+                None
+            };
+
+            outputs.extend(
+                image_variants
+                    .iter()
+                    .map(|variant| (format!("{}w", variant.width), variant.format.as_str())),
+            );
+            if let Some(image_cache_dir) = &image_cache_dir {
+                fs::create_dir_all(image_cache_dir)
+                    .io_context("create_dir_all", image_cache_dir)?;
+                for (suffix, format) in &outputs {
+                    let cached = image_cache_dir.join(cached_file_name(suffix, format));
+                    fs::copy(variant_path(suffix, format), &cached).io_context("copy", &cached)?;
+                }
             }
+
+            let files = outputs.len();
+            let bytes_saved = bytes_saved + variant_savings(&outputs);
+            progress.finish_one();
+            Ok(ProcessedImage {
+                key: relative_original,
+                source_hash,
+                variants: image_variants,
+                placeholder,
+                color,
+                art_variants,
+                cached: false,
+                files,
+                bytes_saved,
+            })
         })
         .collect();
 
     let mut variants: HashMap<String, Vec<ImageVariant>> = HashMap::new();
+    let mut placeholders: HashMap<String, String> = HashMap::new();
+    let mut colors: HashMap<String, String> = HashMap::new();
+    let mut art_direction: HashMap<String, Vec<ArtDirectedVariant>> = HashMap::new();
+    let mut stats = ImageStats::default();
+    let mut new_cache = ImageCache {
+        settings: cache_settings(config),
+        images: HashMap::new(),
+    };
     for result in results {
-        if let Some((key, value)) = result? {
-            variants.insert(key, value);
+        let ProcessedImage {
+            key,
+            source_hash,
+            variants: image_variants,
+            placeholder,
+            color,
+            art_variants,
+            cached,
+            files,
+            bytes_saved,
+        } = result?;
+        stats.images += 1;
+        stats.cached += usize::from(cached);
+        stats.variants += files;
+        stats.bytes_saved += bytes_saved;
+        new_cache.images.insert(
+            source_hash,
+            CachedImage {
+                variants: image_variants
+                    .iter()
+                    .map(|variant| CachedVariant {
+                        width: variant.width,
+                        format: variant.format.clone(),
+                    })
+                    .collect(),
+                placeholder: placeholder.clone(),
+                color: color.clone(),
+            },
+        );
+        if let Some(color) = color {
+            colors.insert(key.clone(), color);
+        }
+        if let Some(placeholder) = placeholder {
+            placeholders.insert(key.clone(), placeholder);
+        }
+        if !art_variants.is_empty() {
+            art_direction.insert(key.clone(), art_variants);
         }
+        if !image_variants.is_empty() {
+            variants.insert(key, image_variants);
+        }
+    }
+    if let Some(cache_dir) = cache_dir {
+        save_image_cache(cache_dir, &new_cache)?;
     }
 
-    Ok(ImageManifest { variants })
+    Ok(ImageManifest {
+        variants,
+        placeholders,
+        colors,
+        art_direction,
+        stats,
+    })
 }
 
-/// Builds a `srcset` attribute value for the given original image using the
-/// variants recorded in `manifest`. Returns an empty string if nothing has
-/// been generated for that path.
-pub fn generate_srcset(original_path: &str, manifest: &ImageManifest) -> String {
-    let escaped_path = crate::xml::escape(original_path);
-    let Some(image_variants) = manifest.variants.get(original_path) else {
-        return format!("<img src=\"/{}\">", escaped_path);
+/// Compiles `[images] exclude` patterns. `*` stays within one directory;
+/// `**` crosses directories.
+fn exclude_patterns(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern.trim_start_matches('/'))
+            .literal_separator(true)
+            .build()
+            .map_err(|error| crate::error::BambooError::ImageProcessing {
+                message: format!("invalid [images] exclude pattern '{pattern}': {error}"),
+            })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|error| crate::error::BambooError::ImageProcessing {
+            message: format!("invalid [images] exclude patterns: {error}"),
+        })
+}
+
+/// Decodes the image at `path` with its EXIF orientation applied to the
+/// pixels.
+fn decode_image(path: &Path) -> Result<DynamicImage> {
+    let decode_error = |error: image::ImageError| crate::error::BambooError::ImageProcessing {
+        message: format!("failed to decode {}: {}", path.display(), error),
     };
+    let mut decoder = ImageReader::open(path)
+        .map_err(|error| crate::error::BambooError::ImageProcessing {
+            message: format!("failed to open {}: {}", path.display(), error),
+        })?
+        .into_decoder()
+        .map_err(decode_error)?;
+    let orientation = decoder.orientation().map_err(decode_error)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(decode_error)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
 
-    if image_variants.is_empty() {
-        return format!("<img src=\"/{}\">", escaped_path);
+/// `image` scaled (and cropped) to the size `request` asks for.
+fn resize_for_request(image: &DynamicImage, request: &ImageRequest) -> DynamicImage {
+    match request.height {
+        Some(height) => image.resize_to_fill(request.width, height, FilterType::Lanczos3),
+        None if request.width < image.width() => resize_to_width(image, request.width),
+        None => image.clone(),
     }
+}
 
-    let mut formats_seen: Vec<String> = Vec::new();
-    for variant in image_variants {
-        let normalized = if variant.format == "jpeg" {
-            "jpg".to_string()
-        } else {
-            variant.format.clone()
+/// `path` relative to `output_dir`, `/`-separated.
+fn relative_path(output_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(output_dir)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Scales `image` to `width`, keeping its aspect ratio.
+fn resize_to_width(image: &DynamicImage, width: u32) -> DynamicImage {
+    let scale_factor = width as f64 / image.width() as f64;
+    let height = (image.height() as f64 * scale_factor).round() as u32;
+    image.resize_exact(width, height, FilterType::Lanczos3)
+}
+
+/// Encodes `image` as `format` (`"webp"`, `"jpg"`, or any format the
+/// `image` crate infers from the extension) into `path`.
+fn write_variant(image: &DynamicImage, format: &str, quality: u8, path: &Path) -> Result<()> {
+    let write_result: std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> =
+        match format {
+            "webp" => {
+                let rgba_image = image.to_rgba8();
+                let encoder =
+                    webp::Encoder::from_rgba(rgba_image.as_raw(), image.width(), image.height());
+                let encoded = encoder.encode(quality as f32);
+                fs::write(path, &*encoded).map_err(|error| error.into())
+            }
+            "jpg" | "jpeg" => {
+                (|| -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+                    let file = File::create(path)?;
+                    let encoder = JpegEncoder::new_with_quality(&file, quality);
+                    let rgb_image = image.to_rgb8();
+                    encoder.write_image(
+                        rgb_image.as_raw(),
+                        image.width(),
+                        image.height(),
+                        image::ExtendedColorType::Rgb8,
+                    )?;
+                    Ok(())
+                })()
+            }
+            _ => image.save(path).map_err(|error| error.into()),
         };
-        if !formats_seen.contains(&normalized) {
-            formats_seen.push(normalized);
-        }
+
+    write_result.map_err(|error| crate::error::BambooError::ImageProcessing {
+        message: format!("failed to write variant {}: {}", path.display(), error),
+    })
+}
+
+/// Scales the encoded image `source` to fill a `size`×`size` square,
+/// cropping the longer side evenly, and encodes the result as PNG. Used for
+/// the icon set of the web app manifest.
+pub fn square_png(source: &[u8], size: u32) -> Result<Vec<u8>> {
+    let image_error = |error: image::ImageError| crate::error::BambooError::ImageProcessing {
+        message: format!("failed to resize icon to {size}px: {error}"),
+    };
+    let icon = image::load_from_memory(source)
+        .map_err(image_error)?
+        .resize_to_fill(size, size, FilterType::Lanczos3);
+    let mut png = std::io::Cursor::new(Vec::new());
+    icon.write_to(&mut png, image::ImageFormat::Png)
+        .map_err(image_error)?;
+    Ok(png.into_inner())
+}
+
+/// Builds a `<picture>` for the given original image using the variants
+/// recorded in `manifest`, displayed at `sizes`. Returns a bare `<img>` if
+/// nothing has been generated for that path.
+pub fn generate_srcset(original_path: &str, manifest: &ImageManifest, sizes: &str) -> String {
+    let img = format!("<img src=\"/{}\">", crate::xml::escape(original_path));
+    let sources = picture_sources(original_path, manifest, sizes);
+    if sources.is_empty() {
+        return img;
     }
+    let img = match img_srcset(original_path, manifest) {
+        Some(srcset) => with_srcset(&img, &srcset, sizes),
+        None => img,
+    };
+    format!("<picture>{sources}{img}</picture>")
+}
 
-    let mut parts = Vec::new();
-    parts.push("<picture>".to_string());
+/// Format of a variant, with `jpeg` folded into `jpg`.
+fn normalized_format(variant: &ImageVariant) -> &str {
+    if variant.format == "jpeg" {
+        "jpg"
+    } else {
+        &variant.format
+    }
+}
 
-    for format in &formats_seen {
-        let matching: Vec<&ImageVariant> = image_variants
-            .iter()
-            .filter(|variant| {
-                let normalized = if variant.format == "jpeg" {
-                    "jpg"
-                } else {
-                    &variant.format
-                };
-                normalized == format
-            })
-            .collect();
+/// `srcset` value listing `variants` with their widths.
+fn srcset_value<'a>(variants: impl Iterator<Item = &'a ImageVariant>) -> String {
+    variants
+        .map(|variant| format!("/{} {}w", crate::xml::escape(&variant.path), variant.width))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
-        if !matching.is_empty() {
-            let mime_type = format_to_mime(format);
-            let srcset_entries: Vec<String> = matching
+/// `srcset` for the fallback `<img>` of `original_path`, read by browsers
+/// and feed readers that ignore `<picture>`: the width variants in the
+/// original's own format, or else in the last format generated (the most
+/// widely supported one, by convention).
+fn img_srcset(original_path: &str, manifest: &ImageManifest) -> Option<String> {
+    let image_variants = manifest.variants.get(original_path)?;
+    let original_format = Path::new(original_path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(policy_key);
+    let format = original_format
+        .filter(|format| {
+            image_variants
                 .iter()
-                .map(|variant| format!("/{} {}w", crate::xml::escape(&variant.path), variant.width))
-                .collect();
-            parts.push(format!(
-                "<source type=\"{}\" srcset=\"{}\">",
-                mime_type,
-                srcset_entries.join(", ")
-            ));
-        }
+                .any(|variant| normalized_format(variant) == format)
+        })
+        .or_else(|| {
+            image_variants
+                .last()
+                .map(|variant| normalized_format(variant).to_string())
+        })?;
+    Some(srcset_value(
+        image_variants
+            .iter()
+            .filter(|variant| normalized_format(variant) == format),
+    ))
+}
+
+/// `img_tag` with `srcset` and `sizes` attributes, unless it already has
+/// its own `srcset`.
+fn with_srcset(img_tag: &str, srcset: &str, sizes: &str) -> String {
+    let lower_tag = img_tag.to_ascii_lowercase();
+    if find_standalone_src(&lower_tag, "srcset=").is_some() {
+        return img_tag.to_string();
+    }
+    let sizes = if find_standalone_src(&lower_tag, "sizes=").is_some() {
+        String::new()
+    } else {
+        format!(" sizes=\"{}\"", crate::xml::escape(sizes))
+    };
+    format!(
+        "<img srcset=\"{srcset}\"{sizes}{}",
+        &img_tag["<img".len()..]
+    )
+}
+
+/// The `<source>` tags of `original_path`'s `<picture>`: its art-directed
+/// crops with their `media` queries first, since the browser takes the
+/// first match, then one `srcset` of width variants per format. Empty if
+/// nothing has been generated for the image.
+fn picture_sources(original_path: &str, manifest: &ImageManifest, sizes: &str) -> String {
+    let mut sources = String::new();
+    for variant in manifest
+        .art_direction
+        .get(original_path)
+        .into_iter()
+        .flatten()
+    {
+        sources.push_str(&format!(
+            "<source media=\"{}\" type=\"{}\" srcset=\"/{}\">",
+            crate::xml::escape(&variant.media),
+            format_to_mime(&variant.format),
+            crate::xml::escape(&variant.path)
+        ));
     }
 
-    parts.push(format!("<img src=\"/{}\">", escaped_path));
-    parts.push("</picture>".to_string());
+    let image_variants = manifest
+        .variants
+        .get(original_path)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let mut formats_seen: Vec<&str> = Vec::new();
+    for variant in image_variants {
+        let format = normalized_format(variant);
+        if !formats_seen.contains(&format) {
+            formats_seen.push(format);
+        }
+    }
 
-    parts.join("")
+    for format in formats_seen {
+        let srcset = srcset_value(
+            image_variants
+                .iter()
+                .filter(|variant| normalized_format(variant) == format),
+        );
+        sources.push_str(&format!(
+            "<source type=\"{}\" srcset=\"{srcset}\" sizes=\"{}\">",
+            format_to_mime(format),
+            crate::xml::escape(sizes)
+        ));
+    }
+    sources
 }
 
 fn format_to_mime(format: &str) -> &'static str {
@@ -303,9 +1258,18 @@ fn format_to_mime(format: &str) -> &'static str {
 
 /// Walks every HTML file under `output_dir` and rewrites `<img>` tags for
 /// images present in `manifest` to include the matching `srcset`, so the
-/// browser can pick an appropriately-sized variant.
-pub fn apply_srcset_to_html(output_dir: &Path, manifest: &ImageManifest) -> Result<()> {
-    if manifest.variants.is_empty() {
+/// browser can pick an appropriately-sized variant. `sizes` gives each
+/// page's `sizes` attribute.
+pub fn apply_srcset_to_html(
+    output_dir: &Path,
+    manifest: &ImageManifest,
+    sizes: &ImageSizes,
+) -> Result<()> {
+    if manifest.variants.is_empty()
+        && manifest.placeholders.is_empty()
+        && manifest.colors.is_empty()
+        && manifest.art_direction.is_empty()
+    {
         return Ok(());
     }
 
@@ -321,7 +1285,8 @@ pub fn apply_srcset_to_html(output_dir: &Path, manifest: &ImageManifest) -> Resu
         }
 
         let content = fs::read_to_string(path)?;
-        let updated = replace_img_tags_with_srcset(&content, manifest);
+        let page_sizes = sizes.for_page(path.strip_prefix(output_dir).unwrap_or(path));
+        let updated = replace_img_tags_with_srcset(&content, manifest, page_sizes);
 
         if updated != content {
             fs::write(path, updated)?;
@@ -397,7 +1362,7 @@ fn find_tag_end(html: &str) -> Option<usize> {
     None
 }
 
-fn replace_img_tags_with_srcset(html: &str, manifest: &ImageManifest) -> String {
+fn replace_img_tags_with_srcset(html: &str, manifest: &ImageManifest, sizes: &str) -> String {
     let mut output = String::with_capacity(html.len());
     let mut remaining = html;
 
@@ -407,60 +1372,33 @@ fn replace_img_tags_with_srcset(html: &str, manifest: &ImageManifest) -> String
 
         if let Some(tag_end) = find_tag_end(remaining) {
             let tag_length = tag_end + 1;
-            let img_tag = &remaining[..tag_length];
-
-            if let Some(src) = extract_src_attribute(img_tag) {
-                let normalized = src.trim_start_matches('/');
-                if manifest.variants.contains_key(normalized) {
-                    let image_variants = &manifest.variants[normalized];
-                    let mut formats_seen: Vec<String> = Vec::new();
-                    for variant in image_variants {
-                        let normalized_format = if variant.format == "jpeg" {
-                            "jpg".to_string()
-                        } else {
-                            variant.format.clone()
-                        };
-                        if !formats_seen.contains(&normalized_format) {
-                            formats_seen.push(normalized_format);
-                        }
-                    }
-
-                    if !formats_seen.is_empty() {
-                        output.push_str("<picture>");
-                        for format in &formats_seen {
-                            let matching: Vec<&ImageVariant> = image_variants
-                                .iter()
-                                .filter(|variant| {
-                                    let nf = if variant.format == "jpeg" {
-                                        "jpg"
-                                    } else {
-                                        &variant.format
-                                    };
-                                    nf == format
-                                })
-                                .collect();
-                            let mime_type = format_to_mime(format);
-                            let srcset: Vec<String> = matching
-                                .iter()
-                                .map(|variant| {
-                                    format!(
-                                        "/{} {}w",
-                                        crate::xml::escape(&variant.path),
-                                        variant.width
-                                    )
-                                })
-                                .collect();
-                            output.push_str(&format!(
-                                "<source type=\"{}\" srcset=\"{}\">",
-                                mime_type,
-                                srcset.join(", ")
-                            ));
-                        }
-                        output.push_str(img_tag);
-                        output.push_str("</picture>");
-                        remaining = &remaining[tag_length..];
-                        continue;
+            let original_tag = &remaining[..tag_length];
+            let src = extract_src_attribute(original_tag);
+            let key = src.as_deref().map(|src| src.trim_start_matches('/'));
+            let color = key.and_then(|key| manifest.colors.get(key));
+            let placeholder = key.and_then(|key| manifest.placeholders.get(key));
+            let declarations: Vec<String> = color
+                .map(|color| format!("background-color:{color}"))
+                .into_iter()
+                .chain(placeholder.map(|placeholder| placeholder_declarations(placeholder)))
+                .collect();
+            let styled_tag = (!declarations.is_empty())
+                .then(|| with_style(original_tag, &declarations.join(";")));
+            let img_tag = styled_tag.as_deref().unwrap_or(original_tag);
+
+            if let Some(src) = src {
+                let original_path = src.trim_start_matches('/');
+                let sources = picture_sources(original_path, manifest, sizes);
+                if !sources.is_empty() {
+                    output.push_str("<picture>");
+                    output.push_str(&sources);
+                    match img_srcset(original_path, manifest) {
+                        Some(srcset) => output.push_str(&with_srcset(img_tag, &srcset, sizes)),
+                        None => output.push_str(img_tag),
                     }
+                    output.push_str("</picture>");
+                    remaining = &remaining[tag_length..];
+                    continue;
                 }
             }
 
@@ -476,6 +1414,28 @@ fn replace_img_tags_with_srcset(html: &str, manifest: &ImageManifest) -> String
     output
 }
 
+/// CSS declarations showing `placeholder` as a background.
+fn placeholder_declarations(placeholder: &str) -> String {
+    format!("background-image:url({placeholder});background-size:cover;background-repeat:no-repeat")
+}
+
+/// `img_tag` with `declarations` ahead of any inline style it already
+/// has.
+fn with_style(img_tag: &str, declarations: &str) -> String {
+    let lower_tag = img_tag.to_ascii_lowercase();
+    for quote in ['"', '\''] {
+        if let Some(position) = find_standalone_src(&lower_tag, &format!("style={quote}")) {
+            let value_start = position + "style=".len() + 1;
+            return format!(
+                "{}{declarations};{}",
+                &img_tag[..value_start],
+                &img_tag[value_start..]
+            );
+        }
+    }
+    format!("<img style=\"{declarations}\"{}", &img_tag["<img".len()..])
+}
+
 fn find_standalone_src(tag: &str, pattern: &str) -> Option<usize> {
     let mut search_from = 0;
     while let Some(position) = tag[search_from..].find(pattern) {
@@ -524,6 +1484,432 @@ mod tests {
         assert!(!is_image_file(Path::new("readme.md")));
     }
 
+    #[test]
+    fn test_process_images_strips_metadata() {
+        let mut exif = b"II\x2a\0\x08\0\0\0\x01\0".to_vec();
+        exif.extend([0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0]);
+        exif.extend(b"GPS 51.5072N 0.1276W");
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let original = output_dir.path().join("photo.jpg");
+        let mut encoder = JpegEncoder::new(File::create(&original).unwrap());
+        encoder.set_exif_metadata(exif).unwrap();
+        encoder
+            .write_image(
+                image::RgbImage::new(40, 20).as_raw(),
+                40,
+                20,
+                image::ExtendedColorType::Rgb8,
+            )
+            .unwrap();
+        let has_gps = |path: &Path| fs::read(path).unwrap().windows(3).any(|w| w == b"GPS");
+        assert!(has_gps(&original));
+
+        let config = ImageConfig {
+            widths: vec![10],
+            formats: vec!["jpg".to_string()],
+            strip_metadata: true,
+            placeholders: true,
+            ..ImageConfig::default()
+        };
+        let manifest = process_images(output_dir.path(), &config, &[], None).unwrap();
+        assert_eq!(manifest.variants["photo.jpg"][0].path, "photo-10w.jpg");
+        assert!(manifest.placeholders["photo.jpg"].starts_with("data:image/jpeg;base64,"));
+
+        let variant = output_dir.path().join("photo-10w.jpg");
+        assert!(!has_gps(&variant));
+        let variant = image::open(&variant).unwrap();
+        assert_eq!((variant.width(), variant.height()), (10, 20));
+
+        assert!(!has_gps(&original));
+        let mut decoder = ImageReader::open(&original)
+            .unwrap()
+            .into_decoder()
+            .unwrap();
+        assert_eq!(
+            decoder.orientation().unwrap(),
+            image::metadata::Orientation::Rotate90
+        );
+    }
+
+    #[test]
+    fn test_process_images_cache() {
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        image::RgbImage::new(40, 20)
+            .save(output_dir.path().join("photo.png"))
+            .unwrap();
+        let config = ImageConfig {
+            widths: vec![10, 20],
+            formats: vec!["png".to_string()],
+            ..ImageConfig::default()
+        };
+
+        let first =
+            process_images(output_dir.path(), &config, &[], Some(cache_dir.path())).unwrap();
+        assert_eq!(first.variants["photo.png"].len(), 2);
+        assert_eq!(
+            (first.stats.images, first.stats.cached, first.stats.variants),
+            (1, 0, 2)
+        );
+        let cache: ImageCache = serde_json::from_str(
+            &fs::read_to_string(cache_dir.path().join(IMAGE_CACHE_FILE)).unwrap(),
+        )
+        .unwrap();
+        let hash = cache.images.keys().next().unwrap().clone();
+        let cached = cache_dir
+            .path()
+            .join(IMAGE_CACHE_DIR)
+            .join(&hash)
+            .join("10w.png");
+        assert!(cached.is_file());
+
+        fs::write(&cached, "cached").unwrap();
+        fs::remove_file(output_dir.path().join("photo-10w.png")).unwrap();
+        let second =
+            process_images(output_dir.path(), &config, &[], Some(cache_dir.path())).unwrap();
+        assert_eq!(second.variants["photo.png"].len(), 2);
+        assert_eq!(
+            fs::read_to_string(output_dir.path().join("photo-10w.png")).unwrap(),
+            "cached"
+        );
+        assert_eq!(
+            (
+                second.stats.images,
+                second.stats.cached,
+                second.stats.variants
+            ),
+            (1, 1, 2)
+        );
+        let source_bytes = fs::metadata(output_dir.path().join("photo.png"))
+            .unwrap()
+            .len();
+        assert!(second.stats.bytes_saved >= source_bytes - "cached".len() as u64);
+
+        let config = ImageConfig {
+            quality: 50,
+            ..config
+        };
+        process_images(output_dir.path(), &config, &[], Some(cache_dir.path())).unwrap();
+        assert_ne!(
+            fs::read(output_dir.path().join("photo-10w.png")).unwrap(),
+            b"cached"
+        );
+
+        fs::remove_file(output_dir.path().join("photo.png")).unwrap();
+        fs::remove_file(output_dir.path().join("photo-10w.png")).unwrap();
+        fs::remove_file(output_dir.path().join("photo-20w.png")).unwrap();
+        process_images(output_dir.path(), &config, &[], Some(cache_dir.path())).unwrap();
+        assert!(!cache_dir.path().join(IMAGE_CACHE_DIR).join(&hash).exists());
+    }
+
+    #[test]
+    fn test_image_stats_display() {
+        let stats = ImageStats {
+            images: 3,
+            cached: 2,
+            variants: 12,
+            bytes_saved: 3 * 1024 * 1024 + 512 * 1024,
+        };
+        assert_eq!(
+            stats.to_string(),
+            "Processed 3 image(s) (2 cached) into 12 variant(s), saving 3.5 MiB"
+        );
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+    }
+
+    #[test]
+    fn test_process_images_progress() {
+        let output_dir = tempfile::TempDir::new().unwrap();
+        for name in ["a.png", "b.png"] {
+            image::RgbImage::new(40, 20)
+                .save(output_dir.path().join(name))
+                .unwrap();
+        }
+        let config = ImageConfig {
+            widths: vec![10],
+            formats: vec!["png".to_string()],
+            threads: Some(1),
+            ..ImageConfig::default()
+        };
+        let seen = std::sync::Mutex::new(Vec::new());
+        let record = |progress: &ImageProgress<'_>| {
+            seen.lock()
+                .unwrap()
+                .push((progress.path.to_string(), progress.total));
+        };
+
+        let manifest =
+            process_images_with_progress(output_dir.path(), &config, &[], None, Some(&record))
+                .unwrap();
+
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort();
+        assert_eq!(seen, [("a.png".to_string(), 2), ("b.png".to_string(), 2)]);
+        assert_eq!(manifest.stats.images, 2);
+
+        let progress = ImageProgress {
+            done: 1,
+            total: 3,
+            path: "a.png",
+            elapsed: Duration::from_secs(4),
+        };
+        assert_eq!(progress.remaining(), Some(Duration::from_secs(8)));
+        assert_eq!(
+            ImageProgress {
+                done: 0,
+                ..progress
+            }
+            .remaining(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_process_images_requests() {
+        let output_dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(output_dir.path().join("images")).unwrap();
+        image::RgbImage::new(40, 20)
+            .save(output_dir.path().join("images/photo.png"))
+            .unwrap();
+        let config = ImageConfig {
+            widths: vec![20],
+            formats: vec!["png".to_string()],
+            ..ImageConfig::default()
+        };
+        let requests = [
+            ImageRequest {
+                src: "/images/photo.png".to_string(),
+                width: 12,
+                height: Some(12),
+            },
+            ImageRequest {
+                src: "/images/photo.png".to_string(),
+                width: 80,
+                height: None,
+            },
+        ];
+        assert_eq!(requests[0].variant_path("webp"), "images/photo-12x12.webp");
+
+        for _ in 0..2 {
+            let manifest = process_images(output_dir.path(), &config, &requests, None).unwrap();
+            assert_eq!(manifest.variants.len(), 1);
+            assert_eq!(manifest.variants["images/photo.png"].len(), 1);
+        }
+        let dimensions = |name: &str| {
+            image::image_dimensions(output_dir.path().join("images").join(name)).unwrap()
+        };
+        assert_eq!(dimensions("photo-20w.png"), (20, 10));
+        assert_eq!(dimensions("photo-12x12.png"), (12, 12));
+        assert_eq!(dimensions("photo-80w.png"), (40, 20));
+    }
+
+    #[test]
+    fn test_format_policy() {
+        let config: ImageConfig = toml::from_str(
+            r#"
+            formats = ["webp", "jpg"]
+
+            [format_policy]
+            PNG = ["webp", "png"]
+            jpeg = ["avif", "jpg"]
+            gif = "passthrough"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.formats_for("images/shot.png"), ["webp", "png"]);
+        assert_eq!(config.formats_for("photo.JPG"), ["avif", "jpg"]);
+        assert_eq!(config.formats_for("photo.jpeg"), ["avif", "jpg"]);
+        assert!(config.formats_for("spinner.gif").is_empty());
+        assert_eq!(config.formats_for("photo.webp"), ["webp", "jpg"]);
+
+        let error = toml::from_str::<ImageConfig>("[format_policy]\ngif = \"skip\"\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("passthrough"), "{error}");
+    }
+
+    #[test]
+    fn test_process_images_exclusions() {
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let root = output_dir.path();
+        for directory in ["images/logos", "qr/nested"] {
+            fs::create_dir_all(root.join(directory)).unwrap();
+        }
+        for image in [
+            "images/photo.png",
+            "images/logos/brand.png",
+            "qr/nested/code.png",
+            "images/qr-contact.png",
+        ] {
+            image::RgbImage::new(40, 20).save(root.join(image)).unwrap();
+        }
+        fs::write(root.join("qr").join(NO_IMAGES_MARKER), "").unwrap();
+        let config = ImageConfig {
+            widths: vec![20],
+            formats: vec!["png".to_string()],
+            placeholders: true,
+            exclude: vec!["images/logos/**".to_string(), "/**/qr-*.png".to_string()],
+            ..ImageConfig::default()
+        };
+        let requests = [ImageRequest {
+            src: "/images/logos/brand.png".to_string(),
+            width: 10,
+            height: None,
+        }];
+
+        let manifest = process_images(root, &config, &requests, None).unwrap();
+        assert_eq!(
+            manifest.variants.keys().collect::<Vec<_>>(),
+            ["images/photo.png"]
+        );
+        assert_eq!(
+            manifest.placeholders.keys().collect::<Vec<_>>(),
+            ["images/photo.png"]
+        );
+        assert!(!root.join("images/logos/brand-20w.png").exists());
+        assert!(!root.join("qr/nested/code-20w.png").exists());
+        assert!(!root.join("images/qr-contact-20w.png").exists());
+        assert_eq!(
+            image::image_dimensions(root.join("images/logos/brand-10w.png")).unwrap(),
+            (10, 5)
+        );
+
+        let invalid = ImageConfig {
+            exclude: vec!["images/[".to_string()],
+            ..ImageConfig::default()
+        };
+        let error = process_images(root, &invalid, &[], None)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("images/["), "{error}");
+    }
+
+    #[test]
+    fn test_process_images_format_policy() {
+        let output_dir = tempfile::TempDir::new().unwrap();
+        image::RgbImage::new(40, 20)
+            .save(output_dir.path().join("shot.png"))
+            .unwrap();
+        image::RgbImage::new(40, 20)
+            .save(output_dir.path().join("photo.jpg"))
+            .unwrap();
+        let config = ImageConfig {
+            widths: vec![20],
+            formats: vec!["webp".to_string(), "jpg".to_string()],
+            format_policy: BTreeMap::from([
+                ("png".to_string(), Vec::new()),
+                ("jpg".to_string(), vec!["png".to_string()]),
+            ]),
+            ..ImageConfig::default()
+        };
+        let requests = [ImageRequest {
+            src: "/photo.jpg".to_string(),
+            width: 10,
+            height: None,
+        }];
+
+        for _ in 0..2 {
+            let manifest = process_images(output_dir.path(), &config, &requests, None).unwrap();
+            assert_eq!(manifest.variants.len(), 1);
+            let variants = &manifest.variants["photo.jpg"];
+            assert_eq!(variants.len(), 1);
+            assert_eq!(variants[0].path, "photo-20w.png");
+        }
+        assert!(output_dir.path().join("photo-10w.png").is_file());
+        assert!(!output_dir.path().join("photo-20w.webp").exists());
+        assert!(!output_dir.path().join("shot-20w.webp").exists());
+    }
+
+    #[test]
+    fn test_parse_art_direction() {
+        assert_eq!(
+            parse_art_direction("(max-width: 640px) 640x640; (min-width: 1400px) 1400x600;")
+                .unwrap(),
+            [
+                ArtDirection {
+                    media: "(max-width: 640px)".to_string(),
+                    width: 640,
+                    height: 640,
+                },
+                ArtDirection {
+                    media: "(min-width: 1400px)".to_string(),
+                    width: 1400,
+                    height: 600,
+                },
+            ]
+        );
+        assert!(parse_art_direction("").unwrap().is_empty());
+        assert!(parse_art_direction("640x640").is_err());
+        assert!(parse_art_direction("(max-width: 640px) 640").is_err());
+        assert!(parse_art_direction("(max-width: 640px) 0x640").is_err());
+    }
+
+    #[test]
+    fn test_process_images_art_direction() {
+        let output_dir = tempfile::TempDir::new().unwrap();
+        image::RgbImage::new(40, 20)
+            .save(output_dir.path().join("photo.png"))
+            .unwrap();
+        fs::write(
+            output_dir.path().join("index.html"),
+            r#"<img src="/photo.png" alt="">"#,
+        )
+        .unwrap();
+        let config = ImageConfig {
+            widths: vec![20],
+            formats: vec!["webp".to_string(), "png".to_string()],
+            art_direction: vec![ArtDirection {
+                media: "(max-width: 640px)".to_string(),
+                width: 10,
+                height: 10,
+            }],
+            ..ImageConfig::default()
+        };
+
+        for _ in 0..2 {
+            let manifest = process_images(output_dir.path(), &config, &[], None).unwrap();
+            assert_eq!(manifest.variants.len(), 1);
+            let crops = &manifest.art_direction["photo.png"];
+            assert_eq!(crops.len(), 2);
+            assert_eq!(crops[0].path, "photo-10x10.webp");
+            assert_eq!(crops[1].path, "photo-10x10.png");
+        }
+        assert_eq!(
+            image::image_dimensions(output_dir.path().join("photo-10x10.png")).unwrap(),
+            (10, 10)
+        );
+
+        let manifest = process_images(output_dir.path(), &config, &[], None).unwrap();
+        let sizes = ImageSizes {
+            default: "(max-width: 720px) 100vw, 720px".to_string(),
+            ..ImageSizes::default()
+        };
+        apply_srcset_to_html(output_dir.path(), &manifest, &sizes).unwrap();
+        let html = fs::read_to_string(output_dir.path().join("index.html")).unwrap();
+        assert_eq!(
+            html,
+            concat!(
+                r#"<picture><source media="(max-width: 640px)" type="image/webp" srcset="/photo-10x10.webp">"#,
+                r#"<source media="(max-width: 640px)" type="image/png" srcset="/photo-10x10.png">"#,
+                r#"<source type="image/webp" srcset="/photo-20w.webp 20w" sizes="(max-width: 720px) 100vw, 720px">"#,
+                r#"<source type="image/png" srcset="/photo-20w.png 20w" sizes="(max-width: 720px) 100vw, 720px">"#,
+                r#"<img srcset="/photo-20w.png 20w" sizes="(max-width: 720px) 100vw, 720px" src="/photo.png" alt=""></picture>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_square_png() {
+        let mut source = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::new(40, 20)
+            .write_to(&mut source, image::ImageFormat::Png)
+            .unwrap();
+        let icon = image::load_from_memory(&square_png(source.get_ref(), 16).unwrap()).unwrap();
+        assert_eq!((icon.width(), icon.height()), (16, 16));
+        assert!(square_png(b"not an image", 16).is_err());
+    }
+
     #[test]
     fn test_is_generated_variant() {
         let widths = vec![320, 640, 1024];
@@ -537,8 +1923,12 @@ mod tests {
     fn test_generate_srcset_no_variants() {
         let manifest = ImageManifest {
             variants: HashMap::new(),
+            placeholders: HashMap::new(),
+            colors: HashMap::new(),
+            art_direction: HashMap::new(),
+            stats: ImageStats::default(),
         };
-        let result = generate_srcset("images/photo.jpg", &manifest);
+        let result = generate_srcset("images/photo.jpg", &manifest, "100vw");
         assert_eq!(result, "<img src=\"/images/photo.jpg\">");
     }
 
@@ -560,8 +1950,14 @@ mod tests {
                 },
             ],
         );
-        let manifest = ImageManifest { variants };
-        let result = generate_srcset("images/photo.jpg", &manifest);
+        let manifest = ImageManifest {
+            variants,
+            placeholders: HashMap::new(),
+            colors: HashMap::new(),
+            art_direction: HashMap::new(),
+            stats: ImageStats::default(),
+        };
+        let result = generate_srcset("images/photo.jpg", &manifest, "100vw");
         assert!(result.contains("<picture>"));
         assert!(result.contains("</picture>"));
         assert!(result.contains("<source"));
@@ -580,13 +1976,238 @@ mod tests {
                 format: "webp".to_string(),
             }],
         );
-        let manifest = ImageManifest { variants };
+        let manifest = ImageManifest {
+            variants,
+            placeholders: HashMap::new(),
+            colors: HashMap::new(),
+            art_direction: HashMap::new(),
+            stats: ImageStats::default(),
+        };
         let html = r#"<p><img src="/images/photo.jpg"></p>"#;
-        let result = replace_img_tags_with_srcset(html, &manifest);
+        let result = replace_img_tags_with_srcset(html, &manifest, "100vw");
         assert!(result.contains("<picture>"));
         assert!(result.contains("</picture>"));
     }
 
+    #[test]
+    fn test_replace_img_tags_adds_img_srcset() {
+        let variant = |path: &str, width: u32, format: &str| ImageVariant {
+            path: path.to_string(),
+            width,
+            format: format.to_string(),
+        };
+        let manifest = ImageManifest {
+            variants: HashMap::from([
+                (
+                    "photo.jpg".to_string(),
+                    vec![
+                        variant("photo-320w.jpg", 320, "jpg"),
+                        variant("photo-320w.webp", 320, "webp"),
+                        variant("photo-640w.jpg", 640, "jpg"),
+                        variant("photo-640w.webp", 640, "webp"),
+                    ],
+                ),
+                (
+                    "shot.png".to_string(),
+                    vec![
+                        variant("shot-320w.webp", 320, "webp"),
+                        variant("shot-320w.jpeg", 320, "jpeg"),
+                    ],
+                ),
+            ]),
+            placeholders: HashMap::new(),
+            colors: HashMap::new(),
+            art_direction: HashMap::new(),
+            stats: ImageStats::default(),
+        };
+
+        let result = replace_img_tags_with_srcset(r#"<img src="/photo.jpg">"#, &manifest, "50vw");
+        assert!(result.contains(
+            r#"<img srcset="/photo-320w.jpg 320w, /photo-640w.jpg 640w" sizes="50vw" src="/photo.jpg">"#
+        ));
+        assert!(
+            result
+                .contains(r#"srcset="/photo-320w.webp 320w, /photo-640w.webp 640w" sizes="50vw""#)
+        );
+
+        let result = replace_img_tags_with_srcset(r#"<img src="/shot.png">"#, &manifest, "50vw");
+        assert!(
+            result.contains(r#"<img srcset="/shot-320w.jpeg 320w" sizes="50vw" src="/shot.png">"#)
+        );
+
+        let own = r#"<img src="/photo.jpg" srcset="/custom.jpg 2x" sizes="10vw">"#;
+        assert!(replace_img_tags_with_srcset(own, &manifest, "50vw").contains(own));
+        let own_sizes = r#"<img src="/photo.jpg" sizes="10vw">"#;
+        assert!(
+            replace_img_tags_with_srcset(own_sizes, &manifest, "50vw").contains(
+                r#"<img srcset="/photo-320w.jpg 320w, /photo-640w.jpg 640w" src="/photo.jpg" sizes="10vw">"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_image_sizes_by_section() {
+        let site = crate::template_test::sample_site().unwrap();
+        let config = ImageConfig {
+            sizes: "(max-width: 1200px) 100vw, 1200px".to_string(),
+            section_sizes: HashMap::from([
+                (
+                    "posts".to_string(),
+                    "(max-width: 720px) 100vw, 720px".to_string(),
+                ),
+                (
+                    "pages".to_string(),
+                    "(max-width: 960px) 100vw, 960px".to_string(),
+                ),
+            ]),
+            ..ImageConfig::default()
+        };
+        let sizes = ImageSizes::new(&site, &config);
+        assert_eq!(
+            sizes.for_page(&site.posts[0].content.path),
+            "(max-width: 720px) 100vw, 720px"
+        );
+        let page = site
+            .pages
+            .iter()
+            .find(|page| page.content.source_path.components().count() == 1)
+            .unwrap();
+        assert_eq!(
+            sizes.for_page(&page.content.path),
+            "(max-width: 960px) 100vw, 960px"
+        );
+        assert_eq!(
+            sizes.for_page(Path::new("tags/rust/index.html")),
+            "(max-width: 1200px) 100vw, 1200px"
+        );
+        assert_eq!(ImageConfig::default().sizes, "100vw");
+    }
+
+    #[test]
+    fn test_placeholder() {
+        let photo = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 32, |x, _| {
+            image::Rgb([x as u8 * 4, 128, 255 - x as u8 * 4])
+        }));
+        let uri = placeholder(&photo).unwrap();
+        let jpeg = base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            uri.strip_prefix("data:image/jpeg;base64,").unwrap(),
+        )
+        .unwrap();
+        let preview = image::load_from_memory(&jpeg).unwrap();
+        assert_eq!((preview.width(), preview.height()), (16, 8));
+        assert!(uri.len() < 1024);
+
+        let transparent = DynamicImage::ImageRgba8(image::RgbaImage::new(32, 32));
+        assert_eq!(placeholder(&transparent), None);
+    }
+
+    #[test]
+    fn test_dominant_color() {
+        let photo = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, _| {
+            if x < 40 {
+                image::Rgb([200, 30, 30])
+            } else {
+                image::Rgb([20, 40, 220])
+            }
+        }));
+        assert_eq!(dominant_color(&photo).as_deref(), Some("#c81e1e"));
+
+        let logo = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(64, 64, |x, _| {
+            if x < 16 {
+                image::Rgba([0, 128, 0, 255])
+            } else {
+                image::Rgba([255, 255, 255, 0])
+            }
+        }));
+        assert_eq!(dominant_color(&logo).as_deref(), Some("#008000"));
+
+        let transparent = DynamicImage::ImageRgba8(image::RgbaImage::new(8, 8));
+        assert_eq!(dominant_color(&transparent), None);
+    }
+
+    #[test]
+    fn test_process_images_dominant_colors() {
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        image::RgbImage::from_pixel(40, 20, image::Rgb([16, 32, 48]))
+            .save(output_dir.path().join("photo.png"))
+            .unwrap();
+        fs::write(
+            output_dir.path().join("index.html"),
+            r#"<img src="/photo.png">"#,
+        )
+        .unwrap();
+        let config = ImageConfig {
+            widths: vec![],
+            formats: vec!["png".to_string()],
+            dominant_colors: true,
+            ..ImageConfig::default()
+        };
+
+        for _ in 0..2 {
+            let manifest =
+                process_images(output_dir.path(), &config, &[], Some(cache_dir.path())).unwrap();
+            assert_eq!(manifest.colors["photo.png"], "#102030");
+        }
+        let manifest = process_images(output_dir.path(), &config, &[], None).unwrap();
+        apply_srcset_to_html(output_dir.path(), &manifest, &ImageSizes::default()).unwrap();
+        assert_eq!(
+            fs::read_to_string(output_dir.path().join("index.html")).unwrap(),
+            r#"<img style="background-color:#102030" src="/photo.png">"#
+        );
+    }
+
+    #[test]
+    fn test_replace_img_tags_with_placeholder() {
+        let manifest = ImageManifest {
+            variants: HashMap::new(),
+            placeholders: HashMap::from([(
+                "images/photo.jpg".to_string(),
+                "data:image/jpeg;base64,AAAA".to_string(),
+            )]),
+            colors: HashMap::new(),
+            art_direction: HashMap::new(),
+            stats: ImageStats::default(),
+        };
+        let background = "background-image:url(data:image/jpeg;base64,AAAA);background-size:cover;background-repeat:no-repeat";
+        assert_eq!(
+            replace_img_tags_with_srcset(
+                r#"<img src="/images/photo.jpg" alt="">"#,
+                &manifest,
+                "100vw"
+            ),
+            format!(r#"<img style="{background}" src="/images/photo.jpg" alt="">"#)
+        );
+        assert_eq!(
+            replace_img_tags_with_srcset(
+                r#"<img src="/images/photo.jpg" style="width:100%">"#,
+                &manifest,
+                "100vw"
+            ),
+            format!(r#"<img src="/images/photo.jpg" style="{background};width:100%">"#)
+        );
+        assert_eq!(
+            replace_img_tags_with_srcset(r#"<img src="/other.jpg">"#, &manifest, "100vw"),
+            r#"<img src="/other.jpg">"#
+        );
+
+        let manifest = ImageManifest {
+            colors: HashMap::from([("images/photo.jpg".to_string(), "#336699".to_string())]),
+            ..manifest
+        };
+        assert_eq!(
+            replace_img_tags_with_srcset(
+                r#"<img src="/images/photo.jpg" style='width:100%'>"#,
+                &manifest,
+                "100vw"
+            ),
+            format!(
+                r#"<img src="/images/photo.jpg" style='background-color:#336699;{background};width:100%'>"#
+            )
+        );
+    }
+
     #[test]
     fn test_extract_src_attribute_double_quotes() {
         assert_eq!(
@@ -0,0 +1,370 @@
+//! Converts RSS 2.0 and Atom feeds into dated post files, so content from
+//! hosted platforms (Substack, Medium, WordPress exports) can be migrated
+//! into `content/posts/`.
+
+use crate::error::{IoContext, Result};
+use crate::parsing::slugify;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One entry parsed out of an RSS `<item>` or Atom `<entry>`.
+#[derive(Debug, Clone)]
+pub struct ImportedEntry {
+    /// Entry title (falls back to `"Untitled"`).
+    pub title: String,
+    /// Publication date, if the feed provided a parseable one.
+    pub date: Option<DateTime<Utc>>,
+    /// Category / tag names attached to the entry.
+    pub tags: Vec<String>,
+    /// Original permalink, written as `canonical_url` frontmatter.
+    pub link: Option<String>,
+    /// Entry body (usually HTML, kept as-is since markdown passes HTML
+    /// through).
+    pub body: String,
+}
+
+/// What [`import_feed`] did with each entry.
+#[derive(Debug, Clone, Default)]
+pub struct FeedImport {
+    /// Post files created.
+    pub written: Vec<PathBuf>,
+    /// Post files that already existed, so their entries weren't imported.
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Parses every `<item>` (RSS) or `<entry>` (Atom) in `xml`. Unknown
+/// elements are ignored; malformed entries are skipped rather than
+/// failing the whole import.
+pub fn parse_feed(xml: &str) -> Vec<ImportedEntry> {
+    let (open, close) = if xml.contains("<entry") && !xml.contains("<item") {
+        ("<entry", "</entry>")
+    } else {
+        ("<item", "</item>")
+    };
+
+    let mut entries = Vec::new();
+    let mut remaining = xml;
+    while let Some(start) = find_element_start(remaining, open) {
+        let after_start = &remaining[start..];
+        let Some(end) = after_start.find(close) else {
+            break;
+        };
+        let block = &after_start[..end];
+        entries.push(parse_entry(block));
+        remaining = &after_start[end + close.len()..];
+    }
+    entries
+}
+
+fn parse_entry(block: &str) -> ImportedEntry {
+    let title = element_text(block, "title")
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| "Untitled".to_string());
+
+    let date = ["pubDate", "published", "dc:date", "updated"]
+        .iter()
+        .filter_map(|name| element_text(block, name))
+        .find_map(|value| parse_feed_date(value.trim()));
+
+    let mut tags = Vec::new();
+    let mut remaining = block;
+    while let Some(start) = find_element_start(remaining, "<category") {
+        let tag = &remaining[start..];
+        let Some(tag_end) = tag.find('>') else {
+            break;
+        };
+        let opening = &tag[..tag_end];
+        let term = if let Some(term) = attribute_value(opening, "term") {
+            Some(term)
+        } else if !opening.ends_with('/') {
+            tag[tag_end + 1..]
+                .find("</category>")
+                .map(|close| decode_text(&tag[tag_end + 1..tag_end + 1 + close]))
+        } else {
+            None
+        };
+        if let Some(term) = term.map(|term| term.trim().to_string())
+            && !term.is_empty()
+            && !tags.contains(&term)
+        {
+            tags.push(term);
+        }
+        remaining = &tag[tag_end + 1..];
+    }
+
+    let link = atom_alternate_link(block)
+        .or_else(|| element_text(block, "link"))
+        .map(|link| link.trim().to_string())
+        .filter(|link| !link.is_empty());
+
+    let body = ["content:encoded", "content", "description", "summary"]
+        .iter()
+        .filter_map(|name| element_text(block, name))
+        .find(|body| !body.trim().is_empty())
+        .unwrap_or_default();
+
+    ImportedEntry {
+        title,
+        date,
+        tags,
+        link,
+        body: body.trim().to_string(),
+    }
+}
+
+/// Renders an entry as a post file: TOML frontmatter followed by the body.
+pub fn entry_to_post(entry: &ImportedEntry) -> String {
+    let mut output = String::from("+++\n");
+    output.push_str(&format!("title = {}\n", toml_string(&entry.title)));
+    if let Some(date) = entry.date {
+        output.push_str(&format!("date = \"{}\"\n", date.format("%Y-%m-%d")));
+    }
+    if !entry.tags.is_empty() {
+        let tags: Vec<String> = entry.tags.iter().map(|tag| toml_string(tag)).collect();
+        output.push_str(&format!("tags = [{}]\n", tags.join(", ")));
+    }
+    if let Some(ref link) = entry.link {
+        output.push_str(&format!("canonical_url = {}\n", toml_string(link)));
+    }
+    output.push_str("+++\n\n");
+    output.push_str(&entry.body);
+    output.push('\n');
+    output
+}
+
+/// Writes every entry in `xml` to `posts_dir` as `YYYY-MM-DD-<slug>.md`.
+/// Entries sharing a date and slug get `-2`, `-3`, ... suffixes in feed
+/// order. Existing files are left untouched and listed in
+/// [`FeedImport::skipped`], so importing the same feed again skips every
+/// entry.
+pub fn import_feed(xml: &str, posts_dir: &Path) -> Result<FeedImport> {
+    fs::create_dir_all(posts_dir).io_context("create_dir_all", posts_dir)?;
+
+    let mut import = FeedImport::default();
+    let mut claimed = HashSet::new();
+    for entry in parse_feed(xml) {
+        let date = entry.date.unwrap_or_else(Utc::now);
+        let slug = slugify(&entry.title);
+        let slug = if slug.is_empty() {
+            "untitled".to_string()
+        } else {
+            slug
+        };
+        let stem = format!("{}-{}", date.format("%Y-%m-%d"), slug);
+        let name = (1..)
+            .map(|number| match number {
+                1 => format!("{stem}.md"),
+                _ => format!("{stem}-{number}.md"),
+            })
+            .find(|name| claimed.insert(name.clone()))
+            .unwrap_or_default();
+        let path = posts_dir.join(name);
+        if path.exists() {
+            import.skipped.push(path);
+            continue;
+        }
+        let entry = ImportedEntry {
+            date: Some(date),
+            ..entry
+        };
+        fs::write(&path, entry_to_post(&entry)).io_context("write", &path)?;
+        import.written.push(path);
+    }
+    Ok(import)
+}
+
+fn toml_string(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+fn parse_feed_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value)
+        .or_else(|_| DateTime::parse_from_rfc3339(value))
+        .map(|date| date.with_timezone(&Utc))
+        .ok()
+}
+
+fn find_element_start(haystack: &str, open: &str) -> Option<usize> {
+    let mut search_from = 0;
+    while let Some(position) = haystack[search_from..].find(open) {
+        let absolute = search_from + position;
+        let next = haystack[absolute + open.len()..].chars().next();
+        if matches!(next, Some('>' | ' ' | '/' | '\n' | '\r' | '\t')) {
+            return Some(absolute);
+        }
+        search_from = absolute + open.len();
+    }
+    None
+}
+
+fn element_text(block: &str, name: &str) -> Option<String> {
+    let open = format!("<{name}");
+    let start = find_element_start(block, &open)?;
+    let tag = &block[start..];
+    let tag_end = tag.find('>')?;
+    if tag[..tag_end].ends_with('/') {
+        return None;
+    }
+    let inner = &tag[tag_end + 1..];
+    let close = inner.find(&format!("</{name}>"))?;
+    Some(decode_text(&inner[..close]))
+}
+
+fn atom_alternate_link(block: &str) -> Option<String> {
+    let mut remaining = block;
+    while let Some(start) = find_element_start(remaining, "<link") {
+        let tag = &remaining[start..];
+        let tag_end = tag.find('>')?;
+        let opening = &tag[..tag_end];
+        if let Some(href) = attribute_value(opening, "href") {
+            let rel = attribute_value(opening, "rel");
+            if rel.is_none() || rel.as_deref() == Some("alternate") {
+                return Some(href);
+            }
+        }
+        remaining = &tag[tag_end + 1..];
+    }
+    None
+}
+
+fn attribute_value(tag: &str, name: &str) -> Option<String> {
+    for quote in ['"', '\''] {
+        let pattern = format!(" {name}={quote}");
+        if let Some(position) = tag.find(&pattern) {
+            let rest = &tag[position + pattern.len()..];
+            let end = rest.find(quote)?;
+            return Some(crate::xml::unescape(&rest[..end]));
+        }
+    }
+    None
+}
+
+fn decode_text(raw: &str) -> String {
+    let trimmed = raw.trim();
+    if let Some(cdata) = trimmed
+        .strip_prefix("<![CDATA[")
+        .and_then(|rest| rest.strip_suffix("]]>"))
+    {
+        cdata.to_string()
+    } else {
+        crate::xml::unescape(trimmed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Blog</title>
+<item>
+  <title>Hello &amp; Welcome</title>
+  <link>https://old.example.com/hello</link>
+  <pubDate>Mon, 15 Jan 2024 10:00:00 +0000</pubDate>
+  <category>rust</category>
+  <category><![CDATA[web]]></category>
+  <content:encoded><![CDATA[<p>Body text</p>]]></content:encoded>
+</item>
+</channel></rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <entry>
+    <title>Atom Post</title>
+    <link href="https://old.example.com/atom-post" rel="alternate"/>
+    <published>2024-02-01T00:00:00Z</published>
+    <category term="notes"/>
+    <content type="html">&lt;p&gt;Atom body&lt;/p&gt;</content>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn test_parse_rss_item() {
+        let entries = parse_feed(RSS);
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.title, "Hello & Welcome");
+        assert_eq!(entry.link.as_deref(), Some("https://old.example.com/hello"));
+        assert_eq!(entry.tags, vec!["rust".to_string(), "web".to_string()]);
+        assert_eq!(entry.body, "<p>Body text</p>");
+        assert_eq!(
+            entry.date.unwrap().format("%Y-%m-%d").to_string(),
+            "2024-01-15"
+        );
+    }
+
+    #[test]
+    fn test_parse_atom_entry() {
+        let entries = parse_feed(ATOM);
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.title, "Atom Post");
+        assert_eq!(
+            entry.link.as_deref(),
+            Some("https://old.example.com/atom-post")
+        );
+        assert_eq!(entry.tags, vec!["notes".to_string()]);
+        assert_eq!(entry.body, "<p>Atom body</p>");
+    }
+
+    #[test]
+    fn test_import_feed_writes_dated_posts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let written = import_feed(RSS, dir.path()).unwrap().written;
+        assert_eq!(written.len(), 1);
+        assert!(written[0].ends_with("2024-01-15-hello-welcome.md"));
+
+        let contents = fs::read_to_string(&written[0]).unwrap();
+        assert!(contents.contains("title = \"Hello & Welcome\""));
+        assert!(contents.contains("tags = [\"rust\", \"web\"]"));
+        assert!(contents.contains("canonical_url = \"https://old.example.com/hello\""));
+
+        let again = import_feed(RSS, dir.path()).unwrap();
+        assert!(again.written.is_empty());
+        assert_eq!(again.skipped, written);
+    }
+
+    #[test]
+    fn test_import_feed_suffixes_colliding_entries() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let item = RSS
+            .split_once("<item>")
+            .and_then(|(_, rest)| rest.split_once("</item>"))
+            .unwrap()
+            .0;
+        let feed = format!("<rss><channel><item>{item}</item><item>{item}</item></channel></rss>");
+
+        let import = import_feed(&feed, dir.path()).unwrap();
+        assert!(import.written[0].ends_with("2024-01-15-hello-welcome.md"));
+        assert!(import.written[1].ends_with("2024-01-15-hello-welcome-2.md"));
+
+        let again = import_feed(&feed, dir.path()).unwrap();
+        assert!(again.written.is_empty());
+        assert_eq!(again.skipped, import.written);
+    }
+
+    #[test]
+    fn test_imported_post_links_its_canonical_url() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("bamboo.toml"),
+            "title = \"T\"\nbase_url = \"https://example.com\"\n",
+        )
+        .unwrap();
+        import_feed(RSS, &dir.path().join("content/posts")).unwrap();
+        let site = crate::SiteBuilder::new(dir.path()).build().unwrap();
+        let output = dir.path().join("public");
+
+        crate::ThemeEngine::new("default")
+            .unwrap()
+            .render_site(&site, &output)
+            .unwrap();
+
+        let html = fs::read_to_string(output.join("posts/hello-welcome/index.html")).unwrap();
+        assert!(html.contains(r#"<link rel="canonical" href="https://old.example.com/hello">"#));
+        assert!(!html.contains(r#"<link rel="canonical" href="https://example.com/posts/"#));
+    }
+}
@@ -0,0 +1,32 @@
+//! Build concurrency. Content loading, rendering, hashing, and asset and
+//! image processing all run on rayon; [`with_jobs`] runs a build on a pool
+//! of a chosen size so it can be throttled on shared CI machines or
+//! laptops instead of taking every core.
+
+use crate::error::Result;
+
+/// Runs `work` on a thread pool with `jobs` worker threads, or one per core
+/// when `jobs` is `None` or `0`. Every parallel step `work` starts uses
+/// that pool.
+pub fn with_jobs<T: Send>(jobs: Option<usize>, work: impl FnOnce() -> T + Send) -> Result<T> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .thread_name(|index| format!("bamboo-worker-{index}"))
+        .build()
+        .map_err(|error| std::io::Error::other(error.to_string()))?;
+    Ok(pool.install(work))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_jobs_limits_threads() {
+        assert_eq!(with_jobs(Some(2), rayon::current_num_threads).unwrap(), 2);
+        assert_eq!(
+            with_jobs(None, rayon::current_num_threads).unwrap(),
+            std::thread::available_parallelism().map_or(1, |count| count.get())
+        );
+    }
+}
@@ -15,6 +15,8 @@
 //!   `static/`, `templates/`) and produces an in-memory [`Site`] tree.
 //! - [`ThemeEngine`] renders a [`Site`] to an output directory using Tera
 //!   templates from the built-in default theme or a custom theme.
+//! - [`SiteGraph`] indexes a built [`Site`] for lookups by section, URL,
+//!   source path, or taxonomy term.
 //!
 //! # Example
 //!
@@ -33,36 +35,98 @@
 
 #![warn(missing_docs)]
 
+pub mod archetypes;
+pub mod archive;
 pub mod assets;
+pub mod build_hooks;
+pub mod build_manifest;
+pub mod bundler;
 pub mod cache;
+pub mod changelog;
+pub mod deploy;
+pub(crate) mod diagnostics;
+pub mod discussions;
 pub mod error;
+pub mod export;
+pub mod extensions;
 pub mod feeds;
+pub mod fonts;
+pub mod generated_pages;
+pub mod gh_pages;
+pub mod graph;
+pub mod head;
+pub mod headers;
+pub mod htaccess;
+pub mod image_metadata;
 pub mod images;
+pub mod import;
+pub mod jobs;
+pub mod link_previews;
 pub mod links;
+pub mod lint;
+pub mod llms;
+pub mod migrate;
+pub mod netlify;
+pub mod newsletter;
+pub mod opml;
+pub mod outputs;
+pub(crate) mod pagination;
 pub mod parsing;
+pub mod precompress;
 pub mod redirects;
+pub mod remote;
+pub mod remote_images;
+pub mod resources;
+pub mod review;
+pub mod rsync;
+pub mod s3;
 pub mod search;
+pub mod search_language;
+pub mod seo;
 pub mod shortcodes;
+pub mod sink;
 pub mod site;
 pub mod sitemap;
+pub mod social_images;
+pub mod source_maps;
+pub mod stats;
+#[cfg(feature = "tantivy")]
+pub mod tantivy_index;
 pub(crate) mod taxonomy;
+pub mod template_test;
+#[cfg(test)]
+pub(crate) mod test_site;
 pub mod theme;
 pub mod types;
+pub mod vercel;
+pub mod vercel_deploy;
+pub mod verify;
+pub mod webmanifest;
+pub mod wellknown;
 pub mod xml;
 
 pub use cache::{
-    BuildState, ChangeClassification, RenderTarget, classify_changes, compute_content_hashes,
-    expand_targets, load_cache, save_cache, should_render,
+    BuildState, ChangeClassification, ExcludedPaths, RenderTarget, TemplateDependencies, cache_dir,
+    changed_paths, classify_changes, compute_content_hashes, expand_targets, load_cache,
+    save_cache, should_render,
 };
+pub use discussions::Discussion;
 pub use error::{BambooError, IoContext, Result};
-pub use links::{LinkWarning, validate_internal_links};
+pub use extensions::TemplateExtensions;
+pub use graph::{Node, SiteGraph, Term};
+pub use jobs::with_jobs;
+pub use links::{
+    BrokenReference, LinkWarning, ReferenceProblem, check_links, validate_internal_links,
+};
 pub use parsing::{
-    MarkdownRenderer, RenderedMarkdown, extract_excerpt, extract_frontmatter,
-    parse_date_from_filename, reading_time, slugify, word_count,
+    MarkdownRenderer, RenderedMarkdown, extract_excerpt, extract_frontmatter, page_stats,
+    parse_date_from_filename, parse_datetime, reading_time, slugify, word_count,
 };
+pub use sink::{MemorySink, RenderSink, TarSink};
 pub use site::SiteBuilder;
-pub use theme::{ThemeEngine, clean_output_dir};
+pub use theme::{RenderReport, ThemeEngine, clean_output_dir};
 pub use types::{
-    Asset, Collection, CollectionItem, Content, Frontmatter, Page, Post, Site, SiteConfig,
-    TaxonomyDefinition, TocEntry,
+    Asset, Collection, CollectionConfig, CollectionFeedOrder, CollectionItem, Content, Frontmatter,
+    GenerateConfig, Note, Page, PageStats, Post, Site, SiteConfig, TaxonomyDefinition, TocEntry,
+    Translation,
 };
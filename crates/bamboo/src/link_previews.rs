@@ -0,0 +1,314 @@
+//! Link previews for bookmark-style posts. A post with `bookmark_of`
+//! frontmatter points at another page; with `[link_previews] fetch = true`,
+//! `bamboo build` fetches that page's title, description, image, and site
+//! name from its Open Graph tags (falling back to `<title>` and
+//! `<meta name="description">`) and exposes them as `post.link_preview`,
+//! so themes can render a link card without fetching anything client-side.
+//!
+//! Previews are cached in `.bamboo-cache/link-previews.json`. Cached URLs
+//! are never fetched again, and offline builds use whatever the cache has.
+
+use crate::error::{IoContext, Result};
+use crate::remote::RemoteClient;
+use crate::resources::{attribute_value, tags};
+use crate::types::Site;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const CACHE_FILE_NAME: &str = "link-previews.json";
+
+/// Frontmatter field naming the page a post links to.
+pub const BOOKMARK_FIELD: &str = "bookmark_of";
+
+/// `[link_previews]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LinkPreviewConfig {
+    /// Fetch previews for `bookmark_of` URLs during `bamboo build`.
+    /// Defaults to `false`.
+    pub fetch: bool,
+}
+
+/// Metadata of a bookmarked page, exposed to templates as
+/// `post.link_preview`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkPreview {
+    /// The bookmarked URL.
+    pub url: String,
+    /// `og:title`, `twitter:title`, or `<title>`.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// `og:description`, `twitter:description`, or
+    /// `<meta name="description">`.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// `og:image` or `twitter:image`, resolved against `url`.
+    #[serde(default)]
+    pub image: Option<String>,
+    /// `og:site_name`, falling back to the URL's host.
+    #[serde(default)]
+    pub site_name: Option<String>,
+}
+
+/// Previews keyed by bookmarked URL.
+pub type LinkPreviewCache = HashMap<String, LinkPreview>;
+
+/// Loads the preview cache from `cache_dir`, returning an empty cache if
+/// the file is missing or malformed.
+pub fn load_link_preview_cache(cache_dir: &Path) -> LinkPreviewCache {
+    fs::read_to_string(cache_dir.join(CACHE_FILE_NAME))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Writes the preview cache to `cache_dir/link-previews.json`.
+pub fn save_link_preview_cache(cache_dir: &Path, cache: &LinkPreviewCache) -> Result<()> {
+    fs::create_dir_all(cache_dir).io_context("create_dir_all", cache_dir)?;
+    let path = cache_dir.join(CACHE_FILE_NAME);
+    let content = serde_json::to_string_pretty(cache)
+        .map_err(|error| std::io::Error::other(error.to_string()))?;
+    fs::write(&path, content).io_context("write", &path)?;
+    Ok(())
+}
+
+/// Fills in `link_preview` for every post with `bookmark_of` frontmatter.
+/// URLs missing from the cache in `cache_dir` are fetched through `client`;
+/// offline or on a failed fetch, the post is left without a preview.
+/// Returns the number of posts that ended up with one.
+pub fn fetch_link_previews(
+    client: &RemoteClient,
+    site: &mut Site,
+    cache_dir: &Path,
+) -> Result<usize> {
+    let mut cache = load_link_preview_cache(cache_dir);
+    let mut cache_changed = false;
+    let mut previewed = 0;
+
+    for post in &mut site.posts {
+        let Some(url) = post.content.frontmatter.get_string(BOOKMARK_FIELD) else {
+            continue;
+        };
+        if !cache.contains_key(&url)
+            && !client.is_offline()
+            && let Ok(html) = client.fetch_text(&url)
+        {
+            cache.insert(url.clone(), parse_link_preview(&url, &html));
+            cache_changed = true;
+        }
+        post.link_preview = cache.get(&url).cloned();
+        if post.link_preview.is_some() {
+            previewed += 1;
+        }
+    }
+
+    if cache_changed {
+        save_link_preview_cache(cache_dir, &cache)?;
+    }
+    Ok(previewed)
+}
+
+/// Reads a [`LinkPreview`] for `url` out of the page's `html`.
+pub fn parse_link_preview(url: &str, html: &str) -> LinkPreview {
+    let lowercase = html.to_ascii_lowercase();
+    let mut meta: HashMap<String, String> = HashMap::new();
+    for tag in tags(html, &lowercase, "<meta") {
+        let Some(key) = attribute_value(tag, "property").or_else(|| attribute_value(tag, "name"))
+        else {
+            continue;
+        };
+        if let Some(content) = attribute_value(tag, "content") {
+            let content = crate::xml::unescape(content.trim());
+            if !content.is_empty() {
+                meta.entry(key.to_ascii_lowercase()).or_insert(content);
+            }
+        }
+    }
+    let first = |keys: &[&str]| keys.iter().find_map(|key| meta.get(*key).cloned());
+
+    LinkPreview {
+        url: url.to_string(),
+        title: first(&["og:title", "twitter:title"]).or_else(|| title_element(html, &lowercase)),
+        description: first(&["og:description", "twitter:description", "description"]),
+        image: first(&["og:image", "og:image:url", "twitter:image"])
+            .map(|image| resolve_url(url, &image)),
+        site_name: first(&["og:site_name"]).or_else(|| host(url).map(str::to_string)),
+    }
+}
+
+fn title_element(html: &str, lowercase: &str) -> Option<String> {
+    let open = tags(html, lowercase, "<title").into_iter().next()?;
+    let start = html.find(open)? + open.len() + 1;
+    let end = start + lowercase.get(start..)?.find("</title")?;
+    let title = crate::xml::unescape(html[start..end].trim());
+    (!title.is_empty()).then_some(title)
+}
+
+fn host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://")?.1;
+    let host = rest.split(['/', '?', '#']).next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+/// Resolves `reference` against `base`: absolute URLs are kept,
+/// protocol-relative and root-relative ones take `base`'s scheme and host,
+/// and anything else is joined to `base`'s directory.
+fn resolve_url(base: &str, reference: &str) -> String {
+    if reference.contains("://") {
+        return reference.to_string();
+    }
+    let scheme = base.split_once("://").map_or("https", |(scheme, _)| scheme);
+    if let Some(rest) = reference.strip_prefix("//") {
+        return format!("{scheme}://{rest}");
+    }
+    let Some(host) = host(base) else {
+        return reference.to_string();
+    };
+    if reference.starts_with('/') {
+        return format!("{scheme}://{host}{reference}");
+    }
+    let path = base
+        .split_once("://")
+        .map(|(_, rest)| &rest[host.len()..])
+        .unwrap_or("")
+        .split(['?', '#'])
+        .next()
+        .unwrap_or("");
+    let directory = &path[..path.rfind('/').map_or(0, |slash| slash + 1)];
+    let directory = if directory.is_empty() { "/" } else { directory };
+    format!("{scheme}://{host}{directory}{reference}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Content, Frontmatter, PageStats, Post};
+    use chrono::Utc;
+    use std::path::PathBuf;
+
+    const PAGE: &str = r#"<!doctype html><html><head>
+<title>Fallback &amp; Title</title>
+<meta property="og:title" content="Rust 2024 &amp; beyond">
+<meta name="description" content="Plain description">
+<meta property="og:image" content="/images/card.png" />
+<meta property="og:site_name" content="Rust Blog">
+</head><body></body></html>"#;
+
+    #[test]
+    fn test_parse_link_preview_open_graph() {
+        assert_eq!(
+            parse_link_preview("https://blog.rust-lang.org/2024/post.html", PAGE),
+            LinkPreview {
+                url: "https://blog.rust-lang.org/2024/post.html".to_string(),
+                title: Some("Rust 2024 & beyond".to_string()),
+                description: Some("Plain description".to_string()),
+                image: Some("https://blog.rust-lang.org/images/card.png".to_string()),
+                site_name: Some("Rust Blog".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_link_preview_fallbacks() {
+        let preview = parse_link_preview(
+            "https://example.com/a/b?x=1",
+            "<head><TITLE> Plain page </TITLE><meta name=twitter:image content=card.png></head>",
+        );
+        assert_eq!(preview.title.as_deref(), Some("Plain page"));
+        assert_eq!(preview.description, None);
+        assert_eq!(
+            preview.image.as_deref(),
+            Some("https://example.com/a/card.png")
+        );
+        assert_eq!(preview.site_name.as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn test_resolve_url() {
+        assert_eq!(
+            resolve_url("https://a.com/x/", "https://b.com/i.png"),
+            "https://b.com/i.png"
+        );
+        assert_eq!(
+            resolve_url("http://a.com/x/", "//cdn.com/i.png"),
+            "http://cdn.com/i.png"
+        );
+        assert_eq!(resolve_url("https://a.com", "i.png"), "https://a.com/i.png");
+    }
+
+    #[test]
+    fn test_fetch_link_previews_offline_uses_cache() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let cached_url = "https://example.com/cached";
+        let mut cache = LinkPreviewCache::new();
+        cache.insert(
+            cached_url.to_string(),
+            LinkPreview {
+                url: cached_url.to_string(),
+                title: Some("Cached".to_string()),
+                ..LinkPreview::default()
+            },
+        );
+        save_link_preview_cache(cache_dir.path(), &cache).unwrap();
+
+        let bookmark = |slug: &str, url: Option<&str>| {
+            let mut frontmatter = Frontmatter::default();
+            if let Some(url) = url {
+                frontmatter
+                    .raw
+                    .insert(BOOKMARK_FIELD.to_string(), serde_json::json!(url));
+            }
+            Post {
+                content: Content {
+                    slug: slug.to_string(),
+                    title: slug.to_string(),
+                    html: String::new(),
+                    raw_content: String::new(),
+                    frontmatter,
+                    path: PathBuf::from(format!("posts/{slug}/index.html")),
+                    source_path: PathBuf::new(),
+                    modified: None,
+                    template: None,
+                    weight: 0,
+                    word_count: 0,
+                    reading_time: 0,
+                    stats: PageStats::default(),
+                    lang: None,
+                    translations: Vec::new(),
+                    toc: Vec::new(),
+                    url: format!("/posts/{slug}/"),
+                },
+                date: Utc::now(),
+                excerpt: None,
+                draft: false,
+                tags: Vec::new(),
+                categories: Vec::new(),
+                taxonomies_map: HashMap::new(),
+                redirect_from: Vec::new(),
+                discussion: None,
+                link_preview: None,
+            }
+        };
+        let mut site = crate::template_test::sample_site().unwrap();
+        site.posts = vec![
+            bookmark("cached", Some(cached_url)),
+            bookmark("uncached", Some("https://example.com/new")),
+            bookmark("plain", None),
+        ];
+
+        let client = RemoteClient::default().offline(true);
+        let previewed = fetch_link_previews(&client, &mut site, cache_dir.path()).unwrap();
+        assert_eq!(previewed, 1);
+        assert_eq!(
+            site.posts[0]
+                .link_preview
+                .as_ref()
+                .and_then(|preview| preview.title.as_deref()),
+            Some("Cached")
+        );
+        assert!(site.posts[1].link_preview.is_none());
+        assert!(site.posts[2].link_preview.is_none());
+    }
+}
@@ -1,7 +1,14 @@
 //! Post-build internal link validation: walks the generated HTML and reports
 //! references that resolve nowhere in the output tree.
-
-use std::collections::HashSet;
+//!
+//! [`validate_internal_links`] is the quick check every build runs on root-
+//! relative `href`s. [`check_links`], behind `bamboo check` and
+//! `bamboo build --check-links`, also follows relative links, `src`,
+//! `srcset`, and `poster` attributes, and stylesheet `url()`s, and checks
+//! that every `#fragment` names an element on the target page.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
@@ -101,6 +108,331 @@ pub fn validate_internal_links(
     warnings
 }
 
+/// Why [`check_links`] reported a [`BrokenReference`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceProblem {
+    /// No file in the output matches the path.
+    MissingFile,
+    /// The page exists, but no element on it has this `id` (or `<a name>`).
+    MissingAnchor(String),
+}
+
+/// A reference in the output that [`check_links`] couldn't resolve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenReference {
+    /// HTML or CSS file containing the reference, relative to the output
+    /// directory.
+    pub source: PathBuf,
+    /// Where it appeared: `href`, `src`, `srcset`, `poster`, or `url()`.
+    pub attribute: &'static str,
+    /// The reference as written.
+    pub target: String,
+    /// What's missing.
+    pub problem: ReferenceProblem,
+}
+
+impl fmt::Display for BrokenReference {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = self.source.to_string_lossy().replace('\\', "/");
+        write!(formatter, "{source}: {} '{}' ", self.attribute, self.target)?;
+        match &self.problem {
+            ReferenceProblem::MissingFile => write!(formatter, "points to a missing file"),
+            ReferenceProblem::MissingAnchor(anchor) => {
+                write!(formatter, "points to a missing anchor '#{anchor}'")
+            }
+        }
+    }
+}
+
+/// Checks every internal reference in the HTML and CSS under
+/// `output_dir`: links, images, scripts, stylesheets, media, and CSS
+/// `url()`s, whether root-relative, relative to the page, or under
+/// `base_url`. A `#fragment` must match an `id` (or `<a name>`) on the
+/// target page; `#` and `#top` always do. References inside `<script>`,
+/// `<style>`, and comments are skipped, as are paths under
+/// `ignore_prefixes`. Results are sorted by source, then target.
+pub fn check_links(
+    output_dir: &Path,
+    base_url: &str,
+    ignore_prefixes: &[String],
+) -> Vec<BrokenReference> {
+    let base_url = base_url.trim_end_matches('/');
+    let mut anchors: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+    let mut broken = Vec::new();
+
+    for entry in WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+    {
+        let path = entry.path();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("");
+        if !entry.file_type().is_file() || !matches!(extension, "html" | "css") {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(path) else {
+            continue;
+        };
+        let relative = path.strip_prefix(output_dir).unwrap_or(path).to_path_buf();
+        let directory = relative.parent().unwrap_or(Path::new(""));
+
+        // (attribute, as written, output-relative path, fragment)
+        let mut references = Vec::new();
+        if extension == "html" {
+            let page = scan_html(&content);
+            for (attribute, reference) in page.references {
+                if let Some((target, fragment)) = resolve_reference(&reference, base_url, directory)
+                {
+                    references.push((attribute, reference, target, fragment));
+                }
+            }
+            anchors.insert(path.to_path_buf(), page.ids);
+        } else {
+            crate::assets::rewrite_css_urls(&content, directory, base_url, |written, resolved| {
+                if !written.is_empty() {
+                    let written = written.to_string();
+                    references.push(("url()", written, Some(percent_decode(resolved)), None));
+                }
+                None
+            });
+        }
+
+        let mut seen = HashSet::new();
+        for (attribute, reference, target, fragment) in references {
+            if !seen.insert(reference.clone()) {
+                continue;
+            }
+            let target_file = match target {
+                Some(ref target) => {
+                    if is_ignored(&format!("/{target}"), ignore_prefixes) {
+                        continue;
+                    }
+                    resolve_file(output_dir, target)
+                }
+                None => Some(path.to_path_buf()),
+            };
+            let problem = match (target_file, fragment) {
+                (None, _) => Some(ReferenceProblem::MissingFile),
+                (Some(target_file), Some(fragment))
+                    if target_file
+                        .extension()
+                        .is_some_and(|extension| extension == "html") =>
+                {
+                    let ids = anchors
+                        .entry(target_file)
+                        .or_insert_with_key(|target_file| {
+                            fs::read_to_string(target_file)
+                                .map(|html| scan_html(&html).ids)
+                                .unwrap_or_default()
+                        });
+                    (!ids.contains(&fragment)).then_some(ReferenceProblem::MissingAnchor(fragment))
+                }
+                _ => None,
+            };
+            if let Some(problem) = problem {
+                broken.push(BrokenReference {
+                    source: relative.clone(),
+                    attribute,
+                    target: reference,
+                    problem,
+                });
+            }
+        }
+    }
+
+    broken.sort_by(|a, b| {
+        a.source
+            .cmp(&b.source)
+            .then_with(|| a.target.cmp(&b.target))
+    });
+    broken
+}
+
+/// The references and anchors [`scan_html`] found on a page.
+struct ScannedPage {
+    /// `(attribute, value)` for every reference outside scripts, styles,
+    /// and comments; one entry per `srcset` candidate.
+    references: Vec<(&'static str, String)>,
+    /// Every `id`, plus the `name` of each `<a>`.
+    ids: HashSet<String>,
+}
+
+fn scan_html(html: &str) -> ScannedPage {
+    let lowercase = html.to_ascii_lowercase();
+    let mut page = ScannedPage {
+        references: Vec::new(),
+        ids: HashSet::new(),
+    };
+
+    let mut position = 0;
+    while let Some(offset) = lowercase[position..].find('<') {
+        let start = position + offset;
+        if lowercase[start..].starts_with("<!--") {
+            position = lowercase[start..]
+                .find("-->")
+                .map_or(html.len(), |end| start + end + "-->".len());
+            continue;
+        }
+        let name_end = lowercase[start + 1..]
+            .find(|character: char| !character.is_ascii_alphanumeric() && character != '-')
+            .map_or(html.len(), |end| start + 1 + end);
+        let name = &lowercase[start + 1..name_end];
+        let Some(tag_end) = lowercase[start..].find('>').map(|end| start + end) else {
+            break;
+        };
+        position = tag_end + 1;
+        if name.is_empty() {
+            continue;
+        }
+
+        let tag = &html[start..tag_end];
+        if name != "base" {
+            for attribute in ["href", "src", "poster"] {
+                if let Some(value) = crate::resources::attribute_value(tag, attribute) {
+                    page.references.push((attribute, decode_entities(&value)));
+                }
+            }
+        }
+        if let Some(srcset) = crate::resources::attribute_value(tag, "srcset")
+            && !srcset.trim_start().starts_with("data:")
+        {
+            for candidate in decode_entities(&srcset).split(',') {
+                if let Some(url) = candidate.split_whitespace().next() {
+                    page.references.push(("srcset", url.to_string()));
+                }
+            }
+        }
+        if let Some(id) = crate::resources::attribute_value(tag, "id") {
+            page.ids.insert(decode_entities(&id));
+        }
+        if name == "a"
+            && let Some(anchor) = crate::resources::attribute_value(tag, "name")
+        {
+            page.ids.insert(decode_entities(&anchor));
+        }
+
+        if name == "script" || name == "style" {
+            position = lowercase[position..]
+                .find(&format!("</{name}"))
+                .map_or(html.len(), |end| position + end);
+        }
+    }
+
+    page
+}
+
+/// Splits `reference` into an output-relative path (`None` for a
+/// fragment-only link to the same page) and fragment, resolving it
+/// against `directory`. External and non-HTTP references return `None`.
+fn resolve_reference(
+    reference: &str,
+    base_url: &str,
+    directory: &Path,
+) -> Option<(Option<String>, Option<String>)> {
+    let reference = reference.trim();
+    let (rest, fragment) = match reference.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (reference, None),
+    };
+    let fragment = fragment.map(percent_decode).filter(|fragment| {
+        !fragment.is_empty() && fragment != "top" && !fragment.starts_with(":~:")
+    });
+    let path = rest.split('?').next().unwrap_or(rest);
+
+    if path.is_empty() {
+        return if rest.is_empty() && !reference.is_empty() {
+            Some((None, fragment))
+        } else {
+            None
+        };
+    }
+
+    let under_base = (!base_url.is_empty())
+        .then(|| path.strip_prefix(base_url))
+        .flatten()
+        .filter(|path| path.is_empty() || path.starts_with('/'));
+    let resolved = if let Some(path) = under_base {
+        crate::assets::normalize_path(Path::new(""), path)
+    } else if path.starts_with("//") || has_scheme(path) {
+        return None;
+    } else if path.starts_with('/') {
+        crate::assets::normalize_path(Path::new(""), path)
+    } else {
+        crate::assets::normalize_path(directory, path)
+    };
+    let trailing_slash = if path.ends_with('/') && !resolved.is_empty() {
+        "/"
+    } else {
+        ""
+    };
+    Some((
+        Some(percent_decode(&format!("{resolved}{trailing_slash}"))),
+        fragment,
+    ))
+}
+
+fn has_scheme(reference: &str) -> bool {
+    reference.split_once(':').is_some_and(|(scheme, _)| {
+        !scheme.is_empty()
+            && scheme
+                .chars()
+                .all(|character| character.is_ascii_alphanumeric() || "+.-".contains(character))
+    })
+}
+
+/// The file an output-relative path is served from: the file itself, or
+/// the `index.html` of a directory.
+fn resolve_file(output_dir: &Path, path: &str) -> Option<PathBuf> {
+    let direct = output_dir.join(path);
+    if !path.ends_with('/') && direct.is_file() {
+        return Some(direct);
+    }
+    let index = output_dir
+        .join(path.trim_end_matches('/'))
+        .join("index.html");
+    index.is_file().then_some(index)
+}
+
+/// Undoes the HTML escaping templates apply to URLs (`&#x2F;`, `&amp;`).
+fn decode_entities(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+    value
+        .replace("&#x2F;", "/")
+        .replace("&#x2f;", "/")
+        .replace("&#47;", "/")
+        .replace("&#x27;", "'")
+        .replace("&#39;", "'")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
 fn is_ignored(path: &str, ignore_prefixes: &[String]) -> bool {
     ignore_prefixes.iter().any(|prefix| {
         let trimmed = prefix.trim_end_matches('/');
@@ -159,7 +491,7 @@ fn link_resolves(output_dir: &Path, href: &str) -> bool {
     output_dir.join(trimmed).join("index.html").exists()
 }
 
-fn extract_hrefs(html: &str) -> Vec<String> {
+pub(crate) fn extract_hrefs(html: &str) -> Vec<String> {
     let mut hrefs = Vec::new();
     let bytes = html.as_bytes();
     let length = bytes.len();
@@ -467,4 +799,69 @@ mod tests {
         let warnings = validate_internal_links(dir.path(), "https://example.com", &[]);
         assert!(warnings.is_empty());
     }
+
+    #[test]
+    fn test_check_links_reports_files_and_anchors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("posts/hello")).unwrap();
+        fs::create_dir_all(dir.path().join("css")).unwrap();
+        fs::create_dir_all(dir.path().join("images")).unwrap();
+        fs::write(dir.path().join("images/a.png"), "png").unwrap();
+        fs::write(
+            dir.path().join("posts/hello/index.html"),
+            r#"<h2 id="intro">Intro</h2><a name="legacy"></a>"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("index.html"),
+            r##"<h1 id="top-heading">Home</h1>
+<a href="https:&#x2F;&#x2F;example.com&#x2F;posts&#x2F;hello&#x2F;#intro">ok</a>
+<a href="posts/hello/#legacy">ok</a>
+<a href="/posts/hello/#missing">bad anchor</a>
+<a href="#top-heading">ok</a><a href="#nowhere">bad</a><a href="#">ok</a>
+<img src="/images/a.png" srcset="images/a.png 1x, /images/a@2x.png 2x">
+<link rel="stylesheet" href="/css/site.css?v=1">
+<a href="mailto:me@example.com">mail</a><a href="https://other.com/x">ext</a>
+<!-- <a href="/commented/"> -->
+<script>document.body.innerHTML = '<a href="' + url + '">';</script>"##,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("css/site.css"),
+            "body{background:url(../images/a.png)}h1{background:url('/images/gone.png')}",
+        )
+        .unwrap();
+
+        let broken = check_links(dir.path(), "https://example.com", &[]);
+        let found: Vec<String> = broken.iter().map(ToString::to_string).collect();
+
+        assert_eq!(
+            found,
+            vec![
+                "css/site.css: url() '/images/gone.png' points to a missing file",
+                "index.html: href '#nowhere' points to a missing anchor '#nowhere'",
+                "index.html: srcset '/images/a@2x.png' points to a missing file",
+                "index.html: href '/posts/hello/#missing' points to a missing anchor '#missing'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_links_relative_to_page_and_ignored_prefixes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("docs/guide")).unwrap();
+        fs::write(dir.path().join("docs/setup.html"), "").unwrap();
+        fs::write(
+            dir.path().join("docs/guide/index.html"),
+            r#"<a href="../setup.html">up</a><a href="../../missing/">gone</a><a href="/sibling/app/">other</a>"#,
+        )
+        .unwrap();
+
+        let broken = check_links(dir.path(), "", &["/sibling".to_string()]);
+
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].source, Path::new("docs/guide/index.html"));
+        assert_eq!(broken[0].target, "../../missing/");
+        assert_eq!(broken[0].problem, ReferenceProblem::MissingFile);
+    }
 }
@@ -0,0 +1,336 @@
+//! Template linting for `bamboo lint`: checks every loaded template for
+//! unknown filters, functions, and tests, `{% include %}`s of templates
+//! that don't exist, and undefined variables when rendered against the
+//! [`template_test`](crate::template_test) sample context. Every problem
+//! is collected in one pass instead of stopping at the first error the
+//! way a build does.
+
+use crate::template_test::test_template;
+use crate::theme::ThemeEngine;
+use std::collections::BTreeSet;
+use std::fmt;
+use tera::Context;
+use tera::ast::{Expr, ExprVal, FunctionCall, Node};
+
+/// Template name prefixes that are only rendered from other templates
+/// (or by the shortcode and render hook processors), so they are checked
+/// statically but not rendered on their own.
+const FRAGMENT_PREFIXES: [&str; 4] = ["partials/", "macros/", "shortcodes/", "_hooks/"];
+
+/// Filters Tera's renderer handles itself rather than looking up.
+const RENDERER_FILTERS: [&str; 2] = ["safe", "default"];
+
+/// One kind of problem found in a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintProblem {
+    /// A variable the sample context doesn't provide, e.g. `page.subtitle`.
+    UndefinedVariable(String),
+    /// A `| filter` that isn't registered.
+    UnknownFilter(String),
+    /// A `function()` call that isn't registered.
+    UnknownFunction(String),
+    /// An `is test` that isn't registered.
+    UnknownTest(String),
+    /// An `{% include %}` with no loaded template among its candidates.
+    MissingTemplate(String),
+    /// Any other rendering error, with its causes joined by `: `.
+    RenderError(String),
+}
+
+impl fmt::Display for LintProblem {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UndefinedVariable(name) => write!(formatter, "undefined variable: {name}"),
+            Self::UnknownFilter(name) => write!(formatter, "unknown filter: {name}"),
+            Self::UnknownFunction(name) => write!(formatter, "unknown function: {name}"),
+            Self::UnknownTest(name) => write!(formatter, "unknown test: {name}"),
+            Self::MissingTemplate(name) => write!(formatter, "missing template: {name}"),
+            Self::RenderError(message) => write!(formatter, "error: {message}"),
+        }
+    }
+}
+
+/// A problem and the template it was found in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    /// Template name, e.g. `partials/header.html`.
+    pub template: String,
+    /// What is wrong.
+    pub problem: LintProblem,
+}
+
+/// Names referenced by a template body.
+#[derive(Debug, Default)]
+pub(crate) struct TemplateRefs {
+    /// Each `{% include %}`'s candidate names and whether it was marked
+    /// `ignore missing`.
+    pub(crate) includes: Vec<(Vec<String>, bool)>,
+    pub(crate) filters: BTreeSet<String>,
+    pub(crate) functions: BTreeSet<String>,
+    pub(crate) tests: BTreeSet<String>,
+    /// Top-level variables read, e.g. `page` for `page.title`.
+    pub(crate) variables: BTreeSet<String>,
+}
+
+impl TemplateRefs {
+    /// Collects every include, filter, function, test, and variable used
+    /// anywhere in `nodes`.
+    pub(crate) fn from_nodes(nodes: &[Node]) -> Self {
+        let mut refs = Self::default();
+        refs.visit_nodes(nodes);
+        refs
+    }
+
+    fn visit_nodes(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            match node {
+                Node::Include(_, names, ignore_missing) => {
+                    self.includes.push((names.clone(), *ignore_missing));
+                }
+                Node::VariableBlock(_, expr) => self.visit_expr(expr),
+                Node::Set(_, set) => self.visit_expr(&set.value),
+                Node::Block(_, block, _) => self.visit_nodes(&block.body),
+                Node::MacroDefinition(_, definition, _) => {
+                    for default in definition.args.values().flatten() {
+                        self.visit_expr(default);
+                    }
+                    self.visit_nodes(&definition.body);
+                }
+                Node::FilterSection(_, section, _) => {
+                    self.visit_call(&section.filter, true);
+                    self.visit_nodes(&section.body);
+                }
+                Node::Forloop(_, forloop, _) => {
+                    self.visit_expr(&forloop.container);
+                    self.visit_nodes(&forloop.body);
+                    if let Some(empty_body) = &forloop.empty_body {
+                        self.visit_nodes(empty_body);
+                    }
+                }
+                Node::If(condition, _) => {
+                    for (_, expr, body) in &condition.conditions {
+                        self.visit_expr(expr);
+                        self.visit_nodes(body);
+                    }
+                    if let Some((_, body)) = &condition.otherwise {
+                        self.visit_nodes(body);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.visit_value(&expr.val);
+        for filter in &expr.filters {
+            self.visit_call(filter, true);
+        }
+    }
+
+    fn visit_value(&mut self, value: &ExprVal) {
+        match value {
+            ExprVal::Math(math) => {
+                self.visit_expr(&math.lhs);
+                self.visit_expr(&math.rhs);
+            }
+            ExprVal::Logic(logic) => {
+                self.visit_expr(&logic.lhs);
+                self.visit_expr(&logic.rhs);
+            }
+            ExprVal::In(membership) => {
+                self.visit_expr(&membership.lhs);
+                self.visit_expr(&membership.rhs);
+            }
+            ExprVal::Test(test) => {
+                self.tests.insert(test.name.clone());
+                for arg in &test.args {
+                    self.visit_expr(arg);
+                }
+            }
+            ExprVal::MacroCall(call) => {
+                for arg in call.args.values() {
+                    self.visit_expr(arg);
+                }
+            }
+            ExprVal::FunctionCall(call) => self.visit_call(call, false),
+            ExprVal::Array(items) => {
+                for item in items {
+                    self.visit_expr(item);
+                }
+            }
+            ExprVal::StringConcat(concat) => {
+                for value in &concat.values {
+                    self.visit_value(value);
+                }
+            }
+            ExprVal::Ident(ident) => {
+                let root = ident.split(['.', '[']).next().unwrap_or(ident);
+                self.variables.insert(root.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_call(&mut self, call: &FunctionCall, is_filter: bool) {
+        if is_filter {
+            self.filters.insert(call.name.clone());
+        } else {
+            self.functions.insert(call.name.clone());
+        }
+        for arg in call.args.values() {
+            self.visit_expr(arg);
+        }
+    }
+}
+
+/// Lints every template loaded in `engine`. Each template is checked for
+/// unknown filters, functions, and tests and for missing includes; page
+/// templates (anything outside `partials/`, `macros/`, `shortcodes/`, and
+/// `_hooks/`) are also rendered with `context` to find undefined
+/// variables. Issues are ordered by template name.
+pub fn lint_templates(engine: &ThemeEngine, context: &Context) -> Vec<LintIssue> {
+    let tera = engine.tera();
+    let mut issues = Vec::new();
+
+    for name in engine.template_names() {
+        let Some(template) = tera.templates.get(&name) else {
+            continue;
+        };
+        let refs = TemplateRefs::from_nodes(&template.ast);
+        let mut problems = Vec::new();
+
+        for (candidates, ignore_missing) in &refs.includes {
+            if !ignore_missing
+                && !candidates
+                    .iter()
+                    .any(|candidate| tera.templates.contains_key(candidate))
+            {
+                problems.push(LintProblem::MissingTemplate(candidates.join(", ")));
+            }
+        }
+        problems.extend(
+            refs.filters
+                .iter()
+                .filter(|filter| {
+                    !RENDERER_FILTERS.contains(&filter.as_str()) && tera.get_filter(filter).is_err()
+                })
+                .map(|filter| LintProblem::UnknownFilter(filter.clone())),
+        );
+        problems.extend(
+            refs.functions
+                .iter()
+                .filter(|function| tera.get_function(function).is_err())
+                .map(|function| LintProblem::UnknownFunction(function.clone())),
+        );
+        problems.extend(
+            refs.tests
+                .iter()
+                .filter(|test| tera.get_tester(test).is_err())
+                .map(|test| LintProblem::UnknownTest(test.clone())),
+        );
+
+        if !FRAGMENT_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+        {
+            let report = test_template(engine, &name, context);
+            problems.extend(
+                report
+                    .undefined_variables
+                    .into_iter()
+                    .map(LintProblem::UndefinedVariable),
+            );
+            // Unknown names and missing includes are reported statically
+            // above, in whichever template uses them.
+            if let Some(error) = report.error
+                && !error.ends_with("' not found")
+            {
+                problems.push(LintProblem::RenderError(error));
+            }
+        }
+
+        issues.extend(problems.into_iter().map(|problem| LintIssue {
+            template: name.clone(),
+            problem,
+        }));
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template_test::{sample_context, sample_site};
+
+    #[test]
+    fn test_builtin_theme_is_clean() {
+        let engine = ThemeEngine::new("default").unwrap();
+        let site = sample_site().unwrap();
+        let issues: Vec<LintIssue> = lint_templates(&engine, &sample_context(&site))
+            .into_iter()
+            // `portfolio.html` reads data files the sample site doesn't have.
+            .filter(|issue| {
+                !matches!(&issue.problem, LintProblem::UndefinedVariable(name) if name.starts_with("site.data."))
+            })
+            .collect();
+        assert!(issues.is_empty(), "{issues:?}");
+    }
+
+    #[test]
+    fn test_reports_every_problem_at_once() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let templates = dir.path().join("templates");
+        std::fs::create_dir_all(templates.join("partials")).unwrap();
+        std::fs::write(
+            templates.join("partials/broken.html"),
+            "{{ page.title | shout }}{% if page is fancy %}{{ now_ish() }}{% endif %}",
+        )
+        .unwrap();
+        std::fs::write(
+            templates.join("page.html"),
+            "{% include \"partials/missing.html\" %}\
+             {% include \"partials/optional.html\" ignore missing %}",
+        )
+        .unwrap();
+        std::fs::write(
+            templates.join("custom.html"),
+            "{{ page.subtitle }}{{ site.config.extra.tagline }}",
+        )
+        .unwrap();
+        let engine = ThemeEngine::new_with_overrides("default", dir.path()).unwrap();
+        let site = sample_site().unwrap();
+
+        let issues = lint_templates(&engine, &sample_context(&site));
+        let problems_in = |template: &str| -> Vec<LintProblem> {
+            issues
+                .iter()
+                .filter(|issue| issue.template == template)
+                .map(|issue| issue.problem.clone())
+                .collect()
+        };
+
+        assert_eq!(
+            problems_in("partials/broken.html"),
+            vec![
+                LintProblem::UnknownFilter("shout".to_string()),
+                LintProblem::UnknownFunction("now_ish".to_string()),
+                LintProblem::UnknownTest("fancy".to_string()),
+            ]
+        );
+        assert_eq!(
+            problems_in("page.html"),
+            vec![LintProblem::MissingTemplate(
+                "partials/missing.html".to_string()
+            )]
+        );
+        assert_eq!(
+            problems_in("custom.html"),
+            vec![
+                LintProblem::UndefinedVariable("page.subtitle".to_string()),
+                LintProblem::UndefinedVariable("site.config.extra.tagline".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! `llms.txt` ([llmstxt.org](https://llmstxt.org)): a markdown index of the
+//! site for language models, written when `bamboo.toml` has an `[llms]`
+//! table. It opens with the site title and description, then lists pages,
+//! posts, notes, and each collection under its own heading, one link per
+//! entry with the entry's description.
+//!
+//! With `markdown` on (the default), each listed page also gets its
+//! markdown source written next to its HTML as `index.md`, and the links
+//! point there. `full = true` adds `llms-full.txt`, every entry's markdown
+//! in one file.
+
+use crate::error::Result;
+use crate::feeds::{NOTES_SECTION, POSTS_SECTION};
+use crate::redirects::redirect_to;
+use crate::sink::RenderSink;
+use crate::sitemap::PAGES_SECTION;
+use crate::types::{Content, Site};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Output path of the index.
+pub const LLMS_FILE: &str = "llms.txt";
+/// Output path of the full-text version.
+pub const LLMS_FULL_FILE: &str = "llms-full.txt";
+
+/// `[llms]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LlmsConfig {
+    /// Write each listed page's markdown as `index.md` next to its HTML
+    /// and link to that instead. Defaults to `true`.
+    #[serde(default = "default_markdown")]
+    pub markdown: bool,
+    /// Also write `llms-full.txt`. Defaults to `false`.
+    #[serde(default)]
+    pub full: bool,
+    /// Sections listed under `## Optional`, which readers may skip when
+    /// context is short: `"pages"`, `"posts"`, `"notes"`, or collection
+    /// names.
+    #[serde(default)]
+    pub optional: Vec<String>,
+}
+
+fn default_markdown() -> bool {
+    true
+}
+
+impl Default for LlmsConfig {
+    fn default() -> Self {
+        Self {
+            markdown: default_markdown(),
+            full: false,
+            optional: Vec::new(),
+        }
+    }
+}
+
+/// One `##` section of llms.txt.
+#[derive(Debug, Clone)]
+pub struct LlmsSection<'a> {
+    /// Section name: `pages`, `posts`, `notes`, or a collection name.
+    pub name: String,
+    /// Entries in listing order.
+    pub entries: Vec<&'a Content>,
+}
+
+/// The sections of `site` in llms.txt order: pages, posts, notes, then
+/// collections by name. Empty sections, the 404 page, and redirect stubs
+/// are left out.
+pub fn llms_sections(site: &Site) -> Vec<LlmsSection<'_>> {
+    let listed = |content: &&Content| content.slug != "404" && redirect_to(content).is_none();
+    let mut sections = vec![
+        LlmsSection {
+            name: PAGES_SECTION.to_string(),
+            entries: site.pages.iter().map(|page| &page.content).collect(),
+        },
+        LlmsSection {
+            name: POSTS_SECTION.to_string(),
+            entries: site.posts.iter().map(|post| &post.content).collect(),
+        },
+        LlmsSection {
+            name: NOTES_SECTION.to_string(),
+            entries: site.notes.iter().map(|note| &note.content).collect(),
+        },
+    ];
+    let mut names: Vec<&String> = site.collections.keys().collect();
+    names.sort();
+    sections.extend(names.into_iter().map(|name| {
+        LlmsSection {
+            name: name.clone(),
+            entries: site.collections[name]
+                .items
+                .iter()
+                .map(|item| &item.content)
+                .collect(),
+        }
+    }));
+    for section in &mut sections {
+        section.entries.retain(listed);
+    }
+    sections.retain(|section| !section.entries.is_empty());
+    sections
+}
+
+/// Output path of `content`'s markdown export: its HTML path with an `.md`
+/// extension.
+pub fn markdown_path(content: &Content) -> PathBuf {
+    content.path.with_extension("md")
+}
+
+/// `content`'s markdown export: the title as a heading, then the source.
+pub fn render_markdown(content: &Content) -> String {
+    format!(
+        "# {}\n\n{}\n",
+        content.title,
+        content.raw_content.trim_end()
+    )
+}
+
+/// Renders llms.txt for `site`.
+pub fn render_llms_txt(site: &Site, config: &LlmsConfig) -> String {
+    let base_url = site.config.base_url.trim_end_matches('/');
+    let mut text = format!("# {}\n", site.config.title);
+    if let Some(description) = &site.config.description {
+        text.push_str(&format!("\n> {description}\n"));
+    }
+
+    let entry = |content: &Content| {
+        let url = if config.markdown {
+            format!(
+                "{base_url}/{}",
+                markdown_path(content).to_string_lossy().replace('\\', "/")
+            )
+        } else {
+            format!("{base_url}{}", content.url)
+        };
+        match content.frontmatter.get_string("description") {
+            Some(description) => format!("- [{}]({url}): {description}\n", content.title),
+            None => format!("- [{}]({url})\n", content.title),
+        }
+    };
+    let (optional, required): (Vec<_>, Vec<_>) = llms_sections(site)
+        .into_iter()
+        .partition(|section| config.optional.contains(&section.name));
+    for section in &required {
+        text.push_str(&format!("\n## {}\n\n", heading(&section.name)));
+        section
+            .entries
+            .iter()
+            .for_each(|content| text.push_str(&entry(content)));
+    }
+    if !optional.is_empty() {
+        text.push_str("\n## Optional\n\n");
+        optional
+            .iter()
+            .flat_map(|section| &section.entries)
+            .for_each(|content| text.push_str(&entry(content)));
+    }
+    text
+}
+
+/// Renders llms-full.txt: the llms.txt header followed by every entry's
+/// markdown export, each with its URL.
+pub fn render_llms_full_txt(site: &Site) -> String {
+    let base_url = site.config.base_url.trim_end_matches('/');
+    let mut text = format!("# {}\n", site.config.title);
+    if let Some(description) = &site.config.description {
+        text.push_str(&format!("\n> {description}\n"));
+    }
+    for content in llms_sections(site)
+        .iter()
+        .flat_map(|section| &section.entries)
+    {
+        text.push_str(&format!(
+            "\n---\n\nURL: {base_url}{}\n\n{}",
+            content.url,
+            render_markdown(content)
+        ));
+    }
+    text
+}
+
+/// Writes llms.txt, the markdown exports, and llms-full.txt into `output`
+/// as `[llms]` asks.
+pub fn generate_llms_txt(site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
+    let Some(config) = site.config.llms.as_ref() else {
+        return Ok(());
+    };
+    if config.markdown {
+        for content in llms_sections(site)
+            .iter()
+            .flat_map(|section| &section.entries)
+        {
+            output.write_file(&markdown_path(content), render_markdown(content).as_bytes())?;
+        }
+    }
+    output.write_file(
+        Path::new(LLMS_FILE),
+        render_llms_txt(site, config).as_bytes(),
+    )?;
+    if config.full {
+        output.write_file(
+            Path::new(LLMS_FULL_FILE),
+            render_llms_full_txt(site).as_bytes(),
+        )?;
+    }
+    Ok(())
+}
+
+/// `posts` → `Posts`, `release-notes` → `Release notes`.
+fn heading(name: &str) -> String {
+    let name = name.replace(['-', '_'], " ");
+    let mut characters = name.chars();
+    characters
+        .next()
+        .map(|first| first.to_uppercase().chain(characters).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::MemorySink;
+    use crate::test_site::site;
+
+    #[test]
+    fn test_llms_sections() {
+        let site = site("[llms]");
+        let names: Vec<String> = llms_sections(&site)
+            .into_iter()
+            .map(|section| section.name)
+            .collect();
+        assert_eq!(names, ["pages", "posts", "notes", "projects"]);
+        assert_eq!(heading("release-notes"), "Release notes");
+    }
+
+    #[test]
+    fn test_render_llms_txt() {
+        let mut site =
+            site("description = \"A test site\"\n[llms]\noptional = [\"notes\", \"projects\"]");
+        site.pages[0]
+            .content
+            .frontmatter
+            .raw
+            .insert("description".to_string(), "Who we are".into());
+        let text = render_llms_txt(&site, site.config.llms.as_ref().unwrap());
+
+        assert!(text.starts_with(
+            "# Test\n\n> A test site\n\n## Pages\n\n\
+             - [About](https://example.com/about/index.md): Who we are\n\n## Posts\n\n\
+             - [Second](https://example.com/posts/second/index.md)\n"
+        ));
+        assert!(text.contains(
+            "\n## Optional\n\n- [A note.](https://example.com/notes/2024-03-01-090000/index.md)\n\
+             - [Bamboo](https://example.com/projects/bamboo/index.md)\n"
+        ));
+
+        let html_links = render_llms_txt(
+            &site,
+            &LlmsConfig {
+                markdown: false,
+                ..LlmsConfig::default()
+            },
+        );
+        assert!(html_links.contains("- [About](https://example.com/about/): Who we are\n"));
+    }
+
+    #[test]
+    fn test_generate_llms_txt() {
+        let site = site("[llms]\nfull = true");
+        let sink = MemorySink::new();
+        generate_llms_txt(&site, &sink).unwrap();
+
+        assert_eq!(
+            String::from_utf8(sink.read_file(Path::new("about/index.md")).unwrap()).unwrap(),
+            "# About\n\nAn about page.\n"
+        );
+        assert!(sink.file_exists(Path::new(LLMS_FILE)));
+        let full = String::from_utf8(sink.read_file(Path::new(LLMS_FULL_FILE)).unwrap()).unwrap();
+        assert!(full.contains("\n---\n\nURL: https://example.com/about/\n\n# About\n"));
+    }
+}
@@ -0,0 +1,1750 @@
+//! `bamboo migrate`: converting a [Hugo](https://gohugo.io) or
+//! [Zola](https://www.getzola.org) site into a bamboo project, so an
+//! existing site can be tried out without converting it by hand.
+//!
+//! - Content: the blog section (`posts/`, `post/`, or `blog/`, or a Zola
+//!   section sorted by date) becomes `content/posts/`. Page bundles are
+//!   flattened to `<slug>.md` with their files copied to
+//!   `static/posts/<slug>/`, and posts whose URL changes get a
+//!   `redirect_from` entry for the old one. Every other page keeps its
+//!   path.
+//! - Frontmatter is rewritten as TOML using bamboo's keys: `aliases`
+//!   become `redirect_from`, `lastmod` becomes `updated`, `url` and `path`
+//!   become `permalink`, and Zola's `[taxonomies]` terms move to the top
+//!   level.
+//! - Shortcodes are rewritten in bamboo's syntax, `{{< name key="value" >}}`
+//!   or `{{% name %}}…{{% /name %}}` with a body. Positional arguments of
+//!   `youtube` and `gist` are named, Hugo's `highlight` becomes a fenced
+//!   code block, and `relref` and Zola's `@/` links become `ref`.
+//! - `bamboo.toml` gets the title, base URL, description, language,
+//!   author, taxonomies, and `[extra]` (Hugo's `params`).
+//! - `static/` and `data/` are copied, along with Hugo's `assets/` and
+//!   Zola's `sass/`, whose stylesheets bamboo compiles from `static/`.
+//!
+//! Themes and templates aren't converted, so the migrated site starts on
+//! the default theme. Everything that needs a look by hand is listed in
+//! [`MigrationReport::warnings`].
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+use walkdir::WalkDir;
+
+use crate::error::{BambooError, IoContext, Result};
+use crate::parsing::{parse_date_from_filename, slugify};
+
+/// The generator a site is migrated from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    /// Hugo: `hugo.toml` or `config.toml` (or YAML or JSON), and
+    /// `{{< shortcode >}}`s.
+    Hugo,
+    /// Zola: `config.toml`, and `{{ shortcode() }}`s.
+    Zola,
+}
+
+impl Generator {
+    fn name(self) -> &'static str {
+        match self {
+            Generator::Hugo => "Hugo",
+            Generator::Zola => "Zola",
+        }
+    }
+
+    /// Config files, in the order the generator looks for them.
+    fn config_files(self) -> &'static [&'static str] {
+        match self {
+            Generator::Hugo => &[
+                "hugo.toml",
+                "hugo.yaml",
+                "hugo.yml",
+                "hugo.json",
+                "config.toml",
+                "config.yaml",
+                "config.yml",
+                "config.json",
+                "config/_default/hugo.toml",
+                "config/_default/hugo.yaml",
+                "config/_default/hugo.yml",
+                "config/_default/hugo.json",
+                "config/_default/config.toml",
+                "config/_default/config.yaml",
+                "config/_default/config.yml",
+                "config/_default/config.json",
+            ],
+            Generator::Zola => &["config.toml"],
+        }
+    }
+
+    /// Directories of the source site that aren't migrated.
+    fn template_dirs(self) -> &'static [&'static str] {
+        match self {
+            Generator::Hugo => &["layouts", "themes", "i18n"],
+            Generator::Zola => &["templates", "themes"],
+        }
+    }
+}
+
+/// Section names taken to be the blog when none is given.
+const POST_SECTIONS: [&str; 3] = ["posts", "post", "blog"];
+
+/// Shortcodes bamboo renders without a site template.
+const BUILTIN_SHORTCODES: [&str; 10] = [
+    "youtube", "figure", "image", "gist", "pdf", "note", "details", "tabs", "tab", "ref",
+];
+
+/// Zola section settings with no bamboo equivalent, dropped from
+/// `_index.md` frontmatter.
+const ZOLA_SECTION_KEYS: [&str; 12] = [
+    "sort_by",
+    "paginate_by",
+    "paginate_path",
+    "paginate_reversed",
+    "insert_anchor_links",
+    "page_template",
+    "transparent",
+    "render",
+    "generate_feed",
+    "generate_feeds",
+    "in_search_index",
+    "sort",
+];
+
+/// Frontmatter keys written first, in this order. Other keys follow
+/// alphabetically.
+const FRONTMATTER_ORDER: [&str; 11] = [
+    "title",
+    "date",
+    "updated",
+    "draft",
+    "description",
+    "excerpt",
+    "permalink",
+    "redirect_from",
+    "weight",
+    "tags",
+    "categories",
+];
+
+/// `bamboo.toml` keys written first, in this order.
+const CONFIG_ORDER: [&str; 10] = [
+    "title",
+    "base_url",
+    "description",
+    "language",
+    "author",
+    "posts_per_page",
+    "syntax_theme",
+    "minify",
+    "drafts",
+    "future",
+];
+
+/// What [`migrate`] converted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Posts written to `content/posts/`.
+    pub posts: usize,
+    /// Pages written elsewhere under `content/`.
+    pub pages: usize,
+    /// Static, data, and page bundle files copied.
+    pub files: usize,
+    /// Anything that wasn't carried over or needs checking by hand.
+    pub warnings: Vec<String>,
+}
+
+/// Converts the `generator` site in `source_dir` into a bamboo project in
+/// `output_dir`, which must be empty or missing. `posts_section` names the
+/// content section holding the blog, relative to `content/`; without it,
+/// the first of `posts`, `post`, and `blog` is used, or for Zola a section
+/// sorted by date.
+pub fn migrate(
+    generator: Generator,
+    source_dir: &Path,
+    output_dir: &Path,
+    posts_section: Option<&str>,
+) -> Result<MigrationReport> {
+    let config_path = generator
+        .config_files()
+        .iter()
+        .map(|name| source_dir.join(name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| BambooError::Migrate {
+            path: source_dir.to_path_buf(),
+            message: format!(
+                "not a {} site: no {} found",
+                generator.name(),
+                generator.config_files()[0]
+            ),
+        })?;
+    if fs::read_dir(output_dir).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(BambooError::Migrate {
+            path: output_dir.to_path_buf(),
+            message: "the directory is not empty".to_string(),
+        });
+    }
+
+    let content_dir = source_dir.join("content");
+    let section = match posts_section {
+        Some(section) => {
+            let section = section.trim_matches('/');
+            if !content_dir.join(section).is_dir() {
+                return Err(BambooError::Migrate {
+                    path: content_dir.join(section),
+                    message: "the posts section doesn't exist".to_string(),
+                });
+            }
+            Some(section.to_string())
+        }
+        None => find_posts_section(generator, &content_dir),
+    };
+
+    let mut migration = Migration {
+        generator,
+        source_dir,
+        output_dir,
+        section,
+        paths: HashMap::new(),
+        custom_shortcodes: BTreeSet::new(),
+        report: MigrationReport::default(),
+    };
+    if migration.section.is_none() {
+        migration.warn(format!(
+            "no blog section found, so everything was migrated as pages; pass the section holding {} posts to migrate them as posts",
+            generator.name()
+        ));
+    }
+
+    let config = migration.site_config(&config_path)?;
+    migration.migrate_content(&content_dir)?;
+    let static_dir = output_dir.join("static");
+    migration.copy_tree(&source_dir.join("static"), &static_dir)?;
+    migration.copy_tree(&source_dir.join("data"), &output_dir.join("data"))?;
+    match generator {
+        Generator::Hugo => migration.copy_tree(&source_dir.join("assets"), &static_dir)?,
+        Generator::Zola => migration.copy_tree(&source_dir.join("sass"), &static_dir)?,
+    }
+    for dir in generator.template_dirs() {
+        if source_dir.join(dir).is_dir() {
+            migration.warn(format!(
+                "{dir}/ was not migrated; {} templates need porting to bamboo's Tera templates in templates/",
+                generator.name()
+            ));
+        }
+    }
+    if !migration.custom_shortcodes.is_empty() {
+        let names: Vec<&str> = migration
+            .custom_shortcodes
+            .iter()
+            .map(String::as_str)
+            .collect();
+        migration.warn(format!(
+            "shortcodes without a bamboo template, to add as templates/shortcodes/<name>.html: {}",
+            names.join(", ")
+        ));
+    }
+
+    let config_file = output_dir.join("bamboo.toml");
+    fs::write(&config_file, write_toml(&config, &CONFIG_ORDER))
+        .io_context("write", &config_file)?;
+    Ok(migration.report)
+}
+
+/// The first conventional blog section under `content_dir`, or for Zola,
+/// the first section whose `_index.md` sorts its pages by date.
+fn find_posts_section(generator: Generator, content_dir: &Path) -> Option<String> {
+    if let Some(section) = POST_SECTIONS
+        .iter()
+        .find(|section| content_dir.join(section).is_dir())
+    {
+        return Some(section.to_string());
+    }
+    if generator != Generator::Zola {
+        return None;
+    }
+    let mut sections: Vec<PathBuf> = fs::read_dir(content_dir)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    sections.sort();
+    sections.into_iter().find_map(|section| {
+        let text = fs::read_to_string(section.join("_index.md")).ok()?;
+        let (format, frontmatter, _) = split_frontmatter(&text)?;
+        let frontmatter = parse_data(frontmatter, format, &section).ok()?;
+        (frontmatter.get("sort_by")?.as_str()? == "date")
+            .then(|| section.file_name()?.to_str().map(str::to_string))?
+    })
+}
+
+/// A markdown file being migrated.
+struct Document {
+    /// Path relative to the source `content/`.
+    old: String,
+    /// Path relative to the output `content/`.
+    new: String,
+    post: bool,
+    /// `None` when the frontmatter couldn't be read and the file is copied
+    /// unchanged.
+    frontmatter: Option<Map<String, Value>>,
+    body: String,
+}
+
+struct Migration<'a> {
+    generator: Generator,
+    source_dir: &'a Path,
+    output_dir: &'a Path,
+    /// Blog section, relative to `content/`.
+    section: Option<String>,
+    /// New path of each markdown file under `content/`, keyed by every
+    /// form a reference to it may take: its old path, without `.md`, and
+    /// a bundle's directory.
+    paths: HashMap<String, String>,
+    /// Shortcodes used that bamboo has no built-in template for.
+    custom_shortcodes: BTreeSet<String>,
+    report: MigrationReport,
+}
+
+impl Migration<'_> {
+    fn warn(&mut self, warning: String) {
+        self.report.warnings.push(warning);
+    }
+
+    fn migrate_content(&mut self, content_dir: &Path) -> Result<()> {
+        let mut files = Vec::new();
+        for entry in WalkDir::new(content_dir).sort_by_file_name() {
+            let entry = entry.map_err(|error| BambooError::WalkDir {
+                path: content_dir.to_path_buf(),
+                message: error.to_string(),
+            })?;
+            if entry.file_type().is_file()
+                && let Ok(relative) = entry.path().strip_prefix(content_dir)
+            {
+                files.push(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+        let bundles: HashSet<&str> = files
+            .iter()
+            .filter_map(|file| {
+                file.strip_suffix("/index.md")
+                    .or_else(|| file.strip_suffix("/index.markdown"))
+            })
+            .collect();
+
+        let mut documents = Vec::new();
+        let mut resources = Vec::new();
+        let mut bundle_dirs: HashMap<String, String> = HashMap::new();
+        for file in &files {
+            let path = content_dir.join(file);
+            let (parent, name) = file.rsplit_once('/').unwrap_or(("", file));
+            let extension = name.rsplit_once('.').map(|(_, extension)| extension);
+            match extension.map(str::to_ascii_lowercase).as_deref() {
+                Some("md" | "markdown") => {
+                    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+                    if stem != "index" && bundles.iter().any(|bundle| in_dir(parent, bundle)) {
+                        self.warn(format!(
+                            "content/{file} is a bundle resource, not a page, and was skipped"
+                        ));
+                        continue;
+                    }
+                    let text = fs::read_to_string(&path).io_context("read", &path)?;
+                    let document = self.plan_document(file, &path, &text);
+                    if let Some(document) = document {
+                        if document.post && stem == "index" {
+                            let slug = document.new.trim_start_matches("posts/");
+                            let slug = slug.strip_suffix(".md").unwrap_or(slug);
+                            let slug = parse_date_from_filename(slug)
+                                .map_or(slug.to_string(), |(_, slug)| slug);
+                            bundle_dirs.insert(parent.to_string(), format!("posts/{slug}"));
+                        }
+                        documents.push(document);
+                    }
+                }
+                Some("html" | "htm" | "org" | "adoc" | "asciidoc" | "rst" | "pandoc")
+                    if fs::read_to_string(&path)
+                        .is_ok_and(|text| split_frontmatter(&text).is_some()) =>
+                {
+                    self.warn(format!(
+                        "content/{file} isn't markdown and was skipped; bamboo only renders markdown content"
+                    ));
+                }
+                _ => resources.push(file),
+            }
+        }
+
+        for document in &documents {
+            let old = document.old.as_str();
+            self.paths.insert(old.to_string(), document.new.clone());
+            let without_extension = old.rsplit_once('.').map_or(old, |(stem, _)| stem);
+            self.paths
+                .insert(without_extension.to_string(), document.new.clone());
+            if let Some(dir) = without_extension
+                .strip_suffix("/index")
+                .or_else(|| without_extension.strip_suffix("/_index"))
+            {
+                self.paths.insert(dir.to_string(), document.new.clone());
+            }
+        }
+
+        let mut written = HashSet::new();
+        for document in documents {
+            let label = format!("content/{}", document.old);
+            if !written.insert(document.new.clone()) {
+                self.warn(format!(
+                    "{label} would overwrite content/{} and was skipped",
+                    document.new
+                ));
+                continue;
+            }
+            let text = match document.frontmatter {
+                Some(frontmatter) => {
+                    let body = match self.generator {
+                        Generator::Hugo => self.convert_hugo(&label, &document.body),
+                        Generator::Zola => self.convert_zola(&label, &document.body),
+                    };
+                    let frontmatter = to_toml_table(frontmatter);
+                    if frontmatter.is_empty() {
+                        format!("{}\n", body.trim_end())
+                    } else {
+                        format!(
+                            "+++\n{}+++\n\n{}\n",
+                            write_toml(&frontmatter, &FRONTMATTER_ORDER),
+                            body.trim_end()
+                        )
+                    }
+                }
+                None => document.body,
+            };
+            let path = self.output_dir.join("content").join(&document.new);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).io_context("create_dir_all", parent)?;
+            }
+            fs::write(&path, text).io_context("write", &path)?;
+            if document.post {
+                self.report.posts += 1;
+            } else {
+                self.report.pages += 1;
+            }
+        }
+
+        for file in resources {
+            let (parent, _) = file.rsplit_once('/').unwrap_or(("", file));
+            let destination = bundle_dirs
+                .iter()
+                .find(|(bundle, _)| in_dir(parent, bundle))
+                .map_or(file.clone(), |(bundle, moved)| {
+                    format!("{moved}{}", &file[bundle.len()..])
+                });
+            self.copy_file(
+                &content_dir.join(file),
+                &self.output_dir.join("static").join(destination),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Works out where a markdown file goes and maps its frontmatter.
+    /// Returns `None` for files that aren't migrated.
+    fn plan_document(&mut self, file: &str, path: &Path, text: &str) -> Option<Document> {
+        let label = format!("content/{file}");
+        let text = text.replace("\r\n", "\n");
+        let (parent, name) = file.rsplit_once('/').unwrap_or(("", file));
+        let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+        let post = self.section.as_ref().is_some_and(|section| {
+            file.strip_prefix(section.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+        });
+        if post && stem == "_index" {
+            self.warn(format!(
+                "{label} was dropped; bamboo generates the post listing itself"
+            ));
+            return None;
+        }
+
+        let (frontmatter, body) = match split_frontmatter(&text) {
+            Some((format, raw, body)) => match parse_data(raw, format, path) {
+                Ok(Value::Object(frontmatter)) => (frontmatter, body),
+                Ok(_) => (Map::new(), body),
+                Err(error) => {
+                    self.warn(format!(
+                        "{label} was copied unchanged because its frontmatter couldn't be read: {error}"
+                    ));
+                    return Some(Document {
+                        old: file.to_string(),
+                        new: markdown_path(file),
+                        post,
+                        frontmatter: None,
+                        body: text,
+                    });
+                }
+            },
+            None => (Map::new(), text.as_str()),
+        };
+        let mut frontmatter = self.map_frontmatter(&label, frontmatter);
+        let body = body.trim_start_matches('\n').to_string();
+        if stem == "_index" && frontmatter.is_empty() && body.trim().is_empty() {
+            return None;
+        }
+        let slug = frontmatter
+            .remove("slug")
+            .and_then(|slug| slug.as_str().map(|slug| slug.trim_matches('/').to_string()))
+            .filter(|slug| !slug.is_empty());
+
+        if !post {
+            if let Some(slug) = slug
+                && !frontmatter.contains_key("permalink")
+                && !matches!(stem, "index" | "_index")
+            {
+                let dir = if stem == "index" { "" } else { parent };
+                let permalink = if dir.is_empty() {
+                    format!("/{slug}/")
+                } else {
+                    format!("/{dir}/{slug}/")
+                };
+                frontmatter.insert("permalink".to_string(), Value::String(permalink));
+            }
+            return Some(Document {
+                old: file.to_string(),
+                new: markdown_path(file),
+                post,
+                frontmatter: Some(frontmatter),
+                body,
+            });
+        }
+
+        // A bundle is named after its directory.
+        let (dir, stem) = if stem == "index" {
+            parent.rsplit_once('/').unwrap_or(("", parent))
+        } else {
+            (parent, stem)
+        };
+        let (date_prefix, stem_slug) = parse_date_from_filename(stem)
+            .map_or((None, stem.to_string()), |(date, slug)| (Some(date), slug));
+        let has_date = frontmatter.contains_key("date");
+        let new_slug = slug.clone().unwrap_or_else(|| stem_slug.clone());
+        let new = match date_prefix {
+            Some(ref date) if !has_date => format!("posts/{date}-{new_slug}.md"),
+            _ => format!("posts/{new_slug}.md"),
+        };
+        if !has_date && date_prefix.is_none() {
+            self.warn(format!(
+                "{label} has no date; add one to content/{new} before building"
+            ));
+        }
+
+        if !frontmatter.contains_key("permalink") {
+            let old_slug = slug.unwrap_or_else(|| match self.generator {
+                Generator::Hugo => stem.to_string(),
+                Generator::Zola => slugify(&stem_slug),
+            });
+            let mut old_url = format!("/{dir}/{old_slug}/");
+            if self.generator == Generator::Hugo {
+                old_url = old_url.to_lowercase();
+            }
+            if old_url != format!("/posts/{new_slug}/") {
+                let redirects = frontmatter
+                    .entry("redirect_from")
+                    .or_insert_with(|| Value::Array(Vec::new()));
+                if let Value::Array(redirects) = redirects
+                    && !redirects.contains(&Value::String(old_url.clone()))
+                {
+                    redirects.push(Value::String(old_url));
+                }
+            }
+        }
+
+        Some(Document {
+            old: file.to_string(),
+            new,
+            post,
+            frontmatter: Some(frontmatter),
+            body,
+        })
+    }
+
+    /// Renames frontmatter keys to bamboo's.
+    fn map_frontmatter(
+        &mut self,
+        label: &str,
+        frontmatter: Map<String, Value>,
+    ) -> Map<String, Value> {
+        let mut mapped = Map::new();
+        for (key, value) in frontmatter {
+            if value.is_null() {
+                continue;
+            }
+            let lowercase = key.to_lowercase();
+            let key = match (self.generator, lowercase.as_str()) {
+                (_, "aliases") => "redirect_from".to_string(),
+                (Generator::Hugo, "lastmod") => "updated".to_string(),
+                (Generator::Hugo, "summary") => "excerpt".to_string(),
+                (Generator::Hugo, "url") | (Generator::Zola, "path") => "permalink".to_string(),
+                (Generator::Hugo, "publishdate") => {
+                    mapped.entry("date").or_insert(value);
+                    continue;
+                }
+                (Generator::Hugo, "date") => {
+                    mapped.insert("date".to_string(), value);
+                    continue;
+                }
+                (
+                    Generator::Hugo,
+                    "title" | "draft" | "description" | "weight" | "tags" | "categories" | "slug"
+                    | "keywords",
+                ) => lowercase,
+                (Generator::Zola, "in_search_index") if !label.ends_with("_index.md") => {
+                    "in_search".to_string()
+                }
+                (Generator::Zola, "taxonomies") => {
+                    let Value::Object(taxonomies) = value else {
+                        continue;
+                    };
+                    for (taxonomy, terms) in taxonomies {
+                        if mapped.contains_key(&taxonomy) {
+                            self.warn(format!(
+                                "{label}: taxonomy `{taxonomy}` clashes with a frontmatter key of the same name and was dropped"
+                            ));
+                        } else {
+                            mapped.insert(taxonomy, terms);
+                        }
+                    }
+                    continue;
+                }
+                (Generator::Zola, "template") => {
+                    self.warn(format!(
+                        "{label}: template `{}` was dropped; set `template` once it is ported",
+                        value.as_str().unwrap_or_default()
+                    ));
+                    continue;
+                }
+                (Generator::Zola, section_key) if ZOLA_SECTION_KEYS.contains(&section_key) => {
+                    continue;
+                }
+                _ => key,
+            };
+            let value = match key.as_str() {
+                "redirect_from" => Value::Array(
+                    string_list(&value)
+                        .into_iter()
+                        .map(|alias| {
+                            let alias = alias.trim_start_matches('/');
+                            Value::String(format!("/{alias}"))
+                        })
+                        .collect(),
+                ),
+                _ => value,
+            };
+            mapped.insert(key, value);
+        }
+        mapped
+    }
+
+    /// Rewrites Hugo shortcodes, outside fenced code blocks, in bamboo's
+    /// syntax.
+    fn convert_hugo(&mut self, label: &str, body: &str) -> String {
+        let segments = split_code_blocks(body);
+        let mut closed = HashSet::new();
+        for (_, text) in segments.iter().filter(|(code, _)| !code) {
+            let mut rest = *text;
+            while let Some((_, end, inner)) = next_hugo_tag(rest) {
+                if let Some(name) = inner.strip_prefix('/') {
+                    closed.insert(name.trim().to_string());
+                }
+                rest = &rest[end..];
+            }
+        }
+        segments
+            .into_iter()
+            .map(|(code, text)| {
+                if code {
+                    text.to_string()
+                } else {
+                    self.convert_hugo_text(label, text, &closed)
+                }
+            })
+            .collect()
+    }
+
+    fn convert_hugo_text(&mut self, label: &str, text: &str, closed: &HashSet<String>) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some((start, end, inner)) = next_hugo_tag(rest) {
+            output.push_str(&rest[..start]);
+            let tag = &rest[start..end];
+            rest = &rest[end..];
+            if inner.starts_with("/*") {
+                self.warn(format!(
+                    "{label}: escaped shortcode `{tag}` has no bamboo equivalent and was left as is"
+                ));
+                output.push_str(tag);
+                continue;
+            }
+            if let Some(name) = inner.strip_prefix('/') {
+                let name = match name.trim() {
+                    "relref" => "ref",
+                    name => name,
+                };
+                output.push_str(&format!("{{{{% /{name} %}}}}"));
+                continue;
+            }
+            let (inner, self_closing) = match inner.strip_suffix('/') {
+                Some(inner) => (inner.trim_end(), true),
+                None => (inner, false),
+            };
+            let (name, arguments) = parse_hugo_arguments(inner);
+            if name == "highlight"
+                && let Some((close_start, close_end)) = find_hugo_closing_tag(rest, name)
+            {
+                let language = arguments
+                    .iter()
+                    .find(|(key, _)| key.is_none())
+                    .map_or("", |(_, value)| value.as_str());
+                let code = rest[..close_start].trim_matches('\n');
+                output.push_str(&format!("```{language}\n{code}\n```"));
+                rest = &rest[close_end..];
+                continue;
+            }
+            let block = closed.contains(name) && !self_closing;
+            output.push_str(&self.hugo_shortcode(label, name, arguments, block));
+        }
+        output.push_str(rest);
+        output
+    }
+
+    fn hugo_shortcode(
+        &mut self,
+        label: &str,
+        name: &str,
+        arguments: Vec<(Option<String>, String)>,
+        block: bool,
+    ) -> String {
+        let name = if name == "relref" { "ref" } else { name };
+        let (named, positional): (Vec<_>, Vec<_>) =
+            arguments.into_iter().partition(|(key, _)| key.is_some());
+        let mut named: Vec<(String, String)> = named
+            .into_iter()
+            .filter_map(|(key, value)| Some((key?, value)))
+            .collect();
+        let mut positional: Vec<String> = positional.into_iter().map(|(_, value)| value).collect();
+
+        if name == "ref" {
+            let target = positional
+                .first()
+                .cloned()
+                .or_else(|| {
+                    named
+                        .iter()
+                        .find(|(key, _)| key == "path")
+                        .map(|(_, value)| value.clone())
+                })
+                .unwrap_or_default();
+            return self.reference(&target);
+        }
+
+        let parameter_names: &[&str] = match name {
+            "youtube" | "vimeo" | "instagram" => &["id"],
+            "gist" => &["user", "id", "file"],
+            "tweet" | "x" => &["user", "id"],
+            _ => &[],
+        };
+        if positional.len() <= parameter_names.len() {
+            for (key, value) in parameter_names.iter().zip(positional.drain(..)) {
+                named.push((key.to_string(), value));
+            }
+        } else if positional.len() > 1 {
+            for (index, value) in positional.drain(..).enumerate() {
+                named.push((format!("_{index}"), value));
+            }
+            self.warn(format!(
+                "{label}: positional arguments of `{name}` were renamed _0, _1, …"
+            ));
+        }
+        if !BUILTIN_SHORTCODES.contains(&name) {
+            self.custom_shortcodes.insert(name.to_string());
+        }
+        shortcode_tag(name, positional.first().map(String::as_str), &named, block)
+    }
+
+    /// Rewrites Zola shortcodes and `@/` internal links, outside fenced
+    /// code blocks, in bamboo's syntax.
+    fn convert_zola(&mut self, label: &str, body: &str) -> String {
+        let mut open = Vec::new();
+        let output = split_code_blocks(body)
+            .into_iter()
+            .map(|(code, text)| {
+                if code {
+                    text.to_string()
+                } else {
+                    self.convert_zola_text(label, text, &mut open)
+                }
+            })
+            .collect();
+        for name in open {
+            self.warn(format!("{label}: shortcode `{name}` is never closed"));
+        }
+        output
+    }
+
+    fn convert_zola_text(&mut self, label: &str, text: &str, open: &mut Vec<String>) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = [rest.find("{{"), rest.find("{%"), rest.find("](@/")]
+            .into_iter()
+            .flatten()
+            .min()
+        {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            if let Some(link) = rest.strip_prefix("](@/")
+                && let Some(end) = link.find(')')
+            {
+                output.push_str(&format!("]({})", self.reference(&link[..end])));
+                rest = &link[end + 1..];
+                continue;
+            }
+            let close = if rest.starts_with("{{") { "}}" } else { "%}" };
+            let Some(end) = rest[2..].find(close).map(|end| end + 2) else {
+                output.push_str(&rest[..2]);
+                rest = &rest[2..];
+                continue;
+            };
+            let tag = &rest[..end + 2];
+            let inner = rest[2..end].trim();
+            rest = &rest[end + 2..];
+            let block = close == "%}";
+
+            if block && inner == "end" {
+                match open.pop() {
+                    Some(name) => output.push_str(&format!("{{{{% /{name} %}}}}")),
+                    None => output.push_str(tag),
+                }
+                continue;
+            }
+            match parse_zola_call(inner) {
+                Some((name, arguments)) => {
+                    if block {
+                        open.push(name.to_string());
+                    }
+                    output.push_str(&self.zola_shortcode(name, arguments, block));
+                }
+                None => {
+                    if inner.starts_with("/*") {
+                        self.warn(format!(
+                            "{label}: escaped shortcode `{tag}` has no bamboo equivalent and was left as is"
+                        ));
+                    }
+                    output.push_str(tag);
+                }
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+
+    fn zola_shortcode(
+        &mut self,
+        name: &str,
+        arguments: Vec<(String, String)>,
+        block: bool,
+    ) -> String {
+        let mut arguments = arguments;
+        if name == "gist"
+            && let Some(index) = arguments.iter().position(|(key, _)| key == "url")
+        {
+            let (_, url) = arguments.remove(index);
+            let mut segments = url.trim_end_matches('/').rsplit('/');
+            if let (Some(id), Some(user)) = (segments.next(), segments.next()) {
+                arguments.insert(0, ("id".to_string(), id.to_string()));
+                arguments.insert(0, ("user".to_string(), user.to_string()));
+            }
+        }
+        if !BUILTIN_SHORTCODES.contains(&name) {
+            self.custom_shortcodes.insert(name.to_string());
+        }
+        shortcode_tag(name, None, &arguments, block)
+    }
+
+    /// A `ref` shortcode for a link to `target`, a content path as the old
+    /// site wrote it, with its `#fragment` kept after the shortcode.
+    fn reference(&self, target: &str) -> String {
+        let (path, fragment) = match target.split_once('#') {
+            Some((path, fragment)) => (path, format!("#{fragment}")),
+            None => (target, String::new()),
+        };
+        let path = path.trim_matches('/');
+        let path = self.paths.get(path).map_or(path, String::as_str);
+        shortcode_tag("ref", Some(path), &[], false) + &fragment
+    }
+
+    /// Builds `bamboo.toml` from the source site's config.
+    fn site_config(&mut self, config_path: &Path) -> Result<toml::Table> {
+        let text = fs::read_to_string(config_path).io_context("read", config_path)?;
+        let format = data_format(config_path);
+        let Value::Object(mut config) = parse_data(&text, format, config_path)? else {
+            return Ok(toml::Table::new());
+        };
+        let mut site = Map::new();
+        let mut taxonomies: Vec<(String, String)> = Vec::new();
+        let ignored: &[&str];
+
+        match self.generator {
+            Generator::Hugo => {
+                config = config
+                    .into_iter()
+                    .map(|(key, value)| (key.to_lowercase(), value))
+                    .collect();
+                let mut params = match config.remove("params") {
+                    Some(Value::Object(params)) => params,
+                    _ => Map::new(),
+                };
+                if let Some(dir) = config_path.parent()
+                    && dir.ends_with("config/_default")
+                {
+                    for name in ["params.toml", "params.yaml", "params.yml", "params.json"] {
+                        let path = dir.join(name);
+                        if let Ok(text) = fs::read_to_string(&path)
+                            && let Value::Object(more) =
+                                parse_data(&text, data_format(&path), &path)?
+                        {
+                            params.extend(more);
+                        }
+                    }
+                }
+                move_key(&mut config, "title", &mut site, "title");
+                move_key(&mut config, "baseurl", &mut site, "base_url");
+                move_key(&mut config, "languagecode", &mut site, "language");
+                move_key(&mut config, "defaultcontentlanguage", &mut site, "language");
+                if let Some(description) = params.get("description") {
+                    site.insert("description".to_string(), description.clone());
+                }
+                let author = config
+                    .remove("author")
+                    .or_else(|| params.get("author").cloned());
+                if let Some(author) = author.as_ref().and_then(author_name) {
+                    site.insert("author".to_string(), Value::String(author));
+                }
+                move_key(&mut config, "paginate", &mut site, "posts_per_page");
+                if let Some(Value::Object(mut pagination)) = config.remove("pagination") {
+                    move_key(&mut pagination, "pagerSize", &mut site, "posts_per_page");
+                    move_key(&mut pagination, "pagersize", &mut site, "posts_per_page");
+                }
+                move_key(&mut config, "builddrafts", &mut site, "drafts");
+                move_key(&mut config, "buildfuture", &mut site, "future");
+                if let Some(Value::Object(declared)) = config.remove("taxonomies") {
+                    for (singular, plural) in declared {
+                        if let Some(plural) = plural.as_str() {
+                            taxonomies.push((plural.to_string(), singular));
+                        }
+                    }
+                } else {
+                    taxonomies = default_taxonomies();
+                }
+                if config.remove("permalinks").is_some() {
+                    self.warn(
+                        "[permalinks] was not migrated; posts are served from /posts/<slug>/ with redirects from their default Hugo URLs".to_string(),
+                    );
+                }
+                if !params.is_empty() {
+                    site.insert("extra".to_string(), Value::Object(params));
+                }
+                ignored = &["enablerobotstxt", "relativeurls", "canonifyurls"];
+            }
+            Generator::Zola => {
+                for key in ["title", "base_url", "description", "author"] {
+                    move_key(&mut config, key, &mut site, key);
+                }
+                move_key(&mut config, "default_language", &mut site, "language");
+                move_key(&mut config, "minify_html", &mut site, "minify");
+                move_key(&mut config, "extra", &mut site, "extra");
+                let highlight_theme = config.remove("highlight_theme").or_else(|| {
+                    let Some(Value::Object(markdown)) = config.get_mut("markdown") else {
+                        return None;
+                    };
+                    markdown.remove("highlight_theme")
+                });
+                if let Some(theme) = highlight_theme
+                    && theme.as_str() != Some("css")
+                {
+                    site.insert("syntax_theme".to_string(), theme);
+                }
+                if let Some(Value::Object(markdown)) = config.get("markdown")
+                    && markdown.keys().all(|key| key.starts_with("highlight"))
+                {
+                    config.remove("markdown");
+                }
+                match config.remove("taxonomies") {
+                    Some(Value::Array(declared)) => {
+                        for taxonomy in declared {
+                            if let Some(name) = taxonomy.get("name").and_then(Value::as_str) {
+                                taxonomies.push((name.to_string(), singular(name)));
+                            }
+                        }
+                    }
+                    _ => taxonomies = default_taxonomies(),
+                }
+                ignored = &[
+                    "compile_sass",
+                    "build_search_index",
+                    "generate_feed",
+                    "generate_feeds",
+                    "feed_filename",
+                    "feed_filenames",
+                    "generate_sitemap",
+                    "generate_robots_txt",
+                ];
+            }
+        }
+
+        if let Some(theme) = config.remove("theme") {
+            self.warn(format!(
+                "theme `{}` was not migrated; the site uses bamboo's default theme",
+                theme.as_str().unwrap_or_default()
+            ));
+        }
+        let leftover: Vec<&str> = config
+            .keys()
+            .map(String::as_str)
+            .filter(|key| !ignored.contains(key))
+            .collect();
+        if !leftover.is_empty() {
+            self.warn(format!(
+                "{} settings not migrated: {}",
+                self.generator.name(),
+                leftover.join(", ")
+            ));
+        }
+
+        taxonomies.sort();
+        if taxonomies != default_taxonomies() {
+            let declared: Map<String, Value> = taxonomies
+                .into_iter()
+                .map(|(plural, singular)| {
+                    let definition =
+                        Map::from_iter([("singular".to_string(), Value::String(singular))]);
+                    (plural, Value::Object(definition))
+                })
+                .collect();
+            site.insert("taxonomies".to_string(), Value::Object(declared));
+        }
+        Ok(to_toml_table(site))
+    }
+
+    /// Copies every file under `from` into `to`, keeping files already
+    /// there.
+    fn copy_tree(&mut self, from: &Path, to: &Path) -> Result<()> {
+        if !from.is_dir() {
+            return Ok(());
+        }
+        for entry in WalkDir::new(from).sort_by_file_name() {
+            let entry = entry.map_err(|error| BambooError::WalkDir {
+                path: from.to_path_buf(),
+                message: error.to_string(),
+            })?;
+            if entry.file_type().is_file()
+                && let Ok(relative) = entry.path().strip_prefix(from)
+            {
+                self.copy_file(entry.path(), &to.join(relative))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn copy_file(&mut self, from: &Path, to: &Path) -> Result<()> {
+        if to.exists() {
+            let from = from.strip_prefix(self.source_dir).unwrap_or(from);
+            let to = to.strip_prefix(self.output_dir).unwrap_or(to);
+            self.warn(format!(
+                "{} was not copied because {} already exists",
+                from.display(),
+                to.display()
+            ));
+            return Ok(());
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).io_context("create_dir_all", parent)?;
+        }
+        fs::copy(from, to).io_context("copy", from)?;
+        self.report.files += 1;
+        Ok(())
+    }
+}
+
+/// Whether `dir` is `ancestor` or inside it; both relative, `/`-separated.
+fn in_dir(dir: &str, ancestor: &str) -> bool {
+    dir.strip_prefix(ancestor)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// `file` with a `.md` extension.
+fn markdown_path(file: &str) -> String {
+    match file.strip_suffix(".markdown") {
+        Some(stem) => format!("{stem}.md"),
+        None => file.to_string(),
+    }
+}
+
+/// Bamboo's taxonomies when none are declared, as `(plural, singular)`.
+fn default_taxonomies() -> Vec<(String, String)> {
+    vec![
+        ("categories".to_string(), "category".to_string()),
+        ("tags".to_string(), "tag".to_string()),
+    ]
+}
+
+/// A guess at the singular of a taxonomy name.
+fn singular(plural: &str) -> String {
+    if let Some(stem) = plural.strip_suffix("ies") {
+        format!("{stem}y")
+    } else if let Some(stem) = plural.strip_suffix('s')
+        && !plural.ends_with("ss")
+    {
+        stem.to_string()
+    } else {
+        plural.to_string()
+    }
+}
+
+/// An author's name: a string, or the `name` of an author table.
+fn author_name(author: &Value) -> Option<String> {
+    match author {
+        Value::String(name) => Some(name.clone()),
+        Value::Object(author) => author.get("name")?.as_str().map(str::to_string),
+        _ => None,
+    }
+}
+
+/// A string or list of strings as a list.
+fn string_list(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(item) => vec![item.clone()],
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Moves `from[key]` to `to[new_key]`, unless `to` already has one.
+fn move_key(from: &mut Map<String, Value>, key: &str, to: &mut Map<String, Value>, new_key: &str) {
+    if let Some(value) = from.remove(key) {
+        to.entry(new_key).or_insert(value);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DataFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+fn data_format(path: &Path) -> DataFormat {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml" | "yml") => DataFormat::Yaml,
+        Some("json") => DataFormat::Json,
+        _ => DataFormat::Toml,
+    }
+}
+
+fn parse_data(text: &str, format: DataFormat, path: &Path) -> Result<Value> {
+    match format {
+        DataFormat::Toml => toml::from_str(text).map_err(|error| BambooError::TomlParse {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        }),
+        DataFormat::Yaml => serde_yml::from_str(text).map_err(|error| BambooError::YamlParse {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        }),
+        DataFormat::Json => serde_json::from_str(text).map_err(|error| BambooError::JsonParse {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        }),
+    }
+}
+
+/// Splits a content file into its frontmatter format, frontmatter, and
+/// body: TOML between `+++` lines, YAML between `---` lines, or (Hugo
+/// only) a leading JSON object.
+fn split_frontmatter(text: &str) -> Option<(DataFormat, &str, &str)> {
+    let text = text.trim_start_matches('\u{feff}');
+    for (delimiter, format) in [("+++", DataFormat::Toml), ("---", DataFormat::Yaml)] {
+        let Some(rest) = text
+            .strip_prefix(delimiter)
+            .and_then(|rest| rest.trim_start_matches([' ', '\t']).strip_prefix('\n'))
+        else {
+            continue;
+        };
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            if line.trim_end() == delimiter {
+                return Some((format, &rest[..offset], &rest[offset + line.len()..]));
+            }
+            offset += line.len();
+        }
+        return None;
+    }
+    if text.starts_with('{') {
+        let mut values = serde_json::Deserializer::from_str(text).into_iter::<Value>();
+        values.next()?.ok()?;
+        let end = values.byte_offset();
+        return Some((DataFormat::Json, &text[..end], &text[end..]));
+    }
+    None
+}
+
+/// Splits markdown into prose and fenced code blocks, as `(is_code, text)`
+/// pairs, so shortcode syntax in code samples is left alone.
+fn split_code_blocks(body: &str) -> Vec<(bool, &str)> {
+    let mut segments = Vec::new();
+    let mut fence: Option<(char, usize)> = None;
+    let mut start = 0;
+    let mut position = 0;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let marker = ['`', '~'].into_iter().find_map(|character| {
+            let count = trimmed.chars().take_while(|&c| c == character).count();
+            (count >= 3).then_some((character, count))
+        });
+        match (fence, marker) {
+            (None, Some(marker)) => {
+                if start < position {
+                    segments.push((false, &body[start..position]));
+                }
+                start = position;
+                fence = Some(marker);
+            }
+            (Some((character, count)), Some((closing, closing_count)))
+                if character == closing
+                    && closing_count >= count
+                    && trimmed.trim_end().chars().all(|c| c == character) =>
+            {
+                let end = position + line.len();
+                segments.push((true, &body[start..end]));
+                start = end;
+                fence = None;
+            }
+            _ => {}
+        }
+        position += line.len();
+    }
+    if start < body.len() {
+        segments.push((fence.is_some(), &body[start..]));
+    }
+    segments
+}
+
+/// The next `{{< … >}}` or `{{% … %}}` tag in `text`: where it starts,
+/// where it ends, and what's between the delimiters, trimmed.
+fn next_hugo_tag(text: &str) -> Option<(usize, usize, &str)> {
+    let start = [text.find("{{<"), text.find("{{%")]
+        .into_iter()
+        .flatten()
+        .min()?;
+    let close = if text[start..].starts_with("{{<") {
+        ">}}"
+    } else {
+        "%}}"
+    };
+    let inner_start = start + 3;
+    let inner_end = inner_start + text[inner_start..].find(close)?;
+    Some((
+        start,
+        inner_end + close.len(),
+        text[inner_start..inner_end].trim(),
+    ))
+}
+
+/// The span of the first `{{< /name >}}` (or `{{% /name %}}`) in `text`.
+fn find_hugo_closing_tag(text: &str, name: &str) -> Option<(usize, usize)> {
+    let mut offset = 0;
+    while let Some((start, end, inner)) = next_hugo_tag(&text[offset..]) {
+        if inner
+            .strip_prefix('/')
+            .is_some_and(|closing| closing.trim() == name)
+        {
+            return Some((offset + start, offset + end));
+        }
+        offset += end;
+    }
+    None
+}
+
+/// A Hugo shortcode's name and arguments, `(Some(key), value)` for named
+/// ones and `(None, value)` for positional ones.
+fn parse_hugo_arguments(inner: &str) -> (&str, Vec<(Option<String>, String)>) {
+    let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+    let name = &inner[..name_end];
+    let mut arguments = Vec::new();
+    let mut rest = inner[name_end..].trim_start();
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(rest.len());
+        let key = match rest[key_end..].strip_prefix('=') {
+            Some(value) if key_end > 0 => {
+                let key = rest[..key_end].to_string();
+                rest = value;
+                Some(key)
+            }
+            _ => None,
+        };
+        let (value, remaining) = read_value(rest, |c| c.is_whitespace());
+        arguments.push((key, value));
+        rest = remaining.trim_start();
+    }
+    (name, arguments)
+}
+
+/// A Zola shortcode call, `name(key=value, …)`, as its name and
+/// arguments. `None` if `inner` isn't one.
+fn parse_zola_call(inner: &str) -> Option<(&str, Vec<(String, String)>)> {
+    let name_end = inner.find(|c: char| !(c.is_alphanumeric() || c == '_'))?;
+    let name = &inner[..name_end];
+    let mut rest = inner[name_end..]
+        .trim()
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .trim();
+    if name.is_empty() {
+        return None;
+    }
+    let mut arguments = Vec::new();
+    while !rest.is_empty() {
+        let key_end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_'))?;
+        let key = &rest[..key_end];
+        if key.is_empty() {
+            return None;
+        }
+        rest = rest[key_end..].trim_start().strip_prefix('=')?.trim_start();
+        let (value, remaining) = read_value(rest, |c| c == ',' || c.is_whitespace());
+        arguments.push((key.to_string(), value));
+        rest = remaining.trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+    Some((name, arguments))
+}
+
+/// Reads one argument value off the front of `input`: a `"`, `'`, or
+/// `` ` `` quoted string, a `[…]` list (kept as written), or a bare word
+/// ending where `ends` matches. Returns the value and the rest.
+fn read_value(input: &str, ends: impl Fn(char) -> bool) -> (String, &str) {
+    match input.chars().next() {
+        Some(quote @ ('"' | '\'' | '`')) => {
+            let mut value = String::new();
+            let mut escaped = false;
+            for (index, character) in input.char_indices().skip(1) {
+                if escaped {
+                    value.push(character);
+                    escaped = false;
+                } else if character == '\\' && quote != '`' {
+                    escaped = true;
+                } else if character == quote {
+                    return (value, &input[index + 1..]);
+                } else {
+                    value.push(character);
+                }
+            }
+            (value, "")
+        }
+        Some('[') => {
+            let end = input.find(']').map_or(input.len(), |end| end + 1);
+            (input[..end].to_string(), &input[end..])
+        }
+        _ => {
+            let end = input.find(ends).unwrap_or(input.len());
+            (input[..end].to_string(), &input[end..])
+        }
+    }
+}
+
+/// A bamboo shortcode tag: `{{< name … >}}`, or `{{% name … %}}` opening
+/// a shortcode with a body.
+fn shortcode_tag(
+    name: &str,
+    positional: Option<&str>,
+    arguments: &[(String, String)],
+    block: bool,
+) -> String {
+    let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut tag = name.to_string();
+    if let Some(value) = positional {
+        tag.push_str(&format!(" \"{}\"", escape(value)));
+    }
+    for (key, value) in arguments {
+        tag.push_str(&format!(" {key}=\"{}\"", escape(value)));
+    }
+    if block {
+        format!("{{{{% {tag} %}}}}")
+    } else {
+        format!("{{{{< {tag} >}}}}")
+    }
+}
+
+/// Converts a JSON map to TOML, dropping nulls, which TOML can't express.
+fn to_toml_table(map: Map<String, Value>) -> toml::Table {
+    map.into_iter()
+        .filter_map(|(key, value)| Some((key, to_toml(value)?)))
+        .collect()
+}
+
+fn to_toml(value: Value) -> Option<toml::Value> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(flag) => toml::Value::Boolean(flag),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => toml::Value::Integer(integer),
+            None => toml::Value::Float(number.as_f64()?),
+        },
+        Value::String(text) => toml::Value::String(text),
+        Value::Array(items) => toml::Value::Array(items.into_iter().filter_map(to_toml).collect()),
+        Value::Object(map) => {
+            if let Some(Value::String(datetime)) = map.get(crate::types::TOML_DATETIME_KEY)
+                && let Ok(datetime) = datetime.parse()
+            {
+                return Some(toml::Value::Datetime(datetime));
+            }
+            toml::Value::Table(to_toml_table(map))
+        }
+    })
+}
+
+/// Writes `table` as TOML with the keys in `order` first, then the other
+/// keys alphabetically, then tables.
+fn write_toml(table: &toml::Table, order: &[&str]) -> String {
+    let mut keys: Vec<&String> = table.keys().filter(|key| !table[*key].is_table()).collect();
+    keys.sort_by_key(|key| {
+        (
+            order
+                .iter()
+                .position(|name| name == key)
+                .unwrap_or(order.len()),
+            key.as_str(),
+        )
+    });
+    let mut output = String::new();
+    for key in keys {
+        let bare = !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        let name = if bare {
+            key.clone()
+        } else {
+            toml::Value::String(key.clone()).to_string()
+        };
+        // A bare datetime displays as the table serde sees it.
+        let value = match &table[key] {
+            toml::Value::Datetime(datetime) => datetime.to_string(),
+            value => value.to_string(),
+        };
+        output.push_str(&format!("{name} = {value}\n"));
+    }
+    let tables: toml::Table = table
+        .iter()
+        .filter(|(_, value)| value.is_table())
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if !tables.is_empty() {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&toml::to_string(&tables).unwrap_or_default());
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SiteBuilder;
+
+    fn write(root: &Path, file: &str, contents: &str) {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn read(root: &Path, file: &str) -> String {
+        fs::read_to_string(root.join(file)).unwrap()
+    }
+
+    #[test]
+    fn test_migrate_hugo() {
+        let source = tempfile::TempDir::new().unwrap();
+        let source = source.path();
+        write(
+            source,
+            "hugo.toml",
+            r#"baseURL = "https://example.com/"
+title = "Old Site"
+languageCode = "en-us"
+theme = "ananke"
+paginate = 5
+enableEmoji = true
+
+[params]
+description = "Notes"
+author = { name = "Sam" }
+
+[taxonomies]
+tag = "tags"
+series = "series"
+"#,
+        );
+        write(
+            source,
+            "content/_index.md",
+            "---\ntitle: Home\n---\nWelcome.\n",
+        );
+        write(
+            source,
+            "content/about.md",
+            "+++\ntitle = \"About\"\nslug = \"about-me\"\n+++\nAbout {{< ref \"blog/first/index.md\" >}}.\n",
+        );
+        write(
+            source,
+            "content/blog/_index.md",
+            "+++\ntitle = \"Blog\"\n+++\n",
+        );
+        write(
+            source,
+            "content/blog/first/index.md",
+            "+++\ntitle = \"First\"\ndate = 2024-01-15T10:00:00Z\nlastmod = 2024-02-01\ntags = [\"rust\"]\nseries = [\"intro\"]\n+++\n![Cover](cover.png)\n\n{{< youtube dQw4w9WgXcQ >}}\n\n{{< highlight rust >}}\nfn main() {}\n{{< /highlight >}}\n\n```go\n{{< not-a-shortcode >}}\n```\n",
+        );
+        fs::write(source.join("content/blog/first/cover.png"), b"png").unwrap();
+        write(
+            source,
+            "content/blog/second.md",
+            "---\ntitle: Second\ndate: 2024-03-01\naliases: [old-second]\nurl: null\n---\nSee [first]({{< relref \"blog/first\" >}}#intro).\n\n{{% note type=\"warning\" %}}\nCareful.\n{{% /note %}}\n",
+        );
+        write(source, "static/css/site.css", "body {}");
+        fs::create_dir_all(source.join("layouts")).unwrap();
+        let output = tempfile::TempDir::new().unwrap();
+        let output = output.path();
+
+        let report = migrate(Generator::Hugo, source, output, None).unwrap();
+
+        assert_eq!((report.posts, report.pages, report.files), (2, 2, 2));
+        assert_eq!(
+            read(output, "bamboo.toml"),
+            r#"title = "Old Site"
+base_url = "https://example.com/"
+description = "Notes"
+language = "en-us"
+author = "Sam"
+posts_per_page = 5
+
+[extra]
+description = "Notes"
+
+[extra.author]
+name = "Sam"
+
+[taxonomies.series]
+singular = "series"
+
+[taxonomies.tags]
+singular = "tag"
+"#
+        );
+        assert_eq!(
+            read(output, "content/posts/first.md"),
+            r#"+++
+title = "First"
+date = 2024-01-15T10:00:00Z
+updated = 2024-02-01
+redirect_from = ["/blog/first/"]
+tags = ["rust"]
+series = ["intro"]
++++
+
+![Cover](cover.png)
+
+{{< youtube id="dQw4w9WgXcQ" >}}
+
+```rust
+fn main() {}
+```
+
+```go
+{{< not-a-shortcode >}}
+```
+"#
+        );
+        assert_eq!(
+            read(output, "content/posts/second.md"),
+            "+++\ntitle = \"Second\"\ndate = \"2024-03-01\"\nredirect_from = [\"/old-second\", \"/blog/second/\"]\n+++\n\nSee [first]({{< ref \"posts/first.md\" >}}#intro).\n\n{{% note type=\"warning\" %}}\nCareful.\n{{% /note %}}\n"
+        );
+        assert_eq!(
+            read(output, "content/about.md"),
+            "+++\ntitle = \"About\"\npermalink = \"/about-me/\"\n+++\n\nAbout {{< ref \"posts/first.md\" >}}.\n"
+        );
+        assert!(read(output, "content/_index.md").contains("title = \"Home\""));
+        assert!(!output.join("content/posts/_index.md").exists());
+        assert_eq!(read(output, "static/posts/first/cover.png"), "png");
+        assert_eq!(read(output, "static/css/site.css"), "body {}");
+        let warnings = report.warnings.join("\n");
+        assert!(
+            warnings.contains("content/blog/_index.md was dropped"),
+            "{warnings}"
+        );
+        assert!(warnings.contains("layouts/ was not migrated"), "{warnings}");
+        assert!(warnings.contains("theme `ananke`"), "{warnings}");
+        assert!(
+            warnings.contains("Hugo settings not migrated: enableemoji"),
+            "{warnings}"
+        );
+
+        let site = SiteBuilder::new(output).build().unwrap();
+        assert_eq!(site.posts.len(), 2);
+        let first = site
+            .posts
+            .iter()
+            .find(|post| post.content.title == "First")
+            .unwrap();
+        assert_eq!(first.content.url, "/posts/first/");
+        assert!(first.content.html.contains("youtube.com/embed/dQw4w9WgXcQ"));
+    }
+
+    #[test]
+    fn test_migrate_zola() {
+        let source = tempfile::TempDir::new().unwrap();
+        let source = source.path();
+        write(
+            source,
+            "config.toml",
+            r#"base_url = "https://example.com"
+title = "Zola Site"
+default_language = "en"
+compile_sass = true
+taxonomies = [{ name = "tags" }, { name = "authors" }]
+
+[markdown]
+highlight_code = true
+highlight_theme = "base16-ocean-dark"
+
+[extra]
+github = "someone"
+"#,
+        );
+        write(
+            source,
+            "content/journal/_index.md",
+            "+++\ntitle = \"Journal\"\nsort_by = \"date\"\n+++\n",
+        );
+        write(
+            source,
+            "content/journal/2024-01-15-hello-world.md",
+            "+++\ntitle = \"Hello\"\ntemplate = \"special.html\"\n\n[taxonomies]\ntags = [\"zola\"]\nauthors = [\"sam\"]\n+++\n{{ youtube(id=\"abc\", autoplay=true) }}\n\n{% note(type=\"warning\") %}\nRead [this](@/about.md#why).\n{% end %}\n\n{{ gist(url=\"https://gist.github.com/sam/123\") }}\n",
+        );
+        write(
+            source,
+            "content/about.md",
+            "+++\ntitle = \"About\"\npath = \"me\"\n+++\nHi {{ custom() }}.\n",
+        );
+        write(source, "sass/main.scss", "$c: red; body { color: $c; }");
+        let output = tempfile::TempDir::new().unwrap();
+        let output = output.path();
+
+        let report = migrate(Generator::Zola, source, output, None).unwrap();
+
+        assert_eq!((report.posts, report.pages, report.files), (1, 1, 1));
+        assert_eq!(
+            read(output, "bamboo.toml"),
+            r#"title = "Zola Site"
+base_url = "https://example.com"
+language = "en"
+syntax_theme = "base16-ocean-dark"
+
+[extra]
+github = "someone"
+
+[taxonomies.authors]
+singular = "author"
+
+[taxonomies.tags]
+singular = "tag"
+"#
+        );
+        assert_eq!(
+            read(output, "content/posts/2024-01-15-hello-world.md"),
+            r#"+++
+title = "Hello"
+redirect_from = ["/journal/hello-world/"]
+tags = ["zola"]
+authors = ["sam"]
++++
+
+{{< youtube id="abc" autoplay="true" >}}
+
+{{% note type="warning" %}}
+Read [this]({{< ref "about.md" >}}#why).
+{{% /note %}}
+
+{{< gist user="sam" id="123" >}}
+"#
+        );
+        assert!(read(output, "content/about.md").contains("permalink = \"me\""));
+        assert_eq!(
+            read(output, "static/main.scss"),
+            "$c: red; body { color: $c; }"
+        );
+        let warnings = report.warnings.join("\n");
+        assert!(
+            warnings.contains("template `special.html` was dropped"),
+            "{warnings}"
+        );
+        assert!(
+            warnings.contains("templates/shortcodes/<name>.html: custom"),
+            "{warnings}"
+        );
+        assert!(!warnings.contains("settings not migrated"), "{warnings}");
+    }
+
+    #[test]
+    fn test_migrate_refuses_non_empty_output() {
+        let source = tempfile::TempDir::new().unwrap();
+        let output = tempfile::TempDir::new().unwrap();
+        let error = migrate(Generator::Zola, source.path(), output.path(), None).unwrap_err();
+        assert!(error.to_string().contains("not a Zola site"), "{error}");
+
+        write(source.path(), "config.toml", "title = \"T\"\n");
+        write(output.path(), "bamboo.toml", "title = \"T\"\n");
+        let error = migrate(Generator::Zola, source.path(), output.path(), None).unwrap_err();
+        assert!(error.to_string().contains("is not empty"), "{error}");
+    }
+
+    #[test]
+    fn test_parse_shortcode_arguments() {
+        let (name, arguments) =
+            parse_hugo_arguments(r#"gist spf13 7896402 file="img \"x\".html" raw=`a b`"#);
+        assert_eq!(name, "gist");
+        assert_eq!(
+            arguments,
+            [
+                (None, "spf13".to_string()),
+                (None, "7896402".to_string()),
+                (Some("file".to_string()), "img \"x\".html".to_string()),
+                (Some("raw".to_string()), "a b".to_string()),
+            ]
+        );
+
+        let (name, arguments) =
+            parse_zola_call("figure(src='a.png', sizes=[1, 2], lazy=true)").unwrap();
+        assert_eq!(name, "figure");
+        assert_eq!(
+            arguments,
+            [
+                ("src".to_string(), "a.png".to_string()),
+                ("sizes".to_string(), "[1, 2]".to_string()),
+                ("lazy".to_string(), "true".to_string()),
+            ]
+        );
+        assert_eq!(parse_zola_call("page.title"), None);
+    }
+}
@@ -0,0 +1,349 @@
+//! `bamboo deploy netlify`: uploading the output to
+//! [Netlify](https://www.netlify.com) through its API, without the Netlify
+//! CLI.
+//!
+//! ```toml
+//! [deploy.netlify]
+//! site_id = "3970e0fe-8564-4903-9a55-c5f8de49fb8b"
+//! ```
+//!
+//! The output directory is zipped and sent as a single deploy. Deploys are
+//! drafts unless `production` is requested, so every run gets its own
+//! preview URL and the live site only changes on purpose. The personal
+//! access token is read from `NETLIFY_AUTH_TOKEN` (or the variable named by
+//! `token_env`) and never from `bamboo.toml`, and `NETLIFY_SITE_ID` can
+//! stand in for `site_id`, matching the Netlify CLI.
+//!
+//! Requests go through [`RemoteClient::send`], which keeps the token out of
+//! the process list.
+
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{BambooError, IoContext, Result};
+use crate::remote::{RemoteClient, Request};
+
+const TARGET: &str = "netlify";
+
+/// Netlify API root.
+pub const API_URL: &str = "https://api.netlify.com/api/v1";
+
+/// Environment variable consulted when `site_id` isn't set.
+pub const SITE_ID_ENV_VAR: &str = "NETLIFY_SITE_ID";
+
+/// How often, and how many times, a deploy is checked while Netlify
+/// processes it.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const POLL_ATTEMPTS: u32 = 90;
+
+/// The `[deploy.netlify]` table in `bamboo.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetlifyConfig {
+    /// API ID of the site, from its settings page. Falls back to
+    /// `NETLIFY_SITE_ID`.
+    pub site_id: Option<String>,
+    /// Environment variable holding the access token. Defaults to
+    /// `NETLIFY_AUTH_TOKEN`.
+    pub token_env: String,
+}
+
+impl Default for NetlifyConfig {
+    fn default() -> Self {
+        Self {
+            site_id: None,
+            token_env: "NETLIFY_AUTH_TOKEN".to_string(),
+        }
+    }
+}
+
+impl NetlifyConfig {
+    /// The site ID and access token to deploy with, failing if either is
+    /// missing.
+    pub fn credentials(&self) -> Result<(String, String)> {
+        let missing = |message: String| BambooError::Deploy {
+            target: TARGET,
+            message,
+        };
+        let site_id = self
+            .site_id
+            .clone()
+            .or_else(|| std::env::var(SITE_ID_ENV_VAR).ok())
+            .filter(|site_id| !site_id.is_empty())
+            .ok_or_else(|| {
+                missing(format!(
+                    "no site; set `site_id` under [deploy.netlify] or {SITE_ID_ENV_VAR}"
+                ))
+            })?;
+        let token = std::env::var(&self.token_env)
+            .ok()
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| missing(format!("{} is not set", self.token_env)))?;
+        Ok((site_id, token))
+    }
+}
+
+/// A deploy as reported by Netlify.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct NetlifyDeploy {
+    /// Deploy ID.
+    pub id: String,
+    /// Processing state: `uploaded`, `processing`, `ready`, or `error`.
+    #[serde(default)]
+    pub state: String,
+    /// URL of this deploy alone: the preview of a draft.
+    #[serde(default)]
+    pub deploy_ssl_url: String,
+    /// URL of the live site.
+    #[serde(default)]
+    pub ssl_url: String,
+    /// Why processing failed, when `state` is `error`.
+    #[serde(default)]
+    pub error_message: Option<String>,
+}
+
+/// Zips `output_dir`, uploads it as a deploy of the configured site, and
+/// waits for Netlify to finish processing it, failing if it's still
+/// processing after three minutes. Without `production` the deploy is a
+/// draft that doesn't replace the live site.
+pub fn deploy(
+    client: &RemoteClient,
+    output_dir: &Path,
+    config: &NetlifyConfig,
+    production: bool,
+) -> Result<NetlifyDeploy> {
+    let deploy_error = |message: String| BambooError::Deploy {
+        target: TARGET,
+        message,
+    };
+    let (site_id, token) = config.credentials()?;
+    let authorization = format!("Bearer {token}");
+
+    let archive = tempfile::Builder::new()
+        .prefix("bamboo-netlify-")
+        .suffix(".zip")
+        .tempfile()
+        .io_context("create", &std::env::temp_dir())?;
+    let mut writer = BufWriter::new(archive.as_file());
+    zip_dir(output_dir, &mut writer)?;
+    writer.flush().io_context("write", archive.path())?;
+    drop(writer);
+    let url = format!("{API_URL}/sites/{site_id}/deploys?draft={}", !production);
+    let response = client.send(
+        &Request::new("POST", &url)
+            .header("Authorization", &authorization)
+            .header("Content-Type", "application/zip")
+            .body(archive.path()),
+    )?;
+    let mut deploy = parse_deploy(&response.body)?;
+
+    let url = format!("{API_URL}/deploys/{}", deploy.id);
+    for _ in 0..POLL_ATTEMPTS {
+        match deploy.state.as_str() {
+            "ready" => return Ok(deploy),
+            "error" => {
+                return Err(deploy_error(format!(
+                    "deploy {} failed: {}",
+                    deploy.id,
+                    deploy.error_message.as_deref().unwrap_or("unknown error")
+                )));
+            }
+            _ => {}
+        }
+        std::thread::sleep(POLL_INTERVAL);
+        let response =
+            client.send(&Request::new("GET", &url).header("Authorization", &authorization))?;
+        deploy = parse_deploy(&response.body)?;
+    }
+    Err(deploy_error(format!(
+        "deploy {} is still {} after {} seconds; check the Netlify dashboard",
+        deploy.id,
+        deploy.state,
+        (POLL_INTERVAL * POLL_ATTEMPTS).as_secs()
+    )))
+}
+
+fn parse_deploy(body: &[u8]) -> Result<NetlifyDeploy> {
+    serde_json::from_slice(body).map_err(|error| BambooError::Deploy {
+        target: TARGET,
+        message: format!("unexpected response from Netlify: {error}"),
+    })
+}
+
+/// Writes a zip archive of every file under `dir` to `archive`, deflated,
+/// with `/`-separated paths relative to it. Files are compressed one at a
+/// time, so only the largest needs to fit in memory. Archives are limited
+/// to 65535 files and 4 GiB, and fail with [`BambooError::Deploy`] beyond
+/// that.
+pub fn zip_dir(dir: &Path, archive: &mut impl Write) -> Result<()> {
+    // 1980-01-01 00:00, the earliest date the format can hold, so the
+    // archive depends only on the files' contents.
+    const DOS_TIME: u16 = 0;
+    const DOS_DATE: u16 = (1 << 5) | 1;
+    const UTF8_NAMES: u16 = 1 << 11;
+    const DEFLATE: u16 = 8;
+
+    let mut written = 0;
+    let mut central = Vec::new();
+    let files = crate::verify::output_files(dir);
+    if files.len() > u16::MAX as usize {
+        return Err(BambooError::Deploy {
+            target: TARGET,
+            message: format!("{} files is more than a zip archive holds", files.len()),
+        });
+    }
+    for name in &files {
+        let path = dir.join(name);
+        let contents = fs::read(&path).io_context("read", &path)?;
+        let mut crc = flate2::Crc::new();
+        crc.update(&contents);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&contents).io_context("compress", &path)?;
+        let compressed = encoder.finish().io_context("compress", &path)?;
+        let offset = zip_u32(written, "the archive")?;
+
+        let mut common = Vec::new();
+        for field in [20, UTF8_NAMES, DEFLATE, DOS_TIME, DOS_DATE] {
+            common.extend_from_slice(&field.to_le_bytes());
+        }
+        let sizes = [
+            zip_u32(compressed.len(), name)?,
+            zip_u32(contents.len(), name)?,
+        ];
+        for field in [crc.sum(), sizes[0], sizes[1]] {
+            common.extend_from_slice(&field.to_le_bytes());
+        }
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        let mut local = 0x04034b50u32.to_le_bytes().to_vec();
+        local.extend_from_slice(&common);
+        local.extend_from_slice(name.as_bytes());
+        for part in [&local, &compressed] {
+            archive.write_all(part).io_context("write", &path)?;
+            written += part.len();
+        }
+
+        central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&common);
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = zip_u32(written, "the archive")?;
+    let central_size = zip_u32(central.len(), "the archive's directory")?;
+    central.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    central.extend_from_slice(&[0; 4]);
+    central.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    central.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    central.extend_from_slice(&central_size.to_le_bytes());
+    central.extend_from_slice(&central_offset.to_le_bytes());
+    central.extend_from_slice(&0u16.to_le_bytes());
+    archive.write_all(&central).io_context("write", dir)
+}
+
+/// `size` as a zip size or offset field, failing once `what` outgrows
+/// the 4 GiB those fields hold.
+fn zip_u32(size: usize, what: &str) -> Result<u32> {
+    u32::try_from(size).map_err(|_| BambooError::Deploy {
+        target: TARGET,
+        message: format!("{what} is larger than the 4 GiB a zip archive holds"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    fn u16_at(bytes: &[u8], offset: usize) -> usize {
+        u16::from_le_bytes([bytes[offset], bytes[offset + 1]]) as usize
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> usize {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+    }
+
+    #[test]
+    fn test_zip_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("posts")).unwrap();
+        fs::write(dir.path().join("index.html"), "<h1>Home</h1>".repeat(20)).unwrap();
+        fs::write(dir.path().join("posts/a.html"), "post").unwrap();
+
+        let mut archive = Vec::new();
+        zip_dir(dir.path(), &mut archive).unwrap();
+
+        let end = archive.len() - 22;
+        assert_eq!(u32_at(&archive, end), 0x06054b50);
+        assert_eq!(u16_at(&archive, end + 10), 2);
+        let central = u32_at(&archive, end + 16);
+        assert_eq!(u32_at(&archive, central), 0x02014b50);
+        assert_eq!(&archive[central + 46..central + 56], b"index.html");
+
+        let local = u32_at(&archive, central + 42);
+        assert_eq!(u32_at(&archive, local), 0x04034b50);
+        let compressed = u32_at(&archive, local + 18);
+        let name_len = u16_at(&archive, local + 26);
+        let data = local + 30 + name_len;
+        let mut contents = String::new();
+        DeflateDecoder::new(&archive[data..data + compressed])
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "<h1>Home</h1>".repeat(20));
+        let mut crc = flate2::Crc::new();
+        crc.update(contents.as_bytes());
+        assert_eq!(u32_at(&archive, local + 14), crc.sum() as usize);
+
+        let mut again = Vec::new();
+        zip_dir(dir.path(), &mut again).unwrap();
+        assert_eq!(again, archive);
+    }
+
+    #[test]
+    fn test_zip_u32_rejects_sizes_over_4_gib() {
+        assert_eq!(zip_u32(u32::MAX as usize, "a.bin").unwrap(), u32::MAX);
+        let error = zip_u32(u32::MAX as usize + 1, "a.bin").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Deploy to netlify failed: a.bin is larger than the 4 GiB a zip archive holds"
+        );
+    }
+
+    #[test]
+    fn test_deploy_requires_site_and_token() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let client = RemoteClient::default().offline(true);
+        let config = NetlifyConfig {
+            site_id: Some("site".to_string()),
+            token_env: "BAMBOO_TEST_NETLIFY_TOKEN_UNSET".to_string(),
+        };
+        let error = deploy(&client, dir.path(), &config, false).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("BAMBOO_TEST_NETLIFY_TOKEN_UNSET is not set"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_parse_deploy() {
+        let deploy = parse_deploy(
+            br#"{"id": "d1", "state": "ready", "deploy_ssl_url": "https://d1--site.netlify.app", "ssl_url": "https://site.netlify.app", "required": []}"#,
+        )
+        .unwrap();
+        assert_eq!(deploy.id, "d1");
+        assert_eq!(deploy.deploy_ssl_url, "https://d1--site.netlify.app");
+        assert_eq!(deploy.error_message, None);
+    }
+}
@@ -0,0 +1,207 @@
+//! Email newsletter export. With a `[newsletter]` table in `bamboo.toml`,
+//! the build renders the latest posts into `newsletter.html`, an email-safe
+//! page (table layout, inline styles, absolute links), and
+//! `newsletter.txt`, its plain-text twin, ready to paste into a
+//! mailing-list tool.
+//!
+//! Both render through theme templates of the same names, falling back to
+//! built-in ones. Templates see `site` and `newsletter`, with `title`,
+//! `url`, `full_content`, and `posts`; each post has `title`, `url`,
+//! `date`, `summary`, `content` (HTML), and `text` (plain text).
+
+use crate::diagnostics::render_template;
+use crate::error::Result;
+use crate::search::strip_html_tags;
+use crate::sink::RenderSink;
+use crate::theme::site_metadata;
+use crate::types::Site;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tera::{Context, Tera};
+
+/// Template and output name of the HTML newsletter.
+pub const NEWSLETTER_HTML: &str = "newsletter.html";
+/// Template and output name of the plain-text newsletter.
+pub const NEWSLETTER_TXT: &str = "newsletter.txt";
+
+/// `[newsletter]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NewsletterConfig {
+    /// Number of newest posts to include. Defaults to 5.
+    #[serde(default = "default_posts")]
+    pub posts: usize,
+    /// Include each post's full body instead of its excerpt. Defaults to
+    /// `false`.
+    #[serde(default)]
+    pub full_content: bool,
+    /// Subject-style title. Defaults to the site title.
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+fn default_posts() -> usize {
+    5
+}
+
+impl Default for NewsletterConfig {
+    fn default() -> Self {
+        Self {
+            posts: default_posts(),
+            full_content: false,
+            title: None,
+        }
+    }
+}
+
+/// The `newsletter` template variable.
+#[derive(Debug, Clone, Serialize)]
+pub struct Newsletter {
+    /// Configured title, or the site title.
+    pub title: String,
+    /// Absolute URL of the site.
+    pub url: String,
+    /// Whether templates should show `content` rather than `summary`.
+    pub full_content: bool,
+    /// Newest posts first.
+    pub posts: Vec<NewsletterPost>,
+}
+
+/// One post in [`Newsletter::posts`].
+#[derive(Debug, Clone, Serialize)]
+pub struct NewsletterPost {
+    /// Post title.
+    pub title: String,
+    /// Absolute URL of the post.
+    pub url: String,
+    /// Publication date.
+    pub date: DateTime<Utc>,
+    /// Excerpt as plain text.
+    pub summary: String,
+    /// Rendered body with site-relative links and images made absolute.
+    pub content: String,
+    /// Rendered body as plain text.
+    pub text: String,
+}
+
+/// Rewrites `href="/..."` and `src="/..."` in `html` to absolute URLs under
+/// `base_url`, since an email has no site to resolve them against.
+pub fn absolutize_links(html: &str, base_url: &str) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let mut absolute = html.to_string();
+    for attribute in ["href", "src"] {
+        for quote in ['"', '\''] {
+            let relative = format!("{attribute}={quote}/");
+            let mut rewritten = String::with_capacity(absolute.len());
+            let mut rest = absolute.as_str();
+            while let Some(index) = rest.find(&relative) {
+                let after = &rest[index + relative.len()..];
+                rewritten.push_str(&rest[..index]);
+                if after.starts_with('/') {
+                    rewritten.push_str(&relative);
+                } else {
+                    rewritten.push_str(&format!("{attribute}={quote}{base_url}/"));
+                }
+                rest = after;
+            }
+            rewritten.push_str(rest);
+            absolute = rewritten;
+        }
+    }
+    absolute
+}
+
+/// The `newsletter` variable for `site`: the newest `config.posts` posts,
+/// leaving out drafts and posts with `in_feed = false`.
+pub fn newsletter(site: &Site, config: &NewsletterConfig) -> Newsletter {
+    let base_url = site.config.base_url.trim_end_matches('/');
+    let posts = site
+        .posts
+        .iter()
+        .filter(|post| !post.draft && post.content.in_feed())
+        .take(config.posts)
+        .map(|post| NewsletterPost {
+            title: post.content.title.clone(),
+            url: format!("{base_url}{}", post.content.url),
+            date: post.date,
+            summary: post.excerpt.clone().unwrap_or_default(),
+            content: absolutize_links(&post.content.html, base_url),
+            text: strip_html_tags(&post.content.html),
+        })
+        .collect();
+    Newsletter {
+        title: config
+            .title
+            .clone()
+            .unwrap_or_else(|| site.config.title.clone()),
+        url: format!("{base_url}/"),
+        full_content: config.full_content,
+        posts,
+    }
+}
+
+/// Renders `newsletter.html` and `newsletter.txt` into `output` when
+/// `[newsletter]` is configured.
+pub fn generate_newsletter(
+    tera: &Tera,
+    site: &Site,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    let Some(config) = site.config.newsletter.as_ref() else {
+        return Ok(());
+    };
+    let mut context = Context::new();
+    context.insert("site", &site_metadata(site));
+    context.insert("newsletter", &newsletter(site, config));
+    for template in [NEWSLETTER_HTML, NEWSLETTER_TXT] {
+        let rendered = render_template(tera, template, &context)?;
+        output.write_file(Path::new(template), rendered.as_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::MemorySink;
+    use crate::test_site::site;
+
+    #[test]
+    fn test_absolutize_links() {
+        assert_eq!(
+            absolutize_links(
+                "<a href=\"/posts/a/\">a</a><img src='/img.png'><a href=\"//cdn.example\">",
+                "https://example.com/"
+            ),
+            "<a href=\"https://example.com/posts/a/\">a</a><img src='https://example.com/img.png'>\
+             <a href=\"//cdn.example\">"
+        );
+    }
+
+    #[test]
+    fn test_newsletter_posts() {
+        let site = site("[newsletter]\nposts = 1");
+        let newsletter = newsletter(&site, site.config.newsletter.as_ref().unwrap());
+        assert_eq!(newsletter.title, "Test");
+        assert_eq!(newsletter.posts.len(), 1);
+        assert_eq!(newsletter.posts[0].title, "Second");
+        assert_eq!(newsletter.posts[0].url, "https://example.com/posts/second/");
+    }
+
+    #[test]
+    fn test_generate_newsletter() {
+        let site = site("[newsletter]\nfull_content = true\ntitle = \"Monthly digest\"");
+        let engine = crate::theme::ThemeEngine::new("default").unwrap();
+        let sink = MemorySink::new();
+        generate_newsletter(engine.tera(), &site, &sink).unwrap();
+
+        let html = String::from_utf8(sink.read_file(Path::new(NEWSLETTER_HTML)).unwrap()).unwrap();
+        assert!(html.contains("<title>Monthly digest</title>"));
+        assert!(html.contains("href=\"https://example.com/posts/second/\""));
+        assert!(html.contains("style=\""));
+        let text = String::from_utf8(sink.read_file(Path::new(NEWSLETTER_TXT)).unwrap()).unwrap();
+        assert!(text.starts_with("Test\n"));
+        assert!(text.contains("Read on the site: https://example.com/posts/first/"));
+        assert!(!text.contains('<'));
+    }
+}
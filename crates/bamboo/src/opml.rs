@@ -0,0 +1,174 @@
+//! `feeds.opml`, an OPML 2.0 subscription list of every feed the build
+//! writes: the main feed, the notes feed, each collection with
+//! `feed = true`, and the changelog's release feed. Feed readers can import
+//! it to subscribe to all of them at once, and directories can use it to
+//! discover the site's feeds.
+//!
+//! Each outline points at the RSS version of a feed, or the Atom version
+//! when `[generate] rss = false`. Nothing is written when both are off.
+
+use crate::error::Result;
+use crate::feeds::{ATOM_TEMPLATE, RELEASES_FEED, RSS_TEMPLATE};
+use crate::sink::RenderSink;
+use crate::types::Site;
+use crate::xml::escape;
+use std::path::Path;
+
+/// Output filename of the subscription list.
+pub const OPML_FILE: &str = "feeds.opml";
+
+/// One feed in the subscription list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedOutline {
+    /// Feed title, matching the feed's own `<title>`.
+    pub title: String,
+    /// Absolute URL of the feed file.
+    pub xml_url: String,
+    /// Absolute URL of the page the feed describes.
+    pub html_url: String,
+}
+
+/// Every feed the build writes for `site`, main feed first and
+/// collections in name order.
+pub fn feed_outlines(site: &Site) -> Vec<FeedOutline> {
+    let generate = &site.config.generate;
+    let template = match (generate.rss, generate.atom) {
+        (true, _) => RSS_TEMPLATE,
+        (false, true) => ATOM_TEMPLATE,
+        (false, false) => return Vec::new(),
+    };
+    let filename = site.config.feed.filename(template);
+    let base_url = site.config.base_url.trim_end_matches('/');
+    let section = |section: &str| FeedOutline {
+        title: format!("{} - {section}", site.config.title),
+        xml_url: format!("{base_url}/{section}/{filename}"),
+        html_url: format!("{base_url}/{section}/"),
+    };
+
+    let mut outlines = vec![FeedOutline {
+        title: site.config.title.clone(),
+        xml_url: format!("{base_url}/{filename}"),
+        html_url: format!("{base_url}/"),
+    }];
+    if !site.notes.is_empty() {
+        outlines.push(section(crate::feeds::NOTES_SECTION));
+    }
+    let mut collections: Vec<&String> = site
+        .collections
+        .iter()
+        .filter(|(_, collection)| collection.config.feed)
+        .map(|(name, _)| name)
+        .collect();
+    collections.sort();
+    outlines.extend(collections.into_iter().map(|name| section(name)));
+    if generate.rss
+        && let Some(config) = site.config.changelog.as_ref()
+        && !crate::changelog::releases(site).is_empty()
+    {
+        outlines.push(FeedOutline {
+            title: format!("{} releases", site.config.title),
+            xml_url: format!("{base_url}/{RELEASES_FEED}"),
+            html_url: format!("{base_url}/{}/", config.collection),
+        });
+    }
+    outlines
+}
+
+/// Renders the OPML document listing `outlines`.
+pub fn render_opml(site: &Site, outlines: &[FeedOutline]) -> String {
+    let mut opml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    opml.push_str("<opml version=\"2.0\">\n  <head>\n");
+    opml.push_str(&format!(
+        "    <title>{}</title>\n",
+        escape(&site.config.title)
+    ));
+    if let Some(author) = &site.config.author {
+        opml.push_str(&format!("    <ownerName>{}</ownerName>\n", escape(author)));
+    }
+    opml.push_str("  </head>\n  <body>\n");
+    for outline in outlines {
+        opml.push_str(&format!(
+            "    <outline type=\"rss\" text=\"{title}\" title=\"{title}\" xmlUrl=\"{}\" htmlUrl=\"{}\"/>\n",
+            escape(&outline.xml_url),
+            escape(&outline.html_url),
+            title = escape(&outline.title),
+        ));
+    }
+    opml.push_str("  </body>\n</opml>\n");
+    opml
+}
+
+/// Writes `feeds.opml` into `output`, unless the site has no feeds.
+pub fn generate_opml(site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
+    let outlines = feed_outlines(site);
+    if outlines.is_empty() {
+        return Ok(());
+    }
+    output.write_file(
+        Path::new(OPML_FILE),
+        render_opml(site, &outlines).as_bytes(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_site::site;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_feed_outlines() {
+        let mut site = site("");
+        site.config.title = "Tom & Jerry".to_string();
+        site.notes.clear();
+        assert_eq!(
+            feed_outlines(&site),
+            [FeedOutline {
+                title: "Tom & Jerry".to_string(),
+                xml_url: "https://example.com/rss.xml".to_string(),
+                html_url: "https://example.com/".to_string(),
+            }]
+        );
+
+        let name = site.collections.keys().next().unwrap().clone();
+        site.collections.get_mut(&name).unwrap().config.feed = true;
+        site.config.generate.rss = false;
+        let outlines = feed_outlines(&site);
+        assert_eq!(outlines.len(), 2);
+        assert_eq!(
+            outlines[1].xml_url,
+            format!("https://example.com/{name}/atom.xml")
+        );
+
+        site.config.generate.atom = false;
+        assert!(feed_outlines(&site).is_empty());
+    }
+
+    #[test]
+    fn test_generate_opml() {
+        let mut site = site("author = \"Ada\"\n[feed]\nrss_filename = \"feed.xml\"");
+        site.config.title = "Tom & Jerry".to_string();
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_opml(&site, output_dir.path()).unwrap();
+
+        let opml = std::fs::read_to_string(output_dir.path().join(OPML_FILE)).unwrap();
+        assert!(opml.contains("<title>Tom &amp; Jerry</title>"));
+        assert!(opml.contains("<ownerName>Ada</ownerName>"));
+        assert!(opml.contains(
+            "<outline type=\"rss\" text=\"Tom &amp; Jerry\" title=\"Tom &amp; Jerry\" \
+             xmlUrl=\"https://example.com/feed.xml\" htmlUrl=\"https://example.com/\"/>"
+        ));
+        assert!(opml.contains("xmlUrl=\"https://example.com/notes/feed.xml\""));
+    }
+
+    #[test]
+    fn test_generate_opml_without_feeds() {
+        let mut site = site("");
+        site.collections = HashMap::new();
+        site.config.generate.rss = false;
+        site.config.generate.atom = false;
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_opml(&site, output_dir.path()).unwrap();
+        assert!(!output_dir.path().join(OPML_FILE).exists());
+    }
+}
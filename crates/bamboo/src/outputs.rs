@@ -0,0 +1,186 @@
+//! Alternative output formats. A page's `outputs` frontmatter (or the
+//! site-wide `outputs` in `bamboo.toml`) lists formats such as `txt` or
+//! `json` to render next to its HTML, so `/about/index.html` can get an
+//! `/about/index.json` for client-side apps or an `/about/index.txt`
+//! plain-text version.
+//!
+//! Each format renders through the template named after the page's HTML
+//! template with the format as its extension (`post.html` → `post.json`),
+//! falling back to `default.<format>`. The built-in theme ships
+//! `default.txt` and `default.json`.
+
+use crate::diagnostics::render_template;
+use crate::error::Result;
+use crate::sink::RenderSink;
+use crate::types::{Content, Site};
+use tera::{Context, Tera};
+
+/// The format every page is always rendered in.
+pub const HTML_OUTPUT: &str = "html";
+
+/// Formats `content` is rendered in besides HTML: its `outputs` frontmatter,
+/// or the site-wide `outputs`, without `html` or duplicates.
+pub fn extra_outputs(site: &Site, content: &Content) -> Vec<String> {
+    let requested = content
+        .frontmatter
+        .get_array("outputs")
+        .unwrap_or_else(|| site.config.outputs.clone());
+    let mut formats: Vec<String> = Vec::new();
+    for format in requested {
+        let format = format.trim().trim_start_matches('.').to_lowercase();
+        if !format.is_empty() && format != HTML_OUTPUT && !formats.contains(&format) {
+            formats.push(format);
+        }
+    }
+    formats
+}
+
+/// Template used for `format` when the HTML is rendered with
+/// `html_template`: `<stem>.<format>` if the theme has it, otherwise
+/// `default.<format>`.
+pub fn output_template(tera: &Tera, html_template: &str, format: &str) -> String {
+    let stem = html_template.strip_suffix(".html").unwrap_or(html_template);
+    let specific = format!("{stem}.{format}");
+    if tera.get_template_names().any(|name| name == specific) {
+        specific
+    } else {
+        format!("default.{format}")
+    }
+}
+
+/// Renders every extra output of `content` next to its HTML file. Each
+/// template receives the page's HTML `context` plus `content` (the page,
+/// post, note, or item), `raw_content` (its markdown source), and
+/// `output_format`.
+pub(crate) fn render_outputs(
+    tera: &Tera,
+    site: &Site,
+    content: &Content,
+    html_template: &str,
+    context: &Context,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    let formats = extra_outputs(site, content);
+    if formats.is_empty() {
+        return Ok(());
+    }
+    let mut context = context.clone();
+    context.insert("content", content);
+    context.insert("raw_content", &content.raw_content);
+    let html_path = &content.path;
+    for format in formats {
+        context.insert("output_format", &format);
+        let template = output_template(tera, html_template, &format);
+        let rendered = render_template(tera, &template, &context)?;
+        output.write_file(&html_path.with_extension(&format), rendered.as_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Frontmatter, Page, SiteConfig};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn about_page(frontmatter: Frontmatter) -> Page {
+        Page {
+            content: Content {
+                slug: "about".to_string(),
+                title: "About".to_string(),
+                html: "<p>Hello</p>".to_string(),
+                raw_content: "Hello".to_string(),
+                frontmatter,
+                path: PathBuf::from("about/index.html"),
+                source_path: PathBuf::from("about.md"),
+                modified: None,
+                template: None,
+                weight: 0,
+                word_count: 1,
+                reading_time: 1,
+                stats: crate::types::PageStats::default(),
+                lang: None,
+                translations: Vec::new(),
+                toc: Vec::new(),
+                url: "/about/".to_string(),
+            },
+            draft: false,
+            redirect_from: Vec::new(),
+        }
+    }
+
+    fn site(outputs: Vec<String>) -> Site {
+        let config: SiteConfig = toml::from_str(&format!(
+            "title = \"Test\"\nbase_url = \"https://example.com\"\noutputs = {outputs:?}"
+        ))
+        .unwrap();
+        Site {
+            config,
+            home: None,
+            pages: Vec::new(),
+            posts: Vec::new(),
+            notes: Vec::new(),
+            collections: HashMap::new(),
+            data: HashMap::new(),
+            assets: Vec::new(),
+            image_requests: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_extra_outputs_prefers_frontmatter() {
+        let site = site(vec!["html".to_string(), "txt".to_string()]);
+        assert_eq!(
+            extra_outputs(&site, &about_page(Frontmatter::default()).content),
+            vec!["txt"]
+        );
+
+        let frontmatter = Frontmatter::new(HashMap::from([(
+            "outputs".to_string(),
+            serde_json::json!(["HTML", "json", ".txt", "json"]),
+        )]));
+        assert_eq!(
+            extra_outputs(&site, &about_page(frontmatter).content),
+            vec!["json", "txt"]
+        );
+    }
+
+    #[test]
+    fn test_output_template_falls_back_to_default() {
+        let mut tera = Tera::default();
+        tera.add_raw_template("post.json", "{}").unwrap();
+        assert_eq!(output_template(&tera, "post.html", "json"), "post.json");
+        assert_eq!(output_template(&tera, "page.html", "json"), "default.json");
+        assert_eq!(output_template(&tera, "post.html", "txt"), "default.txt");
+    }
+
+    #[test]
+    fn test_render_outputs_writes_next_to_html() {
+        let site = site(vec!["txt".to_string()]);
+        let page = about_page(Frontmatter::default());
+        let mut tera = Tera::default();
+        tera.add_raw_template(
+            "default.txt",
+            "{{ content.title }} ({{ output_format }}): {{ raw_content }}",
+        )
+        .unwrap();
+
+        let output_dir = tempfile::TempDir::new().unwrap();
+        render_outputs(
+            &tera,
+            &site,
+            &page.content,
+            "page.html",
+            &Context::new(),
+            output_dir.path(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(output_dir.path().join("about/index.txt")).unwrap(),
+            "About (txt): Hello"
+        );
+    }
+}
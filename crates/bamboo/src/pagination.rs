@@ -0,0 +1,130 @@
+//! Template variables shared by every paginated listing: the post index,
+//! `/page/N/` pages, taxonomy term pages, and collection indexes.
+//!
+//! Page 1 of a listing lives at its root URL and page `N` at
+//! `{root}page/N/`.
+
+use serde::Serialize;
+use tera::Context;
+
+/// Pages shown on each side of the current page in `page_numbers`.
+const PAGE_NUMBER_WINDOW: usize = 2;
+
+/// One entry of `page_numbers`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct PageLink {
+    pub number: usize,
+    pub url: String,
+    pub current: bool,
+}
+
+/// URL of page `page_number` of the listing rooted at `root_url` (which
+/// ends with `/`).
+pub(crate) fn page_url(root_url: &str, page_number: usize) -> String {
+    if page_number <= 1 {
+        root_url.to_string()
+    } else {
+        format!("{root_url}page/{page_number}/")
+    }
+}
+
+/// Pages from `current_page - PAGE_NUMBER_WINDOW` to
+/// `current_page + PAGE_NUMBER_WINDOW`, clamped to `1..=total_pages`.
+pub(crate) fn page_numbers(
+    root_url: &str,
+    current_page: usize,
+    total_pages: usize,
+) -> Vec<PageLink> {
+    let first = current_page.saturating_sub(PAGE_NUMBER_WINDOW).max(1);
+    let last = (current_page + PAGE_NUMBER_WINDOW).min(total_pages);
+    (first..=last)
+        .map(|number| PageLink {
+            number,
+            url: page_url(root_url, number),
+            current: number == current_page,
+        })
+        .collect()
+}
+
+/// Inserts `current_page`, `total_pages`, `first_page_url`,
+/// `last_page_url`, `page_numbers`, and, when they exist,
+/// `prev_page_url` and `next_page_url`.
+pub(crate) fn insert_pagination(
+    context: &mut Context,
+    root_url: &str,
+    current_page: usize,
+    total_pages: usize,
+) {
+    context.insert("current_page", &current_page);
+    context.insert("total_pages", &total_pages);
+    context.insert("first_page_url", &page_url(root_url, 1));
+    context.insert("last_page_url", &page_url(root_url, total_pages));
+    context.insert(
+        "page_numbers",
+        &page_numbers(root_url, current_page, total_pages),
+    );
+    if current_page > 1 {
+        context.insert("prev_page_url", &page_url(root_url, current_page - 1));
+    }
+    if current_page < total_pages {
+        context.insert("next_page_url", &page_url(root_url, current_page + 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "https://example.com/tags/rust/";
+
+    fn numbers(current_page: usize, total_pages: usize) -> Vec<usize> {
+        page_numbers(ROOT, current_page, total_pages)
+            .iter()
+            .map(|link| link.number)
+            .collect()
+    }
+
+    #[test]
+    fn test_page_numbers_window() {
+        assert_eq!(numbers(1, 1), vec![1]);
+        assert_eq!(numbers(1, 10), vec![1, 2, 3]);
+        assert_eq!(numbers(5, 10), vec![3, 4, 5, 6, 7]);
+        assert_eq!(numbers(10, 10), vec![8, 9, 10]);
+        let links = page_numbers(ROOT, 2, 3);
+        assert_eq!(
+            links[0],
+            PageLink {
+                number: 1,
+                url: ROOT.to_string(),
+                current: false
+            }
+        );
+        assert!(links[1].current);
+        assert_eq!(links[2].url, "https://example.com/tags/rust/page/3/");
+    }
+
+    #[test]
+    fn test_insert_pagination() {
+        let mut context = Context::new();
+        insert_pagination(&mut context, ROOT, 2, 4);
+        let json = context.into_json();
+        assert_eq!(json["first_page_url"], ROOT);
+        assert_eq!(
+            json["last_page_url"],
+            "https://example.com/tags/rust/page/4/"
+        );
+        assert_eq!(json["prev_page_url"], ROOT);
+        assert_eq!(
+            json["next_page_url"],
+            "https://example.com/tags/rust/page/3/"
+        );
+        assert_eq!(json["page_numbers"].as_array().unwrap().len(), 4);
+
+        let mut context = Context::new();
+        insert_pagination(&mut context, ROOT, 1, 1);
+        let json = context.into_json();
+        assert!(json.get("prev_page_url").is_none());
+        assert!(json.get("next_page_url").is_none());
+        assert_eq!(json["last_page_url"], ROOT);
+    }
+}
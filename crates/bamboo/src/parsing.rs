@@ -4,8 +4,8 @@
 //! time, and excerpt generation.
 
 use crate::error::{BambooError, Result};
-use crate::types::{Frontmatter, TocEntry};
-use chrono::NaiveDate;
+use crate::types::{Frontmatter, PageStats, TocEntry};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
 use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
 use serde_json::Value;
 use std::collections::{HashMap, HashSet};
@@ -22,6 +22,64 @@ pub struct MarkdownRenderer {
     syntax_set: SyntaxSet,
     theme_set: ThemeSet,
     theme_name: String,
+    hooks: Option<RenderHooks>,
+}
+
+/// Render-hook templates loaded from `templates/_hooks/`. When a hook is
+/// present, [`MarkdownRenderer`] renders it in place of its default markup:
+///
+/// - `_hooks/link.html` receives `url`, `title`, `text` (inner HTML), and
+///   `is_external`.
+/// - `_hooks/image.html` receives `url`, `title`, and `alt`.
+/// - `_hooks/heading.html` receives `level`, `id`, `text` (inner HTML), and
+///   `plain_text`.
+///
+/// A hook that fails to render fails the render with
+/// [`BambooError::TemplateRender`].
+pub struct RenderHooks {
+    tera: tera::Tera,
+}
+
+const LINK_HOOK: &str = "_hooks/link.html";
+const IMAGE_HOOK: &str = "_hooks/image.html";
+const HEADING_HOOK: &str = "_hooks/heading.html";
+
+impl RenderHooks {
+    /// Loads `_hooks/*.html` from each templates directory in order, so a
+    /// site's hooks shadow a theme's. Returns `None` when no hooks exist.
+    pub fn from_template_dirs(templates_dirs: &[std::path::PathBuf]) -> Result<Option<Self>> {
+        let mut templates = HashMap::new();
+        for templates_dir in templates_dirs {
+            for (name, _) in
+                crate::shortcodes::collect_subdirectory_templates(templates_dir, "_hooks")?
+            {
+                templates.insert(name.clone(), (templates_dir.join(&name), Some(name)));
+            }
+        }
+        if templates.is_empty() {
+            return Ok(None);
+        }
+        // Loaded from their files, so render errors can quote the source.
+        let mut tera = tera::Tera::default();
+        tera.add_template_files(templates.into_values())?;
+        Ok(Some(Self { tera }))
+    }
+
+    /// Registers consumer-supplied filters, functions, and testers so hook
+    /// templates can use them.
+    pub fn register_extensions(&mut self, extensions: &crate::extensions::TemplateExtensions) {
+        extensions.apply(&mut self.tera);
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.tera
+            .get_template_names()
+            .any(|existing| existing == name)
+    }
+
+    fn render(&self, name: &str, context: &tera::Context) -> Result<String> {
+        crate::diagnostics::render_template(&self.tera, name, context)
+    }
 }
 
 impl Default for MarkdownRenderer {
@@ -47,6 +105,7 @@ impl MarkdownRenderer {
             syntax_set: SyntaxSet::load_defaults_newlines(),
             theme_set: ThemeSet::load_defaults(),
             theme_name: "base16-ocean.dark".to_string(),
+            hooks: None,
         }
     }
 
@@ -63,12 +122,37 @@ impl MarkdownRenderer {
             syntax_set: SyntaxSet::load_defaults_newlines(),
             theme_set,
             theme_name: theme_name.to_string(),
+            hooks: None,
         })
     }
 
+    /// Installs render hooks that replace the default link, image, and
+    /// heading markup.
+    pub fn set_render_hooks(&mut self, hooks: Option<RenderHooks>) {
+        self.hooks = hooks;
+    }
+
+    /// Renders a single paragraph of markdown without the surrounding
+    /// `<p>` wrapper, for markup that sits inside running text. Content
+    /// that renders to anything other than one paragraph is returned as-is.
+    pub fn render_inline(&self, content: &str) -> Result<String> {
+        let html = self.render(content)?.html;
+        let trimmed = html.trim();
+        Ok(
+            match trimmed
+                .strip_prefix("<p>")
+                .and_then(|rest| rest.strip_suffix("</p>"))
+            {
+                Some(inner) if !inner.contains("<p>") => inner.to_string(),
+                _ => trimmed.to_string(),
+            },
+        )
+    }
+
     /// Renders `content` as markdown and collects heading information for
-    /// the table of contents.
-    pub fn render(&self, content: &str) -> RenderedMarkdown {
+    /// the table of contents. Fails with [`BambooError::TemplateRender`]
+    /// when a render hook does.
+    pub fn render(&self, content: &str) -> Result<RenderedMarkdown> {
         let mut options = Options::empty();
         options.insert(Options::ENABLE_TABLES);
         options.insert(Options::ENABLE_FOOTNOTES);
@@ -87,10 +171,83 @@ impl MarkdownRenderer {
         let mut heading_plain_text = String::new();
         let mut heading_events: Vec<Event<'_>> = Vec::new();
         let mut used_heading_ids: HashSet<String> = HashSet::new();
+        let mut hooked_link: Option<(String, String, Vec<Event<'_>>)> = None;
+        let mut hooked_image: Option<(String, String, String)> = None;
 
         let theme = &self.theme_set.themes[&self.theme_name];
+        let hooks = self.hooks.as_ref();
+        let link_hook = hooks.filter(|hooks| hooks.has(LINK_HOOK));
+        let image_hook = hooks.filter(|hooks| hooks.has(IMAGE_HOOK));
+        let heading_hook = hooks.filter(|hooks| hooks.has(HEADING_HOOK));
 
         for event in parser {
+            if let Some((_, _, ref mut alt)) = hooked_image {
+                match event {
+                    Event::End(TagEnd::Image) => {
+                        let (url, title, alt) = hooked_image.take().unwrap_or_default();
+                        let mut context = tera::Context::new();
+                        context.insert("url", &url);
+                        context.insert("title", &title);
+                        context.insert("alt", &alt);
+                        let rendered = match image_hook {
+                            Some(hooks) => hooks.render(IMAGE_HOOK, &context)?,
+                            None => default_image_html(&url, &title, &alt),
+                        };
+                        if let Some((_, _, ref mut events)) = hooked_link {
+                            events.push(Event::InlineHtml(rendered.into()));
+                        } else if in_heading {
+                            heading_events.push(Event::InlineHtml(rendered.into()));
+                        } else {
+                            html_output.push_str(&rendered);
+                        }
+                    }
+                    Event::Text(ref text) | Event::Code(ref text) => alt.push_str(text),
+                    _ => {}
+                }
+                continue;
+            }
+            if image_hook.is_some()
+                && let Event::Start(Tag::Image {
+                    ref dest_url,
+                    ref title,
+                    ..
+                }) = event
+            {
+                hooked_image = Some((dest_url.to_string(), title.to_string(), String::new()));
+                continue;
+            }
+            if let Some(hooks) = link_hook
+                && !in_heading
+            {
+                match event {
+                    Event::Start(Tag::Link {
+                        ref dest_url,
+                        ref title,
+                        ..
+                    }) if hooked_link.is_none() => {
+                        hooked_link = Some((dest_url.to_string(), title.to_string(), Vec::new()));
+                        continue;
+                    }
+                    Event::End(TagEnd::Link) if hooked_link.is_some() => {
+                        let (url, title, events) = hooked_link.take().unwrap_or_default();
+                        let mut text = String::new();
+                        pulldown_cmark::html::push_html(&mut text, events.into_iter());
+                        let mut context = tera::Context::new();
+                        context.insert("url", &url);
+                        context.insert("title", &title);
+                        context.insert("text", &text);
+                        context.insert("is_external", &is_external_url(&url));
+                        html_output.push_str(&hooks.render(LINK_HOOK, &context)?);
+                        continue;
+                    }
+                    _ => {
+                        if let Some((_, _, ref mut events)) = hooked_link {
+                            events.push(event);
+                            continue;
+                        }
+                    }
+                }
+            }
             match event {
                 Event::Start(Tag::Heading { level, .. }) => {
                     in_heading = true;
@@ -123,12 +280,22 @@ impl MarkdownRenderer {
                         id: heading_id.clone(),
                         title: heading_plain_text.clone(),
                     });
-                    html_output.push_str(&format!(
-                        "<h{level} id=\"{id}\"><a class=\"anchor\" href=\"#{id}\">#</a>{text}</h{level}>\n",
-                        level = heading_level,
-                        id = escape_html(&heading_id),
-                        text = heading_html,
-                    ));
+                    match heading_hook {
+                        Some(hooks) => {
+                            let mut context = tera::Context::new();
+                            context.insert("level", &heading_level);
+                            context.insert("id", &heading_id);
+                            context.insert("text", &heading_html);
+                            context.insert("plain_text", &heading_plain_text);
+                            html_output.push_str(&hooks.render(HEADING_HOOK, &context)?);
+                        }
+                        None => html_output.push_str(&format!(
+                            "<h{level} id=\"{id}\"><a class=\"anchor\" href=\"#{id}\">#</a>{text}</h{level}>\n",
+                            level = heading_level,
+                            id = escape_html(&heading_id),
+                            text = heading_html,
+                        )),
+                    }
                 }
                 Event::Start(Tag::CodeBlock(kind)) => {
                     in_code_block = true;
@@ -192,10 +359,10 @@ impl MarkdownRenderer {
             }
         }
 
-        RenderedMarkdown {
+        Ok(RenderedMarkdown {
             html: html_output,
             toc,
-        }
+        })
     }
 }
 
@@ -323,11 +490,57 @@ fn escape_html(input: &str) -> String {
     crate::xml::escape(input)
 }
 
+fn is_external_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://") || url.starts_with("//")
+}
+
+fn default_image_html(url: &str, title: &str, alt: &str) -> String {
+    if title.is_empty() {
+        format!(
+            "<img src=\"{}\" alt=\"{}\" />",
+            escape_html(url),
+            escape_html(alt)
+        )
+    } else {
+        format!(
+            "<img src=\"{}\" alt=\"{}\" title=\"{}\" />",
+            escape_html(url),
+            escape_html(alt),
+            escape_html(title)
+        )
+    }
+}
+
 /// Counts whitespace-separated words in `text`.
 pub fn word_count(text: &str) -> usize {
     text.split_whitespace().count()
 }
 
+/// Computes [`PageStats`] for rendered HTML. Headings are counted from
+/// `toc` so the numbers agree with the table of contents.
+pub fn page_stats(html: &str, toc: &[TocEntry]) -> PageStats {
+    let words = word_count(&crate::search::strip_html_tags(html));
+    PageStats {
+        word_count: words,
+        reading_time: reading_time(words),
+        heading_count: toc.len(),
+        image_count: count_elements(html, "img"),
+        code_block_count: count_elements(html, "pre"),
+    }
+}
+
+fn count_elements(html: &str, name: &str) -> usize {
+    let open = format!("<{name}");
+    html.match_indices(&open)
+        .filter(|(position, _)| {
+            matches!(
+                html[position + open.len()..].chars().next(),
+                Some('>' | ' ' | '/' | '\n' | '\t')
+            )
+        })
+        .count()
+}
+
 /// Estimated reading time in minutes at ~200 WPM. Rounds up to 1 minute for
 /// any non-empty content so short posts don't show "0 min read".
 pub fn reading_time(word_count: usize) -> usize {
@@ -662,7 +875,7 @@ fn parse_toml_frontmatter(content: &str, path: &Path) -> Result<(Frontmatter, St
         })?;
 
     let body = &rest[end_index + 3..];
-    Ok((Frontmatter { raw }, body.trim().to_string()))
+    Ok((Frontmatter::new(raw), body.trim().to_string()))
 }
 
 fn parse_yaml_frontmatter(content: &str, path: &Path) -> Result<(Frontmatter, String)> {
@@ -682,7 +895,7 @@ fn parse_yaml_frontmatter(content: &str, path: &Path) -> Result<(Frontmatter, St
             message: error.to_string(),
         })?;
 
-    Ok((Frontmatter { raw }, body.trim().to_string()))
+    Ok((Frontmatter::new(raw), body.trim().to_string()))
 }
 
 fn find_closing_delimiter(content: &str, delimiter: &str) -> Option<usize> {
@@ -701,6 +914,43 @@ fn find_closing_delimiter(content: &str, delimiter: &str) -> Option<usize> {
     None
 }
 
+/// Parses a timestamp from frontmatter or a filename stem. Accepts RFC
+/// 3339, `YYYY-MM-DD HH:MM[:SS[.fff]]` (or with `T`, as TOML local
+/// datetimes are written), a plain `YYYY-MM-DD`, and filename forms like `2024-06-01-1430` or
+/// `2024-06-01-143005`, optionally followed by `-anything`.
+pub fn parse_datetime(raw: &str) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(raw) {
+        return Some(datetime.with_timezone(&Utc));
+    }
+    for format in [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(raw, format) {
+            return Some(Utc.from_utc_datetime(&naive));
+        }
+    }
+
+    let date = NaiveDate::parse_from_str(raw.get(..10)?, "%Y-%m-%d").ok()?;
+    let rest = &raw[10..];
+    let time = match rest.strip_prefix('-') {
+        Some(rest) => {
+            let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+            match digits.len() {
+                6 => NaiveTime::parse_from_str(&digits, "%H%M%S").ok()?,
+                4 => NaiveTime::parse_from_str(&digits, "%H%M").ok()?,
+                _ => NaiveTime::MIN,
+            }
+        }
+        None if rest.is_empty() => NaiveTime::MIN,
+        None => return None,
+    };
+    Some(Utc.from_utc_datetime(&date.and_time(time)))
+}
+
 /// Parses a `YYYY-MM-DD-slug` filename prefix, returning `(date, slug)`
 /// strings. Returns `None` if the filename doesn't match the pattern.
 pub fn parse_date_from_filename(filename: &str) -> Option<(String, String)> {
@@ -740,7 +990,99 @@ mod tests {
     use std::path::PathBuf;
 
     fn render(input: &str) -> RenderedMarkdown {
-        MarkdownRenderer::new().render(input)
+        MarkdownRenderer::new().render(input).unwrap()
+    }
+
+    fn renderer_with_hooks(hooks: &[(&str, &str)]) -> MarkdownRenderer {
+        let dir = tempfile::TempDir::new().unwrap();
+        let hooks_dir = dir.path().join("_hooks");
+        std::fs::create_dir_all(&hooks_dir).unwrap();
+        for (name, content) in hooks {
+            std::fs::write(hooks_dir.join(name), content).unwrap();
+        }
+        let mut renderer = MarkdownRenderer::new();
+        renderer.set_render_hooks(
+            RenderHooks::from_template_dirs(&[dir.path().to_path_buf()]).unwrap(),
+        );
+        renderer
+    }
+
+    #[test]
+    fn test_link_render_hook() {
+        let renderer = renderer_with_hooks(&[(
+            "link.html",
+            "<a href=\"{{ url }}\"{% if is_external %} rel=\"external\"{% endif %}>{{ text | safe }}</a>",
+        )]);
+        let output = renderer
+            .render("[Out](https://example.org) and [*in*](/about/)")
+            .unwrap();
+        assert!(output.html.contains("rel=\"external\">Out</a>"));
+        assert!(output.html.contains("&#x2F;about&#x2F;\"><em>in</em></a>"));
+        assert_eq!(output.html.matches("rel=\"external\"").count(), 1);
+    }
+
+    #[test]
+    fn test_image_render_hook() {
+        let renderer = renderer_with_hooks(&[(
+            "image.html",
+            "<figure><img src=\"{{ url }}\" alt=\"{{ alt }}\"><figcaption>{{ title }}</figcaption></figure>",
+        )]);
+        let output = renderer.render("![A cat](/cat.png \"Cat\")").unwrap();
+        assert!(output.html.contains(
+            "<figure><img src=\"&#x2F;cat.png\" alt=\"A cat\"><figcaption>Cat</figcaption></figure>"
+        ));
+    }
+
+    #[test]
+    fn test_heading_render_hook() {
+        let renderer = renderer_with_hooks(&[(
+            "heading.html",
+            "<h{{ level }} id=\"{{ id }}\">{{ plain_text }}</h{{ level }}>",
+        )]);
+        let output = renderer.render("## Getting Started").unwrap();
+        assert!(
+            output
+                .html
+                .contains("<h2 id=\"getting-started\">Getting Started</h2>")
+        );
+        assert_eq!(output.toc.len(), 1);
+    }
+
+    #[test]
+    fn test_failing_render_hook() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("_hooks")).unwrap();
+        std::fs::write(
+            dir.path().join("_hooks/link.html"),
+            "<a href=\"{{ url }}\">\n{{ missing.field }}</a>",
+        )
+        .unwrap();
+        let mut renderer = MarkdownRenderer::new();
+        renderer.set_render_hooks(
+            RenderHooks::from_template_dirs(&[dir.path().to_path_buf()]).unwrap(),
+        );
+
+        match renderer.render("[Out](https://example.org)") {
+            Err(BambooError::TemplateRender {
+                template,
+                line,
+                excerpt,
+                ..
+            }) => {
+                assert_eq!(template, LINK_HOOK);
+                assert_eq!(line, Some(2));
+                assert!(excerpt.unwrap().contains("{{ missing.field }}"));
+            }
+            Err(other) => panic!("expected a template render error, got {other:?}"),
+            Ok(_) => panic!("expected the link hook to fail"),
+        }
+    }
+
+    #[test]
+    fn test_no_hooks_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let hooks = RenderHooks::from_template_dirs(&[dir.path().to_path_buf()]).unwrap();
+        assert!(hooks.is_none());
     }
 
     #[test]
@@ -815,6 +1157,20 @@ mod tests {
         assert_eq!(line_wrappers, 2);
     }
 
+    #[test]
+    fn test_page_stats() {
+        let renderer = MarkdownRenderer::new();
+        let rendered = renderer
+            .render("# Title\n\nSome words here.\n\n## Part\n\n![alt](a.png)\n\n```rust\nfn main() {}\n```\n\n<picture><img src=\"b.png\"></picture>")
+            .unwrap();
+        let stats = page_stats(&rendered.html, &rendered.toc);
+        assert_eq!(stats.heading_count, 2);
+        assert_eq!(stats.image_count, 2);
+        assert_eq!(stats.code_block_count, 1);
+        assert!(stats.word_count >= 5);
+        assert_eq!(stats.reading_time, 1);
+    }
+
     #[test]
     fn test_word_count_and_reading_time() {
         let text = "one two three four five";
@@ -957,4 +1313,22 @@ mod tests {
         let result = extract_frontmatter(content, &path);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_parse_datetime_formats() {
+        let expected = Utc.with_ymd_and_hms(2024, 6, 1, 14, 30, 0).unwrap();
+        assert_eq!(parse_datetime("2024-06-01T14:30:00Z"), Some(expected));
+        assert_eq!(parse_datetime("2024-06-01 14:30"), Some(expected));
+        assert_eq!(parse_datetime("2024-06-01-1430"), Some(expected));
+        assert_eq!(parse_datetime("2024-06-01-143000-walk"), Some(expected));
+        assert_eq!(
+            parse_datetime("2024-06-01"),
+            Some(Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_datetime("2024-06-01T14:30:00.250"),
+            Some(expected + chrono::Duration::milliseconds(250))
+        );
+        assert_eq!(parse_datetime("coffee"), None);
+    }
 }
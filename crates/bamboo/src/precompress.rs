@@ -0,0 +1,291 @@
+//! Precompressed siblings for text output: `index.html.gz` and
+//! `index.html.br` next to `index.html`, so nginx (`gzip_static`,
+//! `brotli_static`) and Caddy (`precompressed`) can serve them without
+//! compressing on every request.
+//!
+//! Gzip is built in. Brotli is delegated to the system `brotli` binary,
+//! like [`crate::remote`] delegates HTTP to `curl`; when it isn't
+//! installed only `.gz` files are written.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::error::{BambooError, Result};
+
+/// `[precompress]` table from `bamboo.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrecompressConfig {
+    /// Encodings to write. Defaults to both.
+    pub formats: Vec<Encoding>,
+    /// Files smaller than this many bytes are left alone. Defaults to
+    /// 1024.
+    pub min_size: u64,
+    /// File extensions to compress. Defaults to `html`, `css`, `js`,
+    /// `json`, and `xml`.
+    pub extensions: Vec<String>,
+}
+
+impl Default for PrecompressConfig {
+    fn default() -> Self {
+        Self {
+            formats: vec![Encoding::Gzip, Encoding::Brotli],
+            min_size: 1024,
+            extensions: ["html", "css", "js", "json", "xml"]
+                .iter()
+                .map(|extension| extension.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// A precompressed encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// `.gz`, for every client.
+    Gzip,
+    /// `.br`, smaller; sent to clients that accept `br`.
+    Brotli,
+}
+
+impl Encoding {
+    /// The suffix appended to the original file name.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gz",
+            Encoding::Brotli => "br",
+        }
+    }
+}
+
+/// What [`precompress_output`] wrote.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrecompressStats {
+    /// Compressed files written, across all encodings.
+    pub files: usize,
+    /// `true` if Brotli was requested but the `brotli` binary couldn't
+    /// be run.
+    pub brotli_unavailable: bool,
+}
+
+/// Writes compressed siblings for every matching file under `output_dir`.
+/// A sibling is only kept when it's smaller than the original; stale
+/// siblings from earlier builds are removed.
+pub fn precompress_output(
+    output_dir: &Path,
+    config: &PrecompressConfig,
+) -> Result<PrecompressStats> {
+    precompress_with(output_dir, config, "brotli")
+}
+
+fn precompress_with(
+    output_dir: &Path,
+    config: &PrecompressConfig,
+    brotli_command: &str,
+) -> Result<PrecompressStats> {
+    let mut candidates: Vec<(PathBuf, u64)> = Vec::new();
+    for entry in WalkDir::new(output_dir) {
+        let entry = entry.map_err(|error| BambooError::WalkDir {
+            path: output_dir.to_path_buf(),
+            message: error.to_string(),
+        })?;
+        let path = entry.path();
+        let matches = entry.file_type().is_file()
+            && path.extension().is_some_and(|extension| {
+                config
+                    .extensions
+                    .iter()
+                    .any(|wanted| extension.eq_ignore_ascii_case(wanted.as_str()))
+            });
+        if matches {
+            let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            candidates.push((path.to_path_buf(), size));
+        }
+    }
+
+    let brotli_unavailable = AtomicBool::new(false);
+    let written: Vec<usize> = candidates
+        .par_iter()
+        .map(|(path, size)| -> Result<usize> {
+            let original = fs::read(path).map_err(|source| BambooError::IoPath {
+                operation: "read",
+                path: path.clone(),
+                source,
+            })?;
+            let mut count = 0;
+            for encoding in [Encoding::Gzip, Encoding::Brotli] {
+                let sibling = sibling_path(path, encoding);
+                let compressed = if *size >= config.min_size && config.formats.contains(&encoding) {
+                    match encoding {
+                        Encoding::Gzip => Some(gzip(&original)?),
+                        Encoding::Brotli if brotli_unavailable.load(Ordering::Relaxed) => None,
+                        Encoding::Brotli => {
+                            let compressed = brotli(brotli_command, path);
+                            if compressed.is_none() {
+                                brotli_unavailable.store(true, Ordering::Relaxed);
+                            }
+                            compressed
+                        }
+                    }
+                } else {
+                    None
+                };
+                match compressed {
+                    Some(bytes) if (bytes.len() as u64) < *size => {
+                        fs::write(&sibling, bytes).map_err(|source| BambooError::IoPath {
+                            operation: "write",
+                            path: sibling.clone(),
+                            source,
+                        })?;
+                        count += 1;
+                    }
+                    _ => {
+                        if sibling.is_file() {
+                            fs::remove_file(&sibling).map_err(|source| BambooError::IoPath {
+                                operation: "remove",
+                                path: sibling.clone(),
+                                source,
+                            })?;
+                        }
+                    }
+                }
+            }
+            Ok(count)
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(PrecompressStats {
+        files: written.iter().sum(),
+        brotli_unavailable: brotli_unavailable.into_inner(),
+    })
+}
+
+fn sibling_path(path: &Path, encoding: Encoding) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(encoding.extension());
+    PathBuf::from(name)
+}
+
+fn gzip(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(bytes)?;
+    Ok(encoder.finish()?)
+}
+
+/// Runs `brotli` at maximum quality, or `None` if it can't be run.
+fn brotli(command: &str, path: &Path) -> Option<Vec<u8>> {
+    let output = Command::new(command)
+        .args(["--quality=11", "--stdout", "--"])
+        .arg(path)
+        .output()
+        .ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn page() -> String {
+        "<p>Hello, precompressed world.</p>\n".repeat(100)
+    }
+
+    #[test]
+    fn test_gzip_siblings_above_threshold() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("posts")).unwrap();
+        fs::write(dir.path().join("posts/index.html"), page()).unwrap();
+        fs::write(dir.path().join("tiny.css"), "body{margin:0}").unwrap();
+        fs::write(dir.path().join("photo.png"), page()).unwrap();
+        let config = PrecompressConfig {
+            formats: vec![Encoding::Gzip],
+            ..PrecompressConfig::default()
+        };
+
+        let stats = precompress_output(dir.path(), &config).unwrap();
+
+        assert_eq!(stats.files, 1);
+        assert!(!stats.brotli_unavailable);
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(
+            fs::File::open(dir.path().join("posts/index.html.gz")).unwrap(),
+        )
+        .read_to_string(&mut decoded)
+        .unwrap();
+        assert_eq!(decoded, page());
+        assert!(!dir.path().join("tiny.css.gz").exists());
+        assert!(!dir.path().join("photo.png.gz").exists());
+        assert!(!dir.path().join("posts/index.html.br").exists());
+    }
+
+    #[test]
+    fn test_stale_siblings_removed() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("index.html"), "short").unwrap();
+        fs::write(dir.path().join("index.html.gz"), "stale").unwrap();
+        fs::write(dir.path().join("index.html.br"), "stale").unwrap();
+
+        precompress_with(dir.path(), &PrecompressConfig::default(), "true").unwrap();
+
+        assert!(!dir.path().join("index.html.gz").exists());
+        assert!(!dir.path().join("index.html.br").exists());
+    }
+
+    #[test]
+    fn test_missing_brotli_binary() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("index.html"), page()).unwrap();
+
+        let stats = precompress_with(
+            dir.path(),
+            &PrecompressConfig::default(),
+            "bamboo-test-missing-brotli",
+        )
+        .unwrap();
+
+        assert!(stats.brotli_unavailable);
+        assert_eq!(stats.files, 1);
+        assert!(dir.path().join("index.html.gz").exists());
+        assert!(!dir.path().join("index.html.br").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_brotli_binary_output_written() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::TempDir::new().unwrap();
+        let site = dir.path().join("site");
+        fs::create_dir_all(&site).unwrap();
+        fs::write(site.join("feed.xml"), page()).unwrap();
+        let fake = dir.path().join("fake-brotli");
+        fs::write(&fake, "#!/bin/sh\nprintf 'compressed'\n").unwrap();
+        fs::set_permissions(&fake, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let stats =
+            precompress_with(&site, &PrecompressConfig::default(), fake.to_str().unwrap()).unwrap();
+
+        assert_eq!(stats.files, 2);
+        assert_eq!(fs::read(site.join("feed.xml.br")).unwrap(), b"compressed");
+    }
+
+    #[test]
+    fn test_config_parsing() {
+        let config: PrecompressConfig =
+            toml::from_str("formats = [\"brotli\"]\nmin_size = 10\n").unwrap();
+        assert_eq!(config.formats, vec![Encoding::Brotli]);
+        assert_eq!(config.min_size, 10);
+        assert!(config.extensions.contains(&"json".to_string()));
+    }
+}
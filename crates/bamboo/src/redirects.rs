@@ -1,11 +1,206 @@
 //! Generates HTML redirect stubs for every `redirect_from` entry declared
 //! in frontmatter, so old URLs continue to resolve after a content move.
+//! A page or post with `redirect_to` frontmatter is itself written as a
+//! redirect.
+//!
+//! Redirects that lead to other redirects (`/a/` → `/b/` where `/b/` has
+//! `redirect_to = "/c/"`, say, after two renames) are flattened so every
+//! stub points straight at the final URL; [`redirect_chains`] lists them
+//! for reporting.
 
 use crate::error::Result;
-use crate::types::Site;
-use std::fs;
+use crate::sink::RenderSink;
+use crate::types::{Content, Site};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
 use std::path::Path;
 
+/// Frontmatter field that turns a page or post into a redirect.
+pub const REDIRECT_TO_FIELD: &str = "redirect_to";
+
+/// A redirect that passes through other redirects, as found by
+/// [`redirect_chains`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectChain {
+    /// Every URL from the first source to the last target.
+    pub hops: Vec<String>,
+    /// `true` if the chain leads back to one of its own URLs. Loops are
+    /// written unflattened, one hop per stub.
+    pub cycle: bool,
+}
+
+impl fmt::Display for RedirectChain {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.hops.join(" -> ");
+        if self.cycle {
+            write!(formatter, "redirect loop {path}")
+        } else {
+            write!(
+                formatter,
+                "redirect chain {path} (flattened to {})",
+                self.hops.last().map(String::as_str).unwrap_or_default()
+            )
+        }
+    }
+}
+
+/// `redirect_to` frontmatter of `content`, if set.
+pub fn redirect_to(content: &Content) -> Option<String> {
+    content
+        .frontmatter
+        .get_string(REDIRECT_TO_FIELD)
+        .filter(|target| !target.trim().is_empty())
+}
+
+/// Every redirect with more than one hop, sorted by source URL.
+pub fn redirect_chains(site: &Site) -> Vec<RedirectChain> {
+    let redirects = redirect_map(site);
+    redirects
+        .keys()
+        .filter_map(|source| {
+            let (hops, cycle) = follow(&redirects, source);
+            (cycle || hops.len() > 2).then_some(RedirectChain { hops, cycle })
+        })
+        .collect()
+}
+
+/// Site-relative form of a local URL (`/about/`); external URLs are
+/// returned unchanged.
+fn normalize(site: &Site, url: &str) -> String {
+    let url = url.trim();
+    let url = url
+        .strip_prefix(site.config.base_url.trim_end_matches('/'))
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        .unwrap_or(url);
+    if url.contains("://") || url.starts_with("//") {
+        return url.to_string();
+    }
+    let trimmed = url.trim_matches('/');
+    let last_segment = trimmed.rsplit('/').next().unwrap_or_default();
+    if trimmed.is_empty() {
+        "/".to_string()
+    } else if last_segment.contains(['.', '?', '#']) {
+        format!("/{trimmed}")
+    } else {
+        format!("/{trimmed}/")
+    }
+}
+
+/// Every redirect declared by the site, from site-relative source URL to
+/// target: `redirect_from` sources point at their content, and content
+/// with `redirect_to` points at that URL. The first declaration of a
+/// source wins, and `redirect_from` entries naming a real page are left
+/// out, as no stub is written over it.
+fn redirect_map(site: &Site) -> BTreeMap<String, String> {
+    let real_pages: HashSet<String> = site
+        .home
+        .iter()
+        .map(|home| &home.content)
+        .chain(site.pages.iter().map(|page| &page.content))
+        .chain(site.posts.iter().map(|post| &post.content))
+        .chain(site.notes.iter().map(|note| &note.content))
+        .chain(
+            site.collections
+                .values()
+                .flat_map(|collection| collection.items.iter().map(|item| &item.content)),
+        )
+        .filter(|content| redirect_to(content).is_none())
+        .map(|content| normalize(site, &content.url))
+        .collect();
+
+    let contents = site
+        .posts
+        .iter()
+        .map(|post| (&post.content, &post.redirect_from))
+        .chain(
+            site.pages
+                .iter()
+                .map(|page| (&page.content, &page.redirect_from)),
+        );
+
+    let mut redirects = BTreeMap::new();
+    for (content, redirect_from) in contents {
+        if let Some(target) = redirect_to(content) {
+            redirects
+                .entry(normalize(site, &content.url))
+                .or_insert_with(|| normalize(site, &target));
+        }
+        for source in redirect_from {
+            let source = normalize(site, source);
+            if !real_pages.contains(&source) {
+                redirects
+                    .entry(source)
+                    .or_insert_with(|| normalize(site, &content.url));
+            }
+        }
+    }
+    redirects
+}
+
+/// URLs visited from `source` until reaching one that isn't redirected,
+/// and whether a URL came up twice.
+fn follow(redirects: &BTreeMap<String, String>, source: &str) -> (Vec<String>, bool) {
+    let mut hops = vec![source.to_string()];
+    let mut seen: HashSet<&str> = HashSet::from([source]);
+    let mut current = source;
+    while let Some(next) = redirects.get(current) {
+        hops.push(next.clone());
+        if !seen.insert(next) {
+            return (hops, true);
+        }
+        current = next;
+    }
+    (hops, false)
+}
+
+/// Where a redirect from `source` should point: the end of its chain, or
+/// the first hop when the chain loops. Site-relative unless the target is
+/// external.
+fn final_hop(redirects: &BTreeMap<String, String>, source: &str) -> String {
+    let (mut hops, cycle) = follow(redirects, source);
+    if cycle {
+        hops.swap_remove(1)
+    } else {
+        hops.pop().unwrap_or_default()
+    }
+}
+
+/// Absolute URL that a redirect from `source` should point at.
+fn final_target(site: &Site, redirects: &BTreeMap<String, String>, source: &str) -> String {
+    let target = final_hop(redirects, source);
+    if target.starts_with('/') && !target.starts_with("//") {
+        format!("{}{target}", site.config.base_url.trim_end_matches('/'))
+    } else {
+        target
+    }
+}
+
+/// Path component of `base_url`, without a trailing slash: `/repo` for
+/// `https://user.github.io/repo/`, empty for a site at the root.
+pub(crate) fn base_path(site: &Site) -> &str {
+    let base_url = site.config.base_url.trim_end_matches('/');
+    let after_scheme = base_url
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(base_url);
+    after_scheme
+        .find('/')
+        .map(|index| &after_scheme[index..])
+        .unwrap_or("")
+}
+
+/// Every redirect declared by the site as `(source, target)` pairs sorted
+/// by source, with chains flattened. Both are site-relative (`/old/`),
+/// except targets on other sites. Used for host redirect rules such as
+/// `vercel.json`.
+pub fn flattened_redirects(site: &Site) -> Vec<(String, String)> {
+    let redirects = redirect_map(site);
+    redirects
+        .keys()
+        .map(|source| (source.clone(), final_hop(&redirects, source)))
+        .collect()
+}
+
 const WINDOWS_RESERVED_NAMES: &[&str] = &[
     "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
     "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
@@ -67,45 +262,53 @@ fn build_redirect_html(target_url: &str) -> String {
     )
 }
 
-fn write_redirect(output_dir: &Path, redirect_path: &str, target_url: &str) -> Result<()> {
+fn write_redirect(
+    output: &(impl RenderSink + ?Sized),
+    redirect_path: &str,
+    target_url: &str,
+) -> Result<()> {
     let clean_path = redirect_path.trim_matches('/');
     if !is_safe_redirect_path(clean_path) {
         return Ok(());
     }
-    let redirect_dir = output_dir.join(clean_path);
-    if redirect_dir.join("index.html").exists() {
+    let redirect_path = Path::new(clean_path).join("index.html");
+    if output.file_exists(&redirect_path) {
         return Ok(());
     }
-    fs::create_dir_all(&redirect_dir)?;
-    fs::write(
-        redirect_dir.join("index.html"),
-        build_redirect_html(target_url),
-    )?;
-    Ok(())
+    output.write_file(&redirect_path, build_redirect_html(target_url).as_bytes())
 }
 
-/// Writes an HTML redirect stub into `output_dir` for every
-/// `redirect_from` entry declared across the site's pages and posts.
-pub fn generate_redirects(site: &Site, output_dir: &Path) -> Result<()> {
-    let base_url = site.config.base_url.trim_end_matches('/');
-
-    for post in &site.posts {
-        let target_url = format!("{}/posts/{}/", base_url, post.content.slug);
-        for redirect_path in &post.redirect_from {
-            write_redirect(output_dir, redirect_path, &target_url)?;
-        }
-    }
+/// Writes an HTML redirect stub into `output` for every
+/// `redirect_from` entry declared across the site's pages and posts,
+/// pointing at the end of its redirect chain.
+pub fn generate_redirects(site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
+    let redirects = redirect_map(site);
 
-    for page in &site.pages {
-        let target_url = format!("{}/{}/", base_url, page.content.slug);
-        for redirect_path in &page.redirect_from {
-            write_redirect(output_dir, redirect_path, &target_url)?;
-        }
+    let redirect_from = site
+        .posts
+        .iter()
+        .flat_map(|post| &post.redirect_from)
+        .chain(site.pages.iter().flat_map(|page| &page.redirect_from));
+    for redirect_path in redirect_from {
+        let source = normalize(site, redirect_path);
+        let target_url = final_target(site, &redirects, &source);
+        write_redirect(output, redirect_path, &target_url)?;
     }
 
     Ok(())
 }
 
+/// Writes `content`, which has `redirect_to` frontmatter, as a redirect
+/// stub at its own output path.
+pub(crate) fn render_redirect_to(
+    site: &Site,
+    content: &Content,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    let target_url = final_target(site, &redirect_map(site), &normalize(site, &content.url));
+    output.write_file(&content.path, build_redirect_html(&target_url).as_bytes())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,20 +328,54 @@ mod tests {
                 posts_per_page: 10,
                 minify: false,
                 fingerprint: false,
+                dedupe_assets: None,
+                static_ignore: Vec::new(),
+                assets: crate::assets::AssetsConfig::default(),
+                build_manifest: false,
+                search_format: crate::search::SearchFormat::Json,
+                search_exclude: Vec::new(),
                 images: None,
+                social_images: None,
                 syntax_theme: crate::types::default_syntax_theme(),
                 taxonomies: crate::types::default_taxonomies(),
                 math: false,
                 favicon: None,
                 link_check_ignore: Vec::new(),
+                link_archive: None,
+                precompress: None,
+                fonts: Vec::new(),
+                build: crate::build_hooks::BuildConfig::default(),
+                deploy: crate::deploy::DeployConfig::default(),
+                remote: crate::remote::RemoteConfig::default(),
+                feed: crate::feeds::FeedConfig::default(),
+                sitemap: crate::sitemap::SitemapConfig::default(),
+                wellknown: crate::wellknown::WellKnownConfig::default(),
+                manifest: None,
+                llms: None,
+                vercel: None,
+                htaccess: None,
+                headers: None,
+                newsletter: None,
+                discussions: crate::discussions::DiscussionConfig::default(),
+                link_previews: crate::link_previews::LinkPreviewConfig::default(),
+                changelog: None,
+                external_resources: None,
+                generate: crate::types::GenerateConfig::default(),
+                drafts: false,
+                future: false,
+                generated_pages: Vec::new(),
+                outputs: Vec::new(),
+                jobs: None,
                 extra: HashMap::new(),
             },
             home: None,
             pages: vec![],
             posts: vec![],
+            notes: vec![],
             collections: HashMap::new(),
             data: HashMap::new(),
             assets: vec![],
+            image_requests: Vec::new(),
         }
     }
 
@@ -161,10 +398,15 @@ mod tests {
                 raw_content: String::new(),
                 frontmatter: Frontmatter::default(),
                 path: PathBuf::from("posts/new-post/index.html"),
+                source_path: PathBuf::new(),
+                modified: None,
                 template: None,
                 weight: 0,
                 word_count: 0,
                 reading_time: 0,
+                stats: crate::types::PageStats::default(),
+                lang: None,
+                translations: Vec::new(),
                 toc: vec![],
                 url: "/posts/new-post/".to_string(),
             },
@@ -175,6 +417,8 @@ mod tests {
             categories: vec![],
             taxonomies_map: std::collections::HashMap::new(),
             redirect_from: vec!["/old-post/".to_string()],
+            discussion: None,
+            link_preview: None,
         });
 
         let output_dir = tempfile::TempDir::new().unwrap();
@@ -198,10 +442,15 @@ mod tests {
                 raw_content: String::new(),
                 frontmatter: Frontmatter::default(),
                 path: PathBuf::from("new-page/index.html"),
+                source_path: PathBuf::new(),
+                modified: None,
                 template: None,
                 weight: 0,
                 word_count: 0,
                 reading_time: 0,
+                stats: crate::types::PageStats::default(),
+                lang: None,
+                translations: Vec::new(),
                 toc: vec![],
                 url: "/new-page/".to_string(),
             },
@@ -216,6 +465,104 @@ mod tests {
         assert!(redirect_file.exists());
     }
 
+    fn page(slug: &str, frontmatter: serde_json::Value, redirect_from: &[&str]) -> Page {
+        let frontmatter = match frontmatter {
+            serde_json::Value::Object(map) => Frontmatter::new(map.into_iter().collect()),
+            _ => Frontmatter::default(),
+        };
+        Page {
+            content: Content {
+                slug: slug.to_string(),
+                title: slug.to_string(),
+                html: String::new(),
+                raw_content: String::new(),
+                frontmatter,
+                path: PathBuf::from(format!("{slug}/index.html")),
+                source_path: PathBuf::new(),
+                modified: None,
+                template: None,
+                weight: 0,
+                word_count: 0,
+                reading_time: 0,
+                stats: crate::types::PageStats::default(),
+                lang: None,
+                translations: Vec::new(),
+                toc: vec![],
+                url: format!("/{slug}/"),
+            },
+            draft: false,
+            redirect_from: redirect_from.iter().map(|path| path.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_redirect_chains_are_flattened() {
+        let mut site = minimal_site();
+        // `/a/` was renamed to `/b/`, which was later moved to `/c/` with a
+        // `redirect_to` stub left behind.
+        site.pages.push(page(
+            "b",
+            serde_json::json!({"redirect_to": "/c"}),
+            &["/a/"],
+        ));
+        site.pages.push(page("c", serde_json::json!({}), &[]));
+
+        assert_eq!(
+            redirect_chains(&site),
+            vec![RedirectChain {
+                hops: vec!["/a/".to_string(), "/b/".to_string(), "/c/".to_string()],
+                cycle: false,
+            }]
+        );
+        assert_eq!(
+            redirect_chains(&site)[0].to_string(),
+            "redirect chain /a/ -> /b/ -> /c/ (flattened to /c/)"
+        );
+
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_redirects(&site, output_dir.path()).unwrap();
+        render_redirect_to(&site, &site.pages[0].content, output_dir.path()).unwrap();
+
+        let stub = std::fs::read_to_string(output_dir.path().join("a/index.html")).unwrap();
+        assert!(stub.contains("url=https://example.com/c/"));
+        let stub = std::fs::read_to_string(output_dir.path().join("b/index.html")).unwrap();
+        assert!(stub.contains("url=https://example.com/c/"));
+
+        assert_eq!(
+            flattened_redirects(&site),
+            vec![
+                ("/a/".to_string(), "/c/".to_string()),
+                ("/b/".to_string(), "/c/".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redirect_loops_and_real_pages() {
+        let mut site = minimal_site();
+        site.pages.push(page(
+            "x",
+            serde_json::json!({"redirect_to": "https://example.com/y/"}),
+            &[],
+        ));
+        site.pages
+            .push(page("y", serde_json::json!({"redirect_to": "/x/"}), &[]));
+        // `/z/` is a real page, so `redirect_from = ["/z/"]` never takes
+        // effect and doesn't extend any chain.
+        site.pages.push(page("z", serde_json::json!({}), &[]));
+        site.pages.push(page("w", serde_json::json!({}), &["/z/"]));
+
+        let chains = redirect_chains(&site);
+        assert_eq!(chains.len(), 2);
+        assert!(chains.iter().all(|chain| chain.cycle));
+        assert_eq!(chains[0].to_string(), "redirect loop /x/ -> /y/ -> /x/");
+
+        let output_dir = tempfile::TempDir::new().unwrap();
+        render_redirect_to(&site, &site.pages[0].content, output_dir.path()).unwrap();
+        let stub = std::fs::read_to_string(output_dir.path().join("x/index.html")).unwrap();
+        assert!(stub.contains("url=https://example.com/y/"));
+    }
+
     #[test]
     fn test_unsafe_path_rejection() {
         assert!(!is_safe_redirect_path("../etc/passwd"));
@@ -242,10 +589,15 @@ mod tests {
                 raw_content: String::new(),
                 frontmatter: Frontmatter::default(),
                 path: PathBuf::from("posts/post/index.html"),
+                source_path: PathBuf::new(),
+                modified: None,
                 template: None,
                 weight: 0,
                 word_count: 0,
                 reading_time: 0,
+                stats: crate::types::PageStats::default(),
+                lang: None,
+                translations: Vec::new(),
                 toc: vec![],
                 url: "/posts/post/".to_string(),
             },
@@ -256,6 +608,8 @@ mod tests {
             categories: vec![],
             taxonomies_map: std::collections::HashMap::new(),
             redirect_from: vec!["/existing/".to_string()],
+            discussion: None,
+            link_preview: None,
         });
 
         let output_dir = tempfile::TempDir::new().unwrap();
@@ -0,0 +1,551 @@
+//! Shared HTTP access for build steps that need the network (feed import,
+//! archive lookups, API deploys, and future remote data / link checks).
+//! Requests are delegated to the system `curl` binary so the crate doesn't
+//! carry a TLS stack of its own.
+//!
+//! A [`RemoteClient`] retries transient failures with exponential backoff,
+//! caps the number of requests in flight, and can be switched to offline
+//! mode so builds degrade gracefully without network access.
+
+use crate::error::{BambooError, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+/// Environment variable that forces offline mode when set to `1` or
+/// `true`.
+pub const OFFLINE_ENV_VAR: &str = "BAMBOO_OFFLINE";
+
+/// Longest delay between retries, regardless of attempt number.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Separates the body from the headers and status curl writes after it.
+const WRITE_OUT_MARKER: &str = "\n--bamboo-response-headers--\n";
+
+/// `[remote]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    /// Extra attempts after the first failed one. Defaults to 3.
+    pub retries: u32,
+    /// Delay before the first retry in milliseconds; doubles on each
+    /// subsequent retry. Defaults to 500.
+    pub backoff_ms: u64,
+    /// Maximum number of requests in flight at once. Defaults to 4.
+    pub max_connections: usize,
+    /// Per-request timeout in seconds. Defaults to 30.
+    pub timeout_secs: u64,
+    /// If `true`, every request fails immediately with
+    /// [`BambooError::Offline`].
+    pub offline: bool,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            backoff_ms: 500,
+            max_connections: 4,
+            timeout_secs: 30,
+            offline: false,
+        }
+    }
+}
+
+/// HTTP client shared by every networked build step. Safe to use from
+/// multiple threads; at most `max_connections` requests run concurrently.
+#[derive(Debug)]
+pub struct RemoteClient {
+    config: RemoteConfig,
+    in_flight: Mutex<usize>,
+    slot_freed: Condvar,
+}
+
+impl Default for RemoteClient {
+    fn default() -> Self {
+        Self::new(RemoteConfig::default())
+    }
+}
+
+impl RemoteClient {
+    /// Creates a client from `config`. Offline mode is also enabled when
+    /// the `BAMBOO_OFFLINE` environment variable is `1` or `true`.
+    pub fn new(config: RemoteConfig) -> Self {
+        let offline = config.offline || offline_from_env();
+        Self {
+            config: RemoteConfig { offline, ..config },
+            in_flight: Mutex::new(0),
+            slot_freed: Condvar::new(),
+        }
+    }
+
+    /// Forces offline mode on or off, overriding config and environment.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.config.offline = offline;
+        self
+    }
+
+    /// Returns `true` if requests are disabled.
+    pub fn is_offline(&self) -> bool {
+        self.config.offline
+    }
+
+    /// Fetches `url` and returns the response body as text.
+    pub fn fetch_text(&self, url: &str) -> Result<String> {
+        let body = self.fetch_bytes(url)?;
+        String::from_utf8(body).map_err(|error| BambooError::Remote {
+            url: url.to_string(),
+            message: error.to_string(),
+        })
+    }
+
+    /// Fetches `url` and returns the raw response body. Redirects are
+    /// followed. Connection failures, timeouts, 408, 429, and 5xx
+    /// responses are retried; other non-2xx statuses fail immediately.
+    pub fn fetch_bytes(&self, url: &str) -> Result<Vec<u8>> {
+        self.fetch_response(url).map(|response| response.body)
+    }
+
+    /// [`RemoteClient::fetch_bytes`], also returning the status and the
+    /// headers of the final response.
+    pub fn fetch_response(&self, url: &str) -> Result<Response> {
+        if self.config.offline {
+            return Err(BambooError::Offline {
+                url: url.to_string(),
+            });
+        }
+
+        let mut attempt = 0;
+        loop {
+            let outcome = {
+                let _slot = self.acquire_slot();
+                self.attempt(url)
+            };
+            match outcome {
+                Ok(response) => return Ok(response),
+                Err(failure) if failure.retryable && attempt < self.config.retries => {
+                    std::thread::sleep(backoff_delay(self.config.backoff_ms, attempt));
+                    attempt += 1;
+                }
+                Err(failure) => {
+                    let message = if attempt > 0 {
+                        format!("{} (after {} attempts)", failure.message, attempt + 1)
+                    } else {
+                        failure.message
+                    };
+                    return Err(BambooError::Remote {
+                        url: url.to_string(),
+                        message,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Sends `request`, which unlike [`RemoteClient::fetch_bytes`] may use
+    /// any method, headers, and body. Requests aren't retried, since they
+    /// may not be idempotent, and aren't limited by `timeout_secs` once
+    /// connected, so large uploads can finish. A non-2xx response fails
+    /// with the start of its body, where APIs explain the problem.
+    pub fn send(&self, request: &Request) -> Result<Response> {
+        let remote_error = |message: String| BambooError::Remote {
+            url: request.url.to_string(),
+            message,
+        };
+        if self.config.offline {
+            return Err(BambooError::Offline {
+                url: request.url.to_string(),
+            });
+        }
+
+        let timeout = self.config.timeout_secs.to_string();
+        let mut args = vec![
+            OsString::from("--request"),
+            request.method.into(),
+            "--connect-timeout".into(),
+            timeout.into(),
+        ];
+        if let Some(body) = request.body {
+            let mut data = OsString::from("@");
+            data.push(body);
+            args.extend(["--data-binary".into(), data]);
+        }
+        let headers: String = request
+            .headers
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}\n"))
+            .collect();
+        let response = {
+            let _slot = self.acquire_slot();
+            self.curl(request.url, &args, &headers)
+        }
+        .map_err(|failure| remote_error(failure.message))?;
+
+        if response.status == 0 || (200..300).contains(&response.status) {
+            return Ok(response);
+        }
+        let body = String::from_utf8_lossy(&response.body);
+        let body: String = body.trim().chars().take(200).collect();
+        Err(remote_error(if body.is_empty() {
+            format!("HTTP status {}", response.status)
+        } else {
+            format!("HTTP status {}: {body}", response.status)
+        }))
+    }
+
+    fn attempt(&self, url: &str) -> std::result::Result<Response, Failure> {
+        let timeout = self.config.timeout_secs.to_string();
+        let response = self.curl(url, &["--max-time".into(), timeout.into()], "")?;
+
+        // Non-HTTP schemes (e.g. `file://`) report status 000.
+        if response.status == 0 || (200..300).contains(&response.status) {
+            Ok(response)
+        } else {
+            let status = response.status;
+            Err(Failure {
+                message: format!("HTTP status {status}"),
+                retryable: is_retryable_status(status),
+            })
+        }
+    }
+
+    /// Runs curl for `url` with `args`, passing `headers` (`Name: value`
+    /// lines) on stdin so secrets such as API tokens stay out of the
+    /// process list. Returns the response whatever its status.
+    fn curl(
+        &self,
+        url: &str,
+        args: &[OsString],
+        headers: &str,
+    ) -> std::result::Result<Response, Failure> {
+        let write_out = format!("{WRITE_OUT_MARKER}%{{header_json}}%{{http_code}}");
+        let mut command = Command::new("curl");
+        command
+            .args(["--silent", "--show-error", "--location"])
+            .args(args)
+            .args(["--write-out", &write_out]);
+        if headers.is_empty() {
+            command.stdin(Stdio::null());
+        } else {
+            command.args(["--header", "@-"]).stdin(Stdio::piped());
+        }
+        let spawn_failure = |error: std::io::Error| Failure {
+            message: format!("failed to run curl: {error}"),
+            retryable: false,
+        };
+        let mut child = command
+            .arg(url)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(spawn_failure)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(headers.as_bytes()).map_err(spawn_failure)?;
+        }
+        let output = child.wait_with_output().map_err(spawn_failure)?;
+
+        if !output.status.success() {
+            return Err(Failure {
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                retryable: output.status.code().is_some_and(is_retryable_exit_code),
+            });
+        }
+        Ok(parse_output(output.stdout))
+    }
+
+    fn acquire_slot(&self) -> Slot<'_> {
+        let limit = self.config.max_connections.max(1);
+        let mut in_flight = self
+            .in_flight
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        while *in_flight >= limit {
+            in_flight = self
+                .slot_freed
+                .wait(in_flight)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        *in_flight += 1;
+        Slot { client: self }
+    }
+}
+
+/// A request for [`RemoteClient::send`].
+#[derive(Debug, Clone)]
+pub struct Request<'a> {
+    /// HTTP method, e.g. `POST`.
+    pub method: &'a str,
+    /// URL to send the request to.
+    pub url: &'a str,
+    /// Request headers.
+    pub headers: Vec<(String, String)>,
+    /// File whose contents are the request body.
+    pub body: Option<&'a Path>,
+}
+
+impl<'a> Request<'a> {
+    /// A `method` request to `url` with no headers or body.
+    pub fn new(method: &'a str, url: &'a str) -> Self {
+        Self {
+            method,
+            url,
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    /// Adds a header.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sends the contents of `path` as the body.
+    pub fn body(mut self, path: &'a Path) -> Self {
+        self.body = Some(path);
+        self
+    }
+}
+
+/// A fetched response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Response {
+    /// HTTP status, or 0 for non-HTTP schemes such as `file://`.
+    pub status: u16,
+    /// Headers of the final response after redirects, with lowercase
+    /// names. Empty if the installed curl predates `header_json` (7.83).
+    pub headers: Vec<(String, String)>,
+    /// Response body.
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// Value of the first header called `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Splits curl's stdout into the body, the `header_json` object, and the
+/// three-digit status written after [`WRITE_OUT_MARKER`].
+fn parse_output(mut stdout: Vec<u8>) -> Response {
+    let marker = WRITE_OUT_MARKER.as_bytes();
+    let Some(start) = stdout
+        .windows(marker.len())
+        .rposition(|window| window == marker)
+    else {
+        return Response {
+            body: stdout,
+            ..Response::default()
+        };
+    };
+    let trailer = String::from_utf8_lossy(&stdout[start + marker.len()..]).into_owned();
+    stdout.truncate(start);
+
+    let split = trailer.len().saturating_sub(3);
+    let status = trailer
+        .get(split..)
+        .and_then(|code| code.parse().ok())
+        .unwrap_or(0);
+    let headers = trailer
+        .get(..split)
+        .and_then(|json| {
+            serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(json).ok()
+        })
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|(name, values)| {
+            let values = match values {
+                serde_json::Value::Array(values) => values,
+                value => vec![value],
+            };
+            values
+                .into_iter()
+                .filter_map(|value| value.as_str().map(str::to_string))
+                .map(move |value| (name.to_ascii_lowercase(), value))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    Response {
+        status,
+        headers,
+        body: stdout,
+    }
+}
+
+struct Failure {
+    message: String,
+    retryable: bool,
+}
+
+/// Releases a connection slot when dropped.
+struct Slot<'a> {
+    client: &'a RemoteClient,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self
+            .client
+            .in_flight
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *in_flight -= 1;
+        self.client.slot_freed.notify_one();
+    }
+}
+
+fn offline_from_env() -> bool {
+    std::env::var(OFFLINE_ENV_VAR)
+        .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    status == 408 || status == 429 || (500..600).contains(&status)
+}
+
+/// Whether curl exit code `code` is a network failure worth retrying: a
+/// host that didn't resolve or answer, a timeout, or a connection that
+/// broke mid-transfer. Bad URLs, unsupported protocols, and unreadable
+/// `file://` paths fail the same way every time.
+fn is_retryable_exit_code(code: i32) -> bool {
+    matches!(code, 5 | 6 | 7 | 16 | 18 | 28 | 35 | 52 | 55 | 56 | 92 | 95)
+}
+
+/// Delay before retry number `attempt` (zero-based): `base_ms * 2^attempt`,
+/// capped at 30 seconds.
+fn backoff_delay(base_ms: u64, attempt: u32) -> Duration {
+    let multiplier = 1u64.checked_shl(attempt).unwrap_or(u64::MAX);
+    Duration::from_millis(base_ms.saturating_mul(multiplier)).min(MAX_BACKOFF)
+}
+
+/// Percent-encodes `input` for use as a single query-string value.
+pub fn encode_query_value(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_query_value() {
+        assert_eq!(
+            encode_query_value("https://example.com/a b?x=1"),
+            "https%3A%2F%2Fexample.com%2Fa%20b%3Fx%3D1"
+        );
+        assert_eq!(encode_query_value("plain-text_1.0~"), "plain-text_1.0~");
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles_and_caps() {
+        assert_eq!(backoff_delay(500, 0), Duration::from_millis(500));
+        assert_eq!(backoff_delay(500, 1), Duration::from_millis(1000));
+        assert_eq!(backoff_delay(500, 3), Duration::from_millis(4000));
+        assert_eq!(backoff_delay(500, 20), MAX_BACKOFF);
+        assert_eq!(backoff_delay(500, 200), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(is_retryable_status(503));
+        assert!(is_retryable_status(429));
+        assert!(!is_retryable_status(404));
+        assert!(!is_retryable_status(301));
+    }
+
+    #[test]
+    fn test_retryable_exit_codes() {
+        for timeout_or_connection in [6, 7, 28, 52, 56] {
+            assert!(is_retryable_exit_code(timeout_or_connection));
+        }
+        for permanent in [1, 3, 37, 60] {
+            assert!(!is_retryable_exit_code(permanent));
+        }
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let mut stdout = b"<p>body</p>".to_vec();
+        stdout.extend_from_slice(WRITE_OUT_MARKER.as_bytes());
+        stdout
+            .extend_from_slice(br#"{"content-type":["text/html"],"Set-Cookie":["a=1","b=2"]}200"#);
+        let response = parse_output(stdout);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"<p>body</p>");
+        assert_eq!(response.header("Content-Type"), Some("text/html"));
+        assert_eq!(
+            response
+                .headers
+                .iter()
+                .filter(|(name, _)| name == "set-cookie")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_offline_mode_skips_request() {
+        let client = RemoteClient::default().offline(true);
+        let error = client.fetch_text("https://example.com/").unwrap_err();
+        assert!(matches!(error, BambooError::Offline { .. }));
+    }
+
+    #[test]
+    fn test_fetch_retries_only_network_failures() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("body.txt");
+        std::fs::write(&path, "hello").unwrap();
+        let client = RemoteClient::new(RemoteConfig {
+            retries: 2,
+            backoff_ms: 0,
+            ..RemoteConfig::default()
+        })
+        .offline(false);
+
+        let body = client
+            .fetch_text(&format!("file://{}", path.display()))
+            .unwrap();
+        assert_eq!(body, "hello");
+
+        let missing = format!("file://{}", dir.path().join("missing.txt").display());
+        let error = client.fetch_text(&missing).unwrap_err().to_string();
+        assert!(!error.contains("attempts"), "{error}");
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let refused = format!("http://127.0.0.1:{port}/");
+        let error = client.fetch_text(&refused).unwrap_err().to_string();
+        assert!(error.contains("after 3 attempts"), "{error}");
+
+        let url = format!("file://{}", path.display());
+        let request = Request::new("GET", &url).header("Authorization", "Bearer secret");
+        assert_eq!(client.send(&request).unwrap().body, b"hello");
+        let error = client
+            .send(&Request::new("GET", &missing))
+            .unwrap_err()
+            .to_string();
+        assert!(!error.contains("attempts"), "{error}");
+    }
+}
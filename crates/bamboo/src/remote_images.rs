@@ -0,0 +1,338 @@
+//! Local copies of remote images. With `[images] remote = true`,
+//! `bamboo build` downloads every `http(s)` image referenced from a
+//! content body's `<img>` tags or from `image` / `og_image` frontmatter,
+//! serves it from `/images/remote/`, and lets the `[images]` pipeline
+//! resize it like any local image. Pages stop hotlinking other hosts and
+//! keep their images when those hosts go down.
+//!
+//! Downloads are cached in `.bamboo-cache/remote-images/`. Cached URLs are
+//! never fetched again, and offline builds use whatever the cache has; an
+//! image that can't be fetched keeps its remote URL.
+
+use crate::error::{IoContext, Result};
+use crate::remote::RemoteClient;
+use crate::resources::{attribute_value, tags};
+use crate::types::{Asset, Content, Site};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CACHE_FILE_NAME: &str = "remote-images.json";
+const CACHE_DIR_NAME: &str = "remote-images";
+
+/// Output directory, relative to the site root, that downloaded images
+/// are served from.
+pub const OUTPUT_DIR: &str = "images/remote";
+
+/// Frontmatter fields holding an image URL.
+const IMAGE_FIELDS: &[&str] = &["image", "og_image"];
+
+/// Cached file names keyed by remote URL.
+pub type RemoteImageCache = HashMap<String, String>;
+
+/// Loads the download index from `cache_dir`, returning an empty cache if
+/// the file is missing or malformed.
+pub fn load_remote_image_cache(cache_dir: &Path) -> RemoteImageCache {
+    fs::read_to_string(cache_dir.join(CACHE_FILE_NAME))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Writes the download index to `cache_dir/remote-images.json`.
+pub fn save_remote_image_cache(cache_dir: &Path, cache: &RemoteImageCache) -> Result<()> {
+    fs::create_dir_all(cache_dir).io_context("create_dir_all", cache_dir)?;
+    let path = cache_dir.join(CACHE_FILE_NAME);
+    let content = serde_json::to_string_pretty(cache)
+        .map_err(|error| std::io::Error::other(error.to_string()))?;
+    fs::write(&path, content).io_context("write", &path)?;
+    Ok(())
+}
+
+/// Downloads the remote images referenced by every page, post, note, and
+/// collection entry into `cache_dir`, points their `<img>` tags and image
+/// frontmatter at the local copies, and adds those copies to
+/// [`Site::assets`]. URLs missing from the cache are fetched through
+/// `client`. Returns the number of images served locally.
+pub fn fetch_remote_images(
+    client: &RemoteClient,
+    site: &mut Site,
+    cache_dir: &Path,
+) -> Result<usize> {
+    let mut cache = load_remote_image_cache(cache_dir);
+    let files_dir = cache_dir.join(CACHE_DIR_NAME);
+    cache.retain(|_, file_name| files_dir.join(file_name).is_file());
+
+    let mut urls = BTreeSet::new();
+    for content in contents_mut(site) {
+        urls.extend(remote_image_urls(content));
+    }
+
+    let mut cache_changed = false;
+    for url in &urls {
+        if cache.contains_key(url) || client.is_offline() {
+            continue;
+        }
+        if let Some(file_name) = download(client, url, &files_dir)? {
+            cache.insert(url.clone(), file_name);
+            cache_changed = true;
+        }
+    }
+
+    let local: HashMap<&str, String> = urls
+        .iter()
+        .filter_map(|url| {
+            let file_name = cache.get(url)?;
+            Some((url.as_str(), format!("/{OUTPUT_DIR}/{file_name}")))
+        })
+        .collect();
+    for content in contents_mut(site) {
+        localize(content, &local);
+    }
+
+    let served: BTreeSet<&String> = local.keys().filter_map(|url| cache.get(*url)).collect();
+    for file_name in &served {
+        site.assets.push(Asset {
+            source: files_dir.join(file_name),
+            dest: PathBuf::from(OUTPUT_DIR).join(file_name),
+        });
+    }
+
+    if cache_changed {
+        save_remote_image_cache(cache_dir, &cache)?;
+    }
+    Ok(served.len())
+}
+
+fn contents_mut(site: &mut Site) -> impl Iterator<Item = &mut Content> {
+    site.home
+        .iter_mut()
+        .chain(site.pages.iter_mut())
+        .map(|page| &mut page.content)
+        .chain(site.posts.iter_mut().map(|post| &mut post.content))
+        .chain(site.notes.iter_mut().map(|note| &mut note.content))
+        .chain(site.collections.values_mut().flat_map(|collection| {
+            collection
+                .index
+                .iter_mut()
+                .chain(collection.items.iter_mut().map(|item| &mut item.content))
+        }))
+}
+
+/// Remote URLs of the `<img>` tags in `content`'s body and of its image
+/// frontmatter, unescaped.
+fn remote_image_urls(content: &Content) -> Vec<String> {
+    let lowercase = content.html.to_ascii_lowercase();
+    tags(&content.html, &lowercase, "<img")
+        .into_iter()
+        .filter_map(|tag| attribute_value(tag, "src"))
+        .map(|src| crate::xml::unescape(&src))
+        .chain(
+            IMAGE_FIELDS
+                .iter()
+                .filter_map(|field| content.frontmatter.get_string(field)),
+        )
+        .filter(|url| is_remote(url))
+        .collect()
+}
+
+/// Rewrites `content`'s `<img>` sources and image frontmatter found in
+/// `local` (remote URL → local path).
+fn localize(content: &mut Content, local: &HashMap<&str, String>) {
+    for field in IMAGE_FIELDS {
+        if let Some(path) = content
+            .frontmatter
+            .get_string(field)
+            .and_then(|url| local.get(url.as_str()))
+        {
+            content
+                .frontmatter
+                .raw
+                .insert(field.to_string(), serde_json::Value::String(path.clone()));
+        }
+    }
+
+    let html = &content.html;
+    let lowercase = html.to_ascii_lowercase();
+    let mut output = String::with_capacity(html.len());
+    let mut copied = 0;
+    for tag in tags(html, &lowercase, "<img") {
+        let Some(src) = attribute_value(tag, "src") else {
+            continue;
+        };
+        let Some(path) = local.get(crate::xml::unescape(&src).as_str()) else {
+            continue;
+        };
+        let start = tag.as_ptr() as usize - html.as_ptr() as usize;
+        output.push_str(&html[copied..start]);
+        output.push_str(&tag.replacen(&src, path, 1));
+        copied = start + tag.len();
+    }
+    if copied > 0 {
+        output.push_str(&html[copied..]);
+        content.html = output;
+    }
+}
+
+fn is_remote(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://") || url.starts_with("//")
+}
+
+/// Fetches `url` into `files_dir` as `<hash>.<extension>` and returns the
+/// file name, or `None` if the fetch fails or the response isn't an
+/// image.
+fn download(client: &RemoteClient, url: &str, files_dir: &Path) -> Result<Option<String>> {
+    let fetch_url = if url.starts_with("//") {
+        format!("https:{url}")
+    } else {
+        url.to_string()
+    };
+    let Ok(response) = client.fetch_response(&fetch_url) else {
+        return Ok(None);
+    };
+    let Some(extension) = image_extension(response.header("content-type"), url) else {
+        return Ok(None);
+    };
+
+    let hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+    let file_name = format!("{}.{extension}", &hash[..16]);
+    fs::create_dir_all(files_dir).io_context("create_dir_all", files_dir)?;
+    let path = files_dir.join(&file_name);
+    fs::write(&path, &response.body).io_context("write", &path)?;
+    Ok(Some(file_name))
+}
+
+/// File extension for an image served as `content_type`, falling back to
+/// the extension of `url`'s path when the type is missing or generic.
+/// Returns `None` for anything that isn't an image.
+fn image_extension(content_type: Option<&str>, url: &str) -> Option<&'static str> {
+    let mime = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|mime| mime.trim().to_ascii_lowercase());
+    let from_mime = match mime.as_deref() {
+        Some("image/jpeg" | "image/jpg" | "image/pjpeg") => Some("jpg"),
+        Some("image/png") => Some("png"),
+        Some("image/gif") => Some("gif"),
+        Some("image/webp") => Some("webp"),
+        Some("image/avif") => Some("avif"),
+        Some("image/svg+xml") => Some("svg"),
+        Some(mime) if mime.starts_with("image/") || mime == "application/octet-stream" => None,
+        Some(_) => return None,
+        None => None,
+    };
+    from_mime.or_else(|| {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "jpg" | "jpeg" => Some("jpg"),
+            "png" => Some("png"),
+            "gif" => Some("gif"),
+            "webp" => Some("webp"),
+            "avif" => Some("avif"),
+            "svg" => Some("svg"),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_extension() {
+        assert_eq!(
+            image_extension(Some("image/jpeg; charset=binary"), "https://x.test/a"),
+            Some("jpg")
+        );
+        assert_eq!(
+            image_extension(Some("image/webp"), "https://x.test/a.png"),
+            Some("webp")
+        );
+        assert_eq!(
+            image_extension(None, "https://x.test/photo.JPEG?w=800"),
+            Some("jpg")
+        );
+        assert_eq!(
+            image_extension(Some("application/octet-stream"), "https://x.test/a.png"),
+            Some("png")
+        );
+        assert_eq!(
+            image_extension(Some("text/html"), "https://x.test/a.png"),
+            None
+        );
+        assert_eq!(image_extension(None, "https://x.test/page"), None);
+    }
+
+    #[test]
+    fn test_fetch_remote_images_from_cache() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let url = "https://cdn.example.com/photo.jpg?w=800&h=600";
+        let mut cache = RemoteImageCache::new();
+        cache.insert(url.to_string(), "abc.jpg".to_string());
+        cache.insert(
+            "https://cdn.example.com/gone.png".to_string(),
+            "gone.png".to_string(),
+        );
+        save_remote_image_cache(cache_dir.path(), &cache).unwrap();
+        let files_dir = cache_dir.path().join(CACHE_DIR_NAME);
+        fs::create_dir_all(&files_dir).unwrap();
+        fs::write(files_dir.join("abc.jpg"), b"jpeg").unwrap();
+
+        let mut site = crate::template_test::sample_site().unwrap();
+        let assets = site.assets.len();
+        let post = &mut site.posts[0].content;
+        post.html = concat!(
+            r#"<p><img src="https://cdn.example.com/photo.jpg?w=800&amp;h=600" alt="Photo"></p>"#,
+            r#"<img src="https://cdn.example.com/gone.png">"#,
+            r#"<img src="/images/local.png">"#,
+        )
+        .to_string();
+        post.frontmatter.raw.insert(
+            "image".to_string(),
+            serde_json::Value::String(url.to_string()),
+        );
+
+        let client = RemoteClient::default().offline(true);
+        let served = fetch_remote_images(&client, &mut site, cache_dir.path()).unwrap();
+        assert_eq!(served, 1);
+
+        let post = &site.posts[0].content;
+        assert_eq!(
+            post.html,
+            concat!(
+                r#"<p><img src="/images/remote/abc.jpg" alt="Photo"></p>"#,
+                r#"<img src="https://cdn.example.com/gone.png">"#,
+                r#"<img src="/images/local.png">"#,
+            )
+        );
+        assert_eq!(
+            post.frontmatter.get_string("image").as_deref(),
+            Some("/images/remote/abc.jpg")
+        );
+        assert_eq!(site.assets.len(), assets + 1);
+        let asset = site.assets.last().unwrap();
+        assert_eq!(asset.source, files_dir.join("abc.jpg"));
+        assert_eq!(asset.dest, Path::new("images/remote/abc.jpg"));
+    }
+
+    #[test]
+    fn test_fetch_remote_images_skips_failed_downloads() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let mut site = crate::template_test::sample_site().unwrap();
+        let html = r#"<img src="http://127.0.0.1:9/missing.png">"#;
+        site.posts[0].content.html = html.to_string();
+
+        let client = RemoteClient::new(crate::remote::RemoteConfig {
+            retries: 0,
+            timeout_secs: 2,
+            ..crate::remote::RemoteConfig::default()
+        })
+        .offline(false);
+        let served = fetch_remote_images(&client, &mut site, cache_dir.path()).unwrap();
+        assert_eq!(served, 0);
+        assert_eq!(site.posts[0].content.html, html);
+        assert!(load_remote_image_cache(cache_dir.path()).is_empty());
+    }
+}
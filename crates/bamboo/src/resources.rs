@@ -0,0 +1,439 @@
+//! Post-build check for third-party resources: walks the generated HTML
+//! and CSS and reports external scripts, stylesheets, and fonts whose URL
+//! isn't on the `[external_resources]` allowlist, so a theme or shortcode
+//! can't quietly start loading assets from a new host.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+const FONT_EXTENSIONS: [&str; 5] = ["woff2", "woff", "ttf", "otf", "eot"];
+
+/// `[external_resources]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ExternalResourceConfig {
+    /// Allowed sources. A bare host (`"fonts.gstatic.com"`) allows that
+    /// host and its subdomains; an entry with a scheme
+    /// (`"https://cdn.jsdelivr.net/npm/katex@"`) allows URLs starting
+    /// with it.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// If `true`, `bamboo build` fails when anything outside the
+    /// allowlist is loaded. Otherwise each one is printed as a warning.
+    #[serde(default)]
+    pub strict: bool,
+}
+
+impl ExternalResourceConfig {
+    /// Returns `true` if `url` (absolute or protocol-relative) matches an
+    /// allowlist entry.
+    pub fn allows(&self, url: &str) -> bool {
+        let url = match url.strip_prefix("//") {
+            Some(rest) => format!("https://{rest}"),
+            None => url.to_string(),
+        };
+        let host = url
+            .split("://")
+            .nth(1)
+            .and_then(|rest| rest.split(['/', '?', '#', ':']).next())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        self.allow.iter().any(|entry| {
+            if entry.contains("://") {
+                url.starts_with(entry.as_str())
+            } else {
+                let entry = entry.to_ascii_lowercase();
+                host == entry || host.ends_with(&format!(".{entry}"))
+            }
+        })
+    }
+}
+
+/// What kind of resource an external URL was loaded as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ResourceKind {
+    /// `<script src>` or a script preload.
+    Script,
+    /// `<link rel="stylesheet">`, a style preload, or a CSS `@import`.
+    Stylesheet,
+    /// A font preload or a font file referenced from CSS `url()`.
+    Font,
+}
+
+impl fmt::Display for ResourceKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::Script => "script",
+            Self::Stylesheet => "stylesheet",
+            Self::Font => "font",
+        })
+    }
+}
+
+/// A single disallowed resource found by [`check_external_resources`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceWarning {
+    /// Path of the HTML or CSS file that loads the resource, relative to
+    /// the output directory.
+    pub source: PathBuf,
+    /// The external URL as written.
+    pub url: String,
+    /// How the resource is loaded.
+    pub kind: ResourceKind,
+}
+
+impl fmt::Display for ResourceWarning {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source_display = self.source.to_string_lossy().replace('\\', "/");
+        write!(
+            formatter,
+            "external {} '{}' in {} is not on the allowlist",
+            self.kind, self.url, source_display
+        )
+    }
+}
+
+/// Walks every `.html` and `.css` file under `output_dir` and returns the
+/// external scripts, stylesheets, and fonts that `config` doesn't allow.
+/// URLs under `base_url` count as local. Each URL is reported once per
+/// file; results are sorted by file, then URL.
+pub fn check_external_resources(
+    output_dir: &Path,
+    base_url: &str,
+    config: &ExternalResourceConfig,
+) -> Vec<ResourceWarning> {
+    let base_url = base_url.trim_end_matches('/');
+    let mut warnings = Vec::new();
+    let mut seen: HashSet<(PathBuf, String)> = HashSet::new();
+
+    for entry in WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+    {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        let resources = match (extension, fs::read_to_string(path)) {
+            (Some("html"), Ok(content)) => extract_html_resources(&content),
+            (Some("css"), Ok(content)) => extract_css_resources(&content),
+            _ => continue,
+        };
+
+        let relative = path.strip_prefix(output_dir).unwrap_or(path).to_path_buf();
+        for (url, kind) in resources {
+            if !is_external(&url, base_url) || config.allows(&url) {
+                continue;
+            }
+            if seen.insert((relative.clone(), url.clone())) {
+                warnings.push(ResourceWarning {
+                    source: relative.clone(),
+                    url,
+                    kind,
+                });
+            }
+        }
+    }
+
+    warnings.sort_by(|a, b| a.source.cmp(&b.source).then_with(|| a.url.cmp(&b.url)));
+    warnings
+}
+
+fn is_external(url: &str, base_url: &str) -> bool {
+    let remote = url.starts_with("http://") || url.starts_with("https://") || url.starts_with("//");
+    remote && (base_url.is_empty() || !url.starts_with(base_url))
+}
+
+/// Resources loaded by `<script src>`, `<link>` stylesheets and preloads,
+/// and inline `<style>` blocks.
+fn extract_html_resources(html: &str) -> Vec<(String, ResourceKind)> {
+    let lowercase = html.to_ascii_lowercase();
+    let mut resources = Vec::new();
+
+    for tag in tags(html, &lowercase, "<script") {
+        if let Some(src) = attribute_value(tag, "src") {
+            resources.push((src, ResourceKind::Script));
+        }
+    }
+
+    for tag in tags(html, &lowercase, "<link") {
+        let Some(href) = attribute_value(tag, "href") else {
+            continue;
+        };
+        let rel = attribute_value(tag, "rel")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let rels: Vec<&str> = rel.split_whitespace().collect();
+        let kind = if rels.contains(&"stylesheet") {
+            Some(ResourceKind::Stylesheet)
+        } else if rels.contains(&"modulepreload") {
+            Some(ResourceKind::Script)
+        } else if rels.contains(&"preload") || rels.contains(&"prefetch") {
+            match attribute_value(tag, "as").as_deref() {
+                Some("script") => Some(ResourceKind::Script),
+                Some("style") => Some(ResourceKind::Stylesheet),
+                Some("font") => Some(ResourceKind::Font),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        if let Some(kind) = kind {
+            resources.push((href, kind));
+        }
+    }
+
+    let mut position = 0;
+    while let Some(offset) = lowercase[position..].find("<style") {
+        let start = position + offset;
+        let Some(body_offset) = lowercase[start..].find('>') else {
+            break;
+        };
+        let body_start = start + body_offset + 1;
+        let body_end = lowercase[body_start..]
+            .find("</style")
+            .map_or(html.len(), |end| body_start + end);
+        resources.extend(extract_css_resources(&html[body_start..body_end]));
+        position = body_end;
+    }
+
+    resources
+}
+
+/// Stylesheets pulled in with `@import` and font files referenced from
+/// `url()`. Other `url()`s, such as background images, are ignored.
+fn extract_css_resources(css: &str) -> Vec<(String, ResourceKind)> {
+    let mut resources = Vec::new();
+
+    let mut position = 0;
+    while let Some(offset) = css[position..].find("@import") {
+        position += offset + "@import".len();
+        let rest = css[position..].trim_start();
+        let rest = rest.strip_prefix("url(").unwrap_or(rest);
+        if let Some(url) = quoted_or_bare(rest, &[')', ';']) {
+            resources.push((url, ResourceKind::Stylesheet));
+        }
+    }
+
+    let mut position = 0;
+    while let Some(offset) = css[position..].find("url(") {
+        position += offset + "url(".len();
+        let Some(url) = quoted_or_bare(css[position..].trim_start(), &[')']) else {
+            continue;
+        };
+        let path = url.split(['?', '#']).next().unwrap_or("");
+        let is_font = path.rsplit_once('.').is_some_and(|(_, extension)| {
+            FONT_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+        });
+        if is_font {
+            resources.push((url, ResourceKind::Font));
+        }
+    }
+
+    resources
+}
+
+/// Reads a `"quoted"`, `'quoted'`, or bare value from the start of
+/// `input`, ending a bare value at whitespace or any of `terminators`.
+fn quoted_or_bare(input: &str, terminators: &[char]) -> Option<String> {
+    let value = match input.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let rest = &input[1..];
+            &rest[..rest.find(quote)?]
+        }
+        _ => {
+            let end = input
+                .find(|character: char| {
+                    character.is_whitespace() || terminators.contains(&character)
+                })
+                .unwrap_or(input.len());
+            &input[..end]
+        }
+    };
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Every opening tag in `html` starting with `prefix` (matched against
+/// `lowercase`, an ASCII-lowercased copy of `html`), without the
+/// trailing `>`.
+pub(crate) fn tags<'a>(html: &'a str, lowercase: &str, prefix: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut position = 0;
+    while let Some(offset) = lowercase[position..].find(prefix) {
+        let start = position + offset;
+        let name_end = start + prefix.len();
+        position = name_end;
+        let boundary = lowercase[name_end..].chars().next();
+        if !boundary.is_some_and(|character| character.is_whitespace() || character == '>') {
+            continue;
+        }
+        let Some(end) = lowercase[name_end..].find('>') else {
+            break;
+        };
+        found.push(&html[start..name_end + end]);
+        position = name_end + end;
+    }
+    found
+}
+
+/// Value of attribute `name` in `tag`, quoted or not (minified output
+/// drops quotes where it can).
+pub(crate) fn attribute_value(tag: &str, name: &str) -> Option<String> {
+    let lowercase = tag.to_ascii_lowercase();
+    let needle = format!("{name}=");
+    let mut position = 0;
+    while let Some(offset) = lowercase[position..].find(&needle) {
+        let start = position + offset;
+        position = start + needle.len();
+        let preceded_by_space = lowercase[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_whitespace);
+        if preceded_by_space {
+            return quoted_or_bare(&tag[position..], &['>']);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(allow: &[&str]) -> ExternalResourceConfig {
+        ExternalResourceConfig {
+            allow: allow.iter().map(|entry| entry.to_string()).collect(),
+            strict: false,
+        }
+    }
+
+    #[test]
+    fn test_allows_hosts_and_prefixes() {
+        let config = config(&["gstatic.com", "https://cdn.jsdelivr.net/npm/katex@"]);
+        assert!(config.allows("https://fonts.gstatic.com/s/inter.woff2"));
+        assert!(config.allows("//gstatic.com/font.woff2"));
+        assert!(config.allows("https://cdn.jsdelivr.net/npm/katex@0.16/dist/katex.min.css"));
+        assert!(!config.allows("https://cdn.jsdelivr.net/npm/other/x.js"));
+        assert!(!config.allows("https://notgstatic.com/x.js"));
+    }
+
+    #[test]
+    fn test_extract_html_resources() {
+        let html = r#"<head>
+<script src="https://cdn.example.com/app.js"></script>
+<script>inline()</script>
+<LINK rel="stylesheet" href='https://fonts.googleapis.com/css2?family=Inter'>
+<link rel=preload as=font href=https://fonts.gstatic.com/inter.woff2 crossorigin>
+<link rel="alternate" type="application/rss+xml" href="https://example.com/rss.xml">
+<link rel="canonical" href="https://example.com/">
+<style>@font-face { src: url("https://fonts.example.org/a.woff2") format("woff2"); }
+body { background: url(https://images.example.org/bg.png); }</style>
+</head>"#;
+        assert_eq!(
+            extract_html_resources(html),
+            vec![
+                (
+                    "https://cdn.example.com/app.js".to_string(),
+                    ResourceKind::Script
+                ),
+                (
+                    "https://fonts.googleapis.com/css2?family=Inter".to_string(),
+                    ResourceKind::Stylesheet
+                ),
+                (
+                    "https://fonts.gstatic.com/inter.woff2".to_string(),
+                    ResourceKind::Font
+                ),
+                (
+                    "https://fonts.example.org/a.woff2".to_string(),
+                    ResourceKind::Font
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_css_imports() {
+        let css =
+            r#"@import url("https://cdn.example.com/reset.css"); @import 'https://x.org/y.css';"#;
+        assert_eq!(
+            extract_css_resources(css),
+            vec![
+                (
+                    "https://cdn.example.com/reset.css".to_string(),
+                    ResourceKind::Stylesheet
+                ),
+                ("https://x.org/y.css".to_string(), ResourceKind::Stylesheet),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_external_resources() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("posts/hello")).unwrap();
+        fs::write(
+            dir.path().join("index.html"),
+            r#"<script src="https://example.com/local.js"></script>
+<script src="/also-local.js"></script>
+<script src="https://tracker.example.net/t.js"></script>
+<script src="https://tracker.example.net/t.js"></script>
+<link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/katex@0.16/katex.min.css">"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("posts/hello/index.html"),
+            r#"<link rel="stylesheet" href="//unpkg.com/lib.css">"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("style.css"),
+            "@font-face { src: url(https://fonts.gstatic.com/inter.woff2); }",
+        )
+        .unwrap();
+
+        let warnings = check_external_resources(
+            dir.path(),
+            "https://example.com/",
+            &config(&["cdn.jsdelivr.net"]),
+        );
+        let found: Vec<(String, &str, ResourceKind)> = warnings
+            .iter()
+            .map(|warning| {
+                (
+                    warning.source.to_string_lossy().replace('\\', "/"),
+                    warning.url.as_str(),
+                    warning.kind,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "index.html".to_string(),
+                    "https://tracker.example.net/t.js",
+                    ResourceKind::Script
+                ),
+                (
+                    "posts/hello/index.html".to_string(),
+                    "//unpkg.com/lib.css",
+                    ResourceKind::Stylesheet
+                ),
+                (
+                    "style.css".to_string(),
+                    "https://fonts.gstatic.com/inter.woff2",
+                    ResourceKind::Font
+                ),
+            ]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "external script 'https://tracker.example.net/t.js' in index.html is not on the allowlist"
+        );
+    }
+}
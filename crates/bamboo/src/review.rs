@@ -0,0 +1,166 @@
+//! Content ownership and review tracking. Pages, posts, and collection
+//! items may declare `owner = "..."` and `review_by = "YYYY-MM-DD"` in
+//! frontmatter; [`review_due`] lists everything whose review date has
+//! passed, grouped by owner.
+
+use crate::types::{Content, Site};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+/// Owner name used for content without an `owner` field.
+pub const UNOWNED: &str = "(unowned)";
+
+/// One piece of content that is due (or overdue) for review.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewItem {
+    /// Content title.
+    pub title: String,
+    /// Site-relative URL.
+    pub url: String,
+    /// The `review_by` date from frontmatter.
+    pub review_by: NaiveDate,
+    /// Whole days between `review_by` and the report date.
+    pub days_overdue: i64,
+}
+
+/// Result of [`review_due`].
+#[derive(Debug, Clone, Default)]
+pub struct ReviewReport {
+    /// Due items keyed by owner, each list sorted most-overdue first.
+    pub due: BTreeMap<String, Vec<ReviewItem>>,
+    /// Content whose `review_by` value couldn't be parsed, as
+    /// `(url, raw value)`.
+    pub invalid: Vec<(String, String)>,
+}
+
+impl ReviewReport {
+    /// Total number of due items across all owners.
+    pub fn due_count(&self) -> usize {
+        self.due.values().map(Vec::len).sum()
+    }
+}
+
+/// Collects content whose `review_by` date is on or before `today`.
+pub fn review_due(site: &Site, today: NaiveDate) -> ReviewReport {
+    let mut report = ReviewReport::default();
+    let contents = site
+        .home
+        .iter()
+        .chain(&site.pages)
+        .map(|page| &page.content)
+        .chain(site.posts.iter().map(|post| &post.content))
+        .chain(
+            site.collections
+                .values()
+                .flat_map(|collection| collection.items.iter().map(|item| &item.content)),
+        );
+
+    for content in contents {
+        let Some(raw) = content.frontmatter.get_string("review_by") else {
+            continue;
+        };
+        let Some(review_by) = parse_review_date(&raw) else {
+            report.invalid.push((content.url.clone(), raw));
+            continue;
+        };
+        if review_by > today {
+            continue;
+        }
+        report
+            .due
+            .entry(owner(content))
+            .or_default()
+            .push(ReviewItem {
+                title: content.title.clone(),
+                url: content.url.clone(),
+                review_by,
+                days_overdue: (today - review_by).num_days(),
+            });
+    }
+
+    for items in report.due.values_mut() {
+        items.sort_by(|a, b| {
+            a.review_by
+                .cmp(&b.review_by)
+                .then_with(|| a.url.cmp(&b.url))
+        });
+    }
+    report.invalid.sort();
+    report
+}
+
+fn owner(content: &Content) -> String {
+    content
+        .frontmatter
+        .get_string("owner")
+        .map(|owner| owner.trim().to_string())
+        .filter(|owner| !owner.is_empty())
+        .unwrap_or_else(|| UNOWNED.to_string())
+}
+
+/// Accepts `YYYY-MM-DD`, or a full RFC 3339 timestamp whose date part is
+/// used.
+fn parse_review_date(raw: &str) -> Option<NaiveDate> {
+    let raw = raw.trim();
+    NaiveDate::parse_from_str(raw.get(..10).unwrap_or(raw), "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SiteBuilder;
+    use std::fs;
+
+    #[test]
+    fn test_review_due_groups_by_owner() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("bamboo.toml"),
+            "title = \"T\"\nbase_url = \"https://example.com\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("content/posts")).unwrap();
+        fs::write(
+            dir.path().join("content/setup.md"),
+            "+++\ntitle = \"Setup\"\nowner = \"docs-team\"\nreview_by = \"2024-01-01\"\n+++\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/api.md"),
+            "+++\ntitle = \"API\"\nowner = \"docs-team\"\nreview_by = \"2024-03-01\"\n+++\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/future.md"),
+            "+++\ntitle = \"Future\"\nowner = \"docs-team\"\nreview_by = \"2030-01-01\"\n+++\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/posts/2024-01-01-note.md"),
+            "+++\ntitle = \"Note\"\nreview_by = \"2024-02-01\"\n+++\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/broken.md"),
+            "+++\ntitle = \"Broken\"\nreview_by = \"soon\"\n+++\n",
+        )
+        .unwrap();
+
+        let site = SiteBuilder::new(dir.path()).build().unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let report = review_due(&site, today);
+
+        assert_eq!(report.due_count(), 3);
+        let docs: Vec<&str> = report.due["docs-team"]
+            .iter()
+            .map(|item| item.title.as_str())
+            .collect();
+        assert_eq!(docs, vec!["Setup", "API"]);
+        assert_eq!(report.due["docs-team"][0].days_overdue, 152);
+        assert_eq!(report.due[UNOWNED][0].title, "Note");
+        assert_eq!(
+            report.invalid,
+            vec![("/broken/".to_string(), "soon".to_string())]
+        );
+    }
+}
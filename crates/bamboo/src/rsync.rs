@@ -0,0 +1,252 @@
+//! `bamboo deploy rsync`: uploading the output to a plain server over SSH
+//! with `rsync`.
+//!
+//! ```toml
+//! [deploy.rsync]
+//! host = "deploy@example.com"
+//! dest = "/var/www/example.com"
+//! ```
+//!
+//! Files are compared by checksum rather than modification time, since
+//! every build rewrites the whole output, so only files whose contents
+//! changed are transferred. Nothing on the server is deleted unless
+//! `delete = true`, and even then paths under `protect` (and `exclude`)
+//! are kept, at most `max_delete` files are removed per deploy, and an
+//! empty output directory is refused rather than mirrored.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::deploy::run_tool;
+use crate::error::{BambooError, Result};
+
+const TARGET: &str = "rsync";
+
+/// The `[deploy.rsync]` table in `bamboo.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RsyncConfig {
+    /// SSH destination, `user@host` or a `~/.ssh/config` alias. Without
+    /// one, `dest` is a local path, e.g. a mounted share.
+    pub host: Option<String>,
+    /// Directory on the server the site is served from. Required.
+    pub dest: String,
+    /// SSH port, if not 22.
+    pub port: Option<u16>,
+    /// SSH private key to authenticate with.
+    pub identity_file: Option<PathBuf>,
+    /// Delete files on the server that are no longer in the output.
+    /// Defaults to `false`.
+    pub delete: bool,
+    /// With `delete`, remove at most this many files per deploy; rsync
+    /// stops deleting and reports an error beyond it. Defaults to 1000.
+    pub max_delete: u32,
+    /// rsync patterns for server paths that `delete` never removes, such
+    /// as `.well-known/acme-challenge/` or uploaded files.
+    pub protect: Vec<String>,
+    /// rsync patterns for output files that aren't uploaded. Matching
+    /// server files are kept too.
+    pub exclude: Vec<String>,
+}
+
+impl Default for RsyncConfig {
+    fn default() -> Self {
+        Self {
+            host: None,
+            dest: String::new(),
+            port: None,
+            identity_file: None,
+            delete: false,
+            max_delete: 1000,
+            protect: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl RsyncConfig {
+    /// The rsync destination: `host:dest/`, or `dest/` without a host.
+    pub fn destination(&self) -> String {
+        let dest = format!("{}/", self.dest.trim_end_matches('/'));
+        match &self.host {
+            Some(host) => format!("{host}:{dest}"),
+            None => dest,
+        }
+    }
+}
+
+/// What a deploy transferred, or would transfer with `dry_run`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RsyncReport {
+    /// Files uploaded because they are new or changed.
+    pub uploaded: Vec<String>,
+    /// Files deleted from the server.
+    pub deleted: Vec<String>,
+}
+
+/// Arguments for `rsync` to upload `output_dir`.
+pub fn rsync_args(output_dir: &Path, config: &RsyncConfig, dry_run: bool) -> Vec<OsString> {
+    let mut args: Vec<OsString> = ["--recursive", "--links", "--perms", "--times"]
+        .into_iter()
+        .chain(["--compress", "--checksum", "--itemize-changes"])
+        .map(OsString::from)
+        .collect();
+    if dry_run {
+        args.push("--dry-run".into());
+    }
+    if config.host.is_some() && (config.port.is_some() || config.identity_file.is_some()) {
+        let mut ssh = String::from("ssh");
+        if let Some(port) = config.port {
+            ssh.push_str(&format!(" -p {port}"));
+        }
+        if let Some(identity) = &config.identity_file {
+            ssh.push_str(&format!(" -i '{}'", identity.display()));
+        }
+        args.push("--rsh".into());
+        args.push(ssh.into());
+    }
+    if config.delete {
+        args.push("--delete".into());
+        args.push(format!("--max-delete={}", config.max_delete).into());
+    }
+    for pattern in &config.protect {
+        args.push(format!("--filter=protect {pattern}").into());
+    }
+    for pattern in &config.exclude {
+        args.push(format!("--exclude={pattern}").into());
+    }
+
+    let mut source = output_dir.as_os_str().to_os_string();
+    if !source.to_string_lossy().ends_with(['/', '\\']) {
+        source.push("/");
+    }
+    args.push(source);
+    args.push(config.destination().into());
+    args
+}
+
+/// Uploads `output_dir` as configured, or with `dry_run` only reports what
+/// would change.
+pub fn deploy(output_dir: &Path, config: &RsyncConfig, dry_run: bool) -> Result<RsyncReport> {
+    let dest = config.dest.trim();
+    if dest.is_empty() || dest.trim_end_matches('/').is_empty() {
+        return Err(BambooError::Deploy {
+            target: TARGET,
+            message: "set `dest` under [deploy.rsync] to the directory to upload into".to_string(),
+        });
+    }
+    if config.delete && crate::verify::output_files(output_dir).is_empty() {
+        return Err(BambooError::Deploy {
+            target: TARGET,
+            message: format!(
+                "{} is empty; refusing to delete everything on the server",
+                output_dir.display()
+            ),
+        });
+    }
+
+    let itemized = run_tool(
+        TARGET,
+        Command::new("rsync").args(rsync_args(output_dir, config, dry_run)),
+    )?;
+    Ok(parse_itemized(&itemized))
+}
+
+/// Reads rsync's `--itemize-changes` output: `<f+++++++++ path` for an
+/// upload (`>f` when the destination is local) and `*deleting path`.
+fn parse_itemized(output: &str) -> RsyncReport {
+    let mut report = RsyncReport::default();
+    for line in output.lines() {
+        let Some((changes, path)) = line.split_once(' ') else {
+            continue;
+        };
+        let path = path.trim().to_string();
+        if changes == "*deleting" {
+            report.deleted.push(path);
+        } else if changes.len() > 1 && matches!(&changes[..2], "<f" | ">f") {
+            report.uploaded.push(path);
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rsync_args() {
+        let config = RsyncConfig {
+            host: Some("deploy@example.com".to_string()),
+            dest: "/var/www/site/".to_string(),
+            port: Some(2222),
+            delete: true,
+            max_delete: 50,
+            protect: vec!["uploads/".to_string()],
+            exclude: vec!["*.map".to_string()],
+            ..RsyncConfig::default()
+        };
+
+        let args = rsync_args(Path::new("dist"), &config, true);
+
+        let args: Vec<String> = args
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect();
+        assert_eq!(
+            args,
+            [
+                "--recursive",
+                "--links",
+                "--perms",
+                "--times",
+                "--compress",
+                "--checksum",
+                "--itemize-changes",
+                "--dry-run",
+                "--rsh",
+                "ssh -p 2222",
+                "--delete",
+                "--max-delete=50",
+                "--filter=protect uploads/",
+                "--exclude=*.map",
+                "dist/",
+                "deploy@example.com:/var/www/site/",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_itemized() {
+        let output = "cd+++++++++ posts/\n<f+++++++++ posts/index.html\n<fcsT...... index.html\n.d..t...... ./\n*deleting   old/index.html\n";
+        assert_eq!(
+            parse_itemized(output),
+            RsyncReport {
+                uploaded: vec!["posts/index.html".to_string(), "index.html".to_string()],
+                deleted: vec!["old/index.html".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_deploy_refuses_unsafe_deletes() {
+        let output = tempfile::TempDir::new().unwrap();
+        let config = RsyncConfig {
+            dest: "/".to_string(),
+            ..RsyncConfig::default()
+        };
+        let error = deploy(output.path(), &config, false).unwrap_err();
+        assert!(error.to_string().contains("set `dest`"), "{error}");
+
+        let config = RsyncConfig {
+            dest: "/var/www".to_string(),
+            delete: true,
+            ..RsyncConfig::default()
+        };
+        let error = deploy(output.path(), &config, false).unwrap_err();
+        assert!(error.to_string().contains("is empty"), "{error}");
+    }
+}
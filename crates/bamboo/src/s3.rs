@@ -0,0 +1,521 @@
+//! `bamboo deploy s3`: uploading the output to an S3 bucket, optionally
+//! fronted by CloudFront, with the `aws` CLI.
+//!
+//! ```toml
+//! [deploy.s3]
+//! bucket = "example-site"
+//! distribution = "E1A2B3C4D5E6F7"
+//! ```
+//!
+//! Each deploy hashes the output the way [`crate::build_manifest`] does and
+//! stores the result in the bucket (at `manifest_key`). The next deploy
+//! fetches it and uploads only the files whose hashes changed, so an
+//! unchanged site uploads nothing. Every upload gets a `Content-Type` from
+//! its extension and a `Cache-Control` by class: documents (HTML, feeds,
+//! JSON) revalidate on every request, fingerprinted assets are cached for a
+//! year, and other assets for a day. With `distribution` set, the changed
+//! and deleted HTML pages are invalidated in CloudFront (everything under
+//! the prefix on the first deploy, or when too many pages changed).
+//!
+//! Credentials, profiles, and the default region come from the usual AWS
+//! environment variables and config files.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::build_manifest::{BuildManifest, hash_output};
+use crate::deploy::run_tool;
+use crate::error::{BambooError, IoContext, Result};
+use crate::headers::IMMUTABLE_CACHE_CONTROL;
+
+const TARGET: &str = "s3";
+
+/// Invalidations listing more paths than this invalidate everything under
+/// the prefix instead; CloudFront caps a single request at 3000 paths.
+const MAX_INVALIDATION_PATHS: usize = 1000;
+
+/// The `[deploy.s3]` table in `bamboo.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct S3Config {
+    /// Bucket to upload to. Required here or as `--bucket`.
+    pub bucket: Option<String>,
+    /// Key prefix the site lives under, e.g. `blog`. Empty for the bucket
+    /// root.
+    pub prefix: String,
+    /// AWS region, if not the CLI's default.
+    pub region: Option<String>,
+    /// CloudFront distribution ID whose cache is invalidated for changed
+    /// HTML.
+    pub distribution: Option<String>,
+    /// Delete objects for files that were in the previous deploy but are
+    /// no longer in the output. Defaults to `false`.
+    pub delete: bool,
+    /// Key, relative to `prefix`, of the deploy manifest. Defaults to
+    /// `.bamboo-deploy.json`.
+    pub manifest_key: String,
+    /// `Cache-Control` for each class of file.
+    pub cache_control: CacheControlConfig,
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        Self {
+            bucket: None,
+            prefix: String::new(),
+            region: None,
+            distribution: None,
+            delete: false,
+            manifest_key: ".bamboo-deploy.json".to_string(),
+            cache_control: CacheControlConfig::default(),
+        }
+    }
+}
+
+/// The `[deploy.s3.cache_control]` table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheControlConfig {
+    /// HTML, XML, JSON, and text files, whose contents change under the
+    /// same URL. Defaults to `public, max-age=0, must-revalidate`.
+    pub documents: String,
+    /// Files with a fingerprint in their name. Defaults to a year,
+    /// `immutable`.
+    pub fingerprinted: String,
+    /// Everything else: images, fonts, unfingerprinted CSS and JS.
+    /// Defaults to `public, max-age=86400`.
+    pub assets: String,
+}
+
+impl Default for CacheControlConfig {
+    fn default() -> Self {
+        Self {
+            documents: "public, max-age=0, must-revalidate".to_string(),
+            fingerprinted: IMMUTABLE_CACHE_CONTROL.to_string(),
+            assets: "public, max-age=86400".to_string(),
+        }
+    }
+}
+
+impl S3Config {
+    fn bucket(&self) -> Result<&str> {
+        self.bucket.as_deref().ok_or_else(|| BambooError::Deploy {
+            target: TARGET,
+            message: "no bucket; pass --bucket or set `bucket` under [deploy.s3]".to_string(),
+        })
+    }
+
+    /// The object key for an output path.
+    pub fn key(&self, path: &str) -> String {
+        match self.prefix.trim_matches('/') {
+            "" => path.to_string(),
+            prefix => format!("{prefix}/{path}"),
+        }
+    }
+
+    fn url(&self, bucket: &str, path: &str) -> String {
+        format!("s3://{bucket}/{}", self.key(path))
+    }
+
+    fn aws(&self) -> Command {
+        let mut command = Command::new("aws");
+        if let Some(region) = &self.region {
+            command.args(["--region", region]);
+        }
+        command
+    }
+}
+
+/// How an output file is cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileClass {
+    /// HTML, feeds, and other files whose URL stays the same when they
+    /// change.
+    Document,
+    /// `<stem>.<8 hex digits>.<extension>`, as written by
+    /// `fingerprint = true`.
+    Fingerprinted,
+    /// Any other static file.
+    Asset,
+}
+
+/// The class of the output file at `path`.
+pub fn classify(path: &str) -> FileClass {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let mut parts = name.rsplitn(3, '.');
+    if let (Some(_), Some(hash), Some(stem)) = (parts.next(), parts.next(), parts.next())
+        && !stem.is_empty()
+        && hash.len() == 8
+        && hash.bytes().all(|byte| byte.is_ascii_hexdigit())
+    {
+        return FileClass::Fingerprinted;
+    }
+    match extension(path).as_deref() {
+        None | Some("html" | "htm" | "xml" | "json" | "txt" | "webmanifest") => FileClass::Document,
+        Some(_) => FileClass::Asset,
+    }
+}
+
+/// The `Content-Type` to store the output file at `path` with.
+pub fn content_type(path: &str) -> &'static str {
+    match extension(path).as_deref() {
+        None | Some("txt") => "text/plain; charset=utf-8",
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json" | "map") => "application/json",
+        Some("xml") => "application/xml",
+        Some("webmanifest") => "application/manifest+json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("pdf") => "application/pdf",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("mp3") => "audio/mpeg",
+        Some("wasm") => "application/wasm",
+        Some("gz") => "application/gzip",
+        Some(_) => "application/octet-stream",
+    }
+}
+
+fn extension(path: &str) -> Option<String> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+}
+
+/// What a deploy will do.
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3Plan {
+    /// The output as it is now, stored in the bucket after the uploads.
+    pub manifest: BuildManifest,
+    /// Files to upload, changed or new since the previous deploy.
+    pub uploads: Vec<S3Upload>,
+    /// Output paths to delete, if `delete` is set.
+    pub deletes: Vec<String>,
+    /// CloudFront paths to invalidate, if `distribution` is set: the
+    /// changed and deleted pages, or `/<prefix>/*` on a first deploy.
+    pub invalidations: Vec<String>,
+}
+
+/// One file to upload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Upload {
+    /// Path relative to the output directory.
+    pub path: String,
+    /// `Content-Type` header.
+    pub content_type: &'static str,
+    /// `Cache-Control` header.
+    pub cache_control: String,
+}
+
+/// Plans a deploy of `current` over `previous`, the manifest of the last
+/// deploy, if any.
+pub fn plan(current: BuildManifest, previous: Option<&BuildManifest>, config: &S3Config) -> S3Plan {
+    let changed: Vec<String> = match previous {
+        Some(previous) => current
+            .changed_since(previous)
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        None => current.files.iter().map(|file| file.path.clone()).collect(),
+    };
+    let deletes: Vec<String> = match previous {
+        Some(previous) if config.delete => {
+            let current: Vec<&str> = current
+                .files
+                .iter()
+                .map(|file| file.path.as_str())
+                .collect();
+            previous
+                .files
+                .iter()
+                .filter(|file| !current.contains(&file.path.as_str()))
+                .map(|file| file.path.clone())
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    let uploads = changed
+        .iter()
+        .map(|path| S3Upload {
+            path: path.clone(),
+            content_type: content_type(path),
+            cache_control: match classify(path) {
+                FileClass::Document => config.cache_control.documents.clone(),
+                FileClass::Fingerprinted => config.cache_control.fingerprinted.clone(),
+                FileClass::Asset => config.cache_control.assets.clone(),
+            },
+        })
+        .collect();
+
+    let mut invalidations = Vec::new();
+    if config.distribution.is_some() {
+        for path in changed.iter().chain(&deletes) {
+            if !matches!(extension(path).as_deref(), Some("html" | "htm")) {
+                continue;
+            }
+            let key = config.key(path);
+            if let Some(directory) = key.strip_suffix("index.html") {
+                invalidations.push(format!("/{directory}"));
+            }
+            invalidations.push(format!("/{key}"));
+        }
+    }
+    if invalidations.len() > MAX_INVALIDATION_PATHS
+        || (previous.is_none() && config.distribution.is_some())
+    {
+        invalidations = vec![format!("/{}", config.key("*"))];
+    }
+
+    S3Plan {
+        manifest: current,
+        uploads,
+        deletes,
+        invalidations,
+    }
+}
+
+/// Hashes `output_dir` and plans its deploy against the manifest of the
+/// previous deploy stored in the bucket.
+pub fn plan_deploy(output_dir: &Path, config: &S3Config) -> Result<S3Plan> {
+    let current = hash_output(output_dir, &HashMap::new())?;
+    let previous = previous_manifest(config)?;
+    Ok(plan(current, previous.as_ref(), config))
+}
+
+/// The manifest stored by the previous deploy, or `None` on the first.
+fn previous_manifest(config: &S3Config) -> Result<Option<BuildManifest>> {
+    let bucket = config.bucket()?;
+    let url = config.url(bucket, &config.manifest_key);
+    let output = config
+        .aws()
+        .args(["s3", "cp", "--quiet", &url, "-"])
+        .output()
+        .map_err(|error| BambooError::Deploy {
+            target: TARGET,
+            message: format!("failed to run aws: {error}"),
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("(404)") || stderr.contains("NoSuchKey") {
+            return Ok(None);
+        }
+        return Err(BambooError::Deploy {
+            target: TARGET,
+            message: format!("could not read {url}: {}", stderr.trim()),
+        });
+    }
+    serde_json::from_slice(&output.stdout)
+        .map(Some)
+        .map_err(|error| BambooError::Deploy {
+            target: TARGET,
+            message: format!("{url} is not a deploy manifest: {error}"),
+        })
+}
+
+/// Uploads and deletes the files in `plan`, stores its manifest, and
+/// invalidates the changed pages. Returns the CloudFront invalidation ID,
+/// if one was created.
+pub fn apply(output_dir: &Path, config: &S3Config, plan: &S3Plan) -> Result<Option<String>> {
+    let bucket = config.bucket()?;
+
+    plan.uploads.par_iter().try_for_each(|upload| {
+        let local = output_dir.join(&upload.path);
+        run_tool(
+            TARGET,
+            config
+                .aws()
+                .args(["s3", "cp", "--quiet"])
+                .arg(&local)
+                .arg(config.url(bucket, &upload.path))
+                .args(["--content-type", upload.content_type])
+                .args(["--cache-control", &upload.cache_control]),
+        )
+        .map(drop)
+    })?;
+    plan.deletes.par_iter().try_for_each(|path| {
+        run_tool(
+            TARGET,
+            config
+                .aws()
+                .args(["s3", "rm", "--quiet", &config.url(bucket, path)]),
+        )
+        .map(drop)
+    })?;
+
+    let manifest_path =
+        std::env::temp_dir().join(format!("bamboo-deploy-{}.json", std::process::id()));
+    let json = serde_json::to_string(&plan.manifest).map_err(std::io::Error::other)?;
+    std::fs::write(&manifest_path, json).io_context("write", &manifest_path)?;
+    let stored = run_tool(
+        TARGET,
+        config
+            .aws()
+            .args(["s3", "cp", "--quiet"])
+            .arg(&manifest_path)
+            .arg(config.url(bucket, &config.manifest_key))
+            .args(["--content-type", "application/json"])
+            .args(["--cache-control", "no-store"]),
+    );
+    let _ = std::fs::remove_file(&manifest_path);
+    stored?;
+
+    let Some(distribution) = &config.distribution else {
+        return Ok(None);
+    };
+    if plan.invalidations.is_empty() {
+        return Ok(None);
+    }
+    let response = run_tool(
+        TARGET,
+        config
+            .aws()
+            .args([
+                "cloudfront",
+                "create-invalidation",
+                "--output",
+                "json",
+                "--distribution-id",
+                distribution,
+                "--paths",
+            ])
+            .args(&plan.invalidations),
+    )?;
+    Ok(serde_json::from_str::<serde_json::Value>(&response)
+        .ok()
+        .and_then(|response| response["Invalidation"]["Id"].as_str().map(str::to_string)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_manifest::ManifestFile;
+
+    fn manifest(files: &[(&str, &str)]) -> BuildManifest {
+        BuildManifest {
+            files: files
+                .iter()
+                .map(|(path, sha256)| ManifestFile {
+                    path: path.to_string(),
+                    sha256: sha256.to_string(),
+                    size: 1,
+                    source: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_classify_and_content_type() {
+        assert_eq!(classify("posts/a/index.html"), FileClass::Document);
+        assert_eq!(classify("rss.xml"), FileClass::Document);
+        assert_eq!(classify("CNAME"), FileClass::Document);
+        assert_eq!(classify("css/site.0123abcd.css"), FileClass::Fingerprinted);
+        assert_eq!(classify("css/site.css"), FileClass::Asset);
+        assert_eq!(classify("img/photo.final.jpg"), FileClass::Asset);
+
+        assert_eq!(content_type("index.html"), "text/html; charset=utf-8");
+        assert_eq!(content_type("site.0123abcd.CSS"), "text/css; charset=utf-8");
+        assert_eq!(content_type("fonts/inter.woff2"), "font/woff2");
+        assert_eq!(
+            content_type("downloads.v1/archive"),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(content_type("archive.zip"), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_plan() {
+        let config = S3Config {
+            bucket: Some("site".to_string()),
+            prefix: "/blog/".to_string(),
+            distribution: Some("E123".to_string()),
+            delete: true,
+            ..S3Config::default()
+        };
+        let previous = manifest(&[
+            ("index.html", "1"),
+            ("old/index.html", "1"),
+            ("site.0123abcd.css", "1"),
+            ("about/index.html", "1"),
+        ]);
+        let current = manifest(&[
+            ("about/index.html", "1"),
+            ("index.html", "2"),
+            ("logo.png", "1"),
+            ("site.0123abcd.css", "1"),
+        ]);
+
+        let plan = plan(current, Some(&previous), &config);
+
+        assert_eq!(
+            plan.uploads,
+            [
+                S3Upload {
+                    path: "index.html".to_string(),
+                    content_type: "text/html; charset=utf-8",
+                    cache_control: "public, max-age=0, must-revalidate".to_string(),
+                },
+                S3Upload {
+                    path: "logo.png".to_string(),
+                    content_type: "image/png",
+                    cache_control: "public, max-age=86400".to_string(),
+                },
+            ]
+        );
+        assert_eq!(plan.deletes, ["old/index.html"]);
+        assert_eq!(
+            plan.invalidations,
+            [
+                "/blog/",
+                "/blog/index.html",
+                "/blog/old/",
+                "/blog/old/index.html"
+            ]
+        );
+        assert_eq!(config.key("index.html"), "blog/index.html");
+    }
+
+    #[test]
+    fn test_first_deploy_uploads_everything() {
+        let config = S3Config {
+            distribution: Some("E123".to_string()),
+            delete: true,
+            ..S3Config::default()
+        };
+        let first = plan(
+            manifest(&[("index.html", "1"), ("app.js", "1")]),
+            None,
+            &config,
+        );
+        assert_eq!(first.uploads.len(), 2);
+        assert!(first.deletes.is_empty());
+        assert_eq!(first.invalidations, ["/*"]);
+
+        let many: Vec<(String, String)> = (0..=MAX_INVALIDATION_PATHS)
+            .map(|page| (format!("p{page}/index.html"), "2".to_string()))
+            .collect();
+        let many: Vec<(&str, &str)> = many
+            .iter()
+            .map(|(path, hash)| (path.as_str(), hash.as_str()))
+            .collect();
+        let large = plan(manifest(&many), Some(&manifest(&[])), &config);
+        assert_eq!(large.uploads.len(), MAX_INVALIDATION_PATHS + 1);
+        assert_eq!(large.invalidations, ["/*"]);
+    }
+}
@@ -1,12 +1,60 @@
 //! Client-side search index generation. Produces a `search-index.json` file
-//! that the Fuse.js-based search page in the default theme consumes.
-
+//! that the search page in the default theme consumes: by default a plain
+//! list of documents for Fuse.js to scan, or with
+//! `search_format = "elasticlunr"` an inverted index built ahead of time,
+//! which elasticlunr.js loads directly so search stays fast on large sites.
+//! `search_format = "chunked"` splits the inverted index into a
+//! `search-index/` directory fetched piece by piece. Both inverted indexes
+//! analyze text with the site language's [`SearchLanguage`].
+//! `search_format = "tantivy"` writes a Tantivy index there instead; see
+//! `crate::tantivy_index`, which is built with the `tantivy` feature.
+//!
+//! The single-file formats are also written gzipped to
+//! `search-index.json.gz`, wrapped with a schema version and generation
+//! timestamp, and the search page gets its hashed URL as
+//! `search_index_url`.
+
+use std::collections::BTreeMap;
+use std::io::Write;
 use std::path::Path;
 
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use sha2::{Digest, Sha256};
 
 use crate::error::Result;
-use crate::types::Site;
+use crate::feeds::POSTS_SECTION;
+use crate::redirects::redirect_to;
+use crate::search_language::SearchLanguage;
+use crate::sink::RenderSink;
+#[cfg(feature = "tantivy")]
+use crate::tantivy_index::write_tantivy_index;
+use crate::types::{Content, Site};
+
+/// Shape of `search-index.json`, set with `search_format` in `bamboo.toml`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchFormat {
+    /// A JSON array of [`SearchEntry`] documents, searched by Fuse.js.
+    #[default]
+    Json,
+    /// A serialized elasticlunr.js index over `title`, `tags`, and `body`,
+    /// storing each entry's `title`, `url`, `tags`, `date`, and `excerpt`
+    /// and keyed by `url`.
+    Elasticlunr,
+    /// A `search-index/` directory of small files: `meta.json`, the word
+    /// index split into `words/` chunks by each term's first two
+    /// characters, and one `fragments/<id>.json` per entry. The search
+    /// page fetches only the chunks and fragments a query needs.
+    Chunked,
+    /// A Tantivy index in `search-index/`, queried in the browser by the
+    /// `bamboo-search-wasm` module. Needs the `tantivy` feature; see
+    /// `crate::tantivy_index`.
+    Tantivy,
+}
 
 /// One entry in the generated `search-index.json`. Consumed by the Fuse.js
 /// search page in the default theme.
@@ -135,12 +183,29 @@ fn truncate_content(content: &str, max_chars: usize) -> String {
     content.chars().take(max_chars).collect()
 }
 
-/// Writes `search-index.json` into `output_dir`, containing one
-/// [`SearchEntry`] per page and post.
-pub fn generate_search_index(site: &Site, output_dir: &Path) -> Result<()> {
-    let mut entries: Vec<SearchEntry> = Vec::new();
+/// Top-level `content/` directory of a nested page's source file, the
+/// section `search_exclude` matches it by.
+fn page_section(content: &Content) -> Option<String> {
+    let mut components = content.source_path.components();
+    let first = components.next()?;
+    components
+        .next()
+        .map(|_| first.as_os_str().to_string_lossy().into_owned())
+}
 
-    if let Some(ref home) = site.home {
+/// One [`SearchEntry`] per page, post, collection item, collection
+/// landing page, taxonomy term page, and generated page that isn't left
+/// out of search by frontmatter or `search_exclude`.
+pub fn search_entries(site: &Site) -> Result<Vec<SearchEntry>> {
+    let mut entries: Vec<SearchEntry> = Vec::new();
+    let excluded = |section: &str| {
+        site.config
+            .search_exclude
+            .iter()
+            .any(|name| name == section)
+    };
+
+    if let Some(home) = site.home.as_ref().filter(|home| home.content.in_search()) {
         entries.push(SearchEntry {
             title: home.content.title.clone(),
             url: home.content.url.clone(),
@@ -154,7 +219,9 @@ pub fn generate_search_index(site: &Site, output_dir: &Path) -> Result<()> {
         });
     }
 
-    for post in &site.posts {
+    for post in site.posts.iter().filter(|post| {
+        redirect_to(&post.content).is_none() && post.content.in_search() && !excluded(POSTS_SECTION)
+    }) {
         entries.push(SearchEntry {
             title: post.content.title.clone(),
             url: post.content.url.clone(),
@@ -169,7 +236,11 @@ pub fn generate_search_index(site: &Site, output_dir: &Path) -> Result<()> {
     }
 
     for page in &site.pages {
-        if page.content.slug == "404" {
+        if page.content.slug == "404"
+            || redirect_to(&page.content).is_some()
+            || !page.content.in_search()
+            || page_section(&page.content).is_some_and(|section| excluded(&section))
+        {
             continue;
         }
         entries.push(SearchEntry {
@@ -185,8 +256,16 @@ pub fn generate_search_index(site: &Site, output_dir: &Path) -> Result<()> {
         });
     }
 
-    for collection in site.collections.values() {
-        for item in &collection.items {
+    for collection in site
+        .collections
+        .values()
+        .filter(|collection| !excluded(&collection.name))
+    {
+        for item in collection
+            .items
+            .iter()
+            .filter(|item| item.content.in_search())
+        {
             entries.push(SearchEntry {
                 title: item.content.title.clone(),
                 url: item.content.url.clone(),
@@ -201,8 +280,358 @@ pub fn generate_search_index(site: &Site, output_dir: &Path) -> Result<()> {
         }
     }
 
-    let json = serde_json::to_string_pretty(&entries).map_err(std::io::Error::other)?;
-    std::fs::write(output_dir.join("search-index.json"), json)?;
+    let mut collections: Vec<_> = site
+        .collections
+        .values()
+        .filter(|collection| !excluded(&collection.name))
+        .collect();
+    collections.sort_by(|a, b| a.name.cmp(&b.name));
+    for collection in collections {
+        let entry = match &collection.index {
+            Some(index) if !index.in_search() => continue,
+            Some(index) => SearchEntry {
+                title: index.title.clone(),
+                url: index.url.clone(),
+                tags: Vec::new(),
+                date: String::new(),
+                excerpt: index
+                    .frontmatter
+                    .get_string("description")
+                    .unwrap_or_default(),
+                content: truncate_content(&strip_html_tags(&index.html), MAX_SEARCH_CONTENT_CHARS),
+            },
+            None => SearchEntry {
+                title: collection.name.clone(),
+                url: format!("/{}/", collection.name),
+                tags: Vec::new(),
+                date: String::new(),
+                excerpt: String::new(),
+                content: String::new(),
+            },
+        };
+        entries.push(entry);
+    }
+
+    let mut taxonomies: Vec<&String> = site.config.taxonomies.keys().collect();
+    taxonomies.sort();
+    for taxonomy in taxonomies
+        .into_iter()
+        .filter(|taxonomy| !excluded(taxonomy))
+    {
+        for term in crate::taxonomy::taxonomy_terms(site, taxonomy) {
+            entries.push(SearchEntry {
+                url: format!("/{}/{}/", taxonomy, term.slug),
+                tags: vec![term.name.clone()],
+                date: String::new(),
+                excerpt: format!(
+                    "{} {}",
+                    term.count,
+                    if term.count == 1 { "post" } else { "posts" }
+                ),
+                content: String::new(),
+                title: term.name,
+            });
+        }
+    }
+
+    for generated in &site.config.generated_pages {
+        let data = generated.resolve_data(site)?;
+        let page = generated.page(data);
+        entries.push(SearchEntry {
+            title: page.title,
+            url: page.url,
+            tags: Vec::new(),
+            date: String::new(),
+            excerpt: page.description.unwrap_or_default(),
+            content: truncate_content(
+                &crate::generated_pages::data_text(data),
+                MAX_SEARCH_CONTENT_CHARS,
+            ),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Version of elasticlunr.js the serialized index targets.
+pub const ELASTICLUNR_VERSION: &str = "0.9.5";
+
+/// Pipeline functions the elasticlunr index declares. The search page
+/// registers them under these names, applying the index's `language`
+/// rules to queries; elasticlunr's built-in trimmer, stop-word filter, and
+/// stemmer only know English.
+pub const ELASTICLUNR_PIPELINE: [&str; 3] =
+    ["bambooTrimmer", "bambooStopWordFilter", "bambooStemmer"];
+
+/// Builds the serialized elasticlunr.js index for `entries`, in the shape
+/// `elasticlunr.Index.load` expects, with terms analyzed for `language`.
+/// The [`SearchLanguage`] rules are included as `language`.
+pub fn elasticlunr_index(entries: &[SearchEntry], language: &SearchLanguage) -> Value {
+    const FIELDS: [&str; 3] = ["title", "tags", "body"];
+    let mut docs = Map::new();
+    let mut doc_info = Map::new();
+    let mut index = Map::new();
+    for field in FIELDS {
+        index.insert(
+            field.to_string(),
+            json!({ "root": { "docs": {}, "df": 0 } }),
+        );
+    }
+
+    for entry in entries {
+        docs.insert(
+            entry.url.clone(),
+            json!({
+                "title": entry.title,
+                "url": entry.url,
+                "tags": entry.tags,
+                "date": entry.date,
+                "excerpt": entry.excerpt,
+            }),
+        );
+        let mut field_lengths = Map::new();
+        for field in FIELDS {
+            let tokens = match field {
+                "title" => language.tokens(&entry.title),
+                "tags" => language.tokens(&entry.tags.join(" ")),
+                _ => language.tokens(&entry.content),
+            };
+            field_lengths.insert(field.to_string(), json!(tokens.len()));
+            let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for token in &tokens {
+                *counts.entry(token).or_default() += 1;
+            }
+            let root = &mut index[field]["root"];
+            for (token, count) in counts {
+                let mut node = &mut *root;
+                for character in token.chars() {
+                    let key = character.to_string();
+                    if node.get(&key).is_none() {
+                        node[&key] = json!({ "docs": {}, "df": 0 });
+                    }
+                    node = &mut node[&key];
+                }
+                node["docs"][&entry.url] = json!({ "tf": (count as f64).sqrt() });
+                node["df"] = json!(node["docs"].as_object().map_or(0, Map::len));
+            }
+        }
+        doc_info.insert(entry.url.clone(), Value::Object(field_lengths));
+    }
+
+    json!({
+        "version": ELASTICLUNR_VERSION,
+        "fields": FIELDS,
+        "ref": "url",
+        "documentStore": {
+            "docs": docs,
+            "docInfo": doc_info,
+            "length": entries.len(),
+            "save": true,
+        },
+        "index": index,
+        "pipeline": ELASTICLUNR_PIPELINE,
+        "language": language,
+    })
+}
+
+/// Output directory of the [`SearchFormat::Chunked`] and
+/// [`SearchFormat::Tantivy`] indexes.
+pub const CHUNKED_INDEX_DIR: &str = "search-index";
+
+/// Number of leading characters that pick a term's word chunk.
+const CHUNK_PREFIX_CHARS: usize = 2;
+
+/// `search-index/meta.json`, the one file the search page loads up front.
+#[derive(Debug, Serialize)]
+pub struct ChunkedIndexMeta {
+    /// Number of fragments, with ids `0..documents`.
+    pub documents: usize,
+    /// Characters of a term that select its chunk.
+    pub prefix_length: usize,
+    /// Stop words and stemming rules the index was built with, for the
+    /// search page to apply to queries too.
+    pub language: SearchLanguage,
+    /// Each term prefix mapped to its file under `words/`.
+    pub chunks: BTreeMap<String, String>,
+}
+
+/// Word chunks keyed by term prefix, each mapping a term to
+/// `[fragment id, weight]` pairs.
+pub type WordChunks = BTreeMap<String, BTreeMap<String, Vec<(usize, usize)>>>;
+
+/// File name under `words/` for the chunk of terms starting with
+/// `prefix`: the prefix itself when it is ASCII alphanumeric, otherwise
+/// `x` followed by its UTF-8 bytes in hex.
+fn chunk_file_name(prefix: &str) -> String {
+    if prefix
+        .chars()
+        .all(|character| character.is_ascii_alphanumeric())
+    {
+        format!("{prefix}.json")
+    } else {
+        let hex: String = prefix.bytes().map(|byte| format!("{byte:02x}")).collect();
+        format!("x{hex}.json")
+    }
+}
+
+/// Builds the [`SearchFormat::Chunked`] index for `entries`: its
+/// `meta.json` and its [`WordChunks`]. A term's weight counts its
+/// occurrences in the body once, in tags twice, and in the title three
+/// times. Terms are analyzed for `language`.
+pub fn chunked_index(
+    entries: &[SearchEntry],
+    language: &SearchLanguage,
+) -> (ChunkedIndexMeta, WordChunks) {
+    let mut chunks = WordChunks::new();
+    for (id, entry) in entries.iter().enumerate() {
+        let mut weights: BTreeMap<String, usize> = BTreeMap::new();
+        let fields = [
+            (language.tokens(&entry.title), 3),
+            (language.tokens(&entry.tags.join(" ")), 2),
+            (language.tokens(&entry.content), 1),
+        ];
+        for (tokens, weight) in fields {
+            for token in tokens {
+                *weights.entry(token).or_default() += weight;
+            }
+        }
+        for (term, weight) in weights {
+            let prefix: String = term.chars().take(CHUNK_PREFIX_CHARS).collect();
+            chunks
+                .entry(prefix)
+                .or_default()
+                .entry(term)
+                .or_default()
+                .push((id, weight));
+        }
+    }
+    let meta = ChunkedIndexMeta {
+        documents: entries.len(),
+        prefix_length: CHUNK_PREFIX_CHARS,
+        language: *language,
+        chunks: chunks
+            .keys()
+            .map(|prefix| (prefix.clone(), chunk_file_name(prefix)))
+            .collect(),
+    };
+    (meta, chunks)
+}
+
+/// Writes the [`SearchFormat::Chunked`] index into `search-index/`.
+fn write_chunked_index(
+    entries: &[SearchEntry],
+    language: &SearchLanguage,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    let directory = Path::new(CHUNKED_INDEX_DIR);
+    let (meta, chunks) = chunked_index(entries, language);
+    for (prefix, terms) in &chunks {
+        let json = serde_json::to_string(terms).map_err(std::io::Error::other)?;
+        output.write_file(
+            &directory.join("words").join(&meta.chunks[prefix]),
+            json.as_bytes(),
+        )?;
+    }
+    for (id, entry) in entries.iter().enumerate() {
+        let fragment = json!({
+            "title": entry.title,
+            "url": entry.url,
+            "tags": entry.tags,
+            "date": entry.date,
+            "excerpt": entry.excerpt,
+        });
+        output.write_file(
+            &directory.join("fragments").join(format!("{id}.json")),
+            fragment.to_string().as_bytes(),
+        )?;
+    }
+    let json = serde_json::to_string(&meta).map_err(std::io::Error::other)?;
+    output.write_file(&directory.join("meta.json"), json.as_bytes())
+}
+
+/// Stands in for `crate::tantivy_index` when bamboo is built without the
+/// `tantivy` feature.
+#[cfg(not(feature = "tantivy"))]
+fn write_tantivy_index(
+    _entries: &[SearchEntry],
+    _language: &SearchLanguage,
+    _output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    Err(crate::error::BambooError::SearchIndex {
+        message: "search_format = \"tantivy\" needs bamboo built with the `tantivy` feature"
+            .to_string(),
+    })
+}
+
+/// Output path of the compressed search index.
+pub const COMPRESSED_SEARCH_INDEX: &str = "search-index.json.gz";
+
+/// Schema version of [`COMPRESSED_SEARCH_INDEX`]'s envelope, bumped when
+/// its shape changes.
+pub const SEARCH_INDEX_VERSION: u32 = 1;
+
+/// The gzipped search index and the URL templates load it from.
+#[derive(Debug, Clone)]
+pub struct CompressedSearchIndex {
+    /// Gzipped JSON: `version` ([`SEARCH_INDEX_VERSION`]), `generated` (an
+    /// RFC 3339 timestamp), `format`, and `index`, which holds what
+    /// `search-index.json` does.
+    pub bytes: Vec<u8>,
+    /// Absolute URL with a `?v=` hash of the index, which changes only
+    /// when the index does, so clients can cache it indefinitely.
+    pub url: String,
+}
+
+/// Builds [`COMPRESSED_SEARCH_INDEX`] for `site`, generated at
+/// `generated`. Returns `None` for [`SearchFormat::Chunked`] and
+/// [`SearchFormat::Tantivy`], whose indexes are already split into files.
+pub fn compressed_search_index(
+    site: &Site,
+    generated: DateTime<Utc>,
+) -> Result<Option<CompressedSearchIndex>> {
+    let entries = search_entries(site)?;
+    let index = match site.config.search_format {
+        SearchFormat::Json => serde_json::to_value(&entries).map_err(std::io::Error::other)?,
+        SearchFormat::Elasticlunr => elasticlunr_index(
+            &entries,
+            &SearchLanguage::for_language(site.config.language.as_deref()),
+        ),
+        SearchFormat::Chunked | SearchFormat::Tantivy => return Ok(None),
+    };
+    let mut envelope = json!({
+        "version": SEARCH_INDEX_VERSION,
+        "format": site.config.search_format,
+        "index": index,
+    });
+    let hash = format!("{:x}", Sha256::digest(envelope.to_string()));
+    envelope["generated"] = json!(generated.to_rfc3339());
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(envelope.to_string().as_bytes())?;
+    Ok(Some(CompressedSearchIndex {
+        bytes: encoder.finish()?,
+        url: format!(
+            "{}/{COMPRESSED_SEARCH_INDEX}?v={}",
+            site.config.base_url.trim_end_matches('/'),
+            &hash[..8]
+        ),
+    }))
+}
+
+/// Writes `search-index.json`, or the `search-index/` directory for
+/// [`SearchFormat::Chunked`] and [`SearchFormat::Tantivy`], into `output` in the configured
+/// [`SearchFormat`].
+pub fn generate_search_index(site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
+    let entries = search_entries(site)?;
+    let language = SearchLanguage::for_language(site.config.language.as_deref());
+    let json = match site.config.search_format {
+        SearchFormat::Json => serde_json::to_string_pretty(&entries),
+        SearchFormat::Elasticlunr => serde_json::to_string(&elasticlunr_index(&entries, &language)),
+        SearchFormat::Chunked => return write_chunked_index(&entries, &language, output),
+        SearchFormat::Tantivy => return write_tantivy_index(&entries, &language, output),
+    }
+    .map_err(std::io::Error::other)?;
+    output.write_file(Path::new("search-index.json"), json.as_bytes())?;
 
     Ok(())
 }
@@ -210,6 +639,7 @@ pub fn generate_search_index(site: &Site, output_dir: &Path) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::PathBuf;
 
     #[test]
     fn test_strip_html_tags_basic() {
@@ -274,20 +704,54 @@ mod tests {
                 posts_per_page: 10,
                 minify: false,
                 fingerprint: false,
+                dedupe_assets: None,
+                static_ignore: Vec::new(),
+                assets: crate::assets::AssetsConfig::default(),
+                build_manifest: false,
+                search_format: crate::search::SearchFormat::Json,
+                search_exclude: Vec::new(),
                 images: None,
+                social_images: None,
                 syntax_theme: crate::types::default_syntax_theme(),
                 taxonomies: crate::types::default_taxonomies(),
                 math: false,
                 favicon: None,
                 link_check_ignore: Vec::new(),
+                link_archive: None,
+                precompress: None,
+                fonts: Vec::new(),
+                build: crate::build_hooks::BuildConfig::default(),
+                deploy: crate::deploy::DeployConfig::default(),
+                remote: crate::remote::RemoteConfig::default(),
+                feed: crate::feeds::FeedConfig::default(),
+                sitemap: crate::sitemap::SitemapConfig::default(),
+                wellknown: crate::wellknown::WellKnownConfig::default(),
+                manifest: None,
+                llms: None,
+                vercel: None,
+                htaccess: None,
+                headers: None,
+                newsletter: None,
+                discussions: crate::discussions::DiscussionConfig::default(),
+                link_previews: crate::link_previews::LinkPreviewConfig::default(),
+                changelog: None,
+                external_resources: None,
+                generate: crate::types::GenerateConfig::default(),
+                drafts: false,
+                future: false,
+                generated_pages: Vec::new(),
+                outputs: Vec::new(),
+                jobs: None,
                 extra: HashMap::new(),
             },
             home: None,
             pages: vec![],
             posts: vec![],
+            notes: vec![],
             collections: HashMap::new(),
             data: HashMap::new(),
             assets: vec![],
+            image_requests: Vec::new(),
         };
 
         let output_dir = tempfile::TempDir::new().unwrap();
@@ -299,4 +763,239 @@ mod tests {
         let entries: Vec<serde_json::Value> = serde_json::from_str(&content).unwrap();
         assert!(entries.is_empty());
     }
+
+    #[test]
+    fn test_search_index_skips_in_search_false() {
+        let mut site = crate::template_test::sample_site().unwrap();
+        let hidden = site.posts[0].content.url.clone();
+        site.posts[0]
+            .content
+            .frontmatter
+            .raw
+            .insert("in_search".to_string(), serde_json::json!(false));
+
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_search_index(&site, output_dir.path()).unwrap();
+        let content = std::fs::read_to_string(output_dir.path().join("search-index.json")).unwrap();
+        let entries: Vec<serde_json::Value> = serde_json::from_str(&content).unwrap();
+        assert!(!entries.is_empty());
+        assert!(entries.iter().all(|entry| entry["url"] != hidden.as_str()));
+    }
+
+    #[test]
+    fn test_elasticlunr_index() {
+        let entry = |url: &str, title: &str, content: &str| SearchEntry {
+            title: title.to_string(),
+            url: url.to_string(),
+            tags: vec!["rust".to_string()],
+            date: String::new(),
+            excerpt: String::new(),
+            content: content.to_string(),
+        };
+        let index = elasticlunr_index(
+            &[
+                entry("/a/", "Hello", "go go go"),
+                entry("/b/", "Goodbye", "go home"),
+            ],
+            &SearchLanguage::for_language(Some("en")),
+        );
+
+        assert_eq!(index["ref"], "url");
+        assert_eq!(index["language"]["code"], "en");
+        assert_eq!(index["documentStore"]["length"], 2);
+        assert_eq!(index["documentStore"]["docs"]["/b/"]["title"], "Goodbye");
+        assert_eq!(index["documentStore"]["docInfo"]["/a/"]["body"], 3);
+        let go = &index["index"]["body"]["root"]["g"]["o"];
+        assert_eq!(go["df"], 2);
+        assert_eq!(go["docs"]["/a/"]["tf"], 3f64.sqrt());
+        assert_eq!(go["docs"]["/b/"]["tf"], 1.0);
+        assert_eq!(index["index"]["body"]["root"]["g"]["df"], 0);
+        assert_eq!(index["index"]["tags"]["root"]["r"]["u"]["s"]["t"]["df"], 2);
+    }
+
+    #[test]
+    fn test_generate_elasticlunr_search_index() {
+        let mut site = crate::template_test::sample_site().unwrap();
+        site.config.search_format = SearchFormat::Elasticlunr;
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_search_index(&site, output_dir.path()).unwrap();
+
+        let content = std::fs::read_to_string(output_dir.path().join("search-index.json")).unwrap();
+        let index: Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(index["version"], ELASTICLUNR_VERSION);
+        assert_eq!(
+            index["documentStore"]["length"],
+            search_entries(&site).unwrap().len()
+        );
+        assert!(index["documentStore"]["docs"][&site.posts[0].content.url].is_object());
+    }
+
+    #[test]
+    fn test_chunk_file_name() {
+        assert_eq!(chunk_file_name("ru"), "ru.json");
+        assert_eq!(chunk_file_name("é"), "xc3a9.json");
+        assert_eq!(chunk_file_name("_"), "x5f.json");
+    }
+
+    #[cfg(not(feature = "tantivy"))]
+    #[test]
+    fn test_tantivy_format_needs_feature() {
+        let mut site = crate::template_test::sample_site().unwrap();
+        site.config.search_format = SearchFormat::Tantivy;
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let error = generate_search_index(&site, output_dir.path()).unwrap_err();
+        assert!(error.to_string().contains("`tantivy` feature"), "{error}");
+    }
+
+    #[test]
+    fn test_generate_chunked_search_index() {
+        let mut site = crate::template_test::sample_site().unwrap();
+        site.config.search_format = SearchFormat::Chunked;
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_search_index(&site, output_dir.path()).unwrap();
+
+        let directory = output_dir.path().join(CHUNKED_INDEX_DIR);
+        assert!(!output_dir.path().join("search-index.json").exists());
+        let meta: Value =
+            serde_json::from_str(&std::fs::read_to_string(directory.join("meta.json")).unwrap())
+                .unwrap();
+        let entries = search_entries(&site).unwrap();
+        assert_eq!(meta["documents"], entries.len());
+        assert_eq!(meta["prefix_length"], 2);
+
+        let second = entries
+            .iter()
+            .position(|entry| entry.title == "Second Post")
+            .unwrap();
+        let chunk: Value = serde_json::from_str(
+            &std::fs::read_to_string(directory.join("words").join("se.json")).unwrap(),
+        )
+        .unwrap();
+        assert!(
+            chunk["second"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|pair| pair[0] == second && pair[1].as_u64().unwrap() >= 3)
+        );
+        let fragment: Value = serde_json::from_str(
+            &std::fs::read_to_string(directory.join("fragments").join(format!("{second}.json")))
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(fragment["url"], "/posts/second-post/");
+        assert!(fragment.get("content").is_none());
+    }
+
+    #[test]
+    fn test_search_index_exclusions() {
+        let mut site = crate::template_test::sample_site().unwrap();
+        site.pages[0]
+            .content
+            .frontmatter
+            .raw
+            .insert("search".to_string(), serde_json::json!(false));
+        let hidden = site.pages[0].content.url.clone();
+        let mut policy = site.pages[0].clone();
+        policy.content.frontmatter.raw.clear();
+        policy.content.source_path = PathBuf::from("legal/privacy.md");
+        policy.content.url = "/legal/privacy/".to_string();
+        site.pages.push(policy);
+        site.config.search_exclude = vec!["posts".to_string(), "legal".to_string()];
+
+        let urls: Vec<String> = search_entries(&site)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.url)
+            .collect();
+        assert!(!urls.contains(&hidden));
+        assert!(!urls.contains(&"/legal/privacy/".to_string()));
+        assert!(
+            site.posts
+                .iter()
+                .all(|post| !urls.contains(&post.content.url))
+        );
+
+        site.config.search_exclude.clear();
+        let urls: Vec<String> = search_entries(&site)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.url)
+            .collect();
+        assert!(urls.contains(&"/legal/privacy/".to_string()));
+        assert!(!urls.contains(&hidden));
+    }
+
+    #[test]
+    fn test_search_index_landing_pages() {
+        let mut site = crate::template_test::sample_site().unwrap();
+        let entries = search_entries(&site).unwrap();
+        let tag = entries
+            .iter()
+            .find(|entry| entry.url == "/tags/sample/")
+            .unwrap();
+        assert_eq!(tag.title, "sample");
+        assert_eq!(tag.excerpt, format!("{} posts", site.posts.len()));
+        let projects = entries
+            .iter()
+            .find(|entry| entry.url == "/projects/")
+            .unwrap();
+        assert_eq!(projects.title, "projects");
+
+        let mut index = site.collections["projects"].items[0].content.clone();
+        index.title = "Projects".to_string();
+        index.url = "/projects/".to_string();
+        index.frontmatter.raw.insert(
+            "description".to_string(),
+            serde_json::json!("Things I built"),
+        );
+        site.collections.get_mut("projects").unwrap().index = Some(index);
+        site.config.search_exclude = vec!["tags".to_string()];
+        let entries = search_entries(&site).unwrap();
+        let projects = entries
+            .iter()
+            .find(|entry| entry.url == "/projects/")
+            .unwrap();
+        assert_eq!(projects.title, "Projects");
+        assert_eq!(projects.excerpt, "Things I built");
+        assert!(entries.iter().all(|entry| !entry.url.starts_with("/tags/")));
+    }
+
+    #[test]
+    fn test_compressed_search_index() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let mut site = crate::template_test::sample_site().unwrap();
+        let generated = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let compressed = compressed_search_index(&site, generated).unwrap().unwrap();
+        let mut json = String::new();
+        GzDecoder::new(compressed.bytes.as_slice())
+            .read_to_string(&mut json)
+            .unwrap();
+        let envelope: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(envelope["version"], SEARCH_INDEX_VERSION);
+        assert_eq!(envelope["format"], "json");
+        assert_eq!(envelope["generated"], "2024-05-01T12:00:00+00:00");
+        assert_eq!(
+            envelope["index"].as_array().unwrap().len(),
+            search_entries(&site).unwrap().len()
+        );
+        assert!(
+            compressed
+                .url
+                .starts_with("https://example.com/search-index.json.gz?v=")
+        );
+
+        let later = compressed_search_index(&site, Utc::now()).unwrap().unwrap();
+        assert_eq!(later.url, compressed.url);
+        site.posts[0].content.title = "Renamed".to_string();
+        let changed = compressed_search_index(&site, generated).unwrap().unwrap();
+        assert_ne!(changed.url, compressed.url);
+
+        site.config.search_format = SearchFormat::Chunked;
+        assert!(compressed_search_index(&site, generated).unwrap().is_none());
+    }
 }
@@ -0,0 +1,359 @@
+//! Language-aware text analysis for the prebuilt search indexes
+//! (`search_format = "elasticlunr"` and `"chunked"`). The site `language`
+//! picks a stop-word list and a light suffix-stripping stemmer, so `deploy`,
+//! `deploys`, and `deployed` share one index term and filler words aren't
+//! indexed at all.
+//!
+//! The stemmer is a short table of suffix rules rather than a full Snowball
+//! algorithm, because the search page has to analyze queries exactly the
+//! way the index was built: the rules are written into the index and
+//! applied in the browser too. Languages without rules get neither stop
+//! words nor stemming, only tokenizing.
+
+use serde::Serialize;
+
+/// Stop words and stemming rules for one language. Serialized into the
+/// search index for the search page to apply to queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SearchLanguage {
+    /// Primary language subtag, such as `en`, or empty when the site
+    /// language has no rules.
+    pub code: &'static str,
+    /// Lowercase words left out of the index.
+    pub stop_words: &'static [&'static str],
+    /// `(suffix, replacement)` rules, tried in order. The first suffix a
+    /// term ends with is replaced, as long as at least `min_stem`
+    /// characters remain before it.
+    pub suffixes: &'static [(&'static str, &'static str)],
+    /// Shortest stem a rule may leave.
+    pub min_stem: usize,
+}
+
+const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a", "able", "about", "across", "after", "all", "almost", "also", "am", "among", "an", "and",
+    "any", "are", "as", "at", "be", "because", "been", "but", "by", "can", "cannot", "could",
+    "dear", "did", "do", "does", "either", "else", "ever", "every", "for", "from", "get", "got",
+    "had", "has", "have", "he", "her", "hers", "him", "his", "how", "however", "i", "if", "in",
+    "into", "is", "it", "its", "just", "least", "let", "like", "likely", "may", "me", "might",
+    "most", "must", "my", "neither", "no", "nor", "not", "of", "off", "often", "on", "only", "or",
+    "other", "our", "own", "rather", "said", "say", "says", "she", "should", "since", "so", "some",
+    "than", "that", "the", "their", "them", "then", "there", "these", "they", "this", "tis", "to",
+    "too", "twas", "us", "wants", "was", "we", "were", "what", "when", "where", "which", "while",
+    "who", "whom", "why", "will", "with", "would", "yet", "you", "your",
+];
+
+const ENGLISH_SUFFIXES: &[(&str, &str)] = &[
+    ("ational", "ate"),
+    ("ization", "ize"),
+    ("fulness", "ful"),
+    ("iveness", "ive"),
+    ("ousness", "ous"),
+    ("ments", ""),
+    ("ment", ""),
+    ("ness", ""),
+    ("ings", ""),
+    ("ing", ""),
+    ("edly", ""),
+    ("ied", "y"),
+    ("ies", "y"),
+    ("ed", ""),
+    ("ly", ""),
+    ("ss", "ss"),
+    ("us", "us"),
+    ("is", "is"),
+    ("s", ""),
+];
+
+const FRENCH_STOP_WORDS: &[&str] = &[
+    "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "elle", "en", "et", "eux", "il",
+    "ils", "je", "la", "le", "les", "leur", "lui", "ma", "mais", "me", "mes", "moi", "mon", "ne",
+    "nos", "notre", "nous", "on", "ou", "par", "pas", "pour", "qu", "que", "qui", "sa", "se",
+    "ses", "son", "sur", "ta", "te", "tes", "toi", "ton", "tu", "un", "une", "vos", "votre",
+    "vous", "est", "sont", "été", "être", "avoir", "c", "d", "j", "l", "m", "n", "s", "t", "y",
+];
+
+const FRENCH_SUFFIXES: &[(&str, &str)] = &[
+    ("issements", ""),
+    ("issement", ""),
+    ("ements", ""),
+    ("ement", ""),
+    ("ations", ""),
+    ("ation", ""),
+    ("euses", ""),
+    ("euse", ""),
+    ("ités", ""),
+    ("ité", ""),
+    ("ments", ""),
+    ("ment", ""),
+    ("eaux", "eau"),
+    ("aux", "al"),
+    ("ées", ""),
+    ("ée", ""),
+    ("és", ""),
+    ("é", ""),
+    ("es", ""),
+    ("s", ""),
+    ("x", ""),
+    ("e", ""),
+];
+
+const GERMAN_STOP_WORDS: &[&str] = &[
+    "aber", "als", "am", "an", "auch", "auf", "aus", "bei", "bin", "bis", "da", "das", "dass",
+    "dem", "den", "der", "des", "die", "du", "ein", "eine", "einem", "einen", "einer", "eines",
+    "er", "es", "für", "hat", "ich", "ihr", "im", "in", "ist", "ja", "mit", "nach", "nicht",
+    "noch", "nur", "oder", "sich", "sie", "sind", "so", "um", "und", "uns", "von", "vor", "war",
+    "was", "wie", "wir", "wird", "zu", "zum", "zur",
+];
+
+const GERMAN_SUFFIXES: &[(&str, &str)] = &[
+    ("ungen", ""),
+    ("ung", ""),
+    ("heiten", ""),
+    ("heit", ""),
+    ("keiten", ""),
+    ("keit", ""),
+    ("lichen", ""),
+    ("liche", ""),
+    ("lich", ""),
+    ("ern", ""),
+    ("em", ""),
+    ("en", ""),
+    ("er", ""),
+    ("es", ""),
+    ("e", ""),
+    ("s", ""),
+];
+
+const SPANISH_STOP_WORDS: &[&str] = &[
+    "a", "al", "como", "con", "de", "del", "el", "en", "es", "esta", "este", "la", "las", "le",
+    "les", "lo", "los", "mas", "me", "mi", "no", "nos", "o", "para", "pero", "por", "que", "se",
+    "si", "sin", "su", "sus", "te", "tu", "un", "una", "uno", "unos", "y", "ya",
+];
+
+const SPANISH_SUFFIXES: &[(&str, &str)] = &[
+    ("amientos", ""),
+    ("imientos", ""),
+    ("amiento", ""),
+    ("imiento", ""),
+    ("aciones", ""),
+    ("ación", ""),
+    ("idades", ""),
+    ("idad", ""),
+    ("mente", ""),
+    ("es", ""),
+    ("os", ""),
+    ("as", ""),
+    ("o", ""),
+    ("a", ""),
+    ("e", ""),
+    ("s", ""),
+];
+
+const ITALIAN_STOP_WORDS: &[&str] = &[
+    "a", "al", "alla", "che", "con", "da", "dal", "del", "della", "di", "e", "è", "gli", "i", "il",
+    "in", "la", "le", "lo", "ma", "mi", "ne", "non", "per", "più", "se", "si", "su", "sua", "suo",
+    "un", "una", "uno",
+];
+
+const ITALIAN_SUFFIXES: &[(&str, &str)] = &[
+    ("amenti", ""),
+    ("amento", ""),
+    ("imenti", ""),
+    ("imento", ""),
+    ("azioni", ""),
+    ("azione", ""),
+    ("mente", ""),
+    ("ità", ""),
+    ("i", ""),
+    ("e", ""),
+    ("o", ""),
+    ("a", ""),
+];
+
+const PORTUGUESE_STOP_WORDS: &[&str] = &[
+    "a", "ao", "as", "com", "como", "da", "das", "de", "do", "dos", "e", "é", "em", "na", "nas",
+    "no", "nos", "o", "os", "ou", "para", "pela", "pelo", "por", "que", "se", "sem", "seu", "sua",
+    "um", "uma",
+];
+
+const PORTUGUESE_SUFFIXES: &[(&str, &str)] = &[
+    ("amentos", ""),
+    ("amento", ""),
+    ("ações", ""),
+    ("ação", ""),
+    ("idades", ""),
+    ("idade", ""),
+    ("mente", ""),
+    ("ões", "ão"),
+    ("s", ""),
+    ("a", ""),
+    ("o", ""),
+    ("e", ""),
+];
+
+const DUTCH_STOP_WORDS: &[&str] = &[
+    "aan", "al", "als", "bij", "dat", "de", "die", "dit", "een", "en", "er", "het", "hij", "ik",
+    "in", "is", "je", "maar", "met", "niet", "nog", "of", "om", "op", "te", "tot", "uit", "van",
+    "voor", "was", "we", "wij", "zijn", "ze", "zo",
+];
+
+const DUTCH_SUFFIXES: &[(&str, &str)] = &[
+    ("heden", ""),
+    ("heid", ""),
+    ("ingen", ""),
+    ("ing", ""),
+    ("lijk", ""),
+    ("en", ""),
+    ("e", ""),
+    ("s", ""),
+];
+
+/// Languages with stop words and stemming rules.
+pub const SEARCH_LANGUAGES: &[SearchLanguage] = &[
+    SearchLanguage {
+        code: "en",
+        stop_words: ENGLISH_STOP_WORDS,
+        suffixes: ENGLISH_SUFFIXES,
+        min_stem: 3,
+    },
+    SearchLanguage {
+        code: "fr",
+        stop_words: FRENCH_STOP_WORDS,
+        suffixes: FRENCH_SUFFIXES,
+        min_stem: 3,
+    },
+    SearchLanguage {
+        code: "de",
+        stop_words: GERMAN_STOP_WORDS,
+        suffixes: GERMAN_SUFFIXES,
+        min_stem: 4,
+    },
+    SearchLanguage {
+        code: "es",
+        stop_words: SPANISH_STOP_WORDS,
+        suffixes: SPANISH_SUFFIXES,
+        min_stem: 3,
+    },
+    SearchLanguage {
+        code: "it",
+        stop_words: ITALIAN_STOP_WORDS,
+        suffixes: ITALIAN_SUFFIXES,
+        min_stem: 3,
+    },
+    SearchLanguage {
+        code: "pt",
+        stop_words: PORTUGUESE_STOP_WORDS,
+        suffixes: PORTUGUESE_SUFFIXES,
+        min_stem: 3,
+    },
+    SearchLanguage {
+        code: "nl",
+        stop_words: DUTCH_STOP_WORDS,
+        suffixes: DUTCH_SUFFIXES,
+        min_stem: 3,
+    },
+];
+
+/// Analysis for languages without rules: tokenizing only.
+const UNKNOWN_LANGUAGE: SearchLanguage = SearchLanguage {
+    code: "",
+    stop_words: &[],
+    suffixes: &[],
+    min_stem: 0,
+};
+
+impl SearchLanguage {
+    /// Rules for an IETF language tag such as `fr-CA`, matched by its
+    /// primary subtag. A site without a `language` is treated as English.
+    pub fn for_language(language: Option<&str>) -> SearchLanguage {
+        let primary = language
+            .unwrap_or("en")
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        SEARCH_LANGUAGES
+            .iter()
+            .find(|candidate| candidate.code == primary)
+            .copied()
+            .unwrap_or(UNKNOWN_LANGUAGE)
+    }
+
+    /// Applies the first matching suffix rule to `token`.
+    pub fn stem(&self, token: &str) -> String {
+        let length = token.chars().count();
+        for (suffix, replacement) in self.suffixes {
+            let suffix_length = suffix.chars().count();
+            if token.ends_with(suffix) && length >= suffix_length + self.min_stem {
+                return format!("{}{replacement}", &token[..token.len() - suffix.len()]);
+            }
+        }
+        token.to_string()
+    }
+
+    /// Splits `text` into index terms: lowercase, split on whitespace,
+    /// hyphens, and apostrophes, trim non-alphanumeric characters from both ends, drop stop
+    /// words, and stem.
+    pub fn tokens(&self, text: &str) -> Vec<String> {
+        let is_word = |character: char| character.is_alphanumeric() || character == '_';
+        text.to_lowercase()
+            .split(|character: char| {
+                character.is_whitespace() || matches!(character, '-' | '\'' | '’')
+            })
+            .map(|token| token.trim_matches(|character: char| !is_word(character)))
+            .filter(|token| !token.is_empty() && !self.stop_words.contains(token))
+            .map(|token| self.stem(token))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_language() {
+        assert_eq!(SearchLanguage::for_language(None).code, "en");
+        assert_eq!(SearchLanguage::for_language(Some("fr-CA")).code, "fr");
+        assert_eq!(SearchLanguage::for_language(Some("PT_br")).code, "pt");
+        assert_eq!(SearchLanguage::for_language(Some("ja")), UNKNOWN_LANGUAGE);
+    }
+
+    #[test]
+    fn test_stem() {
+        let english = SearchLanguage::for_language(Some("en"));
+        for (word, stem) in [
+            ("deploys", "deploy"),
+            ("deployed", "deploy"),
+            ("deploying", "deploy"),
+            ("libraries", "library"),
+            ("class", "class"),
+            ("analysis", "analysis"),
+            ("is", "is"),
+            ("bed", "bed"),
+        ] {
+            assert_eq!(english.stem(word), stem, "{word}");
+        }
+        let french = SearchLanguage::for_language(Some("fr"));
+        assert_eq!(french.stem("journaux"), "journal");
+        assert_eq!(french.stem("publiées"), "publi");
+    }
+
+    #[test]
+    fn test_tokens() {
+        let english = SearchLanguage::for_language(Some("en"));
+        assert_eq!(
+            english.tokens("The Rust-powered (static) site generators!"),
+            ["rust", "power", "static", "site", "generator"]
+        );
+        let french = SearchLanguage::for_language(Some("fr"));
+        assert_eq!(
+            french.tokens("L'histoire des journaux"),
+            ["histoir", "journal"]
+        );
+        assert_eq!(
+            SearchLanguage::for_language(Some("ja")).tokens("The Sites"),
+            ["the", "sites"]
+        );
+    }
+}
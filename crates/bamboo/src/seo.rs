@@ -0,0 +1,359 @@
+//! Open Graph and Twitter card metadata. [`content_seo`] works out the
+//! title, description, image, and URL for a page, post, note, or item with
+//! fallbacks, and the built-in `partials/seo.html` (available to every
+//! theme) turns the result into `<meta>` tags, so themes don't each
+//! reimplement the fallback chain.
+//!
+//! Templates see the page's metadata as `seo` and the site-wide defaults
+//! used by listing pages as `site.seo`.
+
+use crate::types::{Content, Post, Site};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Name of the built-in partial that renders [`SeoMeta`] as `<meta>` tags.
+pub const SEO_PARTIAL: &str = "partials/seo.html";
+
+/// Longest auto-generated description, in characters.
+const DESCRIPTION_LENGTH: usize = 160;
+
+/// Metadata behind the Open Graph and Twitter card tags of one page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SeoMeta {
+    /// `og:title`: the page title followed by the site title.
+    pub title: String,
+    /// `og:description` and `<meta name="description">`.
+    pub description: String,
+    /// `og:type`: `article` for posts and notes, otherwise `website`.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Absolute canonical URL: the page's own URL, or `canonical_url`
+    /// frontmatter for content first published elsewhere.
+    pub url: String,
+    /// Absolute `og:image` URL, if any image was found.
+    pub image: Option<String>,
+    /// `og:site_name`.
+    pub site_name: String,
+    /// `twitter:card`: `summary_large_image` with an image, else `summary`.
+    pub twitter_card: &'static str,
+    /// `twitter:site`, from `extra.twitter_handle`.
+    pub twitter_site: Option<String>,
+}
+
+/// Metadata for pages without content of their own (the home page without
+/// `_index.md`, listings, and pagination): the site title and description,
+/// `extra.og_image`, and the home URL.
+pub fn site_seo(site: &Site) -> SeoMeta {
+    let image = extra_string(&site.config.extra, "og_image").or_else(|| author_avatar(site));
+    build(
+        site,
+        site.config.title.clone(),
+        site.config.description.clone().unwrap_or_default(),
+        "website",
+        format!("{}/", site.config.base_url),
+        image,
+    )
+}
+
+/// Metadata for `content`. `article` selects `og:type = "article"` and
+/// `excerpt` is the post's excerpt, if it has one.
+///
+/// The description is the first of `description` frontmatter,
+/// `extra.description`, `excerpt`, and the opening paragraph. The image is
+/// the first of `og_image`, `image`, and `extra.image` frontmatter, the
+/// first `<img>` in the body, `extra.og_image` in `bamboo.toml`, and the
+/// author profile avatar.
+pub fn content_seo(
+    site: &Site,
+    content: &Content,
+    article: bool,
+    excerpt: Option<&str>,
+) -> SeoMeta {
+    content_meta(site, content, article, excerpt, None)
+}
+
+/// [`content_seo`] for a post, with its generated
+/// [social image](crate::social_images) ahead of the body's first `<img>`.
+pub fn post_seo(site: &Site, post: &Post) -> SeoMeta {
+    content_meta(
+        site,
+        &post.content,
+        true,
+        post.excerpt.as_deref(),
+        crate::social_images::social_image_url(site, &post.content),
+    )
+}
+
+fn content_meta(
+    site: &Site,
+    content: &Content,
+    article: bool,
+    excerpt: Option<&str>,
+    generated_image: Option<String>,
+) -> SeoMeta {
+    let frontmatter = &content.frontmatter;
+    let extra: Option<Value> = frontmatter.get("extra");
+    let extra_field = |key: &str| {
+        extra
+            .as_ref()
+            .and_then(|extra| extra.get(key))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+
+    let description = frontmatter
+        .get_string("description")
+        .or_else(|| extra_field("description"))
+        .or_else(|| excerpt.map(str::to_string))
+        .or_else(|| crate::parsing::extract_excerpt(&content.raw_content, DESCRIPTION_LENGTH))
+        .or_else(|| site.config.description.clone())
+        .unwrap_or_default();
+
+    let image = frontmatter
+        .get_string("og_image")
+        .or_else(|| frontmatter.get_string("image"))
+        .or_else(|| extra_field("image"))
+        .map(|image| absolute_image(site, content, &image))
+        .or(generated_image)
+        .or_else(|| first_image(&content.html).map(|image| absolute_image(site, content, &image)))
+        .or_else(|| extra_string(&site.config.extra, "og_image"))
+        .or_else(|| author_avatar(site));
+
+    let title = if content.url == "/" {
+        site.config.title.clone()
+    } else {
+        format!("{} | {}", content.title, site.config.title)
+    };
+
+    build(
+        site,
+        title,
+        description,
+        if article { "article" } else { "website" },
+        frontmatter
+            .get_string("canonical_url")
+            .unwrap_or_else(|| format!("{}{}", site.config.base_url, content.url)),
+        image,
+    )
+}
+
+fn build(
+    site: &Site,
+    title: String,
+    description: String,
+    kind: &'static str,
+    url: String,
+    image: Option<String>,
+) -> SeoMeta {
+    let image = image.map(|image| {
+        if image.starts_with('/') && !image.starts_with("//") {
+            format!("{}{image}", site.config.base_url)
+        } else {
+            image
+        }
+    });
+    SeoMeta {
+        title,
+        description,
+        kind,
+        url,
+        twitter_card: if image.is_some() {
+            "summary_large_image"
+        } else {
+            "summary"
+        },
+        image,
+        site_name: site.config.title.clone(),
+        twitter_site: extra_string(&site.config.extra, "twitter_handle"),
+    }
+}
+
+fn extra_string(extra: &std::collections::HashMap<String, Value>, key: &str) -> Option<String> {
+    extra
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn author_avatar(site: &Site) -> Option<String> {
+    site.config
+        .extra
+        .get("author_profile")
+        .and_then(|profile| profile.get("avatar"))
+        .and_then(Value::as_str)
+        .filter(|avatar| !avatar.is_empty())
+        .map(str::to_string)
+}
+
+/// `src` of the first `<img>` in `html`.
+fn first_image(html: &str) -> Option<String> {
+    let tag_start = html.find("<img")?;
+    let tag = &html[tag_start..tag_start + html[tag_start..].find('>')?];
+    let src_start = tag.find("src=")? + "src=".len();
+    let quote = tag[src_start..].chars().next()?;
+    if quote != '"' && quote != '\'' {
+        return None;
+    }
+    let value = &tag[src_start + 1..];
+    let src = &value[..value.find(quote)?];
+    (!src.is_empty()).then(|| src.replace("&amp;", "&"))
+}
+
+/// Resolves an image path relative to `content`'s URL; root-relative and
+/// absolute URLs are left for [`build`] and as-is.
+fn absolute_image(site: &Site, content: &Content, image: &str) -> String {
+    if image.starts_with('/') || image.contains("://") {
+        image.to_string()
+    } else {
+        format!("{}{}{image}", site.config.base_url, content.url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Frontmatter, SiteConfig};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    fn blog_site(extra: &str) -> Site {
+        let config: SiteConfig = toml::from_str(&format!(
+            "title = \"Blog\"\nbase_url = \"https://example.com\"\ndescription = \"A blog\"\n[extra]\n{extra}"
+        ))
+        .unwrap();
+        Site {
+            config,
+            home: None,
+            pages: Vec::new(),
+            posts: Vec::new(),
+            notes: Vec::new(),
+            collections: HashMap::new(),
+            data: HashMap::new(),
+            assets: Vec::new(),
+            image_requests: Vec::new(),
+        }
+    }
+
+    fn content(frontmatter: Value, raw_content: &str, html: &str) -> Content {
+        let frontmatter = match frontmatter {
+            Value::Object(map) => Frontmatter::new(map.into_iter().collect()),
+            _ => Frontmatter::default(),
+        };
+        Content {
+            slug: "hello".to_string(),
+            title: "Hello".to_string(),
+            html: html.to_string(),
+            raw_content: raw_content.to_string(),
+            frontmatter,
+            path: PathBuf::from("posts/hello/index.html"),
+            source_path: PathBuf::from("posts/hello.md"),
+            modified: None,
+            template: None,
+            weight: 0,
+            word_count: 0,
+            reading_time: 0,
+            stats: crate::types::PageStats::default(),
+            lang: None,
+            translations: Vec::new(),
+            toc: Vec::new(),
+            url: "/posts/hello/".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_content_seo_prefers_frontmatter() {
+        let site = blog_site("twitter_handle = \"@blog\"");
+        let content = content(
+            serde_json::json!({"description": "Custom", "image": "/cover.png"}),
+            "First paragraph.",
+            "<p><img src=\"inline.png\"></p>",
+        );
+
+        let seo = content_seo(&site, &content, true, Some("Excerpt"));
+
+        assert_eq!(
+            seo,
+            SeoMeta {
+                title: "Hello | Blog".to_string(),
+                description: "Custom".to_string(),
+                kind: "article",
+                url: "https://example.com/posts/hello/".to_string(),
+                image: Some("https://example.com/cover.png".to_string()),
+                site_name: "Blog".to_string(),
+                twitter_card: "summary_large_image",
+                twitter_site: Some("@blog".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_content_seo_fallbacks() {
+        let site = blog_site("og_image = \"/default.png\"");
+        let with_inline = content(
+            serde_json::json!({}),
+            "Opening paragraph.\n\nMore.",
+            "<p><img alt=\"x\" src=\"diagram.png\"></p>",
+        );
+        let seo = content_seo(&site, &with_inline, false, None);
+        assert_eq!(seo.description, "Opening paragraph.");
+        assert_eq!(seo.kind, "website");
+        assert_eq!(
+            seo.image.as_deref(),
+            Some("https://example.com/posts/hello/diagram.png")
+        );
+
+        let bare = content(serde_json::json!({}), "", "");
+        let seo = content_seo(&site, &bare, false, None);
+        assert_eq!(seo.description, "A blog");
+        assert_eq!(
+            seo.image.as_deref(),
+            Some("https://example.com/default.png")
+        );
+
+        let seo = content_seo(&blog_site(""), &bare, false, None);
+        assert_eq!(seo.image, None);
+        assert_eq!(seo.twitter_card, "summary");
+    }
+
+    #[test]
+    fn test_post_seo_uses_social_image() {
+        let mut site = blog_site("");
+        site.config.social_images = Some(Default::default());
+        let post = |frontmatter: Value| Post {
+            content: content(frontmatter, "", "<img src=\"inline.png\">"),
+            date: chrono::Utc::now(),
+            excerpt: None,
+            draft: false,
+            tags: Vec::new(),
+            categories: Vec::new(),
+            taxonomies_map: HashMap::new(),
+            redirect_from: Vec::new(),
+            discussion: None,
+            link_preview: None,
+        };
+
+        let seo = post_seo(&site, &post(serde_json::json!({})));
+        assert_eq!(
+            seo.image.as_deref(),
+            Some("https://example.com/posts/hello/social.png")
+        );
+
+        let seo = post_seo(&site, &post(serde_json::json!({"image": "/cover.png"})));
+        assert_eq!(seo.image.as_deref(), Some("https://example.com/cover.png"));
+
+        let seo = post_seo(&site, &post(serde_json::json!({"social_image": false})));
+        assert_eq!(
+            seo.image.as_deref(),
+            Some("https://example.com/posts/hello/inline.png")
+        );
+    }
+
+    #[test]
+    fn test_site_seo() {
+        let seo = site_seo(&blog_site(""));
+        assert_eq!(seo.title, "Blog");
+        assert_eq!(seo.url, "https://example.com/");
+        assert_eq!(seo.description, "A blog");
+    }
+}
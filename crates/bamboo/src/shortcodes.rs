@@ -2,23 +2,30 @@
 //! `{{% name %}}...{{% /name %}}` tags in markdown content by rendering
 //! Tera templates under `templates/shortcodes/`.
 //!
-//! Built-in shortcodes (`youtube`, `figure`, `gist`, `pdf`, `note`, `details`)
+//! Built-in shortcodes (`youtube`, `figure`, `image`, `gist`, `pdf`, `note`,
+//! `details`, `tabs`)
 //! are compiled into the binary; user-provided templates in the site or theme
 //! take priority.
 
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
 
+use sha2::{Digest, Sha256};
 use tera::Tera;
 
 use crate::error::{BambooError, Result};
+use crate::images::ImageRequest;
 use crate::parsing::MarkdownRenderer;
 
 const BUILTIN_YOUTUBE: &str = include_str!("../themes/default/templates/shortcodes/youtube.html");
 const BUILTIN_FIGURE: &str = include_str!("../themes/default/templates/shortcodes/figure.html");
+const BUILTIN_IMAGE: &str = include_str!("../themes/default/templates/shortcodes/image.html");
 const BUILTIN_NOTE: &str = include_str!("../themes/default/templates/shortcodes/note.html");
 const BUILTIN_DETAILS: &str = include_str!("../themes/default/templates/shortcodes/details.html");
 const BUILTIN_GIST: &str = include_str!("../themes/default/templates/shortcodes/gist.html");
 const BUILTIN_PDF: &str = include_str!("../themes/default/templates/shortcodes/pdf.html");
+const BUILTIN_TABS: &str = include_str!("../themes/default/templates/shortcodes/tabs.html");
 
 /// Expands `{{< ... >}}` inline and `{{% ... %}}` block shortcodes found in
 /// markdown content by rendering Tera templates from either the built-in
@@ -27,6 +34,8 @@ pub struct ShortcodeProcessor {
     tera: Tera,
     ref_registry: HashMap<String, String>,
     base_url: String,
+    site_config: serde_json::Value,
+    image_requests: Arc<Mutex<BTreeSet<ImageRequest>>>,
 }
 
 impl ShortcodeProcessor {
@@ -39,6 +48,8 @@ impl ShortcodeProcessor {
             .map_err(BambooError::Template)?;
         tera.add_raw_template("shortcodes/figure.html", BUILTIN_FIGURE)
             .map_err(BambooError::Template)?;
+        tera.add_raw_template("shortcodes/image.html", BUILTIN_IMAGE)
+            .map_err(BambooError::Template)?;
         tera.add_raw_template("shortcodes/note.html", BUILTIN_NOTE)
             .map_err(BambooError::Template)?;
         tera.add_raw_template("shortcodes/details.html", BUILTIN_DETAILS)
@@ -47,8 +58,14 @@ impl ShortcodeProcessor {
             .map_err(BambooError::Template)?;
         tera.add_raw_template("shortcodes/pdf.html", BUILTIN_PDF)
             .map_err(BambooError::Template)?;
+        tera.add_raw_template("shortcodes/tabs.html", BUILTIN_TABS)
+            .map_err(BambooError::Template)?;
 
+        let mut custom_templates = Vec::new();
         for directory in shortcode_dirs {
+            if let Some(templates_dir) = directory.parent() {
+                custom_templates.extend(collect_subdirectory_templates(templates_dir, "macros")?);
+            }
             if directory.is_dir()
                 && let Ok(entries) = std::fs::read_dir(directory)
             {
@@ -59,20 +76,36 @@ impl ShortcodeProcessor {
                     {
                         let template_name = format!("shortcodes/{}", file_name);
                         let template_content = std::fs::read_to_string(&path)?;
-                        tera.add_raw_template(&template_name, &template_content)
-                            .map_err(BambooError::Template)?;
+                        custom_templates.push((template_name, template_content));
                     }
                 }
             }
         }
+        tera.add_raw_templates(custom_templates)
+            .map_err(BambooError::Template)?;
+
+        let image_requests = Arc::new(Mutex::new(BTreeSet::new()));
+        register_image_variant(&mut tera, &image_requests);
+        register_art_direction(&mut tera);
 
         Ok(Self {
             tera,
             ref_registry: HashMap::new(),
             base_url: String::new(),
+            site_config: serde_json::Value::Null,
+            image_requests,
         })
     }
 
+    /// Image sizes requested through `image_variant` (by the `image`
+    /// shortcode) so far, sorted.
+    pub fn image_requests(&self) -> Vec<ImageRequest> {
+        self.image_requests
+            .lock()
+            .map(|requests| requests.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
     /// Replaces the `ref` resolution table used by `{{< ref "path.md" >}}`
     /// with the given map from source-path to resolved URL.
     pub fn set_ref_registry(&mut self, registry: HashMap<String, String>) {
@@ -86,6 +119,18 @@ impl ShortcodeProcessor {
         self.base_url = base_url.into().trim_end_matches('/').to_string();
     }
 
+    /// Exposes the site configuration to shortcode templates as
+    /// `{{ site.config.* }}`.
+    pub fn set_site_config(&mut self, config: &crate::types::SiteConfig) {
+        self.site_config = serde_json::to_value(config).unwrap_or_default();
+    }
+
+    /// Registers consumer-supplied filters, functions, and testers on the
+    /// shortcode template engine.
+    pub fn register_extensions(&mut self, extensions: &crate::extensions::TemplateExtensions) {
+        extensions.apply(&mut self.tera);
+    }
+
     /// Registers the default theme's `partials/header.html`,
     /// `partials/footer.html`, and `partials/nav.html` so shortcodes can
     /// `{% include %}` them.
@@ -125,40 +170,50 @@ impl ShortcodeProcessor {
         &mut self,
         templates_dir: &std::path::Path,
     ) -> Result<()> {
-        let partials_dir = templates_dir.join("partials");
-        if !partials_dir.is_dir() {
-            return Ok(());
-        }
-        for entry in walkdir::WalkDir::new(&partials_dir)
-            .min_depth(1)
-            .into_iter()
-            .filter_map(|entry| entry.ok())
-        {
-            let path = entry.path();
-            if !path.is_file() {
-                continue;
-            }
-            if path.extension().and_then(|extension| extension.to_str()) != Some("html") {
-                continue;
-            }
-            let relative =
-                path.strip_prefix(templates_dir)
-                    .map_err(|_| BambooError::InvalidPath {
-                        path: path.to_path_buf(),
-                    })?;
-            let template_name = relative.to_string_lossy().replace('\\', "/");
-            let content = std::fs::read_to_string(path)?;
-            self.tera
-                .add_raw_template(&template_name, &content)
-                .map_err(BambooError::Template)?;
-        }
-        Ok(())
+        let templates = collect_subdirectory_templates(templates_dir, "partials")?;
+        self.tera
+            .add_raw_templates(templates)
+            .map_err(BambooError::Template)
+    }
+
+    /// Registers every `*.html` file under `templates_dir/macros/` so
+    /// shortcode templates and partials can
+    /// `{% import "macros/<file>.html" as <name> %}`.
+    pub fn register_macros_from_directory(
+        &mut self,
+        templates_dir: &std::path::Path,
+    ) -> Result<()> {
+        let templates = collect_subdirectory_templates(templates_dir, "macros")?;
+        self.tera
+            .add_raw_templates(templates)
+            .map_err(BambooError::Template)
     }
 
     /// Expands every shortcode in `content` and returns the result.
     /// Block-shortcode bodies are rendered as markdown via `renderer`
     /// before substitution.
     pub fn process(&self, content: &str, renderer: &MarkdownRenderer) -> Result<String> {
+        self.process_in_scope(content, renderer, &Scope::new(None))
+    }
+
+    /// Like [`ShortcodeProcessor::process`], but templates also receive
+    /// `page` describing the content being rendered.
+    pub fn process_for_page(
+        &self,
+        content: &str,
+        renderer: &MarkdownRenderer,
+        page: &ShortcodePage<'_>,
+    ) -> Result<String> {
+        let page = page.to_value(&self.base_url);
+        self.process_in_scope(content, renderer, &Scope::new(Some(&page)))
+    }
+
+    fn process_in_scope(
+        &self,
+        content: &str,
+        renderer: &MarkdownRenderer,
+        scope: &Scope<'_>,
+    ) -> Result<String> {
         let mut output = String::with_capacity(content.len());
         let mut remaining = content;
 
@@ -214,21 +269,24 @@ impl ShortcodeProcessor {
                         output.push_str(&remaining[..block_start]);
                         remaining = &remaining[block_start..];
                         remaining =
-                            self.process_block_shortcode(remaining, &mut output, renderer)?;
+                            self.process_block_shortcode(remaining, &mut output, renderer, scope)?;
                     } else {
                         output.push_str(&remaining[..inline_start]);
                         remaining = &remaining[inline_start..];
-                        remaining = self.process_inline_shortcode(remaining, &mut output)?;
+                        remaining =
+                            self.process_inline_shortcode(remaining, &mut output, scope.page)?;
                     }
                 } else {
                     output.push_str(&remaining[..inline_start]);
                     remaining = &remaining[inline_start..];
-                    remaining = self.process_inline_shortcode(remaining, &mut output)?;
+                    remaining =
+                        self.process_inline_shortcode(remaining, &mut output, scope.page)?;
                 }
             } else if let Some(block_start) = next_block {
                 output.push_str(&remaining[..block_start]);
                 remaining = &remaining[block_start..];
-                remaining = self.process_block_shortcode(remaining, &mut output, renderer)?;
+                remaining =
+                    self.process_block_shortcode(remaining, &mut output, renderer, scope)?;
             } else {
                 output.push_str(remaining);
                 break;
@@ -238,7 +296,12 @@ impl ShortcodeProcessor {
         Ok(output)
     }
 
-    fn process_inline_shortcode<'a>(&self, input: &'a str, output: &mut String) -> Result<&'a str> {
+    fn process_inline_shortcode<'a>(
+        &self,
+        input: &'a str,
+        output: &mut String,
+        page: Option<&serde_json::Value>,
+    ) -> Result<&'a str> {
         let after_open = &input[3..];
 
         let close_position = after_open
@@ -269,11 +332,7 @@ impl ShortcodeProcessor {
         }
 
         let template_name = format!("shortcodes/{}.html", name);
-        let mut context = tera::Context::new();
-        for (key, value) in &arguments {
-            context.insert(key.as_str(), value);
-        }
-        context.insert("base_url", &self.base_url);
+        let context = self.base_context(&arguments, page);
 
         let rendered = self
             .tera
@@ -293,7 +352,9 @@ impl ShortcodeProcessor {
         input: &'a str,
         output: &mut String,
         renderer: &MarkdownRenderer,
+        scope: &Scope<'_>,
     ) -> Result<&'a str> {
+        let occurrence = scope.next_block();
         let after_open = &input[3..];
 
         let close_position = after_open
@@ -321,16 +382,41 @@ impl ShortcodeProcessor {
         })?;
 
         let body_raw = &after_opening_tag[..closing_position];
-        let body_processed = self.process(body_raw.trim(), renderer)?;
-        let body_rendered = renderer.render(&body_processed);
+        let (body_raw, slot_sources) = self.extract_slots(&name, body_raw)?;
+        let inline = match arguments.get("markdown").map(String::as_str) {
+            Some("inline") => true,
+            Some("block") => false,
+            Some(other) => {
+                return Err(BambooError::ShortcodeParse {
+                    message: format!(
+                        "invalid markdown mode '{other}' for shortcode '{name}', expected \"inline\" or \"block\""
+                    ),
+                });
+            }
+            None => is_inline_position(output) && !body_raw.trim().contains('\n'),
+        };
+        let body_html = self.render_body(&body_raw, inline, renderer, scope)?;
+
+        let mut slots = Vec::with_capacity(slot_sources.len());
+        for (kind, slot_arguments, slot_body) in slot_sources {
+            let slot_inline = slot_arguments.get("markdown").map(String::as_str) == Some("inline");
+            let body = self.render_body(slot_body, slot_inline, renderer, scope)?;
+            slots.push(ShortcodeSlot {
+                kind,
+                args: slot_arguments,
+                body,
+            });
+        }
 
         let template_name = format!("shortcodes/{}.html", name);
-        let mut context = tera::Context::new();
-        for (key, value) in &arguments {
-            context.insert(key.as_str(), value);
-        }
-        context.insert("body", &body_rendered.html);
-        context.insert("base_url", &self.base_url);
+        let mut context = self.base_context(&arguments, scope.page);
+        context.insert("body", &body_html);
+        context.insert("slots", &slots);
+        context.insert(
+            "shortcode_id",
+            &shortcode_id(occurrence, body_raw.as_ref(), &slots),
+        );
+        context.insert("inline", &inline);
 
         let rendered = self
             .tera
@@ -340,10 +426,366 @@ impl ShortcodeProcessor {
                 message: error.to_string(),
             })?;
 
-        output.push_str(&rendered);
+        if inline {
+            output.push_str(rendered.trim());
+        } else {
+            output.push_str(&rendered);
+        }
 
         Ok(&after_opening_tag[closing_position + closing_tag.len()..])
     }
+
+    /// Context shared by inline and block shortcodes: `site`, `page` (when
+    /// known), and `base_url`, with the shortcode's own arguments taking
+    /// precedence over `site`/`page` of the same name.
+    fn base_context(
+        &self,
+        arguments: &HashMap<String, String>,
+        page: Option<&serde_json::Value>,
+    ) -> tera::Context {
+        let mut context = tera::Context::new();
+        context.insert("site", &serde_json::json!({ "config": self.site_config }));
+        if let Some(page) = page {
+            context.insert("page", page);
+        }
+        for (key, value) in arguments {
+            context.insert(key.as_str(), value);
+        }
+        context.insert("base_url", &self.base_url);
+        context
+    }
+
+    fn render_body(
+        &self,
+        raw: &str,
+        inline: bool,
+        renderer: &MarkdownRenderer,
+        scope: &Scope<'_>,
+    ) -> Result<String> {
+        let processed = self.process_in_scope(raw.trim(), renderer, scope)?;
+        if inline {
+            renderer.render_inline(&processed)
+        } else {
+            Ok(renderer.render(&processed)?.html)
+        }
+    }
+
+    /// Splits top-level child block shortcodes that have no template of
+    /// their own (e.g. `{{% tab %}}` inside `{{% tabs %}}`) out of the body
+    /// of `parent`. Returns the remaining body and each slot's name,
+    /// arguments, and raw body in source order. Such a child is an unknown
+    /// shortcode, and an error, unless the parent's template uses `slots`.
+    fn extract_slots<'a>(
+        &self,
+        parent: &str,
+        body: &'a str,
+    ) -> Result<(std::borrow::Cow<'a, str>, Vec<SlotSource<'a>>)> {
+        let mut slots = Vec::new();
+        let mut remaining_body = String::new();
+        let mut cursor = 0;
+        let mut search_from = 0;
+
+        while let Some(offset) = body[search_from..].find("{{%") {
+            let start = search_from + offset;
+            let after_open = &body[start + 3..];
+            let Some(close) = after_open.find("%}}") else {
+                break;
+            };
+            let inner = after_open[..close].trim();
+            if inner.starts_with('/') {
+                search_from = start + 3;
+                continue;
+            }
+            let (child, child_arguments) = parse_shortcode_args(inner)?;
+            let after_tag = start + 3 + close + 3;
+            let closing_tag = format!("{{{{% /{} %}}}}", child);
+            let Some(child_close) = find_matching_closing_tag(
+                &body[after_tag..],
+                &format!("{{{{% {} ", child),
+                &format!("{{{{% {} %}}}}", child),
+                &closing_tag,
+            ) else {
+                search_from = after_tag;
+                continue;
+            };
+            let end = after_tag + child_close + closing_tag.len();
+
+            let has_template = self
+                .tera
+                .get_template_names()
+                .any(|existing| existing == format!("shortcodes/{child}.html"));
+            if !has_template {
+                if !self.uses_slots(parent) {
+                    return Err(BambooError::ShortcodeRender {
+                        message: format!(
+                            "no template shortcodes/{child}.html, and '{parent}' takes no slots"
+                        ),
+                        name: child,
+                    });
+                }
+                remaining_body.push_str(&body[cursor..start]);
+                slots.push((
+                    child,
+                    child_arguments,
+                    &body[after_tag..after_tag + child_close],
+                ));
+                cursor = end;
+            }
+            search_from = end;
+        }
+
+        if slots.is_empty() {
+            return Ok((std::borrow::Cow::Borrowed(body), slots));
+        }
+        remaining_body.push_str(&body[cursor..]);
+        Ok((std::borrow::Cow::Owned(remaining_body), slots))
+    }
+
+    /// Whether the template of block shortcode `name` reads `slots`.
+    fn uses_slots(&self, name: &str) -> bool {
+        self.tera
+            .get_template(&format!("shortcodes/{name}.html"))
+            .is_ok_and(|template| {
+                crate::lint::TemplateRefs::from_nodes(&template.ast)
+                    .variables
+                    .contains("slots")
+            })
+    }
+}
+
+/// The content shortcodes are being expanded in: the page, if known, and
+/// how many block shortcodes have been expanded in it so far.
+struct Scope<'a> {
+    page: Option<&'a serde_json::Value>,
+    blocks: Cell<usize>,
+}
+
+impl<'a> Scope<'a> {
+    fn new(page: Option<&'a serde_json::Value>) -> Self {
+        Self {
+            page,
+            blocks: Cell::new(0),
+        }
+    }
+
+    /// Position of the block shortcode starting now among those of the
+    /// content, counting nested ones.
+    fn next_block(&self) -> usize {
+        let index = self.blocks.get();
+        self.blocks.set(index + 1);
+        index
+    }
+}
+
+/// The content a shortcode is being expanded in, exposed to templates as
+/// `page`. Frontmatter fields are flattened alongside `title`, `slug`,
+/// `url`, and `permalink` (the absolute URL).
+#[derive(Debug, Clone, Copy)]
+pub struct ShortcodePage<'a> {
+    /// Content title.
+    pub title: &'a str,
+    /// URL slug.
+    pub slug: &'a str,
+    /// Site-relative URL, e.g. `/posts/hello/`.
+    pub url: &'a str,
+    /// Parsed frontmatter.
+    pub frontmatter: &'a crate::types::Frontmatter,
+}
+
+impl ShortcodePage<'_> {
+    fn to_value(self, base_url: &str) -> serde_json::Value {
+        let mut map: serde_json::Map<String, serde_json::Value> = self
+            .frontmatter
+            .raw
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        map.insert("title".to_string(), self.title.into());
+        map.insert("slug".to_string(), self.slug.into());
+        map.insert("url".to_string(), self.url.into());
+        map.insert(
+            "permalink".to_string(),
+            format!("{}{}", base_url, self.url).into(),
+        );
+        serde_json::Value::Object(map)
+    }
+}
+
+/// A slot's child shortcode name, arguments, and raw body.
+type SlotSource<'a> = (String, HashMap<String, String>, &'a str);
+
+/// A named inner section of a block shortcode, exposed to the outer
+/// template as an entry of `slots`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ShortcodeSlot {
+    /// Child shortcode name, e.g. `tab`.
+    pub kind: String,
+    /// Arguments written on the child tag, e.g. `name="Rust"`.
+    pub args: HashMap<String, String>,
+    /// Child body rendered as markdown.
+    pub body: String,
+}
+
+/// Identifier for one block shortcode invocation, derived from its
+/// position among the content's block shortcodes and its content, so
+/// identical blocks on one page get distinct ids and element ids survive
+/// rebuilds unchanged.
+fn shortcode_id(occurrence: usize, body: &str, slots: &[ShortcodeSlot]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update((occurrence as u64).to_le_bytes());
+    let mut feed = |text: &str| {
+        hasher.update((text.len() as u64).to_le_bytes());
+        hasher.update(text);
+    };
+    feed(body);
+    for slot in slots {
+        feed(&slot.kind);
+        feed(&slot.body);
+    }
+    format!("sc-{}", &format!("{:x}", hasher.finalize())[..8])
+}
+
+/// Returns `true` when the text already emitted on the current line is
+/// non-blank, i.e. a shortcode starting here sits in the middle of a
+/// sentence rather than on its own line.
+fn is_inline_position(output: &str) -> bool {
+    output
+        .rsplit('\n')
+        .next()
+        .is_some_and(|line| !line.trim().is_empty())
+}
+
+/// Names of every shortcode opened in `content` (`{{< name ... >}}` or
+/// `{{% name ... %}}`), without rendering anything. Used to track which
+/// `shortcodes/*.html` templates a page depends on.
+pub(crate) fn shortcode_names(content: &str) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let mut remaining = content;
+    while let Some(start) = remaining
+        .find("{{<")
+        .into_iter()
+        .chain(remaining.find("{{%"))
+        .min()
+    {
+        let after_open = remaining[start + 3..].trim_start();
+        let name: String = after_open
+            .chars()
+            .take_while(|character| {
+                character.is_alphanumeric() || *character == '_' || *character == '-'
+            })
+            .collect();
+        if !name.is_empty() {
+            names.insert(name);
+        }
+        remaining = &remaining[start + 3..];
+    }
+    names
+}
+
+/// Registers `image_variant(src, width, height, format)`, which records an
+/// [`ImageRequest`] in `requests` and returns the variant's path from the
+/// site root, e.g. `/images/photo-800w.webp`. `width` and `height` may be
+/// numbers or numeric strings, as shortcode arguments are; an empty
+/// `height` means no crop.
+fn register_image_variant(tera: &mut Tera, requests: &Arc<Mutex<BTreeSet<ImageRequest>>>) {
+    let requests = requests.clone();
+    tera.register_function(
+        "image_variant",
+        move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+            let dimension = |name: &str| -> tera::Result<Option<u32>> {
+                let value = match args.get(name) {
+                    None | Some(tera::Value::Null) => return Ok(None),
+                    Some(tera::Value::String(text)) if text.trim().is_empty() => return Ok(None),
+                    Some(value) => value,
+                };
+                value
+                    .as_u64()
+                    .or_else(|| value.as_str().and_then(|text| text.trim().parse().ok()))
+                    .and_then(|number| u32::try_from(number).ok())
+                    .filter(|number| *number > 0)
+                    .map(Some)
+                    .ok_or_else(|| {
+                        tera::Error::msg(format!(
+                            "image_variant `{name}` must be a positive number of pixels"
+                        ))
+                    })
+            };
+            let src = args
+                .get("src")
+                .and_then(|value| value.as_str())
+                .ok_or_else(|| tera::Error::msg("image_variant requires a `src` argument"))?;
+            let format = args
+                .get("format")
+                .and_then(|value| value.as_str())
+                .ok_or_else(|| tera::Error::msg("image_variant requires a `format` argument"))?;
+            let width = dimension("width")?
+                .ok_or_else(|| tera::Error::msg("image_variant requires a `width` argument"))?;
+            let request = ImageRequest {
+                src: src.to_string(),
+                width,
+                height: dimension("height")?,
+            };
+            let path = request.variant_path(format);
+            if let Ok(mut requests) = requests.lock() {
+                requests.insert(request);
+            }
+            Ok(tera::Value::String(format!("/{path}")))
+        },
+    );
+}
+
+/// Registers `art_direction(rules)`, which parses the `image` shortcode's
+/// `art` argument into the same `{media, width, height}` list as
+/// `site.config.images.art_direction`.
+fn register_art_direction(tera: &mut Tera) {
+    tera.register_function(
+        "art_direction",
+        |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+            let rules = args
+                .get("rules")
+                .and_then(|value| value.as_str())
+                .ok_or_else(|| tera::Error::msg("art_direction requires a `rules` argument"))?;
+            let rules = crate::images::parse_art_direction(rules).map_err(tera::Error::msg)?;
+            tera::to_value(rules).map_err(tera::Error::from)
+        },
+    );
+}
+
+/// Reads every `*.html` file found recursively under
+/// `templates_dir/<subdirectory>/`, named by its path relative to
+/// `templates_dir` (e.g. `macros/cards.html`).
+pub(crate) fn collect_subdirectory_templates(
+    templates_dir: &std::path::Path,
+    subdirectory: &str,
+) -> Result<Vec<(String, String)>> {
+    let directory = templates_dir.join(subdirectory);
+    let mut templates = Vec::new();
+    if !directory.is_dir() {
+        return Ok(templates);
+    }
+    for entry in walkdir::WalkDir::new(&directory)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+    {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        if path.extension().and_then(|extension| extension.to_str()) != Some("html") {
+            continue;
+        }
+        let relative = path
+            .strip_prefix(templates_dir)
+            .map_err(|_| BambooError::InvalidPath {
+                path: path.to_path_buf(),
+            })?;
+        let template_name = relative.to_string_lossy().replace('\\', "/");
+        let content = std::fs::read_to_string(path)?;
+        templates.push((template_name, content));
+    }
+    Ok(templates)
 }
 
 fn parse_shortcode_args(input: &str) -> Result<(String, HashMap<String, String>)> {
@@ -596,6 +1038,276 @@ mod tests {
         MarkdownRenderer::new()
     }
 
+    #[test]
+    fn test_shortcode_imports_site_macros() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let templates = dir.path().join("templates");
+        std::fs::create_dir_all(templates.join("shortcodes")).unwrap();
+        std::fs::create_dir_all(templates.join("macros")).unwrap();
+        std::fs::write(
+            templates.join("shortcodes/badge.html"),
+            "{% import \"macros/ui.html\" as ui %}{{ ui::badge(text=text) }}",
+        )
+        .unwrap();
+        std::fs::write(
+            templates.join("macros/ui.html"),
+            "{% macro badge(text) %}<span class=\"badge\">{{ text }}</span>{% endmacro badge %}",
+        )
+        .unwrap();
+
+        let processor = ShortcodeProcessor::new(&[templates.join("shortcodes")]).unwrap();
+        let result = processor
+            .process("{{< badge text=\"new\" >}}", &renderer())
+            .unwrap();
+        assert!(result.contains("<span class=\"badge\">new</span>"));
+    }
+
+    #[test]
+    fn test_block_shortcode_inline_mode() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let shortcodes = dir.path().join("shortcodes");
+        std::fs::create_dir_all(&shortcodes).unwrap();
+        std::fs::write(
+            shortcodes.join("kbd.html"),
+            "<kbd>{{ body | safe }}</kbd>\n",
+        )
+        .unwrap();
+        let processor = ShortcodeProcessor::new(&[shortcodes]).unwrap();
+
+        let mid_sentence = processor
+            .process("Press {{% kbd %}}**Ctrl**{{% /kbd %}} now.", &renderer())
+            .unwrap();
+        assert_eq!(mid_sentence, "Press <kbd><strong>Ctrl</strong></kbd> now.");
+
+        let explicit = processor
+            .process("{{% kbd markdown=\"inline\" %}}C{{% /kbd %}}", &renderer())
+            .unwrap();
+        assert_eq!(explicit, "<kbd>C</kbd>");
+
+        let block = processor
+            .process("{{% kbd %}}C{{% /kbd %}}", &renderer())
+            .unwrap();
+        assert!(block.contains("<kbd><p>C</p>"));
+
+        let invalid = processor.process("{{% kbd markdown=\"bogus\" %}}C{{% /kbd %}}", &renderer());
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_block_shortcode_named_slots() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let shortcodes = dir.path().join("shortcodes");
+        std::fs::create_dir_all(&shortcodes).unwrap();
+        std::fs::write(
+            shortcodes.join("panels.html"),
+            "{% for slot in slots %}[{{ slot.kind }}:{{ slot.args.name }}={{ slot.body | safe }}]{% endfor %}|{{ body | safe }}",
+        )
+        .unwrap();
+        let processor = ShortcodeProcessor::new(&[shortcodes]).unwrap();
+
+        let input = "{{% panels %}}\nIntro\n{{% tab name=\"Rust\" %}}\n**fn**\n{{% /tab %}}\n{{% tab name=\"Go\" markdown=\"inline\" %}}func{{% /tab %}}\n{{% /panels %}}";
+        let result = processor.process(input, &renderer()).unwrap();
+        assert!(
+            result.starts_with("[tab:Rust=<p><strong>fn</strong></p>\n][tab:Go=func]|<p>Intro</p>")
+        );
+    }
+
+    #[test]
+    fn test_builtin_tabs_shortcode() {
+        let processor = processor();
+        let input = "{{% tabs %}}\n{{% tab name=\"Rust\" %}}\nfn main() {}\n{{% /tab %}}\n{{% tab name=\"Python\" %}}\ndef main(): pass\n{{% /tab %}}\n{{% /tabs %}}";
+        let result = processor.process(input, &renderer()).unwrap();
+        assert!(result.contains("<label for=\"sc-"));
+        assert!(result.contains(">Rust</label>"));
+        assert!(result.contains(">Python</label>"));
+        assert_eq!(result.matches("class=\"bamboo-tab-panel\"").count(), 2);
+        assert_eq!(result, processor.process(input, &renderer()).unwrap());
+    }
+
+    #[test]
+    fn test_identical_tabs_get_distinct_ids() {
+        let processor = processor();
+        let tabs = "{{% tabs %}}\n{{% tab name=\"A\" %}}\nSame\n{{% /tab %}}\n{{% /tabs %}}";
+        let result = processor
+            .process(&format!("{tabs}\n\n{tabs}"), &renderer())
+            .unwrap();
+        let ids: Vec<&str> = result
+            .match_indices("class=\"bamboo-tabs\" id=\"")
+            .map(|(start, matched)| {
+                let id = &result[start + matched.len()..];
+                &id[..id.find('"').unwrap()]
+            })
+            .collect();
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], ids[1]);
+        assert!(ids[0].starts_with("sc-") && ids[0].len() == 11);
+    }
+
+    #[test]
+    fn test_unknown_child_shortcode_without_slots() {
+        let processor = processor();
+        let error = processor
+            .process(
+                "{{% note %}}\n{{% warning %}}\nCareful\n{{% /warning %}}\n{{% /note %}}",
+                &renderer(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Shortcode render error in 'warning': no template shortcodes/warning.html, and 'note' takes no slots"
+        );
+    }
+
+    #[test]
+    fn test_builtin_image_shortcode() {
+        let mut processor = processor();
+        let input = r#"{{< image src="/images/photo.jpg" width="800" height="600" alt="A lake" caption="Morning" >}}"#;
+        let plain = processor.process(input, &renderer()).unwrap();
+        assert!(
+            plain.contains(r#"<img src="/images/photo.jpg" alt="A lake" width="800" height="600""#)
+        );
+        assert!(!plain.contains("<picture>"));
+        assert!(processor.image_requests().is_empty());
+
+        let mut config = crate::template_test::sample_site().unwrap().config;
+        config.images = Some(crate::images::ImageConfig::default());
+        processor.set_site_config(&config);
+        processor.set_base_url("https://example.com/blog/");
+        let result = processor.process(input, &renderer()).unwrap();
+        assert!(result.contains(
+            r#"<source type="image/webp" srcset="https://example.com/blog/images/photo-800x600.webp">"#
+        ));
+        assert!(result.contains(
+            r#"<img src="https://example.com/blog/images/photo-800x600.jpg" alt="A lake" width="800" height="600" loading="lazy">"#
+        ));
+        assert!(result.contains("<figcaption>Morning</figcaption>"));
+
+        processor
+            .process(
+                r#"{{< image src="/images/photo.jpg" width="400" >}}"#,
+                &renderer(),
+            )
+            .unwrap();
+        assert_eq!(
+            processor.image_requests(),
+            [
+                ImageRequest {
+                    src: "/images/photo.jpg".to_string(),
+                    width: 400,
+                    height: None,
+                },
+                ImageRequest {
+                    src: "/images/photo.jpg".to_string(),
+                    width: 800,
+                    height: Some(600),
+                },
+            ]
+        );
+        assert!(
+            processor
+                .process(r#"{{< image src="/a.jpg" width="wide" >}}"#, &renderer())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_builtin_image_shortcode_format_policy() {
+        let mut processor = processor();
+        let mut config = crate::template_test::sample_site().unwrap().config;
+        config.images = Some(crate::images::ImageConfig {
+            formats: vec!["webp".to_string(), "jpg".to_string()],
+            format_policy: std::collections::BTreeMap::from([
+                (
+                    "png".to_string(),
+                    vec!["webp".to_string(), "png".to_string()],
+                ),
+                ("gif".to_string(), Vec::new()),
+            ]),
+            ..crate::images::ImageConfig::default()
+        });
+        processor.set_site_config(&config);
+
+        let result = processor
+            .process(r#"{{< image src="/shot.PNG" width="800" >}}"#, &renderer())
+            .unwrap();
+        assert!(result.contains(r#"<source type="image/webp" srcset="/shot-800w.webp">"#));
+        assert!(result.contains(r#"<img src="/shot-800w.png""#));
+
+        let result = processor
+            .process(
+                r#"{{< image src="/spinner.gif" width="64" >}}"#,
+                &renderer(),
+            )
+            .unwrap();
+        assert!(!result.contains("<picture>"));
+        assert!(result.contains(r#"<img src="/spinner.gif" alt="" width="64""#));
+        assert!(
+            !processor
+                .image_requests()
+                .iter()
+                .any(|request| request.src == "/spinner.gif")
+        );
+    }
+
+    #[test]
+    fn test_builtin_image_shortcode_art_direction() {
+        let mut processor = processor();
+        let mut config = crate::template_test::sample_site().unwrap().config;
+        config.images = Some(crate::images::ImageConfig {
+            formats: vec!["webp".to_string(), "jpg".to_string()],
+            art_direction: vec![crate::images::ArtDirection {
+                media: "(max-width: 640px)".to_string(),
+                width: 640,
+                height: 640,
+            }],
+            ..crate::images::ImageConfig::default()
+        });
+        processor.set_site_config(&config);
+
+        let result = processor
+            .process(
+                r#"{{< image src="/images/photo.jpg" width="1200" >}}"#,
+                &renderer(),
+            )
+            .unwrap();
+        assert!(result.contains(
+            r#"<source media="(max-width: 640px)" type="image/webp" srcset="/images/photo-640x640.webp">"#
+        ));
+        assert!(result.contains(
+            r#"<source media="(max-width: 640px)" type="image/jpeg" srcset="/images/photo-640x640.jpg">"#
+        ));
+        assert!(result.contains(r#"<source type="image/webp" srcset="/images/photo-1200w.webp">"#));
+
+        let result = processor
+            .process(
+                r#"{{< image src="/images/wide.jpg" width="1200" art="(max-width: 480px) 480x600; (max-width: 900px) 900x500" >}}"#,
+                &renderer(),
+            )
+            .unwrap();
+        let narrow = result
+            .find(r#"<source media="(max-width: 480px)" type="image/webp" srcset="/images/wide-480x600.webp">"#)
+            .unwrap();
+        let medium = result
+            .find(r#"<source media="(max-width: 900px)" type="image/webp" srcset="/images/wide-900x500.webp">"#)
+            .unwrap();
+        assert!(narrow < medium);
+        assert!(!result.contains("wide-640x640"));
+        assert!(processor.image_requests().contains(&ImageRequest {
+            src: "/images/wide.jpg".to_string(),
+            width: 900,
+            height: Some(500),
+        }));
+
+        assert!(
+            processor
+                .process(
+                    r#"{{< image src="/a.jpg" width="800" art="640x640" >}}"#,
+                    &renderer()
+                )
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_parse_shortcode_args_simple() {
         let (name, args) = parse_shortcode_args("youtube id=\"abc123\"").unwrap();
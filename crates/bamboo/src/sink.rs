@@ -0,0 +1,344 @@
+//! Where rendered output goes. Every generator (pages, feeds, sitemap,
+//! search index, redirects, and the rest) writes through a [`RenderSink`]
+//! instead of straight to disk, so the same build can land in a directory,
+//! an in-memory map ([`MemorySink`]), or a tar stream ([`TarSink`]).
+//!
+//! A `Path` is a sink that writes files under that directory. Steps that
+//! rewrite output in place (asset fingerprinting and minification, Sass,
+//! and responsive images) need a real directory and are skipped for other
+//! sinks, which receive static files as-is.
+//!
+//! ```no_run
+//! use bamboo_ssg::sink::MemorySink;
+//! use bamboo_ssg::{SiteBuilder, ThemeEngine};
+//!
+//! let site = SiteBuilder::new("./my-site").build()?;
+//! let sink = MemorySink::new();
+//! ThemeEngine::new("default")?.render_to_sink(&site, &sink)?;
+//! for (path, bytes) in sink.into_files() {
+//!     println!("{} ({} bytes)", path.display(), bytes.len());
+//! }
+//! # Ok::<_, bamboo_ssg::BambooError>(())
+//! ```
+
+use crate::error::{BambooError, IoContext, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Destination for rendered output. `path` is always relative to the site
+/// root, such as `posts/hello/index.html`. Sinks are shared across render
+/// threads.
+pub trait RenderSink: Sync {
+    /// Writes `contents` to `path`, replacing anything written there
+    /// before.
+    fn write_file(&self, path: &Path, contents: &[u8]) -> Result<()>;
+
+    /// Contents of `path`, if it was written and the sink can read it back.
+    fn read_file(&self, path: &Path) -> Option<Vec<u8>>;
+
+    /// Returns `true` if something was written to `path`.
+    fn file_exists(&self, path: &Path) -> bool;
+
+    /// Writes the file at `source` on disk to `path`.
+    fn copy_file(&self, source: &Path, path: &Path) -> Result<()> {
+        let contents = fs::read(source).io_context("read", source)?;
+        self.write_file(path, &contents)
+    }
+
+    /// The output directory, for sinks that write to disk. Steps that
+    /// post-process output files in place only run when this is set.
+    fn directory(&self) -> Option<&Path> {
+        None
+    }
+}
+
+impl RenderSink for Path {
+    fn write_file(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        let target = self.join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).io_context("create_dir_all", parent)?;
+        }
+        fs::write(&target, contents).io_context("write", &target)
+    }
+
+    fn read_file(&self, path: &Path) -> Option<Vec<u8>> {
+        fs::read(self.join(path)).ok()
+    }
+
+    fn file_exists(&self, path: &Path) -> bool {
+        self.join(path).exists()
+    }
+
+    fn copy_file(&self, source: &Path, path: &Path) -> Result<()> {
+        let target = self.join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).io_context("create_dir_all", parent)?;
+        }
+        fs::copy(source, &target).io_context("copy", &target)?;
+        Ok(())
+    }
+
+    fn directory(&self) -> Option<&Path> {
+        Some(self)
+    }
+}
+
+impl RenderSink for PathBuf {
+    fn write_file(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        self.as_path().write_file(path, contents)
+    }
+
+    fn read_file(&self, path: &Path) -> Option<Vec<u8>> {
+        self.as_path().read_file(path)
+    }
+
+    fn file_exists(&self, path: &Path) -> bool {
+        self.as_path().file_exists(path)
+    }
+
+    fn copy_file(&self, source: &Path, path: &Path) -> Result<()> {
+        self.as_path().copy_file(source, path)
+    }
+
+    fn directory(&self) -> Option<&Path> {
+        Some(self)
+    }
+}
+
+/// Keeps every rendered file in memory, keyed by its site-relative path.
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemorySink {
+    /// Creates an empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Paths written so far, sorted.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.lock().keys().cloned().collect()
+    }
+
+    /// Takes every file out of the sink.
+    pub fn into_files(self) -> BTreeMap<PathBuf, Vec<u8>> {
+        self.files
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<PathBuf, Vec<u8>>> {
+        self.files
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl RenderSink for MemorySink {
+    fn write_file(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        self.lock().insert(path.to_path_buf(), contents.to_vec());
+        Ok(())
+    }
+
+    fn read_file(&self, path: &Path) -> Option<Vec<u8>> {
+        self.lock().get(path).cloned()
+    }
+
+    fn file_exists(&self, path: &Path) -> bool {
+        self.lock().contains_key(path)
+    }
+}
+
+/// Streams every rendered file into a ustar archive as it is written.
+/// Files can't be read back, so a `[social_images] template` has to come
+/// from a directory or [`MemorySink`] build. Call [`TarSink::finish`] to
+/// end the archive.
+#[derive(Debug)]
+pub struct TarSink<W: Write + Send> {
+    state: Mutex<TarState<W>>,
+}
+
+#[derive(Debug)]
+struct TarState<W> {
+    writer: W,
+    written: HashSet<PathBuf>,
+}
+
+impl<W: Write + Send> TarSink<W> {
+    /// Starts an archive on `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            state: Mutex::new(TarState {
+                writer,
+                written: HashSet::new(),
+            }),
+        }
+    }
+
+    /// Writes the end-of-archive marker and returns the writer.
+    pub fn finish(self) -> Result<W> {
+        let mut state = self
+            .state
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.writer.write_all(&[0; 1024])?;
+        state.writer.flush()?;
+        Ok(state.writer)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TarState<W>> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<W: Write + Send> RenderSink for TarSink<W> {
+    fn write_file(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        let header = tar_header(path, contents.len())?;
+        let padding = (512 - contents.len() % 512) % 512;
+        let mut state = self.lock();
+        state.writer.write_all(&header)?;
+        state.writer.write_all(contents)?;
+        state.writer.write_all(&vec![0; padding])?;
+        state.written.insert(path.to_path_buf());
+        Ok(())
+    }
+
+    fn read_file(&self, _path: &Path) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn file_exists(&self, path: &Path) -> bool {
+        self.lock().written.contains(path)
+    }
+}
+
+/// A 512-byte ustar header for a regular file. Names longer than 100 bytes
+/// are split into the 155-byte prefix field at a `/`.
+fn tar_header(path: &Path, size: usize) -> Result<[u8; 512]> {
+    let invalid = || BambooError::InvalidPath {
+        path: path.to_path_buf(),
+    };
+    let name = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let (prefix, name) = if name.len() <= 100 {
+        ("", name.as_str())
+    } else {
+        let split = name
+            .char_indices()
+            .rev()
+            .filter(|&(index, character)| {
+                character == '/' && index <= 155 && name.len() - index - 1 <= 100
+            })
+            .map(|(index, _)| index)
+            .next()
+            .ok_or_else(invalid)?;
+        (&name[..split], &name[split + 1..])
+    };
+    if size as u64 >= 1 << 33 {
+        return Err(invalid());
+    }
+
+    let mut header = [0u8; 512];
+    let mut field = |offset: usize, value: &[u8]| {
+        header[offset..offset + value.len()].copy_from_slice(value);
+    };
+    field(0, name.as_bytes());
+    field(100, b"0000644\0");
+    field(108, b"0000000\0");
+    field(116, b"0000000\0");
+    field(124, format!("{size:011o}\0").as_bytes());
+    field(136, b"00000000000\0");
+    field(148, b"        ");
+    field(156, b"0");
+    field(257, b"ustar\0");
+    field(263, b"00");
+    field(345, prefix.as_bytes());
+
+    let checksum: u32 = header.iter().map(|&byte| u32::from(byte)).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directory_sink() {
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let sink = output_dir.path();
+        sink.write_file(Path::new("a/b/index.html"), b"hello")
+            .unwrap();
+
+        assert!(sink.file_exists(Path::new("a/b/index.html")));
+        assert_eq!(
+            fs::read_to_string(output_dir.path().join("a/b/index.html")).unwrap(),
+            "hello"
+        );
+        assert_eq!(sink.directory(), Some(output_dir.path()));
+    }
+
+    #[test]
+    fn test_memory_sink() {
+        let sink = MemorySink::new();
+        sink.write_file(Path::new("b.txt"), b"2").unwrap();
+        sink.write_file(Path::new("a.txt"), b"1").unwrap();
+        sink.write_file(Path::new("a.txt"), b"one").unwrap();
+
+        assert!(sink.file_exists(Path::new("a.txt")));
+        assert_eq!(sink.read_file(Path::new("a.txt")), Some(b"one".to_vec()));
+        assert_eq!(sink.directory(), None);
+        assert_eq!(
+            sink.paths(),
+            vec![PathBuf::from("a.txt"), PathBuf::from("b.txt")]
+        );
+    }
+
+    #[test]
+    fn test_tar_sink() {
+        let sink = TarSink::new(Vec::new());
+        sink.write_file(Path::new("posts/hello/index.html"), b"<p>hi</p>")
+            .unwrap();
+        let long = format!("{}/index.html", "nested/".repeat(20));
+        sink.write_file(Path::new(&long), b"deep").unwrap();
+        assert!(sink.file_exists(Path::new("posts/hello/index.html")));
+        let archive = sink.finish().unwrap();
+
+        assert_eq!(archive.len(), 512 * 2 + 512 * 2 + 1024);
+        assert_eq!(&archive[..22], b"posts/hello/index.html");
+        assert_eq!(&archive[257..263], b"ustar\0");
+        assert_eq!(&archive[512..521], b"<p>hi</p>");
+        let checksum: u32 = archive[..512]
+            .iter()
+            .enumerate()
+            .map(|(index, &byte)| {
+                if (148..156).contains(&index) {
+                    u32::from(b' ')
+                } else {
+                    u32::from(byte)
+                }
+            })
+            .sum();
+        assert_eq!(&archive[148..154], format!("{checksum:06o}").as_bytes());
+
+        let second = &archive[1024..1536];
+        assert_eq!(&second[..10], b"index.html");
+        assert!(second[345..].starts_with(b"nested/nested/"));
+    }
+
+    #[test]
+    fn test_tar_header_rejects_unsplittable_names() {
+        let name = "x".repeat(120);
+        assert!(tar_header(Path::new(&name), 0).is_err());
+    }
+}
@@ -3,16 +3,17 @@
 //! ready to hand to the [`theme`](crate::theme) renderer.
 
 use crate::error::{BambooError, IoContext, Result};
+use crate::extensions::TemplateExtensions;
 use crate::parsing::{
-    MarkdownRenderer, extract_excerpt, extract_frontmatter, parse_date_from_filename,
-    preprocess_math, reading_time, word_count,
+    MarkdownRenderer, RenderHooks, RenderedMarkdown, extract_excerpt, extract_frontmatter,
+    page_stats, parse_date_from_filename, parse_datetime, preprocess_math,
 };
-use crate::search::strip_html_tags;
-use crate::shortcodes::ShortcodeProcessor;
+use crate::shortcodes::{ShortcodePage, ShortcodeProcessor};
 use crate::types::{
-    Asset, Collection, CollectionItem, Content, Page, Post, Site, SiteConfig, TaxonomyDefinition,
+    Asset, Collection, CollectionItem, Content, Note, Page, Post, Site, SiteConfig,
+    TaxonomyDefinition, Translation,
 };
-use chrono::{NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono::{DateTime, Utc};
 use rayon::prelude::*;
 use serde_json::Value;
 use std::collections::HashMap;
@@ -29,6 +30,7 @@ struct ContentInput {
     rendered: crate::parsing::RenderedMarkdown,
     frontmatter: crate::types::Frontmatter,
     output_path: PathBuf,
+    source_path: PathBuf,
     url: String,
 }
 
@@ -50,11 +52,17 @@ struct ContentInput {
 pub struct SiteBuilder {
     input_dir: PathBuf,
     include_drafts: bool,
+    include_future: bool,
+    environment: Option<String>,
+    drafts_enabled: bool,
+    future_enabled: bool,
     base_url_override: Option<String>,
     shortcode_processor: Option<ShortcodeProcessor>,
     renderer: Option<MarkdownRenderer>,
     math_enabled: bool,
     theme_templates_dir: Option<PathBuf>,
+    extensions: TemplateExtensions,
+    commit_dates: Option<HashMap<PathBuf, DateTime<Utc>>>,
 }
 
 impl SiteBuilder {
@@ -69,21 +77,45 @@ impl SiteBuilder {
         Self {
             input_dir: input_dir.as_ref().to_path_buf(),
             include_drafts: false,
+            include_future: false,
+            environment: None,
+            drafts_enabled: false,
+            future_enabled: false,
             base_url_override: None,
             shortcode_processor: None,
             renderer: None,
             math_enabled: false,
             theme_templates_dir: None,
+            extensions: TemplateExtensions::default(),
+            commit_dates: None,
         }
     }
 
     /// If `true`, content marked `draft = true` is kept in the build output.
-    /// Defaults to `false`.
+    /// Defaults to `false`, in which case `drafts` in `bamboo.toml` (or the
+    /// active environment) decides.
     pub fn include_drafts(mut self, include: bool) -> Self {
         self.include_drafts = include;
         self
     }
 
+    /// If `true`, posts and notes dated in the future are kept in the build
+    /// output. Defaults to `false`, in which case `future` in `bamboo.toml`
+    /// (or the active environment) decides.
+    pub fn include_future(mut self, include: bool) -> Self {
+        self.include_future = include;
+        self
+    }
+
+    /// Selects the `[environments.<name>]` table of `bamboo.toml` to merge
+    /// over the top-level config, e.g. `"dev"` or `"production"`. Without
+    /// one, or when the table doesn't exist, only the top-level config is
+    /// used.
+    pub fn environment(mut self, name: impl Into<String>) -> Self {
+        self.environment = Some(name.into());
+        self
+    }
+
     /// Overrides `bamboo.toml`'s `base_url`. Useful for building the same
     /// site at multiple deployment URLs (e.g. preview vs production).
     pub fn base_url(mut self, url: impl Into<String>) -> Self {
@@ -108,17 +140,38 @@ impl SiteBuilder {
         }
     }
 
+    /// Makes consumer-supplied Tera filters, functions, and testers
+    /// available to shortcode and render-hook templates. Register the same
+    /// set on the [`crate::ThemeEngine`] for page templates.
+    pub fn template_extensions(mut self, extensions: TemplateExtensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
     /// Loads the site and returns a fully-populated [`Site`]. Consumes no
     /// fields so the same builder can be reused for incremental rebuilds.
     pub fn build(&mut self) -> Result<Site> {
-        let mut config = self.load_config()?;
+        let mut config = self.config()?;
 
         if let Some(ref url) = self.base_url_override {
             config.base_url = url.trim_end_matches('/').to_string();
         }
 
-        self.renderer = Some(MarkdownRenderer::with_theme(&config.syntax_theme)?);
+        let mut renderer = MarkdownRenderer::with_theme(&config.syntax_theme)?;
+        let mut hook_dirs = Vec::new();
+        if let Some(ref theme_templates) = self.theme_templates_dir {
+            hook_dirs.push(theme_templates.clone());
+        }
+        hook_dirs.push(self.input_dir.join("templates"));
+        let mut hooks = RenderHooks::from_template_dirs(&hook_dirs)?;
+        if let Some(ref mut hooks) = hooks {
+            hooks.register_extensions(&self.extensions);
+        }
+        renderer.set_render_hooks(hooks);
+        self.renderer = Some(renderer);
         self.math_enabled = config.math;
+        self.drafts_enabled = self.include_drafts || config.drafts;
+        self.future_enabled = self.include_future || config.future;
 
         if self.shortcode_processor.is_none() {
             let mut dirs = Vec::new();
@@ -131,23 +184,38 @@ impl SiteBuilder {
 
         let ref_registry = self.build_ref_registry()?;
         if let Some(ref mut processor) = self.shortcode_processor {
+            processor.register_extensions(&self.extensions);
             processor.register_builtin_default_partials()?;
             if let Some(ref theme_templates) = self.theme_templates_dir {
+                processor.register_macros_from_directory(theme_templates)?;
                 processor.register_partials_from_directory(theme_templates)?;
             }
             let site_templates = self.input_dir.join("templates");
             if site_templates.is_dir() {
+                processor.register_macros_from_directory(&site_templates)?;
                 processor.register_partials_from_directory(&site_templates)?;
             }
             processor.set_ref_registry(ref_registry);
             processor.set_base_url(&config.base_url);
+            processor.set_site_config(&config);
         }
 
-        let (home, mut pages) = self.load_pages()?;
-        let posts = self.load_posts(&config.taxonomies)?;
+        // Only the sitemap's lastmod needs commit dates, and a builder reused
+        // by `bamboo serve` reads them once rather than on every rebuild.
+        if config.generate.sitemap && self.commit_dates.is_none() {
+            self.commit_dates = Some(commit_dates(&self.input_dir));
+        }
+        let (mut home, mut pages) = self.load_pages()?;
+        let mut posts = self.load_posts(&config.taxonomies)?;
+        let mut notes = self.load_notes()?;
         let mut collections = self.load_collections()?;
         let data = self.load_data()?;
-        let assets = self.collect_assets()?;
+        let assets = self.collect_assets(&config.static_ignore)?;
+
+        for post in &mut posts {
+            post.discussion =
+                crate::discussions::discussion_for(&config.discussions, &config.base_url, post);
+        }
 
         pages.sort_by(|a, b| {
             a.content
@@ -156,6 +224,21 @@ impl SiteBuilder {
                 .then_with(|| a.content.slug.cmp(&b.content.slug))
         });
 
+        link_translations(
+            home.iter_mut()
+                .chain(pages.iter_mut())
+                .map(|page| &mut page.content)
+                .chain(posts.iter_mut().map(|post| &mut post.content))
+                .chain(notes.iter_mut().map(|note| &mut note.content))
+                .chain(
+                    collections
+                        .values_mut()
+                        .flat_map(|collection| collection.items.iter_mut())
+                        .map(|item| &mut item.content),
+                ),
+            config.language.as_deref(),
+        );
+
         for collection in collections.values_mut() {
             collection.items.sort_by(|a, b| {
                 a.content
@@ -170,13 +253,27 @@ impl SiteBuilder {
             home,
             pages,
             posts,
+            notes,
             collections,
             data,
             assets,
+            image_requests: self
+                .shortcode_processor
+                .as_ref()
+                .map(ShortcodeProcessor::image_requests)
+                .unwrap_or_default(),
         })
     }
 
-    fn load_config(&self) -> Result<SiteConfig> {
+    /// Whether dated content should be built: drafts only when drafts are
+    /// enabled, and future-dated content only when future content is.
+    fn is_published(&self, draft: bool, date: chrono::DateTime<Utc>) -> bool {
+        (!draft || self.drafts_enabled) && (self.future_enabled || date <= Utc::now())
+    }
+
+    /// Reads `bamboo.toml` with the selected environment merged in, without
+    /// loading any content.
+    pub fn config(&self) -> Result<SiteConfig> {
         let config_path = self.input_dir.join("bamboo.toml");
 
         if !config_path.exists() {
@@ -185,11 +282,30 @@ impl SiteBuilder {
 
         let content =
             fs::read_to_string(&config_path).io_context("reading config", &config_path)?;
-        let mut config: SiteConfig =
-            toml::from_str(&content).map_err(|error| BambooError::TomlParse {
-                path: config_path.clone(),
-                message: error.to_string(),
-            })?;
+        let parse_error = |message: String| BambooError::TomlParse {
+            path: config_path.clone(),
+            message,
+        };
+        let mut table: toml::Table =
+            toml::from_str(&content).map_err(|error| parse_error(error.to_string()))?;
+
+        let environments = table.remove("environments");
+        if let Some(name) = &self.environment
+            && let Some(overlay) = environments
+                .as_ref()
+                .and_then(|environments| environments.get(name))
+        {
+            let Some(overlay) = overlay.as_table() else {
+                return Err(parse_error(format!(
+                    "[environments.{name}] must be a table"
+                )));
+            };
+            merge_toml(&mut table, overlay.clone());
+        }
+
+        let mut config: SiteConfig = toml::Value::Table(table)
+            .try_into()
+            .map_err(|error: toml::de::Error| parse_error(error.to_string()))?;
 
         config.base_url = config.base_url.trim_end_matches('/').to_string();
 
@@ -252,7 +368,7 @@ impl SiteBuilder {
         let mut seen_slugs: HashMap<String, PathBuf> = HashMap::new();
 
         for (page, path, relative) in parsed_pages {
-            if page.draft && !self.include_drafts {
+            if page.draft && !self.drafts_enabled {
                 continue;
             }
 
@@ -280,7 +396,7 @@ impl SiteBuilder {
     }
 
     fn find_reserved_dirs(&self, content_dir: &Path) -> Result<Vec<PathBuf>> {
-        let mut reserved = vec![content_dir.join("posts")];
+        let mut reserved = vec![content_dir.join("posts"), content_dir.join("notes")];
 
         for entry in WalkDir::new(content_dir)
             .min_depth(1)
@@ -300,21 +416,37 @@ impl SiteBuilder {
         Ok(reserved)
     }
 
-    fn process_shortcodes(&self, content: &str) -> Result<String> {
+    fn process_shortcodes(&self, content: &str, page: &ShortcodePage<'_>) -> Result<String> {
         if let Some(ref processor) = self.shortcode_processor
             && let Some(ref renderer) = self.renderer
         {
-            processor.process(content, renderer)
+            processor.process_for_page(content, renderer, page)
         } else {
             Ok(content.to_string())
         }
     }
 
+    /// Expands shortcodes (with `page` as their page context), applies math
+    /// preprocessing, and renders the result as markdown.
+    fn render_content(
+        &self,
+        raw_content: &str,
+        page: &ShortcodePage<'_>,
+    ) -> Result<RenderedMarkdown> {
+        let processed_content = self.process_shortcodes(raw_content, page)?;
+        let math_processed = if self.should_enable_math(page.frontmatter) {
+            preprocess_math(&processed_content)
+        } else {
+            processed_content
+        };
+        self.render_markdown(&math_processed)
+    }
+
     fn should_enable_math(&self, frontmatter: &crate::types::Frontmatter) -> bool {
         self.math_enabled || frontmatter.get_bool("math").unwrap_or(false)
     }
 
-    fn render_markdown(&self, content: &str) -> crate::parsing::RenderedMarkdown {
+    fn render_markdown(&self, content: &str) -> Result<RenderedMarkdown> {
         self.renderer
             .as_ref()
             .expect("renderer must be initialized before rendering markdown")
@@ -495,11 +627,34 @@ impl SiteBuilder {
         }
     }
 
+    /// `path` relative to the content directory, e.g. `posts/hello.md`.
+    fn source_path(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(self.input_dir.join("content"))
+            .unwrap_or(path)
+            .to_path_buf()
+    }
+
+    /// Last change to the content file at `source_path`: its latest commit,
+    /// or its modification time when git doesn't track it or no sitemap is
+    /// generated.
+    fn modified(&self, source_path: &Path) -> Option<DateTime<Utc>> {
+        let committed = self
+            .commit_dates
+            .as_ref()
+            .and_then(|dates| dates.get(source_path));
+        committed.copied().or_else(|| {
+            let path = self.input_dir.join("content").join(source_path);
+            let modified = fs::metadata(path).ok()?.modified().ok()?;
+            Some(DateTime::<Utc>::from(modified))
+        })
+    }
+
     fn build_content(&self, input: ContentInput) -> Content {
-        let plain_text = strip_html_tags(&input.rendered.html);
-        let words = word_count(&plain_text);
+        let stats = page_stats(&input.rendered.html, &input.rendered.toc);
         let template = input.frontmatter.get_string("template");
         let weight = input.frontmatter.get_i64("weight").unwrap_or(0) as i32;
+        let lang = input.frontmatter.get_string("lang");
+        let modified = self.modified(&input.source_path);
         Content {
             slug: input.slug,
             title: input.title,
@@ -507,11 +662,16 @@ impl SiteBuilder {
             raw_content: input.raw_content,
             frontmatter: input.frontmatter,
             path: input.output_path,
+            source_path: input.source_path,
+            modified,
             template,
             weight,
-            word_count: words,
-            reading_time: reading_time(words),
+            word_count: stats.word_count,
+            reading_time: stats.reading_time,
             toc: input.rendered.toc,
+            stats,
+            lang,
+            translations: Vec::new(),
             url: input.url,
         }
     }
@@ -519,14 +679,6 @@ impl SiteBuilder {
     fn parse_page(&self, path: &Path, relative: &Path) -> Result<Page> {
         let file_content = fs::read_to_string(path).io_context("reading page", path)?;
         let (frontmatter, raw_content) = extract_frontmatter(&file_content, path)?;
-        let processed_content = self.process_shortcodes(&raw_content)?;
-        let math_processed = if self.should_enable_math(&frontmatter) {
-            preprocess_math(&processed_content)
-        } else {
-            processed_content
-        };
-        let rendered = self.render_markdown(&math_processed);
-
         let filename = path.file_name().unwrap().to_string_lossy();
 
         let relative_dir = relative.parent().unwrap_or(Path::new(""));
@@ -572,6 +724,16 @@ impl SiteBuilder {
 
         Self::apply_permalink(&frontmatter, &mut url, &mut output_path);
 
+        let rendered = self.render_content(
+            &raw_content,
+            &ShortcodePage {
+                title: &title,
+                slug: &slug,
+                url: &url,
+                frontmatter: &frontmatter,
+            },
+        )?;
+
         let content = self.build_content(ContentInput {
             slug,
             title,
@@ -579,6 +741,7 @@ impl SiteBuilder {
             rendered,
             frontmatter,
             output_path,
+            source_path: self.source_path(path),
             url,
         });
 
@@ -629,7 +792,7 @@ impl SiteBuilder {
             .map(|path| self.parse_post(path, taxonomy_definitions))
             .collect::<Result<Vec<_>>>()?
             .into_iter()
-            .filter(|post| !post.draft || self.include_drafts)
+            .filter(|post| self.is_published(post.draft, post.date))
             .collect();
 
         posts.sort_by_key(|post| std::cmp::Reverse(post.date));
@@ -644,39 +807,26 @@ impl SiteBuilder {
     ) -> Result<Post> {
         let file_content = fs::read_to_string(path).io_context("reading post", path)?;
         let (frontmatter, raw_content) = extract_frontmatter(&file_content, path)?;
-        let processed_content = self.process_shortcodes(&raw_content)?;
-        let math_processed = if self.should_enable_math(&frontmatter) {
-            preprocess_math(&processed_content)
-        } else {
-            processed_content
-        };
-        let rendered = self.render_markdown(&math_processed);
-
         let filename = path.file_name().unwrap().to_string_lossy();
 
-        let (date_str, slug) = if let Some((date, slug)) = parse_date_from_filename(&filename) {
-            (Some(date), slug)
+        let (date, slug) = if let Some((date, slug)) = parse_date_from_filename(&filename) {
+            (parse_datetime(&date), slug)
         } else {
             let slug = filename
                 .strip_suffix(".md")
                 .unwrap_or(&filename)
                 .to_string();
-            (frontmatter.get_string("date"), slug)
-        };
-
-        let date = if let Some(date_str) = date_str {
-            let naive = NaiveDate::parse_from_str(&date_str, "%Y-%m-%d").map_err(|_| {
-                BambooError::InvalidDate {
+            if !frontmatter.raw.contains_key("date") {
+                return Err(BambooError::MissingField {
+                    field: "date".to_string(),
                     path: path.to_path_buf(),
-                }
-            })?;
-            Utc.from_utc_datetime(&naive.and_time(NaiveTime::MIN))
-        } else {
-            return Err(BambooError::MissingField {
-                field: "date".to_string(),
-                path: path.to_path_buf(),
-            });
+                });
+            }
+            (frontmatter.get_date("date"), slug)
         };
+        let date = date.ok_or_else(|| BambooError::InvalidDate {
+            path: path.to_path_buf(),
+        })?;
 
         let title = frontmatter
             .get_string("title")
@@ -706,6 +856,16 @@ impl SiteBuilder {
 
         Self::apply_permalink(&frontmatter, &mut url, &mut output_path);
 
+        let rendered = self.render_content(
+            &raw_content,
+            &ShortcodePage {
+                title: &title,
+                slug: &slug,
+                url: &url,
+                frontmatter: &frontmatter,
+            },
+        )?;
+
         let content = self.build_content(ContentInput {
             slug,
             title,
@@ -713,6 +873,7 @@ impl SiteBuilder {
             rendered,
             frontmatter,
             output_path,
+            source_path: self.source_path(path),
             url,
         });
 
@@ -725,6 +886,124 @@ impl SiteBuilder {
             categories,
             taxonomies_map,
             redirect_from,
+            discussion: None,
+            link_preview: None,
+        })
+    }
+
+    fn load_notes(&self) -> Result<Vec<Note>> {
+        let notes_dir = self.input_dir.join("content").join("notes");
+
+        if !notes_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut sources = Vec::new();
+        for entry in WalkDir::new(&notes_dir)
+            .min_depth(1)
+            .max_depth(1)
+            .sort_by_file_name()
+        {
+            let entry = entry.map_err(|error| BambooError::WalkDir {
+                path: notes_dir.clone(),
+                message: error.to_string(),
+            })?;
+            let path = entry.path();
+            let filename = path.file_name().unwrap().to_string_lossy();
+            if !path.is_file()
+                || path.extension().is_none_or(|extension| extension != "md")
+                || filename.starts_with('_')
+            {
+                continue;
+            }
+            let file_content = fs::read_to_string(path).io_context("reading note", path)?;
+            let (frontmatter, raw_content) = extract_frontmatter(&file_content, path)?;
+            let stem = filename.strip_suffix(".md").unwrap_or(&filename);
+            let date = frontmatter
+                .get_date("date")
+                .or_else(|| parse_datetime(stem))
+                .ok_or_else(|| BambooError::MissingField {
+                    field: "date".to_string(),
+                    path: path.to_path_buf(),
+                })?;
+            sources.push((path.to_path_buf(), frontmatter, raw_content, date));
+        }
+
+        // Notes sharing a timestamp get `-2`, `-3`, ... in filename order.
+        let mut slug_counts: HashMap<String, usize> = HashMap::new();
+        let sources: Vec<_> = sources
+            .into_iter()
+            .map(|(path, frontmatter, raw_content, date)| {
+                let base_slug = date.format("%Y-%m-%d-%H%M%S").to_string();
+                let count = slug_counts.entry(base_slug.clone()).or_default();
+                *count += 1;
+                let slug = if *count == 1 {
+                    base_slug
+                } else {
+                    format!("{base_slug}-{count}")
+                };
+                (path, frontmatter, raw_content, date, slug)
+            })
+            .collect();
+
+        let mut notes: Vec<Note> = sources
+            .into_par_iter()
+            .map(|(path, frontmatter, raw_content, date, slug)| {
+                self.parse_note(&path, frontmatter, raw_content, date, slug)
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .filter(|note| self.is_published(note.draft, note.date))
+            .collect();
+
+        notes.sort_by_key(|note| std::cmp::Reverse(note.date));
+
+        Ok(notes)
+    }
+
+    fn parse_note(
+        &self,
+        path: &Path,
+        frontmatter: crate::types::Frontmatter,
+        raw_content: String,
+        date: chrono::DateTime<Utc>,
+        slug: String,
+    ) -> Result<Note> {
+        let draft = frontmatter.get_bool("draft").unwrap_or(false);
+        let tags = frontmatter.get_array("tags").unwrap_or_default();
+
+        let mut output_path = PathBuf::from("notes").join(&slug).join("index.html");
+        let mut url = format!("/notes/{}/", slug);
+        Self::apply_permalink(&frontmatter, &mut url, &mut output_path);
+
+        let explicit_title = frontmatter.get_string("title");
+        let rendered = self.render_content(
+            &raw_content,
+            &ShortcodePage {
+                title: explicit_title.as_deref().unwrap_or(""),
+                slug: &slug,
+                url: &url,
+                frontmatter: &frontmatter,
+            },
+        )?;
+        let title = explicit_title.unwrap_or_else(|| note_title(&rendered.html));
+
+        let content = self.build_content(ContentInput {
+            slug,
+            title,
+            raw_content,
+            rendered,
+            frontmatter,
+            output_path,
+            source_path: self.source_path(path),
+            url,
+        });
+
+        Ok(Note {
+            content,
+            date,
+            draft,
+            tags,
         })
     }
 
@@ -754,7 +1033,7 @@ impl SiteBuilder {
 
             let dir_name = path.file_name().unwrap().to_string_lossy();
 
-            if dir_name == "posts" {
+            if dir_name == "posts" || dir_name == "notes" {
                 continue;
             }
 
@@ -801,12 +1080,60 @@ impl SiteBuilder {
             .map(|(path, relative)| self.parse_collection_item(path, name, relative))
             .collect::<Result<Vec<_>>>()?;
 
+        let config_path = dir.join("_collection.toml");
+        let content = fs::read_to_string(&config_path)
+            .io_context("reading collection config", &config_path)?;
+        let config = toml::from_str(&content).map_err(|error| BambooError::TomlParse {
+            path: config_path.clone(),
+            message: error.to_string(),
+        })?;
+
+        let index_path = dir.join("_index.md");
+        let index = if index_path.is_file() {
+            Some(self.parse_collection_index(&index_path, name)?)
+        } else {
+            None
+        };
+
         Ok(Collection {
             name: name.to_string(),
             items,
+            config,
+            index,
         })
     }
 
+    fn parse_collection_index(&self, path: &Path, collection_name: &str) -> Result<Content> {
+        let file_content = fs::read_to_string(path).io_context("reading collection index", path)?;
+        let (frontmatter, raw_content) = extract_frontmatter(&file_content, path)?;
+        let slug = collection_name.to_string();
+        let title = frontmatter
+            .get_string("title")
+            .unwrap_or_else(|| slug.clone());
+        let url = format!("/{}/", collection_name);
+
+        let rendered = self.render_content(
+            &raw_content,
+            &ShortcodePage {
+                title: &title,
+                slug: &slug,
+                url: &url,
+                frontmatter: &frontmatter,
+            },
+        )?;
+
+        Ok(self.build_content(ContentInput {
+            output_path: PathBuf::from(collection_name).join("index.html"),
+            slug,
+            title,
+            raw_content,
+            rendered,
+            frontmatter,
+            source_path: self.source_path(path),
+            url,
+        }))
+    }
+
     fn parse_collection_item(
         &self,
         path: &Path,
@@ -815,14 +1142,6 @@ impl SiteBuilder {
     ) -> Result<CollectionItem> {
         let file_content = fs::read_to_string(path).io_context("reading collection item", path)?;
         let (frontmatter, raw_content) = extract_frontmatter(&file_content, path)?;
-        let processed_content = self.process_shortcodes(&raw_content)?;
-        let math_processed = if self.should_enable_math(&frontmatter) {
-            preprocess_math(&processed_content)
-        } else {
-            processed_content
-        };
-        let rendered = self.render_markdown(&math_processed);
-
         let filename = path.file_name().unwrap().to_string_lossy();
         let file_slug = filename
             .strip_suffix(".md")
@@ -849,6 +1168,16 @@ impl SiteBuilder {
 
         Self::apply_permalink(&frontmatter, &mut url, &mut output_path);
 
+        let rendered = self.render_content(
+            &raw_content,
+            &ShortcodePage {
+                title: &title,
+                slug: &slug,
+                url: &url,
+                frontmatter: &frontmatter,
+            },
+        )?;
+
         let content = self.build_content(ContentInput {
             slug,
             title,
@@ -856,6 +1185,7 @@ impl SiteBuilder {
             rendered,
             frontmatter,
             output_path,
+            source_path: self.source_path(path),
             url,
         });
 
@@ -927,13 +1257,14 @@ impl SiteBuilder {
         Ok(data)
     }
 
-    fn collect_assets(&self) -> Result<Vec<Asset>> {
+    fn collect_assets(&self, static_ignore: &[String]) -> Result<Vec<Asset>> {
         let static_dir = self.input_dir.join("static");
         let mut assets = Vec::new();
 
         if !static_dir.exists() {
             return Ok(assets);
         }
+        let ignored = crate::assets::exclude_globs(static_ignore)?;
 
         for entry in WalkDir::new(&static_dir).min_depth(1).into_iter() {
             let entry = entry.map_err(|error| BambooError::WalkDir {
@@ -943,7 +1274,7 @@ impl SiteBuilder {
 
             let path = entry.path();
 
-            if !path.is_file() {
+            if !path.is_file() || crate::assets::is_excluded(&ignored, &static_dir, path) {
                 continue;
             }
 
@@ -959,6 +1290,100 @@ impl SiteBuilder {
     }
 }
 
+/// Number of words of body text used as the title of an untitled note.
+const NOTE_TITLE_WORDS: usize = 10;
+
+/// Fallback title for a note without a `title` field: its first few words
+/// of plain text.
+fn note_title(html: &str) -> String {
+    let text = crate::search::strip_html_tags(html);
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() <= NOTE_TITLE_WORDS {
+        words.join(" ")
+    } else {
+        format!("{}…", words[..NOTE_TITLE_WORDS].join(" "))
+    }
+}
+
+/// Date of the latest commit touching each file under `input_dir/content`,
+/// keyed by path relative to `content/`. Empty when `input_dir` isn't in a
+/// git repository or git isn't installed.
+fn commit_dates(input_dir: &Path) -> HashMap<PathBuf, DateTime<Utc>> {
+    let mut dates = HashMap::new();
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(input_dir)
+        .args([
+            "-c",
+            "core.quotepath=off",
+            "log",
+            "--format=%x00%cI",
+            "--name-only",
+            "--relative",
+            "--",
+            "content",
+        ])
+        .stderr(std::process::Stdio::null())
+        .output();
+    let Ok(output) = output else {
+        return dates;
+    };
+    if !output.status.success() {
+        return dates;
+    }
+
+    let mut current = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(date) = line.strip_prefix('\0') {
+            current = DateTime::parse_from_rfc3339(date)
+                .ok()
+                .map(|date| date.with_timezone(&Utc));
+        } else if let (Some(date), Some(path)) = (current, line.strip_prefix("content/")) {
+            dates.entry(PathBuf::from(path)).or_insert(date);
+        }
+    }
+    dates
+}
+
+/// Fills in each item's effective language and links items sharing a
+/// `translation_key` frontmatter value to one another.
+fn link_translations<'a>(
+    contents: impl Iterator<Item = &'a mut Content>,
+    default_lang: Option<&str>,
+) {
+    let mut groups: HashMap<String, Vec<&'a mut Content>> = HashMap::new();
+    for content in contents {
+        if content.lang.is_none() {
+            content.lang = default_lang.map(str::to_string);
+        }
+        if let Some(key) = content.frontmatter.get_string("translation_key") {
+            groups.entry(key).or_default().push(content);
+        }
+    }
+
+    for members in groups.values_mut() {
+        let links: Vec<Translation> = members
+            .iter()
+            .filter_map(|content| {
+                Some(Translation {
+                    lang: content.lang.clone()?,
+                    title: content.title.clone(),
+                    url: content.url.clone(),
+                })
+            })
+            .collect();
+        for content in members.iter_mut() {
+            let mut translations: Vec<Translation> = links
+                .iter()
+                .filter(|link| link.url != content.url)
+                .cloned()
+                .collect();
+            translations.sort_by(|a, b| a.lang.cmp(&b.lang).then_with(|| a.url.cmp(&b.url)));
+            content.translations = translations;
+        }
+    }
+}
+
 fn build_data_key(path: &Path) -> Vec<String> {
     let mut parts: Vec<String> = path
         .parent()
@@ -1054,9 +1479,26 @@ fn insert_nested_value<M: NestedInsert>(container: &mut M, keys: &[String], valu
     }
 }
 
+/// Merges `overlay` into `base`: tables merge key by key, anything else in
+/// `overlay` replaces the value in `base`.
+fn merge_toml(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_toml(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::{CollectionConfig, CollectionFeedOrder};
+    use chrono::TimeZone;
     use std::fs;
     use tempfile::TempDir;
 
@@ -1248,6 +1690,77 @@ url = "/"
         assert_eq!(site.posts.len(), 2);
     }
 
+    #[test]
+    fn test_future_posts_excluded_by_default() {
+        let dir = create_test_site();
+        fs::write(
+            dir.path().join("content/posts/2999-01-01-scheduled.md"),
+            "+++\ntitle = \"Scheduled\"\n+++\n\nNot yet",
+        )
+        .unwrap();
+
+        let site = SiteBuilder::new(dir.path()).build().unwrap();
+        assert_eq!(site.posts.len(), 1);
+
+        let site = SiteBuilder::new(dir.path())
+            .include_future(true)
+            .build()
+            .unwrap();
+        assert_eq!(site.posts.len(), 2);
+    }
+
+    #[test]
+    fn test_environment_overlay() {
+        let dir = create_test_site();
+        fs::write(
+            dir.path().join("bamboo.toml"),
+            r#"
+title = "Test Site"
+base_url = "https://example.com"
+
+[extra]
+analytics = true
+theme_color = "green"
+
+[environments.dev]
+base_url = "http://localhost:3000"
+drafts = true
+future = true
+
+[environments.dev.extra]
+analytics = false
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/posts/2024-02-01-draft.md"),
+            "+++\ntitle = \"Draft\"\ndraft = true\n+++\n\nDraft post",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/posts/2999-01-01-scheduled.md"),
+            "+++\ntitle = \"Scheduled\"\n+++\n\nNot yet",
+        )
+        .unwrap();
+
+        let site = SiteBuilder::new(dir.path())
+            .environment("dev")
+            .build()
+            .unwrap();
+        assert_eq!(site.posts.len(), 3);
+        assert_eq!(site.config.base_url, "http://localhost:3000");
+        assert_eq!(site.config.extra["analytics"], false);
+        assert_eq!(site.config.extra["theme_color"], "green");
+
+        let site = SiteBuilder::new(dir.path())
+            .environment("production")
+            .build()
+            .unwrap();
+        assert_eq!(site.posts.len(), 1);
+        assert_eq!(site.config.base_url, "https://example.com");
+        assert_eq!(site.config.extra["analytics"], true);
+    }
+
     #[test]
     fn test_collections() {
         let dir = create_test_site();
@@ -1274,6 +1787,105 @@ url = "/"
         assert!(site.collections.contains_key("docs"));
         let docs = &site.collections["docs"];
         assert_eq!(docs.items.len(), 2);
+        assert_eq!(docs.config, CollectionConfig::default());
+        assert!(docs.index.is_none());
+
+        fs::write(
+            dir.path().join("content/docs/_index.md"),
+            "+++\ntitle = \"Documentation\"\ndescription = \"Guides\"\n+++\n\nStart here",
+        )
+        .unwrap();
+        let site = SiteBuilder::new(dir.path()).build().unwrap();
+        let docs = &site.collections["docs"];
+        assert_eq!(docs.items.len(), 2);
+        let index = docs.index.as_ref().unwrap();
+        assert_eq!(index.title, "Documentation");
+        assert_eq!(index.url, "/docs/");
+        assert_eq!(index.path, PathBuf::from("docs/index.html"));
+        assert!(index.html.contains("Start here"));
+    }
+
+    #[test]
+    fn test_content_modified_from_git_or_mtime() {
+        let dir = create_test_site();
+        let site = SiteBuilder::new(dir.path()).build().unwrap();
+        assert!(site.home.unwrap().content.modified.is_some());
+
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .env("GIT_AUTHOR_DATE", "2023-02-03T04:05:06Z")
+                .env("GIT_COMMITTER_DATE", "2023-02-03T04:05:06Z")
+                .output()
+                .is_ok_and(|output| output.status.success())
+        };
+        let commit_content = || {
+            git(&["init", "-q"])
+                && git(&["add", "content"])
+                && git(&[
+                    "-c",
+                    "user.name=Test",
+                    "-c",
+                    "user.email=test@example.com",
+                    "commit",
+                    "-qm",
+                    "Add content",
+                ])
+        };
+        if !commit_content() {
+            return;
+        }
+
+        let committed = Some(Utc.with_ymd_and_hms(2023, 2, 3, 4, 5, 6).unwrap());
+        let mut builder = SiteBuilder::new(dir.path());
+        let site = builder.build().unwrap();
+        assert_eq!(site.home.unwrap().content.modified, committed);
+
+        // A reused builder keeps the dates it read instead of asking git again.
+        fs::remove_dir_all(dir.path().join(".git")).unwrap();
+        let site = builder.build().unwrap();
+        assert_eq!(site.home.unwrap().content.modified, committed);
+
+        // Without a sitemap, git isn't consulted at all.
+        assert!(commit_content());
+        let config = fs::read_to_string(dir.path().join("bamboo.toml")).unwrap();
+        fs::write(
+            dir.path().join("bamboo.toml"),
+            format!("{config}\n[generate]\nsitemap = false\n"),
+        )
+        .unwrap();
+        let site = SiteBuilder::new(dir.path()).build().unwrap();
+        let modified = site.home.unwrap().content.modified;
+        assert!(modified.is_some());
+        assert_ne!(modified, committed);
+    }
+
+    #[test]
+    fn test_collection_config() {
+        let dir = create_test_site();
+        fs::create_dir_all(dir.path().join("content/changelog")).unwrap();
+        fs::write(
+            dir.path().join("content/changelog/_collection.toml"),
+            "feed = true\nfeed_order = \"weight\"",
+        )
+        .unwrap();
+
+        let site = SiteBuilder::new(dir.path()).build().unwrap();
+        let config = &site.collections["changelog"].config;
+        assert!(config.feed);
+        assert_eq!(config.feed_order, CollectionFeedOrder::Weight);
+
+        fs::write(
+            dir.path().join("content/changelog/_collection.toml"),
+            "feed = \"yes\"",
+        )
+        .unwrap();
+        assert!(matches!(
+            SiteBuilder::new(dir.path()).build(),
+            Err(BambooError::TomlParse { .. })
+        ));
     }
 
     #[test]
@@ -1391,6 +2003,33 @@ url = "/"
         assert_eq!(site.assets.len(), 2);
     }
 
+    #[test]
+    fn test_static_ignore_skips_matching_assets() {
+        let dir = create_test_site();
+        let config = fs::read_to_string(dir.path().join("bamboo.toml")).unwrap();
+        fs::write(
+            dir.path().join("bamboo.toml"),
+            format!("{config}static_ignore = [\"**/*.psd\", \"**/.DS_Store\", \"drafts/**\"]\n"),
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("static/images")).unwrap();
+        fs::create_dir_all(dir.path().join("static/drafts/old")).unwrap();
+        fs::write(dir.path().join("static/images/logo.png"), "png").unwrap();
+        fs::write(dir.path().join("static/images/logo.psd"), "psd").unwrap();
+        fs::write(dir.path().join("static/.DS_Store"), "junk").unwrap();
+        fs::write(dir.path().join("static/drafts/old/notes.txt"), "draft").unwrap();
+
+        let mut builder = SiteBuilder::new(dir.path());
+        let site = builder.build().unwrap();
+
+        let dests: Vec<&Path> = site
+            .assets
+            .iter()
+            .map(|asset| asset.dest.as_path())
+            .collect();
+        assert_eq!(dests, vec![Path::new("images/logo.png")]);
+    }
+
     #[test]
     fn test_nested_collections() {
         let dir = create_test_site();
@@ -1500,4 +2139,155 @@ url = "/"
 
         assert_eq!(registry.get("2024-01-15-hello.md").unwrap(), "/blog/hello/");
     }
+
+    #[test]
+    fn test_shortcodes_receive_page_and_site_context() {
+        let dir = create_test_site();
+        fs::create_dir_all(dir.path().join("templates/shortcodes")).unwrap();
+        fs::write(
+            dir.path().join("templates/shortcodes/download.html"),
+            "<a href=\"{{ base_url | safe }}/files/{{ page.slug }}.{{ format }}\">{{ page.version }} of {{ site.config.title }} ({{ page.permalink }})</a>",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/posts/2024-01-15-hello.md"),
+            "+++\ntitle = \"Hello\"\nversion = \"1.2\"\n+++\n\n{{< download format=\"zip\" >}}",
+        )
+        .unwrap();
+
+        let site = SiteBuilder::new(dir.path()).build().unwrap();
+        assert!(site.posts[0].content.html.contains(
+            "<a href=\"https://example.com/files/hello.zip\">1.2 of Test Site (https://example.com/posts/hello/)</a>"
+        ));
+    }
+
+    #[test]
+    fn test_template_extensions_reach_shortcodes() {
+        let dir = create_test_site();
+        fs::create_dir_all(dir.path().join("templates/shortcodes")).unwrap();
+        fs::write(
+            dir.path().join("templates/shortcodes/shout.html"),
+            "<b>{{ text | shout }}</b>",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/about.md"),
+            "+++\ntitle = \"About\"\n+++\n\n{{< shout text=\"hi\" >}}",
+        )
+        .unwrap();
+
+        let mut extensions = TemplateExtensions::new();
+        extensions.filter(
+            "shout",
+            |value: &Value, _: &HashMap<String, Value>| -> tera::Result<Value> {
+                Ok(Value::String(
+                    value.as_str().unwrap_or_default().to_uppercase(),
+                ))
+            },
+        );
+        let site = SiteBuilder::new(dir.path())
+            .template_extensions(extensions)
+            .build()
+            .unwrap();
+
+        let about = site
+            .pages
+            .iter()
+            .find(|page| page.content.slug == "about")
+            .unwrap();
+        assert!(about.content.html.contains("<b>HI</b>"));
+    }
+
+    #[test]
+    fn test_translations_linked_by_key() {
+        let dir = create_test_site();
+        fs::write(
+            dir.path().join("bamboo.toml"),
+            "title = \"T\"\nbase_url = \"https://example.com\"\nlanguage = \"en\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/about.md"),
+            "+++\ntitle = \"About\"\ntranslation_key = \"about\"\n+++\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/a-propos.md"),
+            "+++\ntitle = \"A propos\"\nlang = \"fr\"\ntranslation_key = \"about\"\n+++\n",
+        )
+        .unwrap();
+
+        let site = SiteBuilder::new(dir.path()).build().unwrap();
+        let find = |slug: &str| {
+            site.pages
+                .iter()
+                .find(|page| page.content.slug == slug)
+                .unwrap()
+        };
+
+        let about = find("about");
+        assert_eq!(about.content.lang.as_deref(), Some("en"));
+        assert_eq!(
+            about.content.translations,
+            vec![Translation {
+                lang: "fr".to_string(),
+                title: "A propos".to_string(),
+                url: "/a-propos/".to_string(),
+            }]
+        );
+        assert_eq!(find("a-propos").content.lang.as_deref(), Some("fr"));
+        assert_eq!(find("a-propos").content.translations[0].url, "/about/");
+        assert!(find("contact").content.translations.is_empty());
+    }
+
+    #[test]
+    fn test_notes_loaded_without_titles() {
+        let dir = create_test_site();
+        fs::create_dir_all(dir.path().join("content/notes")).unwrap();
+        fs::write(
+            dir.path().join("content/notes/2024-06-01-1430.md"),
+            "Trying out **short** posts today.\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/notes/coffee.md"),
+            "+++\ndate = \"2024-06-02T08:15:00Z\"\ntags = [\"life\"]\n+++\nOne two three four five six seven eight nine ten eleven.\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/notes/same-time.md"),
+            "+++\ndate = \"2024-06-01 14:30\"\ntitle = \"Titled\"\n+++\nSame minute.\n",
+        )
+        .unwrap();
+
+        let site = SiteBuilder::new(dir.path()).build().unwrap();
+
+        let slugs: Vec<&str> = site
+            .notes
+            .iter()
+            .map(|note| note.content.slug.as_str())
+            .collect();
+        assert_eq!(
+            slugs,
+            vec![
+                "2024-06-02-081500",
+                "2024-06-01-143000",
+                "2024-06-01-143000-2"
+            ]
+        );
+        assert_eq!(site.notes[0].content.url, "/notes/2024-06-02-081500/");
+        assert_eq!(
+            site.notes[0].content.title,
+            "One two three four five six seven eight nine ten…"
+        );
+        assert_eq!(site.notes[0].tags, vec!["life"]);
+        assert_eq!(site.notes[1].content.title, "Trying out short posts today.");
+        assert_eq!(site.notes[2].content.title, "Titled");
+        assert!(
+            site.pages
+                .iter()
+                .all(|page| !page.content.url.starts_with("/notes/"))
+        );
+        assert!(!site.collections.contains_key("notes"));
+    }
 }
@@ -1,54 +1,242 @@
 //! `sitemap.xml` generation covering every page, post, taxonomy index,
 //! and pagination slice in a built [`Site`].
-
-use crate::error::Result;
+//!
+//! The file is rendered through the theme's `sitemap.xml` template (the
+//! built-in one unless the theme or site ships its own), so sites that need
+//! image, video, or news sitemap namespaces can override it.
+//!
+//! The protocol caps a sitemap at 50,000 URLs. Past `[sitemap] max_urls`
+//! (50,000 by default) the URLs are split across `sitemap-1.xml`,
+//! `sitemap-2.xml`, and so on, each rendered through the same template,
+//! and `sitemap.xml` becomes a sitemap index pointing at them.
+
+use crate::diagnostics::render_template;
+use crate::error::{IoContext, Result};
+use crate::feeds::{NOTES_SECTION, POSTS_SECTION};
+use crate::graph::Node;
 use crate::parsing::slugify;
-use crate::types::Site;
-use crate::xml::escape;
-use std::fs;
+use crate::redirects::redirect_to;
+use crate::sink::RenderSink;
+use crate::types::{Content, Site};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
+use tera::{Context, Tera};
+
+/// Name of the sitemap template, which is also the output filename.
+pub const SITEMAP_TEMPLATE: &str = "sitemap.xml";
+
+/// Most URLs the sitemap protocol allows in one file.
+pub const DEFAULT_MAX_URLS: usize = 50_000;
+
+/// Section name of top-level pages in [`SitemapConfig::sections`].
+pub const PAGES_SECTION: &str = "pages";
+
+/// `[sitemap]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SitemapConfig {
+    /// URLs per sitemap file before the sitemap is split behind an index.
+    /// Defaults to [`DEFAULT_MAX_URLS`].
+    pub max_urls: usize,
+    /// `<changefreq>` of every URL without a more specific one. Left out
+    /// of the sitemap by default.
+    pub changefreq: Option<ChangeFreq>,
+    /// Defaults for content in a section, keyed by `"posts"`, `"notes"`, a
+    /// collection name, the top-level directory of nested pages, or
+    /// [`PAGES_SECTION`] for top-level pages.
+    pub sections: HashMap<String, SectionDefaults>,
+}
 
-/// Writes `sitemap.xml` into `output_dir`, listing every page, post,
-/// taxonomy index, and paginated slice in the site.
-pub fn generate_sitemap(site: &Site, output_dir: &Path) -> Result<()> {
-    let base_url = site.config.base_url.trim_end_matches('/');
-    let escaped_base_url = escape(base_url);
+impl Default for SitemapConfig {
+    fn default() -> Self {
+        Self {
+            max_urls: DEFAULT_MAX_URLS,
+            changefreq: None,
+            sections: HashMap::new(),
+        }
+    }
+}
+
+/// One `[sitemap.sections.<name>]` table.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SectionDefaults {
+    /// Priority of the section's content, replacing the 0.8 default.
+    pub priority: Option<f64>,
+    /// `<changefreq>` of the section's content.
+    pub changefreq: Option<ChangeFreq>,
+}
 
-    let mut urls = String::new();
+/// A `<changefreq>` value from the sitemap protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeFreq {
+    /// Changes on every access.
+    Always,
+    /// Hourly.
+    Hourly,
+    /// Daily.
+    Daily,
+    /// Weekly.
+    Weekly,
+    /// Monthly.
+    Monthly,
+    /// Yearly.
+    Yearly,
+    /// Archived content that won't change.
+    Never,
+}
 
-    urls.push_str(&format!(
-        "  <url>\n    <loc>{}/</loc>\n  </url>\n",
-        escaped_base_url
-    ));
+impl ChangeFreq {
+    /// Parses a protocol value, ignoring case.
+    pub fn parse(value: &str) -> Option<Self> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "always" => Self::Always,
+            "hourly" => Self::Hourly,
+            "daily" => Self::Daily,
+            "weekly" => Self::Weekly,
+            "monthly" => Self::Monthly,
+            "yearly" => Self::Yearly,
+            "never" => Self::Never,
+            _ => return None,
+        })
+    }
+}
+
+/// Priority of the home page.
+const HOME_PRIORITY: f64 = 1.0;
+/// Priority of pages, posts, notes, and collection items.
+const CONTENT_PRIORITY: f64 = 0.8;
+/// Priority of section, taxonomy, and term listings.
+const LISTING_PRIORITY: f64 = 0.5;
+/// Priority of the second and later pages of a listing.
+const PAGINATION_PRIORITY: f64 = 0.3;
+
+/// One `<url>` of the sitemap, as seen by the `sitemap.xml` template.
+#[derive(Debug, Clone, Serialize)]
+pub struct SitemapEntry<'a> {
+    /// Absolute URL.
+    pub url: String,
+    /// Last modification date (`YYYY-MM-DD`) from `updated` or `date`
+    /// frontmatter, or the post or note date.
+    pub lastmod: Option<String>,
+    /// `sitemap_priority` frontmatter, the section's priority from
+    /// `[sitemap.sections]`, or a default by kind: 1.0 for the home page,
+    /// 0.8 for content, 0.5 for listings, 0.3 for later pages of a listing.
+    pub priority: f64,
+    /// `sitemap_changefreq` frontmatter, the section's `changefreq`, or the
+    /// site-wide `[sitemap] changefreq`.
+    pub changefreq: Option<ChangeFreq>,
+    /// The URL of this content in each language, including its own, when it
+    /// has translations.
+    pub alternates: Vec<Alternate>,
+    /// The page, post, note, or item behind the URL; `None` for listings.
+    pub page: Option<&'a Content>,
+}
+
+/// An `<xhtml:link rel="alternate">` target.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alternate {
+    /// Language code.
+    pub lang: String,
+    /// Absolute URL.
+    pub url: String,
+}
+
+/// Every URL in the sitemap, in output order: home, pages, posts, notes,
+/// post pagination, collections, then taxonomies.
+pub fn sitemap_entries(site: &Site) -> Vec<SitemapEntry<'_>> {
+    let base_url = site.config.base_url.trim_end_matches('/');
+    let config = &site.config.sitemap;
+    let listing = |path: String, priority: f64| SitemapEntry {
+        url: format!("{base_url}{path}"),
+        lastmod: None,
+        priority,
+        changefreq: config.changefreq,
+        alternates: Vec::new(),
+        page: None,
+    };
+    let entry_for = |content, section: Option<&str>, priority, date| {
+        content_entry(config, section, content, base_url, priority, date)
+    };
+
+    let mut entries = Vec::new();
+
+    match &site.home {
+        Some(home) if !home.content.in_sitemap() => {}
+        Some(home) => entries.push(SitemapEntry {
+            url: format!("{base_url}/"),
+            ..entry_for(&home.content, None, HOME_PRIORITY, None)
+        }),
+        None => entries.push(listing("/".to_string(), HOME_PRIORITY)),
+    }
 
     for page in &site.pages {
-        if page.content.slug == "404" {
+        if page.content.slug == "404"
+            || redirect_to(&page.content).is_some()
+            || !page.content.in_sitemap()
+        {
             continue;
         }
-        urls.push_str(&format!(
-            "  <url>\n    <loc>{}/{}/</loc>\n  </url>\n",
-            escaped_base_url,
-            escape(&page.content.slug)
+        let section = match Node::Page(page).section() {
+            "" => PAGES_SECTION,
+            section => section,
+        };
+        entries.push(entry_for(
+            &page.content,
+            Some(section),
+            CONTENT_PRIORITY,
+            None,
         ));
     }
 
-    for post in &site.posts {
-        let lastmod = post.date.format("%Y-%m-%d").to_string();
-        urls.push_str(&format!(
-            "  <url>\n    <loc>{}/posts/{}/</loc>\n    <lastmod>{}</lastmod>\n  </url>\n",
-            escaped_base_url,
-            escape(&post.content.slug),
-            lastmod
+    for post in site
+        .posts
+        .iter()
+        .filter(|post| redirect_to(&post.content).is_none() && post.content.in_sitemap())
+    {
+        let date = post.date.format("%Y-%m-%d").to_string();
+        entries.push(entry_for(
+            &post.content,
+            Some(POSTS_SECTION),
+            CONTENT_PRIORITY,
+            Some(date),
         ));
     }
 
+    if !site.notes.is_empty() {
+        entries.push(SitemapEntry {
+            lastmod: site
+                .notes
+                .iter()
+                .map(|note| dated_lastmod(&note.content, note.date))
+                .max(),
+            ..listing("/notes/".to_string(), LISTING_PRIORITY)
+        });
+    }
+    for note in site.notes.iter().filter(|note| note.content.in_sitemap()) {
+        let date = note.date.format("%Y-%m-%d").to_string();
+        entries.push(entry_for(
+            &note.content,
+            Some(NOTES_SECTION),
+            CONTENT_PRIORITY,
+            Some(date),
+        ));
+    }
+
+    for generated in &site.config.generated_pages {
+        entries.push(listing(generated.url(), CONTENT_PRIORITY));
+    }
+
     let posts_per_page = site.config.posts_per_page;
     if posts_per_page > 0 && !site.posts.is_empty() {
         let total_pages = site.posts.len().div_ceil(posts_per_page);
         for page_number in 2..=total_pages {
-            urls.push_str(&format!(
-                "  <url>\n    <loc>{}/page/{}/</loc>\n  </url>\n",
-                escaped_base_url, page_number
+            entries.push(listing(
+                format!("/page/{page_number}/"),
+                PAGINATION_PRIORITY,
             ));
         }
     }
@@ -57,31 +245,31 @@ pub fn generate_sitemap(site: &Site, output_dir: &Path) -> Result<()> {
         site.collections.iter().collect();
     sorted_collections.sort_by_key(|(name, _)| name.as_str());
     for (name, collection) in sorted_collections {
-        urls.push_str(&format!(
-            "  <url>\n    <loc>{}/{}/</loc>\n  </url>\n",
-            escaped_base_url,
-            escape(name)
-        ));
+        entries.push(SitemapEntry {
+            lastmod: collection
+                .items
+                .iter()
+                .filter_map(|item| undated_lastmod(&item.content))
+                .max(),
+            ..listing(format!("/{name}/"), LISTING_PRIORITY)
+        });
 
         if posts_per_page > 0 && !collection.items.is_empty() {
             let total_collection_pages = collection.items.len().div_ceil(posts_per_page);
             for page_number in 2..=total_collection_pages {
-                urls.push_str(&format!(
-                    "  <url>\n    <loc>{}/{}/page/{}/</loc>\n  </url>\n",
-                    escaped_base_url,
-                    escape(name),
-                    page_number
+                entries.push(listing(
+                    format!("/{name}/page/{page_number}/"),
+                    PAGINATION_PRIORITY,
                 ));
             }
         }
 
-        for item in &collection.items {
-            urls.push_str(&format!(
-                "  <url>\n    <loc>{}/{}/{}/</loc>\n  </url>\n",
-                escaped_base_url,
-                escape(name),
-                escape(&item.content.slug)
-            ));
+        for item in collection
+            .items
+            .iter()
+            .filter(|item| item.content.in_sitemap())
+        {
+            entries.push(entry_for(&item.content, Some(name), CONTENT_PRIORITY, None));
         }
     }
 
@@ -89,69 +277,218 @@ pub fn generate_sitemap(site: &Site, output_dir: &Path) -> Result<()> {
     sorted_taxonomy_names.sort();
 
     for taxonomy_name in sorted_taxonomy_names {
-        use std::collections::HashMap as TaxonomyCountMap;
-        let mut term_counts: TaxonomyCountMap<String, usize> = TaxonomyCountMap::new();
+        let mut term_counts: HashMap<String, (usize, String)> = HashMap::new();
         for post in &site.posts {
             if let Some(terms) = post.taxonomies_map.get(taxonomy_name) {
+                let lastmod = dated_lastmod(&post.content, post.date);
                 for term in terms {
-                    let slug = slugify(term);
-                    *term_counts.entry(slug).or_default() += 1;
+                    let (count, newest) = term_counts.entry(slugify(term)).or_default();
+                    *count += 1;
+                    if lastmod > *newest {
+                        newest.clone_from(&lastmod);
+                    }
                 }
             }
         }
-        if !term_counts.is_empty() {
-            urls.push_str(&format!(
-                "  <url>\n    <loc>{}/{}/</loc>\n  </url>\n",
-                escaped_base_url,
-                escape(taxonomy_name)
-            ));
-            let mut sorted_terms: Vec<(&String, &usize)> = term_counts.iter().collect();
-            sorted_terms.sort_by_key(|(slug, _)| slug.as_str());
-            for (slug, count) in sorted_terms {
-                urls.push_str(&format!(
-                    "  <url>\n    <loc>{}/{}/{}/</loc>\n  </url>\n",
-                    escaped_base_url,
-                    escape(taxonomy_name),
-                    escape(slug)
-                ));
-                if posts_per_page > 0 {
-                    let total_pages = count.div_ceil(posts_per_page);
-                    for page_number in 2..=total_pages {
-                        urls.push_str(&format!(
-                            "  <url>\n    <loc>{}/{}/{}/page/{}/</loc>\n  </url>\n",
-                            escaped_base_url,
-                            escape(taxonomy_name),
-                            escape(slug),
-                            page_number
-                        ));
-                    }
+        if term_counts.is_empty() {
+            continue;
+        }
+        entries.push(SitemapEntry {
+            lastmod: term_counts.values().map(|(_, newest)| newest.clone()).max(),
+            ..listing(format!("/{taxonomy_name}/"), LISTING_PRIORITY)
+        });
+        let mut sorted_terms: Vec<(&String, &(usize, String))> = term_counts.iter().collect();
+        sorted_terms.sort_by_key(|(slug, _)| slug.as_str());
+        for (slug, (count, newest)) in sorted_terms {
+            entries.push(SitemapEntry {
+                lastmod: Some(newest.clone()),
+                ..listing(format!("/{taxonomy_name}/{slug}/"), LISTING_PRIORITY)
+            });
+            if posts_per_page > 0 {
+                let total_pages = count.div_ceil(posts_per_page);
+                for page_number in 2..=total_pages {
+                    entries.push(listing(
+                        format!("/{taxonomy_name}/{slug}/page/{page_number}/"),
+                        PAGINATION_PRIORITY,
+                    ));
                 }
             }
         }
     }
 
-    let sitemap = format!(
-        r#"<?xml version="1.0" encoding="UTF-8"?>
-<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
-{}
-</urlset>
-"#,
-        urls
-    );
+    entries
+}
 
-    fs::write(output_dir.join("sitemap.xml"), sitemap)?;
+/// Renders `sitemap.xml` into `output` from the theme's sitemap
+/// template, listing every page, post, taxonomy index, and paginated slice
+/// in the site. The template receives `site` and `urls` (a list of
+/// [`SitemapEntry`]). Sites with more than `[sitemap] max_urls` URLs get
+/// numbered sitemap files and a sitemap index instead. Numbered files left
+/// in a directory by an earlier build are removed first.
+pub fn generate_sitemap(
+    tera: &Tera,
+    site: &Site,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    if let Some(output_dir) = output.directory() {
+        remove_split_sitemaps(output_dir)?;
+    }
+    let entries = sitemap_entries(site);
+    let max_urls = site.config.sitemap.max_urls.max(1);
+    let mut context = Context::new();
+    context.insert("site", &crate::theme::site_metadata(site));
+
+    if entries.len() <= max_urls {
+        context.insert("urls", &entries);
+        let sitemap = render_template(tera, SITEMAP_TEMPLATE, &context)?;
+        return output.write_file(Path::new(SITEMAP_TEMPLATE), sitemap.as_bytes());
+    }
 
+    let base_url = site.config.base_url.trim_end_matches('/');
+    let mut index = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for (number, chunk) in entries.chunks(max_urls).enumerate() {
+        let filename = format!("sitemap-{}.xml", number + 1);
+        context.insert("urls", chunk);
+        let sitemap = render_template(tera, SITEMAP_TEMPLATE, &context)?;
+        output.write_file(Path::new(&filename), sitemap.as_bytes())?;
+
+        index.push_str(&format!(
+            "  <sitemap>\n    <loc>{}</loc>\n",
+            crate::xml::escape(&format!("{base_url}/{filename}"))
+        ));
+        if let Some(lastmod) = chunk
+            .iter()
+            .filter_map(|entry| entry.lastmod.as_ref())
+            .max()
+        {
+            index.push_str(&format!("    <lastmod>{lastmod}</lastmod>\n"));
+        }
+        index.push_str("  </sitemap>\n");
+    }
+    index.push_str("</sitemapindex>\n");
+    output.write_file(Path::new(SITEMAP_TEMPLATE), index.as_bytes())
+}
+
+/// Removes the `sitemap-<n>.xml` files in `output_dir`, so a build that
+/// splits into fewer files, or none, doesn't leave old ones behind.
+fn remove_split_sitemaps(output_dir: &Path) -> Result<()> {
+    let Ok(entries) = std::fs::read_dir(output_dir) else {
+        return Ok(());
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let is_split = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("sitemap-"))
+            .and_then(|name| name.strip_suffix(".xml"))
+            .is_some_and(|number| {
+                !number.is_empty() && number.bytes().all(|byte| byte.is_ascii_digit())
+            });
+        if is_split {
+            let path = entry.path();
+            std::fs::remove_file(&path).io_context("remove", &path)?;
+        }
+    }
     Ok(())
 }
 
+/// Entry for a page, post, note, or item in `section` (`None` for the
+/// home page). `date` is the fallback `lastmod` when the frontmatter has
+/// neither `updated` nor `date`.
+fn content_entry<'a>(
+    config: &SitemapConfig,
+    section: Option<&str>,
+    content: &'a Content,
+    base_url: &str,
+    priority: f64,
+    date: Option<String>,
+) -> SitemapEntry<'a> {
+    let defaults = section.and_then(|section| config.sections.get(section));
+    SitemapEntry {
+        url: format!("{base_url}{}", content.url),
+        lastmod: content_lastmod(content)
+            .or(date)
+            .or_else(|| modified(content)),
+        priority: content
+            .frontmatter
+            .get_f64("sitemap_priority")
+            .or(defaults.and_then(|defaults| defaults.priority))
+            .unwrap_or(priority),
+        changefreq: content
+            .frontmatter
+            .get_string("sitemap_changefreq")
+            .and_then(|value| ChangeFreq::parse(&value))
+            .or(defaults.and_then(|defaults| defaults.changefreq))
+            .or(config.changefreq),
+        alternates: alternates(content, base_url),
+        page: Some(content),
+    }
+}
+
+/// `updated` frontmatter, falling back to `date`, as `YYYY-MM-DD`.
+fn content_lastmod(content: &Content) -> Option<String> {
+    content
+        .frontmatter
+        .get_date("updated")
+        .or_else(|| content.frontmatter.get_date("date"))
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// [`Content::modified`] as `YYYY-MM-DD`.
+fn modified(content: &Content) -> Option<String> {
+    content
+        .modified
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// `lastmod` of a post or note: frontmatter, then its `date`.
+fn dated_lastmod(content: &Content, date: DateTime<Utc>) -> String {
+    content_lastmod(content).unwrap_or_else(|| date.format("%Y-%m-%d").to_string())
+}
+
+/// `lastmod` of a page or collection item: frontmatter, then the last
+/// change to its source file.
+fn undated_lastmod(content: &Content) -> Option<String> {
+    content_lastmod(content).or_else(|| modified(content))
+}
+
+/// The content itself and each translation, when the content has
+/// translations. Empty otherwise.
+fn alternates(content: &Content, base_url: &str) -> Vec<Alternate> {
+    let (Some(lang), false) = (&content.lang, content.translations.is_empty()) else {
+        return Vec::new();
+    };
+    std::iter::once((lang.as_str(), content.url.as_str()))
+        .chain(
+            content
+                .translations
+                .iter()
+                .map(|translation| (translation.lang.as_str(), translation.url.as_str())),
+        )
+        .map(|(lang, url)| Alternate {
+            lang: lang.to_string(),
+            url: format!("{base_url}{url}"),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::types::*;
     use chrono::{NaiveDate, NaiveTime, TimeZone, Utc};
-    use std::collections::HashMap;
     use std::path::PathBuf;
 
+    fn tera() -> &'static Tera {
+        static ENGINE: std::sync::OnceLock<crate::theme::ThemeEngine> = std::sync::OnceLock::new();
+        ENGINE
+            .get_or_init(|| crate::theme::ThemeEngine::new("default").unwrap())
+            .tera()
+    }
+
     fn minimal_site() -> Site {
         Site {
             config: SiteConfig {
@@ -163,20 +500,54 @@ mod tests {
                 posts_per_page: 10,
                 minify: false,
                 fingerprint: false,
+                dedupe_assets: None,
+                static_ignore: Vec::new(),
+                assets: crate::assets::AssetsConfig::default(),
+                build_manifest: false,
+                search_format: crate::search::SearchFormat::Json,
+                search_exclude: Vec::new(),
                 images: None,
+                social_images: None,
                 syntax_theme: crate::types::default_syntax_theme(),
                 taxonomies: crate::types::default_taxonomies(),
                 math: false,
                 favicon: None,
                 link_check_ignore: Vec::new(),
+                link_archive: None,
+                precompress: None,
+                fonts: Vec::new(),
+                build: crate::build_hooks::BuildConfig::default(),
+                deploy: crate::deploy::DeployConfig::default(),
+                remote: crate::remote::RemoteConfig::default(),
+                feed: crate::feeds::FeedConfig::default(),
+                sitemap: crate::sitemap::SitemapConfig::default(),
+                wellknown: crate::wellknown::WellKnownConfig::default(),
+                manifest: None,
+                llms: None,
+                vercel: None,
+                htaccess: None,
+                headers: None,
+                newsletter: None,
+                discussions: crate::discussions::DiscussionConfig::default(),
+                link_previews: crate::link_previews::LinkPreviewConfig::default(),
+                changelog: None,
+                external_resources: None,
+                generate: crate::types::GenerateConfig::default(),
+                drafts: false,
+                future: false,
+                generated_pages: Vec::new(),
+                outputs: Vec::new(),
+                jobs: None,
                 extra: HashMap::new(),
             },
             home: None,
             pages: vec![],
             posts: vec![],
+            notes: vec![],
             collections: HashMap::new(),
             data: HashMap::new(),
             assets: vec![],
+            image_requests: Vec::new(),
         }
     }
 
@@ -194,10 +565,15 @@ mod tests {
                 raw_content: String::new(),
                 frontmatter: Frontmatter::default(),
                 path: PathBuf::from(format!("posts/{}/index.html", slug)),
+                source_path: PathBuf::new(),
+                modified: None,
                 template: None,
                 weight: 0,
                 word_count: 0,
                 reading_time: 0,
+                stats: crate::types::PageStats::default(),
+                lang: None,
+                translations: Vec::new(),
                 toc: vec![],
                 url: format!("/posts/{}/", slug),
             },
@@ -225,6 +601,8 @@ mod tests {
                 map
             },
             redirect_from: vec![],
+            discussion: None,
+            link_preview: None,
         }
     }
 
@@ -239,10 +617,15 @@ mod tests {
                 raw_content: String::new(),
                 frontmatter: Frontmatter::default(),
                 path: PathBuf::from("about/index.html"),
+                source_path: PathBuf::new(),
+                modified: None,
                 template: None,
                 weight: 0,
                 word_count: 0,
                 reading_time: 0,
+                stats: crate::types::PageStats::default(),
+                lang: None,
+                translations: Vec::new(),
                 toc: vec![],
                 url: "/about/".to_string(),
             },
@@ -251,13 +634,45 @@ mod tests {
         });
 
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_sitemap(&site, output_dir.path()).unwrap();
+        generate_sitemap(tera(), &site, output_dir.path()).unwrap();
 
         let content = std::fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
         assert!(content.contains("https://example.com/"));
         assert!(content.contains("https://example.com/about/"));
     }
 
+    #[test]
+    fn test_sitemap_lastmod_for_pages_and_listings() {
+        let mut site = minimal_site();
+        let mut page = make_post("about", vec![], vec![]).content;
+        page.url = "/about/".to_string();
+        page.modified = Some(Utc.with_ymd_and_hms(2024, 3, 9, 12, 0, 0).unwrap());
+        site.pages.push(Page {
+            content: page,
+            draft: false,
+            redirect_from: vec![],
+        });
+        site.posts.push(make_post("old", vec!["rust"], vec![]));
+        let mut newer = make_post("new", vec!["rust", "web"], vec![]);
+        newer.content.frontmatter = Frontmatter::new(HashMap::from([(
+            "updated".to_string(),
+            serde_json::json!("2024-05-01"),
+        )]));
+        site.posts.push(newer);
+
+        let entries = sitemap_entries(&site);
+        let lastmod = |url: &str| {
+            entries
+                .iter()
+                .find(|entry| entry.url == format!("https://example.com{url}"))
+                .and_then(|entry| entry.lastmod.clone())
+        };
+        assert_eq!(lastmod("/about/").as_deref(), Some("2024-03-09"));
+        assert_eq!(lastmod("/tags/").as_deref(), Some("2024-05-01"));
+        assert_eq!(lastmod("/tags/rust/").as_deref(), Some("2024-05-01"));
+        assert_eq!(lastmod("/tags/web/").as_deref(), Some("2024-05-01"));
+    }
+
     #[test]
     fn test_sitemap_excludes_404() {
         let mut site = minimal_site();
@@ -269,10 +684,15 @@ mod tests {
                 raw_content: String::new(),
                 frontmatter: Frontmatter::default(),
                 path: PathBuf::from("404.html"),
+                source_path: PathBuf::new(),
+                modified: None,
                 template: None,
                 weight: 0,
                 word_count: 0,
                 reading_time: 0,
+                stats: crate::types::PageStats::default(),
+                lang: None,
+                translations: Vec::new(),
                 toc: vec![],
                 url: "/404/".to_string(),
             },
@@ -281,7 +701,7 @@ mod tests {
         });
 
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_sitemap(&site, output_dir.path()).unwrap();
+        generate_sitemap(tera(), &site, output_dir.path()).unwrap();
 
         let content = std::fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
         assert!(!content.contains("/404/"));
@@ -294,7 +714,7 @@ mod tests {
             .push(make_post("hello", vec!["rust"], vec!["tech"]));
 
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_sitemap(&site, output_dir.path()).unwrap();
+        generate_sitemap(tera(), &site, output_dir.path()).unwrap();
 
         let content = std::fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
         assert!(content.contains("/tags/"));
@@ -311,7 +731,7 @@ mod tests {
         site.posts.push(make_post("b", vec![], vec![]));
 
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_sitemap(&site, output_dir.path()).unwrap();
+        generate_sitemap(tera(), &site, output_dir.path()).unwrap();
 
         let content = std::fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
         assert!(content.contains("/page/2/"));
@@ -332,19 +752,26 @@ mod tests {
                         raw_content: String::new(),
                         frontmatter: Frontmatter::default(),
                         path: PathBuf::from("docs/intro/index.html"),
+                        source_path: PathBuf::new(),
+                        modified: None,
                         template: None,
                         weight: 0,
                         word_count: 0,
                         reading_time: 0,
+                        stats: crate::types::PageStats::default(),
+                        lang: None,
+                        translations: Vec::new(),
                         toc: vec![],
                         url: "/docs/intro/".to_string(),
                     },
                 }],
+                config: CollectionConfig::default(),
+                index: None,
             },
         );
 
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_sitemap(&site, output_dir.path()).unwrap();
+        generate_sitemap(tera(), &site, output_dir.path()).unwrap();
 
         let content = std::fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
         assert!(content.contains("/docs/"));
@@ -365,10 +792,15 @@ mod tests {
                     raw_content: String::new(),
                     frontmatter: Frontmatter::default(),
                     path: PathBuf::from(format!("docs/item-{}/index.html", index)),
+                    source_path: PathBuf::new(),
+                    modified: None,
                     template: None,
                     weight: 0,
                     word_count: 0,
                     reading_time: 0,
+                    stats: crate::types::PageStats::default(),
+                    lang: None,
+                    translations: Vec::new(),
                     toc: vec![],
                     url: format!("/docs/item-{}/", index),
                 },
@@ -380,11 +812,13 @@ mod tests {
             Collection {
                 name: "docs".to_string(),
                 items,
+                config: CollectionConfig::default(),
+                index: None,
             },
         );
 
         let output_dir = tempfile::TempDir::new().unwrap();
-        generate_sitemap(&site, output_dir.path()).unwrap();
+        generate_sitemap(tera(), &site, output_dir.path()).unwrap();
 
         let content = std::fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
         assert!(content.contains("/docs/"));
@@ -392,4 +826,194 @@ mod tests {
         assert!(content.contains("/docs/page/3/"));
         assert!(content.contains("/docs/item-0/"));
     }
+
+    #[test]
+    fn test_sitemap_hreflang_alternates() {
+        let mut site = minimal_site();
+        let mut english = make_post("hello", vec![], vec![]);
+        english.content.lang = Some("en".to_string());
+        english.content.translations = vec![Translation {
+            lang: "fr".to_string(),
+            title: "Bonjour".to_string(),
+            url: "/posts/bonjour/".to_string(),
+        }];
+        let mut solo = make_post("solo", vec![], vec![]);
+        solo.content.lang = Some("en".to_string());
+        site.posts.push(english);
+        site.posts.push(solo);
+
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_sitemap(tera(), &site, output_dir.path()).unwrap();
+
+        let content = std::fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
+        assert!(content.contains("xmlns:xhtml=\"http://www.w3.org/1999/xhtml\""));
+        assert!(content.contains(
+            "<xhtml:link rel=\"alternate\" hreflang=\"en\" href=\"https://example.com/posts/hello/\"/>"
+        ));
+        assert!(content.contains(
+            "<xhtml:link rel=\"alternate\" hreflang=\"fr\" href=\"https://example.com/posts/bonjour/\"/>"
+        ));
+        assert_eq!(content.matches("<xhtml:link").count(), 2);
+    }
+
+    #[test]
+    fn test_sitemap_priority_and_lastmod() {
+        let mut site = minimal_site();
+        site.config.posts_per_page = 1;
+        site.posts.push(make_post("a", vec![], vec![]));
+        site.posts.push(make_post("b", vec![], vec![]));
+        site.posts[1].content.frontmatter = Frontmatter::new(HashMap::from([
+            ("updated".to_string(), serde_json::json!("2024-07-04")),
+            ("sitemap_priority".to_string(), serde_json::json!(0.9)),
+        ]));
+
+        let entries = sitemap_entries(&site);
+        let summary: Vec<(&str, Option<&str>, f64)> = entries
+            .iter()
+            .map(|entry| (entry.url.as_str(), entry.lastmod.as_deref(), entry.priority))
+            .collect();
+        assert_eq!(summary[0], ("https://example.com/", None, 1.0));
+        assert_eq!(
+            summary[2],
+            ("https://example.com/posts/b/", Some("2024-07-04"), 0.9)
+        );
+        assert!(summary.contains(&("https://example.com/page/2/", None, 0.3)));
+
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_sitemap(tera(), &site, output_dir.path()).unwrap();
+        let content = std::fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
+        assert!(content.contains(
+            "<loc>https://example.com/posts/b/</loc>\n    <lastmod>2024-07-04</lastmod>\n    <priority>0.9</priority>"
+        ));
+    }
+
+    #[test]
+    fn test_sitemap_section_defaults_and_changefreq() {
+        let mut site = minimal_site();
+        site.posts.push(make_post("a", vec![], vec![]));
+        site.posts.push(make_post("b", vec![], vec![]));
+        site.posts[1].content.frontmatter = Frontmatter::new(HashMap::from([
+            ("sitemap_priority".to_string(), serde_json::json!(0.9)),
+            ("sitemap_changefreq".to_string(), serde_json::json!("Daily")),
+        ]));
+        site.config.sitemap = toml::from_str(
+            "changefreq = \"yearly\"\n[sections.posts]\npriority = 0.6\nchangefreq = \"monthly\"",
+        )
+        .unwrap();
+
+        let entries = sitemap_entries(&site);
+        let find = |url: &str| {
+            entries
+                .iter()
+                .find(|entry| entry.url == url)
+                .map(|entry| (entry.priority, entry.changefreq))
+                .unwrap()
+        };
+        assert_eq!(
+            find("https://example.com/"),
+            (1.0, Some(ChangeFreq::Yearly))
+        );
+        assert_eq!(
+            find("https://example.com/posts/a/"),
+            (0.6, Some(ChangeFreq::Monthly))
+        );
+        assert_eq!(
+            find("https://example.com/posts/b/"),
+            (0.9, Some(ChangeFreq::Daily))
+        );
+
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_sitemap(tera(), &site, output_dir.path()).unwrap();
+        let content = std::fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
+        assert!(content.contains(
+            "<loc>https://example.com/posts/a/</loc>\n    <lastmod>2024-01-01</lastmod>\n    \
+             <changefreq>monthly</changefreq>\n    <priority>0.6</priority>"
+        ));
+        assert!(toml::from_str::<SitemapConfig>("changefreq = \"sometimes\"").is_err());
+    }
+
+    #[test]
+    fn test_sitemap_skips_in_sitemap_false() {
+        let mut site = minimal_site();
+        site.posts.push(make_post("shown", vec![], vec![]));
+        site.posts.push(make_post("hidden", vec![], vec![]));
+        site.posts[1]
+            .content
+            .frontmatter
+            .raw
+            .insert("in_sitemap".to_string(), serde_json::json!(false));
+
+        let urls: Vec<String> = sitemap_entries(&site)
+            .into_iter()
+            .map(|entry| entry.url)
+            .collect();
+        assert!(urls.contains(&"https://example.com/posts/shown/".to_string()));
+        assert!(!urls.contains(&"https://example.com/posts/hidden/".to_string()));
+    }
+
+    #[test]
+    fn test_sitemap_index_splitting() {
+        let mut site = minimal_site();
+        for slug in ["a", "b", "c"] {
+            site.posts.push(make_post(slug, vec![], vec![]));
+        }
+        let total = sitemap_entries(&site).len();
+        site.config.sitemap = toml::from_str("max_urls = 2").unwrap();
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let stale = output_dir.path().join("sitemap-99.xml");
+        std::fs::write(&stale, "<urlset></urlset>").unwrap();
+        std::fs::write(output_dir.path().join("sitemap-news.xml"), "").unwrap();
+        generate_sitemap(tera(), &site, output_dir.path()).unwrap();
+        assert!(!stale.exists());
+        assert!(output_dir.path().join("sitemap-news.xml").exists());
+
+        let files = total.div_ceil(2);
+        let index = std::fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
+        assert!(index.contains("<sitemapindex"));
+        assert_eq!(index.matches("<sitemap>").count(), files);
+        assert!(index.contains("<loc>https://example.com/sitemap-1.xml</loc>"));
+        let mut urls = 0;
+        for number in 1..=files {
+            let path = output_dir.path().join(format!("sitemap-{number}.xml"));
+            let sitemap = std::fs::read_to_string(path).unwrap();
+            assert!(sitemap.contains("<urlset"));
+            urls += sitemap.matches("<loc>").count();
+        }
+        assert_eq!(urls, total);
+        assert!(
+            !output_dir
+                .path()
+                .join(format!("sitemap-{}.xml", files + 1))
+                .exists()
+        );
+
+        site.config.sitemap.max_urls = total;
+        generate_sitemap(tera(), &site, output_dir.path()).unwrap();
+        assert!(!output_dir.path().join("sitemap-1.xml").exists());
+        let sitemap = std::fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
+        assert!(sitemap.contains("<urlset"));
+    }
+
+    #[test]
+    fn test_sitemap_template_override() {
+        let mut site = minimal_site();
+        site.posts.push(make_post("hello", vec![], vec![]));
+        let mut tera = Tera::default();
+        tera.add_raw_template(
+            SITEMAP_TEMPLATE,
+            "<urlset xmlns:image=\"http://www.google.com/schemas/sitemap-image/1.1\">\
+             {% for entry in urls %}<url><loc>{{ entry.url | safe }}</loc>\
+             {% if entry.page %}<image:title>{{ entry.page.title }}</image:title>{% endif %}</url>\
+             {% endfor %}</urlset>",
+        )
+        .unwrap();
+
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_sitemap(&tera, &site, output_dir.path()).unwrap();
+        let content = std::fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
+        assert!(content.starts_with("<urlset xmlns:image="));
+        assert!(content.contains(
+            "<url><loc>https://example.com/posts/hello/</loc><image:title>hello</image:title></url>"
+        ));
+    }
 }
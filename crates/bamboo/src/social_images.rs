@@ -0,0 +1,776 @@
+//! Per-post social preview images. With a `[social_images]` table in
+//! `bamboo.toml`, every post gets a `social.png` card next to its
+//! `index.html`: the post title and site title drawn over a background image
+//! or a solid color. The card becomes the post's `og:image` unless its
+//! frontmatter names an image of its own, and `social_image = false` turns
+//! it off for a single post.
+//!
+//! Text is drawn with the TrueType or OpenType font named by `font`. Without
+//! one, a built-in 5×7 pixel font scaled up by whole pixels is used, so no
+//! font files are needed; it only covers ASCII, so other characters are
+//! approximated or drawn as `?`.
+
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont, point};
+use image::imageops::FilterType;
+use image::{ImageFormat, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::error::{BambooError, Result};
+use crate::sink::RenderSink;
+use crate::types::{Content, Site};
+
+/// File name of the card written next to each post's `index.html`.
+pub const SOCIAL_IMAGE_NAME: &str = "social.png";
+
+/// Columns and rows of one glyph in the built-in font, before scaling.
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+/// `[social_images]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SocialImageConfig {
+    /// Background image, relative to the output directory (so any file from
+    /// `static/` or the theme's `static/`). Scaled and cropped to fill the
+    /// card. Without one, the card is filled with `background`.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// TrueType or OpenType font for both titles, relative to the output
+    /// directory like `template`. Without one, the built-in pixel font is
+    /// used.
+    #[serde(default)]
+    pub font: Option<String>,
+    /// Card width in pixels. Defaults to 1200.
+    #[serde(default = "default_width")]
+    pub width: u32,
+    /// Card height in pixels. Defaults to 630.
+    #[serde(default = "default_height")]
+    pub height: u32,
+    /// Fill color when there is no `template`, as `#rrggbb` or `#rgb`.
+    #[serde(default = "default_background")]
+    pub background: String,
+    /// Color of the post title.
+    #[serde(default = "default_title_color")]
+    pub title_color: String,
+    /// Color of the site title along the bottom edge.
+    #[serde(default = "default_site_color")]
+    pub site_color: String,
+    /// Size of each font pixel in the post title. Defaults to 8, which
+    /// makes capital letters 56 pixels tall. A `font` is drawn 10 × scale
+    /// pixels tall from ascender to descender, which gives capitals about
+    /// the same height.
+    #[serde(default = "default_title_scale")]
+    pub title_scale: u32,
+    /// Size of each font pixel in the site title. Defaults to 4.
+    #[serde(default = "default_site_scale")]
+    pub site_scale: u32,
+}
+
+fn default_width() -> u32 {
+    1200
+}
+
+fn default_height() -> u32 {
+    630
+}
+
+fn default_background() -> String {
+    "#1e293b".to_string()
+}
+
+fn default_title_color() -> String {
+    "#f8fafc".to_string()
+}
+
+fn default_site_color() -> String {
+    "#94a3b8".to_string()
+}
+
+fn default_title_scale() -> u32 {
+    8
+}
+
+fn default_site_scale() -> u32 {
+    4
+}
+
+impl Default for SocialImageConfig {
+    fn default() -> Self {
+        Self {
+            template: None,
+            font: None,
+            width: default_width(),
+            height: default_height(),
+            background: default_background(),
+            title_color: default_title_color(),
+            site_color: default_site_color(),
+            title_scale: default_title_scale(),
+            site_scale: default_site_scale(),
+        }
+    }
+}
+
+/// Whether `content` gets a generated card: `[social_images]` is configured
+/// and its `social_image` frontmatter isn't `false`.
+pub fn is_enabled(site: &Site, content: &Content) -> bool {
+    site.config.social_images.is_some()
+        && content.frontmatter.get_bool("social_image").unwrap_or(true)
+}
+
+/// Output path of `content`'s card, relative to the output directory.
+pub fn social_image_path(content: &Content) -> PathBuf {
+    content.path.with_file_name(SOCIAL_IMAGE_NAME)
+}
+
+/// Site-relative URL of `content`'s card, or `None` when it doesn't get one.
+pub fn social_image_url(site: &Site, content: &Content) -> Option<String> {
+    is_enabled(site, content).then(|| {
+        format!(
+            "/{}",
+            social_image_path(content)
+                .to_string_lossy()
+                .replace('\\', "/")
+        )
+    })
+}
+
+/// Draws `content`'s card and writes it under `output`. Does nothing
+/// when the card is disabled.
+pub fn generate_social_image(
+    site: &Site,
+    content: &Content,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    let Some(config) = site.config.social_images.as_ref() else {
+        return Ok(());
+    };
+    if !is_enabled(site, content) {
+        return Ok(());
+    }
+
+    let card = render_card(config, &content.title, &site.config.title, output)?;
+    let output_path = social_image_path(content);
+    let mut png = std::io::Cursor::new(Vec::new());
+    card.write_to(&mut png, ImageFormat::Png)
+        .map_err(|error| BambooError::ImageProcessing {
+            message: format!("failed to encode {}: {}", output_path.display(), error),
+        })?;
+    output.write_file(&output_path, png.get_ref())
+}
+
+/// Draws a card with `title` wrapped across the top and `site_title` along
+/// the bottom edge.
+pub fn render_card(
+    config: &SocialImageConfig,
+    title: &str,
+    site_title: &str,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<RgbaImage> {
+    let width = config.width.max(1);
+    let height = config.height.max(1);
+    let mut canvas = match config.template.as_deref() {
+        Some(template) => {
+            let path = Path::new(template.trim_start_matches('/'));
+            let image_error = |error: &dyn std::fmt::Display| BambooError::ImageProcessing {
+                message: format!(
+                    "failed to load social image template {}: {}",
+                    path.display(),
+                    error
+                ),
+            };
+            let bytes = output
+                .read_file(path)
+                .ok_or_else(|| image_error(&"not found in the output"))?;
+            image::load_from_memory(&bytes)
+                .map_err(|error| image_error(&error))?
+                .resize_to_fill(width, height, FilterType::Lanczos3)
+                .to_rgba8()
+        }
+        None => RgbaImage::from_pixel(width, height, parse_color(&config.background)?),
+    };
+
+    let typeface = Typeface::load(config.font.as_deref(), output)?;
+    let margin = width.min(height) / 8;
+    let text_width = width.saturating_sub(2 * margin);
+    let title_scale = config.title_scale.max(1);
+    let site_scale = config.site_scale.max(1);
+    let site_top = height.saturating_sub(margin + typeface.text_height(site_scale));
+    let title_bottom = site_top.saturating_sub(margin / 2);
+    let max_lines = (title_bottom.saturating_sub(margin) / line_height(title_scale)).max(1);
+
+    let title_color = parse_color(&config.title_color)?;
+    let title_lines = wrap(title, max_lines as usize, |line| {
+        typeface.width(line, title_scale) <= text_width
+    });
+    for (index, line) in title_lines.iter().enumerate() {
+        let top = margin + index as u32 * line_height(title_scale);
+        typeface.draw(&mut canvas, line, margin, top, title_scale, title_color);
+    }
+
+    let site_lines = wrap(site_title, 1, |line| {
+        typeface.width(line, site_scale) <= text_width
+    });
+    if let Some(site_line) = site_lines.first() {
+        typeface.draw(
+            &mut canvas,
+            site_line,
+            margin,
+            site_top,
+            site_scale,
+            parse_color(&config.site_color)?,
+        );
+    }
+    Ok(canvas)
+}
+
+/// How card text is drawn.
+enum Typeface {
+    /// The built-in 5×7 pixel font.
+    Builtin,
+    /// A font loaded from `font`.
+    Font(FontVec),
+}
+
+impl Typeface {
+    /// Loads the font at `path` in the output, or the built-in font
+    /// without one.
+    fn load(path: Option<&str>, output: &(impl RenderSink + ?Sized)) -> Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::Builtin);
+        };
+        let path = Path::new(path.trim_start_matches('/'));
+        let font_error = |error: &dyn std::fmt::Display| BambooError::ImageProcessing {
+            message: format!(
+                "failed to load social image font {}: {}",
+                path.display(),
+                error
+            ),
+        };
+        let bytes = output
+            .read_file(path)
+            .ok_or_else(|| font_error(&"not found in the output"))?;
+        FontVec::try_from_vec(bytes)
+            .map(Self::Font)
+            .map_err(|error| font_error(&error))
+    }
+
+    /// Pixel size of a font drawn at `scale`, from ascender to descender.
+    fn px_scale(scale: u32) -> PxScale {
+        PxScale::from(line_height(scale) as f32)
+    }
+
+    /// Height of the text above the baseline at `scale`.
+    fn text_height(&self, scale: u32) -> u32 {
+        match self {
+            Self::Builtin => GLYPH_HEIGHT * scale,
+            Self::Font(font) => font.as_scaled(Self::px_scale(scale)).ascent().ceil() as u32,
+        }
+    }
+
+    /// Width of `text` in pixels at `scale`.
+    fn width(&self, text: &str, scale: u32) -> u32 {
+        match self {
+            Self::Builtin => {
+                let glyphs: usize = text
+                    .chars()
+                    .map(|character| ascii_approximation(character).len())
+                    .sum();
+                glyphs as u32 * advance(scale)
+            }
+            Self::Font(font) => {
+                let font = font.as_scaled(Self::px_scale(scale));
+                let mut width = 0.0;
+                let mut previous = None;
+                for character in text.chars() {
+                    let id = font.glyph_id(character);
+                    if let Some(previous) = previous {
+                        width += font.kern(previous, id);
+                    }
+                    width += font.h_advance(id);
+                    previous = Some(id);
+                }
+                width.ceil() as u32
+            }
+        }
+    }
+
+    /// Draws `text` with the top of its line at `top`.
+    fn draw(
+        &self,
+        canvas: &mut RgbaImage,
+        text: &str,
+        left: u32,
+        top: u32,
+        scale: u32,
+        color: Rgba<u8>,
+    ) {
+        match self {
+            Self::Builtin => draw_text(canvas, text, left, top, scale, color),
+            Self::Font(font) => {
+                let scaled = font.as_scaled(Self::px_scale(scale));
+                let baseline = top as f32 + scaled.ascent();
+                let mut x = left as f32;
+                let mut previous = None;
+                for character in text.chars() {
+                    let id = scaled.glyph_id(character);
+                    if let Some(previous) = previous {
+                        x += scaled.kern(previous, id);
+                    }
+                    let glyph = id.with_scale_and_position(scaled.scale(), point(x, baseline));
+                    x += scaled.h_advance(id);
+                    previous = Some(id);
+                    let Some(outline) = font.outline_glyph(glyph) else {
+                        continue;
+                    };
+                    let bounds = outline.px_bounds();
+                    outline.draw(|dx, dy, coverage| {
+                        let x = bounds.min.x as i64 + dx as i64;
+                        let y = bounds.min.y as i64 + dy as i64;
+                        if (0..canvas.width() as i64).contains(&x)
+                            && (0..canvas.height() as i64).contains(&y)
+                        {
+                            blend(canvas.get_pixel_mut(x as u32, y as u32), color, coverage);
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Mixes `color` over `pixel` by `coverage`, from 0 to 1.
+fn blend(pixel: &mut Rgba<u8>, color: Rgba<u8>, coverage: f32) {
+    let coverage = coverage.clamp(0.0, 1.0);
+    for channel in 0..3 {
+        let mixed = pixel[channel] as f32 * (1.0 - coverage) + color[channel] as f32 * coverage;
+        pixel[channel] = mixed.round() as u8;
+    }
+    pixel[3] = pixel[3].max((coverage * 255.0).round() as u8);
+}
+
+/// Parses `#rrggbb` or `#rgb`.
+fn parse_color(color: &str) -> Result<Rgba<u8>> {
+    let invalid = || BambooError::ImageProcessing {
+        message: format!("invalid social image color '{color}', expected #rrggbb"),
+    };
+    let hex = color.trim().strip_prefix('#').ok_or_else(invalid)?;
+    let expanded: String = match hex.len() {
+        3 => hex.chars().flat_map(|digit| [digit, digit]).collect(),
+        6 => hex.to_string(),
+        _ => return Err(invalid()),
+    };
+    let channel = |index: usize| {
+        expanded
+            .get(index..index + 2)
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            .ok_or_else(invalid)
+    };
+    Ok(Rgba([channel(0)?, channel(2)?, channel(4)?, 255]))
+}
+
+/// Horizontal distance between glyphs at `scale`.
+fn advance(scale: u32) -> u32 {
+    (GLYPH_WIDTH + 1) * scale
+}
+
+/// Vertical distance between lines at `scale`.
+fn line_height(scale: u32) -> u32 {
+    (GLYPH_HEIGHT + 3) * scale
+}
+
+/// Greedy word wrap into lines for which `fits` holds. Words too long for
+/// a line are split, and text beyond `max_lines` is cut off with `...`.
+fn wrap(text: &str, max_lines: usize, fits: impl Fn(&str) -> bool) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if !current.is_empty() && fits(&format!("{current} {word}")) {
+            current.push(' ');
+            current.push_str(word);
+            continue;
+        }
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        let mut rest = word;
+        while !fits(rest) {
+            // The longest prefix that fits, but at least one character.
+            let mut split = rest.chars().next().map_or(0, char::len_utf8);
+            for (index, _) in rest.char_indices().skip(1) {
+                if !fits(&rest[..index]) {
+                    break;
+                }
+                split = index;
+            }
+            lines.push(rest[..split].to_string());
+            rest = &rest[split..];
+        }
+        current = rest.to_string();
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        if let Some(last) = lines.last_mut() {
+            let mut shortened = last.clone();
+            while !shortened.is_empty() && !fits(&format!("{}...", shortened.trim_end())) {
+                shortened.pop();
+            }
+            shortened.truncate(shortened.trim_end().len());
+            shortened.push_str("...");
+            *last = shortened;
+        }
+    }
+    lines
+}
+
+fn draw_text(canvas: &mut RgbaImage, text: &str, left: u32, top: u32, scale: u32, color: Rgba<u8>) {
+    let mut x = left;
+    for character in text.chars() {
+        for replacement in ascii_approximation(character).chars() {
+            draw_glyph(canvas, glyph(replacement), x, top, scale, color);
+            x += advance(scale);
+        }
+    }
+}
+
+fn draw_glyph(
+    canvas: &mut RgbaImage,
+    columns: [u8; 5],
+    left: u32,
+    top: u32,
+    scale: u32,
+    color: Rgba<u8>,
+) {
+    for (column, bits) in columns.iter().enumerate() {
+        for row in 0..GLYPH_HEIGHT {
+            if bits & (1 << row) == 0 {
+                continue;
+            }
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let x = left + column as u32 * scale + dx;
+                    let y = top + row * scale + dy;
+                    if x < canvas.width() && y < canvas.height() {
+                        canvas.put_pixel(x, y, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Closest ASCII spelling of typographic characters common in titles.
+fn ascii_approximation(character: char) -> String {
+    match character {
+        ' '..='~' => character.to_string(),
+        '\u{2018}' | '\u{2019}' => "'".to_string(),
+        '\u{201C}' | '\u{201D}' => "\"".to_string(),
+        '\u{2013}' | '\u{2014}' => "-".to_string(),
+        '\u{2026}' => "...".to_string(),
+        '\u{00A0}' => " ".to_string(),
+        _ => "?".to_string(),
+    }
+}
+
+/// Columns of `character` in the built-in font, least significant bit at the
+/// top. `character` must be printable ASCII.
+fn glyph(character: char) -> [u8; 5] {
+    let index = (character as usize).saturating_sub(' ' as usize);
+    FONT.get(index)
+        .copied()
+        .unwrap_or(FONT['?' as usize - ' ' as usize])
+}
+
+/// Printable ASCII from `' '` to `'~'`, one glyph per character.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5F, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7F, 0x14, 0x7F, 0x14],
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50],
+    [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1C, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1C, 0x00],
+    [0x14, 0x08, 0x3E, 0x08, 0x14],
+    [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3E, 0x51, 0x49, 0x45, 0x3E],
+    [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46],
+    [0x21, 0x41, 0x45, 0x4B, 0x31],
+    [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3C, 0x4A, 0x49, 0x49, 0x30],
+    [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x06, 0x49, 0x49, 0x29, 0x1E],
+    [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x08, 0x14, 0x22, 0x41, 0x00],
+    [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08],
+    [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3E],
+    [0x7E, 0x11, 0x11, 0x11, 0x7E],
+    [0x7F, 0x49, 0x49, 0x49, 0x36],
+    [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C],
+    [0x7F, 0x49, 0x49, 0x49, 0x41],
+    [0x7F, 0x09, 0x09, 0x09, 0x01],
+    [0x3E, 0x41, 0x49, 0x49, 0x7A],
+    [0x7F, 0x08, 0x08, 0x08, 0x7F],
+    [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01],
+    [0x7F, 0x08, 0x14, 0x22, 0x41],
+    [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F],
+    [0x7F, 0x04, 0x08, 0x10, 0x7F],
+    [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06],
+    [0x3E, 0x41, 0x51, 0x21, 0x5E],
+    [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7F, 0x01, 0x01],
+    [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F],
+    [0x3F, 0x40, 0x38, 0x40, 0x3F],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x07, 0x08, 0x70, 0x08, 0x07],
+    [0x61, 0x51, 0x49, 0x45, 0x43],
+    [0x00, 0x7F, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x00, 0x41, 0x41, 0x7F, 0x00],
+    [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x01, 0x02, 0x04, 0x00],
+    [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7F, 0x48, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7F],
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x08, 0x7E, 0x09, 0x01, 0x02],
+    [0x0C, 0x52, 0x52, 0x52, 0x3E],
+    [0x7F, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7D, 0x40, 0x00],
+    [0x20, 0x40, 0x44, 0x3D, 0x00],
+    [0x7F, 0x10, 0x28, 0x44, 0x00],
+    [0x00, 0x41, 0x7F, 0x40, 0x00],
+    [0x7C, 0x04, 0x18, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7C, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7C],
+    [0x7C, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3F, 0x44, 0x40, 0x20],
+    [0x3C, 0x40, 0x40, 0x20, 0x7C],
+    [0x1C, 0x20, 0x40, 0x20, 0x1C],
+    [0x3C, 0x40, 0x30, 0x40, 0x3C],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x0C, 0x50, 0x50, 0x50, 0x3C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x7F, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x10, 0x08, 0x08, 0x10, 0x08],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(
+            parse_color("#1e293b").unwrap(),
+            Rgba([0x1e, 0x29, 0x3b, 255])
+        );
+        assert_eq!(parse_color("#fff").unwrap(), Rgba([255, 255, 255, 255]));
+        assert!(parse_color("1e293b").is_err());
+        assert!(parse_color("#12345").is_err());
+        assert!(parse_color("#gggggg").is_err());
+    }
+
+    #[test]
+    fn test_wrap() {
+        let columns = |max: usize| move |line: &str| line.chars().count() <= max;
+        assert_eq!(
+            wrap("Building a static site generator", 5, columns(12)),
+            vec!["Building a", "static site", "generator"]
+        );
+        assert_eq!(
+            wrap("Supercalifragilistic", 5, columns(8)),
+            vec!["Supercal", "ifragili", "stic"]
+        );
+        assert_eq!(
+            wrap("one two three four", 2, columns(8)),
+            vec!["one two", "three..."]
+        );
+        assert!(wrap("   ", 2, columns(8)).is_empty());
+    }
+
+    #[test]
+    fn test_render_card_draws_text() {
+        let config = SocialImageConfig {
+            width: 300,
+            height: 160,
+            background: "#000".to_string(),
+            title_color: "#ff0000".to_string(),
+            site_color: "#00ff00".to_string(),
+            title_scale: 2,
+            site_scale: 1,
+            ..SocialImageConfig::default()
+        };
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let card = render_card(&config, "Hello", "Blog", output_dir.path()).unwrap();
+
+        assert_eq!(card.dimensions(), (300, 160));
+        assert_eq!(*card.get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+        let count = |color: Rgba<u8>| card.pixels().filter(|pixel| **pixel == color).count();
+        assert!(count(Rgba([255, 0, 0, 255])) > 0);
+        assert!(count(Rgba([0, 255, 0, 255])) > 0);
+    }
+
+    /// A TrueType font whose only glyph is a filled box for `é`, 500 by
+    /// 700 units on a 1000-unit em.
+    fn box_font() -> Vec<u8> {
+        let mut head = vec![0u8; 54];
+        head[..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        head[12..16].copy_from_slice(&0x5F0F_3CF5u32.to_be_bytes());
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        let mut hhea = vec![0u8; 36];
+        hhea[..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+        hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+        hhea[34..36].copy_from_slice(&2u16.to_be_bytes());
+        let mut maxp = 0x0000_5000u32.to_be_bytes().to_vec();
+        maxp.extend_from_slice(&2u16.to_be_bytes());
+        let hmtx = [0u16, 0, 700, 100]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+
+        // One contour through (100, 0), (100, 700), (600, 700), (600, 0).
+        let mut glyph = Vec::new();
+        for value in [1i16, 100, 0, 600, 700, 3, 0] {
+            glyph.extend_from_slice(&value.to_be_bytes());
+        }
+        glyph.extend_from_slice(&[1, 1, 1, 1]);
+        for delta in [100i16, 0, 500, 0, 0, 700, 0, -700] {
+            glyph.extend_from_slice(&delta.to_be_bytes());
+        }
+        let loca = [0u16, 0, (glyph.len() / 2) as u16]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+
+        // One Windows UCS-4 subtable in format 12, mapping U+00E9 to glyph 1.
+        let mut cmap = Vec::new();
+        for value in [0u16, 1, 3, 10] {
+            cmap.extend_from_slice(&value.to_be_bytes());
+        }
+        cmap.extend_from_slice(&12u32.to_be_bytes());
+        for value in [12u16, 0] {
+            cmap.extend_from_slice(&value.to_be_bytes());
+        }
+        for value in [28u32, 0, 1, 0xE9, 0xE9, 1] {
+            cmap.extend_from_slice(&value.to_be_bytes());
+        }
+
+        let tables: [(&[u8; 4], Vec<u8>); 7] = [
+            (b"cmap", cmap),
+            (b"glyf", glyph),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"loca", loca),
+            (b"maxp", maxp),
+        ];
+        let mut font = 0x0001_0000u32.to_be_bytes().to_vec();
+        for value in [tables.len() as u16, 0, 0, 0] {
+            font.extend_from_slice(&value.to_be_bytes());
+        }
+        let data_start = font.len() + tables.len() * 16;
+        let mut data = Vec::new();
+        for (tag, table) in &tables {
+            font.extend_from_slice(*tag);
+            font.extend_from_slice(&0u32.to_be_bytes());
+            font.extend_from_slice(&((data_start + data.len()) as u32).to_be_bytes());
+            font.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            data.extend_from_slice(table);
+            data.resize(data.len().next_multiple_of(4), 0);
+        }
+        font.extend_from_slice(&data);
+        font
+    }
+
+    #[test]
+    fn test_render_card_with_font() {
+        let output_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(output_dir.path().join("card.ttf"), box_font()).unwrap();
+        let config = SocialImageConfig {
+            font: Some("/card.ttf".to_string()),
+            width: 300,
+            height: 160,
+            background: "#000".to_string(),
+            title_color: "#ff0000".to_string(),
+            title_scale: 4,
+            ..SocialImageConfig::default()
+        };
+
+        let card = render_card(&config, "é", "", output_dir.path()).unwrap();
+        // The box is 20 by 28 pixels at 40 pixels from ascender to
+        // descender; the built-in font would have drawn a small `?`.
+        let red = card
+            .pixels()
+            .filter(|pixel| **pixel == Rgba([255, 0, 0, 255]))
+            .count();
+        assert!(red >= 19 * 27, "{red} red pixels");
+
+        let missing = SocialImageConfig {
+            font: Some("missing.ttf".to_string()),
+            ..config.clone()
+        };
+        let error = render_card(&missing, "é", "", output_dir.path()).unwrap_err();
+        assert!(error.to_string().contains("missing.ttf"), "{error}");
+
+        std::fs::write(output_dir.path().join("broken.ttf"), "not a font").unwrap();
+        let broken = SocialImageConfig {
+            font: Some("broken.ttf".to_string()),
+            ..config
+        };
+        assert!(render_card(&broken, "é", "", output_dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_render_card_uses_template() {
+        let output_dir = tempfile::TempDir::new().unwrap();
+        RgbaImage::from_pixel(40, 20, Rgba([0, 0, 255, 255]))
+            .save(output_dir.path().join("card.png"))
+            .unwrap();
+        let config = SocialImageConfig {
+            template: Some("/card.png".to_string()),
+            width: 120,
+            height: 60,
+            ..SocialImageConfig::default()
+        };
+
+        let card = render_card(&config, "", "", output_dir.path()).unwrap();
+        assert_eq!(card.dimensions(), (120, 60));
+        assert_eq!(*card.get_pixel(0, 0), Rgba([0, 0, 255, 255]));
+
+        let missing = SocialImageConfig {
+            template: Some("missing.png".to_string()),
+            ..config
+        };
+        assert!(render_card(&missing, "", "", output_dir.path()).is_err());
+    }
+}
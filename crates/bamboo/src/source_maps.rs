@@ -0,0 +1,241 @@
+//! Source maps for minified CSS and JavaScript, enabled with
+//! `source_maps = true` in the `[assets]` table.
+//!
+//! lightningcss maps CSS as it prints. minify-js has no source map
+//! support, so JavaScript maps are rebuilt afterwards by aligning the
+//! tokens minification leaves intact (string and number literals,
+//! keywords, property names, globals) with their positions in the
+//! original. Renamed locals and dropped punctuation aren't mapped, but
+//! every statement lands within a token or two of its source.
+
+use std::path::Path;
+
+use parcel_sourcemap::{OriginalLocation, SourceMap};
+
+use crate::error::{BambooError, Result};
+
+/// How far ahead in the original a minified token is looked for before
+/// it's treated as synthesized by the minifier.
+const SEARCH_WINDOW: usize = 64;
+
+/// Name of the map written next to `file_path`: `app.js` → `app.js.map`.
+pub fn map_file_name(file_path: &Path) -> String {
+    format!(
+        "{}.map",
+        file_path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default()
+    )
+}
+
+/// The comment appended to a minified file pointing at its map.
+pub fn map_comment(file_path: &Path) -> String {
+    let name = map_file_name(file_path);
+    if file_path
+        .extension()
+        .is_some_and(|extension| extension == "css")
+    {
+        format!("\n/*# sourceMappingURL={} */", name)
+    } else {
+        format!("\n//# sourceMappingURL={}", name)
+    }
+}
+
+/// Serializes `map` with `original` embedded as the content of its first
+/// source, since the minified file replaces the original on disk.
+pub fn to_json(map: &mut SourceMap, file_path: &Path, original: &str) -> Result<String> {
+    let map_error = |error: parcel_sourcemap::SourceMapError| BambooError::IoPath {
+        operation: "write source map for",
+        path: file_path.to_path_buf(),
+        source: std::io::Error::other(error.to_string()),
+    };
+    if !map.get_sources().is_empty() {
+        map.set_source_content(0, original).map_err(map_error)?;
+    }
+    map.to_json(None).map_err(map_error)
+}
+
+/// Builds a source map from `minified` back to `original`, whose source
+/// is named `source_name`.
+pub fn minified_js_map(source_name: &str, original: &str, minified: &str) -> SourceMap {
+    let mut map = SourceMap::new("/");
+    let source = map.add_source(source_name);
+    let original_tokens = js_tokens(original);
+    let mut cursor = 0;
+    for token in js_tokens(minified) {
+        let window = &original_tokens[cursor..(cursor + SEARCH_WINDOW).min(original_tokens.len())];
+        if let Some(found) = window
+            .iter()
+            .position(|candidate| candidate.text == token.text)
+        {
+            let matched = &window[found];
+            map.add_mapping(
+                token.line,
+                token.column,
+                Some(OriginalLocation {
+                    original_line: matched.line,
+                    original_column: matched.column,
+                    source,
+                    name: None,
+                }),
+            );
+            cursor += found + 1;
+        }
+    }
+    map
+}
+
+struct Token<'a> {
+    text: &'a str,
+    line: u32,
+    column: u32,
+}
+
+/// The tokens of `source` likely to survive minification unchanged:
+/// literals and identifiers of three or more characters (minifiers
+/// rename locals to one or two).
+fn js_tokens(source: &str) -> Vec<Token<'_>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = 0u32;
+    let mut line_start = 0usize;
+    let mut previous: Option<u8> = None;
+    let mut index = 0;
+    while index < bytes.len() {
+        let start = index;
+        let byte = bytes[index];
+        let distinctive = match byte {
+            b'\n' => {
+                line += 1;
+                line_start = index + 1;
+                index += 1;
+                continue;
+            }
+            b' ' | b'\t' | b'\r' => {
+                index += 1;
+                continue;
+            }
+            b'/' if bytes.get(index + 1) == Some(&b'/') => {
+                index = source[index..]
+                    .find('\n')
+                    .map_or(bytes.len(), |end| index + end);
+                continue;
+            }
+            b'/' if bytes.get(index + 1) == Some(&b'*') => {
+                let end = source[index + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| index + 2 + end + 2);
+                line += source[index..end].matches('\n').count() as u32;
+                if let Some(newline) = source[index..end].rfind('\n') {
+                    line_start = index + newline + 1;
+                }
+                index = end;
+                continue;
+            }
+            b'\'' | b'"' => {
+                index = crate::bundler::skip_string(bytes, index);
+                true
+            }
+            b'`' => {
+                index = crate::bundler::skip_template(bytes, index);
+                true
+            }
+            b'/' if previous.is_none_or(|before| b"(,=:[!&|?{};+-*%<>~^".contains(&before)) => {
+                index = crate::bundler::skip_regex(bytes, index);
+                index - start > 1
+            }
+            _ if byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' || byte >= 0x80 => {
+                while index < bytes.len()
+                    && (bytes[index].is_ascii_alphanumeric()
+                        || bytes[index] == b'_'
+                        || bytes[index] == b'$'
+                        || bytes[index] >= 0x80)
+                {
+                    index += 1;
+                }
+                byte.is_ascii_digit() || index - start >= 3
+            }
+            _ => {
+                index += 1;
+                false
+            }
+        };
+        if distinctive {
+            tokens.push(Token {
+                text: &source[start..index],
+                line,
+                column: source[line_start..start].encode_utf16().count() as u32,
+            });
+        }
+        // Template literals can span lines.
+        let newlines = source[start..index].matches('\n').count();
+        if newlines > 0 {
+            line += newlines as u32;
+            line_start = start + source[start..index].rfind('\n').unwrap_or(0) + 1;
+        }
+        previous = Some(bytes[index - 1]);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minified_js_map_aligns_surviving_tokens() {
+        let original = "// greet the user\nfunction greet(name) {\n  const message = \"Hello, \" + name;\n  console.log(message);\n}\n\ngreet(\"world\");\n";
+        let minified = "function greet(a){const b=\"Hello, \"+a;console.log(b)}greet(\"world\")";
+        let mut map = minified_js_map("app.js", original, minified);
+        let json = to_json(&mut map, Path::new("app.js"), original).unwrap();
+        assert!(json.contains("\"sources\":[\"app.js\"]"), "{}", json);
+        assert!(json.contains("greet the user"), "{}", json);
+
+        // `console` in the minified output maps to line 4 (index 3), col 2.
+        let column = minified.find("console").unwrap() as u32;
+        let mapping = map.find_closest_mapping(0, column).unwrap();
+        let location = mapping.original.unwrap();
+        assert_eq!((location.original_line, location.original_column), (3, 2));
+
+        // The final call maps back to the last line.
+        let column = minified.rfind("\"world\"").unwrap() as u32;
+        let location = map
+            .find_closest_mapping(0, column)
+            .unwrap()
+            .original
+            .unwrap();
+        assert_eq!((location.original_line, location.original_column), (6, 6));
+    }
+
+    #[test]
+    fn test_js_tokens_skip_comments_and_short_names() {
+        let tokens = js_tokens("/* a\nb */ let ab = 'x' + 42; // tail\nfoo.bar");
+        let texts: Vec<(&str, u32, u32)> = tokens
+            .iter()
+            .map(|token| (token.text, token.line, token.column))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("let", 1, 5),
+                ("'x'", 1, 14),
+                ("42", 1, 20),
+                ("foo", 2, 0),
+                ("bar", 2, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_map_comment() {
+        assert_eq!(
+            map_comment(Path::new("css/site.css")),
+            "\n/*# sourceMappingURL=site.css.map */"
+        );
+        assert_eq!(
+            map_comment(Path::new("js/app.js")),
+            "\n//# sourceMappingURL=app.js.map"
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Content statistics for `bamboo stats`: how much has been written and
+//! when, how it is tagged, and how large the pages and the built output
+//! are. Handy for year-in-review posts and for spotting pages or assets
+//! that have grown out of proportion.
+
+use crate::types::{Content, Site};
+use chrono::Datelike;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Counts and sizes for a site, from [`site_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SiteStats {
+    /// Number of posts.
+    pub posts: usize,
+    /// Number of pages, including the home page.
+    pub pages: usize,
+    /// Number of notes.
+    pub notes: usize,
+    /// Number of items across all collections.
+    pub collection_items: usize,
+    /// Words across all of the above.
+    pub words: usize,
+    /// Words across posts.
+    pub post_words: usize,
+    /// Mean reading time of a post in minutes, or 0 without posts.
+    pub average_reading_time: f64,
+    /// Posts published each year.
+    pub posts_per_year: BTreeMap<i32, usize>,
+    /// Posts per tag, most used first.
+    pub posts_per_tag: Vec<(String, usize)>,
+    /// Posts per category, most used first.
+    pub posts_per_category: Vec<(String, usize)>,
+    /// The content with the most words, longest first.
+    pub largest_pages: Vec<PageSize>,
+    /// Size of the built site, if it has been built.
+    pub output: Option<OutputStats>,
+}
+
+/// Length of one page, post, note, or collection item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PageSize {
+    /// Title.
+    pub title: String,
+    /// Site-relative URL.
+    pub url: String,
+    /// Words in the rendered body.
+    pub words: usize,
+}
+
+/// Size of the output directory, from [`output_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct OutputStats {
+    /// Number of files.
+    pub files: usize,
+    /// Total size in bytes.
+    pub bytes: u64,
+    /// Bytes per file extension (lowercase, `""` for none), largest first.
+    pub bytes_per_extension: Vec<(String, u64)>,
+    /// The largest files as `(path, bytes)`, largest first.
+    pub largest_files: Vec<(String, u64)>,
+}
+
+/// Gathers statistics for `site`, listing up to `top` of the largest
+/// pages. `output` is left empty; see [`output_stats`].
+pub fn site_stats(site: &Site, top: usize) -> SiteStats {
+    let pages: Vec<&Content> = site
+        .home
+        .iter()
+        .chain(&site.pages)
+        .map(|page| &page.content)
+        .collect();
+    let items: Vec<&Content> = site
+        .collections
+        .values()
+        .flat_map(|collection| collection.items.iter().map(|item| &item.content))
+        .collect();
+    let contents: Vec<&Content> = pages
+        .iter()
+        .copied()
+        .chain(site.posts.iter().map(|post| &post.content))
+        .chain(site.notes.iter().map(|note| &note.content))
+        .chain(items.iter().copied())
+        .collect();
+
+    let post_words = site.posts.iter().map(|post| post.content.word_count).sum();
+    let average_reading_time = if site.posts.is_empty() {
+        0.0
+    } else {
+        site.posts
+            .iter()
+            .map(|post| post.content.reading_time)
+            .sum::<usize>() as f64
+            / site.posts.len() as f64
+    };
+
+    let mut posts_per_year = BTreeMap::new();
+    let mut tags: HashMap<&str, usize> = HashMap::new();
+    let mut categories: HashMap<&str, usize> = HashMap::new();
+    for post in &site.posts {
+        *posts_per_year.entry(post.date.year()).or_insert(0) += 1;
+        for tag in &post.tags {
+            *tags.entry(tag).or_insert(0) += 1;
+        }
+        for category in &post.categories {
+            *categories.entry(category).or_insert(0) += 1;
+        }
+    }
+
+    let mut largest_pages: Vec<PageSize> = contents
+        .iter()
+        .map(|content| PageSize {
+            title: content.title.clone(),
+            url: content.url.clone(),
+            words: content.word_count,
+        })
+        .collect();
+    largest_pages.sort_by(|a, b| b.words.cmp(&a.words).then_with(|| a.url.cmp(&b.url)));
+    largest_pages.truncate(top);
+
+    SiteStats {
+        posts: site.posts.len(),
+        pages: pages.len(),
+        notes: site.notes.len(),
+        collection_items: items.len(),
+        words: contents.iter().map(|content| content.word_count).sum(),
+        post_words,
+        average_reading_time,
+        posts_per_year,
+        posts_per_tag: most_used(tags),
+        posts_per_category: most_used(categories),
+        largest_pages,
+        output: None,
+    }
+}
+
+/// Counts sorted by count descending, then name.
+fn most_used<K: ToString, V: Ord + Copy>(counts: HashMap<K, V>) -> Vec<(String, V)> {
+    let mut counts: Vec<(String, V)> = counts
+        .into_iter()
+        .map(|(name, count)| (name.to_string(), count))
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// Sizes the files under `output_dir`, listing up to `top` of the largest.
+pub fn output_stats(output_dir: &Path, top: usize) -> OutputStats {
+    let mut files: Vec<(String, u64)> = crate::verify::output_files(output_dir)
+        .into_iter()
+        .filter_map(|path| {
+            let size = std::fs::metadata(output_dir.join(&path)).ok()?.len();
+            Some((path, size))
+        })
+        .collect();
+
+    let mut extensions: HashMap<String, u64> = HashMap::new();
+    for (path, size) in &files {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let extension = name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_ascii_lowercase())
+            .unwrap_or_default();
+        *extensions.entry(extension).or_insert(0) += size;
+    }
+
+    let count = files.len();
+    let bytes = files.iter().map(|(_, size)| size).sum();
+    files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    files.truncate(top);
+    OutputStats {
+        files: count,
+        bytes,
+        bytes_per_extension: most_used(extensions),
+        largest_files: files,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SiteBuilder;
+    use std::fs;
+
+    #[test]
+    fn test_site_stats() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("bamboo.toml"),
+            "title = \"T\"\nbase_url = \"https://example.com\"\n",
+        )
+        .unwrap();
+        let posts = dir.path().join("content/posts");
+        fs::create_dir_all(&posts).unwrap();
+        fs::write(
+            dir.path().join("content/about.md"),
+            "+++\ntitle = \"About\"\n+++\n\none two three four five six",
+        )
+        .unwrap();
+        fs::write(
+            posts.join("2023-05-01-first.md"),
+            "+++\ntitle = \"First\"\ntags = [\"rust\"]\ncategories = [\"code\"]\n+++\n\none two",
+        )
+        .unwrap();
+        fs::write(
+            posts.join("2024-02-01-second.md"),
+            "+++\ntitle = \"Second\"\ntags = [\"rust\", \"web\"]\n+++\n\none two three",
+        )
+        .unwrap();
+        let site = SiteBuilder::new(dir.path()).build().unwrap();
+
+        let stats = site_stats(&site, 2);
+
+        assert_eq!(stats.posts, 2);
+        assert_eq!(stats.pages, 1);
+        assert_eq!(stats.words, 11);
+        assert_eq!(stats.post_words, 5);
+        assert_eq!(stats.average_reading_time, 1.0);
+        assert_eq!(stats.posts_per_year, BTreeMap::from([(2023, 1), (2024, 1)]));
+        assert_eq!(
+            stats.posts_per_tag,
+            [("rust".to_string(), 2), ("web".to_string(), 1)]
+        );
+        assert_eq!(stats.posts_per_category, [("code".to_string(), 1)]);
+        let largest: Vec<(&str, usize)> = stats
+            .largest_pages
+            .iter()
+            .map(|page| (page.title.as_str(), page.words))
+            .collect();
+        assert_eq!(largest, [("About", 6), ("Second", 3)]);
+    }
+
+    #[test]
+    fn test_output_stats() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("posts")).unwrap();
+        fs::write(dir.path().join("index.html"), "a".repeat(10)).unwrap();
+        fs::write(dir.path().join("posts/index.html"), "a".repeat(30)).unwrap();
+        fs::write(dir.path().join("logo.PNG"), "a".repeat(20)).unwrap();
+
+        let stats = output_stats(dir.path(), 2);
+
+        assert_eq!(stats.files, 3);
+        assert_eq!(stats.bytes, 60);
+        assert_eq!(
+            stats.bytes_per_extension,
+            [("html".to_string(), 40), ("png".to_string(), 20)]
+        );
+        assert_eq!(
+            stats.largest_files,
+            [
+                ("posts/index.html".to_string(), 30),
+                ("logo.PNG".to_string(), 20)
+            ]
+        );
+    }
+}
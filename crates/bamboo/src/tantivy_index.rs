@@ -0,0 +1,237 @@
+//! `search_format = "tantivy"`: a [Tantivy](https://github.com/quickwit-oss/tantivy)
+//! full-text index of the search entries, for large documentation sites
+//! where scanning a JSON index in the browser is too slow. Only available
+//! when bamboo is built with the `tantivy` feature.
+//!
+//! The index is written into `search-index/` as Tantivy's own files
+//! (`meta.json` and one segment's component files), alongside
+//! [`TANTIVY_MANIFEST`], which lists them and names the language the text
+//! was analyzed for. The `bamboo-search-wasm` crate is the query side: a
+//! small WASM module the default search page loads from `/search-wasm/`,
+//! which fetches the listed files into memory and searches them.
+//!
+//! `title` and `tags` are indexed and stored, `body` is only indexed, and
+//! `url`, `date`, and `excerpt` are only stored. All indexed text goes
+//! through the [`TOKENIZER`] analyzer: split on non-alphanumeric
+//! characters, lowercased, and stemmed for the site language where
+//! Tantivy has a stemmer for it.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tantivy::directory::Directory;
+use tantivy::directory::error::OpenReadError;
+use tantivy::schema::{IndexRecordOption, STORED, Schema, TextFieldIndexing, TextOptions};
+use tantivy::tokenizer::{
+    Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer, TextAnalyzer,
+};
+use tantivy::{Index, IndexWriter, TantivyDocument};
+
+use crate::error::{BambooError, Result};
+use crate::search::{CHUNKED_INDEX_DIR, SearchEntry};
+use crate::search_language::SearchLanguage;
+use crate::sink::RenderSink;
+
+/// Name the text analyzer is registered under. Readers of the index must
+/// register [`analyzer`] under the same name.
+pub const TOKENIZER: &str = "bamboo";
+
+/// File in `search-index/` listing the index files, as a
+/// [`TantivyManifest`].
+pub const TANTIVY_MANIFEST: &str = "files.json";
+
+/// Memory the index writer may buffer before flushing a segment.
+const WRITER_MEMORY: usize = 50_000_000;
+
+/// `search-index/files.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TantivyManifest {
+    /// Primary language subtag the text was analyzed for, such as `en`, or
+    /// empty for no stemming.
+    pub language: String,
+    /// Index files under `search-index/`, `meta.json` first.
+    pub files: Vec<String>,
+}
+
+/// The analyzer for a primary language subtag: [`SimpleTokenizer`],
+/// tokens over 40 bytes dropped, lowercased, and stemmed when Tantivy
+/// has a stemmer for the language.
+pub fn analyzer(language: &str) -> TextAnalyzer {
+    let stemmer = match language {
+        "en" => Some(Language::English),
+        "fr" => Some(Language::French),
+        "de" => Some(Language::German),
+        "es" => Some(Language::Spanish),
+        "it" => Some(Language::Italian),
+        "pt" => Some(Language::Portuguese),
+        "nl" => Some(Language::Dutch),
+        _ => None,
+    };
+    let builder = TextAnalyzer::builder(SimpleTokenizer::default())
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser);
+    match stemmer {
+        Some(stemmer) => builder.filter(Stemmer::new(stemmer)).build(),
+        None => builder.build(),
+    }
+}
+
+/// The index schema: `title`, `tags`, and `body` indexed with
+/// [`TOKENIZER`], and `title`, `tags`, `url`, `date`, and `excerpt`
+/// stored.
+pub fn schema() -> Schema {
+    let indexing = TextFieldIndexing::default()
+        .set_tokenizer(TOKENIZER)
+        .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+    let indexed = TextOptions::default().set_indexing_options(indexing);
+    let mut builder = Schema::builder();
+    builder.add_text_field("title", indexed.clone() | STORED);
+    builder.add_text_field("tags", indexed.clone() | STORED);
+    builder.add_text_field("body", indexed);
+    builder.add_text_field("url", STORED);
+    builder.add_text_field("date", STORED);
+    builder.add_text_field("excerpt", STORED);
+    builder.build()
+}
+
+fn index_error(error: impl std::fmt::Display) -> BambooError {
+    BambooError::SearchIndex {
+        message: format!("tantivy: {error}"),
+    }
+}
+
+/// Builds the index of `entries` in memory, analyzed for `language`, and
+/// returns its files as `(name, contents)`, `meta.json` first.
+pub fn tantivy_index(
+    entries: &[SearchEntry],
+    language: &SearchLanguage,
+) -> Result<Vec<(String, Vec<u8>)>> {
+    let schema = schema();
+    let field = |name: &str| schema.get_field(name).map_err(index_error);
+    let (title, tags, body) = (field("title")?, field("tags")?, field("body")?);
+    let (url, date, excerpt) = (field("url")?, field("date")?, field("excerpt")?);
+
+    let index = Index::create_in_ram(schema.clone());
+    index
+        .tokenizers()
+        .register(TOKENIZER, analyzer(language.code));
+    let mut writer: IndexWriter = index
+        .writer_with_num_threads(1, WRITER_MEMORY)
+        .map_err(index_error)?;
+    for entry in entries {
+        let mut document = TantivyDocument::default();
+        document.add_text(title, &entry.title);
+        for tag in &entry.tags {
+            document.add_text(tags, tag);
+        }
+        document.add_text(body, &entry.content);
+        document.add_text(url, &entry.url);
+        document.add_text(date, &entry.date);
+        document.add_text(excerpt, &entry.excerpt);
+        writer.add_document(document).map_err(index_error)?;
+    }
+    writer.commit().map_err(index_error)?;
+    writer.wait_merging_threads().map_err(index_error)?;
+
+    let directory = index.directory();
+    let mut paths = vec![PathBuf::from("meta.json")];
+    for segment in index.searchable_segment_metas().map_err(index_error)? {
+        let mut segment_files: Vec<PathBuf> = segment.list_files().into_iter().collect();
+        segment_files.sort();
+        paths.extend(segment_files);
+    }
+    let mut files = Vec::new();
+    for path in paths {
+        match directory.atomic_read(&path) {
+            Ok(contents) => files.push((path.to_string_lossy().into_owned(), contents)),
+            Err(OpenReadError::FileDoesNotExist(_)) => {}
+            Err(error) => return Err(index_error(error)),
+        }
+    }
+    Ok(files)
+}
+
+/// Writes the index of `entries` and its [`TANTIVY_MANIFEST`] into
+/// `search-index/`.
+pub(crate) fn write_tantivy_index(
+    entries: &[SearchEntry],
+    language: &SearchLanguage,
+    output: &(impl RenderSink + ?Sized),
+) -> Result<()> {
+    let directory = Path::new(CHUNKED_INDEX_DIR);
+    let files = tantivy_index(entries, language)?;
+    for (name, contents) in &files {
+        output.write_file(&directory.join(name), contents)?;
+    }
+    let manifest = TantivyManifest {
+        language: language.code.to_string(),
+        files: files.into_iter().map(|(name, _)| name).collect(),
+    };
+    let json = serde_json::to_string(&manifest).map_err(std::io::Error::other)?;
+    output.write_file(&directory.join(TANTIVY_MANIFEST), json.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tantivy::collector::TopDocs;
+    use tantivy::directory::RamDirectory;
+    use tantivy::query::QueryParser;
+    use tantivy::schema::Value;
+
+    fn entry(title: &str, url: &str, content: &str) -> SearchEntry {
+        SearchEntry {
+            title: title.to_string(),
+            url: url.to_string(),
+            tags: vec!["guide".to_string()],
+            date: String::new(),
+            excerpt: String::new(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_write_tantivy_index() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let entries = [
+            entry("Deploying", "/deploying/", "How the site deploys to S3."),
+            entry("Themes", "/themes/", "Writing templates for a theme."),
+        ];
+        let language = SearchLanguage::for_language(Some("en"));
+
+        write_tantivy_index(&entries, &language, dir.path()).unwrap();
+
+        let index_dir = dir.path().join(CHUNKED_INDEX_DIR);
+        let manifest: TantivyManifest = serde_json::from_str(
+            &std::fs::read_to_string(index_dir.join(TANTIVY_MANIFEST)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(manifest.language, "en");
+        assert_eq!(manifest.files[0], "meta.json");
+        assert!(manifest.files.iter().any(|name| name.ends_with(".store")));
+
+        let directory = RamDirectory::create();
+        for name in &manifest.files {
+            let contents = std::fs::read(index_dir.join(name)).unwrap();
+            directory.atomic_write(Path::new(name), &contents).unwrap();
+        }
+        let index = Index::open(directory).unwrap();
+        index
+            .tokenizers()
+            .register(TOKENIZER, analyzer(&manifest.language));
+        let schema = index.schema();
+        let body = schema.get_field("body").unwrap();
+        let url = schema.get_field("url").unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let query = QueryParser::for_index(&index, vec![body])
+            .parse_query("deploying")
+            .unwrap();
+        let hits = searcher.search(&query, &TopDocs::with_limit(10)).unwrap();
+        assert_eq!(hits.len(), 1);
+        let document: TantivyDocument = searcher.doc(hits[0].1).unwrap();
+        assert_eq!(
+            document.get_first(url).and_then(|value| value.as_str()),
+            Some("/deploying/")
+        );
+    }
+}
@@ -1,19 +1,45 @@
+use crate::diagnostics::render_template;
 use crate::error::Result;
+use crate::pagination::insert_pagination;
 use crate::parsing::slugify;
+use crate::sink::RenderSink;
 use crate::theme::SiteMetadata;
 use crate::types::Site;
 use rayon::prelude::*;
 use serde::Serialize;
 use std::collections::HashMap;
-use std::fs;
 use std::path::Path;
 use tera::{Context, Tera};
 
 #[derive(Debug, Clone, Serialize)]
-struct TaxonomyInfo {
-    name: String,
-    slug: String,
-    count: usize,
+pub(crate) struct TaxonomyInfo {
+    pub(crate) name: String,
+    pub(crate) slug: String,
+    pub(crate) count: usize,
+}
+
+/// Every term of `taxonomy_name` used by a post, sorted by name: the terms
+/// that get a `/<taxonomy>/<slug>/` page.
+pub(crate) fn taxonomy_terms(site: &Site, taxonomy_name: &str) -> Vec<TaxonomyInfo> {
+    let mut terms: HashMap<String, TaxonomyInfo> = HashMap::new();
+    for term in site
+        .posts
+        .iter()
+        .filter_map(|post| post.taxonomies_map.get(taxonomy_name))
+        .flatten()
+    {
+        terms
+            .entry(slugify(term))
+            .or_insert_with_key(|slug| TaxonomyInfo {
+                name: term.clone(),
+                slug: slug.clone(),
+                count: 0,
+            })
+            .count += 1;
+    }
+    let mut terms: Vec<TaxonomyInfo> = terms.into_values().collect();
+    terms.sort_by(|a, b| a.name.cmp(&b.name));
+    terms
 }
 
 struct TaxonomyConfig<'a> {
@@ -52,7 +78,7 @@ pub(crate) fn render_all_taxonomies(
     tera: &Tera,
     site: &Site,
     metadata: &SiteMetadata,
-    output_dir: &Path,
+    output: &(impl RenderSink + ?Sized),
 ) -> Result<()> {
     for (taxonomy_name, taxonomy_definition) in &site.config.taxonomies {
         let singular = taxonomy_definition
@@ -123,7 +149,7 @@ pub(crate) fn render_all_taxonomies(
         };
 
         let taxonomy_name_owned = taxonomy_name.clone();
-        render_taxonomy_pages(tera, site, metadata, output_dir, config, |post| {
+        render_taxonomy_pages(tera, site, metadata, output, config, |post| {
             post.taxonomies_map
                 .get(&taxonomy_name_owned)
                 .into_iter()
@@ -137,7 +163,7 @@ fn render_taxonomy_pages<'a, F, I>(
     tera: &Tera,
     site: &'a Site,
     metadata: &SiteMetadata,
-    output_dir: &Path,
+    output: &(impl RenderSink + ?Sized),
     taxonomy_config: TaxonomyConfig,
     extract_terms: F,
 ) -> Result<()>
@@ -181,12 +207,10 @@ where
     context.insert("taxonomy_items", &taxonomy_items);
     context.insert("taxonomy_name", taxonomy_config.taxonomy_name);
 
-    let taxonomy_dir = output_dir.join(taxonomy_config.taxonomy_name);
-    let taxonomy_index = taxonomy_dir.join("index.html");
+    let taxonomy_dir = Path::new(taxonomy_config.taxonomy_name);
     let index_template = taxonomy_config.index_template_or_fallback(tera);
-    let rendered = tera.render(index_template, &context)?;
-    fs::create_dir_all(&taxonomy_dir)?;
-    fs::write(taxonomy_index, rendered)?;
+    let rendered = render_template(tera, index_template, &context)?;
+    output.write_file(&taxonomy_dir.join("index.html"), rendered.as_bytes())?;
 
     let posts_per_page = site.config.posts_per_page;
 
@@ -204,7 +228,12 @@ where
                 posts_per_page
             };
             let total_pages = posts.len().div_ceil(effective_per_page);
-            let base_url = site.config.base_url.trim_end_matches('/');
+            let root_url = format!(
+                "{}/{}/{}/",
+                site.config.base_url.trim_end_matches('/'),
+                taxonomy_config.taxonomy_name,
+                slug
+            );
 
             for page_number in 1..=total_pages {
                 let start = (page_number - 1) * effective_per_page;
@@ -219,45 +248,15 @@ where
                 context.insert("term_slug", &slug);
                 context.insert("taxonomy_name", taxonomy_config.taxonomy_name);
                 context.insert("posts", page_posts);
-                context.insert("current_page", &page_number);
-                context.insert("total_pages", &total_pages);
+                insert_pagination(&mut context, &root_url, page_number, total_pages);
 
-                if page_number > 1 {
-                    let prev_url = if page_number == 2 {
-                        format!("{}/{}/{}/", base_url, taxonomy_config.taxonomy_name, slug)
-                    } else {
-                        format!(
-                            "{}/{}/{}/page/{}/",
-                            base_url,
-                            taxonomy_config.taxonomy_name,
-                            slug,
-                            page_number - 1
-                        )
-                    };
-                    context.insert("prev_page_url", &prev_url);
-                }
-
-                if page_number < total_pages {
-                    let next_url = format!(
-                        "{}/{}/{}/page/{}/",
-                        base_url,
-                        taxonomy_config.taxonomy_name,
-                        slug,
-                        page_number + 1
-                    );
-                    context.insert("next_page_url", &next_url);
-                }
-
-                if page_number == 1 {
-                    let rendered = tera.render(item_template, &context)?;
-                    fs::create_dir_all(&term_dir)?;
-                    fs::write(term_dir.join("index.html"), rendered)?;
+                let rendered = render_template(tera, item_template, &context)?;
+                let page_dir = if page_number == 1 {
+                    term_dir.clone()
                 } else {
-                    let rendered = tera.render(item_template, &context)?;
-                    let page_dir = term_dir.join("page").join(page_number.to_string());
-                    fs::create_dir_all(&page_dir)?;
-                    fs::write(page_dir.join("index.html"), rendered)?;
-                }
+                    term_dir.join("page").join(page_number.to_string())
+                };
+                output.write_file(&page_dir.join("index.html"), rendered.as_bytes())?;
             }
 
             Ok(())
@@ -0,0 +1,485 @@
+//! Renders theme templates against a synthetic site so template errors and
+//! undefined variables surface in CI rather than on the first real build.
+//!
+//! The sample context mirrors what [`crate::ThemeEngine`] passes to each
+//! template kind (`page`, `post`, `posts`, pagination, taxonomy,
+//! collection, and feed variables) in one merged [`tera::Context`], so any theme
+//! template can be rendered without knowing which kind it is.
+
+use crate::error::{BambooError, IoContext, Result};
+use crate::parsing::{MarkdownRenderer, page_stats};
+use crate::theme::{ThemeEngine, site_metadata};
+use crate::types::{
+    Collection, CollectionConfig, CollectionItem, Content, Frontmatter, Note, Page, Post, Site,
+    SiteConfig,
+};
+use chrono::{TimeZone, Utc};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tera::Context;
+
+/// Upper bound on undefined-variable retries for a single template.
+const MAX_UNDEFINED_RETRIES: usize = 50;
+
+/// Outcome of rendering one template against the sample context.
+#[derive(Debug, Clone)]
+pub struct TemplateTestReport {
+    /// Template name, e.g. `post.html`.
+    pub template: String,
+    /// Rendered output, if rendering eventually succeeded.
+    pub output: Option<String>,
+    /// Variables the template referenced that the context didn't provide,
+    /// in the order they were encountered (e.g. `page.subtitle`).
+    pub undefined_variables: Vec<String>,
+    /// The first error that wasn't an undefined variable, with its causes
+    /// joined by `: `.
+    pub error: Option<String>,
+}
+
+impl TemplateTestReport {
+    /// `true` if the template rendered with no undefined variables and no
+    /// errors.
+    pub fn is_ok(&self) -> bool {
+        self.output.is_some() && self.undefined_variables.is_empty() && self.error.is_none()
+    }
+}
+
+/// Builds a small, fully-populated [`Site`]: a home page, one page, two
+/// tagged posts, a note, and a `projects` collection with one item.
+pub fn sample_site() -> Result<Site> {
+    let config: SiteConfig = toml::from_str(
+        r#"
+title = "Sample Site"
+base_url = "https://example.com"
+description = "A synthetic site for template tests"
+author = "Sample Author"
+language = "en"
+"#,
+    )
+    .map_err(|error| BambooError::TomlParse {
+        path: PathBuf::from("<sample>"),
+        message: error.to_string(),
+    })?;
+
+    let renderer = MarkdownRenderer::new();
+    let home = Page {
+        content: sample_content(
+            &renderer,
+            "index",
+            "Home",
+            "/",
+            "Welcome to the sample site.",
+        )?,
+        draft: false,
+        redirect_from: Vec::new(),
+    };
+    let about = Page {
+        content: sample_content(
+            &renderer,
+            "about",
+            "About",
+            "/about/",
+            "## Background\n\nAn about page.",
+        )?,
+        draft: false,
+        redirect_from: Vec::new(),
+    };
+    let posts = vec![
+        sample_post(&renderer, "second-post", "Second Post", 2024, 2)?,
+        sample_post(&renderer, "first-post", "First Post", 2024, 1)?,
+    ];
+    let note = Note {
+        content: sample_content(
+            &renderer,
+            "2024-03-01-090000",
+            "A short sample note.",
+            "/notes/2024-03-01-090000/",
+            "A short sample note.",
+        )?,
+        date: Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap(),
+        draft: false,
+        tags: vec!["sample".to_string()],
+    };
+    let collections = HashMap::from([(
+        "projects".to_string(),
+        Collection {
+            name: "projects".to_string(),
+            items: vec![CollectionItem {
+                content: sample_content(
+                    &renderer,
+                    "sample-project",
+                    "Sample Project",
+                    "/projects/sample-project/",
+                    "A sample collection item.",
+                )?,
+            }],
+            config: CollectionConfig::default(),
+            index: None,
+        },
+    )]);
+
+    Ok(Site {
+        config,
+        home: Some(home),
+        pages: vec![about],
+        posts,
+        notes: vec![note],
+        collections,
+        data: HashMap::from([(
+            "nav".to_string(),
+            json!({"links": [{"title": "About", "url": "/about/"}]}),
+        )]),
+        assets: Vec::new(),
+        image_requests: Vec::new(),
+    })
+}
+
+fn sample_content(
+    renderer: &MarkdownRenderer,
+    slug: &str,
+    title: &str,
+    url: &str,
+    markdown: &str,
+) -> Result<Content> {
+    let rendered = renderer.render(markdown)?;
+    let stats = page_stats(&rendered.html, &rendered.toc);
+    let mut frontmatter = Frontmatter::default();
+    frontmatter
+        .raw
+        .insert("title".to_string(), Value::String(title.to_string()));
+    Ok(Content {
+        slug: slug.to_string(),
+        title: title.to_string(),
+        html: rendered.html,
+        raw_content: markdown.to_string(),
+        frontmatter,
+        path: PathBuf::from(url.trim_start_matches('/')).join("index.html"),
+        source_path: match url.trim_matches('/') {
+            "" => PathBuf::from("_index.md"),
+            path => PathBuf::from(format!("{path}.md")),
+        },
+        modified: None,
+        template: None,
+        weight: 0,
+        word_count: stats.word_count,
+        reading_time: stats.reading_time,
+        toc: rendered.toc,
+        stats,
+        lang: None,
+        translations: Vec::new(),
+        url: url.to_string(),
+    })
+}
+
+fn sample_post(
+    renderer: &MarkdownRenderer,
+    slug: &str,
+    title: &str,
+    year: i32,
+    month: u32,
+) -> Result<Post> {
+    let markdown = "An opening paragraph.\n\n## Details\n\n```rust\nfn main() {}\n```";
+    Ok(Post {
+        content: sample_content(renderer, slug, title, &format!("/posts/{slug}/"), markdown)?,
+        date: Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).unwrap(),
+        excerpt: Some("An opening paragraph.".to_string()),
+        draft: false,
+        tags: vec!["sample".to_string()],
+        categories: vec!["notes".to_string()],
+        taxonomies_map: HashMap::from([
+            ("tags".to_string(), vec!["sample".to_string()]),
+            ("categories".to_string(), vec!["notes".to_string()]),
+        ]),
+        redirect_from: Vec::new(),
+        discussion: None,
+        link_preview: None,
+    })
+}
+
+/// Builds a context containing every variable any built-in render step
+/// provides, populated from `site`.
+pub fn sample_context(site: &Site) -> Context {
+    let mut context = Context::new();
+    context.insert("site", &site_metadata(site));
+    context.insert("math", &false);
+
+    if let Some(ref home) = site.home {
+        context.insert("home", home);
+    }
+    if let Some(page) = site.pages.first().or(site.home.as_ref()) {
+        context.insert("page", page);
+        context.insert("content", &page.content);
+        context.insert("raw_content", &page.content.raw_content);
+        context.insert("output_format", "txt");
+        let meta = crate::seo::content_seo(site, &page.content, false, None);
+        context.insert("head", &crate::head::Head::new(site, &meta));
+        context.insert("seo", &meta);
+    }
+    if let Some(post) = site.posts.first() {
+        context.insert("post", post);
+    }
+    if let Some(prev) = site.posts.get(1) {
+        context.insert("prev_post", prev);
+    }
+    context.insert(
+        "related_posts",
+        &site.posts.iter().skip(1).collect::<Vec<_>>(),
+    );
+    context.insert("posts", &site.posts);
+    if let Some(note) = site.notes.first() {
+        context.insert("note", note);
+    }
+    context.insert("notes", &site.notes);
+    context.insert(
+        "search_index_url",
+        &format!(
+            "{}/search-index.json.gz?v=0123abcd",
+            site.config.base_url.trim_end_matches('/')
+        ),
+    );
+    crate::pagination::insert_pagination(
+        &mut context,
+        &format!("{}/", site.config.base_url.trim_end_matches('/')),
+        1,
+        2,
+    );
+
+    if let Some(collection) = site.collections.values().next() {
+        context.insert("collection", collection);
+        context.insert("collection_name", &collection.name);
+        context.insert("items", &collection.items);
+        if let Some(item) = collection.items.first() {
+            context.insert("item", item);
+        }
+    }
+
+    let terms = json!([{"name": "sample", "slug": "sample", "count": site.posts.len()}]);
+    context.insert("taxonomy_items", &terms);
+    context.insert("taxonomy_name", "tags");
+    context.insert("tags", &terms);
+    context.insert("categories", &terms);
+    for key in ["term", "tag", "category"] {
+        context.insert(format!("{key}_name"), "sample");
+        context.insert(format!("{key}_slug"), "sample");
+    }
+    context.extend(crate::feeds::main_feed_context(site));
+    context.insert("urls", &crate::sitemap::sitemap_entries(site));
+    context.insert(
+        "newsletter",
+        &crate::newsletter::newsletter(site, &site.config.newsletter.clone().unwrap_or_default()),
+    );
+    context
+}
+
+/// Deep-merges the JSON object in `path` into `context`, so a theme's CI can supply variables its templates need
+/// beyond the sample site (e.g. `site.config.extra` values).
+pub fn merge_context_file(context: &mut Context, path: &Path) -> Result<()> {
+    let content = fs::read_to_string(path).io_context("reading context", path)?;
+    let json_error = |message: String| BambooError::JsonParse {
+        path: path.to_path_buf(),
+        message,
+    };
+    let value: Value =
+        serde_json::from_str(&content).map_err(|error| json_error(error.to_string()))?;
+    let Value::Object(map) = value else {
+        return Err(json_error("expected a JSON object".to_string()));
+    };
+    let mut merged = std::mem::take(context).into_json();
+    if let Some(object) = merged.as_object_mut() {
+        for (key, value) in map {
+            merge_json(object.entry(key).or_insert(Value::Null), value);
+        }
+    }
+    *context = Context::from_value(merged)?;
+    Ok(())
+}
+
+/// Deep-merges `overlay` into `base`: objects merge key by key, anything
+/// else replaces.
+fn merge_json(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge_json(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Renders `template` with `context`. Each undefined variable is recorded,
+/// stubbed with an empty string, and rendering retried, so one run reports
+/// every missing variable rather than only the first.
+pub fn test_template(
+    engine: &ThemeEngine,
+    template: &str,
+    context: &Context,
+) -> TemplateTestReport {
+    let mut report = TemplateTestReport {
+        template: template.to_string(),
+        output: None,
+        undefined_variables: Vec::new(),
+        error: None,
+    };
+    let mut value = context.clone().into_json();
+
+    for _ in 0..MAX_UNDEFINED_RETRIES {
+        let attempt = Context::from_value(value.clone()).unwrap_or_default();
+        match engine.tera().render(template, &attempt) {
+            Ok(output) => {
+                report.output = Some(output);
+                return report;
+            }
+            Err(error) => {
+                let message = error_chain(&error);
+                match undefined_variable(&message) {
+                    Some(variable)
+                        if !report.undefined_variables.contains(&variable)
+                            && stub_variable(&mut value, &variable) =>
+                    {
+                        report.undefined_variables.push(variable);
+                    }
+                    _ => {
+                        report.error = Some(message);
+                        return report;
+                    }
+                }
+            }
+        }
+    }
+    report.error = Some(format!(
+        "gave up after {MAX_UNDEFINED_RETRIES} undefined variables"
+    ));
+    report
+}
+
+/// Joins an error and its sources into one line.
+pub(crate) fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+fn undefined_variable(message: &str) -> Option<String> {
+    let start = message.find("Variable `")? + "Variable `".len();
+    let rest = &message[start..];
+    let end = rest.find("` not found in context")?;
+    Some(rest[..end].to_string())
+}
+
+/// Sets the dotted `path` in `value` to an empty string, creating
+/// intermediate objects. Returns `false` if a segment traverses a
+/// non-object value.
+fn stub_variable(value: &mut Value, path: &str) -> bool {
+    let mut current = value;
+    let segments: Vec<&str> = path.split('.').collect();
+    for (index, segment) in segments.iter().enumerate() {
+        let Some(object) = current.as_object_mut() else {
+            return false;
+        };
+        if index == segments.len() - 1 {
+            object.insert(segment.to_string(), Value::String(String::new()));
+            return true;
+        }
+        current = object
+            .entry(segment.to_string())
+            .or_insert_with(|| Value::Object(Default::default()));
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_templates_render_against_sample_site() {
+        let engine = ThemeEngine::new("default").unwrap();
+        let site = sample_site().unwrap();
+        let context = sample_context(&site);
+        for template in [
+            "index.html",
+            "page.html",
+            "post.html",
+            "tag.html",
+            "collection.html",
+            "rss.xml",
+            "atom.xml",
+            "sitemap.xml",
+            "default.txt",
+            "default.json",
+            "newsletter.html",
+            "newsletter.txt",
+        ] {
+            let report = test_template(&engine, template, &context);
+            assert!(report.is_ok(), "{template}: {report:?}");
+        }
+    }
+
+    #[test]
+    fn test_reports_every_undefined_variable() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("templates")).unwrap();
+        std::fs::write(
+            dir.path().join("templates/broken.html"),
+            "{{ page.subtitle }} {{ missing_global }} {{ page.title }}",
+        )
+        .unwrap();
+        let engine = ThemeEngine::new_with_overrides("default", dir.path()).unwrap();
+        let site = sample_site().unwrap();
+
+        let report = test_template(&engine, "broken.html", &sample_context(&site));
+        assert_eq!(
+            report.undefined_variables,
+            vec!["page.subtitle".to_string(), "missing_global".to_string()]
+        );
+        assert!(report.error.is_none());
+        assert!(report.output.unwrap().contains("About"));
+    }
+
+    #[test]
+    fn test_merge_context_file_deep_merges() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("context.json");
+        std::fs::write(
+            &path,
+            r#"{"site": {"config": {"extra": {"github": "https://github.com/x"}}}, "banner": "hi"}"#,
+        )
+        .unwrap();
+        let site = sample_site().unwrap();
+        let mut context = sample_context(&site);
+        merge_context_file(&mut context, &path).unwrap();
+
+        let json = context.into_json();
+        assert_eq!(
+            json["site"]["config"]["extra"]["github"],
+            "https://github.com/x"
+        );
+        assert_eq!(json["site"]["config"]["title"], "Sample Site");
+        assert_eq!(json["banner"], "hi");
+    }
+
+    #[test]
+    fn test_reports_non_variable_errors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("templates")).unwrap();
+        std::fs::write(
+            dir.path().join("templates/bad_filter.html"),
+            "{{ page.title | round }}",
+        )
+        .unwrap();
+        let engine = ThemeEngine::new_with_overrides("default", dir.path()).unwrap();
+        let site = sample_site().unwrap();
+
+        let report = test_template(&engine, "bad_filter.html", &sample_context(&site));
+        assert!(!report.is_ok());
+        assert!(report.error.is_some());
+    }
+}
@@ -0,0 +1,93 @@
+//! A small in-memory [`Site`] for the unit tests of generators that only
+//! read the loaded site, such as `_headers`, `llms.txt`, and OPML.
+
+use crate::types::{
+    Collection, CollectionConfig, CollectionItem, Content, Frontmatter, Note, Page, Post, Site,
+    SiteConfig,
+};
+use chrono::{TimeZone, Utc};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// A site titled "Test" at `https://example.com` with an About page, two
+/// posts (newest first), a note, and a `projects` collection with one
+/// item. `config` is appended to that `bamboo.toml`, so each test sets
+/// the keys and section it exercises
+/// (`"[headers.\"/*\"]\nX-Frame-Options = \"DENY\""`).
+pub(crate) fn site(config: &str) -> Site {
+    let config: SiteConfig = toml::from_str(&format!(
+        "title = \"Test\"\nbase_url = \"https://example.com\"\n{config}"
+    ))
+    .unwrap();
+
+    let post = |slug: &str, title: &str, month: u32| Post {
+        content: content(slug, title, &format!("/posts/{slug}/"), "A post."),
+        date: Utc.with_ymd_and_hms(2024, month, 1, 0, 0, 0).unwrap(),
+        excerpt: None,
+        draft: false,
+        tags: Vec::new(),
+        categories: Vec::new(),
+        taxonomies_map: HashMap::new(),
+        redirect_from: Vec::new(),
+        discussion: None,
+        link_preview: None,
+    };
+    let project = Collection {
+        name: "projects".to_string(),
+        items: vec![CollectionItem {
+            content: content("bamboo", "Bamboo", "/projects/bamboo/", "A project."),
+        }],
+        config: CollectionConfig::default(),
+        index: None,
+    };
+
+    Site {
+        config,
+        home: None,
+        pages: vec![Page {
+            content: content("about", "About", "/about/", "An about page."),
+            draft: false,
+            redirect_from: Vec::new(),
+        }],
+        posts: vec![post("second", "Second", 2), post("first", "First", 1)],
+        notes: vec![Note {
+            content: content(
+                "2024-03-01-090000",
+                "A note.",
+                "/notes/2024-03-01-090000/",
+                "A note.",
+            ),
+            date: Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap(),
+            draft: false,
+            tags: Vec::new(),
+        }],
+        collections: HashMap::from([("projects".to_string(), project)]),
+        data: HashMap::new(),
+        assets: Vec::new(),
+        image_requests: Vec::new(),
+    }
+}
+
+fn content(slug: &str, title: &str, url: &str, text: &str) -> Content {
+    let mut frontmatter = Frontmatter::default();
+    frontmatter.raw.insert("title".to_string(), title.into());
+    Content {
+        slug: slug.to_string(),
+        title: title.to_string(),
+        html: format!("<p>{text}</p>"),
+        raw_content: text.to_string(),
+        frontmatter,
+        path: PathBuf::from(url.trim_start_matches('/')).join("index.html"),
+        source_path: PathBuf::from(format!("{}.md", url.trim_matches('/'))),
+        modified: None,
+        template: None,
+        weight: 0,
+        word_count: text.split_whitespace().count(),
+        reading_time: 1,
+        toc: Vec::new(),
+        stats: Default::default(),
+        lang: None,
+        translations: Vec::new(),
+        url: url.to_string(),
+    }
+}
@@ -3,19 +3,30 @@
 //! `include_str!`) or a user-supplied theme directory.
 
 use crate::assets::AssetConfig;
+use crate::cache::{RenderTarget, TemplateDependencies};
+use crate::diagnostics::render_template;
 use crate::error::Result;
+use crate::extensions::TemplateExtensions;
 use crate::feeds;
+use crate::generated_pages;
+use crate::head;
 use crate::images;
+use crate::outputs;
+use crate::pagination::insert_pagination;
 use crate::parsing::slugify;
 use crate::redirects;
 use crate::search;
+use crate::seo;
+use crate::sink::RenderSink;
 use crate::sitemap;
+use crate::social_images;
 use crate::types::{Asset, Site};
 use rayon::prelude::*;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 use tera::{Context, Tera};
 use walkdir::WalkDir;
 
@@ -48,6 +59,19 @@ const DEFAULT_TAXONOMY_TERM_TEMPLATE: &str =
     include_str!("../themes/default/templates/taxonomy_term.html");
 const DEFAULT_PAGINATION_TEMPLATE: &str =
     include_str!("../themes/default/templates/pagination.html");
+const DEFAULT_NOTE_TEMPLATE: &str = include_str!("../themes/default/templates/note.html");
+const DEFAULT_NOTES_TEMPLATE: &str = include_str!("../themes/default/templates/notes.html");
+const DEFAULT_RSS_TEMPLATE: &str = include_str!("../themes/default/templates/rss.xml");
+const DEFAULT_ATOM_TEMPLATE: &str = include_str!("../themes/default/templates/atom.xml");
+const DEFAULT_SITEMAP_TEMPLATE: &str = include_str!("../themes/default/templates/sitemap.xml");
+const DEFAULT_TXT_OUTPUT_TEMPLATE: &str = include_str!("../themes/default/templates/default.txt");
+const DEFAULT_JSON_OUTPUT_TEMPLATE: &str = include_str!("../themes/default/templates/default.json");
+const DEFAULT_NEWSLETTER_HTML_TEMPLATE: &str =
+    include_str!("../themes/default/templates/newsletter.html");
+const DEFAULT_NEWSLETTER_TXT_TEMPLATE: &str =
+    include_str!("../themes/default/templates/newsletter.txt");
+const DEFAULT_SEO_PARTIAL: &str = include_str!("../themes/default/templates/partials/seo.html");
+const DEFAULT_HEAD_PARTIAL: &str = include_str!("../themes/default/templates/partials/head.html");
 const DEFAULT_404_TEMPLATE: &str = include_str!("../themes/default/templates/404.html");
 const DEFAULT_HEADER_PARTIAL: &str =
     include_str!("../themes/default/templates/partials/header.html");
@@ -70,20 +94,103 @@ const DEFAULT_POST_RELATED_PARTIAL: &str =
     include_str!("../themes/default/templates/partials/post_related.html");
 const DEFAULT_POST_PREV_NEXT_PARTIAL: &str =
     include_str!("../themes/default/templates/partials/post_prev_next.html");
+const DEFAULT_POST_DISCUSSION_PARTIAL: &str =
+    include_str!("../themes/default/templates/partials/post_discussion.html");
+const DEFAULT_POST_LINK_PREVIEW_PARTIAL: &str =
+    include_str!("../themes/default/templates/partials/post_link_preview.html");
 const DEFAULT_POST_EDIT_LINK_PARTIAL: &str =
     include_str!("../themes/default/templates/partials/post_edit_link.html");
 const DEFAULT_PAGE_EDIT_LINK_PARTIAL: &str =
     include_str!("../themes/default/templates/partials/page_edit_link.html");
+const DEFAULT_PAGER_PARTIAL: &str = include_str!("../themes/default/templates/partials/pager.html");
 const DEFAULT_SEARCH_TEMPLATE: &str = include_str!("../themes/default/templates/search.html");
 const DEFAULT_STYLESHEET: &str = include_str!("../themes/default/static/style.css");
 
+/// Every template of the built-in default theme, by name. `rss.xml`,
+/// `atom.xml`, and `sitemap.xml` are added separately so directory themes
+/// get them too.
+const BUILTIN_TEMPLATES: [(&str, &str); 42] = [
+    ("base.html", DEFAULT_BASE_TEMPLATE),
+    ("index.html", DEFAULT_INDEX_TEMPLATE),
+    ("page.html", DEFAULT_PAGE_TEMPLATE),
+    ("post.html", DEFAULT_POST_TEMPLATE),
+    ("collection.html", DEFAULT_COLLECTION_TEMPLATE),
+    ("collection_item.html", DEFAULT_COLLECTION_ITEM_TEMPLATE),
+    ("slideshow.html", DEFAULT_SLIDESHOW_TEMPLATE),
+    ("docs.html", DEFAULT_DOCS_TEMPLATE),
+    ("portfolio.html", DEFAULT_PORTFOLIO_TEMPLATE),
+    ("landing.html", DEFAULT_LANDING_TEMPLATE),
+    ("changelog.html", DEFAULT_CHANGELOG_TEMPLATE),
+    ("book.html", DEFAULT_BOOK_TEMPLATE),
+    ("archive.html", DEFAULT_ARCHIVE_TEMPLATE),
+    (
+        "categories_grouped.html",
+        DEFAULT_CATEGORIES_GROUPED_TEMPLATE,
+    ),
+    ("tags_grouped.html", DEFAULT_TAGS_GROUPED_TEMPLATE),
+    ("tags.html", DEFAULT_TAGS_TEMPLATE),
+    ("tag.html", DEFAULT_TAG_TEMPLATE),
+    ("categories.html", DEFAULT_CATEGORIES_TEMPLATE),
+    ("category.html", DEFAULT_CATEGORY_TEMPLATE),
+    ("taxonomy.html", DEFAULT_TAXONOMY_TEMPLATE),
+    ("taxonomy_term.html", DEFAULT_TAXONOMY_TERM_TEMPLATE),
+    ("pagination.html", DEFAULT_PAGINATION_TEMPLATE),
+    ("note.html", DEFAULT_NOTE_TEMPLATE),
+    ("notes.html", DEFAULT_NOTES_TEMPLATE),
+    ("404.html", DEFAULT_404_TEMPLATE),
+    ("partials/header.html", DEFAULT_HEADER_PARTIAL),
+    ("partials/footer.html", DEFAULT_FOOTER_PARTIAL),
+    ("partials/nav.html", DEFAULT_NAV_PARTIAL),
+    (
+        "partials/author_profile.html",
+        DEFAULT_AUTHOR_PROFILE_PARTIAL,
+    ),
+    (
+        "partials/post_breadcrumbs.html",
+        DEFAULT_POST_BREADCRUMBS_PARTIAL,
+    ),
+    ("partials/post_header.html", DEFAULT_POST_HEADER_PARTIAL),
+    (
+        "partials/post_hero_image.html",
+        DEFAULT_POST_HERO_IMAGE_PARTIAL,
+    ),
+    ("partials/post_toc.html", DEFAULT_POST_TOC_PARTIAL),
+    ("partials/post_share.html", DEFAULT_POST_SHARE_PARTIAL),
+    ("partials/post_related.html", DEFAULT_POST_RELATED_PARTIAL),
+    (
+        "partials/post_prev_next.html",
+        DEFAULT_POST_PREV_NEXT_PARTIAL,
+    ),
+    (
+        "partials/post_discussion.html",
+        DEFAULT_POST_DISCUSSION_PARTIAL,
+    ),
+    (
+        "partials/post_link_preview.html",
+        DEFAULT_POST_LINK_PREVIEW_PARTIAL,
+    ),
+    (
+        "partials/post_edit_link.html",
+        DEFAULT_POST_EDIT_LINK_PARTIAL,
+    ),
+    (
+        "partials/page_edit_link.html",
+        DEFAULT_PAGE_EDIT_LINK_PARTIAL,
+    ),
+    ("partials/pager.html", DEFAULT_PAGER_PARTIAL),
+    ("search.html", DEFAULT_SEARCH_TEMPLATE),
+];
+
 #[derive(Debug, Clone, Serialize)]
 pub(crate) struct SiteMetadata<'a> {
     config: &'a crate::types::SiteConfig,
     pages: &'a [crate::types::Page],
     posts: &'a [crate::types::Post],
+    notes: &'a [crate::types::Note],
     data: &'a HashMap<String, serde_json::Value>,
     collections: &'a HashMap<String, crate::types::Collection>,
+    seo: seo::SeoMeta,
+    head: head::Head,
 }
 
 fn related_posts<'a>(
@@ -126,11 +233,33 @@ pub(crate) fn site_metadata(site: &Site) -> SiteMetadata<'_> {
         config: &site.config,
         pages: &site.pages,
         posts: &site.posts,
+        notes: &site.notes,
         data: &site.data,
         collections: &site.collections,
+        seo: seo::site_seo(site),
+        head: head::site_head(site),
     }
 }
 
+/// What a render produced besides the files themselves, for steps that
+/// run on the output afterwards.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderReport {
+    /// Static files renamed by fingerprinting, from output path to
+    /// fingerprinted path. Empty for sinks without a directory; incremental
+    /// renders return the mapping given to
+    /// [`ThemeEngine::set_asset_paths`]. Pass it to
+    /// [`generate_build_manifest`](crate::build_manifest::generate_build_manifest)
+    /// once the output is final.
+    pub fingerprinted: HashMap<String, String>,
+    /// Totals from processing images, when `[images]` is configured and
+    /// the render processed them.
+    pub images: Option<images::ImageStats>,
+    /// Problems that didn't fail the render, such as fonts with
+    /// `subset = true` that had to ship whole.
+    pub warnings: Vec<String>,
+}
+
 /// Renders a loaded [`Site`] to disk using Tera templates from a theme.
 ///
 /// # Example
@@ -147,6 +276,10 @@ pub struct ThemeEngine {
     theme_static_dir: Option<PathBuf>,
     override_static_dir: Option<PathBuf>,
     is_builtin_default: bool,
+    base_url: Arc<RwLock<String>>,
+    asset_paths: Arc<RwLock<HashMap<String, String>>>,
+    cache_dir: Option<PathBuf>,
+    image_progress: Option<Arc<images::ImageProgressFn<'static>>>,
 }
 
 impl ThemeEngine {
@@ -176,12 +309,281 @@ impl ThemeEngine {
         Ok(engine)
     }
 
+    /// Keeps processed images in `cache_dir` (normally
+    /// [`crate::cache_dir`] of the project), so later builds copy the
+    /// variants of unchanged images instead of re-encoding them.
+    pub fn set_cache_dir(&mut self, cache_dir: &Path) {
+        self.cache_dir = Some(cache_dir.to_path_buf());
+    }
+
+    /// Seeds `asset_url` with the fingerprinted names from the last full
+    /// render (its [`RenderReport::fingerprinted`]), for incremental
+    /// renders, which don't copy or hash static files themselves.
+    pub fn set_asset_paths(&mut self, asset_paths: HashMap<String, String>) {
+        if let Ok(mut current) = self.asset_paths.write() {
+            *current = asset_paths;
+        }
+    }
+
+    /// Calls `progress` as each image starts processing, unless the
+    /// site's `[images]` sets `progress = false`.
+    pub fn set_image_progress(
+        &mut self,
+        progress: impl Fn(&images::ImageProgress<'_>) + Send + Sync + 'static,
+    ) {
+        self.image_progress = Some(Arc::new(progress));
+    }
+
+    /// Names of every loaded template, sorted.
+    pub fn template_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.tera.get_template_names().map(str::to_string).collect();
+        names.sort();
+        names
+    }
+
+    pub(crate) fn tera(&self) -> &Tera {
+        &self.tera
+    }
+
+    /// Registers a Tera filter for use in theme templates. Call before
+    /// rendering; a name matching a built-in filter replaces it.
+    pub fn register_filter(&mut self, name: &str, filter: impl tera::Filter + 'static) {
+        self.tera.register_filter(name, filter);
+    }
+
+    /// Registers a Tera global function for use in theme templates.
+    pub fn register_function(&mut self, name: &str, function: impl tera::Function + 'static) {
+        self.tera.register_function(name, function);
+    }
+
+    /// Registers a Tera tester for use in `{% if value is name %}`.
+    pub fn register_tester(&mut self, name: &str, tester: impl tera::Test + 'static) {
+        self.tera.register_tester(name, tester);
+    }
+
+    /// Registers every filter, function, and tester in `extensions`. Pass
+    /// the same set to [`crate::SiteBuilder::template_extensions`] so
+    /// shortcodes and render hooks see them too.
+    pub fn register_extensions(&mut self, extensions: &TemplateExtensions) {
+        extensions.apply(&mut self.tera);
+    }
+
+    /// Maps every template (including partials, parents, macro files, and
+    /// shortcode templates) to the render targets whose output used it
+    /// when rendering `site`. Persist the result in
+    /// [`BuildState`](crate::cache::BuildState) so
+    /// [`classify_changes`](crate::cache::classify_changes) can re-render
+    /// only the affected pages when a template is edited.
+    pub fn template_dependencies(&self, site: &Site) -> TemplateDependencies {
+        let mut dependencies = TemplateDependencies::default();
+
+        let index_template = site
+            .home
+            .as_ref()
+            .and_then(|home| home.content.template.as_deref())
+            .unwrap_or("index.html");
+        let index_target = RenderTarget::Page("index".to_string());
+        self.record_template(&mut dependencies, index_template, &index_target);
+        if let Some(home) = &site.home {
+            self.record_outputs(
+                &mut dependencies,
+                site,
+                &home.content,
+                index_template,
+                &index_target,
+            );
+            self.record_shortcodes(&mut dependencies, &home.content, &[index_target]);
+        }
+
+        for page in &site.pages {
+            if page.content.slug == "404" {
+                continue;
+            }
+            let template = page.content.template.as_deref().unwrap_or("page.html");
+            let target = RenderTarget::Page(page.content.slug.clone());
+            self.record_template(&mut dependencies, template, &target);
+            self.record_outputs(&mut dependencies, site, &page.content, template, &target);
+            self.record_shortcodes(
+                &mut dependencies,
+                &page.content,
+                &[target, RenderTarget::SearchIndex],
+            );
+        }
+
+        for post in &site.posts {
+            let template = post.content.template.as_deref().unwrap_or("post.html");
+            let target = RenderTarget::Post(post.content.slug.clone());
+            self.record_template(&mut dependencies, template, &target);
+            self.record_outputs(&mut dependencies, site, &post.content, template, &target);
+            self.record_shortcodes(
+                &mut dependencies,
+                &post.content,
+                &[target, RenderTarget::Feeds, RenderTarget::SearchIndex],
+            );
+        }
+
+        for generated in &site.config.generated_pages {
+            // Generated pages are only rendered on full builds.
+            self.record_template(&mut dependencies, &generated.template, &RenderTarget::All);
+        }
+
+        if !site.notes.is_empty() {
+            self.record_template(&mut dependencies, "notes.html", &RenderTarget::Notes);
+        }
+        for note in &site.notes {
+            let template = note.content.template.as_deref().unwrap_or("note.html");
+            self.record_template(&mut dependencies, template, &RenderTarget::Notes);
+            self.record_outputs(
+                &mut dependencies,
+                site,
+                &note.content,
+                template,
+                &RenderTarget::Notes,
+            );
+            self.record_shortcodes(
+                &mut dependencies,
+                &note.content,
+                &[RenderTarget::Notes, RenderTarget::Feeds],
+            );
+        }
+
+        for (name, collection) in &site.collections {
+            let target = RenderTarget::Collection(name.clone());
+            self.record_template(&mut dependencies, "collection.html", &target);
+            for item in &collection.items {
+                let template = item
+                    .content
+                    .template
+                    .as_deref()
+                    .filter(|template| self.tera.templates.contains_key(*template))
+                    .unwrap_or(
+                        if self.tera.templates.contains_key("collection_item.html") {
+                            "collection_item.html"
+                        } else {
+                            "page.html"
+                        },
+                    );
+                self.record_template(&mut dependencies, template, &target);
+                self.record_outputs(&mut dependencies, site, &item.content, template, &target);
+                self.record_shortcodes(
+                    &mut dependencies,
+                    &item.content,
+                    &[target.clone(), RenderTarget::SearchIndex],
+                );
+            }
+        }
+
+        self.record_template(&mut dependencies, "404.html", &RenderTarget::All);
+        self.record_template(
+            &mut dependencies,
+            "pagination.html",
+            &RenderTarget::Pagination,
+        );
+        self.record_template(&mut dependencies, "search.html", &RenderTarget::SearchIndex);
+        for template in [feeds::RSS_TEMPLATE, feeds::ATOM_TEMPLATE] {
+            self.record_template(&mut dependencies, template, &RenderTarget::Feeds);
+        }
+        self.record_template(
+            &mut dependencies,
+            sitemap::SITEMAP_TEMPLATE,
+            &RenderTarget::Sitemap,
+        );
+
+        let mut taxonomy_templates = vec![
+            "tags.html",
+            "tag.html",
+            "categories.html",
+            "category.html",
+            "taxonomy.html",
+            "taxonomy_term.html",
+        ];
+        for definition in site.config.taxonomies.values() {
+            taxonomy_templates.extend(definition.index_template.as_deref());
+            taxonomy_templates.extend(definition.term_template.as_deref());
+        }
+        for template in taxonomy_templates {
+            self.record_template(&mut dependencies, template, &RenderTarget::AllTaxonomies);
+        }
+
+        dependencies
+    }
+
+    /// Records `target` against `root` and everything it extends,
+    /// includes, or imports macros from.
+    fn record_template(
+        &self,
+        dependencies: &mut TemplateDependencies,
+        root: &str,
+        target: &RenderTarget,
+    ) {
+        let mut pending = vec![root.to_string()];
+        let mut seen = std::collections::HashSet::new();
+        while let Some(name) = pending.pop() {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            dependencies.record(&name, target.clone());
+            let Some(template) = self.tera.templates.get(&name) else {
+                continue;
+            };
+            pending.extend(template.parents.iter().cloned());
+            pending.extend(
+                template
+                    .imported_macro_files
+                    .iter()
+                    .map(|(path, _)| path.clone()),
+            );
+            let bodies = std::iter::once(&template.ast).chain(
+                template
+                    .blocks_definitions
+                    .values()
+                    .flatten()
+                    .map(|(_, block)| &block.body),
+            );
+            for body in bodies {
+                let refs = crate::lint::TemplateRefs::from_nodes(body);
+                pending.extend(refs.includes.into_iter().flat_map(|(names, _)| names));
+            }
+        }
+    }
+
+    /// Records `target` against the template of each of `content`'s
+    /// extra output formats.
+    fn record_outputs(
+        &self,
+        dependencies: &mut TemplateDependencies,
+        site: &Site,
+        content: &crate::types::Content,
+        html_template: &str,
+        target: &RenderTarget,
+    ) {
+        for format in outputs::extra_outputs(site, content) {
+            let template = outputs::output_template(&self.tera, html_template, &format);
+            self.record_template(dependencies, &template, target);
+        }
+    }
+
+    fn record_shortcodes(
+        &self,
+        dependencies: &mut TemplateDependencies,
+        content: &crate::types::Content,
+        targets: &[RenderTarget],
+    ) {
+        for name in crate::shortcodes::shortcode_names(&content.raw_content) {
+            let template = format!("shortcodes/{name}.html");
+            for target in targets {
+                self.record_template(dependencies, &template, target);
+            }
+        }
+    }
+
     fn apply_overrides(&mut self, override_dir: &Path) -> Result<()> {
         let templates_dir = override_dir.join("templates");
         if !templates_dir.exists() {
             return Ok(());
         }
 
+        let mut templates = Vec::new();
         for entry in WalkDir::new(&templates_dir)
             .min_depth(1)
             .into_iter()
@@ -193,7 +595,7 @@ impl ThemeEngine {
             }
             if path
                 .extension()
-                .map(|extension| extension != "html")
+                .map(|extension| extension != "html" && extension != "xml")
                 .unwrap_or(true)
             {
                 continue;
@@ -204,9 +606,12 @@ impl ThemeEngine {
                 }
             })?;
             let template_name = relative.to_string_lossy().replace('\\', "/");
-            let content = fs::read_to_string(path)?;
-            self.tera.add_raw_template(&template_name, &content)?;
+            templates.push((path.to_path_buf(), Some(template_name)));
         }
+        // Added as one batch so a template can import `macros/*.html` that
+        // sorts after it in the directory walk. Loading from files keeps
+        // each template's path for error excerpts.
+        self.tera.add_template_files(templates)?;
 
         let static_dir = override_dir.join("static");
         if static_dir.exists() {
@@ -218,73 +623,23 @@ impl ThemeEngine {
 
     fn builtin_default() -> Result<Self> {
         let mut tera = Tera::default();
+        tera.add_raw_templates(BUILTIN_TEMPLATES)?;
+        add_fallback_templates(&mut tera)?;
 
-        tera.add_raw_template("base.html", DEFAULT_BASE_TEMPLATE)?;
-        tera.add_raw_template("index.html", DEFAULT_INDEX_TEMPLATE)?;
-        tera.add_raw_template("page.html", DEFAULT_PAGE_TEMPLATE)?;
-        tera.add_raw_template("post.html", DEFAULT_POST_TEMPLATE)?;
-        tera.add_raw_template("collection.html", DEFAULT_COLLECTION_TEMPLATE)?;
-        tera.add_raw_template("collection_item.html", DEFAULT_COLLECTION_ITEM_TEMPLATE)?;
-        tera.add_raw_template("slideshow.html", DEFAULT_SLIDESHOW_TEMPLATE)?;
-        tera.add_raw_template("docs.html", DEFAULT_DOCS_TEMPLATE)?;
-        tera.add_raw_template("portfolio.html", DEFAULT_PORTFOLIO_TEMPLATE)?;
-        tera.add_raw_template("landing.html", DEFAULT_LANDING_TEMPLATE)?;
-        tera.add_raw_template("changelog.html", DEFAULT_CHANGELOG_TEMPLATE)?;
-        tera.add_raw_template("book.html", DEFAULT_BOOK_TEMPLATE)?;
-        tera.add_raw_template("archive.html", DEFAULT_ARCHIVE_TEMPLATE)?;
-        tera.add_raw_template(
-            "categories_grouped.html",
-            DEFAULT_CATEGORIES_GROUPED_TEMPLATE,
-        )?;
-        tera.add_raw_template("tags_grouped.html", DEFAULT_TAGS_GROUPED_TEMPLATE)?;
-        tera.add_raw_template("tags.html", DEFAULT_TAGS_TEMPLATE)?;
-        tera.add_raw_template("tag.html", DEFAULT_TAG_TEMPLATE)?;
-        tera.add_raw_template("categories.html", DEFAULT_CATEGORIES_TEMPLATE)?;
-        tera.add_raw_template("category.html", DEFAULT_CATEGORY_TEMPLATE)?;
-        tera.add_raw_template("taxonomy.html", DEFAULT_TAXONOMY_TEMPLATE)?;
-        tera.add_raw_template("taxonomy_term.html", DEFAULT_TAXONOMY_TERM_TEMPLATE)?;
-        tera.add_raw_template("pagination.html", DEFAULT_PAGINATION_TEMPLATE)?;
-        tera.add_raw_template("404.html", DEFAULT_404_TEMPLATE)?;
-        tera.add_raw_template("partials/header.html", DEFAULT_HEADER_PARTIAL)?;
-        tera.add_raw_template("partials/footer.html", DEFAULT_FOOTER_PARTIAL)?;
-        tera.add_raw_template("partials/nav.html", DEFAULT_NAV_PARTIAL)?;
-        tera.add_raw_template(
-            "partials/author_profile.html",
-            DEFAULT_AUTHOR_PROFILE_PARTIAL,
-        )?;
-        tera.add_raw_template(
-            "partials/post_breadcrumbs.html",
-            DEFAULT_POST_BREADCRUMBS_PARTIAL,
-        )?;
-        tera.add_raw_template("partials/post_header.html", DEFAULT_POST_HEADER_PARTIAL)?;
-        tera.add_raw_template(
-            "partials/post_hero_image.html",
-            DEFAULT_POST_HERO_IMAGE_PARTIAL,
-        )?;
-        tera.add_raw_template("partials/post_toc.html", DEFAULT_POST_TOC_PARTIAL)?;
-        tera.add_raw_template("partials/post_share.html", DEFAULT_POST_SHARE_PARTIAL)?;
-        tera.add_raw_template("partials/post_related.html", DEFAULT_POST_RELATED_PARTIAL)?;
-        tera.add_raw_template(
-            "partials/post_prev_next.html",
-            DEFAULT_POST_PREV_NEXT_PARTIAL,
-        )?;
-        tera.add_raw_template(
-            "partials/post_edit_link.html",
-            DEFAULT_POST_EDIT_LINK_PARTIAL,
-        )?;
-        tera.add_raw_template(
-            "partials/page_edit_link.html",
-            DEFAULT_PAGE_EDIT_LINK_PARTIAL,
-        )?;
-        tera.add_raw_template("search.html", DEFAULT_SEARCH_TEMPLATE)?;
-
-        register_custom_filters(&mut tera);
+        let base_url = Arc::new(RwLock::new(String::new()));
+        let asset_paths = Arc::new(RwLock::new(HashMap::new()));
+        register_custom_filters(&mut tera, &base_url);
+        register_asset_url(&mut tera, &base_url, &asset_paths);
 
         Ok(Self {
             tera,
             theme_static_dir: None,
             override_static_dir: None,
             is_builtin_default: true,
+            base_url,
+            asset_paths,
+            cache_dir: None,
+            image_progress: None,
         })
     }
 
@@ -294,10 +649,14 @@ impl ThemeEngine {
 
         let escaped_templates =
             escape_glob_path(&templates_dir.to_string_lossy().replace('\\', "/"));
-        let pattern_str = format!("{escaped_templates}/**/*.html");
+        let pattern_str = format!("{escaped_templates}/**/*.{{html,xml}}");
 
         let mut tera = Tera::new(&pattern_str)?;
-        register_custom_filters(&mut tera);
+        add_fallback_templates(&mut tera)?;
+        let base_url = Arc::new(RwLock::new(String::new()));
+        let asset_paths = Arc::new(RwLock::new(HashMap::new()));
+        register_custom_filters(&mut tera, &base_url);
+        register_asset_url(&mut tera, &base_url, &asset_paths);
 
         let theme_static_dir = if static_dir.exists() {
             Some(static_dir)
@@ -310,15 +669,41 @@ impl ThemeEngine {
             theme_static_dir,
             override_static_dir: None,
             is_builtin_default: false,
+            base_url,
+            asset_paths,
+            cache_dir: None,
+            image_progress: None,
         })
     }
 
     /// Renders every page, post, collection item, taxonomy page, feed, and
     /// sitemap into `output_dir`. Performs a full build.
-    pub fn render_site(&self, site: &Site, output_dir: &Path) -> Result<()> {
+    ///
+    /// Steps that need the network or run on the finished output are left
+    /// to the caller, as `bamboo build` does: fetching remote data
+    /// ([`discussions`](crate::discussions), [`link_previews`](crate::link_previews),
+    /// [`remote_images`](crate::remote_images)) before rendering, then
+    /// [`archive::annotate_output`](crate::archive::annotate_output),
+    /// [`precompress::precompress_output`](crate::precompress::precompress_output),
+    /// [`build_manifest::generate_build_manifest`](crate::build_manifest::generate_build_manifest),
+    /// and [`resources::check_external_resources`](crate::resources::check_external_resources).
+    pub fn render_site(&self, site: &Site, output_dir: &Path) -> Result<RenderReport> {
         self.render_site_with_targets(site, output_dir, None)
     }
 
+    /// Full build into `sink` instead of a directory, such as a
+    /// [`MemorySink`](crate::sink::MemorySink) or
+    /// [`TarSink`](crate::sink::TarSink). Static files and assets are
+    /// copied as-is: fingerprinting, minification, Sass, and `[images]`
+    /// processing need a directory and are skipped unless `sink` is one.
+    pub fn render_to_sink(
+        &self,
+        site: &Site,
+        sink: &(impl RenderSink + ?Sized),
+    ) -> Result<RenderReport> {
+        self.render_into(site, sink, None)
+    }
+
     /// Incremental variant of [`ThemeEngine::render_site`]. Only writes
     /// output for the [`RenderTarget`](crate::cache::RenderTarget)s listed in
     /// `targets`; use [`classify_changes`](crate::cache::classify_changes) +
@@ -328,32 +713,64 @@ impl ThemeEngine {
         site: &Site,
         output_dir: &Path,
         targets: Option<&std::collections::HashSet<crate::cache::RenderTarget>>,
-    ) -> Result<()> {
+    ) -> Result<RenderReport> {
+        fs::create_dir_all(output_dir)?;
+        self.render_into(site, output_dir, targets)
+    }
+
+    fn render_into(
+        &self,
+        site: &Site,
+        output: &(impl RenderSink + ?Sized),
+        targets: Option<&std::collections::HashSet<crate::cache::RenderTarget>>,
+    ) -> Result<RenderReport> {
         use crate::cache::{
-            RenderTarget, should_render, should_render_any_collection, should_render_any_page,
+            should_render, should_render_any_collection, should_render_any_page,
             should_render_any_post,
         };
 
         let render_all =
             targets.is_none() || targets.is_some_and(|t| t.contains(&RenderTarget::All));
 
-        fs::create_dir_all(output_dir)?;
-
-        if render_all && self.is_builtin_default {
-            fs::write(output_dir.join("style.css"), DEFAULT_STYLESHEET)?;
+        if let Ok(mut base_url) = self.base_url.write() {
+            *base_url = site.config.base_url.clone();
         }
 
+        let asset_config = self.asset_config(site);
+        let mut asset_mapping = if render_all {
+            if self.is_builtin_default {
+                output.write_file(Path::new("style.css"), DEFAULT_STYLESHEET.as_bytes())?;
+            }
+            // Static files go in before rendering so fingerprinted names
+            // are known to `asset_url` by the time templates run.
+            self.copy_theme_static(&site.config.static_ignore, output)?;
+            self.copy_assets(&site.assets, output)?;
+            let mapping = match output.directory() {
+                Some(output_dir) => crate::assets::prepare_assets(output_dir, &asset_config)?,
+                None => HashMap::new(),
+            };
+            if let Ok(mut asset_paths) = self.asset_paths.write() {
+                *asset_paths = mapping.clone();
+            }
+            mapping
+        } else {
+            self.asset_paths
+                .read()
+                .map(|asset_paths| asset_paths.clone())
+                .unwrap_or_default()
+        };
+
         if render_all
             || targets.is_some_and(|t| should_render(t, &RenderTarget::Page("index".to_string())))
         {
-            self.render_index(site, output_dir)?;
+            self.render_index(site, output)?;
         }
 
         if render_all {
             site.pages
                 .par_iter()
                 .filter(|page| page.content.slug != "404")
-                .try_for_each(|page| self.render_page(site, page, output_dir))?;
+                .try_for_each(|page| self.render_page(site, page, output))?;
         } else if let Some(target_set) = targets
             && should_render_any_page(target_set)
         {
@@ -363,7 +780,7 @@ impl ThemeEngine {
                     page.content.slug != "404"
                         && should_render(target_set, &RenderTarget::Page(page.content.slug.clone()))
                 })
-                .try_for_each(|page| self.render_page(site, page, output_dir))?;
+                .try_for_each(|page| self.render_page(site, page, output))?;
         }
 
         let post_tuples: Vec<_> = site
@@ -389,7 +806,7 @@ impl ThemeEngine {
             post_tuples
                 .par_iter()
                 .try_for_each(|(post, prev_post, next_post)| {
-                    self.render_post(site, post, *prev_post, *next_post, output_dir)
+                    self.render_post(site, post, *prev_post, *next_post, output)
                 })?;
         } else if let Some(target_set) = targets
             && should_render_any_post(target_set)
@@ -400,7 +817,7 @@ impl ThemeEngine {
                     should_render(target_set, &RenderTarget::Post(post.content.slug.clone()))
                 })
                 .try_for_each(|(post, prev_post, next_post)| {
-                    self.render_post(site, post, *prev_post, *next_post, output_dir)
+                    self.render_post(site, post, *prev_post, *next_post, output)
                 })?;
         }
 
@@ -408,7 +825,7 @@ impl ThemeEngine {
             site.collections
                 .par_iter()
                 .try_for_each(|(name, collection)| {
-                    self.render_collection(site, name, collection, output_dir)
+                    self.render_collection(site, name, collection, output)
                 })?;
         } else if let Some(target_set) = targets
             && should_render_any_collection(target_set)
@@ -419,82 +836,205 @@ impl ThemeEngine {
                     should_render(target_set, &RenderTarget::Collection(name.to_string()))
                 })
                 .try_for_each(|(name, collection)| {
-                    self.render_collection(site, name, collection, output_dir)
+                    self.render_collection(site, name, collection, output)
                 })?;
         }
 
+        if render_all || targets.is_some_and(|t| should_render(t, &RenderTarget::Notes)) {
+            self.render_notes(site, output)?;
+        }
+
         if render_all || targets.is_some_and(|t| should_render(t, &RenderTarget::Pagination)) {
-            self.render_pagination(site, output_dir)?;
+            self.render_pagination(site, output)?;
         }
 
         if render_all || targets.is_some_and(|t| t.contains(&RenderTarget::AllTaxonomies)) {
             let metadata = site_metadata(site);
-            crate::taxonomy::render_all_taxonomies(&self.tera, site, &metadata, output_dir)?;
+            crate::taxonomy::render_all_taxonomies(&self.tera, site, &metadata, output)?;
         }
 
         if render_all {
-            self.render_404(site, output_dir)?;
+            self.render_404(site, output)?;
+            generated_pages::render_generated_pages(&self.tera, site, output)?;
         }
 
-        if render_all || targets.is_some_and(|t| should_render(t, &RenderTarget::SearchIndex)) {
-            self.render_search(site, output_dir)?;
-        }
-
-        if render_all {
-            self.copy_theme_static(output_dir)?;
-            self.copy_assets(&site.assets, output_dir)?;
+        let generate = &site.config.generate;
+        let render_search_index = generate.search
+            && (render_all
+                || targets.is_some_and(|t| should_render(t, &RenderTarget::SearchIndex)));
+
+        if render_search_index {
+            let compressed = search::compressed_search_index(site, chrono::Utc::now())?;
+            self.render_search(
+                site,
+                compressed.as_ref().map(|index| index.url.as_str()),
+                output,
+            )?;
+            if let Some(compressed) = compressed {
+                output.write_file(
+                    Path::new(search::COMPRESSED_SEARCH_INDEX),
+                    &compressed.bytes,
+                )?;
+            }
         }
 
         if render_all || targets.is_some_and(|t| should_render(t, &RenderTarget::Feeds)) {
-            feeds::generate_rss(site, output_dir)?;
-            feeds::generate_atom(site, output_dir)?;
+            if generate.rss {
+                feeds::generate_rss(&self.tera, site, output)?;
+            }
+            if generate.atom {
+                feeds::generate_atom(&self.tera, site, output)?;
+            }
+            if !site.notes.is_empty() {
+                if generate.rss {
+                    feeds::generate_notes_rss(&self.tera, site, output)?;
+                }
+                if generate.atom {
+                    feeds::generate_notes_atom(&self.tera, site, output)?;
+                }
+            }
             for (name, collection) in &site.collections {
-                feeds::generate_collection_rss(site, name, collection, output_dir)?;
-                feeds::generate_collection_atom(site, name, collection, output_dir)?;
+                if !collection.config.feed {
+                    continue;
+                }
+                if generate.rss {
+                    feeds::generate_collection_rss(&self.tera, site, name, collection, output)?;
+                }
+                if generate.atom {
+                    feeds::generate_collection_atom(&self.tera, site, name, collection, output)?;
+                }
             }
+            if generate.rss {
+                feeds::generate_releases_rss(&self.tera, site, output)?;
+            }
+            crate::opml::generate_opml(site, output)?;
+            crate::changelog::generate_releases_json(site, output)?;
         }
 
-        if render_all || targets.is_some_and(|t| should_render(t, &RenderTarget::Sitemap)) {
-            sitemap::generate_sitemap(site, output_dir)?;
+        if generate.sitemap
+            && (render_all || targets.is_some_and(|t| should_render(t, &RenderTarget::Sitemap)))
+        {
+            sitemap::generate_sitemap(&self.tera, site, output)?;
+        }
+
+        if render_all && generate.redirects {
+            redirects::generate_redirects(site, output)?;
         }
 
         if render_all {
-            redirects::generate_redirects(site, output_dir)?;
+            crate::wellknown::generate_wellknown(site, output)?;
+            crate::webmanifest::generate_webmanifest(site, output)?;
+            crate::llms::generate_llms_txt(site, output)?;
+            crate::vercel::generate_vercel_json(site, output)?;
+            crate::htaccess::generate_htaccess(site, output)?;
+            crate::newsletter::generate_newsletter(&self.tera, site, output)?;
         }
 
-        if render_all || targets.is_some_and(|t| should_render(t, &RenderTarget::SearchIndex)) {
-            search::generate_search_index(site, output_dir)?;
+        if render_search_index {
+            search::generate_search_index(site, output)?;
         }
 
-        if let Some(ref image_config) = site.config.images
+        let mut image_stats = None;
+        let mut warnings = Vec::new();
+        if let Some(output_dir) = output.directory()
             && render_all
         {
-            let manifest = images::process_images(output_dir, image_config)?;
-            images::apply_srcset_to_html(output_dir, &manifest)?;
+            if let Some(ref image_config) = site.config.images {
+                let progress = self
+                    .image_progress
+                    .as_deref()
+                    .filter(|_| image_config.progress);
+                let process = || {
+                    images::process_images_with_progress(
+                        output_dir,
+                        image_config,
+                        &site.image_requests,
+                        self.cache_dir.as_deref(),
+                        progress,
+                    )
+                };
+                let manifest = match image_config.threads {
+                    Some(threads) => crate::jobs::with_jobs(Some(threads), process)??,
+                    None => process()?,
+                };
+                images::apply_srcset_to_html(
+                    output_dir,
+                    &manifest,
+                    &images::ImageSizes::new(site, image_config),
+                )?;
+                image_stats = Some(manifest.stats);
+            }
+            let renamed =
+                crate::assets::fingerprint_deferred(output_dir, &asset_config, &mut asset_mapping)?;
+            if let Ok(mut asset_paths) = self.asset_paths.write() {
+                *asset_paths = asset_mapping.clone();
+            }
+            let fonts = crate::fonts::self_host_fonts(site, output_dir)?;
+            warnings.extend(fonts.into_iter().filter_map(|font| font.warning));
+            let mut references = asset_mapping.clone();
+            references.extend(renamed);
+            crate::assets::finalize_assets(output_dir, &asset_config, &references)?;
+        } else if let Some(output_dir) = output.directory()
+            && asset_config.fingerprint
+        {
+            crate::assets::update_html_references(
+                output_dir,
+                &asset_mapping,
+                &asset_config.base_url,
+            )?;
         }
 
         if render_all {
-            let mut sass_load_paths = Vec::new();
-            if let Some(ref static_dir) = self.theme_static_dir {
-                sass_load_paths.push(static_dir.clone());
-            }
-            if let Some(ref override_dir) = self.override_static_dir {
-                sass_load_paths.push(override_dir.clone());
-            }
+            // After images are fingerprinted, so both list their hashed names.
+            crate::headers::generate_headers(site, &asset_mapping, output)?;
+            crate::assets::generate_asset_manifest(site, &asset_mapping, output)?;
+        }
 
-            let asset_config = AssetConfig {
-                minify: site.config.minify,
-                fingerprint: site.config.fingerprint,
-                base_url: site.config.base_url.clone(),
-                sass_load_paths,
-            };
-            crate::assets::process_assets(output_dir, &asset_config)?;
+        Ok(RenderReport {
+            fingerprinted: asset_mapping,
+            images: image_stats,
+            warnings,
+        })
+    }
+
+    fn asset_config(&self, site: &Site) -> AssetConfig {
+        let mut sass_load_paths = Vec::new();
+        if let Some(ref static_dir) = self.theme_static_dir {
+            sass_load_paths.push(static_dir.clone());
+        }
+        if let Some(ref override_dir) = self.override_static_dir {
+            sass_load_paths.push(override_dir.clone());
         }
 
-        Ok(())
+        // Fonts and images the post-render pipelines read from the output
+        // directory have to keep their names until those pipelines run.
+        // Images are hashed once they have, along with their variants.
+        let mut fingerprint_exclude = site.config.assets.fingerprint_exclude.clone();
+        fingerprint_exclude.extend(site.config.fonts.iter().map(|font| font.src.clone()));
+        let mut fingerprint_deferred = Vec::new();
+        if site.config.images.is_some() {
+            fingerprint_deferred
+                .push("**/*.{jpg,jpeg,png,gif,webp,avif,JPG,JPEG,PNG,GIF,WEBP,AVIF}".to_string());
+        }
+
+        AssetConfig {
+            minify: site.config.minify,
+            fingerprint: site.config.fingerprint,
+            fingerprint_exclude,
+            fingerprint_deferred,
+            minify_exclude: site.config.assets.minify_exclude.clone(),
+            base_url: site.config.base_url.clone(),
+            sass_load_paths,
+            dedupe: site.config.dedupe_assets,
+            css: site.config.assets.css.clone(),
+            js: site.config.assets.js.clone(),
+            source_maps: site.config.assets.source_maps,
+            css_bundles: site.config.assets.css_bundles.clone(),
+            html: site.config.assets.html.clone(),
+        }
     }
 
-    fn render_index(&self, site: &Site, output_dir: &Path) -> Result<()> {
+    fn render_index(&self, site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
         let posts_per_page = site.config.posts_per_page;
         let index_posts: Vec<&crate::types::Post> =
             site.posts.iter().take(posts_per_page).collect();
@@ -509,49 +1049,68 @@ impl ThemeEngine {
         let metadata = site_metadata(site);
         context.insert("site", &metadata);
         context.insert("posts", &index_posts);
-        context.insert("current_page", &1usize);
-        context.insert("total_pages", &total_pages);
-
-        if total_pages > 1 {
-            let next_url = format!("{}/page/2/", base_url);
-            context.insert("next_page_url", &next_url);
-        }
+        insert_pagination(&mut context, &format!("{}/", base_url), 1, total_pages);
 
         let template_name = if let Some(home) = &site.home {
             context.insert("home", home);
             context.insert("page", home);
+            let meta = seo::content_seo(site, &home.content, false, None);
+            context.insert("head", &head::Head::new(site, &meta));
+            context.insert("seo", &meta);
             home.content.template.as_deref().unwrap_or("index.html")
         } else {
             "index.html"
         };
 
-        let rendered = self.tera.render(template_name, &context)?;
-        let output_path = output_dir.join("index.html");
-
-        fs::write(output_path, rendered)?;
+        let rendered = render_template(&self.tera, template_name, &context)?;
+        output.write_file(Path::new("index.html"), rendered.as_bytes())?;
+
+        if let Some(home) = &site.home {
+            outputs::render_outputs(
+                &self.tera,
+                site,
+                &home.content,
+                template_name,
+                &context,
+                output,
+            )?;
+        }
 
         Ok(())
     }
 
-    fn render_page(&self, site: &Site, page: &crate::types::Page, output_dir: &Path) -> Result<()> {
+    fn render_page(
+        &self,
+        site: &Site,
+        page: &crate::types::Page,
+        output: &(impl RenderSink + ?Sized),
+    ) -> Result<()> {
+        if redirects::redirect_to(&page.content).is_some() {
+            return redirects::render_redirect_to(site, &page.content, output);
+        }
         let mut context = Context::new();
         let metadata = site_metadata(site);
         context.insert("site", &metadata);
         context.insert("page", page);
+        let meta = seo::content_seo(site, &page.content, false, None);
+        context.insert("head", &head::Head::new(site, &meta));
+        context.insert("seo", &meta);
         let math = site.config.math || page.content.frontmatter.get_bool("math").unwrap_or(false);
         context.insert("math", &math);
 
         let template_name = page.content.template.as_deref().unwrap_or("page.html");
-        let rendered = self.tera.render(template_name, &context)?;
-
-        let output_path = output_dir.join(&page.content.path);
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-
-        fs::write(output_path, rendered)?;
-
-        Ok(())
+        let rendered = render_template(&self.tera, template_name, &context)?;
+
+        output.write_file(&page.content.path, rendered.as_bytes())?;
+
+        outputs::render_outputs(
+            &self.tera,
+            site,
+            &page.content,
+            template_name,
+            &context,
+            output,
+        )
     }
 
     fn render_post(
@@ -560,12 +1119,18 @@ impl ThemeEngine {
         post: &crate::types::Post,
         prev_post: Option<&crate::types::Post>,
         next_post: Option<&crate::types::Post>,
-        output_dir: &Path,
+        output: &(impl RenderSink + ?Sized),
     ) -> Result<()> {
+        if redirects::redirect_to(&post.content).is_some() {
+            return redirects::render_redirect_to(site, &post.content, output);
+        }
         let mut context = Context::new();
         let metadata = site_metadata(site);
         context.insert("site", &metadata);
         context.insert("post", post);
+        let meta = seo::post_seo(site, post);
+        context.insert("head", &head::post_head(site, post, &meta));
+        context.insert("seo", &meta);
         let math = site.config.math || post.content.frontmatter.get_bool("math").unwrap_or(false);
         context.insert("math", &math);
 
@@ -580,26 +1145,70 @@ impl ThemeEngine {
         context.insert("related_posts", &related);
 
         let template_name = post.content.template.as_deref().unwrap_or("post.html");
-        let rendered = self.tera.render(template_name, &context)?;
+        let rendered = render_template(&self.tera, template_name, &context)?;
+
+        output.write_file(&post.content.path, rendered.as_bytes())?;
+        social_images::generate_social_image(site, &post.content, output)?;
+
+        outputs::render_outputs(
+            &self.tera,
+            site,
+            &post.content,
+            template_name,
+            &context,
+            output,
+        )
+    }
 
-        let output_path = output_dir.join(&post.content.path);
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent)?;
+    /// Renders each note with `note.html` and the compact `notes/` list
+    /// with `notes.html`. Does nothing when the site has no notes.
+    fn render_notes(&self, site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
+        if site.notes.is_empty() {
+            return Ok(());
         }
+        let metadata = site_metadata(site);
 
-        fs::write(output_path, rendered)?;
+        site.notes.par_iter().try_for_each(|note| {
+            let mut context = Context::new();
+            context.insert("site", &metadata);
+            context.insert("note", note);
+            context.insert("page", note);
+            let meta = seo::content_seo(site, &note.content, true, None);
+            context.insert("head", &head::Head::new(site, &meta));
+            context.insert("seo", &meta);
+            let math =
+                site.config.math || note.content.frontmatter.get_bool("math").unwrap_or(false);
+            context.insert("math", &math);
+
+            let template_name = note.content.template.as_deref().unwrap_or("note.html");
+            let rendered = render_template(&self.tera, template_name, &context)?;
+            output.write_file(&note.content.path, rendered.as_bytes())?;
+            outputs::render_outputs(
+                &self.tera,
+                site,
+                &note.content,
+                template_name,
+                &context,
+                output,
+            )
+        })?;
 
-        Ok(())
+        let mut context = Context::new();
+        context.insert("site", &metadata);
+        context.insert("notes", &site.notes);
+        context.insert("math", &site.config.math);
+        let rendered = render_template(&self.tera, "notes.html", &context)?;
+        output.write_file(Path::new("notes/index.html"), rendered.as_bytes())
     }
 
-    fn render_pagination(&self, site: &Site, output_dir: &Path) -> Result<()> {
+    fn render_pagination(&self, site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
         let posts_per_page = site.config.posts_per_page;
         if posts_per_page == 0 || site.posts.is_empty() {
             return Ok(());
         }
 
         let total_pages = site.posts.len().div_ceil(posts_per_page);
-        let base_url = site.config.base_url.trim_end_matches('/');
+        let root_url = format!("{}/", site.config.base_url.trim_end_matches('/'));
         let metadata = site_metadata(site);
 
         for page_number in 2..=total_pages {
@@ -610,31 +1219,19 @@ impl ThemeEngine {
             let mut context = Context::new();
             context.insert("site", &metadata);
             context.insert("posts", page_posts);
-            context.insert("current_page", &page_number);
-            context.insert("total_pages", &total_pages);
-
-            let prev_url = if page_number == 2 {
-                format!("{}/", base_url)
-            } else {
-                format!("{}/page/{}/", base_url, page_number - 1)
-            };
-            context.insert("prev_page_url", &prev_url);
-
-            if page_number < total_pages {
-                let next_url = format!("{}/page/{}/", base_url, page_number + 1);
-                context.insert("next_page_url", &next_url);
-            }
+            insert_pagination(&mut context, &root_url, page_number, total_pages);
 
-            let rendered = self.tera.render("pagination.html", &context)?;
-            let page_dir = output_dir.join("page").join(page_number.to_string());
-            fs::create_dir_all(&page_dir)?;
-            fs::write(page_dir.join("index.html"), rendered)?;
+            let rendered = render_template(&self.tera, "pagination.html", &context)?;
+            let path = Path::new("page")
+                .join(page_number.to_string())
+                .join("index.html");
+            output.write_file(&path, rendered.as_bytes())?;
         }
 
         Ok(())
     }
 
-    fn render_404(&self, site: &Site, output_dir: &Path) -> Result<()> {
+    fn render_404(&self, site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
         let mut context = Context::new();
         let metadata = site_metadata(site);
         context.insert("site", &metadata);
@@ -644,25 +1241,23 @@ impl ThemeEngine {
             context.insert("page", page);
         }
 
-        let rendered = self.tera.render("404.html", &context)?;
-        fs::write(output_dir.join("404.html"), rendered)?;
-
-        Ok(())
+        let rendered = render_template(&self.tera, "404.html", &context)?;
+        output.write_file(Path::new("404.html"), rendered.as_bytes())
     }
 
-    fn render_search(&self, site: &Site, output_dir: &Path) -> Result<()> {
-        let search_dir = output_dir.join("search");
-        let search_index = search_dir.join("index.html");
-
+    fn render_search(
+        &self,
+        site: &Site,
+        search_index_url: Option<&str>,
+        output: &(impl RenderSink + ?Sized),
+    ) -> Result<()> {
         let mut context = Context::new();
         let metadata = site_metadata(site);
         context.insert("site", &metadata);
+        context.insert("search_index_url", &search_index_url);
 
-        let rendered = self.tera.render("search.html", &context)?;
-        fs::create_dir_all(&search_dir)?;
-        fs::write(search_index, rendered)?;
-
-        Ok(())
+        let rendered = render_template(&self.tera, "search.html", &context)?;
+        output.write_file(Path::new("search/index.html"), rendered.as_bytes())
     }
 
     fn render_collection(
@@ -670,7 +1265,7 @@ impl ThemeEngine {
         site: &Site,
         name: &str,
         collection: &crate::types::Collection,
-        output_dir: &Path,
+        output: &(impl RenderSink + ?Sized),
     ) -> Result<()> {
         let metadata = site_metadata(site);
         let items_per_page = site.config.posts_per_page;
@@ -698,43 +1293,25 @@ impl ThemeEngine {
             context.insert("collection", collection);
             context.insert("collection_name", name);
             context.insert("items", &page_items);
-            context.insert("current_page", &page_number);
-            context.insert("total_pages", &total_pages);
-
-            if page_number > 1 {
-                let prev_url = if page_number == 2 {
-                    format!("{}/{}/", base_url, name)
-                } else {
-                    format!("{}/{}/page/{}/", base_url, name, page_number - 1)
-                };
-                context.insert("prev_page_url", &prev_url);
-            }
+            insert_pagination(
+                &mut context,
+                &format!("{}/{}/", base_url, name),
+                page_number,
+                total_pages,
+            );
 
-            if page_number < total_pages {
-                let next_url = format!("{}/{}/page/{}/", base_url, name, page_number + 1);
-                context.insert("next_page_url", &next_url);
-            }
-
-            let rendered = self.tera.render("collection.html", &context)?;
+            let rendered = render_template(&self.tera, "collection.html", &context)?;
 
-            if page_number == 1 {
-                let index_path = output_dir.join(name).join("index.html");
-                if let Some(parent) = index_path.parent() {
-                    fs::create_dir_all(parent)?;
-                }
-                fs::write(index_path, rendered)?;
+            let page_dir = if page_number == 1 {
+                PathBuf::from(name)
             } else {
-                let page_dir = output_dir
-                    .join(name)
-                    .join("page")
-                    .join(page_number.to_string());
-                fs::create_dir_all(&page_dir)?;
-                fs::write(page_dir.join("index.html"), rendered)?;
-            }
+                Path::new(name).join("page").join(page_number.to_string())
+            };
+            output.write_file(&page_dir.join("index.html"), rendered.as_bytes())?;
         }
 
         for item in &collection.items {
-            self.render_collection_item(site, name, collection, item, output_dir)?;
+            self.render_collection_item(site, name, collection, item, output)?;
         }
 
         Ok(())
@@ -746,12 +1323,15 @@ impl ThemeEngine {
         collection_name: &str,
         collection: &crate::types::Collection,
         item: &crate::types::CollectionItem,
-        output_dir: &Path,
+        output: &(impl RenderSink + ?Sized),
     ) -> Result<()> {
         let mut context = Context::new();
         let metadata = site_metadata(site);
         context.insert("site", &metadata);
         context.insert("item", item);
+        let meta = seo::content_seo(site, &item.content, false, None);
+        context.insert("head", &head::Head::new(site, &meta));
+        context.insert("seo", &meta);
         context.insert("collection", collection);
         context.insert("collection_name", collection_name);
         let math = site.config.math || item.content.frontmatter.get_bool("math").unwrap_or(false);
@@ -774,35 +1354,44 @@ impl ThemeEngine {
             "page.html"
         };
 
-        let rendered = self.tera.render(template_name, &context)?;
-        let output_path = output_dir.join(&item.content.path);
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        fs::write(output_path, rendered)?;
-
-        Ok(())
+        let rendered = render_template(&self.tera, template_name, &context)?;
+        output.write_file(&item.content.path, rendered.as_bytes())?;
+
+        outputs::render_outputs(
+            &self.tera,
+            site,
+            &item.content,
+            template_name,
+            &context,
+            output,
+        )
     }
 
-    fn copy_assets(&self, assets: &[Asset], output_dir: &Path) -> Result<()> {
+    fn copy_assets(&self, assets: &[Asset], output: &(impl RenderSink + ?Sized)) -> Result<()> {
         for asset in assets {
-            let dest = output_dir.join(&asset.dest);
-            if let Some(parent) = dest.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            fs::copy(&asset.source, &dest)?;
+            output.copy_file(&asset.source, &asset.dest)?;
         }
 
         Ok(())
     }
 
-    fn copy_theme_static(&self, output_dir: &Path) -> Result<()> {
-        self.copy_static_dir(&self.theme_static_dir, output_dir)?;
-        self.copy_static_dir(&self.override_static_dir, output_dir)?;
+    fn copy_theme_static(
+        &self,
+        static_ignore: &[String],
+        output: &(impl RenderSink + ?Sized),
+    ) -> Result<()> {
+        let ignored = crate::assets::exclude_globs(static_ignore)?;
+        self.copy_static_dir(&self.theme_static_dir, &ignored, output)?;
+        self.copy_static_dir(&self.override_static_dir, &ignored, output)?;
         Ok(())
     }
 
-    fn copy_static_dir(&self, static_dir: &Option<PathBuf>, output_dir: &Path) -> Result<()> {
+    fn copy_static_dir(
+        &self,
+        static_dir: &Option<PathBuf>,
+        ignored: &globset::GlobSet,
+        output: &(impl RenderSink + ?Sized),
+    ) -> Result<()> {
         if let Some(static_dir) = static_dir {
             for entry in WalkDir::new(static_dir)
                 .min_depth(1)
@@ -811,18 +1400,12 @@ impl ThemeEngine {
             {
                 let path = entry.path();
 
-                if !path.is_file() {
+                if !path.is_file() || crate::assets::is_excluded(ignored, static_dir, path) {
                     continue;
                 }
 
                 let relative = path.strip_prefix(static_dir).unwrap();
-                let dest = output_dir.join(relative);
-
-                if let Some(parent) = dest.parent() {
-                    fs::create_dir_all(parent)?;
-                }
-
-                fs::copy(path, &dest)?;
+                output.copy_file(path, relative)?;
             }
         }
 
@@ -830,7 +1413,49 @@ impl ThemeEngine {
     }
 }
 
-fn register_custom_filters(tera: &mut Tera) {
+/// Built-in templates every theme gets unless it ships its own.
+const FALLBACK_TEMPLATES: [(&str, &str); 9] = [
+    (feeds::RSS_TEMPLATE, DEFAULT_RSS_TEMPLATE),
+    (feeds::ATOM_TEMPLATE, DEFAULT_ATOM_TEMPLATE),
+    (sitemap::SITEMAP_TEMPLATE, DEFAULT_SITEMAP_TEMPLATE),
+    ("default.txt", DEFAULT_TXT_OUTPUT_TEMPLATE),
+    ("default.json", DEFAULT_JSON_OUTPUT_TEMPLATE),
+    (
+        crate::newsletter::NEWSLETTER_HTML,
+        DEFAULT_NEWSLETTER_HTML_TEMPLATE,
+    ),
+    (
+        crate::newsletter::NEWSLETTER_TXT,
+        DEFAULT_NEWSLETTER_TXT_TEMPLATE,
+    ),
+    (seo::SEO_PARTIAL, DEFAULT_SEO_PARTIAL),
+    (head::HEAD_PARTIAL, DEFAULT_HEAD_PARTIAL),
+];
+
+/// Adds the built-in feed, sitemap, `default.txt`/`default.json` output,
+/// newsletter, `partials/seo.html`, and `partials/head.html` templates
+/// unless the theme already ships its own.
+fn add_fallback_templates(tera: &mut Tera) -> Result<()> {
+    let names: Vec<String> = tera.get_template_names().map(str::to_string).collect();
+    for (name, template) in FALLBACK_TEMPLATES {
+        if !names.iter().any(|existing| existing == name) {
+            tera.add_raw_template(name, template)?;
+        }
+    }
+    Ok(())
+}
+
+/// Source text of a built-in template, including the fallback feed,
+/// sitemap, output, SEO, and head templates.
+pub(crate) fn builtin_template_source(name: &str) -> Option<&'static str> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .chain(&FALLBACK_TEMPLATES)
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, source)| *source)
+}
+
+fn register_custom_filters(tera: &mut Tera, base_url: &Arc<RwLock<String>>) {
     tera.register_filter(
         "reading_time",
         |value: &tera::Value, _args: &HashMap<String, tera::Value>| {
@@ -887,6 +1512,108 @@ fn register_custom_filters(tera: &mut Tera) {
             Ok(tera::Value::String(slugify(text)))
         },
     );
+
+    tera.register_filter(
+        "truncate_words",
+        |value: &tera::Value, args: &HashMap<String, tera::Value>| {
+            let text = value.as_str().unwrap_or("");
+            let length = args.get("length").and_then(|v| v.as_u64()).unwrap_or(50) as usize;
+            let end = args.get("end").and_then(|v| v.as_str()).unwrap_or("…");
+            Ok(tera::Value::String(truncate_words(text, length, end)))
+        },
+    );
+
+    // Not `striptags`, so Tera's built-in filter keeps its behavior.
+    tera.register_filter(
+        "plain_text",
+        |value: &tera::Value, _args: &HashMap<String, tera::Value>| {
+            let html = value.as_str().unwrap_or("");
+            let stripped = crate::search::strip_html_tags(html);
+            Ok(tera::Value::String(stripped.trim().to_string()))
+        },
+    );
+
+    tera.register_filter(
+        "jsonify",
+        |value: &tera::Value, args: &HashMap<String, tera::Value>| {
+            let pretty = args
+                .get("pretty")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let encoded = if pretty {
+                serde_json::to_string_pretty(value)
+            } else {
+                serde_json::to_string(value)
+            }
+            .map_err(|error| tera::Error::msg(format!("jsonify: {error}")))?;
+            Ok(tera::Value::String(encoded.replace("</", "<\\/")))
+        },
+    );
+
+    let shared_base_url = base_url.clone();
+    tera.register_filter(
+        "absolute_url",
+        move |value: &tera::Value, _args: &HashMap<String, tera::Value>| {
+            let path = value.as_str().unwrap_or("");
+            let base_url = shared_base_url
+                .read()
+                .map(|guard| guard.clone())
+                .unwrap_or_default();
+            Ok(tera::Value::String(absolute_url(&base_url, path)))
+        },
+    );
+}
+
+/// Registers `asset_url(path="style.css")`, which returns the absolute URL
+/// of a static asset, using its fingerprinted name when `fingerprint` is
+/// enabled.
+fn register_asset_url(
+    tera: &mut Tera,
+    base_url: &Arc<RwLock<String>>,
+    asset_paths: &Arc<RwLock<HashMap<String, String>>>,
+) {
+    let shared_base_url = base_url.clone();
+    let shared_asset_paths = asset_paths.clone();
+    tera.register_function(
+        "asset_url",
+        move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+            let path = args
+                .get("path")
+                .and_then(|value| value.as_str())
+                .ok_or_else(|| tera::Error::msg("asset_url requires a `path` argument"))?;
+            let base_url = shared_base_url
+                .read()
+                .map(|guard| guard.clone())
+                .unwrap_or_default();
+            let resolved = shared_asset_paths
+                .read()
+                .ok()
+                .and_then(|paths| paths.get(path.trim_start_matches('/')).cloned())
+                .unwrap_or_else(|| path.to_string());
+            Ok(tera::Value::String(absolute_url(&base_url, &resolved)))
+        },
+    );
+}
+
+/// The first `length` words of `text` joined by single spaces, followed by
+/// `end`, or `text` unchanged when it has no more than `length` words.
+fn truncate_words(text: &str, length: usize, end: &str) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() <= length {
+        return text.to_string();
+    }
+    format!("{}{}", words[..length].join(" "), end)
+}
+
+fn absolute_url(base_url: &str, path: &str) -> String {
+    if path.starts_with("http://") || path.starts_with("https://") || path.starts_with("//") {
+        return path.to_string();
+    }
+    format!(
+        "{}/{}",
+        base_url.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
 }
 
 fn escape_glob_path(path: &str) -> String {
@@ -1060,6 +1787,91 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_truncate_words() {
+        assert_eq!(truncate_words("one two three four", 2, "…"), "one two…");
+        assert_eq!(truncate_words("one\n  two", 5, "…"), "one\n  two");
+    }
+
+    #[test]
+    fn test_absolute_url() {
+        assert_eq!(
+            absolute_url("https://example.com/", "/posts/hello/"),
+            "https://example.com/posts/hello/"
+        );
+        assert_eq!(
+            absolute_url("https://example.com", "https://other.com/x"),
+            "https://other.com/x"
+        );
+    }
+
+    #[test]
+    fn test_jsonify_and_plain_text_filters() {
+        let base_url = Arc::new(RwLock::new(String::new()));
+        let mut tera = Tera::default();
+        register_custom_filters(&mut tera, &base_url);
+        tera.add_raw_template(
+            "test.html",
+            "{{ data | jsonify | safe }}|{{ html | plain_text }}|{{ html | striptags }}",
+        )
+        .unwrap();
+        let mut context = Context::new();
+        context.insert("data", &serde_json::json!({"a": 1}));
+        context.insert("html", "<p>Hello <b>world</b> &#169;</p>");
+        let rendered = tera.render("test.html", &context).unwrap();
+        assert_eq!(rendered, "{\"a\":1}|Hello world ©|Hello world &amp;#169;");
+    }
+
+    #[test]
+    fn test_override_templates_can_import_macros() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let templates = dir.path().join("templates");
+        fs::create_dir_all(templates.join("macros")).unwrap();
+        fs::write(
+            templates.join("aaa.html"),
+            "{% import \"macros/cards.html\" as cards %}{{ cards::card(title=\"Hi\") }}",
+        )
+        .unwrap();
+        fs::write(
+            templates.join("macros/cards.html"),
+            "{% macro card(title) %}<div>{{ title }}</div>{% endmacro card %}",
+        )
+        .unwrap();
+
+        let engine = ThemeEngine::new_with_overrides("default", dir.path()).unwrap();
+        let rendered = engine.tera.render("aaa.html", &Context::new()).unwrap();
+        assert_eq!(rendered, "<div>Hi</div>");
+    }
+
+    #[test]
+    fn test_register_filter_function_and_tester() {
+        let mut engine = ThemeEngine::new("default").unwrap();
+        engine.register_filter(
+            "double",
+            |value: &tera::Value, _: &HashMap<String, tera::Value>| {
+                Ok(tera::Value::from(value.as_i64().unwrap_or(0) * 2))
+            },
+        );
+        engine.register_function("greet", |_: &HashMap<String, tera::Value>| {
+            Ok(tera::Value::from("hello"))
+        });
+        engine.register_tester(
+            "positive",
+            |value: Option<&tera::Value>, _: &[tera::Value]| {
+                Ok(value.and_then(tera::Value::as_i64).is_some_and(|n| n > 0))
+            },
+        );
+        engine
+            .tera
+            .add_raw_template(
+                "custom.html",
+                "{{ 21 | double }} {{ greet() }}{% set n = 3 %}{% if n is positive %} yes{% endif %}",
+            )
+            .unwrap();
+        let rendered = engine.tera.render("custom.html", &Context::new()).unwrap();
+        assert_eq!(rendered, "42 hello yes");
+    }
+
     #[test]
     fn test_builtin_default_theme() {
         let engine = ThemeEngine::new("default").unwrap();
@@ -1087,20 +1899,54 @@ mod tests {
                 posts_per_page: 10,
                 minify: false,
                 fingerprint: false,
+                dedupe_assets: None,
+                static_ignore: Vec::new(),
+                assets: crate::assets::AssetsConfig::default(),
+                build_manifest: false,
+                search_format: crate::search::SearchFormat::Json,
+                search_exclude: Vec::new(),
                 images: None,
+                social_images: None,
                 syntax_theme: crate::types::default_syntax_theme(),
                 taxonomies: crate::types::default_taxonomies(),
                 math: false,
                 favicon: None,
                 link_check_ignore: Vec::new(),
+                link_archive: None,
+                precompress: None,
+                fonts: Vec::new(),
+                build: crate::build_hooks::BuildConfig::default(),
+                deploy: crate::deploy::DeployConfig::default(),
+                remote: crate::remote::RemoteConfig::default(),
+                feed: crate::feeds::FeedConfig::default(),
+                sitemap: crate::sitemap::SitemapConfig::default(),
+                wellknown: crate::wellknown::WellKnownConfig::default(),
+                manifest: None,
+                llms: None,
+                vercel: None,
+                htaccess: None,
+                headers: None,
+                newsletter: None,
+                discussions: crate::discussions::DiscussionConfig::default(),
+                link_previews: crate::link_previews::LinkPreviewConfig::default(),
+                changelog: None,
+                external_resources: None,
+                generate: crate::types::GenerateConfig::default(),
+                drafts: false,
+                future: false,
+                generated_pages: Vec::new(),
+                outputs: Vec::new(),
+                jobs: None,
                 extra: HashMap::new(),
             },
             home: None,
             pages: vec![],
             posts: vec![],
+            notes: vec![],
             collections: HashMap::new(),
             data: HashMap::new(),
             assets: vec![],
+            image_requests: Vec::new(),
         };
 
         let output_dir = tempfile::TempDir::new().unwrap();
@@ -1114,6 +1960,74 @@ mod tests {
         assert!(output_dir.path().join("atom.xml").exists());
         assert!(output_dir.path().join("sitemap.xml").exists());
         assert!(output_dir.path().join("search-index.json").exists());
+        assert!(output_dir.path().join("search-index.json.gz").exists());
+        let search = fs::read_to_string(output_dir.path().join("search/index.html")).unwrap();
+        assert!(search.contains("/search-index.json.gz?v="));
+
+        let mut site = site;
+        site.config.generate = GenerateConfig {
+            rss: false,
+            atom: false,
+            sitemap: false,
+            search: false,
+            redirects: false,
+        };
+        let output_dir = tempfile::TempDir::new().unwrap();
+        engine.render_site(&site, output_dir.path()).unwrap();
+
+        assert!(output_dir.path().join("index.html").exists());
+        for skipped in [
+            "rss.xml",
+            "atom.xml",
+            "sitemap.xml",
+            "search-index.json",
+            "search-index.json.gz",
+            "search",
+        ] {
+            assert!(!output_dir.path().join(skipped).exists(), "{skipped}");
+        }
+        let index = fs::read_to_string(output_dir.path().join("index.html")).unwrap();
+        assert!(!index.contains("/rss.xml"));
+    }
+
+    #[test]
+    fn test_render_to_sink_matches_directory_build() {
+        use crate::sink::{MemorySink, TarSink};
+
+        let site = crate::template_test::sample_site().unwrap();
+        let engine = ThemeEngine::new("default").unwrap();
+        let output_dir = tempfile::TempDir::new().unwrap();
+        engine.render_site(&site, output_dir.path()).unwrap();
+
+        let sink = MemorySink::new();
+        engine.render_to_sink(&site, &sink).unwrap();
+        let files = sink.into_files();
+
+        let on_disk: Vec<PathBuf> = WalkDir::new(output_dir.path())
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(output_dir.path())
+                    .unwrap()
+                    .to_path_buf()
+            })
+            .collect();
+        assert_eq!(files.len(), on_disk.len());
+        for path in &on_disk {
+            assert!(files.contains_key(path), "{}", path.display());
+        }
+        assert_eq!(
+            files[Path::new("index.html")],
+            fs::read(output_dir.path().join("index.html")).unwrap()
+        );
+
+        let tar = TarSink::new(Vec::new());
+        engine.render_to_sink(&site, &tar).unwrap();
+        let archive = tar.finish().unwrap();
+        assert!(archive.len() > files.values().map(Vec::len).sum::<usize>());
     }
 
     #[test]
@@ -1138,12 +2052,44 @@ mod tests {
                 posts_per_page: 10,
                 minify: false,
                 fingerprint: false,
+                dedupe_assets: None,
+                static_ignore: Vec::new(),
+                assets: crate::assets::AssetsConfig::default(),
+                build_manifest: false,
+                search_format: crate::search::SearchFormat::Json,
+                search_exclude: Vec::new(),
                 images: None,
+                social_images: None,
                 syntax_theme: crate::types::default_syntax_theme(),
                 taxonomies: crate::types::default_taxonomies(),
                 math: false,
                 favicon: None,
                 link_check_ignore: Vec::new(),
+                link_archive: None,
+                precompress: None,
+                fonts: Vec::new(),
+                build: crate::build_hooks::BuildConfig::default(),
+                deploy: crate::deploy::DeployConfig::default(),
+                remote: crate::remote::RemoteConfig::default(),
+                feed: crate::feeds::FeedConfig::default(),
+                sitemap: crate::sitemap::SitemapConfig::default(),
+                wellknown: crate::wellknown::WellKnownConfig::default(),
+                manifest: None,
+                llms: None,
+                vercel: None,
+                htaccess: None,
+                headers: None,
+                newsletter: None,
+                discussions: crate::discussions::DiscussionConfig::default(),
+                link_previews: crate::link_previews::LinkPreviewConfig::default(),
+                changelog: None,
+                external_resources: None,
+                generate: crate::types::GenerateConfig::default(),
+                drafts: false,
+                future: false,
+                generated_pages: Vec::new(),
+                outputs: Vec::new(),
+                jobs: None,
                 extra: HashMap::new(),
             },
             home: None,
@@ -1155,10 +2101,15 @@ mod tests {
                     raw_content: "About page".to_string(),
                     frontmatter: Frontmatter::default(),
                     path: PathBuf::from("about/index.html"),
+                    source_path: PathBuf::new(),
+                    modified: None,
                     template: None,
                     weight: 0,
                     word_count: 2,
                     reading_time: 1,
+                    stats: crate::types::PageStats::default(),
+                    lang: None,
+                    translations: Vec::new(),
                     toc: vec![],
                     url: "/about/".to_string(),
                 },
@@ -1173,10 +2124,15 @@ mod tests {
                     raw_content: "Hello world".to_string(),
                     frontmatter: Frontmatter::default(),
                     path: PathBuf::from("posts/hello/index.html"),
+                    source_path: PathBuf::new(),
+                    modified: None,
                     template: None,
                     weight: 0,
                     word_count: 2,
                     reading_time: 1,
+                    stats: crate::types::PageStats::default(),
+                    lang: None,
+                    translations: Vec::new(),
                     toc: vec![],
                     url: "/posts/hello/".to_string(),
                 },
@@ -1190,10 +2146,14 @@ mod tests {
                     ("categories".to_string(), vec!["general".to_string()]),
                 ]),
                 redirect_from: vec![],
+                discussion: None,
+                link_preview: None,
             }],
+            notes: vec![],
             collections: HashMap::new(),
             data: HashMap::new(),
             assets: vec![],
+            image_requests: Vec::new(),
         };
 
         let output_dir = tempfile::TempDir::new().unwrap();
@@ -1236,10 +2196,15 @@ mod tests {
                     raw_content: format!("Post {}", index),
                     frontmatter: Frontmatter::default(),
                     path: PathBuf::from(format!("posts/post-{}/index.html", index)),
+                    source_path: PathBuf::new(),
+                    modified: None,
                     template: None,
                     weight: 0,
                     word_count: 2,
                     reading_time: 1,
+                    stats: crate::types::PageStats::default(),
+                    lang: None,
+                    translations: Vec::new(),
                     toc: vec![],
                     url: format!("/posts/post-{}/", index),
                 },
@@ -1250,6 +2215,8 @@ mod tests {
                 categories: vec![],
                 taxonomies_map: HashMap::new(),
                 redirect_from: vec![],
+                discussion: None,
+                link_preview: None,
             });
         }
 
@@ -1263,20 +2230,54 @@ mod tests {
                 posts_per_page: 1,
                 minify: false,
                 fingerprint: false,
+                dedupe_assets: None,
+                static_ignore: Vec::new(),
+                assets: crate::assets::AssetsConfig::default(),
+                build_manifest: false,
+                search_format: crate::search::SearchFormat::Json,
+                search_exclude: Vec::new(),
                 images: None,
+                social_images: None,
                 syntax_theme: crate::types::default_syntax_theme(),
                 taxonomies: crate::types::default_taxonomies(),
                 math: false,
                 favicon: None,
                 link_check_ignore: Vec::new(),
+                link_archive: None,
+                precompress: None,
+                fonts: Vec::new(),
+                build: crate::build_hooks::BuildConfig::default(),
+                deploy: crate::deploy::DeployConfig::default(),
+                remote: crate::remote::RemoteConfig::default(),
+                feed: crate::feeds::FeedConfig::default(),
+                sitemap: crate::sitemap::SitemapConfig::default(),
+                wellknown: crate::wellknown::WellKnownConfig::default(),
+                manifest: None,
+                llms: None,
+                vercel: None,
+                htaccess: None,
+                headers: None,
+                newsletter: None,
+                discussions: crate::discussions::DiscussionConfig::default(),
+                link_previews: crate::link_previews::LinkPreviewConfig::default(),
+                changelog: None,
+                external_resources: None,
+                generate: crate::types::GenerateConfig::default(),
+                drafts: false,
+                future: false,
+                generated_pages: Vec::new(),
+                outputs: Vec::new(),
+                jobs: None,
                 extra: HashMap::new(),
             },
             home: None,
             pages: vec![],
             posts,
+            notes: vec![],
             collections: HashMap::new(),
             data: HashMap::new(),
             assets: vec![],
+            image_requests: Vec::new(),
         };
 
         let output_dir = tempfile::TempDir::new().unwrap();
@@ -1301,10 +2302,15 @@ mod tests {
                     raw_content: format!("Item {}", index),
                     frontmatter: Frontmatter::default(),
                     path: PathBuf::from(format!("docs/item-{}/index.html", index)),
+                    source_path: PathBuf::new(),
+                    modified: None,
                     template: None,
                     weight: 0,
                     word_count: 2,
                     reading_time: 1,
+                    stats: crate::types::PageStats::default(),
+                    lang: None,
+                    translations: Vec::new(),
                     toc: vec![],
                     url: format!("/docs/item-{}/", index),
                 },
@@ -1317,10 +2323,12 @@ mod tests {
             Collection {
                 name: "docs".to_string(),
                 items,
+                config: CollectionConfig::default(),
+                index: None,
             },
         );
 
-        let site = Site {
+        let mut site = Site {
             config: SiteConfig {
                 title: "Test".to_string(),
                 base_url: "https://example.com".to_string(),
@@ -1330,20 +2338,54 @@ mod tests {
                 posts_per_page: 1,
                 minify: false,
                 fingerprint: false,
+                dedupe_assets: None,
+                static_ignore: Vec::new(),
+                assets: crate::assets::AssetsConfig::default(),
+                build_manifest: false,
+                search_format: crate::search::SearchFormat::Json,
+                search_exclude: Vec::new(),
                 images: None,
+                social_images: None,
                 syntax_theme: crate::types::default_syntax_theme(),
                 taxonomies: crate::types::default_taxonomies(),
                 math: false,
                 favicon: None,
                 link_check_ignore: Vec::new(),
+                link_archive: None,
+                precompress: None,
+                fonts: Vec::new(),
+                build: crate::build_hooks::BuildConfig::default(),
+                deploy: crate::deploy::DeployConfig::default(),
+                remote: crate::remote::RemoteConfig::default(),
+                feed: crate::feeds::FeedConfig::default(),
+                sitemap: crate::sitemap::SitemapConfig::default(),
+                wellknown: crate::wellknown::WellKnownConfig::default(),
+                manifest: None,
+                llms: None,
+                vercel: None,
+                htaccess: None,
+                headers: None,
+                newsletter: None,
+                discussions: crate::discussions::DiscussionConfig::default(),
+                link_previews: crate::link_previews::LinkPreviewConfig::default(),
+                changelog: None,
+                external_resources: None,
+                generate: crate::types::GenerateConfig::default(),
+                drafts: false,
+                future: false,
+                generated_pages: Vec::new(),
+                outputs: Vec::new(),
+                jobs: None,
                 extra: HashMap::new(),
             },
             home: None,
             pages: vec![],
             posts: vec![],
+            notes: vec![],
             collections,
             data: HashMap::new(),
             assets: vec![],
+            image_requests: Vec::new(),
         };
 
         let output_dir = tempfile::TempDir::new().unwrap();
@@ -1356,7 +2398,162 @@ mod tests {
         assert!(output_dir.path().join("docs/item-0/index.html").exists());
         assert!(output_dir.path().join("docs/item-1/index.html").exists());
         assert!(output_dir.path().join("docs/item-2/index.html").exists());
+        assert!(!output_dir.path().join("docs/rss.xml").exists());
+
+        site.collections.get_mut("docs").unwrap().config.feed = true;
+        engine.render_site(&site, output_dir.path()).unwrap();
         assert!(output_dir.path().join("docs/rss.xml").exists());
         assert!(output_dir.path().join("docs/atom.xml").exists());
+        let index = fs::read_to_string(output_dir.path().join("docs/index.html")).unwrap();
+        assert!(index.contains("https://example.com/docs/rss.xml"));
+    }
+
+    #[test]
+    fn test_template_dependencies() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("bamboo.toml"),
+            "title = \"T\"\nbase_url = \"https://example.com\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("content/posts")).unwrap();
+        fs::create_dir_all(dir.path().join("templates/shortcodes")).unwrap();
+        fs::write(
+            dir.path().join("templates/shortcodes/note.html"),
+            "<aside>{{ body }}</aside>",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/about.md"),
+            "+++\ntitle = \"About\"\n+++\n\n{{% note %}}\nHi\n{{% /note %}}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/posts/2024-01-01-hello.md"),
+            "+++\ntitle = \"Hello\"\n+++\n\nBody\n",
+        )
+        .unwrap();
+
+        let site = crate::SiteBuilder::new(dir.path()).build().unwrap();
+        let engine = ThemeEngine::new_with_overrides("default", dir.path()).unwrap();
+        let dependencies = engine.template_dependencies(&site);
+
+        let post = RenderTarget::Post("hello".to_string());
+        let about = RenderTarget::Page("about".to_string());
+        assert_eq!(
+            dependencies.targets_for("post.html").unwrap(),
+            std::slice::from_ref(&post)
+        );
+        assert_eq!(
+            dependencies
+                .targets_for("partials/post_header.html")
+                .unwrap(),
+            std::slice::from_ref(&post)
+        );
+        let header = dependencies.targets_for("partials/header.html").unwrap();
+        assert!(header.contains(&post) && header.contains(&about));
+        assert!(
+            dependencies
+                .targets_for("shortcodes/note.html")
+                .unwrap()
+                .contains(&about)
+        );
+        assert_eq!(
+            dependencies.targets_for("pagination.html").unwrap(),
+            &[RenderTarget::Pagination]
+        );
+        assert!(dependencies.targets_for("_hooks/link.html").is_none());
+    }
+
+    #[test]
+    fn test_asset_url_resolves_fingerprinted_names() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("bamboo.toml"),
+            "title = \"T\"\nbase_url = \"https://example.com\"\nfingerprint = true\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("content")).unwrap();
+        fs::create_dir_all(dir.path().join("static/js")).unwrap();
+        fs::write(dir.path().join("static/js/app.js"), "console.log(1);").unwrap();
+        fs::create_dir_all(dir.path().join("templates")).unwrap();
+        fs::write(
+            dir.path().join("templates/page.html"),
+            "<script src=\"{{ asset_url(path=\"js/app.js\") | safe }}\"></script>\
+             <img src=\"{{ asset_url(path=\"/logo.png\") | safe }}\">",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("content/about.md"),
+            "+++\ntitle = \"About\"\n+++\n",
+        )
+        .unwrap();
+
+        let site = crate::SiteBuilder::new(dir.path()).build().unwrap();
+        let engine = ThemeEngine::new_with_overrides("default", dir.path()).unwrap();
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let report = engine.render_site(&site, output_dir.path()).unwrap();
+
+        let about = fs::read_to_string(output_dir.path().join("about/index.html")).unwrap();
+        let script = about
+            .split("src=\"https://example.com/")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap();
+        assert!(script.starts_with("js/app.") && script.ends_with(".js"));
+        assert_ne!(script, "js/app.js");
+        assert!(output_dir.path().join(script).exists());
+        assert!(about.contains("src=\"https://example.com/logo.png\""));
+
+        let index = fs::read_to_string(output_dir.path().join("index.html")).unwrap();
+        assert!(!index.contains("/style.css\""));
+        assert!(index.contains("https://example.com/style."));
+
+        let mut rebuild = ThemeEngine::new_with_overrides("default", dir.path()).unwrap();
+        rebuild.set_asset_paths(report.fingerprinted.clone());
+        fs::remove_file(output_dir.path().join("about/index.html")).unwrap();
+        let targets = std::collections::HashSet::from([RenderTarget::Page("about".to_string())]);
+        let incremental = rebuild
+            .render_site_with_targets(&site, output_dir.path(), Some(&targets))
+            .unwrap();
+        assert_eq!(incremental.fingerprinted, report.fingerprinted);
+        let about = fs::read_to_string(output_dir.path().join("about/index.html")).unwrap();
+        assert!(about.contains(&format!(
+            "src=\"https://example.com/{}\"",
+            report.fingerprinted["js/app.js"]
+        )));
+    }
+
+    #[test]
+    fn test_render_notes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("bamboo.toml"),
+            "title = \"T\"\nbase_url = \"https://example.com\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("content/notes")).unwrap();
+        fs::write(
+            dir.path().join("content/notes/2024-06-01-1430.md"),
+            "Short and sweet.\n",
+        )
+        .unwrap();
+
+        let site = crate::SiteBuilder::new(dir.path()).build().unwrap();
+        let output_dir = tempfile::TempDir::new().unwrap();
+        ThemeEngine::new("default")
+            .unwrap()
+            .render_site(&site, output_dir.path())
+            .unwrap();
+
+        let note = fs::read_to_string(output_dir.path().join("notes/2024-06-01-143000/index.html"))
+            .unwrap();
+        assert!(note.contains("<p>Short and sweet.</p>"));
+        let list = fs::read_to_string(output_dir.path().join("notes/index.html")).unwrap();
+        assert!(list.contains("Short and sweet."));
+        assert!(output_dir.path().join("notes/rss.xml").exists());
+        assert!(output_dir.path().join("notes/atom.xml").exists());
+        let sitemap = fs::read_to_string(output_dir.path().join("sitemap.xml")).unwrap();
+        assert!(sitemap.contains("https://example.com/notes/2024-06-01-143000/"));
     }
 }
@@ -9,7 +9,13 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::archive::LinkArchiveConfig;
+use crate::changelog::ChangelogConfig;
+use crate::discussions::{Discussion, DiscussionConfig};
+use crate::feeds::FeedConfig;
 use crate::images::ImageConfig;
+use crate::remote::RemoteConfig;
+use crate::resources::ExternalResourceConfig;
 
 /// Describes a taxonomy (e.g. tags, categories) declared under
 /// `[taxonomies.<name>]` in `bamboo.toml`.
@@ -51,6 +57,37 @@ pub fn default_taxonomies() -> HashMap<String, TaxonomyDefinition> {
     taxonomies
 }
 
+/// `[generate]` table from `bamboo.toml`, switching off site-wide
+/// artifacts for a partial site embedded in a larger property that
+/// already provides them. Everything is generated by default; `bamboo
+/// build --skip` turns artifacts off for one build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerateConfig {
+    /// `rss.xml` feeds: the main, notes, collection, and release feeds.
+    pub rss: bool,
+    /// `atom.xml` feeds: the main, notes, and collection feeds.
+    pub atom: bool,
+    /// `sitemap.xml`.
+    pub sitemap: bool,
+    /// `search-index.json` and the `/search/` page.
+    pub search: bool,
+    /// Redirect pages for `redirect_from` frontmatter.
+    pub redirects: bool,
+}
+
+impl Default for GenerateConfig {
+    fn default() -> Self {
+        Self {
+            rss: true,
+            atom: true,
+            sitemap: true,
+            search: true,
+            redirects: true,
+        }
+    }
+}
+
 /// The fully-loaded site, produced by [`SiteBuilder::build`](crate::SiteBuilder::build)
 /// and consumed by [`ThemeEngine::render_site`](crate::ThemeEngine::render_site).
 ///
@@ -66,6 +103,8 @@ pub struct Site {
     pub pages: Vec<Page>,
     /// All blog posts (`content/posts/*.md`), sorted newest-first.
     pub posts: Vec<Post>,
+    /// Short-form notes (`content/notes/*.md`), sorted newest-first.
+    pub notes: Vec<Note>,
     /// User-defined collections keyed by name (directory containing
     /// `_collection.toml` → the [`Collection`] it produced).
     pub collections: HashMap<String, Collection>,
@@ -74,6 +113,10 @@ pub struct Site {
     pub data: HashMap<String, Value>,
     /// Static assets (from `static/`) that will be copied to the output dir.
     pub assets: Vec<Asset>,
+    /// Image sizes requested by `image` shortcodes, generated by the
+    /// `[images]` pipeline alongside its configured widths.
+    #[serde(default)]
+    pub image_requests: Vec<crate::images::ImageRequest>,
 }
 
 /// Parsed `bamboo.toml` contents. Also available in templates as
@@ -104,10 +147,39 @@ pub struct SiteConfig {
     /// If `true`, HTML/CSS/JS output is minified in place after rendering.
     #[serde(default)]
     pub minify: bool,
-    /// If `true`, CSS and JS files receive a content-hash suffix and all
-    /// references to them are rewritten. Useful for aggressive cache headers.
+    /// If `true`, CSS, JS, images, fonts, and media files receive a
+    /// content-hash suffix and all references to them are rewritten. Useful
+    /// for aggressive cache headers. See
+    /// [`crate::assets::FINGERPRINT_EXTENSIONS`].
     #[serde(default)]
     pub fingerprint: bool,
+    /// Collapse byte-identical static files in the output onto one copy:
+    /// `"hardlink"` or `"rewrite"`. See [`crate::assets::DedupeMode`].
+    #[serde(default)]
+    pub dedupe_assets: Option<crate::assets::DedupeMode>,
+    /// Globs of `static/` paths (`"**/*.psd"`, `"drafts/**"`) left out of
+    /// the output, for the site's and the theme's static files alike.
+    #[serde(default)]
+    pub static_ignore: Vec<String>,
+    /// The `[assets]` table; `[assets.css]` configures CSS compilation.
+    /// See [`crate::assets::CssConfig`].
+    #[serde(default)]
+    pub assets: crate::assets::AssetsConfig,
+    /// If `true`, `manifest.json` lists every output file with its hash
+    /// and source. See [`crate::build_manifest`].
+    #[serde(default)]
+    pub build_manifest: bool,
+    /// Shape of the search index: `"json"` (the default),
+    /// `"elasticlunr"` for an index built ahead of time, `"chunked"`
+    /// for one split into files fetched on demand, or `"tantivy"` for a
+    /// Tantivy index (with the `tantivy` feature).
+    #[serde(default)]
+    pub search_format: crate::search::SearchFormat,
+    /// Sections left out of the search index: `"posts"`, collection
+    /// names, taxonomy names (dropping their term pages), or the
+    /// top-level `content/` directories of nested pages.
+    #[serde(default)]
+    pub search_exclude: Vec<String>,
     /// Name of the syntect theme used to highlight fenced code blocks.
     /// Defaults to `base16-ocean.dark`.
     #[serde(default = "default_syntax_theme")]
@@ -115,6 +187,10 @@ pub struct SiteConfig {
     /// Optional responsive-image pipeline configuration.
     #[serde(default)]
     pub images: Option<ImageConfig>,
+    /// Optional `[social_images]` table. When present, every post gets a
+    /// generated `social.png` preview card used as its `og:image`.
+    #[serde(default)]
+    pub social_images: Option<crate::social_images::SocialImageConfig>,
     /// Taxonomy definitions. Defaults to `tags` + `categories`; override
     /// under `[taxonomies.<name>]` to add custom ones.
     #[serde(default = "default_taxonomies")]
@@ -134,6 +210,102 @@ pub struct SiteConfig {
     /// broken internal link.
     #[serde(default)]
     pub link_check_ignore: Vec<String>,
+    /// Optional `[link_archive]` configuration. When present, `bamboo build`
+    /// annotates external links with a Wayback Machine snapshot URL.
+    #[serde(default)]
+    pub link_archive: Option<LinkArchiveConfig>,
+    /// Optional `[precompress]` configuration. When present, `bamboo build`
+    /// writes `.gz`/`.br` siblings for text output.
+    #[serde(default)]
+    pub precompress: Option<crate::precompress::PrecompressConfig>,
+    /// `[[fonts]]` entries: web fonts from `static/` that are subset to
+    /// the site's characters and declared with `@font-face`.
+    #[serde(default)]
+    pub fonts: Vec<crate::fonts::FontConfig>,
+    /// `[build]` table; `[build.hooks]` runs external commands around
+    /// rendering. See [`crate::build_hooks`].
+    #[serde(default)]
+    pub build: crate::build_hooks::BuildConfig,
+    /// `[deploy]` table: settings for each `bamboo deploy` target. See
+    /// [`crate::deploy`].
+    #[serde(default)]
+    pub deploy: crate::deploy::DeployConfig,
+    /// `[remote]` settings for network access: retries, backoff,
+    /// connection limit, and offline mode.
+    #[serde(default)]
+    pub remote: RemoteConfig,
+    /// `[feed]` settings choosing which sections the main RSS/Atom feeds
+    /// cover. Also accepted as `[feeds]`.
+    #[serde(default, alias = "feeds")]
+    pub feed: FeedConfig,
+    /// `[sitemap]` settings, such as when to split `sitemap.xml` into
+    /// several files.
+    #[serde(default)]
+    pub sitemap: crate::sitemap::SitemapConfig,
+    /// `[wellknown]` table: `/.well-known/security.txt` and `/humans.txt`.
+    #[serde(default)]
+    pub wellknown: crate::wellknown::WellKnownConfig,
+    /// Optional `[manifest]` table: `site.webmanifest` and its icon set.
+    #[serde(default)]
+    pub manifest: Option<crate::webmanifest::WebManifestConfig>,
+    /// Optional `[llms]` table: `llms.txt` and markdown exports of each page.
+    #[serde(default)]
+    pub llms: Option<crate::llms::LlmsConfig>,
+    /// Optional `[vercel]` table: `vercel.json` with redirects and headers.
+    #[serde(default)]
+    pub vercel: Option<crate::vercel::VercelConfig>,
+    /// Optional `[htaccess]` table: an Apache `.htaccess` with redirects.
+    #[serde(default)]
+    pub htaccess: Option<crate::htaccess::HtaccessConfig>,
+    /// Optional `[headers]` table: a `_headers` file of response headers
+    /// for Netlify and Cloudflare Pages.
+    #[serde(default)]
+    pub headers: Option<crate::headers::HeadersConfig>,
+    /// Optional `[newsletter]` table: `newsletter.html` and
+    /// `newsletter.txt` with the latest posts.
+    #[serde(default)]
+    pub newsletter: Option<crate::newsletter::NewsletterConfig>,
+    /// `[discussions]` table: per-post discussion links and reply counts.
+    #[serde(default)]
+    pub discussions: DiscussionConfig,
+    /// `[link_previews]` table: metadata for `bookmark_of` posts.
+    #[serde(default)]
+    pub link_previews: crate::link_previews::LinkPreviewConfig,
+    /// Optional `[changelog]` table. When present, the named collection
+    /// also produces `releases.json` and a `releases.xml` feed.
+    #[serde(default)]
+    pub changelog: Option<ChangelogConfig>,
+    /// Optional `[external_resources]` allowlist. When present, `bamboo
+    /// build` reports external scripts, stylesheets, and fonts that aren't
+    /// on it.
+    #[serde(default)]
+    pub external_resources: Option<ExternalResourceConfig>,
+    /// `[generate]` switches for feeds, the sitemap, the search index, and
+    /// redirects.
+    #[serde(default)]
+    pub generate: GenerateConfig,
+    /// Build content marked `draft = true`. Usually set for the dev
+    /// environment only; `--drafts` turns it on for a single build.
+    #[serde(default)]
+    pub drafts: bool,
+    /// Build posts and notes dated in the future. Off by default, so
+    /// scheduled content stays out of production builds.
+    #[serde(default)]
+    pub future: bool,
+    /// `[[generated_pages]]` entries: pages rendered from a template and
+    /// data without a markdown file. See
+    /// [`generated_pages`](crate::generated_pages).
+    #[serde(default)]
+    pub generated_pages: Vec<crate::generated_pages::GeneratedPageConfig>,
+    /// Formats rendered for every page, post, note, and collection item
+    /// besides HTML (e.g. `["txt", "json"]`), unless its own `outputs`
+    /// frontmatter says otherwise. See [`outputs`](crate::outputs).
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    /// Worker threads for loading, rendering, hashing, and asset and image
+    /// processing. Defaults to one per core; `--jobs` overrides it.
+    #[serde(default)]
+    pub jobs: Option<usize>,
     /// Arbitrary user fields from `[extra]`, accessible in templates as
     /// `site.config.extra.<name>`.
     #[serde(default)]
@@ -161,6 +333,34 @@ pub struct TocEntry {
     pub title: String,
 }
 
+/// Structural statistics about a rendered body, exposed to templates as
+/// `page.stats` / `post.stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageStats {
+    /// Word count of the rendered body.
+    pub word_count: usize,
+    /// Estimated reading time in minutes (at roughly 200 WPM).
+    pub reading_time: usize,
+    /// Number of headings (`<h1>`–`<h6>`).
+    pub heading_count: usize,
+    /// Number of `<img>` elements, including those produced by shortcodes.
+    pub image_count: usize,
+    /// Number of fenced or indented code blocks.
+    pub code_block_count: usize,
+}
+
+/// A link to another-language version of a piece of content, exposed to
+/// templates as `page.translations` for language switchers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Translation {
+    /// Language tag of the translation (e.g. `"fr"`).
+    pub lang: String,
+    /// Title of the translated content.
+    pub title: String,
+    /// Site-relative URL of the translated content.
+    pub url: String,
+}
+
 /// Content common to all renderable items: pages, posts, and collection items.
 ///
 /// Typically accessed through the containing [`Page`], [`Post`], or
@@ -181,8 +381,19 @@ pub struct Content {
     pub raw_content: String,
     /// Parsed frontmatter, preserving every field for template access.
     pub frontmatter: Frontmatter,
-    /// Absolute path of the source file on disk.
+    /// Output file path relative to the output directory (e.g.
+    /// `posts/hello/index.html`).
     pub path: PathBuf,
+    /// Source markdown file relative to `content/` (e.g.
+    /// `posts/2024-01-15-hello.md`).
+    #[serde(default)]
+    pub source_path: PathBuf,
+    /// When the source file last changed: the date of its latest git
+    /// commit, or its modification time when git doesn't track it. The
+    /// sitemap's `lastmod` when frontmatter has neither `updated` nor
+    /// `date`.
+    #[serde(default)]
+    pub modified: Option<DateTime<Utc>>,
     /// Explicit `template = "..."` frontmatter override, if set.
     #[serde(default)]
     pub template: Option<String>,
@@ -198,12 +409,46 @@ pub struct Content {
     /// Heading-based table of contents, in source order.
     #[serde(default)]
     pub toc: Vec<TocEntry>,
+    /// Word, heading, image, and code-block counts for the rendered body.
+    #[serde(default)]
+    pub stats: PageStats,
+    /// Language of this content: the `lang` frontmatter field, falling
+    /// back to the site `language`.
+    #[serde(default)]
+    pub lang: Option<String>,
+    /// Other-language versions of this content, linked through a shared
+    /// `translation_key` frontmatter value. Sorted by language.
+    #[serde(default)]
+    pub translations: Vec<Translation>,
     /// Resolved URL path of this content within the site (e.g.
     /// `/posts/hello/`).
     #[serde(default)]
     pub url: String,
 }
 
+impl Content {
+    /// `false` when `in_feed = false` frontmatter keeps this out of every
+    /// feed.
+    pub fn in_feed(&self) -> bool {
+        self.frontmatter.get_bool("in_feed").unwrap_or(true)
+    }
+
+    /// `false` when `in_sitemap = false` frontmatter keeps this out of
+    /// `sitemap.xml`.
+    pub fn in_sitemap(&self) -> bool {
+        self.frontmatter.get_bool("in_sitemap").unwrap_or(true)
+    }
+
+    /// `false` when `in_search = false` (or `search = false`) frontmatter
+    /// keeps this out of the search index.
+    pub fn in_search(&self) -> bool {
+        self.frontmatter
+            .get_bool("in_search")
+            .or_else(|| self.frontmatter.get_bool("search"))
+            .unwrap_or(true)
+    }
+}
+
 /// A non-post page: either the home page (`_index.md`) or any top-level /
 /// nested page under `content/`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -250,6 +495,34 @@ pub struct Post {
     /// frontmatter).
     #[serde(default)]
     pub redirect_from: Vec<String>,
+    /// Discussion thread for comments, from `discussion` frontmatter or
+    /// the `[discussions]` URL pattern.
+    #[serde(default)]
+    pub discussion: Option<Discussion>,
+    /// Title, description, and image of the `bookmark_of` page, when
+    /// [link previews](crate::link_previews) are fetched.
+    #[serde(default)]
+    pub link_preview: Option<crate::link_previews::LinkPreview>,
+}
+
+/// A short, optionally untitled post loaded from `content/notes/*.md`.
+/// Its slug and URL come from its timestamp (e.g.
+/// `/notes/2024-06-01-143000/`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Note {
+    /// Shared content fields. Without a `title` in frontmatter, the title
+    /// is the first few words of the body.
+    #[serde(flatten)]
+    pub content: Content,
+    /// Publication time, from `date` frontmatter or the filename prefix
+    /// (e.g. `2024-06-01-1430.md`).
+    pub date: DateTime<Utc>,
+    /// If `true`, excluded from build output unless `--drafts` is passed.
+    #[serde(default)]
+    pub draft: bool,
+    /// Tag names from `tags` frontmatter.
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 /// A named collection of content items, declared by placing a
@@ -260,6 +533,37 @@ pub struct Collection {
     pub name: String,
     /// Items belonging to this collection, in weight/filename order.
     pub items: Vec<CollectionItem>,
+    /// Settings from `_collection.toml`.
+    #[serde(default)]
+    pub config: CollectionConfig,
+    /// The collection's `_index.md`, if it has one: a title, `description`,
+    /// and body for the `/<collection>/` landing page.
+    #[serde(default)]
+    pub index: Option<Content>,
+}
+
+/// Contents of a collection's `_collection.toml`. Unknown keys are
+/// ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CollectionConfig {
+    /// Generate `/<collection>/rss.xml` and `/<collection>/atom.xml`.
+    /// Defaults to `false`.
+    pub feed: bool,
+    /// Order of the collection's feed entries.
+    pub feed_order: CollectionFeedOrder,
+}
+
+/// How a collection feed orders its entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollectionFeedOrder {
+    /// Newest `date` frontmatter first; undated items follow in weight
+    /// order.
+    #[default]
+    Date,
+    /// Collection order: `weight`, then filename.
+    Weight,
 }
 
 /// A single entry in a [`Collection`].
@@ -283,6 +587,12 @@ pub struct Asset {
 /// Arbitrary key/value pairs parsed from a content file's TOML or YAML
 /// frontmatter block. Unknown fields are preserved so they remain available
 /// to templates as `{{ post.frontmatter.<key> }}`.
+///
+/// Values are normalized on construction so both formats look the same:
+/// TOML dates and datetimes (`date = 2024-01-15`) become the strings YAML
+/// produces (`"2024-01-15"`). The typed getters coerce further: see
+/// [`Frontmatter::get_date`], [`Frontmatter::get_i64`], and
+/// [`Frontmatter::get_array`].
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Frontmatter {
     /// Raw frontmatter map. Flattened into the parent struct during
@@ -292,7 +602,20 @@ pub struct Frontmatter {
     pub raw: HashMap<String, Value>,
 }
 
+/// Key the `toml` crate uses when a datetime passes through serde.
+pub(crate) const TOML_DATETIME_KEY: &str = "$__toml_private_datetime";
+
 impl Frontmatter {
+    /// Wraps a parsed frontmatter map, normalizing format-specific values.
+    pub fn new(raw: HashMap<String, Value>) -> Self {
+        Self {
+            raw: raw
+                .into_iter()
+                .map(|(key, value)| (key, normalize_value(value)))
+                .collect(),
+        }
+    }
+
     /// Fetches a frontmatter field and deserializes it into `T`. Returns
     /// `None` if the key is missing or the value cannot be decoded.
     pub fn get<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
@@ -314,28 +637,74 @@ impl Frontmatter {
         self.raw.get(key).and_then(|value| value.as_bool())
     }
 
-    /// Fetches a signed-integer frontmatter field.
+    /// Fetches a signed-integer frontmatter field. Floats with no
+    /// fractional part (`10.0`) are accepted.
     pub fn get_i64(&self, key: &str) -> Option<i64> {
-        self.raw.get(key).and_then(|value| value.as_i64())
+        let value = self.raw.get(key)?;
+        value.as_i64().or_else(|| {
+            value
+                .as_f64()
+                .filter(|number| number.fract() == 0.0)
+                .map(|number| number as i64)
+        })
+    }
+
+    /// Fetches a numeric frontmatter field, integer or float.
+    pub fn get_f64(&self, key: &str) -> Option<f64> {
+        self.raw.get(key).and_then(|value| value.as_f64())
     }
 
-    /// Fetches a frontmatter field as a `Vec<String>`, keeping only entries
-    /// that deserialize as strings.
+    /// Fetches a frontmatter field as a `Vec<String>`. A single string is
+    /// treated as a one-element list (`tags = "rust"`), and numbers and
+    /// booleans inside a list are converted to strings; other entries are
+    /// skipped.
     pub fn get_array(&self, key: &str) -> Option<Vec<String>> {
-        self.raw.get(key).and_then(|value| {
-            value.as_array().map(|array| {
-                array
-                    .iter()
-                    .filter_map(|item| item.as_str().map(String::from))
-                    .collect()
-            })
-        })
+        match self.raw.get(key)? {
+            Value::String(single) => Some(vec![single.clone()]),
+            Value::Array(array) => Some(array.iter().filter_map(scalar_string).collect()),
+            _ => None,
+        }
+    }
+
+    /// Fetches a date or datetime frontmatter field. Accepts everything
+    /// [`parse_datetime`](crate::parsing::parse_datetime) does, whether
+    /// written as a string or as a bare TOML date.
+    pub fn get_date(&self, key: &str) -> Option<DateTime<Utc>> {
+        self.raw
+            .get(key)
+            .and_then(Value::as_str)
+            .and_then(crate::parsing::parse_datetime)
+    }
+}
+
+fn normalize_value(value: Value) -> Value {
+    match value {
+        Value::Object(map) => match map.get(TOML_DATETIME_KEY) {
+            Some(Value::String(datetime)) if map.len() == 1 => Value::String(datetime.clone()),
+            _ => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| (key, normalize_value(value)))
+                    .collect(),
+            ),
+        },
+        Value::Array(items) => Value::Array(items.into_iter().map(normalize_value).collect()),
+        other => other,
+    }
+}
+
+fn scalar_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
 
     fn frontmatter_with(key: &str, value: Value) -> Frontmatter {
         let mut raw = HashMap::new();
@@ -401,11 +770,65 @@ mod tests {
     }
 
     #[test]
-    fn test_get_array_wrong_type() {
+    fn test_get_array_single_string() {
         let frontmatter = frontmatter_with("tags", Value::String("rust".to_string()));
+        assert_eq!(
+            frontmatter.get_array("tags"),
+            Some(vec!["rust".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_get_array_scalars() {
+        let frontmatter = frontmatter_with("tags", serde_json::json!(["rust", 2024, true, {}]));
+        assert_eq!(
+            frontmatter.get_array("tags"),
+            Some(vec![
+                "rust".to_string(),
+                "2024".to_string(),
+                "true".to_string()
+            ])
+        );
+        let frontmatter = frontmatter_with("tags", serde_json::json!(5));
         assert_eq!(frontmatter.get_array("tags"), None);
     }
 
+    #[test]
+    fn test_get_i64_integral_float() {
+        let frontmatter = frontmatter_with("weight", serde_json::json!(10.0));
+        assert_eq!(frontmatter.get_i64("weight"), Some(10));
+        let frontmatter = frontmatter_with("weight", serde_json::json!(10.5));
+        assert_eq!(frontmatter.get_i64("weight"), None);
+        assert_eq!(frontmatter.get_f64("weight"), Some(10.5));
+    }
+
+    #[test]
+    fn test_toml_and_yaml_dates_match() {
+        let path = std::path::Path::new("post.md");
+        let (toml, _) = crate::parsing::extract_frontmatter(
+            "+++\ndate = 2024-01-15\nupdated = 2024-01-16T10:30:00Z\n[extra]\nshown = [2024-02-01]\n+++\n",
+            path,
+        )
+        .unwrap();
+        let (yaml, _) = crate::parsing::extract_frontmatter(
+            "---\ndate: 2024-01-15\nupdated: 2024-01-16T10:30:00Z\nextra:\n  shown: [2024-02-01]\n---\n",
+            path,
+        )
+        .unwrap();
+        for frontmatter in [&toml, &yaml] {
+            assert_eq!(frontmatter.raw["date"], "2024-01-15");
+            assert_eq!(frontmatter.raw["extra"]["shown"][0], "2024-02-01");
+            assert_eq!(
+                frontmatter.get_date("date"),
+                Some(Utc.with_ymd_and_hms(2024, 1, 15, 0, 0, 0).unwrap())
+            );
+            assert_eq!(
+                frontmatter.get_date("updated"),
+                Some(Utc.with_ymd_and_hms(2024, 1, 16, 10, 30, 0).unwrap())
+            );
+        }
+    }
+
     #[test]
     fn test_get_generic() {
         let frontmatter = frontmatter_with("count", serde_json::json!(5));
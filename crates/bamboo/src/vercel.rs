@@ -0,0 +1,170 @@
+//! `vercel.json` for sites deployed to [Vercel](https://vercel.com), written
+//! when `bamboo.toml` has a `[vercel]` table. It carries every redirect the
+//! site declares (`redirect_from` and `redirect_to`, chains flattened) as
+//! permanent redirects, so Vercel answers old URLs with a 308 before the
+//! HTML redirect stubs are ever served, plus the trailing-slash setting and
+//! any configured response headers:
+//!
+//! ```toml
+//! [vercel]
+//! trailing_slash = true
+//!
+//! [[vercel.headers]]
+//! source = "/(.*)"
+//! headers = { "X-Frame-Options" = "DENY" }
+//! ```
+
+use crate::error::Result;
+use crate::redirects::{base_path, flattened_redirects};
+use crate::sink::RenderSink;
+use crate::types::Site;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Output path of the Vercel configuration.
+pub const VERCEL_FILE: &str = "vercel.json";
+
+/// `[vercel]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VercelConfig {
+    /// Vercel's `trailingSlash`: `true` redirects `/about` to `/about/`,
+    /// matching the URLs bamboo generates. Defaults to `true`.
+    #[serde(default = "default_trailing_slash")]
+    pub trailing_slash: bool,
+    /// Response headers, each applied to the paths matching its `source`.
+    #[serde(default)]
+    pub headers: Vec<VercelHeaderRule>,
+}
+
+fn default_trailing_slash() -> bool {
+    true
+}
+
+impl Default for VercelConfig {
+    fn default() -> Self {
+        Self {
+            trailing_slash: default_trailing_slash(),
+            headers: Vec::new(),
+        }
+    }
+}
+
+/// One `[[vercel.headers]]` entry.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct VercelHeaderRule {
+    /// Vercel path pattern, such as `/(.*)` or `/fonts/(.*)`.
+    pub source: String,
+    /// Header names and values.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Renders `vercel.json` for `site`. Redirects are left out when
+/// `[generate] redirects = false`.
+pub fn render_vercel_json(site: &Site, config: &VercelConfig) -> String {
+    let base_path = base_path(site);
+    let local = |url: &str| {
+        if url.starts_with('/') && !url.starts_with("//") {
+            format!("{base_path}{url}")
+        } else {
+            url.to_string()
+        }
+    };
+
+    let mut vercel = json!({ "trailingSlash": config.trailing_slash });
+    if site.config.generate.redirects {
+        let redirects: Vec<Value> = flattened_redirects(site)
+            .into_iter()
+            .map(|(source, destination)| {
+                json!({
+                    "source": local(&source),
+                    "destination": local(&destination),
+                    "permanent": true,
+                })
+            })
+            .collect();
+        if !redirects.is_empty() {
+            vercel["redirects"] = json!(redirects);
+        }
+    }
+    if !config.headers.is_empty() {
+        let headers: Vec<Value> = config
+            .headers
+            .iter()
+            .map(|rule| {
+                let headers: Vec<Value> = rule
+                    .headers
+                    .iter()
+                    .map(|(key, value)| json!({ "key": key, "value": value }))
+                    .collect();
+                json!({ "source": rule.source, "headers": headers })
+            })
+            .collect();
+        vercel["headers"] = json!(headers);
+    }
+    serde_json::to_string_pretty(&vercel).unwrap_or_default() + "\n"
+}
+
+/// Writes `vercel.json` into `output` when `[vercel]` is configured.
+pub fn generate_vercel_json(site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
+    let Some(config) = site.config.vercel.as_ref() else {
+        return Ok(());
+    };
+    output.write_file(
+        Path::new(VERCEL_FILE),
+        render_vercel_json(site, config).as_bytes(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::MemorySink;
+    use crate::test_site::site;
+
+    #[test]
+    fn test_render_vercel_json() {
+        let mut site = site(
+            "[[vercel.headers]]\nsource = \"/(.*)\"\nheaders = { \"X-Frame-Options\" = \"DENY\" }",
+        );
+        site.config.base_url = "https://example.com/blog/".to_string();
+        site.pages[0].redirect_from = vec!["/old-about".to_string()];
+        let vercel: Value = serde_json::from_str(&render_vercel_json(
+            &site,
+            site.config.vercel.as_ref().unwrap(),
+        ))
+        .unwrap();
+
+        assert_eq!(
+            vercel,
+            json!({
+                "trailingSlash": true,
+                "redirects": [
+                    {"source": "/blog/old-about/", "destination": "/blog/about/", "permanent": true}
+                ],
+                "headers": [
+                    {"source": "/(.*)", "headers": [{"key": "X-Frame-Options", "value": "DENY"}]}
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_generate_vercel_json() {
+        let mut site = site("[vercel]\ntrailing_slash = false");
+        site.config.generate.redirects = false;
+        let sink = MemorySink::new();
+        generate_vercel_json(&site, &sink).unwrap();
+
+        let vercel: Value =
+            serde_json::from_slice(&sink.read_file(Path::new(VERCEL_FILE)).unwrap()).unwrap();
+        assert_eq!(vercel, json!({ "trailingSlash": false }));
+
+        site.config.vercel = None;
+        let sink = MemorySink::new();
+        generate_vercel_json(&site, &sink).unwrap();
+        assert!(sink.paths().is_empty());
+    }
+}
@@ -0,0 +1,348 @@
+//! `bamboo deploy vercel`: uploading the output to
+//! [Vercel](https://vercel.com) through its API, without the Vercel CLI.
+//!
+//! ```toml
+//! [deploy.vercel]
+//! project = "my-site"
+//! ```
+//!
+//! Every output file is uploaded by its SHA-1 through Vercel's files API,
+//! then a deployment listing them is created for the project. Deployments
+//! are previews unless `production` is requested, so every run gets its own
+//! preview URL and the live site only changes on purpose. The access token
+//! is read from `VERCEL_TOKEN` (or the variable named by `token_env`) and
+//! never from `bamboo.toml`. `VERCEL_PROJECT_ID` and `VERCEL_ORG_ID` can
+//! stand in for `project` and `team_id`, matching the Vercel CLI.
+//!
+//! Vercel's `vercel.json` for the deployment comes from the `[vercel]`
+//! table; see [`crate::vercel`].
+//!
+//! Requests go through [`RemoteClient::send`], which keeps the token out of
+//! the process list.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use crate::error::{BambooError, IoContext, Result};
+use crate::remote::{RemoteClient, Request, encode_query_value};
+
+const TARGET: &str = "vercel";
+
+/// Vercel API root.
+pub const API_URL: &str = "https://api.vercel.com";
+
+/// Environment variable consulted when `project` isn't set.
+pub const PROJECT_ENV_VAR: &str = "VERCEL_PROJECT_ID";
+
+/// Environment variable consulted when `team_id` isn't set.
+pub const TEAM_ENV_VAR: &str = "VERCEL_ORG_ID";
+
+/// How often, and how many times, a deployment is checked while Vercel
+/// processes it.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const POLL_ATTEMPTS: u32 = 90;
+
+/// The `[deploy.vercel]` table in `bamboo.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VercelDeployConfig {
+    /// Name or ID of the project to deploy to. Falls back to
+    /// `VERCEL_PROJECT_ID`.
+    pub project: Option<String>,
+    /// Team that owns the project, for projects outside the token owner's
+    /// personal account. Falls back to `VERCEL_ORG_ID`.
+    pub team_id: Option<String>,
+    /// Environment variable holding the access token. Defaults to
+    /// `VERCEL_TOKEN`.
+    pub token_env: String,
+}
+
+impl Default for VercelDeployConfig {
+    fn default() -> Self {
+        Self {
+            project: None,
+            team_id: None,
+            token_env: "VERCEL_TOKEN".to_string(),
+        }
+    }
+}
+
+impl VercelDeployConfig {
+    /// The project and access token to deploy with, failing if either is
+    /// missing.
+    pub fn credentials(&self) -> Result<(String, String)> {
+        let missing = |message: String| BambooError::Deploy {
+            target: TARGET,
+            message,
+        };
+        let project = self
+            .project
+            .clone()
+            .or_else(|| std::env::var(PROJECT_ENV_VAR).ok())
+            .filter(|project| !project.is_empty())
+            .ok_or_else(|| {
+                missing(format!(
+                    "no project; set `project` under [deploy.vercel] or {PROJECT_ENV_VAR}"
+                ))
+            })?;
+        let token = std::env::var(&self.token_env)
+            .ok()
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| missing(format!("{} is not set", self.token_env)))?;
+        Ok((project, token))
+    }
+
+    /// The team to deploy as, if any.
+    pub fn team(&self) -> Option<String> {
+        self.team_id
+            .clone()
+            .or_else(|| std::env::var(TEAM_ENV_VAR).ok())
+            .filter(|team| !team.is_empty())
+    }
+}
+
+/// One output file of a deployment, as Vercel's API lists it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VercelFile {
+    /// `/`-separated path relative to the output directory.
+    pub file: String,
+    /// Hex SHA-1 of the contents, which Vercel stores the upload under.
+    pub sha: String,
+    /// Size in bytes.
+    pub size: u64,
+}
+
+/// A deployment as reported by Vercel.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VercelDeployment {
+    /// Deployment ID.
+    pub id: String,
+    /// Host name of this deployment alone, e.g.
+    /// `my-site-3k2j1h.vercel.app`: the preview URL without `https://`.
+    #[serde(default)]
+    pub url: String,
+    /// Processing state: `QUEUED`, `BUILDING`, `READY`, `ERROR`, or
+    /// `CANCELED`.
+    #[serde(default)]
+    pub ready_state: String,
+    /// Host names assigned to the deployment once ready, such as the
+    /// project's production domains.
+    #[serde(default)]
+    pub alias: Vec<String>,
+    /// Why processing failed, when `ready_state` is `ERROR`.
+    #[serde(default)]
+    pub error_message: Option<String>,
+}
+
+impl VercelDeployment {
+    /// `https://` URL of this deployment alone.
+    pub fn preview_url(&self) -> String {
+        format!("https://{}", self.url)
+    }
+}
+
+/// Lists every file under `output_dir` with its SHA-1 and size.
+pub fn deployment_files(output_dir: &Path) -> Result<Vec<VercelFile>> {
+    crate::verify::output_files(output_dir)
+        .into_par_iter()
+        .map(|file| {
+            let path = output_dir.join(&file);
+            let contents = fs::read(&path).io_context("read", &path)?;
+            Ok(VercelFile {
+                sha: format!("{:x}", Sha1::digest(&contents)),
+                size: contents.len() as u64,
+                file,
+            })
+        })
+        .collect()
+}
+
+/// The body that creates a deployment of `files` in `project`: a
+/// production deployment when `production` is set, otherwise a preview.
+/// No framework is set, so Vercel serves the files as they are.
+pub fn deployment_request(
+    project: &str,
+    files: &[VercelFile],
+    production: bool,
+) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "name": project,
+        "project": project,
+        "files": files,
+        "projectSettings": { "framework": null },
+    });
+    if production {
+        body["target"] = "production".into();
+    }
+    body
+}
+
+/// Uploads `output_dir` as a deployment of the configured project and
+/// waits for Vercel to finish processing it, failing if it's still
+/// building after three minutes. Without `production` the deployment is a
+/// preview that doesn't replace the live site.
+pub fn deploy(
+    client: &RemoteClient,
+    output_dir: &Path,
+    config: &VercelDeployConfig,
+    production: bool,
+) -> Result<VercelDeployment> {
+    let deploy_error = |message: String| BambooError::Deploy {
+        target: TARGET,
+        message,
+    };
+    let (project, token) = config.credentials()?;
+    let authorization = format!("Bearer {token}");
+    let team_query = config
+        .team()
+        .map(|team| format!("?teamId={}", encode_query_value(&team)))
+        .unwrap_or_default();
+
+    let files = deployment_files(output_dir)?;
+    if files.is_empty() {
+        return Err(deploy_error(format!(
+            "{} has no files to deploy",
+            output_dir.display()
+        )));
+    }
+    let mut uploads: Vec<&VercelFile> = files.iter().collect();
+    uploads.sort_by(|a, b| a.sha.cmp(&b.sha));
+    uploads.dedup_by(|a, b| a.sha == b.sha);
+    let upload_url = format!("{API_URL}/v2/files{team_query}");
+    uploads.par_iter().try_for_each(|file| {
+        let path = output_dir.join(&file.file);
+        client
+            .send(
+                &Request::new("POST", &upload_url)
+                    .header("Authorization", &authorization)
+                    .header("Content-Type", "application/octet-stream")
+                    .header("x-vercel-digest", &file.sha)
+                    .body(&path),
+            )
+            .map(|_| ())
+    })?;
+
+    let body = deployment_request(&project, &files, production);
+    let body_file = tempfile::Builder::new()
+        .prefix("bamboo-vercel-")
+        .suffix(".json")
+        .tempfile()
+        .io_context("create", &std::env::temp_dir())?;
+    fs::write(body_file.path(), body.to_string()).io_context("write", body_file.path())?;
+    let url = format!("{API_URL}/v13/deployments{team_query}");
+    let response = client.send(
+        &Request::new("POST", &url)
+            .header("Authorization", &authorization)
+            .header("Content-Type", "application/json")
+            .body(body_file.path()),
+    )?;
+    let mut deployment = parse_deployment(&response.body)?;
+
+    let url = format!("{API_URL}/v13/deployments/{}{team_query}", deployment.id);
+    for _ in 0..POLL_ATTEMPTS {
+        match deployment.ready_state.as_str() {
+            "READY" => return Ok(deployment),
+            "ERROR" | "CANCELED" => {
+                return Err(deploy_error(format!(
+                    "deployment {} failed: {}",
+                    deployment.id,
+                    deployment
+                        .error_message
+                        .as_deref()
+                        .unwrap_or(&deployment.ready_state.to_lowercase())
+                )));
+            }
+            _ => {}
+        }
+        std::thread::sleep(POLL_INTERVAL);
+        let response =
+            client.send(&Request::new("GET", &url).header("Authorization", &authorization))?;
+        deployment = parse_deployment(&response.body)?;
+    }
+    Err(deploy_error(format!(
+        "deployment {} is still {} after {} seconds; check the Vercel dashboard",
+        deployment.id,
+        deployment.ready_state.to_lowercase(),
+        (POLL_INTERVAL * POLL_ATTEMPTS).as_secs()
+    )))
+}
+
+fn parse_deployment(body: &[u8]) -> Result<VercelDeployment> {
+    serde_json::from_slice(body).map_err(|error| BambooError::Deploy {
+        target: TARGET,
+        message: format!("unexpected response from Vercel: {error}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deployment_files_and_request() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("posts")).unwrap();
+        fs::write(dir.path().join("index.html"), "").unwrap();
+        fs::write(dir.path().join("posts/a.html"), "post").unwrap();
+
+        let files = deployment_files(dir.path()).unwrap();
+
+        assert_eq!(
+            files[1],
+            VercelFile {
+                file: "posts/a.html".to_string(),
+                sha: "9b466094ec991a03cb95c489c19c4d75635f0ae5".to_string(),
+                size: 4,
+            }
+        );
+        let preview = deployment_request("my-site", &files, false);
+        assert_eq!(preview["name"], "my-site");
+        assert_eq!(preview["files"][0]["file"], "index.html");
+        assert_eq!(
+            preview["projectSettings"]["framework"],
+            serde_json::Value::Null
+        );
+        assert!(preview.get("target").is_none());
+        assert_eq!(
+            deployment_request("my-site", &files, true)["target"],
+            "production"
+        );
+    }
+
+    #[test]
+    fn test_deploy_requires_project_and_token() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let client = RemoteClient::default().offline(true);
+        let config = VercelDeployConfig {
+            project: Some("my-site".to_string()),
+            token_env: "BAMBOO_TEST_VERCEL_TOKEN_UNSET".to_string(),
+            ..VercelDeployConfig::default()
+        };
+        let error = deploy(&client, dir.path(), &config, false).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("BAMBOO_TEST_VERCEL_TOKEN_UNSET is not set"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_parse_deployment() {
+        let deployment = parse_deployment(
+            br#"{"id": "dpl_1", "url": "my-site-abc.vercel.app", "readyState": "READY", "alias": ["my-site.vercel.app"], "inspectorUrl": "https://vercel.com/x"}"#,
+        )
+        .unwrap();
+        assert_eq!(deployment.id, "dpl_1");
+        assert_eq!(deployment.ready_state, "READY");
+        assert_eq!(deployment.preview_url(), "https://my-site-abc.vercel.app");
+        assert_eq!(deployment.alias, ["my-site.vercel.app"]);
+        assert_eq!(deployment.error_message, None);
+    }
+}
@@ -0,0 +1,291 @@
+//! Checks a deployed site against the local build. [`verify_deploy`]
+//! samples files from the output directory, fetches the same paths from
+//! the live site, and compares SHA-256 hashes of the bodies and the
+//! `Content-Type` each file should be served with, catching stale CDN
+//! caches, partial uploads, and misconfigured hosts.
+
+use crate::error::Result;
+use crate::remote::RemoteClient;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Number of files [`verify_deploy`] checks unless told otherwise.
+pub const DEFAULT_SAMPLE: usize = 20;
+
+/// A way the deployed copy of a file differs from the local build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyIssue {
+    /// The URL couldn't be fetched, usually a 404 from a partial deploy.
+    Unreachable {
+        /// Absolute URL that was fetched.
+        url: String,
+        /// Why the fetch failed.
+        message: String,
+    },
+    /// The deployed body differs from the local file.
+    Mismatch {
+        /// Absolute URL that was fetched.
+        url: String,
+        /// SHA-256 of the local file.
+        local_hash: String,
+        /// SHA-256 of the deployed body.
+        remote_hash: String,
+        /// The response's `Age` header, a hint that a CDN served a cached
+        /// copy.
+        age: Option<String>,
+    },
+    /// The deployed file is served with an unexpected `Content-Type`.
+    ContentType {
+        /// Absolute URL that was fetched.
+        url: String,
+        /// MIME type the file extension calls for.
+        expected: &'static str,
+        /// MIME type the server sent, or `None` without the header.
+        actual: Option<String>,
+    },
+}
+
+impl fmt::Display for VerifyIssue {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreachable { url, message } => write!(formatter, "{url}: {message}"),
+            Self::Mismatch {
+                url,
+                local_hash,
+                remote_hash,
+                age,
+            } => {
+                write!(
+                    formatter,
+                    "{url}: content differs (local {}, deployed {})",
+                    &local_hash[..12],
+                    &remote_hash[..12]
+                )?;
+                if let Some(age) = age {
+                    write!(formatter, ", cached for {age}s")?;
+                }
+                Ok(())
+            }
+            Self::ContentType {
+                url,
+                expected,
+                actual,
+            } => write!(
+                formatter,
+                "{url}: served as {}, expected {expected}",
+                actual.as_deref().unwrap_or("no Content-Type")
+            ),
+        }
+    }
+}
+
+/// Outcome of [`verify_deploy`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Number of files fetched.
+    pub checked: usize,
+    /// Number of files in the local build.
+    pub total: usize,
+    /// Problems found, in sample order.
+    pub issues: Vec<VerifyIssue>,
+}
+
+/// Fetches up to `sample` files of the build in `output_dir` from
+/// `base_url` and compares them with the local copies. A `sample` of 0
+/// checks every file.
+pub fn verify_deploy(
+    client: &RemoteClient,
+    base_url: &str,
+    output_dir: &Path,
+    sample: usize,
+) -> Result<VerifyReport> {
+    let files = output_files(output_dir);
+    let sampled = sample_files(&files, sample);
+    let base_url = base_url.trim_end_matches('/');
+
+    let issues: Vec<Vec<VerifyIssue>> = sampled
+        .par_iter()
+        .map(|path| -> Result<Vec<VerifyIssue>> {
+            let url = format!("{base_url}{}", url_path(path));
+            let local = std::fs::read(output_dir.join(path))?;
+            Ok(compare(client, &url, path, &local))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(VerifyReport {
+        checked: sampled.len(),
+        total: files.len(),
+        issues: issues.into_iter().flatten().collect(),
+    })
+}
+
+fn compare(client: &RemoteClient, url: &str, path: &str, local: &[u8]) -> Vec<VerifyIssue> {
+    let response = match client.fetch_response(url) {
+        Ok(response) => response,
+        Err(error) => {
+            return vec![VerifyIssue::Unreachable {
+                url: url.to_string(),
+                message: error.to_string(),
+            }];
+        }
+    };
+
+    let mut issues = Vec::new();
+    let local_hash = sha256(local);
+    let remote_hash = sha256(&response.body);
+    if local_hash != remote_hash {
+        issues.push(VerifyIssue::Mismatch {
+            url: url.to_string(),
+            local_hash,
+            remote_hash,
+            age: response.header("age").map(str::to_string),
+        });
+    }
+    if response.status != 0
+        && let Some(expected) = expected_content_type(path)
+    {
+        let actual = response
+            .header("content-type")
+            .map(|value| value.split(';').next().unwrap_or("").trim().to_string());
+        if !actual.as_deref().is_some_and(|actual| {
+            actual.eq_ignore_ascii_case(expected)
+                || (expected == "text/javascript"
+                    && actual.eq_ignore_ascii_case("application/javascript"))
+        }) {
+            issues.push(VerifyIssue::ContentType {
+                url: url.to_string(),
+                expected,
+                actual,
+            });
+        }
+    }
+    issues
+}
+
+/// Every file under `output_dir`, as sorted `/`-separated relative paths.
+pub fn output_files(output_dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(output_dir).ok()?;
+            let parts: Vec<_> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect();
+            Some(parts.join("/"))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Up to `count` of `files`, spread evenly across the sorted list so every
+/// section gets checked, with `index.html` always first. A `count` of 0
+/// returns every file.
+pub fn sample_files(files: &[String], count: usize) -> Vec<String> {
+    if count == 0 || count >= files.len() {
+        return files.to_vec();
+    }
+    let mut sampled: Vec<String> = files
+        .iter()
+        .filter(|file| *file == "index.html")
+        .cloned()
+        .collect();
+    let rest: Vec<&String> = files.iter().filter(|file| *file != "index.html").collect();
+    let wanted = count - sampled.len();
+    sampled.extend((0..wanted).map(|index| rest[index * rest.len() / wanted].clone()));
+    sampled
+}
+
+/// Site URL path of an output file: `posts/a/index.html` is served as
+/// `/posts/a/`.
+pub fn url_path(file: &str) -> String {
+    if file == "index.html" || file.ends_with("/index.html") {
+        format!("/{}", &file[..file.len() - "index.html".len()])
+    } else {
+        format!("/{file}")
+    }
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn expected_content_type(path: &str) -> Option<&'static str> {
+    let extension = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "html" => "text/html",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn files(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_url_path() {
+        assert_eq!(url_path("index.html"), "/");
+        assert_eq!(url_path("posts/a/index.html"), "/posts/a/");
+        assert_eq!(url_path("rss.xml"), "/rss.xml");
+    }
+
+    #[test]
+    fn test_sample_files() {
+        let all = files(&["a.css", "b/index.html", "c.xml", "d.png", "index.html"]);
+        assert_eq!(sample_files(&all, 0), all);
+        assert_eq!(sample_files(&all, 10), all);
+        assert_eq!(
+            sample_files(&all, 3),
+            files(&["index.html", "a.css", "c.xml"])
+        );
+    }
+
+    #[test]
+    fn test_verify_deploy_against_file_urls() {
+        let local = tempfile::TempDir::new().unwrap();
+        let deployed = tempfile::TempDir::new().unwrap();
+        for dir in [local.path(), deployed.path()] {
+            fs::write(dir.join("feed.xml"), "<rss/>").unwrap();
+        }
+        fs::write(local.path().join("data.json"), "{\"new\":true}").unwrap();
+        fs::write(deployed.path().join("data.json"), "{\"new\":false}").unwrap();
+        fs::write(local.path().join("style.css"), "body{}").unwrap();
+
+        let client = RemoteClient::new(crate::remote::RemoteConfig {
+            retries: 0,
+            ..Default::default()
+        })
+        .offline(false);
+        let base_url = format!("file://{}", deployed.path().display());
+        let report = verify_deploy(&client, &base_url, local.path(), 0).unwrap();
+
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.issues.len(), 2, "{:?}", report.issues);
+        assert!(matches!(
+            &report.issues[0],
+            VerifyIssue::Mismatch { url, .. } if url.ends_with("/data.json")
+        ));
+        assert!(matches!(
+            &report.issues[1],
+            VerifyIssue::Unreachable { url, .. } if url.ends_with("/style.css")
+        ));
+    }
+}
@@ -0,0 +1,250 @@
+//! `site.webmanifest`, the [web app manifest](https://www.w3.org/TR/appmanifest/)
+//! that makes a site installable. It is written from the `[manifest]` table
+//! of `bamboo.toml`:
+//!
+//! ```toml
+//! [manifest]
+//! short_name = "Blog"
+//! theme_color = "#1e293b"
+//! icon = "/images/logo.png"
+//! ```
+//!
+//! `icon` is resized by the image pipeline into a square PNG for each of
+//! `icon_sizes`, written to `icons/`, and listed in the manifest. Every page
+//! head links the manifest and, with `theme_color`, gets a matching
+//! `<meta name="theme-color">`.
+
+use crate::error::{BambooError, Result};
+use crate::sink::RenderSink;
+use crate::types::Site;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Output path of the manifest.
+pub const MANIFEST_FILE: &str = "site.webmanifest";
+/// Directory the resized icons are written to.
+pub const ICONS_DIR: &str = "icons";
+
+/// `[manifest]` table from `bamboo.toml`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebManifestConfig {
+    /// Full app name. Defaults to the site title.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Name shown under the home screen icon. Defaults to `name`.
+    #[serde(default)]
+    pub short_name: Option<String>,
+    /// Defaults to the site description.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// URL opened when the app launches, relative to the manifest. Defaults
+    /// to `./`, the site root.
+    #[serde(default = "default_start_url")]
+    pub start_url: String,
+    /// `standalone`, `fullscreen`, `minimal-ui`, or `browser`. Defaults to
+    /// `standalone`.
+    #[serde(default = "default_display")]
+    pub display: String,
+    /// Browser UI color, also emitted as `<meta name="theme-color">`.
+    #[serde(default)]
+    pub theme_color: Option<String>,
+    /// Splash screen background color.
+    #[serde(default)]
+    pub background_color: Option<String>,
+    /// Source image for the icon set, relative to the output directory (so
+    /// any file from `static/` or the theme's `static/`). Square images
+    /// work best; others are cropped to the center.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Edge lengths of the generated icons in pixels. Defaults to 192 and
+    /// 512, the sizes browsers require for installation.
+    #[serde(default = "default_icon_sizes")]
+    pub icon_sizes: Vec<u32>,
+}
+
+fn default_start_url() -> String {
+    "./".to_string()
+}
+
+fn default_display() -> String {
+    "standalone".to_string()
+}
+
+fn default_icon_sizes() -> Vec<u32> {
+    vec![192, 512]
+}
+
+impl Default for WebManifestConfig {
+    fn default() -> Self {
+        Self {
+            name: None,
+            short_name: None,
+            description: None,
+            start_url: default_start_url(),
+            display: default_display(),
+            theme_color: None,
+            background_color: None,
+            icon: None,
+            icon_sizes: default_icon_sizes(),
+        }
+    }
+}
+
+/// One entry of the manifest's `icons` array.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestIcon {
+    /// Icon URL, relative to the manifest.
+    pub src: String,
+    /// `<size>x<size>`.
+    pub sizes: String,
+    /// MIME type.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+}
+
+#[derive(Serialize)]
+struct WebManifest<'a> {
+    name: &'a str,
+    short_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lang: Option<&'a str>,
+    start_url: &'a str,
+    scope: &'static str,
+    display: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    theme_color: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    background_color: Option<&'a str>,
+    icons: &'a [ManifestIcon],
+}
+
+/// Output path of the icon `size` pixels square.
+pub fn icon_path(size: u32) -> PathBuf {
+    Path::new(ICONS_DIR).join(format!("icon-{size}x{size}.png"))
+}
+
+/// The `icons` array for `config`: one PNG per size, or none without an
+/// `icon`.
+pub fn manifest_icons(config: &WebManifestConfig) -> Vec<ManifestIcon> {
+    if config.icon.is_none() {
+        return Vec::new();
+    }
+    config
+        .icon_sizes
+        .iter()
+        .map(|&size| ManifestIcon {
+            src: format!("{ICONS_DIR}/icon-{size}x{size}.png"),
+            sizes: format!("{size}x{size}"),
+            kind: "image/png",
+        })
+        .collect()
+}
+
+/// Renders the manifest JSON for `site`.
+pub fn render_manifest(site: &Site, config: &WebManifestConfig) -> String {
+    let name = config.name.as_deref().unwrap_or(&site.config.title);
+    let manifest = WebManifest {
+        name,
+        short_name: config.short_name.as_deref().unwrap_or(name),
+        description: config
+            .description
+            .as_deref()
+            .or(site.config.description.as_deref()),
+        lang: site.config.language.as_deref(),
+        start_url: &config.start_url,
+        scope: "./",
+        display: &config.display,
+        theme_color: config.theme_color.as_deref(),
+        background_color: config.background_color.as_deref(),
+        icons: &manifest_icons(config),
+    };
+    serde_json::to_string_pretty(&manifest).unwrap_or_default() + "\n"
+}
+
+/// Writes the icon set and `site.webmanifest` into `output` when
+/// `[manifest]` is configured. The source icon is read back from `output`,
+/// so static files must already be copied.
+pub fn generate_webmanifest(site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
+    let Some(config) = site.config.manifest.as_ref() else {
+        return Ok(());
+    };
+    if let Some(icon) = config.icon.as_deref() {
+        let source_path = Path::new(icon.trim_start_matches('/'));
+        let source = output
+            .read_file(source_path)
+            .ok_or_else(|| BambooError::ImageProcessing {
+                message: format!(
+                    "failed to load manifest icon {}: not found in the output",
+                    source_path.display()
+                ),
+            })?;
+        for &size in &config.icon_sizes {
+            let png = crate::images::square_png(&source, size)?;
+            output.write_file(&icon_path(size), &png)?;
+        }
+    }
+    output.write_file(
+        Path::new(MANIFEST_FILE),
+        render_manifest(site, config).as_bytes(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::MemorySink;
+    use crate::test_site::site;
+
+    #[test]
+    fn test_render_manifest() {
+        let site = site(
+            "[manifest]\nshort_name = \"Blog\"\ntheme_color = \"#1e293b\"\nicon = \"/logo.png\"",
+        );
+        let config = site.config.manifest.as_ref().unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_str(&render_manifest(&site, config)).unwrap();
+
+        assert_eq!(manifest["name"], site.config.title.as_str());
+        assert_eq!(manifest["short_name"], "Blog");
+        assert_eq!(manifest["start_url"], "./");
+        assert_eq!(manifest["display"], "standalone");
+        assert_eq!(manifest["theme_color"], "#1e293b");
+        assert!(manifest.get("background_color").is_none());
+        assert_eq!(
+            manifest["icons"],
+            serde_json::json!([
+                {"src": "icons/icon-192x192.png", "sizes": "192x192", "type": "image/png"},
+                {"src": "icons/icon-512x512.png", "sizes": "512x512", "type": "image/png"},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_generate_webmanifest() {
+        let site = site("[manifest]\nicon =\"/logo.png\"\nicon_sizes = [32]");
+        let sink = MemorySink::new();
+        assert!(generate_webmanifest(&site, &sink).is_err());
+
+        let mut logo = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::new(64, 48)
+            .write_to(&mut logo, image::ImageFormat::Png)
+            .unwrap();
+        sink.write_file(Path::new("logo.png"), logo.get_ref())
+            .unwrap();
+        generate_webmanifest(&site, &sink).unwrap();
+
+        let icon = image::load_from_memory(&sink.read_file(&icon_path(32)).unwrap()).unwrap();
+        assert_eq!((icon.width(), icon.height()), (32, 32));
+        assert!(sink.file_exists(Path::new(MANIFEST_FILE)));
+    }
+
+    #[test]
+    fn test_generate_webmanifest_without_config() {
+        let site = site("");
+        let sink = MemorySink::new();
+        generate_webmanifest(&site, &sink).unwrap();
+        assert!(sink.paths().is_empty());
+    }
+}
@@ -0,0 +1,347 @@
+//! `/.well-known/security.txt` ([RFC 9116](https://www.rfc-editor.org/rfc/rfc9116))
+//! and `/humans.txt`, written from the `[wellknown]` table of
+//! `bamboo.toml`:
+//!
+//! ```toml
+//! [wellknown.security]
+//! contact = ["mailto:security@example.com"]
+//! expires = "2025-12-31"
+//!
+//! [[wellknown.humans.team]]
+//! name = "Ada Lovelace"
+//! role = "Developer"
+//! ```
+//!
+//! security.txt must carry an `Expires` date, and a stale file tells
+//! researchers the contact can't be trusted, so the build fails once
+//! `expires` has passed. [`security_txt_warnings`] flags dates that are
+//! close to passing or further out than the RFC recommends.
+
+use crate::error::{BambooError, Result};
+use crate::parsing::parse_datetime;
+use crate::sink::RenderSink;
+use crate::types::Site;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::path::Path;
+
+/// Output path of security.txt.
+pub const SECURITY_TXT: &str = ".well-known/security.txt";
+/// Output path of humans.txt.
+pub const HUMANS_TXT: &str = "humans.txt";
+
+/// `[wellknown]` table from `bamboo.toml`. Each file is written only when
+/// its table is present.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WellKnownConfig {
+    /// `[wellknown.security]`: fields of `/.well-known/security.txt`.
+    pub security: Option<SecurityTxtConfig>,
+    /// `[wellknown.humans]`: credits for `/humans.txt`.
+    pub humans: Option<HumansTxtConfig>,
+}
+
+/// Fields of security.txt. `contact` and `expires` are required.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityTxtConfig {
+    /// `Contact` URIs, such as `mailto:` or `https://` links, in order of
+    /// preference.
+    pub contact: Vec<String>,
+    /// `Expires` date, as a quoted or bare TOML date or datetime.
+    #[serde(deserialize_with = "date_string")]
+    pub expires: String,
+    /// `Encryption` key URLs.
+    pub encryption: Vec<String>,
+    /// `Acknowledgments` page URL.
+    pub acknowledgments: Option<String>,
+    /// `Policy` page URL.
+    pub policy: Option<String>,
+    /// `Hiring` page URL.
+    pub hiring: Option<String>,
+    /// `Preferred-Languages`, such as `["en", "fr"]`.
+    pub preferred_languages: Vec<String>,
+}
+
+/// Credits of humans.txt.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HumansTxtConfig {
+    /// People behind the site, under `/* TEAM */`.
+    pub team: Vec<HumansTxtMember>,
+    /// Thanks, one per line under `/* THANKS */`.
+    pub thanks: Vec<String>,
+    /// `Standards` line of `/* SITE */`, such as `["HTML5", "CSS3"]`.
+    pub standards: Vec<String>,
+    /// `Components` line of `/* SITE */`.
+    pub components: Vec<String>,
+}
+
+/// One `[[wellknown.humans.team]]` entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HumansTxtMember {
+    /// Name.
+    pub name: String,
+    /// Role, written as the line's label (`Developer: Ada`). Defaults to
+    /// `Name`.
+    pub role: Option<String>,
+    /// Email address, URL, or handle.
+    pub contact: Option<String>,
+    /// City or country.
+    pub location: Option<String>,
+}
+
+fn date_string<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Date {
+        Toml(toml::value::Datetime),
+        Text(String),
+    }
+    Ok(match Date::deserialize(deserializer)? {
+        Date::Toml(date) => date.to_string(),
+        Date::Text(date) => date,
+    })
+}
+
+impl SecurityTxtConfig {
+    /// The parsed `expires` date. Fails when it is missing, unparsable, or
+    /// before `now`, or when there is no contact.
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+        let invalid = |message: String| BambooError::SecurityTxt { message };
+        if self.contact.is_empty() {
+            return Err(invalid("at least one `contact` is required".to_string()));
+        }
+        let expires = parse_datetime(&self.expires).ok_or_else(|| {
+            invalid(format!(
+                "`expires` must be a date such as 2025-12-31, got '{}'",
+                self.expires
+            ))
+        })?;
+        if expires <= now {
+            return Err(invalid(format!(
+                "`expires` date {} has passed; set a new one",
+                expires.format("%Y-%m-%d")
+            )));
+        }
+        Ok(expires)
+    }
+}
+
+/// Renders security.txt for `site`, validating it against `now`.
+pub fn render_security_txt(
+    site: &Site,
+    config: &SecurityTxtConfig,
+    now: DateTime<Utc>,
+) -> Result<String> {
+    let expires = config.validate(now)?;
+    let mut lines: Vec<String> = config
+        .contact
+        .iter()
+        .map(|contact| format!("Contact: {contact}"))
+        .collect();
+    lines.push(format!("Expires: {}", expires.to_rfc3339()));
+    lines.extend(
+        config
+            .encryption
+            .iter()
+            .map(|key| format!("Encryption: {key}")),
+    );
+    for (field, value) in [
+        ("Acknowledgments", &config.acknowledgments),
+        ("Policy", &config.policy),
+        ("Hiring", &config.hiring),
+    ] {
+        if let Some(value) = value {
+            lines.push(format!("{field}: {value}"));
+        }
+    }
+    if !config.preferred_languages.is_empty() {
+        lines.push(format!(
+            "Preferred-Languages: {}",
+            config.preferred_languages.join(", ")
+        ));
+    }
+    lines.push(format!(
+        "Canonical: {}/{SECURITY_TXT}",
+        site.config.base_url.trim_end_matches('/')
+    ));
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Renders humans.txt for `site`. `/* SITE */` gets the newest post date
+/// as `Last update`, the site language, and the configured standards and
+/// components.
+pub fn render_humans_txt(site: &Site, config: &HumansTxtConfig) -> String {
+    let mut sections = Vec::new();
+    if !config.team.is_empty() {
+        let members: Vec<String> = config
+            .team
+            .iter()
+            .map(|member| {
+                let mut lines = vec![format!(
+                    "\t{}: {}",
+                    member.role.as_deref().unwrap_or("Name"),
+                    member.name
+                )];
+                if let Some(contact) = &member.contact {
+                    lines.push(format!("\tContact: {contact}"));
+                }
+                if let Some(location) = &member.location {
+                    lines.push(format!("\tLocation: {location}"));
+                }
+                lines.join("\n")
+            })
+            .collect();
+        sections.push(format!("/* TEAM */\n{}", members.join("\n\n")));
+    }
+    if !config.thanks.is_empty() {
+        let thanks: Vec<String> = config
+            .thanks
+            .iter()
+            .map(|name| format!("\t{name}"))
+            .collect();
+        sections.push(format!("/* THANKS */\n{}", thanks.join("\n")));
+    }
+
+    let mut site_lines = Vec::new();
+    if let Some(updated) = site.posts.iter().map(|post| post.date).max() {
+        site_lines.push(format!("\tLast update: {}", updated.format("%Y/%m/%d")));
+    }
+    if let Some(language) = &site.config.language {
+        site_lines.push(format!("\tLanguage: {language}"));
+    }
+    if !config.standards.is_empty() {
+        site_lines.push(format!("\tStandards: {}", config.standards.join(", ")));
+    }
+    if !config.components.is_empty() {
+        site_lines.push(format!("\tComponents: {}", config.components.join(", ")));
+    }
+    site_lines.push("\tSoftware: Bamboo".to_string());
+    sections.push(format!("/* SITE */\n{}", site_lines.join("\n")));
+
+    sections.join("\n\n") + "\n"
+}
+
+/// Writes `/.well-known/security.txt` and `/humans.txt` into `output` for
+/// whichever of them `[wellknown]` configures. Fails if security.txt has
+/// expired.
+pub fn generate_wellknown(site: &Site, output: &(impl RenderSink + ?Sized)) -> Result<()> {
+    let config = &site.config.wellknown;
+    if let Some(security) = &config.security {
+        let rendered = render_security_txt(site, security, Utc::now())?;
+        output.write_file(Path::new(SECURITY_TXT), rendered.as_bytes())?;
+    }
+    if let Some(humans) = &config.humans {
+        let rendered = render_humans_txt(site, humans);
+        output.write_file(Path::new(HUMANS_TXT), rendered.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Warnings for a security.txt `expires` date that is valid but within 30
+/// days of `now`, or more than a year after it (RFC 9116 recommends less).
+pub fn security_txt_warnings(site: &Site, now: DateTime<Utc>) -> Vec<String> {
+    let Some(Ok(expires)) = site
+        .config
+        .wellknown
+        .security
+        .as_ref()
+        .map(|security| security.validate(now))
+    else {
+        return Vec::new();
+    };
+    let date = expires.format("%Y-%m-%d");
+    if expires - now < Duration::days(30) {
+        vec![format!(
+            "security.txt expires on {date}; set a new `expires` date"
+        )]
+    } else if expires - now > Duration::days(366) {
+        vec![format!(
+            "security.txt expires on {date}, more than a year away; RFC 9116 recommends less"
+        )]
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_site::site;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_render_security_txt() {
+        let site = site(
+            "[wellknown.security]\ncontact = [\"mailto:security@example.com\"]\nexpires = 2025-06-30\n\
+             preferred_languages = [\"en\", \"fr\"]\npolicy = \"https://example.com/policy/\"",
+        );
+        let security = site.config.wellknown.security.as_ref().unwrap();
+        assert_eq!(
+            render_security_txt(&site, security, now()).unwrap(),
+            "Contact: mailto:security@example.com\n\
+             Expires: 2025-06-30T00:00:00+00:00\n\
+             Policy: https://example.com/policy/\n\
+             Preferred-Languages: en, fr\n\
+             Canonical: https://example.com/.well-known/security.txt\n"
+        );
+    }
+
+    #[test]
+    fn test_security_txt_validation() {
+        let site = site(
+            "[wellknown.security]\ncontact = [\"mailto:a@example.com\"]\nexpires = \"2024-12-31\"",
+        );
+        let security = site.config.wellknown.security.as_ref().unwrap();
+        assert!(matches!(
+            security.validate(now()),
+            Err(BambooError::SecurityTxt { .. })
+        ));
+        let output_dir = tempfile::TempDir::new().unwrap();
+        assert!(generate_wellknown(&site, output_dir.path()).is_err());
+
+        let missing_contact = SecurityTxtConfig {
+            expires: "2025-06-30".to_string(),
+            ..SecurityTxtConfig::default()
+        };
+        assert!(missing_contact.validate(now()).is_err());
+    }
+
+    #[test]
+    fn test_security_txt_warnings() {
+        let config = |expires: &str| {
+            site(&format!(
+                "[wellknown.security]\ncontact = [\"mailto:a@example.com\"]\nexpires = \"{expires}\""
+            ))
+        };
+        assert_eq!(security_txt_warnings(&config("2025-01-15"), now()).len(), 1);
+        assert!(security_txt_warnings(&config("2025-06-30"), now()).is_empty());
+        assert_eq!(security_txt_warnings(&config("2027-01-01"), now()).len(), 1);
+        assert!(security_txt_warnings(&site(""), now()).is_empty());
+    }
+
+    #[test]
+    fn test_generate_humans_txt() {
+        let site = site(
+            "[wellknown.humans]\nthanks = [\"Tera\"]\nstandards = [\"HTML5\"]\n\
+             [[wellknown.humans.team]]\nname = \"Ada\"\nrole = \"Developer\"\ncontact = \"ada@example.com\"",
+        );
+        let output_dir = tempfile::TempDir::new().unwrap();
+        generate_wellknown(&site, output_dir.path()).unwrap();
+
+        let humans = std::fs::read_to_string(output_dir.path().join(HUMANS_TXT)).unwrap();
+        assert!(humans.starts_with(
+            "/* TEAM */\n\tDeveloper: Ada\n\tContact: ada@example.com\n\n/* THANKS */\n\tTera\n\n/* SITE */\n"
+        ));
+        assert!(humans.contains("\tStandards: HTML5\n"));
+        assert!(!output_dir.path().join(SECURITY_TXT).exists());
+    }
+}